@@ -0,0 +1,356 @@
+//! BMP decoding.
+//!
+//! OS/2 core headers and Windows info headers up to V5, at 1, 2, 4, 8, 16,
+//! 24 and 32 bits per pixel, uncompressed or with bit-field masks, stored
+//! bottom-up or top-down. Run-length and embedded JPEG or PNG compression
+//! are not supported.
+//!
+//! Many writers leave the alpha byte of 32-bit pixels zero even where a
+//! header declares an alpha mask, so an image whose alpha is zero
+//! throughout is taken to be opaque.
+
+use alloc::vec::Vec;
+
+use crate::{Error, Image, argb, scale};
+
+const BI_RGB: u32 = 0;
+const BI_RLE8: u32 = 1;
+const BI_RLE4: u32 = 2;
+const BI_BITFIELDS: u32 = 3;
+const BI_ALPHABITFIELDS: u32 = 6;
+
+/// Where a channel sits in a 16- or 32-bit pixel.
+#[derive(Debug, Clone, Copy)]
+struct Mask {
+    shift: u32,
+    /// The channel's largest value, or zero if the pixel doesn't have it.
+    max: u32,
+}
+
+impl Mask {
+    fn new(mask: u32) -> Result<Self, Error> {
+        if mask == 0 {
+            return Ok(Self { shift: 0, max: 0 });
+        }
+        let shift = mask.trailing_zeros();
+        let max = mask >> shift;
+        // The bits must be contiguous.
+        if max & (max + 1) != 0 {
+            return Err(Error::Invalid("bad bit-field mask"));
+        }
+        Ok(Self { shift, max })
+    }
+
+    /// The channel of `pixel`, scaled to eight bits.
+    fn get(self, pixel: u32) -> u8 {
+        match self.max {
+            0 => 0,
+            max => scale((pixel >> self.shift) & max, max),
+        }
+    }
+}
+
+/// Read a little-endian `u16` at `at`.
+fn u16_at(data: &[u8], at: usize) -> Result<u16, Error> {
+    let bytes = data.get(at..at + 2).ok_or(Error::Truncated)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+/// Read a little-endian `u32` at `at`.
+fn u32_at(data: &[u8], at: usize) -> Result<u32, Error> {
+    let bytes = data.get(at..at + 4).ok_or(Error::Truncated)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Decode a BMP file.
+pub(crate) fn decode(data: &[u8]) -> Result<Image, Error> {
+    if !data.starts_with(b"BM") {
+        return Err(Error::UnknownFormat);
+    }
+    let pixel_offset = u32_at(data, 10)? as usize;
+    let header_size = u32_at(data, 14)? as usize;
+
+    let (width, height, bpp, compression, colours_used, palette_entry) = match header_size {
+        12 => (
+            i32::from(u16_at(data, 18)?),
+            i32::from(u16_at(data, 20)?),
+            u16_at(data, 24)?,
+            BI_RGB,
+            0,
+            3,
+        ),
+        40 | 52 | 56 | 108 | 124 => (
+            u32_at(data, 18)? as i32,
+            u32_at(data, 22)? as i32,
+            u16_at(data, 28)?,
+            u32_at(data, 30)?,
+            u32_at(data, 46)?,
+            4,
+        ),
+        _ => return Err(Error::Unsupported("BMP header version")),
+    };
+    let top_down = height < 0;
+    let height = height.unsigned_abs();
+    if width <= 0 {
+        return Err(Error::Invalid("bad width"));
+    }
+    let width = width as u32;
+
+    // Bit fields follow an info header, or sit within the later ones.
+    let masks_at = 14 + 40;
+    let (masks, mut palette_at) = match (compression, bpp) {
+        (BI_BITFIELDS | BI_ALPHABITFIELDS, 16 | 32) => {
+            let count = if compression == BI_ALPHABITFIELDS || header_size >= 56 {
+                4
+            } else {
+                3
+            };
+            let mut masks = [0; 4];
+            for (i, mask) in masks.iter_mut().take(count).enumerate() {
+                *mask = u32_at(data, masks_at + 4 * i)?;
+            }
+            let after = if header_size == 40 {
+                masks_at + 4 * count
+            } else {
+                14 + header_size
+            };
+            (masks, after)
+        }
+        (BI_RGB, 16) => ([0x7c00, 0x03e0, 0x001f, 0], 14 + header_size),
+        (BI_RGB, 32) => ([0x00ff_0000, 0x0000_ff00, 0x0000_00ff, 0], 14 + header_size),
+        (BI_RGB, 1 | 2 | 4 | 8 | 24) => ([0; 4], 14 + header_size),
+        (BI_RLE8 | BI_RLE4, _) => return Err(Error::Unsupported("run-length encoded BMP")),
+        (BI_RGB | BI_BITFIELDS | BI_ALPHABITFIELDS, _) => {
+            return Err(Error::Invalid("bad bits per pixel"));
+        }
+        _ => return Err(Error::Unsupported("BMP compression")),
+    };
+    let [red, green, blue, alpha] = [
+        Mask::new(masks[0])?,
+        Mask::new(masks[1])?,
+        Mask::new(masks[2])?,
+        Mask::new(masks[3])?,
+    ];
+
+    let mut palette = Vec::new();
+    if bpp <= 8 {
+        let count = match colours_used {
+            0 => 1 << bpp,
+            n => n.min(256) as usize,
+        };
+        for _ in 0..count {
+            let entry = data
+                .get(palette_at..palette_at + 3)
+                .ok_or(Error::Truncated)?;
+            palette.push(argb(255, entry[2], entry[1], entry[0]));
+            palette_at += palette_entry;
+        }
+    }
+
+    let mut image = Image::new(width, height)?;
+    let stride = (width as usize * bpp as usize).div_ceil(32) * 4;
+    let pixels = data.get(pixel_offset..).ok_or(Error::Truncated)?;
+    if pixels.len() < stride * height as usize {
+        return Err(Error::Truncated);
+    }
+
+    let mut any_alpha = false;
+    for (row, line) in pixels
+        .chunks_exact(stride)
+        .take(height as usize)
+        .enumerate()
+    {
+        let y = if top_down {
+            row as u32
+        } else {
+            height - 1 - row as u32
+        };
+        for x in 0..width {
+            let i = x as usize;
+            let pixel = match bpp {
+                1 | 2 | 4 | 8 => {
+                    let bit = i * bpp as usize;
+                    let shift = 8 - bpp as usize - bit % 8;
+                    let index = (line[bit / 8] >> shift) & ((1u16 << bpp) - 1) as u8;
+                    *palette
+                        .get(index as usize)
+                        .ok_or(Error::Invalid("palette index out of range"))?
+                }
+                24 => argb(255, line[3 * i + 2], line[3 * i + 1], line[3 * i]),
+                _ => {
+                    let pixel = if bpp == 16 {
+                        u32::from(u16::from_le_bytes([line[2 * i], line[2 * i + 1]]))
+                    } else {
+                        u32::from_le_bytes([
+                            line[4 * i],
+                            line[4 * i + 1],
+                            line[4 * i + 2],
+                            line[4 * i + 3],
+                        ])
+                    };
+                    let a = if alpha.max == 0 {
+                        255
+                    } else {
+                        alpha.get(pixel)
+                    };
+                    any_alpha |= a != 0;
+                    argb(a, red.get(pixel), green.get(pixel), blue.get(pixel))
+                }
+            };
+            image.set_pixel(x, y, pixel);
+        }
+    }
+    if !any_alpha && alpha.max != 0 {
+        for pixel in image.pixels_mut() {
+            *pixel |= 0xff00_0000;
+        }
+    }
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    /// A BMP with an info header of `header_size` bytes (its fields after
+    /// the first 40 zero), then `extra` (masks or palette), then `pixels`.
+    fn bmp(
+        header_size: u32,
+        width: i32,
+        height: i32,
+        bpp: u16,
+        compression: u32,
+        extra: &[u8],
+        pixels: &[u8],
+    ) -> Vec<u8> {
+        let offset = 14 + header_size + extra.len() as u32;
+        let mut out = b"BM".to_vec();
+        out.extend_from_slice(&(offset + pixels.len() as u32).to_le_bytes());
+        out.extend_from_slice(&[0; 4]);
+        out.extend_from_slice(&offset.to_le_bytes());
+        out.extend_from_slice(&header_size.to_le_bytes());
+        out.extend_from_slice(&width.to_le_bytes());
+        out.extend_from_slice(&height.to_le_bytes());
+        out.extend_from_slice(&1u16.to_le_bytes());
+        out.extend_from_slice(&bpp.to_le_bytes());
+        out.extend_from_slice(&compression.to_le_bytes());
+        out.resize(14 + header_size as usize, 0);
+        out.extend_from_slice(extra);
+        out.extend_from_slice(pixels);
+        out
+    }
+
+    #[test]
+    fn decodes_bottom_up_24_bit() {
+        // Rows of two pixels padded to eight bytes, bottom row first.
+        let pixels = [
+            0, 0, 255, 0, 255, 0, 0, 0, //
+            255, 0, 0, 1, 2, 3, 0, 0,
+        ];
+        let image = decode(&bmp(40, 2, 2, 24, BI_RGB, &[], &pixels)).unwrap();
+        assert_eq!(
+            image.pixels(),
+            [0xff0000ff, 0xff030201, 0xffff0000, 0xff00ff00]
+        );
+    }
+
+    #[test]
+    fn decodes_top_down_palettes() {
+        let palette = [0, 0, 0, 0, 255, 255, 255, 0];
+        let pixels = [0b1010_0000, 0, 0, 0, 0b0110_0000, 0, 0, 0];
+        let image = decode(&bmp(40, 3, -2, 1, BI_RGB, &palette, &pixels)).unwrap();
+        let (black, white) = (0xff000000, 0xffffffff);
+        assert_eq!(image.pixels(), [white, black, white, black, white, white]);
+
+        // Four-bit indices past a two-colour palette.
+        let pixels = [0x10, 0, 0, 0];
+        let mut small = palette.to_vec();
+        let mut file = bmp(40, 2, 1, 4, BI_RGB, &small, &pixels);
+        file[46] = 2;
+        assert!(decode(&file).is_ok());
+        small.truncate(4);
+        let mut file = bmp(40, 2, 1, 4, BI_RGB, &small, &pixels);
+        file[46] = 1;
+        assert!(matches!(decode(&file), Err(Error::Invalid(_))));
+    }
+
+    #[test]
+    fn decodes_os2_core_headers() {
+        // Twelve-byte header with 16-bit sizes and a 3-byte palette.
+        let mut file = b"BM".to_vec();
+        file.extend_from_slice(&[0; 8]);
+        file.extend_from_slice(&(14u32 + 12 + 6).to_le_bytes());
+        file.extend_from_slice(&12u32.to_le_bytes());
+        file.extend_from_slice(&[1, 0, 1, 0, 1, 0, 1, 0]);
+        file.extend_from_slice(&[10, 20, 30, 40, 50, 60]);
+        file.extend_from_slice(&[0x80, 0, 0, 0]);
+        assert_eq!(decode(&file).unwrap().pixels(), [0xff3c3228]);
+    }
+
+    #[test]
+    fn decodes_bit_fields() {
+        // RGB565 in an info header followed by three masks.
+        let masks = [0x00, 0xf8, 0, 0, 0xe0, 0x07, 0, 0, 0x1f, 0, 0, 0];
+        let pixels = 0xf81fu16.to_le_bytes();
+        let file = bmp(
+            40,
+            1,
+            1,
+            16,
+            BI_BITFIELDS,
+            &masks,
+            &[pixels[0], pixels[1], 0, 0],
+        );
+        assert_eq!(decode(&file).unwrap().pixels(), [0xffff00ff]);
+
+        // Default 5-5-5 for uncompressed 16-bit.
+        let pixels = 0x03e0u16.to_le_bytes();
+        let file = bmp(40, 1, 1, 16, BI_RGB, &[], &[pixels[0], pixels[1], 0, 0]);
+        assert_eq!(decode(&file).unwrap().pixels(), [0xff00ff00]);
+    }
+
+    #[test]
+    fn decodes_v5_alpha() {
+        let mut header = vec![0; 124 - 40];
+        for (i, mask) in [0x00ff_0000u32, 0x0000_ff00, 0x0000_00ff, 0xff00_0000]
+            .iter()
+            .enumerate()
+        {
+            header[4 * i..4 * i + 4].copy_from_slice(&mask.to_le_bytes());
+        }
+        let mut file = bmp(
+            40,
+            2,
+            1,
+            32,
+            BI_BITFIELDS,
+            &header,
+            &[1, 2, 3, 0x80, 4, 5, 6, 0],
+        );
+        file[14..18].copy_from_slice(&124u32.to_le_bytes());
+        assert_eq!(decode(&file).unwrap().pixels(), [0x80030201, 0x00060504]);
+
+        // Alpha zero throughout means the writer didn't fill it in.
+        let mut file = bmp(40, 1, 1, 32, BI_BITFIELDS, &header, &[1, 2, 3, 0]);
+        file[14..18].copy_from_slice(&124u32.to_le_bytes());
+        assert_eq!(decode(&file).unwrap().pixels(), [0xff030201]);
+
+        // Plain 32-bit pixels have no alpha channel.
+        let file = bmp(40, 1, 1, 32, BI_RGB, &[], &[1, 2, 3, 0x80]);
+        assert_eq!(decode(&file).unwrap().pixels(), [0xff030201]);
+    }
+
+    #[test]
+    fn rejects_unsupported_files() {
+        let file = bmp(40, 1, 1, 8, BI_RLE8, &[], &[0; 4]);
+        assert!(matches!(decode(&file), Err(Error::Unsupported(_))));
+        let file = bmp(64, 1, 1, 24, BI_RGB, &[], &[0; 4]);
+        assert!(matches!(decode(&file), Err(Error::Unsupported(_))));
+        let file = bmp(40, 1, 1, 12, BI_RGB, &[], &[0; 4]);
+        assert!(matches!(decode(&file), Err(Error::Invalid(_))));
+        let file = bmp(40, 4, 4, 24, BI_RGB, &[], &[0; 40]);
+        assert_eq!(decode(&file), Err(Error::Truncated));
+        assert_eq!(decode(b"BM\0\0"), Err(Error::Truncated));
+    }
+}
@@ -0,0 +1,437 @@
+//! DEFLATE (RFC 1951) decompression and its zlib wrapper (RFC 1950).
+//!
+//! Huffman codes decode through a table indexed by the next `n` bits of
+//! input, `n` being the code's longest length, so every symbol costs one
+//! lookup. Tables are rebuilt per dynamic block; at 15 bits at most they
+//! are small next to the data a block carries.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::Error;
+
+/// Base lengths for length symbols 257 to 285.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+
+/// Extra bits read after length symbols 257 to 285.
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// Base distances for distance symbols 0 to 29.
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+
+/// Extra bits read after distance symbols 0 to 29.
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// The order code length code lengths are sent in.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Decompress a zlib stream, checking its Adler-32. Fails with
+/// [`Error::TooLarge`] if the data inflates past `limit` bytes.
+pub(crate) fn zlib_decompress(data: &[u8], limit: usize) -> Result<Vec<u8>, Error> {
+    let [cmf, flg, ..] = *data else {
+        return Err(Error::Truncated);
+    };
+    if cmf & 0x0f != 8 || cmf >> 4 > 7 || (u16::from(cmf) << 8 | u16::from(flg)) % 31 != 0 {
+        return Err(Error::Invalid("bad zlib header"));
+    }
+    if flg & 0x20 != 0 {
+        return Err(Error::Unsupported("zlib preset dictionary"));
+    }
+    let (out, used) = inflate(&data[2..], limit)?;
+    let trailer = data.get(2 + used..2 + used + 4).ok_or(Error::Truncated)?;
+    if adler32(&out).to_be_bytes() != trailer {
+        return Err(Error::Checksum);
+    }
+    Ok(out)
+}
+
+/// Decompress a raw DEFLATE stream, returning the data and how many bytes
+/// of input it took. Fails with [`Error::TooLarge`] if the data inflates
+/// past `limit` bytes.
+pub(crate) fn inflate(data: &[u8], limit: usize) -> Result<(Vec<u8>, usize), Error> {
+    let mut bits = Bits::new(data);
+    let mut out = Vec::new();
+    loop {
+        let last = bits.take(1)? == 1;
+        match bits.take(2)? {
+            0 => {
+                let stored = bits.take_aligned(4)?;
+                let len = u16::from_le_bytes([stored[0], stored[1]]);
+                let nlen = u16::from_le_bytes([stored[2], stored[3]]);
+                if len != !nlen {
+                    return Err(Error::Invalid("bad stored block length"));
+                }
+                if out.len() + len as usize > limit {
+                    return Err(Error::TooLarge);
+                }
+                out.extend_from_slice(bits.take_aligned(len as usize)?);
+            }
+            1 => {
+                let (literals, distances) = fixed_codes();
+                inflate_block(&mut bits, &mut out, &literals, &distances, limit)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(&mut bits)?;
+                inflate_block(&mut bits, &mut out, &literals, &distances, limit)?;
+            }
+            _ => return Err(Error::Invalid("bad block type")),
+        }
+        if last {
+            return Ok((out, bits.position()));
+        }
+    }
+}
+
+/// Decode one compressed block's symbols into `out`.
+fn inflate_block(
+    bits: &mut Bits,
+    out: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+    limit: usize,
+) -> Result<(), Error> {
+    loop {
+        let symbol = literals.decode(bits)? as usize;
+        match symbol {
+            0..=255 => {
+                if out.len() >= limit {
+                    return Err(Error::TooLarge);
+                }
+                out.push(symbol as u8);
+            }
+            256 => return Ok(()),
+            257..=285 => {
+                let index = symbol - 257;
+                let length = LENGTH_BASE[index] as usize + bits.take(LENGTH_EXTRA[index])? as usize;
+                let index = distances.decode(bits)? as usize;
+                if index >= DISTANCE_BASE.len() {
+                    return Err(Error::Invalid("bad distance symbol"));
+                }
+                let distance =
+                    DISTANCE_BASE[index] as usize + bits.take(DISTANCE_EXTRA[index])? as usize;
+                if distance > out.len() {
+                    return Err(Error::Invalid("distance before start of data"));
+                }
+                if out.len() + length > limit {
+                    return Err(Error::TooLarge);
+                }
+                // The copy may overlap what it produces, so go a byte at a
+                // time.
+                let start = out.len() - distance;
+                for i in 0..length {
+                    out.push(out[start + i]);
+                }
+            }
+            _ => return Err(Error::Invalid("bad length symbol")),
+        }
+    }
+}
+
+/// The codes of a fixed Huffman block.
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [0; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    let literals = Huffman::new(&lengths).expect("fixed literal code is complete");
+    let distances = Huffman::new(&[5; 30]).expect("fixed distance code is complete");
+    (literals, distances)
+}
+
+/// Read the code lengths at the start of a dynamic Huffman block.
+fn dynamic_codes(bits: &mut Bits) -> Result<(Huffman, Huffman), Error> {
+    let literal_count = bits.take(5)? as usize + 257;
+    let distance_count = bits.take(5)? as usize + 1;
+    let length_count = bits.take(4)? as usize + 4;
+    if literal_count > 286 || distance_count > 30 {
+        return Err(Error::Invalid("too many codes"));
+    }
+
+    let mut length_lengths = [0; 19];
+    for &i in &CODE_LENGTH_ORDER[..length_count] {
+        length_lengths[i] = bits.take(3)? as u8;
+    }
+    let length_code = Huffman::new(&length_lengths)?;
+
+    let mut lengths = vec![0u8; literal_count + distance_count];
+    let mut i = 0;
+    while i < lengths.len() {
+        let (value, repeat) = match length_code.decode(bits)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = i
+                    .checked_sub(1)
+                    .map(|p| lengths[p])
+                    .ok_or(Error::Invalid("repeat with no previous length"))?;
+                (previous, 3 + bits.take(2)? as usize)
+            }
+            17 => (0, 3 + bits.take(3)? as usize),
+            _ => (0, 11 + bits.take(7)? as usize),
+        };
+        let run = lengths
+            .get_mut(i..i + repeat)
+            .ok_or(Error::Invalid("code lengths overrun"))?;
+        run.fill(value);
+        i += repeat;
+    }
+    if lengths[256] == 0 {
+        return Err(Error::Invalid("no end-of-block code"));
+    }
+
+    let literals = Huffman::new(&lengths[..literal_count])?;
+    let distances = Huffman::new(&lengths[literal_count..])?;
+    Ok((literals, distances))
+}
+
+/// A canonical Huffman code as a lookup table.
+struct Huffman {
+    /// Indexed by the next `bits` bits of input; each entry is the symbol
+    /// shifted left by 4 over its code length, or 0 where no code matches.
+    table: Vec<u16>,
+    bits: u8,
+}
+
+impl Huffman {
+    /// Build the code with these lengths per symbol, zero meaning unused.
+    /// Incomplete codes are allowed (DEFLATE sends one-code distance
+    /// trees); over-subscribed ones are not.
+    fn new(lengths: &[u8]) -> Result<Self, Error> {
+        let mut counts = [0u16; 16];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+        let bits = (1..16).rev().find(|&l| counts[l] != 0).unwrap_or(0) as u8;
+
+        // First code of each length, checking no length has more codes
+        // than are left for it.
+        let mut next = [0u16; 16];
+        let mut code = 0u32;
+        let mut left = 1i32;
+        for length in 1..16 {
+            left = left * 2 - i32::from(counts[length]);
+            if left < 0 {
+                return Err(Error::Invalid("over-subscribed Huffman code"));
+            }
+            code = (code + u32::from(counts[length - 1])) << 1;
+            next[length] = code as u16;
+        }
+
+        let mut table = vec![0u16; 1 << bits];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length == 0 {
+                continue;
+            }
+            let code = next[length as usize];
+            next[length as usize] += 1;
+            // Codes are packed from their most significant bit, the input
+            // from the least, so index by the code reversed.
+            let reversed = code.reverse_bits() >> (16 - length);
+            let entry = (symbol as u16) << 4 | u16::from(length);
+            let mut index = reversed as usize;
+            while index < table.len() {
+                table[index] = entry;
+                index += 1 << length;
+            }
+        }
+        Ok(Self { table, bits })
+    }
+
+    fn decode(&self, bits: &mut Bits) -> Result<u16, Error> {
+        let entry = self.table[bits.peek(self.bits) as usize];
+        let length = entry & 0xf;
+        if length == 0 {
+            return Err(Error::Invalid("bad Huffman code"));
+        }
+        bits.consume(length as u8)?;
+        Ok(entry >> 4)
+    }
+}
+
+/// Reads bits least significant first, as DEFLATE packs them.
+struct Bits<'a> {
+    data: &'a [u8],
+    /// The next byte to load into `buffer`; may run past the end of
+    /// `data`, which reads as zeros.
+    next: usize,
+    buffer: u64,
+    count: u8,
+}
+
+impl<'a> Bits<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            next: 0,
+            buffer: 0,
+            count: 0,
+        }
+    }
+
+    fn refill(&mut self) {
+        while self.count <= 56 {
+            let byte = self.data.get(self.next).copied().unwrap_or(0);
+            self.buffer |= u64::from(byte) << self.count;
+            self.next += 1;
+            self.count += 8;
+        }
+    }
+
+    /// The next `n` bits without consuming them.
+    fn peek(&mut self, n: u8) -> u64 {
+        if self.count < n {
+            self.refill();
+        }
+        self.buffer & ((1 << n) - 1)
+    }
+
+    /// Drop `n` bits, failing if that takes them past the end of the data.
+    fn consume(&mut self, n: u8) -> Result<(), Error> {
+        self.buffer >>= n;
+        self.count -= n;
+        if self.position_bits() > self.data.len() * 8 {
+            return Err(Error::Truncated);
+        }
+        Ok(())
+    }
+
+    fn take(&mut self, n: u8) -> Result<u32, Error> {
+        let value = self.peek(n) as u32;
+        self.consume(n)?;
+        Ok(value)
+    }
+
+    /// Skip to the next byte boundary and take `n` whole bytes.
+    fn take_aligned(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        let start = self.position();
+        let bytes = self.data.get(start..start + n).ok_or(Error::Truncated)?;
+        self.next = start + n;
+        self.buffer = 0;
+        self.count = 0;
+        Ok(bytes)
+    }
+
+    /// How many bits have been consumed.
+    fn position_bits(&self) -> usize {
+        self.next * 8 - self.count as usize
+    }
+
+    /// How many bytes have been consumed, counting a partly used one.
+    fn position(&self) -> usize {
+        self.position_bits().div_ceil(8)
+    }
+}
+
+/// The Adler-32 checksum zlib streams end with.
+pub fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 bytes is the most that can be summed before `b` could overflow.
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += u32::from(byte);
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+    b << 16 | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `zlib.compress(b"hello hello hello hello\n")`: a fixed Huffman block
+    /// with a back-reference.
+    const FIXED: [u8; 17] = [
+        0x78, 0x9c, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x27, 0xb9, 0x00, 0x70, 0xbe,
+        0x08, 0xbb,
+    ];
+
+    /// `zlib.compress(sample(), 9)`: a dynamic Huffman block.
+    const DYNAMIC: [u8; 63] = [
+        0x78, 0xda, 0xc5, 0x8d, 0x89, 0x09, 0x00, 0x30, 0x08, 0x03, 0x67, 0xcd, 0xb3, 0xff, 0x0c,
+        0x35, 0xd1, 0x1d, 0x0a, 0x22, 0x78, 0x39, 0x22, 0x05, 0x02, 0xa0, 0xb2, 0x66, 0x08, 0xf5,
+        0x50, 0xa9, 0xe1, 0x50, 0x35, 0x88, 0xc0, 0xb3, 0x15, 0x14, 0x35, 0xdc, 0x1b, 0xa7, 0x89,
+        0x13, 0x55, 0xd9, 0x32, 0xca, 0xd7, 0x78, 0x6a, 0xb1, 0x98, 0xe4, 0xcf, 0xdf, 0x07, 0x23,
+        0x03, 0x5b, 0xa3,
+    ];
+
+    /// 120 letters skewed towards `a`, twice over.
+    fn sample() -> Vec<u8> {
+        let mut x = 1u32;
+        let half: Vec<u8> = (0..120)
+            .map(|_| {
+                x = x.wrapping_mul(1103515245).wrapping_add(12345) & 0x7fff_ffff;
+                b"aaaaaaaabbbbccd"[(x >> 16) as usize % 15]
+            })
+            .collect();
+        half.repeat(2)
+    }
+
+    #[test]
+    fn inflates_stored_blocks() {
+        // Two stored blocks, "ab" then "c".
+        let data = [
+            0x78, 0x01, 0x00, 0x02, 0x00, 0xfd, 0xff, b'a', b'b', 0x01, 0x01, 0x00, 0xfe, 0xff,
+            b'c', 0x02, 0x4d, 0x01, 0x27,
+        ];
+        assert_eq!(zlib_decompress(&data, 100).unwrap(), b"abc");
+    }
+
+    #[test]
+    fn inflates_fixed_blocks() {
+        assert_eq!(
+            zlib_decompress(&FIXED, 100).unwrap(),
+            b"hello hello hello hello\n"
+        );
+    }
+
+    #[test]
+    fn inflates_dynamic_blocks() {
+        assert_eq!(zlib_decompress(&DYNAMIC, 1000).unwrap(), sample());
+    }
+
+    #[test]
+    fn rejects_bad_streams() {
+        let mut corrupt = FIXED;
+        corrupt[16] ^= 1;
+        assert_eq!(zlib_decompress(&corrupt, 100), Err(Error::Checksum));
+        assert_eq!(zlib_decompress(&FIXED[..12], 100), Err(Error::Truncated));
+        assert_eq!(zlib_decompress(&FIXED, 10), Err(Error::TooLarge));
+        assert!(matches!(
+            zlib_decompress(&[0x78, 0x9d], 100),
+            Err(Error::Invalid(_))
+        ));
+        // Block type 3.
+        assert!(matches!(inflate(&[0x07], 100), Err(Error::Invalid(_))));
+    }
+
+    #[test]
+    fn rejects_over_subscribed_codes() {
+        assert!(Huffman::new(&[1, 1, 1]).is_err());
+        assert!(Huffman::new(&[1, 2, 2]).is_ok());
+        assert!(Huffman::new(&[1]).is_ok());
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+}
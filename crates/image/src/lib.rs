@@ -0,0 +1,216 @@
+//! Image decoding for Panda OS.
+//!
+//! Decodes PNG, BMP and the netpbm formats (PBM, PGM and PPM) into an
+//! [`Image`] of `0xAARRGGBB` pixels with straight (not premultiplied)
+//! alpha, the layout `libpanda`'s `Colour` and `PixelBuffer` use.
+//! [`decode`] picks the decoder from the data's signature. The checksums
+//! the formats use, [`crc32`] and [`adler32`], are exported for encoders.
+//!
+//! The crate is pure logic; `libpanda::graphics` turns images into pixel
+//! buffers and loads them from the filesystem.
+//!
+//! ```
+//! // A 1x1 binary PPM holding one orange pixel.
+//! let image = image::decode(b"P6 1 1 255 \xff\x80\x00").unwrap();
+//! assert_eq!((image.width(), image.height()), (1, 1));
+//! assert_eq!(image.pixel(0, 0), 0xffff8000);
+//! ```
+
+#![no_std]
+
+extern crate alloc;
+
+mod bmp;
+mod inflate;
+mod png;
+mod pnm;
+
+pub use inflate::adler32;
+pub use png::crc32;
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// The widest or tallest image the decoders accept.
+pub const MAX_DIMENSION: u32 = 16384;
+
+/// The most pixels the decoders accept in one image (128 MiB of them).
+pub const MAX_PIXELS: usize = 32 * 1024 * 1024;
+
+/// A decoded image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    /// `0xAARRGGBB`, row by row from the top left.
+    pixels: Vec<u32>,
+}
+
+impl Image {
+    /// A transparent image. Fails with [`Error::TooLarge`] beyond the
+    /// limits, and [`Error::Invalid`] if either side is zero.
+    pub fn new(width: u32, height: u32) -> Result<Self, Error> {
+        if width == 0 || height == 0 {
+            return Err(Error::Invalid("empty image"));
+        }
+        let count = width as usize * height as usize;
+        if width > MAX_DIMENSION || height > MAX_DIMENSION || count > MAX_PIXELS {
+            return Err(Error::TooLarge);
+        }
+        Ok(Self {
+            width,
+            height,
+            pixels: vec![0; count],
+        })
+    }
+
+    /// The width in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The pixels, row by row from the top left.
+    pub fn pixels(&self) -> &[u32] {
+        &self.pixels
+    }
+
+    /// The pixels, mutably.
+    pub fn pixels_mut(&mut self) -> &mut [u32] {
+        &mut self.pixels
+    }
+
+    /// The pixel at `(x, y)`, which must be within the image.
+    pub fn pixel(&self, x: u32, y: u32) -> u32 {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// Set the pixel at `(x, y)`, which must be within the image.
+    pub fn set_pixel(&mut self, x: u32, y: u32, argb: u32) {
+        self.pixels[(y * self.width + x) as usize] = argb;
+    }
+
+    /// Take the pixels.
+    pub fn into_pixels(self) -> Vec<u32> {
+        self.pixels
+    }
+}
+
+/// An image file format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Png,
+    Bmp,
+    /// PBM, PGM or PPM, plain or binary.
+    Pnm,
+}
+
+impl Format {
+    /// The format `data` is in, from its first bytes.
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        if data.starts_with(&png::SIGNATURE) {
+            Some(Self::Png)
+        } else if data.starts_with(b"BM") {
+            Some(Self::Bmp)
+        } else if let [b'P', b'1'..=b'6', ..] = data {
+            Some(Self::Pnm)
+        } else {
+            None
+        }
+    }
+
+    /// Decode `data` as this format.
+    pub fn decode(self, data: &[u8]) -> Result<Image, Error> {
+        match self {
+            Self::Png => png::decode(data),
+            Self::Bmp => bmp::decode(data),
+            Self::Pnm => pnm::decode(data),
+        }
+    }
+}
+
+/// Decode an image in any supported format.
+pub fn decode(data: &[u8]) -> Result<Image, Error> {
+    Format::detect(data)
+        .ok_or(Error::UnknownFormat)?
+        .decode(data)
+}
+
+/// Why an image could not be decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// Not a format this crate knows.
+    UnknownFormat,
+    /// The data ends before the image does.
+    Truncated,
+    /// The data is not a well-formed image.
+    Invalid(&'static str),
+    /// A well-formed image using a feature the decoder lacks.
+    Unsupported(&'static str),
+    /// A checksum does not match the data.
+    Checksum,
+    /// The image is larger than [`MAX_DIMENSION`] or [`MAX_PIXELS`].
+    TooLarge,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnknownFormat => write!(f, "unknown image format"),
+            Error::Truncated => write!(f, "truncated image"),
+            Error::Invalid(what) => write!(f, "invalid image: {}", what),
+            Error::Unsupported(what) => write!(f, "unsupported: {}", what),
+            Error::Checksum => write!(f, "checksum mismatch"),
+            Error::TooLarge => write!(f, "image too large"),
+        }
+    }
+}
+
+/// Pack straight-alpha components into `0xAARRGGBB`.
+fn argb(a: u8, r: u8, g: u8, b: u8) -> u32 {
+    u32::from_be_bytes([a, r, g, b])
+}
+
+/// Scale `value` out of `max` to out of 255, rounding to nearest.
+fn scale(value: u32, max: u32) -> u8 {
+    ((value * 255 + max / 2) / max) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_formats() {
+        assert_eq!(Format::detect(&png::SIGNATURE), Some(Format::Png));
+        assert_eq!(Format::detect(b"BM\0\0"), Some(Format::Bmp));
+        assert_eq!(Format::detect(b"P5\n"), Some(Format::Pnm));
+        assert_eq!(Format::detect(b"P7\n"), None);
+        assert_eq!(Format::detect(b""), None);
+        assert_eq!(decode(b"GIF89a"), Err(Error::UnknownFormat));
+    }
+
+    #[test]
+    fn rejects_oversized_images() {
+        assert_eq!(Image::new(MAX_DIMENSION + 1, 1), Err(Error::TooLarge));
+        assert_eq!(
+            Image::new(MAX_DIMENSION, MAX_DIMENSION),
+            Err(Error::TooLarge)
+        );
+        assert!(matches!(Image::new(0, 4), Err(Error::Invalid(_))));
+        assert_eq!(Image::new(3, 2).unwrap().pixels().len(), 6);
+    }
+
+    #[test]
+    fn scales_samples() {
+        assert_eq!(scale(0, 1), 0);
+        assert_eq!(scale(1, 1), 255);
+        assert_eq!(scale(2, 3), 170);
+        assert_eq!(scale(32768, 65535), 128);
+    }
+}
@@ -0,0 +1,588 @@
+//! PNG decoding.
+//!
+//! Every standard colour type and bit depth, palette and `tRNS`
+//! transparency, and Adam7 interlacing. Sixteen-bit samples are cut to
+//! their high byte, and ancillary chunks other than `tRNS` (gamma, colour
+//! profiles, text) are skipped.
+
+use alloc::vec::Vec;
+
+use crate::inflate::zlib_decompress;
+use crate::{Error, Image, argb, scale};
+
+/// The eight bytes every PNG file starts with.
+pub(crate) const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// Where each Adam7 pass starts and how far apart its pixels are:
+/// `(x, y, dx, dy)`.
+const ADAM7: [(u32, u32, u32, u32); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+/// The image header.
+#[derive(Debug, Clone, Copy)]
+struct Header {
+    width: u32,
+    height: u32,
+    depth: u8,
+    colour: ColourType,
+    interlaced: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColourType {
+    Grey,
+    Rgb,
+    Indexed,
+    GreyAlpha,
+    Rgba,
+}
+
+impl ColourType {
+    fn from_code(code: u8, depth: u8) -> Result<Self, Error> {
+        let (colour, depths): (Self, &[u8]) = match code {
+            0 => (Self::Grey, &[1, 2, 4, 8, 16]),
+            2 => (Self::Rgb, &[8, 16]),
+            3 => (Self::Indexed, &[1, 2, 4, 8]),
+            4 => (Self::GreyAlpha, &[8, 16]),
+            6 => (Self::Rgba, &[8, 16]),
+            _ => return Err(Error::Invalid("bad colour type")),
+        };
+        if !depths.contains(&depth) {
+            return Err(Error::Invalid("bad bit depth for colour type"));
+        }
+        Ok(colour)
+    }
+
+    fn channels(self) -> usize {
+        match self {
+            Self::Grey | Self::Indexed => 1,
+            Self::GreyAlpha => 2,
+            Self::Rgb => 3,
+            Self::Rgba => 4,
+        }
+    }
+}
+
+impl Header {
+    fn bits_per_pixel(&self) -> usize {
+        self.colour.channels() * self.depth as usize
+    }
+
+    /// The bytes in a row of `width` pixels, not counting its filter byte.
+    fn row_bytes(&self, width: u32) -> usize {
+        (width as usize * self.bits_per_pixel()).div_ceil(8)
+    }
+
+    /// The passes the image is stored in, as `(x, y, dx, dy, width,
+    /// height)`, leaving out empty ones.
+    fn passes(&self) -> impl Iterator<Item = (u32, u32, u32, u32, u32, u32)> + '_ {
+        let passes: &[_] = if self.interlaced {
+            &ADAM7
+        } else {
+            &[(0, 0, 1, 1)]
+        };
+        passes.iter().filter_map(|&(x, y, dx, dy)| {
+            let width = self.width.saturating_sub(x).div_ceil(dx);
+            let height = self.height.saturating_sub(y).div_ceil(dy);
+            (width > 0 && height > 0).then_some((x, y, dx, dy, width, height))
+        })
+    }
+}
+
+/// Decode a PNG file.
+pub(crate) fn decode(data: &[u8]) -> Result<Image, Error> {
+    let mut rest = data.strip_prefix(&SIGNATURE).ok_or(Error::UnknownFormat)?;
+    let mut header = None;
+    let mut palette: Vec<[u8; 3]> = Vec::new();
+    let mut transparency: Option<&[u8]> = None;
+    let mut compressed = Vec::new();
+
+    loop {
+        let (Chunk { kind, body }, next) = chunk(rest)?;
+        rest = next;
+        if header.is_none() && &kind != b"IHDR" {
+            return Err(Error::Invalid("first chunk is not IHDR"));
+        }
+        match &kind {
+            b"IHDR" => header = Some(parse_header(body)?),
+            b"PLTE" => {
+                if body.len() % 3 != 0 || body.len() > 256 * 3 {
+                    return Err(Error::Invalid("bad palette length"));
+                }
+                palette = body.chunks_exact(3).map(|c| [c[0], c[1], c[2]]).collect();
+            }
+            b"tRNS" => transparency = Some(body),
+            b"IDAT" => compressed.extend_from_slice(body),
+            b"IEND" => break,
+            // Bit 5 of the first byte clear marks a chunk the image can't
+            // be shown without.
+            _ if kind[0] & 0x20 == 0 => return Err(Error::Unsupported("critical chunk")),
+            _ => {}
+        }
+    }
+
+    let header = header.ok_or(Error::Invalid("no IHDR"))?;
+    if header.colour == ColourType::Indexed && palette.is_empty() {
+        return Err(Error::Invalid("no palette"));
+    }
+    let mut image = Image::new(header.width, header.height)?;
+    let expected: usize = header
+        .passes()
+        .map(|(.., width, height)| (1 + header.row_bytes(width)) * height as usize)
+        .sum();
+    let raw = zlib_decompress(&compressed, expected)?;
+    if raw.len() < expected {
+        return Err(Error::Truncated);
+    }
+
+    let pixels = Pixels::new(&header, &palette, transparency)?;
+    let filter_bpp = header.bits_per_pixel().div_ceil(8);
+    let mut raw = &raw[..];
+    let mut previous = Vec::new();
+    let mut current = Vec::new();
+    for (x0, y0, dx, dy, width, height) in header.passes() {
+        let row_bytes = header.row_bytes(width);
+        previous.clear();
+        previous.resize(row_bytes, 0);
+        for row in 0..height {
+            let (line, next) = raw.split_at(1 + row_bytes);
+            raw = next;
+            current.clear();
+            current.extend_from_slice(&line[1..]);
+            unfilter(line[0], &mut current, &previous, filter_bpp)?;
+            let y = y0 + row * dy;
+            for column in 0..width {
+                image.set_pixel(x0 + column * dx, y, pixels.get(&current, column as usize)?);
+            }
+            core::mem::swap(&mut previous, &mut current);
+        }
+    }
+    Ok(image)
+}
+
+/// A chunk's type and body.
+struct Chunk<'a> {
+    kind: [u8; 4],
+    body: &'a [u8],
+}
+
+/// Split the next chunk off `data`, checking its CRC.
+fn chunk(data: &[u8]) -> Result<(Chunk<'_>, &[u8]), Error> {
+    let length = data.get(..4).ok_or(Error::Truncated)?;
+    let length = u32::from_be_bytes(length.try_into().unwrap()) as usize;
+    let end = 8usize.checked_add(length).ok_or(Error::Truncated)?;
+    let typed = data.get(4..end).ok_or(Error::Truncated)?;
+    let crc = data.get(end..end + 4).ok_or(Error::Truncated)?;
+    if crc32(typed).to_be_bytes() != crc {
+        return Err(Error::Checksum);
+    }
+    let kind = [typed[0], typed[1], typed[2], typed[3]];
+    let chunk = Chunk {
+        kind,
+        body: &typed[4..],
+    };
+    Ok((chunk, &data[end + 4..]))
+}
+
+fn parse_header(body: &[u8]) -> Result<Header, Error> {
+    let [
+        w0,
+        w1,
+        w2,
+        w3,
+        h0,
+        h1,
+        h2,
+        h3,
+        depth,
+        colour,
+        compression,
+        filter,
+        interlace,
+    ] = *body
+    else {
+        return Err(Error::Invalid("bad IHDR length"));
+    };
+    if compression != 0 || filter != 0 || interlace > 1 {
+        return Err(Error::Invalid(
+            "bad compression, filter or interlace method",
+        ));
+    }
+    Ok(Header {
+        width: u32::from_be_bytes([w0, w1, w2, w3]),
+        height: u32::from_be_bytes([h0, h1, h2, h3]),
+        depth,
+        colour: ColourType::from_code(colour, depth)?,
+        interlaced: interlace == 1,
+    })
+}
+
+/// Undo a row's filter in place, given the unfiltered row above (zeros
+/// for a pass's first row) and the bytes per whole pixel.
+fn unfilter(filter: u8, row: &mut [u8], above: &[u8], bpp: usize) -> Result<(), Error> {
+    match filter {
+        0 => {}
+        1 => {
+            for i in bpp..row.len() {
+                row[i] = row[i].wrapping_add(row[i - bpp]);
+            }
+        }
+        2 => {
+            for (byte, &up) in row.iter_mut().zip(above) {
+                *byte = byte.wrapping_add(up);
+            }
+        }
+        3 => {
+            for i in 0..row.len() {
+                let left = if i >= bpp { row[i - bpp] } else { 0 };
+                let average = ((u16::from(left) + u16::from(above[i])) / 2) as u8;
+                row[i] = row[i].wrapping_add(average);
+            }
+        }
+        4 => {
+            for i in 0..row.len() {
+                let (left, up_left) = if i >= bpp {
+                    (row[i - bpp], above[i - bpp])
+                } else {
+                    (0, 0)
+                };
+                row[i] = row[i].wrapping_add(paeth(left, above[i], up_left));
+            }
+        }
+        _ => return Err(Error::Invalid("bad filter type")),
+    }
+    Ok(())
+}
+
+/// Whichever of left, above and upper left is nearest `left + above -
+/// up_left`, preferring them in that order.
+fn paeth(left: u8, above: u8, up_left: u8) -> u8 {
+    let estimate = i16::from(left) + i16::from(above) - i16::from(up_left);
+    let to_left = (estimate - i16::from(left)).abs();
+    let to_above = (estimate - i16::from(above)).abs();
+    let to_up_left = (estimate - i16::from(up_left)).abs();
+    if to_left <= to_above && to_left <= to_up_left {
+        left
+    } else if to_above <= to_up_left {
+        above
+    } else {
+        up_left
+    }
+}
+
+/// Turns unfiltered rows into `0xAARRGGBB` pixels.
+struct Pixels<'a> {
+    depth: u8,
+    colour: ColourType,
+    palette: &'a [[u8; 3]],
+    /// Per-entry palette alpha for indexed images.
+    palette_alpha: [u8; 256],
+    /// The one fully transparent colour of grey or RGB images, as samples
+    /// at the image's depth.
+    transparent: Option<[u16; 3]>,
+}
+
+impl<'a> Pixels<'a> {
+    fn new(
+        header: &Header,
+        palette: &'a [[u8; 3]],
+        transparency: Option<&[u8]>,
+    ) -> Result<Self, Error> {
+        let mut pixels = Self {
+            depth: header.depth,
+            colour: header.colour,
+            palette,
+            palette_alpha: [255; 256],
+            transparent: None,
+        };
+        let Some(trns) = transparency else {
+            return Ok(pixels);
+        };
+        let sample = |i: usize| u16::from_be_bytes([trns[2 * i], trns[2 * i + 1]]);
+        match header.colour {
+            ColourType::Indexed if trns.len() <= 256 => {
+                pixels.palette_alpha[..trns.len()].copy_from_slice(trns);
+            }
+            ColourType::Grey if trns.len() == 2 => {
+                pixels.transparent = Some([sample(0); 3]);
+            }
+            ColourType::Rgb if trns.len() == 6 => {
+                pixels.transparent = Some([sample(0), sample(1), sample(2)]);
+            }
+            _ => return Err(Error::Invalid("bad tRNS chunk")),
+        }
+        Ok(pixels)
+    }
+
+    /// The `index`th sample of `row`, at full depth.
+    fn sample(&self, row: &[u8], index: usize) -> u16 {
+        match self.depth {
+            16 => u16::from_be_bytes([row[2 * index], row[2 * index + 1]]),
+            8 => u16::from(row[index]),
+            depth => {
+                let bit = index * depth as usize;
+                let shift = 8 - depth as usize - bit % 8;
+                u16::from(row[bit / 8] >> shift) & ((1 << depth) - 1)
+            }
+        }
+    }
+
+    /// A sample scaled to eight bits.
+    fn eight_bit(&self, sample: u16) -> u8 {
+        match self.depth {
+            16 => (sample >> 8) as u8,
+            8 => sample as u8,
+            depth => scale(u32::from(sample), (1 << depth) - 1),
+        }
+    }
+
+    /// Pixel `index` of `row`.
+    fn get(&self, row: &[u8], index: usize) -> Result<u32, Error> {
+        let channels = self.colour.channels();
+        let s = |channel| self.sample(row, index * channels + channel);
+        let e = |channel| self.eight_bit(s(channel));
+        Ok(match self.colour {
+            ColourType::Grey => {
+                let grey = s(0);
+                let alpha = if self.transparent == Some([grey; 3]) {
+                    0
+                } else {
+                    255
+                };
+                let grey = self.eight_bit(grey);
+                argb(alpha, grey, grey, grey)
+            }
+            ColourType::Rgb => {
+                let alpha = if self.transparent == Some([s(0), s(1), s(2)]) {
+                    0
+                } else {
+                    255
+                };
+                argb(alpha, e(0), e(1), e(2))
+            }
+            ColourType::Indexed => {
+                let index = s(0) as usize;
+                let [r, g, b] = *self
+                    .palette
+                    .get(index)
+                    .ok_or(Error::Invalid("palette index out of range"))?;
+                argb(self.palette_alpha[index], r, g, b)
+            }
+            ColourType::GreyAlpha => {
+                let grey = e(0);
+                argb(e(1), grey, grey, grey)
+            }
+            ColourType::Rgba => argb(e(3), e(0), e(1), e(2)),
+        })
+    }
+}
+
+/// The CRC-32 PNG chunks carry (ISO-HDLC, polynomial `0xEDB88320`).
+pub fn crc32(data: &[u8]) -> u32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0; 256];
+        let mut n = 0;
+        while n < 256 {
+            let mut c = n as u32;
+            let mut k = 0;
+            while k < 8 {
+                c = if c & 1 != 0 {
+                    0xedb8_8320 ^ (c >> 1)
+                } else {
+                    c >> 1
+                };
+                k += 1;
+            }
+            table[n] = c;
+            n += 1;
+        }
+        table
+    };
+    !data.iter().fold(!0u32, |crc, &byte| {
+        TABLE[((crc ^ u32::from(byte)) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    /// A PNG with these chunks. The image data is stored, not compressed.
+    fn png(header: [u8; 13], chunks: &[(&[u8; 4], &[u8])], raw: &[u8]) -> Vec<u8> {
+        let mut zlib = vec![0x78, 0x01];
+        let mut blocks = raw.chunks(65535).peekable();
+        if blocks.peek().is_none() {
+            zlib.extend_from_slice(&[0x01, 0x00, 0x00, 0xff, 0xff]);
+        }
+        while let Some(block) = blocks.next() {
+            zlib.push(u8::from(blocks.peek().is_none()));
+            let len = block.len() as u16;
+            zlib.extend_from_slice(&len.to_le_bytes());
+            zlib.extend_from_slice(&(!len).to_le_bytes());
+            zlib.extend_from_slice(block);
+        }
+        zlib.extend_from_slice(&crate::inflate::adler32(raw).to_be_bytes());
+
+        let mut out = SIGNATURE.to_vec();
+        let mut put = |kind: &[u8; 4], body: &[u8]| {
+            out.extend_from_slice(&(body.len() as u32).to_be_bytes());
+            let start = out.len();
+            out.extend_from_slice(kind);
+            out.extend_from_slice(body);
+            let crc = crc32(&out[start..]);
+            out.extend_from_slice(&crc.to_be_bytes());
+        };
+        put(b"IHDR", &header);
+        for (kind, body) in chunks {
+            put(kind, body);
+        }
+        put(b"IDAT", &zlib);
+        put(b"IEND", &[]);
+        out
+    }
+
+    fn header(width: u32, height: u32, depth: u8, colour: u8, interlace: u8) -> [u8; 13] {
+        let mut header = [0; 13];
+        header[..4].copy_from_slice(&width.to_be_bytes());
+        header[4..8].copy_from_slice(&height.to_be_bytes());
+        header[8..].copy_from_slice(&[depth, colour, 0, 0, interlace]);
+        header
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn decodes_rgba() {
+        let raw = [
+            0, 255, 0, 0, 255, 0, 255, 0, 128, //
+            0, 0, 0, 255, 0, 1, 2, 3, 4,
+        ];
+        let image = decode(&png(header(2, 2, 8, 6, 0), &[], &raw)).unwrap();
+        assert_eq!(
+            image.pixels(),
+            [0xffff0000, 0x8000ff00, 0x000000ff, 0x04010203]
+        );
+    }
+
+    #[test]
+    fn decodes_sixteen_bit_rgb_with_transparency() {
+        let raw = [0, 0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0, 1, 0, 2, 0, 3];
+        let trns = [0, 1, 0, 2, 0, 3];
+        let image = decode(&png(header(2, 1, 16, 2, 0), &[(b"tRNS", &trns)], &raw)).unwrap();
+        assert_eq!(image.pixels(), [0xff12569a, 0x00000000]);
+    }
+
+    #[test]
+    fn decodes_low_depth_grey_and_palettes() {
+        // 2-bit grey: 0, 1, 2, 3, 3 padded out to two bytes.
+        let image = decode(&png(
+            header(5, 1, 2, 0, 0),
+            &[],
+            &[0, 0b0001_1011, 0b1100_0000],
+        ))
+        .unwrap();
+        assert_eq!(
+            image.pixels(),
+            [0xff000000, 0xff555555, 0xffaaaaaa, 0xffffffff, 0xffffffff]
+        );
+
+        let palette = [10, 20, 30, 40, 50, 60];
+        let chunks: [(&[u8; 4], &[u8]); 2] = [(b"PLTE", &palette), (b"tRNS", &[7])];
+        let image = decode(&png(header(3, 1, 1, 3, 0), &chunks, &[0, 0b0100_0000])).unwrap();
+        assert_eq!(image.pixels(), [0x070a141e, 0xff28323c, 0x070a141e]);
+
+        let bad = decode(&png(header(1, 1, 8, 3, 0), &chunks, &[0, 2]));
+        assert!(matches!(bad, Err(Error::Invalid(_))));
+    }
+
+    #[test]
+    fn undoes_filters() {
+        // Grey-alpha rows: Sub, Up, Average and Paeth.
+        let raw = [
+            1, 10, 200, 5, 1, //
+            2, 1, 1, 1, 1, //
+            3, 4, 0, 6, 0, //
+            4, 1, 1, 0, 0,
+        ];
+        let image = decode(&png(header(2, 4, 8, 4, 0), &[], &raw)).unwrap();
+        let grey = |v: u8, a: u8| argb(a, v, v, v);
+        assert_eq!(
+            image.pixels(),
+            [
+                grey(10, 200),
+                grey(15, 201),
+                grey(11, 201),
+                grey(16, 202),
+                grey(9, 100),
+                grey(18, 151),
+                grey(10, 101),
+                grey(18, 151),
+            ]
+        );
+        let raw = [5, 0, 0];
+        assert!(matches!(
+            decode(&png(header(1, 1, 8, 4, 0), &[], &raw)),
+            Err(Error::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn deinterlaces_adam7() {
+        // An 8x8 grey image whose pixels are numbered across the rows.
+        let mut raw = Vec::new();
+        for &(x0, y0, dx, dy) in &ADAM7 {
+            for y in (y0..8).step_by(dy as usize) {
+                raw.push(0);
+                raw.extend((x0..8).step_by(dx as usize).map(|x| (y * 8 + x) as u8));
+            }
+        }
+        let image = decode(&png(header(8, 8, 8, 0, 1), &[], &raw)).unwrap();
+        let expected: Vec<u32> = (0..64).map(|v| argb(255, v, v, v)).collect();
+        assert_eq!(image.pixels(), expected);
+
+        // Passes with no pixels take no rows: a 1x1 image has only the
+        // first.
+        let image = decode(&png(header(1, 1, 8, 0, 1), &[], &[0, 9])).unwrap();
+        assert_eq!(image.pixels(), [argb(255, 9, 9, 9)]);
+    }
+
+    #[test]
+    fn rejects_damaged_files() {
+        let good = png(header(1, 1, 8, 0, 0), &[], &[0, 9]);
+        assert!(decode(&good).is_ok());
+
+        let mut corrupt = good.clone();
+        corrupt[20] ^= 1;
+        assert_eq!(decode(&corrupt), Err(Error::Checksum));
+        assert_eq!(decode(&good[..good.len() - 12]), Err(Error::Truncated));
+        assert_eq!(
+            decode(&png(header(1, 1, 8, 0, 0), &[], &[0])),
+            Err(Error::Truncated)
+        );
+        assert!(matches!(
+            decode(&png(header(1, 1, 4, 2, 0), &[], &[0, 9])),
+            Err(Error::Invalid(_))
+        ));
+        assert_eq!(
+            decode(&png(header(1, 1, 8, 0, 0), &[(b"ABCD", &[])], &[0, 9])),
+            Err(Error::Unsupported("critical chunk"))
+        );
+        assert!(decode(&png(header(1, 1, 8, 0, 0), &[(b"tEXt", b"a\0b")], &[0, 9])).is_ok());
+        assert_eq!(
+            decode(&png(header(20000, 1, 8, 0, 0), &[], &[])),
+            Err(Error::TooLarge)
+        );
+    }
+}
@@ -0,0 +1,206 @@
+//! Netpbm decoding: PBM (`P1`, `P4`), PGM (`P2`, `P5`) and PPM (`P3`,
+//! `P6`), in their plain (text) and binary forms.
+//!
+//! Samples up to a maximum value of 65535 are scaled to eight bits. A file
+//! holding several images decodes as its first.
+
+use crate::{Error, Image, argb, scale};
+
+/// Reads the whitespace-separated header fields, skipping `#` comments.
+struct Fields<'a> {
+    data: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Fields<'a> {
+    fn skip_space(&mut self) {
+        while let Some(&byte) = self.data.get(self.at) {
+            match byte {
+                b'#' => {
+                    while self.data.get(self.at).is_some_and(|&b| b != b'\n') {
+                        self.at += 1;
+                    }
+                }
+                _ if byte.is_ascii_whitespace() => self.at += 1,
+                _ => break,
+            }
+        }
+    }
+
+    /// The next decimal number.
+    fn number(&mut self) -> Result<u32, Error> {
+        self.skip_space();
+        let start = self.at;
+        let mut value: u32 = 0;
+        while let Some(&byte) = self.data.get(self.at).filter(|b| b.is_ascii_digit()) {
+            value = value
+                .checked_mul(10)
+                .and_then(|v| v.checked_add(u32::from(byte - b'0')))
+                .ok_or(Error::TooLarge)?;
+            self.at += 1;
+        }
+        if self.at == start {
+            return Err(if self.at >= self.data.len() {
+                Error::Truncated
+            } else {
+                Error::Invalid("expected a number")
+            });
+        }
+        Ok(value)
+    }
+
+    /// The next plain PBM bit, which need not be separated from the next.
+    fn bit(&mut self) -> Result<u32, Error> {
+        self.skip_space();
+        match self.data.get(self.at) {
+            Some(&digit @ (b'0' | b'1')) => {
+                self.at += 1;
+                Ok(u32::from(digit - b'0'))
+            }
+            Some(_) => Err(Error::Invalid("expected 0 or 1")),
+            None => Err(Error::Truncated),
+        }
+    }
+}
+
+/// Decode a netpbm file.
+pub(crate) fn decode(data: &[u8]) -> Result<Image, Error> {
+    let kind = match data {
+        [b'P', kind @ b'1'..=b'6', ..] => *kind,
+        _ => return Err(Error::UnknownFormat),
+    };
+    let mut fields = Fields { data, at: 2 };
+    let width = fields.number()?;
+    let height = fields.number()?;
+    let bitmap = matches!(kind, b'1' | b'4');
+    let max = if bitmap { 1 } else { fields.number()? };
+    if max == 0 || max > 65535 {
+        return Err(Error::Invalid("bad maximum value"));
+    }
+    let mut image = Image::new(width, height)?;
+    let channels = if matches!(kind, b'3' | b'6') { 3 } else { 1 };
+
+    // Scale a sample, or fail if it is out of range. PBM is 1 for black.
+    let level = |sample: u32| {
+        if sample > max {
+            Err(Error::Invalid("sample above maximum value"))
+        } else if bitmap {
+            Ok(if sample == 1 { 0 } else { 255 })
+        } else {
+            Ok(scale(sample, max))
+        }
+    };
+    let pixel = |samples: [u32; 3]| -> Result<u32, Error> {
+        Ok(if channels == 3 {
+            argb(
+                255,
+                level(samples[0])?,
+                level(samples[1])?,
+                level(samples[2])?,
+            )
+        } else {
+            let v = level(samples[0])?;
+            argb(255, v, v, v)
+        })
+    };
+
+    let pixels = image.pixels_mut();
+    match kind {
+        b'1' => {
+            for out in pixels.iter_mut() {
+                *out = pixel([fields.bit()?; 3])?;
+            }
+        }
+        b'2' | b'3' => {
+            for out in pixels.iter_mut() {
+                let mut samples = [0; 3];
+                for sample in samples.iter_mut().take(channels) {
+                    *sample = fields.number()?;
+                }
+                *out = pixel(samples)?;
+            }
+        }
+        _ => {
+            // One whitespace byte ends the header of the binary forms.
+            let raster = data.get(fields.at + 1..).ok_or(Error::Truncated)?;
+            if bitmap {
+                let stride = (width as usize).div_ceil(8);
+                if raster.len() < stride * height as usize {
+                    return Err(Error::Truncated);
+                }
+                for (row, line) in pixels.chunks_exact_mut(width as usize).enumerate() {
+                    for (x, out) in line.iter_mut().enumerate() {
+                        let byte = raster[row * stride + x / 8];
+                        *out = pixel([u32::from(byte >> (7 - x % 8)) & 1; 3])?;
+                    }
+                }
+            } else {
+                let bytes = if max > 255 { 2 } else { 1 };
+                let sample_size = bytes * channels;
+                if raster.len() < pixels.len() * sample_size {
+                    return Err(Error::Truncated);
+                }
+                for (out, raw) in pixels.iter_mut().zip(raster.chunks_exact(sample_size)) {
+                    let mut samples = [0; 3];
+                    for (sample, raw) in samples.iter_mut().zip(raw.chunks_exact(bytes)) {
+                        *sample = raw.iter().fold(0, |v, &b| v << 8 | u32::from(b));
+                    }
+                    if channels == 1 {
+                        samples = [samples[0]; 3];
+                    }
+                    *out = pixel(samples)?;
+                }
+            }
+        }
+    }
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_plain_formats() {
+        let image = decode(b"P1\n# a comment\n3 2\n1 0 1\n010").unwrap();
+        let (black, white) = (0xff000000, 0xffffffff);
+        assert_eq!(image.pixels(), [black, white, black, white, black, white]);
+
+        let image = decode(b"P2 2 1 4 0 4").unwrap();
+        assert_eq!(image.pixels(), [black, white]);
+
+        let image = decode(b"P3 1 1 255 #colour\n 255 128 0").unwrap();
+        assert_eq!(image.pixels(), [0xffff8000]);
+    }
+
+    #[test]
+    fn decodes_binary_formats() {
+        let image = decode(b"P4 10 2\n\xc0\x40\x00\x00").unwrap();
+        let (black, white) = (0xff000000, 0xffffffff);
+        let mut expected = [white; 20];
+        expected[0] = black;
+        expected[1] = black;
+        expected[9] = black;
+        assert_eq!(image.pixels(), expected);
+
+        let image = decode(b"P5 2 1 255\n\x00\x80").unwrap();
+        assert_eq!(image.pixels(), [black, 0xff808080]);
+
+        let image = decode(b"P5 1 1 65535\n\x80\x00").unwrap();
+        assert_eq!(image.pixels(), [0xff808080]);
+
+        let image = decode(b"P6 2 1 15\n\x0f\x00\x05\x00\x0f\x0a").unwrap();
+        assert_eq!(image.pixels(), [0xffff0055, 0xff00ffaa]);
+    }
+
+    #[test]
+    fn rejects_bad_files() {
+        assert_eq!(decode(b"P6 2 2 255\n\0\0\0"), Err(Error::Truncated));
+        assert_eq!(decode(b"P2 1 1 255"), Err(Error::Truncated));
+        assert!(matches!(decode(b"P2 1 1 7 8"), Err(Error::Invalid(_))));
+        assert!(matches!(decode(b"P2 1 1 0 0"), Err(Error::Invalid(_))));
+        assert!(matches!(decode(b"P3 x"), Err(Error::Invalid(_))));
+        assert!(matches!(decode(b"P1 1 1 2"), Err(Error::Invalid(_))));
+        assert_eq!(decode(b"P5 99999999999 1 255\n"), Err(Error::TooLarge));
+    }
+}
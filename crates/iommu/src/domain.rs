@@ -0,0 +1,44 @@
+//! RAII ownership of an IOMMU domain.
+
+use crate::iommu::Iommu;
+use crate::types::{DomainId, IommuError};
+
+/// A domain that is destroyed when this value is dropped.
+///
+/// Holding an `IommuDomain` is the proof that the domain exists; embedding
+/// it in whatever owns the device (a claim, a driver) ties the domain's
+/// lifetime to that owner without any explicit cleanup path.
+pub struct IommuDomain {
+    id: DomainId,
+    iommu: &'static dyn Iommu,
+}
+
+impl IommuDomain {
+    /// Create a fresh domain on `iommu`.
+    pub fn new(iommu: &'static dyn Iommu) -> Result<Self, IommuError> {
+        let id = iommu.create_domain()?;
+        Ok(Self { id, iommu })
+    }
+
+    /// The backend's identifier for this domain.
+    pub fn id(&self) -> DomainId {
+        self.id
+    }
+
+    /// The IOMMU this domain lives on.
+    pub fn iommu(&self) -> &'static dyn Iommu {
+        self.iommu
+    }
+}
+
+impl Drop for IommuDomain {
+    fn drop(&mut self) {
+        self.iommu.destroy_domain(self.id);
+    }
+}
+
+impl core::fmt::Debug for IommuDomain {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("IommuDomain").field("id", &self.id).finish()
+    }
+}
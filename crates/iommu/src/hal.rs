@@ -0,0 +1,46 @@
+//! Hardware abstraction traits the kernel implements for this crate.
+//!
+//! These are the only seam between the crate and kernel-private memory
+//! management: page table frames come from a [`FrameAllocator`] and
+//! register access goes through an [`MmioAccess`].
+
+/// Provides 4 KB physically-contiguous frames for IOMMU page table nodes.
+///
+/// # Safety
+/// Implementors must guarantee that the returned frame is:
+/// - 4 KB aligned in both physical and virtual address
+/// - exclusively owned by the caller until `dealloc_frame` is called
+/// - valid for reads and writes of exactly 4096 bytes through the virtual pointer
+pub unsafe trait FrameAllocator: Send + Sync {
+    /// Allocate one page frame.
+    /// Returns `(physical_address, virtual_pointer)` or `None` on OOM.
+    fn alloc_frame(&self) -> Option<(u64, *mut u8)>;
+
+    /// Release a frame previously returned by `alloc_frame`.
+    ///
+    /// # Safety
+    /// `phys` must be a value previously returned by this allocator
+    /// and not yet freed.
+    unsafe fn dealloc_frame(&self, phys: u64);
+}
+
+/// Provides volatile read/write access to a contiguous MMIO register region.
+///
+/// # Safety
+/// Implementors must guarantee that reads and writes go directly to device
+/// memory without caching, and that the region covers at least the offsets
+/// the caller will access.
+pub unsafe trait MmioAccess: Send + Sync {
+    /// # Safety
+    /// `offset` must be within the region and 4-byte aligned.
+    unsafe fn read_u32(&self, offset: usize) -> u32;
+    /// # Safety
+    /// `offset` must be within the region and 4-byte aligned.
+    unsafe fn write_u32(&self, offset: usize, val: u32);
+    /// # Safety
+    /// `offset` must be within the region and 8-byte aligned.
+    unsafe fn read_u64(&self, offset: usize) -> u64;
+    /// # Safety
+    /// `offset` must be within the region and 8-byte aligned.
+    unsafe fn write_u64(&self, offset: usize, val: u64);
+}
@@ -0,0 +1,45 @@
+//! The hardware-agnostic [`Iommu`] trait.
+
+use alloc::vec::Vec;
+
+use crate::types::{DomainId, IommuError, IommuFault, IommuFlags, PciAddress};
+
+/// A DMA remapping unit (or a set of them behind one interface).
+///
+/// Everything below this trait is hardware specific. The kernel stores the
+/// active implementation as a `&'static dyn Iommu`, so code that allocates
+/// DMA memory or assigns devices never needs to know which hardware is
+/// present.
+pub trait Iommu: Send + Sync {
+    /// Allocate a new isolated translation domain with no mappings (other
+    /// than any firmware-reserved regions the backend must always map).
+    fn create_domain(&self) -> Result<DomainId, IommuError>;
+
+    /// Release a domain and all its page table mappings. Devices still
+    /// assigned to it must be reassigned first.
+    fn destroy_domain(&self, domain: DomainId);
+
+    /// Map `size` bytes of physical memory at `phys` into `domain` at
+    /// `iova`. All three must be page aligned.
+    fn map(
+        &self,
+        domain: DomainId,
+        iova: u64,
+        phys: u64,
+        size: usize,
+        flags: IommuFlags,
+    ) -> Result<(), IommuError>;
+
+    /// Remove the mappings covering `iova..iova + size` from `domain`.
+    fn unmap(&self, domain: DomainId, iova: u64, size: usize);
+
+    /// Assign a PCI device to a domain. Device DMA is now constrained to
+    /// mappings within that domain.
+    fn assign_device(&self, domain: DomainId, device: PciAddress) -> Result<(), IommuError>;
+
+    /// Flush the IOTLB for a domain after mapping changes.
+    fn flush(&self, domain: DomainId);
+
+    /// Drain the faults recorded by the hardware since the last call.
+    fn take_faults(&self) -> Vec<IommuFault>;
+}
@@ -0,0 +1,203 @@
+//! I/O virtual address allocation.
+//!
+//! Each domain has its own IOVA space. [`IovaAllocator`] hands out
+//! page-aligned ranges of it from a sorted free list, coalescing adjacent
+//! ranges on free so long-running drivers don't fragment the space.
+
+use alloc::vec::Vec;
+
+use crate::PAGE_SIZE;
+
+/// First-fit allocator over `[start, end)` of an IOVA space.
+#[derive(Debug, Clone)]
+pub struct IovaAllocator {
+    /// Free ranges as `(base, size)`, sorted by base and never adjacent
+    /// (adjacent ranges are always merged).
+    free: Vec<(u64, u64)>,
+}
+
+impl IovaAllocator {
+    /// Create an allocator managing `[range_start, range_end)`. Both bounds
+    /// are rounded inwards to page boundaries.
+    pub fn new(range_start: u64, range_end: u64) -> Self {
+        let start = align_up(range_start, PAGE_SIZE);
+        let end = range_end & !(PAGE_SIZE - 1);
+        let mut free = Vec::new();
+        if end > start {
+            free.push((start, end - start));
+        }
+        Self { free }
+    }
+
+    /// Allocate `size` bytes (rounded up to whole pages) aligned to `align`
+    /// (a power of two, at least one page). Returns the base IOVA.
+    pub fn alloc(&mut self, size: u64, align: u64) -> Option<u64> {
+        if size == 0 || !align.is_power_of_two() {
+            return None;
+        }
+        let size = align_up(size, PAGE_SIZE);
+        let align = align.max(PAGE_SIZE);
+
+        for i in 0..self.free.len() {
+            let (base, len) = self.free[i];
+            let aligned = align_up(base, align);
+            let padding = aligned - base;
+            if len < padding || len - padding < size {
+                continue;
+            }
+
+            let tail_base = aligned + size;
+            let tail_len = len - padding - size;
+            match (padding, tail_len) {
+                (0, 0) => {
+                    self.free.remove(i);
+                }
+                (0, _) => self.free[i] = (tail_base, tail_len),
+                (_, 0) => self.free[i] = (base, padding),
+                (_, _) => {
+                    self.free[i] = (base, padding);
+                    self.free.insert(i + 1, (tail_base, tail_len));
+                }
+            }
+            return Some(aligned);
+        }
+        None
+    }
+
+    /// Return `[base, base + size)` (rounded up to whole pages) to the free
+    /// list, merging with its neighbours.
+    pub fn free(&mut self, base: u64, size: u64) {
+        let size = align_up(size, PAGE_SIZE);
+        if size == 0 {
+            return;
+        }
+        let index = self.free.partition_point(|&(b, _)| b < base);
+        self.free.insert(index, (base, size));
+
+        // Merge with successor first so `index` stays valid.
+        if index + 1 < self.free.len() {
+            let (next_base, next_len) = self.free[index + 1];
+            if base + size == next_base {
+                self.free[index].1 += next_len;
+                self.free.remove(index + 1);
+            }
+        }
+        if index > 0 {
+            let (prev_base, prev_len) = self.free[index - 1];
+            if prev_base + prev_len == base {
+                self.free[index - 1].1 += self.free[index].1;
+                self.free.remove(index);
+            }
+        }
+    }
+
+    /// Remove `[base, base + size)` from the free list so it is never
+    /// handed out — used for ranges that are identity-mapped in every
+    /// domain (VT-d RMRRs).
+    pub fn reserve(&mut self, base: u64, size: u64) {
+        let start = base & !(PAGE_SIZE - 1);
+        let end = align_up(base.saturating_add(size), PAGE_SIZE);
+        let mut kept = Vec::with_capacity(self.free.len() + 1);
+        for &(b, len) in &self.free {
+            let e = b + len;
+            if e <= start || b >= end {
+                kept.push((b, len));
+                continue;
+            }
+            if b < start {
+                kept.push((b, start - b));
+            }
+            if e > end {
+                kept.push((end, e - end));
+            }
+        }
+        self.free = kept;
+    }
+
+    /// Total number of free bytes.
+    pub fn free_bytes(&self) -> u64 {
+        self.free.iter().map(|&(_, len)| len).sum()
+    }
+
+    /// Number of disjoint free ranges (a fragmentation measure).
+    pub fn free_ranges(&self) -> usize {
+        self.free.len()
+    }
+}
+
+const fn align_up(value: u64, align: u64) -> u64 {
+    (value + align - 1) & !(align - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alloc_free_round_trip() {
+        let mut iova = IovaAllocator::new(0x1000, 0x10_0000);
+        let total = iova.free_bytes();
+        let a = iova.alloc(0x3000, PAGE_SIZE).unwrap();
+        let b = iova.alloc(0x1000, PAGE_SIZE).unwrap();
+        assert_eq!(a, 0x1000);
+        assert_eq!(b, 0x4000);
+        assert_eq!(iova.free_bytes(), total - 0x4000);
+
+        iova.free(a, 0x3000);
+        iova.free(b, 0x1000);
+        assert_eq!(iova.free_bytes(), total);
+        assert_eq!(iova.free_ranges(), 1);
+    }
+
+    #[test]
+    fn sizes_round_up_to_pages() {
+        let mut iova = IovaAllocator::new(0, 0x10_0000);
+        let a = iova.alloc(1, PAGE_SIZE).unwrap();
+        let b = iova.alloc(1, PAGE_SIZE).unwrap();
+        assert_eq!(b - a, PAGE_SIZE);
+    }
+
+    #[test]
+    fn free_coalesces_out_of_order() {
+        let mut iova = IovaAllocator::new(0, 0x10000);
+        let a = iova.alloc(0x1000, PAGE_SIZE).unwrap();
+        let b = iova.alloc(0x1000, PAGE_SIZE).unwrap();
+        let c = iova.alloc(0x1000, PAGE_SIZE).unwrap();
+        iova.free(a, 0x1000);
+        iova.free(c, 0x1000);
+        assert_eq!(iova.free_ranges(), 2);
+        iova.free(b, 0x1000);
+        assert_eq!(iova.free_ranges(), 1);
+        assert_eq!(iova.free_bytes(), 0x10000);
+    }
+
+    #[test]
+    fn alignment_is_respected() {
+        let mut iova = IovaAllocator::new(0x1000, 0x100_0000);
+        let a = iova.alloc(0x1000, 0x10_0000).unwrap();
+        assert_eq!(a % 0x10_0000, 0);
+        // The padding before the aligned block is still allocatable.
+        let b = iova.alloc(0x1000, PAGE_SIZE).unwrap();
+        assert_eq!(b, 0x1000);
+    }
+
+    #[test]
+    fn exhaustion_returns_none() {
+        let mut iova = IovaAllocator::new(0, 0x2000);
+        assert!(iova.alloc(0x2000, PAGE_SIZE).is_some());
+        assert!(iova.alloc(0x1000, PAGE_SIZE).is_none());
+        assert!(iova.alloc(0, PAGE_SIZE).is_none());
+    }
+
+    #[test]
+    fn reserve_splits_free_range() {
+        let mut iova = IovaAllocator::new(0, 0x10000);
+        iova.reserve(0x4000, 0x2000);
+        assert_eq!(iova.free_ranges(), 2);
+        assert_eq!(iova.free_bytes(), 0xE000);
+        // Nothing handed out may overlap the reserved range.
+        while let Some(a) = iova.alloc(0x1000, PAGE_SIZE) {
+            assert!(a + 0x1000 <= 0x4000 || a >= 0x6000);
+        }
+    }
+}
@@ -0,0 +1,32 @@
+//! Hardware-agnostic IOMMU abstraction and backends for Panda OS.
+//!
+//! An IOMMU constrains device DMA to the mappings of the device's
+//! **domain**. This crate contains everything that is specification
+//! knowledge or pure bookkeeping — the [`Iommu`] trait, I/O virtual address
+//! allocation and the Intel VT-d backend — and nothing that depends on
+//! kernel internals. The kernel supplies physical frames and MMIO register
+//! access through the [`hal`] traits, and ACPI table bytes through
+//! [`vtd::dmar::parse`]. See `plans/iommu.md` for the full design.
+
+#![no_std]
+
+extern crate alloc;
+
+#[cfg(test)]
+extern crate std;
+
+pub mod domain;
+pub mod hal;
+mod iommu;
+pub mod iova;
+mod types;
+pub mod vtd;
+
+pub use domain::IommuDomain;
+pub use hal::{FrameAllocator, MmioAccess};
+pub use iommu::Iommu;
+pub use iova::IovaAllocator;
+pub use types::{DomainId, FaultKind, IommuError, IommuFault, IommuFlags, PciAddress};
+
+/// Size of the smallest page an IOMMU maps (4 KiB).
+pub const PAGE_SIZE: u64 = 4096;
@@ -0,0 +1,113 @@
+//! Shared types used by the [`Iommu`](crate::Iommu) trait and its backends.
+
+/// Identifies a translation domain. Backends choose the numbering; `0` is
+/// never handed out (VT-d reserves it when caching mode is set).
+pub type DomainId = u32;
+
+/// Access permissions for an IOMMU mapping.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct IommuFlags(u8);
+
+impl IommuFlags {
+    pub const READ: Self = Self(0x01);
+    pub const WRITE: Self = Self(0x02);
+    pub const READ_WRITE: Self = Self(0x03);
+
+    /// Whether the device may read through this mapping.
+    pub const fn readable(self) -> bool {
+        self.0 & Self::READ.0 != 0
+    }
+
+    /// Whether the device may write through this mapping.
+    pub const fn writable(self) -> bool {
+        self.0 & Self::WRITE.0 != 0
+    }
+}
+
+impl core::ops::BitOr for IommuFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// A PCI device address (segment, bus, device, function).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PciAddress {
+    pub segment: u16,
+    pub bus: u8,
+    pub device: u8,
+    pub function: u8,
+}
+
+impl PciAddress {
+    /// The 16-bit PCI requester ID (`bus << 8 | device << 3 | function`)
+    /// that IOMMU hardware reports in fault records.
+    pub const fn requester_id(self) -> u16 {
+        ((self.bus as u16) << 8) | ((self.device as u16 & 0x1f) << 3) | (self.function as u16 & 0x7)
+    }
+
+    /// Inverse of [`requester_id`](Self::requester_id).
+    pub const fn from_requester_id(segment: u16, source_id: u16) -> Self {
+        Self {
+            segment,
+            bus: (source_id >> 8) as u8,
+            device: ((source_id >> 3) & 0x1f) as u8,
+            function: (source_id & 0x7) as u8,
+        }
+    }
+}
+
+impl core::fmt::Display for PciAddress {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{:04x}:{:02x}:{:02x}.{}",
+            self.segment, self.bus, self.device, self.function
+        )
+    }
+}
+
+/// Errors returned by [`Iommu`](crate::Iommu) operations.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IommuError {
+    /// The hardware cannot do what was asked (e.g. a VT-d unit without
+    /// four-level tables).
+    Unsupported,
+    /// No such domain.
+    InvalidDomain,
+    /// Every hardware domain ID is in use.
+    DomainsExhausted,
+    /// A page table frame could not be allocated.
+    OutOfMemory,
+    /// An address or size was not page aligned, or overflowed the address
+    /// width supported by the hardware.
+    InvalidAddress,
+    /// The IOVA range is already mapped in this domain.
+    AlreadyMapped,
+    /// No remapping unit covers this device.
+    NoUnitForDevice,
+    /// The hardware did not complete a command in time.
+    Timeout,
+}
+
+/// The direction of a faulting DMA access.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FaultKind {
+    Read,
+    Write,
+}
+
+/// A DMA access the IOMMU blocked, as reported by the hardware.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct IommuFault {
+    /// The device that issued the access.
+    pub device: PciAddress,
+    /// The I/O virtual address (page granular) it tried to access.
+    pub iova: u64,
+    /// Whether it was a read or a write.
+    pub kind: FaultKind,
+    /// Backend-specific fault reason code (VT-d: the `FR` field).
+    pub reason: u8,
+}
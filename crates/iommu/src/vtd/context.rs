@@ -0,0 +1,214 @@
+//! Root and context tables (VT-d 9.1-9.3, legacy mode).
+//!
+//! The root table is one 4 KB page of 256 root entries, one per PCI bus.
+//! Each present root entry points to a 4 KB context table of 256 entries,
+//! one per device/function (`device << 3 | function`). A context entry
+//! names the device's domain and where its translation starts.
+
+use alloc::collections::BTreeMap;
+
+use super::flush_cache_line;
+use crate::hal::FrameAllocator;
+use crate::types::{DomainId, IommuError};
+
+const PRESENT: u64 = 1 << 0;
+
+/// Context entry translation type: untranslated requests are remapped
+/// through the second-level page table.
+const TT_TRANSLATED: u64 = 0b00 << 2;
+/// Context entry translation type: requests pass through untranslated.
+const TT_PASS_THROUGH: u64 = 0b10 << 2;
+/// Context entry address width: 4-level, 48-bit.
+const AW_4_LEVEL: u64 = 0b010;
+
+/// How a device's DMA is translated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Translation {
+    /// Walk the second-level page table rooted at this physical address.
+    PageTable(u64),
+    /// No translation: IOVA == physical address (requires ECAP.PT).
+    PassThrough,
+}
+
+/// A table frame: physical address and CPU pointer to its 512 qwords.
+#[derive(Clone, Copy)]
+struct TableFrame {
+    phys: u64,
+    virt: *mut u64,
+}
+
+/// The root table and its context tables, generic over where frames come
+/// from.
+pub struct RootTable<F: FrameAllocator> {
+    frames: F,
+    root: TableFrame,
+    /// Context table per bus, allocated on first use.
+    contexts: BTreeMap<u8, TableFrame>,
+    coherent: bool,
+}
+
+// Safety: the raw pointers are owned frames only this table touches, and
+// every mutation goes through `&mut self`.
+unsafe impl<F: FrameAllocator> Send for RootTable<F> {}
+unsafe impl<F: FrameAllocator> Sync for RootTable<F> {}
+
+impl<F: FrameAllocator> RootTable<F> {
+    /// Allocate an empty root table. `coherent` is the unit's ECAP.C bit:
+    /// when false, every entry write is flushed from the CPU cache.
+    pub fn new(frames: F, coherent: bool) -> Result<Self, IommuError> {
+        let root = alloc_zeroed(&frames)?;
+        Ok(Self {
+            frames,
+            root,
+            contexts: BTreeMap::new(),
+            coherent,
+        })
+    }
+
+    /// Physical address to program into `RTADDR`.
+    pub fn physical_address(&self) -> u64 {
+        self.root.phys
+    }
+
+    fn context_table(&mut self, bus: u8) -> Result<TableFrame, IommuError> {
+        if let Some(&table) = self.contexts.get(&bus) {
+            return Ok(table);
+        }
+        let table = alloc_zeroed(&self.frames)?;
+        // Root entries are 128 bits; only the low qword is used in legacy
+        // mode.
+        self.write(self.root, bus as usize * 2, table.phys | PRESENT);
+        self.contexts.insert(bus, table);
+        Ok(table)
+    }
+
+    /// Point `(bus, device, function)` at `domain`. The caller must
+    /// invalidate the context cache afterwards.
+    pub fn set_context_entry(
+        &mut self,
+        bus: u8,
+        device: u8,
+        function: u8,
+        domain: DomainId,
+        translation: Translation,
+    ) -> Result<(), IommuError> {
+        let table = self.context_table(bus)?;
+        let index = context_index(device, function);
+        let low = match translation {
+            Translation::PageTable(root) => (root & !0xfff) | TT_TRANSLATED | PRESENT,
+            Translation::PassThrough => TT_PASS_THROUGH | PRESENT,
+        };
+        let high = ((domain as u64 & 0xffff) << 8) | AW_4_LEVEL;
+        // Clear present first so hardware never sees a half-written entry.
+        self.write(table, index, 0);
+        self.write(table, index + 1, high);
+        self.write(table, index, low);
+        Ok(())
+    }
+
+    /// Mark `(bus, device, function)` not present: all its DMA faults.
+    pub fn clear_context_entry(&mut self, bus: u8, device: u8, function: u8) {
+        if let Some(&table) = self.contexts.get(&bus) {
+            let index = context_index(device, function);
+            self.write(table, index, 0);
+            self.write(table, index + 1, 0);
+        }
+    }
+
+    /// Raw `(low, high)` qwords of a context entry, for diagnostics and
+    /// tests.
+    pub fn context_entry(&self, bus: u8, device: u8, function: u8) -> Option<(u64, u64)> {
+        let table = self.contexts.get(&bus)?;
+        let index = context_index(device, function);
+        unsafe { Some((*table.virt.add(index), *table.virt.add(index + 1))) }
+    }
+
+    fn write(&self, table: TableFrame, index: usize, value: u64) {
+        unsafe {
+            let ptr = table.virt.add(index);
+            core::ptr::write_volatile(ptr, value);
+            if !self.coherent {
+                flush_cache_line(ptr as *const u8);
+            }
+        }
+    }
+}
+
+impl<F: FrameAllocator> Drop for RootTable<F> {
+    fn drop(&mut self) {
+        for table in self.contexts.values() {
+            unsafe { self.frames.dealloc_frame(table.phys) };
+        }
+        unsafe { self.frames.dealloc_frame(self.root.phys) };
+    }
+}
+
+/// Index (in qwords) of a device's 128-bit context entry.
+fn context_index(device: u8, function: u8) -> usize {
+    ((((device & 0x1f) as usize) << 3) | (function & 0x7) as usize) * 2
+}
+
+fn alloc_zeroed<F: FrameAllocator>(frames: &F) -> Result<TableFrame, IommuError> {
+    let (phys, virt) = frames.alloc_frame().ok_or(IommuError::OutOfMemory)?;
+    unsafe { core::ptr::write_bytes(virt, 0, 4096) };
+    Ok(TableFrame {
+        phys,
+        virt: virt as *mut u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vtd::test_support::TestFrames;
+
+    #[test]
+    fn context_entry_round_trip() {
+        let frames = TestFrames::default();
+        let mut root = RootTable::new(frames.clone(), true).unwrap();
+        root.set_context_entry(0, 3, 0, 7, Translation::PageTable(0x1234_5000))
+            .unwrap();
+
+        let (low, high) = root.context_entry(0, 3, 0).unwrap();
+        assert_eq!(low & PRESENT, PRESENT);
+        assert_eq!(low & !0xfff, 0x1234_5000);
+        assert_eq!((low >> 2) & 0b11, 0b00);
+        assert_eq!((high >> 8) & 0xffff, 7);
+        assert_eq!(high & 0b111, AW_4_LEVEL);
+
+        // The root entry for bus 0 points at the context table.
+        let root_entry = frames.read(root.physical_address(), 0);
+        assert_eq!(root_entry & PRESENT, PRESENT);
+        assert_eq!(frames.read(root_entry & !0xfff, context_index(3, 0)), low);
+    }
+
+    #[test]
+    fn pass_through_and_clear() {
+        let frames = TestFrames::default();
+        let mut root = RootTable::new(frames.clone(), true).unwrap();
+        root.set_context_entry(1, 0, 2, 1, Translation::PassThrough)
+            .unwrap();
+        let (low, _) = root.context_entry(1, 0, 2).unwrap();
+        assert_eq!((low >> 2) & 0b11, 0b10);
+
+        root.clear_context_entry(1, 0, 2);
+        assert_eq!(root.context_entry(1, 0, 2), Some((0, 0)));
+        // Untouched neighbours stay absent.
+        assert_eq!(root.context_entry(1, 0, 3), Some((0, 0)));
+        assert_eq!(root.context_entry(2, 0, 0), None);
+    }
+
+    #[test]
+    fn drop_frees_every_frame() {
+        let frames = TestFrames::default();
+        {
+            let mut root = RootTable::new(frames.clone(), true).unwrap();
+            root.set_context_entry(0, 1, 0, 1, Translation::PassThrough)
+                .unwrap();
+            root.set_context_entry(5, 1, 0, 1, Translation::PassThrough)
+                .unwrap();
+            assert_eq!(frames.live(), 3);
+        }
+        assert_eq!(frames.live(), 0);
+    }
+}
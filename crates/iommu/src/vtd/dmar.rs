@@ -0,0 +1,302 @@
+//! DMAR ACPI table parser (Intel VT-d specification, chapter 8).
+//!
+//! Pure byte-slice parsing: the kernel finds and maps the table, then hands
+//! its bytes to [`parse`]. Only the structures the VT-d backend needs are
+//! decoded — DRHD (one per remapping unit) and RMRR (firmware-reserved
+//! regions that must stay identity mapped). Other remapping structures
+//! (ATSR, RHSA, ANDD, SATC) are skipped.
+
+use alloc::vec::Vec;
+
+/// Size of the fixed DMAR header (ACPI SDT header + DMAR fields).
+const DMAR_HEADER_LEN: usize = 48;
+
+const TYPE_DRHD: u16 = 0;
+const TYPE_RMRR: u16 = 1;
+
+/// DRHD flag: this unit covers every PCI device on its segment that is not
+/// explicitly listed in another unit's device scope.
+const DRHD_INCLUDE_PCI_ALL: u8 = 1 << 0;
+
+/// Errors returned by [`parse`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DmarError {
+    /// The signature is not `"DMAR"`.
+    BadSignature,
+    /// The table (or one of its sub-structures) is shorter than its
+    /// declared length, or a length field is impossible.
+    Truncated,
+}
+
+/// Device scope entry type (VT-d 8.3.1).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceScopeKind {
+    PciEndpoint,
+    PciSubHierarchy,
+    IoApic,
+    Hpet,
+    AcpiNamespace,
+    Other(u8),
+}
+
+impl DeviceScopeKind {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Self::PciEndpoint,
+            2 => Self::PciSubHierarchy,
+            3 => Self::IoApic,
+            4 => Self::Hpet,
+            5 => Self::AcpiNamespace,
+            other => Self::Other(other),
+        }
+    }
+}
+
+/// A device (or bridge hierarchy) named by a DRHD or RMRR.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceScope {
+    pub kind: DeviceScopeKind,
+    pub enumeration_id: u8,
+    pub start_bus: u8,
+    /// `(device, function)` hops from `start_bus` to the device.
+    pub path: Vec<(u8, u8)>,
+}
+
+impl DeviceScope {
+    /// The `(bus, device, function)` of the scope's target, if it is
+    /// reachable in one hop from `start_bus`. Deeper paths cross bridges
+    /// whose secondary bus numbers would have to be read from config space;
+    /// the backend treats those conservatively (see `VtdIommu::unit_for`).
+    pub fn direct_target(&self) -> Option<(u8, u8, u8)> {
+        match self.path.as_slice() {
+            [(device, function)] => Some((self.start_bus, *device, *function)),
+            _ => None,
+        }
+    }
+}
+
+/// DMA Remapping Hardware unit Definition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DrhdEntry {
+    /// Physical base address of the unit's register set.
+    pub register_base: u64,
+    pub segment: u16,
+    /// Covers every device on `segment` not claimed by another unit.
+    pub include_pci_all: bool,
+    pub scopes: Vec<DeviceScope>,
+}
+
+/// Reserved Memory Region Reporting structure: a physical range firmware
+/// expects the listed devices to keep reaching via DMA.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RmrrEntry {
+    pub segment: u16,
+    pub base: u64,
+    /// Inclusive upper bound, as reported by firmware.
+    pub limit: u64,
+    pub scopes: Vec<DeviceScope>,
+}
+
+impl RmrrEntry {
+    /// Length of the region in bytes.
+    pub fn size(&self) -> u64 {
+        self.limit.saturating_sub(self.base) + 1
+    }
+}
+
+/// A parsed DMAR table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DmarTable {
+    /// DMA physical address width supported by the platform (the table
+    /// stores N-1; this is N).
+    pub host_address_width: u8,
+    pub flags: u8,
+    pub drhds: Vec<DrhdEntry>,
+    pub rmrrs: Vec<RmrrEntry>,
+}
+
+/// Parse the raw bytes of a DMAR table (starting at its ACPI header).
+pub fn parse(bytes: &[u8]) -> Result<DmarTable, DmarError> {
+    if bytes.len() < DMAR_HEADER_LEN {
+        return Err(DmarError::Truncated);
+    }
+    if &bytes[0..4] != b"DMAR" {
+        return Err(DmarError::BadSignature);
+    }
+    let length = read_u32(bytes, 4) as usize;
+    if length < DMAR_HEADER_LEN || length > bytes.len() {
+        return Err(DmarError::Truncated);
+    }
+    let bytes = &bytes[..length];
+
+    let mut table = DmarTable {
+        host_address_width: bytes[36] + 1,
+        flags: bytes[37],
+        drhds: Vec::new(),
+        rmrrs: Vec::new(),
+    };
+
+    let mut offset = DMAR_HEADER_LEN;
+    while offset < bytes.len() {
+        if bytes.len() - offset < 4 {
+            return Err(DmarError::Truncated);
+        }
+        let kind = read_u16(bytes, offset);
+        let len = read_u16(bytes, offset + 2) as usize;
+        if len < 4 || offset + len > bytes.len() {
+            return Err(DmarError::Truncated);
+        }
+        let entry = &bytes[offset..offset + len];
+
+        match kind {
+            TYPE_DRHD => {
+                if len < 16 {
+                    return Err(DmarError::Truncated);
+                }
+                table.drhds.push(DrhdEntry {
+                    include_pci_all: entry[4] & DRHD_INCLUDE_PCI_ALL != 0,
+                    segment: read_u16(entry, 6),
+                    register_base: read_u64(entry, 8),
+                    scopes: parse_scopes(&entry[16..])?,
+                });
+            }
+            TYPE_RMRR => {
+                if len < 24 {
+                    return Err(DmarError::Truncated);
+                }
+                table.rmrrs.push(RmrrEntry {
+                    segment: read_u16(entry, 6),
+                    base: read_u64(entry, 8),
+                    limit: read_u64(entry, 16),
+                    scopes: parse_scopes(&entry[24..])?,
+                });
+            }
+            _ => {}
+        }
+        offset += len;
+    }
+
+    Ok(table)
+}
+
+fn parse_scopes(mut bytes: &[u8]) -> Result<Vec<DeviceScope>, DmarError> {
+    let mut scopes = Vec::new();
+    while !bytes.is_empty() {
+        if bytes.len() < 6 {
+            return Err(DmarError::Truncated);
+        }
+        let len = bytes[1] as usize;
+        if len < 6 || len > bytes.len() || !(len - 6).is_multiple_of(2) {
+            return Err(DmarError::Truncated);
+        }
+        scopes.push(DeviceScope {
+            kind: DeviceScopeKind::from_u8(bytes[0]),
+            enumeration_id: bytes[4],
+            start_bus: bytes[5],
+            path: bytes[6..len]
+                .chunks_exact(2)
+                .map(|hop| (hop[0], hop[1]))
+                .collect(),
+        });
+        bytes = &bytes[len..];
+    }
+    Ok(scopes)
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut raw = [0u8; 4];
+    raw.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(raw)
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    let mut raw = [0u8; 8];
+    raw.copy_from_slice(&bytes[offset..offset + 8]);
+    u64::from_le_bytes(raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec;
+
+    /// Build a DMAR table shaped like QEMU's `intel-iommu` one: a single
+    /// INCLUDE_PCI_ALL DRHD with an I/O APIC scope, plus an RMRR naming
+    /// device 00:1f.0.
+    fn sample_table() -> Vec<u8> {
+        let mut t = vec![0u8; DMAR_HEADER_LEN];
+        t[0..4].copy_from_slice(b"DMAR");
+        t[36] = 38; // 39-bit host address width
+        t[37] = 0x1;
+
+        // DRHD: 16-byte header + one 8-byte IOAPIC scope.
+        let mut drhd = vec![0u8; 16];
+        drhd[0..2].copy_from_slice(&TYPE_DRHD.to_le_bytes());
+        drhd[2..4].copy_from_slice(&24u16.to_le_bytes());
+        drhd[4] = DRHD_INCLUDE_PCI_ALL;
+        drhd[8..16].copy_from_slice(&0xFED9_0000u64.to_le_bytes());
+        drhd.extend_from_slice(&[3, 8, 0, 0, 0, 0xff, 0x00, 0x00]);
+        t.extend_from_slice(&drhd);
+
+        // RMRR: 24-byte header + one PCI endpoint scope 00:1f.0.
+        let mut rmrr = vec![0u8; 24];
+        rmrr[0..2].copy_from_slice(&TYPE_RMRR.to_le_bytes());
+        rmrr[2..4].copy_from_slice(&32u16.to_le_bytes());
+        rmrr[8..16].copy_from_slice(&0xA_0000u64.to_le_bytes());
+        rmrr[16..24].copy_from_slice(&0xB_FFFFu64.to_le_bytes());
+        rmrr.extend_from_slice(&[1, 8, 0, 0, 0, 0, 0x1f, 0x0]);
+        t.extend_from_slice(&rmrr);
+
+        // An ATSR the parser should skip.
+        t.extend_from_slice(&[2, 0, 8, 0, 0, 0, 0, 0]);
+
+        let len = t.len() as u32;
+        t[4..8].copy_from_slice(&len.to_le_bytes());
+        t
+    }
+
+    #[test]
+    fn parses_drhd_and_rmrr() {
+        let table = parse(&sample_table()).unwrap();
+        assert_eq!(table.host_address_width, 39);
+        assert_eq!(table.drhds.len(), 1);
+        assert_eq!(table.rmrrs.len(), 1);
+
+        let drhd = &table.drhds[0];
+        assert_eq!(drhd.register_base, 0xFED9_0000);
+        assert!(drhd.include_pci_all);
+        assert_eq!(drhd.scopes.len(), 1);
+        assert_eq!(drhd.scopes[0].kind, DeviceScopeKind::IoApic);
+        assert_eq!(drhd.scopes[0].start_bus, 0xff);
+
+        let rmrr = &table.rmrrs[0];
+        assert_eq!(rmrr.base, 0xA_0000);
+        assert_eq!(rmrr.size(), 0x2_0000);
+        assert_eq!(rmrr.scopes[0].kind, DeviceScopeKind::PciEndpoint);
+        assert_eq!(rmrr.scopes[0].direct_target(), Some((0, 0x1f, 0)));
+    }
+
+    #[test]
+    fn rejects_bad_signature() {
+        let mut bytes = sample_table();
+        bytes[0] = b'X';
+        assert_eq!(parse(&bytes), Err(DmarError::BadSignature));
+    }
+
+    #[test]
+    fn rejects_truncated_tables() {
+        let bytes = sample_table();
+        assert_eq!(parse(&bytes[..20]), Err(DmarError::Truncated));
+        // Declared length longer than the bytes we were given.
+        assert_eq!(parse(&bytes[..bytes.len() - 1]), Err(DmarError::Truncated));
+
+        // A sub-structure whose length runs past the end of the table.
+        let mut bytes = sample_table();
+        bytes[DMAR_HEADER_LEN + 2] = 0xff;
+        assert_eq!(parse(&bytes), Err(DmarError::Truncated));
+    }
+}
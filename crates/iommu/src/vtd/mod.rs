@@ -0,0 +1,697 @@
+//! Intel VT-d backend.
+//!
+//! One [`VtdUnit`] per DRHD in the DMAR table, assembled into a
+//! [`VtdIommu`] that implements [`Iommu`]. Devices not explicitly placed
+//! in a domain live in the passthrough domain (see
+//! [`VtdIommu::init_passthrough_domain`]), which preserves the
+//! pre-IOMMU behaviour of devices being able to DMA anywhere in RAM.
+//!
+//! Invalidation is register based (no queued invalidation), and every
+//! mapping change flushes the whole IOTLB — domain-selective invalidation
+//! is a future optimisation.
+
+pub mod context;
+pub mod dmar;
+pub mod page_table;
+pub mod registers;
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use spinning_top::Spinlock;
+
+use crate::hal::{FrameAllocator, MmioAccess};
+use crate::iommu::Iommu;
+use crate::types::{DomainId, FaultKind, IommuError, IommuFault, IommuFlags, PciAddress};
+use context::{RootTable, Translation};
+use dmar::{DeviceScopeKind, DrhdEntry, RmrrEntry};
+use registers::VtdRegisters;
+
+pub use page_table::SlptRoot;
+
+/// The domain every unclaimed device is assigned to.
+pub const PASSTHROUGH_DOMAIN: DomainId = 1;
+
+/// Write a cache line back to memory so a non-coherent remapping unit
+/// (ECAP.C clear) sees a just-written paging-structure entry.
+///
+/// # Safety
+/// `ptr` must be a valid mapped address.
+pub(crate) unsafe fn flush_cache_line(ptr: *const u8) {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        core::arch::asm!("clflush [{}]", in(reg) ptr, options(nostack, preserves_flags));
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    let _ = ptr;
+}
+
+/// One remapping hardware unit: its registers, its root table and the
+/// devices it is responsible for.
+pub struct VtdUnit<F: FrameAllocator, M: MmioAccess> {
+    registers: VtdRegisters<M>,
+    root: RootTable<F>,
+    drhd: DrhdEntry,
+}
+
+impl<F: FrameAllocator, M: MmioAccess> VtdUnit<F, M> {
+    /// Take over the unit described by `drhd`: allocate an empty root
+    /// table, point the hardware at it, and mask the fault interrupt
+    /// (faults are polled through [`Iommu::take_faults`]). Units that can't
+    /// walk four-level tables are rejected, since those are all
+    /// [`SlptRoot`] builds.
+    pub fn new(frames: F, mmio: M, drhd: DrhdEntry) -> Result<Self, IommuError> {
+        let registers = VtdRegisters::new(mmio);
+        if !registers.capability().supports_4_level() {
+            return Err(IommuError::Unsupported);
+        }
+        let coherent = registers.extended_capability().coherent();
+        let root = RootTable::new(frames, coherent)?;
+        registers.mask_fault_interrupt();
+        registers.set_root_table(root.physical_address())?;
+        registers.invalidate_context_cache()?;
+        registers.invalidate_iotlb_global()?;
+        Ok(Self {
+            registers,
+            root,
+            drhd,
+        })
+    }
+
+    pub fn registers(&self) -> &VtdRegisters<M> {
+        &self.registers
+    }
+
+    pub fn drhd(&self) -> &DrhdEntry {
+        &self.drhd
+    }
+
+    /// Whether this unit's device scope explicitly names `device`.
+    fn explicitly_covers(&self, device: PciAddress) -> bool {
+        self.drhd.segment == device.segment
+            && self.drhd.scopes.iter().any(|scope| match scope.kind {
+                DeviceScopeKind::PciEndpoint => {
+                    scope.direct_target() == Some((device.bus, device.device, device.function))
+                }
+                // Without reading bridge config space we can't know the
+                // secondary bus range, so match the bridge's own bus.
+                DeviceScopeKind::PciSubHierarchy => scope.start_bus == device.bus,
+                _ => false,
+            })
+    }
+}
+
+struct Domain<F: FrameAllocator> {
+    /// `None` for a hardware pass-through domain (no page table at all).
+    table: Option<SlptRoot<F>>,
+    devices: Vec<PciAddress>,
+}
+
+struct State<F: FrameAllocator, M: MmioAccess> {
+    units: Vec<VtdUnit<F, M>>,
+    domains: BTreeMap<DomainId, Domain<F>>,
+    next_id: DomainId,
+    free_ids: Vec<DomainId>,
+}
+
+/// All VT-d units on the platform behind one [`Iommu`].
+pub struct VtdIommu<F: FrameAllocator + Clone, M: MmioAccess> {
+    frames: F,
+    rmrrs: Vec<RmrrEntry>,
+    max_domains: u32,
+    coherent: bool,
+    large_pages: bool,
+    pass_through: bool,
+    state: Spinlock<State<F, M>>,
+}
+
+impl<F: FrameAllocator + Clone, M: MmioAccess> VtdIommu<F, M> {
+    /// Assemble units into one IOMMU. Translation stays disabled until
+    /// [`init_passthrough_domain`](Self::init_passthrough_domain).
+    pub fn new(frames: F, units: Vec<VtdUnit<F, M>>, rmrrs: Vec<RmrrEntry>) -> Self {
+        let caps = || units.iter().map(|unit| unit.registers.capability());
+        let ecaps = || {
+            units
+                .iter()
+                .map(|unit| unit.registers.extended_capability())
+        };
+        let max_domains = caps().map(|cap| cap.domain_count()).min().unwrap_or(0);
+        let large_pages = caps().all(|cap| cap.supports_2m_pages());
+        let coherent = ecaps().all(|ecap| ecap.coherent());
+        let pass_through = ecaps().all(|ecap| ecap.pass_through());
+
+        Self {
+            frames,
+            rmrrs,
+            max_domains,
+            coherent,
+            large_pages,
+            pass_through,
+            state: Spinlock::new(State {
+                units,
+                domains: BTreeMap::new(),
+                // Domain ID 0 is reserved when caching mode is set; skip it
+                // unconditionally for simplicity.
+                next_id: PASSTHROUGH_DOMAIN,
+                free_ids: Vec::new(),
+            }),
+        }
+    }
+
+    /// Number of remapping units.
+    pub fn unit_count(&self) -> usize {
+        self.state.lock().units.len()
+    }
+
+    /// Deliver fault events from every unit as the MSI `address`/`data`
+    /// pair. The records themselves stay latched until
+    /// [`Iommu::take_faults`] collects them.
+    pub fn enable_fault_interrupt(&self, address: u64, data: u32) {
+        for unit in &self.state.lock().units {
+            unit.registers.enable_fault_interrupt(address, data);
+        }
+    }
+
+    /// Firmware-reserved regions identity-mapped into every domain.
+    pub fn reserved_regions(&self) -> &[RmrrEntry] {
+        &self.rmrrs
+    }
+
+    /// Create [`PASSTHROUGH_DOMAIN`], assign `devices` to it, and enable
+    /// translation on every unit.
+    ///
+    /// If every unit supports pass-through context entries the domain has
+    /// no page table at all; otherwise `0..limit` is identity mapped, so
+    /// `limit` must cover every physical address a kernel driver may hand
+    /// to a device.
+    pub fn init_passthrough_domain(
+        &self,
+        limit: u64,
+        devices: &[PciAddress],
+    ) -> Result<DomainId, IommuError> {
+        let id = if self.pass_through {
+            let mut state = self.state.lock();
+            let id = Self::allocate_id(&mut state, self.max_domains)?;
+            state.domains.insert(
+                id,
+                Domain {
+                    table: None,
+                    devices: Vec::new(),
+                },
+            );
+            id
+        } else {
+            let id = self.create_domain()?;
+            let limit = limit & !(crate::PAGE_SIZE - 1);
+            let mut state = self.state.lock();
+            let domain = state
+                .domains
+                .get_mut(&id)
+                .ok_or(IommuError::InvalidDomain)?;
+            if let Some(table) = domain.table.as_mut() {
+                // RMRRs were already mapped by create_domain; map around
+                // them rather than failing on the overlap.
+                let mut start = 0;
+                for (base, end) in self.rmrr_ranges() {
+                    let hole_end = base.min(limit);
+                    if hole_end > start {
+                        table.map(
+                            start,
+                            start,
+                            (hole_end - start) as usize,
+                            IommuFlags::READ_WRITE,
+                        )?;
+                    }
+                    start = start.max(end);
+                }
+                if limit > start {
+                    table.map(
+                        start,
+                        start,
+                        (limit - start) as usize,
+                        IommuFlags::READ_WRITE,
+                    )?;
+                }
+            }
+            id
+        };
+
+        for &device in devices {
+            // Devices behind no unit (e.g. the IOMMU's own function) are
+            // simply not translated.
+            match self.assign_device(id, device) {
+                Ok(()) | Err(IommuError::NoUnitForDevice) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        let state = self.state.lock();
+        for unit in &state.units {
+            unit.registers.invalidate_context_cache()?;
+            unit.registers.invalidate_iotlb_global()?;
+            unit.registers.enable_translation()?;
+        }
+        Ok(id)
+    }
+
+    /// Sorted, page-aligned `(base, end)` RMRR ranges.
+    fn rmrr_ranges(&self) -> Vec<(u64, u64)> {
+        let mut ranges: Vec<(u64, u64)> = self
+            .rmrrs
+            .iter()
+            .map(|rmrr| {
+                let base = rmrr.base & !(crate::PAGE_SIZE - 1);
+                let end =
+                    (rmrr.base + rmrr.size() + crate::PAGE_SIZE - 1) & !(crate::PAGE_SIZE - 1);
+                (base, end)
+            })
+            .collect();
+        ranges.sort_unstable();
+        ranges
+    }
+
+    fn allocate_id(state: &mut State<F, M>, max_domains: u32) -> Result<DomainId, IommuError> {
+        if let Some(id) = state.free_ids.pop() {
+            return Ok(id);
+        }
+        if state.next_id >= max_domains {
+            return Err(IommuError::DomainsExhausted);
+        }
+        let id = state.next_id;
+        state.next_id += 1;
+        Ok(id)
+    }
+
+    /// The unit responsible for `device`: one that names it explicitly,
+    /// else the segment's INCLUDE_PCI_ALL unit.
+    fn unit_for(units: &mut [VtdUnit<F, M>], device: PciAddress) -> Option<&mut VtdUnit<F, M>> {
+        let index = units
+            .iter()
+            .position(|unit| unit.explicitly_covers(device))
+            .or_else(|| {
+                units.iter().position(|unit| {
+                    unit.drhd.include_pci_all && unit.drhd.segment == device.segment
+                })
+            })?;
+        units.get_mut(index)
+    }
+
+    fn flush_all(state: &State<F, M>) {
+        for unit in &state.units {
+            // A timeout leaves stale entries cached until the next flush
+            // succeeds; there is nothing better to do with it here.
+            let _ = unit.registers.invalidate_iotlb_global();
+        }
+    }
+}
+
+impl<F: FrameAllocator + Clone, M: MmioAccess> Iommu for VtdIommu<F, M> {
+    fn create_domain(&self) -> Result<DomainId, IommuError> {
+        let mut table = SlptRoot::new(self.frames.clone(), self.coherent, self.large_pages)?;
+        for (base, end) in self.rmrr_ranges() {
+            table.map(base, base, (end - base) as usize, IommuFlags::READ_WRITE)?;
+        }
+
+        let mut state = self.state.lock();
+        let id = Self::allocate_id(&mut state, self.max_domains)?;
+        state.domains.insert(
+            id,
+            Domain {
+                table: Some(table),
+                devices: Vec::new(),
+            },
+        );
+        Ok(id)
+    }
+
+    fn destroy_domain(&self, domain: DomainId) {
+        let mut state = self.state.lock();
+        let Some(removed) = state.domains.remove(&domain) else {
+            return;
+        };
+        // Any device still pointing at the domain's tables must stop
+        // before the frames are freed.
+        for device in &removed.devices {
+            if let Some(unit) = Self::unit_for(&mut state.units, *device) {
+                unit.root
+                    .clear_context_entry(device.bus, device.device, device.function);
+                let _ = unit.registers.invalidate_context_cache();
+            }
+        }
+        Self::flush_all(&state);
+        state.free_ids.push(domain);
+        drop(state);
+        drop(removed);
+    }
+
+    fn map(
+        &self,
+        domain: DomainId,
+        iova: u64,
+        phys: u64,
+        size: usize,
+        flags: IommuFlags,
+    ) -> Result<(), IommuError> {
+        let mut state = self.state.lock();
+        let entry = state
+            .domains
+            .get_mut(&domain)
+            .ok_or(IommuError::InvalidDomain)?;
+        let table = entry.table.as_mut().ok_or(IommuError::Unsupported)?;
+        table.map(iova, phys, size, flags)?;
+        // Caching-mode units may have cached the not-present entry.
+        Self::flush_all(&state);
+        Ok(())
+    }
+
+    fn unmap(&self, domain: DomainId, iova: u64, size: usize) {
+        let mut state = self.state.lock();
+        if let Some(table) = state
+            .domains
+            .get_mut(&domain)
+            .and_then(|entry| entry.table.as_mut())
+        {
+            table.unmap(iova, size);
+            Self::flush_all(&state);
+        }
+    }
+
+    fn assign_device(&self, domain: DomainId, device: PciAddress) -> Result<(), IommuError> {
+        let mut state = self.state.lock();
+        let translation = match state.domains.get(&domain) {
+            Some(Domain {
+                table: Some(table), ..
+            }) => Translation::PageTable(table.root_phys()),
+            Some(Domain { table: None, .. }) => Translation::PassThrough,
+            None => return Err(IommuError::InvalidDomain),
+        };
+
+        let unit = Self::unit_for(&mut state.units, device).ok_or(IommuError::NoUnitForDevice)?;
+        unit.root.set_context_entry(
+            device.bus,
+            device.device,
+            device.function,
+            domain,
+            translation,
+        )?;
+        unit.registers.invalidate_context_cache()?;
+        unit.registers.invalidate_iotlb_global()?;
+
+        for entry in state.domains.values_mut() {
+            entry.devices.retain(|&d| d != device);
+        }
+        if let Some(entry) = state.domains.get_mut(&domain) {
+            entry.devices.push(device);
+        }
+        Ok(())
+    }
+
+    fn flush(&self, _domain: DomainId) {
+        Self::flush_all(&self.state.lock());
+    }
+
+    fn take_faults(&self) -> Vec<IommuFault> {
+        let state = self.state.lock();
+        let mut faults = Vec::new();
+        for unit in &state.units {
+            let segment = unit.drhd.segment;
+            unit.registers.take_fault_records(|record| {
+                faults.push(IommuFault {
+                    device: PciAddress::from_requester_id(segment, record.source_id),
+                    iova: record.address,
+                    kind: if record.read {
+                        FaultKind::Read
+                    } else {
+                        FaultKind::Write
+                    },
+                    reason: record.reason,
+                });
+            });
+        }
+        faults
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test_support {
+    //! A host-side [`FrameAllocator`] whose "physical" addresses are the
+    //! frames' heap addresses, so tests can follow table pointers.
+
+    use std::alloc::{Layout, alloc, dealloc};
+    use std::collections::BTreeSet;
+    use std::sync::{Arc, Mutex};
+
+    use crate::hal::FrameAllocator;
+
+    #[derive(Clone, Default)]
+    pub struct TestFrames {
+        live: Arc<Mutex<BTreeSet<u64>>>,
+    }
+
+    impl TestFrames {
+        fn layout() -> Layout {
+            Layout::from_size_align(4096, 4096).unwrap()
+        }
+
+        /// Number of frames allocated and not yet freed.
+        pub fn live(&self) -> usize {
+            self.live.lock().unwrap().len()
+        }
+
+        /// Read qword `index` of the frame at `phys`.
+        pub fn read(&self, phys: u64, index: usize) -> u64 {
+            assert!(self.live.lock().unwrap().contains(&phys));
+            unsafe { *(phys as *const u64).add(index) }
+        }
+    }
+
+    unsafe impl FrameAllocator for TestFrames {
+        fn alloc_frame(&self) -> Option<(u64, *mut u8)> {
+            let ptr = unsafe { alloc(Self::layout()) };
+            self.live.lock().unwrap().insert(ptr as u64);
+            Some((ptr as u64, ptr))
+        }
+
+        unsafe fn dealloc_frame(&self, phys: u64) {
+            assert!(self.live.lock().unwrap().remove(&phys), "double free");
+            unsafe { dealloc(phys as *mut u8, Self::layout()) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use test_support::TestFrames;
+
+    /// A register file that completes every command instantly: GCMD enable
+    /// bits are mirrored into GSTS and ICC/IVT clear as soon as written.
+    struct FakeRegisters {
+        regs: Mutex<[u8; 0x400]>,
+    }
+
+    const CAP: u64 = (0x20 << 24) // fault records at 0x200
+        | (3 << 40) // four fault records
+        | (1 << 34) // 2 MB superpages
+        | (0b00100 << 8) // 4-level tables
+        | 0x2; // 256 domains
+    const ECAP: u64 = (0x10 << 8) | (1 << 6) | 1; // IOTLB at 0x100, PT, coherent
+
+    impl FakeRegisters {
+        fn new() -> Self {
+            let fake = Self {
+                regs: Mutex::new([0; 0x400]),
+            };
+            unsafe {
+                fake.write_u64(0x08, CAP);
+                fake.write_u64(0x10, ECAP);
+            }
+            fake
+        }
+
+        fn raw_write(&self, offset: usize, bytes: &[u8]) {
+            self.regs.lock().unwrap()[offset..offset + bytes.len()].copy_from_slice(bytes);
+        }
+
+        /// Pretend the hardware blocked a DMA write from `device`.
+        fn inject_fault(&self, index: usize, device: PciAddress, iova: u64, reason: u8) {
+            let offset = 0x200 + index * 16;
+            let high = (1u64 << 63) | ((reason as u64) << 32) | device.requester_id() as u64;
+            self.raw_write(offset, &iova.to_le_bytes());
+            self.raw_write(offset + 8, &high.to_le_bytes());
+            self.raw_write(0x34, &2u32.to_le_bytes());
+        }
+    }
+
+    unsafe impl MmioAccess for FakeRegisters {
+        unsafe fn read_u32(&self, offset: usize) -> u32 {
+            let regs = self.regs.lock().unwrap();
+            u32::from_le_bytes(regs[offset..offset + 4].try_into().unwrap())
+        }
+
+        unsafe fn write_u32(&self, offset: usize, val: u32) {
+            match offset {
+                // GCMD: reflect persistent enables into GSTS.
+                0x18 => self.raw_write(0x1C, &val.to_le_bytes()),
+                // FSTS is write-1-to-clear.
+                0x34 => {
+                    let current = unsafe { self.read_u32(0x34) };
+                    self.raw_write(0x34, &(current & !val).to_le_bytes());
+                }
+                _ => self.raw_write(offset, &val.to_le_bytes()),
+            }
+        }
+
+        unsafe fn read_u64(&self, offset: usize) -> u64 {
+            let regs = self.regs.lock().unwrap();
+            u64::from_le_bytes(regs[offset..offset + 8].try_into().unwrap())
+        }
+
+        unsafe fn write_u64(&self, offset: usize, val: u64) {
+            match offset {
+                // CCMD / IOTLB_REG: command completes immediately.
+                0x28 | 0x108 => self.raw_write(offset, &(val & !(1 << 63)).to_le_bytes()),
+                // Fault record upper half: F is write-1-to-clear.
+                o if o >= 0x200 && o % 16 == 8 => {
+                    let current = unsafe { self.read_u64(o) };
+                    self.raw_write(o, &(current & !val).to_le_bytes());
+                }
+                _ => self.raw_write(offset, &val.to_le_bytes()),
+            }
+        }
+    }
+
+    fn drhd() -> DrhdEntry {
+        DrhdEntry {
+            register_base: 0xFED9_0000,
+            segment: 0,
+            include_pci_all: true,
+            scopes: Vec::new(),
+        }
+    }
+
+    fn device(dev: u8) -> PciAddress {
+        PciAddress {
+            segment: 0,
+            bus: 0,
+            device: dev,
+            function: 0,
+        }
+    }
+
+    fn iommu(frames: &TestFrames) -> VtdIommu<TestFrames, FakeRegisters> {
+        let unit = VtdUnit::new(frames.clone(), FakeRegisters::new(), drhd()).unwrap();
+        VtdIommu::new(frames.clone(), alloc::vec![unit], Vec::new())
+    }
+
+    #[test]
+    fn passthrough_enables_translation() {
+        let frames = TestFrames::default();
+        let iommu = iommu(&frames);
+        let id = iommu
+            .init_passthrough_domain(1 << 32, &[device(1), device(2)])
+            .unwrap();
+        assert_eq!(id, PASSTHROUGH_DOMAIN);
+
+        let state = iommu.state.lock();
+        let unit = &state.units[0];
+        assert!(unit.registers.translation_enabled());
+        let (low, high) = unit.root.context_entry(0, 1, 0).unwrap();
+        assert_eq!((low >> 2) & 0b11, 0b10, "pass-through translation type");
+        assert_eq!((high >> 8) & 0xffff, PASSTHROUGH_DOMAIN as u64);
+    }
+
+    #[test]
+    fn device_moves_between_domains() {
+        let frames = TestFrames::default();
+        let iommu = iommu(&frames);
+        iommu
+            .init_passthrough_domain(1 << 32, &[device(3)])
+            .unwrap();
+
+        let domain = iommu.create_domain().unwrap();
+        assert_ne!(domain, PASSTHROUGH_DOMAIN);
+        iommu.assign_device(domain, device(3)).unwrap();
+        iommu
+            .map(domain, 0x10_0000, 0x5000, 0x1000, IommuFlags::READ_WRITE)
+            .unwrap();
+
+        {
+            let state = iommu.state.lock();
+            let table = state.domains[&domain].table.as_ref().unwrap();
+            let (low, high) = state.units[0].root.context_entry(0, 3, 0).unwrap();
+            assert_eq!(low & !0xfff, table.root_phys());
+            assert_eq!((high >> 8) & 0xffff, domain as u64);
+            assert_eq!(
+                table.translate(0x10_0000),
+                Some((0x5000, IommuFlags::READ_WRITE))
+            );
+            assert_eq!(state.domains[&PASSTHROUGH_DOMAIN].devices, alloc::vec![]);
+        }
+
+        // Moving back and destroying the domain frees its tables.
+        iommu.assign_device(PASSTHROUGH_DOMAIN, device(3)).unwrap();
+        let before = frames.live();
+        iommu.destroy_domain(domain);
+        assert!(frames.live() < before);
+
+        // The ID is recycled.
+        assert_eq!(iommu.create_domain().unwrap(), domain);
+    }
+
+    #[test]
+    fn map_to_unknown_domain_fails() {
+        let frames = TestFrames::default();
+        let iommu = iommu(&frames);
+        assert_eq!(
+            iommu.map(42, 0, 0, 0x1000, IommuFlags::READ),
+            Err(IommuError::InvalidDomain)
+        );
+    }
+
+    #[test]
+    fn rmrrs_are_identity_mapped_in_new_domains() {
+        let frames = TestFrames::default();
+        let unit = VtdUnit::new(frames.clone(), FakeRegisters::new(), drhd()).unwrap();
+        let rmrr = RmrrEntry {
+            segment: 0,
+            base: 0xA_0000,
+            limit: 0xA_1FFF,
+            scopes: Vec::new(),
+        };
+        let iommu = VtdIommu::new(frames.clone(), alloc::vec![unit], alloc::vec![rmrr]);
+        let domain = iommu.create_domain().unwrap();
+
+        let state = iommu.state.lock();
+        let table = state.domains[&domain].table.as_ref().unwrap();
+        assert_eq!(
+            table.translate(0xA_1000),
+            Some((0xA_1000, IommuFlags::READ_WRITE))
+        );
+        assert_eq!(table.translate(0xA_2000), None);
+    }
+
+    #[test]
+    fn faults_are_decoded_and_cleared() {
+        let frames = TestFrames::default();
+        let iommu = iommu(&frames);
+        {
+            let state = iommu.state.lock();
+            let regs = &state.units[0].registers;
+            // Reach the fake through the registers' MMIO handle.
+            regs.mmio().inject_fault(1, device(4), 0xdead_b000, 5);
+        }
+
+        let faults = iommu.take_faults();
+        assert_eq!(
+            faults,
+            alloc::vec![IommuFault {
+                device: device(4),
+                iova: 0xdead_b000,
+                kind: FaultKind::Write,
+                reason: 5,
+            }]
+        );
+        assert!(iommu.take_faults().is_empty());
+    }
+}
@@ -0,0 +1,358 @@
+//! Second-level page tables (VT-d 9.8).
+//!
+//! Same geometry as x86-64 paging — four levels of 512 entries, 9 index
+//! bits per level, 4 KB leaves — but different flag bits: bit 0 grants
+//! read, bit 1 grants write, bit 7 marks a 2 MB superpage at level 2, and
+//! there is no execute-disable. Intermediate tables are allocated on demand
+//! and freed again once an unmap leaves them empty.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use super::flush_cache_line;
+use crate::PAGE_SIZE;
+use crate::hal::FrameAllocator;
+use crate::types::{IommuError, IommuFlags};
+
+const READ: u64 = 1 << 0;
+const WRITE: u64 = 1 << 1;
+const SUPERPAGE: u64 = 1 << 7;
+const ADDRESS_MASK: u64 = 0x000F_FFFF_FFFF_F000;
+
+const ENTRIES: usize = 512;
+const LARGE_PAGE_SIZE: u64 = 2 * 1024 * 1024;
+/// Highest IOVA (exclusive) a 4-level table can translate.
+pub const MAX_IOVA: u64 = 1 << 48;
+
+/// A table frame: physical address and CPU pointer to its 512 entries.
+#[derive(Clone, Copy)]
+struct Node {
+    phys: u64,
+    virt: *mut u64,
+}
+
+impl Node {
+    fn entry(&self, index: usize) -> u64 {
+        unsafe { core::ptr::read_volatile(self.virt.add(index)) }
+    }
+
+    fn is_empty(&self) -> bool {
+        (0..ENTRIES).all(|i| self.entry(i) == 0)
+    }
+}
+
+/// The four-level table of one domain.
+pub struct SlptRoot<F: FrameAllocator> {
+    frames: F,
+    root: Node,
+    /// Every intermediate table by physical address, so entries (which
+    /// hold physical addresses) can be followed with a CPU pointer.
+    nodes: BTreeMap<u64, Node>,
+    coherent: bool,
+    large_pages: bool,
+}
+
+// Safety: the raw pointers are owned frames only this table touches, and
+// every mutation goes through `&mut self`.
+unsafe impl<F: FrameAllocator> Send for SlptRoot<F> {}
+unsafe impl<F: FrameAllocator> Sync for SlptRoot<F> {}
+
+impl<F: FrameAllocator> SlptRoot<F> {
+    /// Allocate an empty table. `coherent` is the unit's ECAP.C bit;
+    /// `large_pages` allows 2 MB leaves where alignment permits.
+    pub fn new(frames: F, coherent: bool, large_pages: bool) -> Result<Self, IommuError> {
+        let root = alloc_node(&frames)?;
+        Ok(Self {
+            frames,
+            root,
+            nodes: BTreeMap::new(),
+            coherent,
+            large_pages,
+        })
+    }
+
+    /// Physical address of the level-4 table, for context entries.
+    pub fn root_phys(&self) -> u64 {
+        self.root.phys
+    }
+
+    /// Number of intermediate tables currently allocated.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Map `[iova, iova + size)` to `[phys, phys + size)`.
+    ///
+    /// On error, any pages this call already mapped are unmapped again.
+    pub fn map(
+        &mut self,
+        iova: u64,
+        phys: u64,
+        size: usize,
+        flags: IommuFlags,
+    ) -> Result<(), IommuError> {
+        let size = size as u64;
+        if !iova.is_multiple_of(PAGE_SIZE)
+            || !phys.is_multiple_of(PAGE_SIZE)
+            || !size.is_multiple_of(PAGE_SIZE)
+        {
+            return Err(IommuError::InvalidAddress);
+        }
+        if iova.checked_add(size).is_none_or(|end| end > MAX_IOVA) {
+            return Err(IommuError::InvalidAddress);
+        }
+
+        let mut leaf = 0;
+        if flags.readable() {
+            leaf |= READ;
+        }
+        if flags.writable() {
+            leaf |= WRITE;
+        }
+
+        let mut offset = 0;
+        while offset < size {
+            let large = self.large_pages
+                && (iova + offset).is_multiple_of(LARGE_PAGE_SIZE)
+                && (phys + offset).is_multiple_of(LARGE_PAGE_SIZE)
+                && size - offset >= LARGE_PAGE_SIZE;
+            let result = if large {
+                self.map_one(iova + offset, (phys + offset) | leaf | SUPERPAGE, 2)
+            } else {
+                self.map_one(iova + offset, (phys + offset) | leaf, 1)
+            };
+            if let Err(e) = result {
+                self.unmap(iova, offset as usize);
+                return Err(e);
+            }
+            offset += if large { LARGE_PAGE_SIZE } else { PAGE_SIZE };
+        }
+        Ok(())
+    }
+
+    /// Install one leaf entry at `level` (1 = 4 KB, 2 = 2 MB).
+    fn map_one(&mut self, iova: u64, entry: u64, level: u32) -> Result<(), IommuError> {
+        let mut node = self.root;
+        for current in (level + 1..=4).rev() {
+            let index = table_index(iova, current);
+            let existing = node.entry(index);
+            node = if existing == 0 {
+                let child = alloc_node(&self.frames)?;
+                self.nodes.insert(child.phys, child);
+                // Non-leaf entries must grant the union of their children's
+                // permissions; grant both and let leaves restrict.
+                self.write(node, index, child.phys | READ | WRITE);
+                child
+            } else if existing & SUPERPAGE != 0 {
+                return Err(IommuError::AlreadyMapped);
+            } else {
+                self.nodes[&(existing & ADDRESS_MASK)]
+            };
+        }
+
+        let index = table_index(iova, level);
+        if node.entry(index) != 0 {
+            return Err(IommuError::AlreadyMapped);
+        }
+        self.write(node, index, entry);
+        Ok(())
+    }
+
+    /// Unmap `[iova, iova + size)`. Unmapped holes are skipped; a superpage
+    /// that overlaps the range is removed whole.
+    pub fn unmap(&mut self, iova: u64, size: usize) {
+        let end = iova.saturating_add(size as u64).min(MAX_IOVA);
+        let mut addr = iova & !(PAGE_SIZE - 1);
+        while addr < end {
+            addr += self.unmap_one(addr);
+        }
+    }
+
+    /// Clear the leaf covering `iova`, free any tables left empty, and
+    /// return how far to advance.
+    fn unmap_one(&mut self, iova: u64) -> u64 {
+        let mut path: Vec<(Node, usize)> = Vec::with_capacity(4);
+        let mut node = self.root;
+        for level in (1..=4).rev() {
+            let index = table_index(iova, level);
+            let entry = node.entry(index);
+            let span = level_span(level);
+            if entry == 0 {
+                // Skip to the end of this empty region.
+                return span - (iova & (span - 1));
+            }
+            if level == 1 || entry & SUPERPAGE != 0 {
+                self.write(node, index, 0);
+                self.reclaim(node, path);
+                return span - (iova & (span - 1));
+            }
+            path.push((node, index));
+            node = self.nodes[&(entry & ADDRESS_MASK)];
+        }
+        PAGE_SIZE
+    }
+
+    /// Free `node` (and then its ancestors) while they are empty.
+    fn reclaim(&mut self, mut node: Node, mut path: Vec<(Node, usize)>) {
+        while let Some((parent, index)) = path.pop() {
+            if !node.is_empty() {
+                return;
+            }
+            self.write(parent, index, 0);
+            self.nodes.remove(&node.phys);
+            unsafe { self.frames.dealloc_frame(node.phys) };
+            node = parent;
+        }
+    }
+
+    /// Walk the table for `iova`: the physical address it maps to and the
+    /// permissions granted, or `None` if unmapped.
+    pub fn translate(&self, iova: u64) -> Option<(u64, IommuFlags)> {
+        let mut node = self.root;
+        for level in (1..=4).rev() {
+            let entry = node.entry(table_index(iova, level));
+            if entry == 0 {
+                return None;
+            }
+            if level == 1 || entry & SUPERPAGE != 0 {
+                let span = level_span(level);
+                let phys = (entry & ADDRESS_MASK & !(span - 1)) + (iova & (span - 1));
+                let flags = match (entry & READ != 0, entry & WRITE != 0) {
+                    (true, true) => IommuFlags::READ_WRITE,
+                    (false, true) => IommuFlags::WRITE,
+                    _ => IommuFlags::READ,
+                };
+                return Some((phys, flags));
+            }
+            node = *self.nodes.get(&(entry & ADDRESS_MASK))?;
+        }
+        None
+    }
+
+    fn write(&self, node: Node, index: usize, value: u64) {
+        unsafe {
+            let ptr = node.virt.add(index);
+            core::ptr::write_volatile(ptr, value);
+            if !self.coherent {
+                flush_cache_line(ptr as *const u8);
+            }
+        }
+    }
+}
+
+impl<F: FrameAllocator> Drop for SlptRoot<F> {
+    fn drop(&mut self) {
+        for node in self.nodes.values() {
+            unsafe { self.frames.dealloc_frame(node.phys) };
+        }
+        unsafe { self.frames.dealloc_frame(self.root.phys) };
+    }
+}
+
+fn table_index(iova: u64, level: u32) -> usize {
+    ((iova >> (12 + 9 * (level - 1))) & 0x1ff) as usize
+}
+
+/// Bytes of IOVA space covered by one entry at `level`.
+fn level_span(level: u32) -> u64 {
+    PAGE_SIZE << (9 * (level - 1))
+}
+
+fn alloc_node<F: FrameAllocator>(frames: &F) -> Result<Node, IommuError> {
+    let (phys, virt) = frames.alloc_frame().ok_or(IommuError::OutOfMemory)?;
+    unsafe { core::ptr::write_bytes(virt, 0, 4096) };
+    Ok(Node {
+        phys,
+        virt: virt as *mut u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vtd::test_support::TestFrames;
+
+    #[test]
+    fn map_sets_leaf_entries_with_flags() {
+        let frames = TestFrames::default();
+        let mut table = SlptRoot::new(frames.clone(), true, false).unwrap();
+        table
+            .map(0x4000_0000, 0x8000, 0x2000, IommuFlags::READ)
+            .unwrap();
+
+        assert_eq!(
+            table.translate(0x4000_0000),
+            Some((0x8000, IommuFlags::READ))
+        );
+        assert_eq!(
+            table.translate(0x4000_1234),
+            Some((0x9234, IommuFlags::READ))
+        );
+        assert_eq!(table.translate(0x4000_2000), None);
+        // Levels 3, 2 and 1 were allocated under the root.
+        assert_eq!(table.node_count(), 3);
+    }
+
+    #[test]
+    fn unmap_clears_entries_and_frees_tables() {
+        let frames = TestFrames::default();
+        let mut table = SlptRoot::new(frames.clone(), true, false).unwrap();
+        table
+            .map(0x1000, 0x20_0000, 0x3000, IommuFlags::READ_WRITE)
+            .unwrap();
+        table.unmap(0x2000, 0x1000);
+        assert_eq!(table.translate(0x2000), None);
+        assert!(table.translate(0x1000).is_some());
+        assert_eq!(table.node_count(), 3);
+
+        table.unmap(0x1000, 0x3000);
+        assert_eq!(table.node_count(), 0);
+        assert_eq!(frames.live(), 1);
+    }
+
+    #[test]
+    fn overlapping_map_is_rejected_and_rolled_back() {
+        let frames = TestFrames::default();
+        let mut table = SlptRoot::new(frames.clone(), true, false).unwrap();
+        table.map(0x3000, 0x3000, 0x1000, IommuFlags::READ).unwrap();
+        assert_eq!(
+            table.map(0x1000, 0x1000, 0x3000, IommuFlags::READ),
+            Err(IommuError::AlreadyMapped)
+        );
+        // The two pages mapped before the collision were undone.
+        assert_eq!(table.translate(0x1000), None);
+        assert_eq!(table.translate(0x2000), None);
+        assert_eq!(table.translate(0x3000), Some((0x3000, IommuFlags::READ)));
+    }
+
+    #[test]
+    fn superpages_used_when_aligned() {
+        let frames = TestFrames::default();
+        let mut table = SlptRoot::new(frames.clone(), true, true).unwrap();
+        table
+            .map(0, 0, 4 * 1024 * 1024 + 0x1000, IommuFlags::READ_WRITE)
+            .unwrap();
+        // Two 2 MB leaves at level 2 plus one 4 KB page: levels 3, 2, 1.
+        assert_eq!(table.node_count(), 3);
+        assert_eq!(
+            table.translate(0x30_0123),
+            Some((0x30_0123, IommuFlags::READ_WRITE))
+        );
+        table.unmap(0, 4 * 1024 * 1024 + 0x1000);
+        assert_eq!(table.node_count(), 0);
+    }
+
+    #[test]
+    fn rejects_unaligned_and_out_of_range() {
+        let frames = TestFrames::default();
+        let mut table = SlptRoot::new(frames, true, false).unwrap();
+        assert_eq!(
+            table.map(0x1001, 0, 0x1000, IommuFlags::READ),
+            Err(IommuError::InvalidAddress)
+        );
+        assert_eq!(
+            table.map(MAX_IOVA - 0x1000, 0, 0x2000, IommuFlags::READ),
+            Err(IommuError::InvalidAddress)
+        );
+    }
+}
@@ -0,0 +1,279 @@
+//! Register interface of one VT-d remapping unit (VT-d specification,
+//! chapter 11).
+
+use crate::hal::MmioAccess;
+use crate::types::IommuError;
+
+const REG_VER: usize = 0x000;
+const REG_CAP: usize = 0x008;
+const REG_ECAP: usize = 0x010;
+const REG_GCMD: usize = 0x018;
+const REG_GSTS: usize = 0x01C;
+const REG_RTADDR: usize = 0x020;
+const REG_CCMD: usize = 0x028;
+const REG_FSTS: usize = 0x034;
+const REG_FECTL: usize = 0x038;
+const REG_FEDATA: usize = 0x03C;
+const REG_FEADDR: usize = 0x040;
+const REG_FEUADDR: usize = 0x044;
+
+/// GCMD/GSTS: translation enable.
+const GCMD_TE: u32 = 1 << 31;
+/// GCMD/GSTS: set root table pointer.
+const GCMD_SRTP: u32 = 1 << 30;
+/// GSTS bits that are one-shot commands rather than persistent enables;
+/// they must be masked out when writing GCMD back (VT-d 11.4.4).
+const GSTS_ONE_SHOT: u32 = 0x96FF_FFFF;
+
+/// CCMD: invalidate context cache (set by software, cleared by hardware).
+const CCMD_ICC: u64 = 1 << 63;
+/// CCMD: global invalidation request granularity.
+const CCMD_CIRG_GLOBAL: u64 = 1 << 61;
+
+/// IOTLB_REG: invalidate IOTLB.
+const IOTLB_IVT: u64 = 1 << 63;
+/// IOTLB_REG: global invalidation request granularity.
+const IOTLB_IIRG_GLOBAL: u64 = 1 << 60;
+/// IOTLB_REG: drain reads / drain writes before completing.
+const IOTLB_DRAIN: u64 = (1 << 49) | (1 << 48);
+
+/// FSTS: primary fault overflow.
+pub const FSTS_PFO: u32 = 1 << 0;
+/// FSTS: primary pending fault.
+pub const FSTS_PPF: u32 = 1 << 1;
+/// FECTL: interrupt mask.
+const FECTL_IM: u32 = 1 << 31;
+
+/// Fault recording register: fault bit (in the upper 64 bits).
+const FRCD_F: u64 = 1 << 63;
+
+/// How many times to poll a status bit before giving up. Emulated and real
+/// hardware complete these commands in a handful of reads.
+const POLL_LIMIT: usize = 1_000_000;
+
+/// Decoded capability register.
+#[derive(Debug, Clone, Copy)]
+pub struct Capability(pub u64);
+
+impl Capability {
+    /// Number of hardware domain IDs supported.
+    pub fn domain_count(self) -> u32 {
+        1 << (4 + 2 * (self.0 & 0x7) as u32)
+    }
+
+    /// Whether the unit supports 4-level (48-bit) second-level tables.
+    pub fn supports_4_level(self) -> bool {
+        (self.0 >> 8) & 0b00100 != 0
+    }
+
+    /// Whether the unit supports 2 MiB second-level superpages.
+    pub fn supports_2m_pages(self) -> bool {
+        (self.0 >> 34) & 0x1 != 0
+    }
+
+    /// Caching mode: the unit may cache not-present entries, so mapping
+    /// changes need an IOTLB flush too (emulated IOMMUs set this).
+    pub fn caching_mode(self) -> bool {
+        (self.0 >> 7) & 0x1 != 0
+    }
+
+    /// Byte offset of the first fault recording register.
+    pub fn fault_recording_offset(self) -> usize {
+        (((self.0 >> 24) & 0x3ff) * 16) as usize
+    }
+
+    /// Number of fault recording registers.
+    pub fn fault_recording_count(self) -> usize {
+        (((self.0 >> 40) & 0xff) + 1) as usize
+    }
+}
+
+/// Decoded extended capability register.
+#[derive(Debug, Clone, Copy)]
+pub struct ExtendedCapability(pub u64);
+
+impl ExtendedCapability {
+    /// Page-walk coherency: if clear, software must flush CPU caches after
+    /// writing paging structures.
+    pub fn coherent(self) -> bool {
+        self.0 & 0x1 != 0
+    }
+
+    /// Pass-through translation type support in context entries.
+    pub fn pass_through(self) -> bool {
+        (self.0 >> 6) & 0x1 != 0
+    }
+
+    /// Byte offset of the IOTLB registers.
+    pub fn iotlb_offset(self) -> usize {
+        (((self.0 >> 8) & 0x3ff) * 16) as usize
+    }
+}
+
+/// One raw fault record read from the hardware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FaultRecord {
+    /// Faulting page address.
+    pub address: u64,
+    /// PCI requester ID of the faulting device.
+    pub source_id: u16,
+    /// Fault reason code.
+    pub reason: u8,
+    /// True for a read request, false for a write.
+    pub read: bool,
+}
+
+/// Typed access to one unit's registers.
+pub struct VtdRegisters<M: MmioAccess> {
+    mmio: M,
+    cap: Capability,
+    ecap: ExtendedCapability,
+}
+
+impl<M: MmioAccess> VtdRegisters<M> {
+    /// Wrap a unit's register region, reading CAP/ECAP up front since the
+    /// IOTLB and fault-recording register offsets are encoded in them.
+    pub fn new(mmio: M) -> Self {
+        let (cap, ecap) = unsafe { (mmio.read_u64(REG_CAP), mmio.read_u64(REG_ECAP)) };
+        Self {
+            mmio,
+            cap: Capability(cap),
+            ecap: ExtendedCapability(ecap),
+        }
+    }
+
+    /// `(major, minor)` architecture version.
+    pub fn version(&self) -> (u8, u8) {
+        let ver = unsafe { self.mmio.read_u32(REG_VER) };
+        (((ver >> 4) & 0xf) as u8, (ver & 0xf) as u8)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn mmio(&self) -> &M {
+        &self.mmio
+    }
+
+    pub fn capability(&self) -> Capability {
+        self.cap
+    }
+
+    pub fn extended_capability(&self) -> ExtendedCapability {
+        self.ecap
+    }
+
+    fn status(&self) -> u32 {
+        unsafe { self.mmio.read_u32(REG_GSTS) }
+    }
+
+    /// Issue a one-shot or enable command and wait for GSTS to reflect it.
+    fn global_command(&self, bit: u32, set: bool) -> Result<(), IommuError> {
+        let persistent = self.status() & GSTS_ONE_SHOT;
+        let command = if set {
+            persistent | bit
+        } else {
+            persistent & !bit
+        };
+        unsafe { self.mmio.write_u32(REG_GCMD, command) };
+        self.poll(|| (self.status() & bit != 0) == set)
+    }
+
+    fn poll(&self, mut done: impl FnMut() -> bool) -> Result<(), IommuError> {
+        for _ in 0..POLL_LIMIT {
+            if done() {
+                return Ok(());
+            }
+            core::hint::spin_loop();
+        }
+        Err(IommuError::Timeout)
+    }
+
+    /// Point the unit at a root table (legacy, non-scalable mode).
+    pub fn set_root_table(&self, phys: u64) -> Result<(), IommuError> {
+        unsafe { self.mmio.write_u64(REG_RTADDR, phys & !0xfff) };
+        self.global_command(GCMD_SRTP, true)
+    }
+
+    pub fn translation_enabled(&self) -> bool {
+        self.status() & GCMD_TE != 0
+    }
+
+    pub fn enable_translation(&self) -> Result<(), IommuError> {
+        self.global_command(GCMD_TE, true)
+    }
+
+    pub fn disable_translation(&self) -> Result<(), IommuError> {
+        self.global_command(GCMD_TE, false)
+    }
+
+    /// Globally invalidate the context-entry cache.
+    pub fn invalidate_context_cache(&self) -> Result<(), IommuError> {
+        unsafe { self.mmio.write_u64(REG_CCMD, CCMD_ICC | CCMD_CIRG_GLOBAL) };
+        self.poll(|| unsafe { self.mmio.read_u64(REG_CCMD) } & CCMD_ICC == 0)
+    }
+
+    /// Globally invalidate the IOTLB, draining in-flight DMA first.
+    pub fn invalidate_iotlb_global(&self) -> Result<(), IommuError> {
+        // IOTLB_REG is the second 64-bit register of the IOTLB block.
+        let reg = self.ecap.iotlb_offset() + 8;
+        unsafe {
+            self.mmio
+                .write_u64(reg, IOTLB_IVT | IOTLB_IIRG_GLOBAL | IOTLB_DRAIN)
+        };
+        self.poll(|| unsafe { self.mmio.read_u64(reg) } & IOTLB_IVT == 0)
+    }
+
+    /// Mask the fault-event interrupt; faults are collected by polling
+    /// [`take_fault_records`](Self::take_fault_records).
+    pub fn mask_fault_interrupt(&self) {
+        unsafe { self.mmio.write_u32(REG_FECTL, FECTL_IM) };
+    }
+
+    /// Route the fault-event interrupt to an MSI `address`/`data` pair and
+    /// unmask it. Pending faults must still be collected with
+    /// [`take_fault_records`](Self::take_fault_records).
+    pub fn enable_fault_interrupt(&self, address: u64, data: u32) {
+        unsafe {
+            self.mmio.write_u32(REG_FEDATA, data);
+            self.mmio.write_u32(REG_FEADDR, address as u32);
+            self.mmio.write_u32(REG_FEUADDR, (address >> 32) as u32);
+            self.mmio.write_u32(REG_FECTL, 0);
+        }
+    }
+
+    /// Fault status register.
+    pub fn fault_status(&self) -> u32 {
+        unsafe { self.mmio.read_u32(REG_FSTS) }
+    }
+
+    /// Read and clear every pending fault record, passing each to `sink`.
+    /// Returns whether further faults were lost to overflow.
+    pub fn take_fault_records(&self, mut sink: impl FnMut(FaultRecord)) -> bool {
+        let status = self.fault_status();
+        if status & (FSTS_PPF | FSTS_PFO) == 0 {
+            return false;
+        }
+
+        let base = self.cap.fault_recording_offset();
+        for index in 0..self.cap.fault_recording_count() {
+            let offset = base + index * 16;
+            let high = unsafe { self.mmio.read_u64(offset + 8) };
+            if high & FRCD_F == 0 {
+                continue;
+            }
+            let low = unsafe { self.mmio.read_u64(offset) };
+            sink(FaultRecord {
+                address: low & !0xfff,
+                source_id: (high & 0xffff) as u16,
+                reason: ((high >> 32) & 0xff) as u8,
+                read: (high >> 62) & 0x1 != 0,
+            });
+            // F is write-1-to-clear.
+            unsafe { self.mmio.write_u64(offset + 8, FRCD_F) };
+        }
+
+        let overflowed = status & FSTS_PFO != 0;
+        // Clear PFO (write-1-to-clear); PPF clears once all F bits are clear.
+        unsafe { self.mmio.write_u32(REG_FSTS, FSTS_PFO) };
+        overflowed
+    }
+}
@@ -0,0 +1,119 @@
+//! Compose sequences: two characters that combine into one.
+//!
+//! The same table serves the Compose key (`Compose`, `'`, `e` types `é`)
+//! and dead keys (dead `´`, then `e`). Dead keys carry the spacing accent
+//! itself, so each accent is listed under both spellings.
+
+/// Accents as typed after the Compose key, and as carried by dead keys.
+const ACCENTS: &[(char, char)] = &[
+    ('\'', '´'),
+    ('`', '`'),
+    ('^', '^'),
+    ('"', '¨'),
+    ('~', '~'),
+    (',', '¸'),
+    ('o', '˚'),
+];
+
+/// Accented letters, keyed by the ASCII spelling of the accent.
+const ACCENTED: &[(char, char, char)] = &[
+    ('\'', 'a', 'á'),
+    ('\'', 'e', 'é'),
+    ('\'', 'i', 'í'),
+    ('\'', 'o', 'ó'),
+    ('\'', 'u', 'ú'),
+    ('\'', 'y', 'ý'),
+    ('\'', 'A', 'Á'),
+    ('\'', 'E', 'É'),
+    ('\'', 'I', 'Í'),
+    ('\'', 'O', 'Ó'),
+    ('\'', 'U', 'Ú'),
+    ('\'', 'Y', 'Ý'),
+    ('`', 'a', 'à'),
+    ('`', 'e', 'è'),
+    ('`', 'i', 'ì'),
+    ('`', 'o', 'ò'),
+    ('`', 'u', 'ù'),
+    ('`', 'A', 'À'),
+    ('`', 'E', 'È'),
+    ('`', 'I', 'Ì'),
+    ('`', 'O', 'Ò'),
+    ('`', 'U', 'Ù'),
+    ('^', 'a', 'â'),
+    ('^', 'e', 'ê'),
+    ('^', 'i', 'î'),
+    ('^', 'o', 'ô'),
+    ('^', 'u', 'û'),
+    ('^', 'A', 'Â'),
+    ('^', 'E', 'Ê'),
+    ('^', 'I', 'Î'),
+    ('^', 'O', 'Ô'),
+    ('^', 'U', 'Û'),
+    ('"', 'a', 'ä'),
+    ('"', 'e', 'ë'),
+    ('"', 'i', 'ï'),
+    ('"', 'o', 'ö'),
+    ('"', 'u', 'ü'),
+    ('"', 'y', 'ÿ'),
+    ('"', 'A', 'Ä'),
+    ('"', 'E', 'Ë'),
+    ('"', 'I', 'Ï'),
+    ('"', 'O', 'Ö'),
+    ('"', 'U', 'Ü'),
+    ('~', 'a', 'ã'),
+    ('~', 'n', 'ñ'),
+    ('~', 'o', 'õ'),
+    ('~', 'A', 'Ã'),
+    ('~', 'N', 'Ñ'),
+    ('~', 'O', 'Õ'),
+    (',', 'c', 'ç'),
+    (',', 'C', 'Ç'),
+    ('o', 'a', 'å'),
+    ('o', 'A', 'Å'),
+];
+
+/// Sequences that only make sense after the Compose key.
+const SYMBOLS: &[(char, char, char)] = &[
+    ('s', 's', 'ß'),
+    ('a', 'e', 'æ'),
+    ('A', 'E', 'Æ'),
+    ('o', '/', 'ø'),
+    ('O', '/', 'Ø'),
+    ('o', 'e', 'œ'),
+    ('O', 'E', 'Œ'),
+    ('e', '=', '€'),
+    ('L', '-', '£'),
+    ('Y', '=', '¥'),
+    ('c', '|', '¢'),
+    ('o', 'c', '©'),
+    ('o', 'r', '®'),
+    ('<', '<', '«'),
+    ('>', '>', '»'),
+    ('?', '?', '¿'),
+    ('!', '!', '¡'),
+    ('1', '2', '½'),
+    ('1', '4', '¼'),
+    ('3', '4', '¾'),
+    ('+', '-', '±'),
+    ('x', 'x', '×'),
+    (':', '-', '÷'),
+    ('o', 'o', '°'),
+    ('-', '-', '—'),
+];
+
+/// The built-in result of composing `first` and `second`, in either order.
+pub(crate) fn lookup(first: char, second: char) -> Option<char> {
+    lookup_ordered(first, second).or_else(|| lookup_ordered(second, first))
+}
+
+fn lookup_ordered(first: char, second: char) -> Option<char> {
+    let accent = ACCENTS
+        .iter()
+        .find(|&&(_, spacing)| spacing == first)
+        .map_or(first, |&(ascii, _)| ascii);
+    ACCENTED
+        .iter()
+        .chain(SYMBOLS)
+        .find(|&&(a, b, _)| a == accent && b == second)
+        .map(|&(_, _, result)| result)
+}
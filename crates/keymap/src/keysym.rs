@@ -0,0 +1,115 @@
+//! Key symbols: what a key means once the layout and modifiers are applied.
+
+use crate::codes::*;
+
+/// The meaning of a key press.
+///
+/// Character keys come from the [`Keymap`](crate::Keymap); everything else
+/// is the same in every layout (see [`fixed`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Keysym {
+    /// The key does nothing at this level.
+    NoSymbol,
+    /// A key that types a character.
+    Char(char),
+    /// A dead key: the accent (in its spacing form, e.g. `´` or `¨`) is
+    /// combined with the next character typed.
+    Dead(char),
+    Enter,
+    Tab,
+    Backspace,
+    Escape,
+    Delete,
+    Insert,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Up,
+    Down,
+    Left,
+    Right,
+    /// A function key, F1 to F12.
+    F(u8),
+    Shift,
+    Control,
+    Alt,
+    /// Right Alt on layouts with a third level.
+    AltGr,
+    Super,
+    CapsLock,
+    NumLock,
+    ScrollLock,
+    /// Starts a two-character compose sequence.
+    Compose,
+}
+
+impl Keysym {
+    /// Whether this is a modifier or lock key.
+    pub fn is_modifier(self) -> bool {
+        matches!(
+            self,
+            Keysym::Shift
+                | Keysym::Control
+                | Keysym::Alt
+                | Keysym::AltGr
+                | Keysym::Super
+                | Keysym::CapsLock
+                | Keysym::NumLock
+                | Keysym::ScrollLock
+        )
+    }
+}
+
+/// The symbol for a key that isn't part of any layout, or `None` for
+/// character keys. The keypad types digits with Num Lock on and navigates
+/// with it off.
+pub fn fixed(code: u16, num_lock: bool) -> Option<Keysym> {
+    let keypad = |digit: char, nav: Keysym| {
+        if num_lock { Keysym::Char(digit) } else { nav }
+    };
+    Some(match code {
+        KEY_ESC => Keysym::Escape,
+        KEY_BACKSPACE => Keysym::Backspace,
+        KEY_TAB => Keysym::Tab,
+        KEY_ENTER | KEY_KPENTER => Keysym::Enter,
+        KEY_LEFTSHIFT | KEY_RIGHTSHIFT => Keysym::Shift,
+        KEY_LEFTCTRL | KEY_RIGHTCTRL => Keysym::Control,
+        KEY_LEFTALT => Keysym::Alt,
+        KEY_RIGHTALT => Keysym::AltGr,
+        KEY_LEFTMETA | KEY_RIGHTMETA => Keysym::Super,
+        KEY_CAPSLOCK => Keysym::CapsLock,
+        KEY_NUMLOCK => Keysym::NumLock,
+        KEY_SCROLLLOCK => Keysym::ScrollLock,
+        KEY_COMPOSE => Keysym::Compose,
+        KEY_F1..=KEY_F10 => Keysym::F((code - KEY_F1 + 1) as u8),
+        KEY_F11 => Keysym::F(11),
+        KEY_F12 => Keysym::F(12),
+        KEY_HOME => Keysym::Home,
+        KEY_END => Keysym::End,
+        KEY_PAGEUP => Keysym::PageUp,
+        KEY_PAGEDOWN => Keysym::PageDown,
+        KEY_UP => Keysym::Up,
+        KEY_DOWN => Keysym::Down,
+        KEY_LEFT => Keysym::Left,
+        KEY_RIGHT => Keysym::Right,
+        KEY_INSERT => Keysym::Insert,
+        KEY_DELETE => Keysym::Delete,
+        KEY_KPSLASH => Keysym::Char('/'),
+        KEY_KPASTERISK => Keysym::Char('*'),
+        KEY_KPMINUS => Keysym::Char('-'),
+        KEY_KPPLUS => Keysym::Char('+'),
+        KEY_KP7 => keypad('7', Keysym::Home),
+        KEY_KP8 => keypad('8', Keysym::Up),
+        KEY_KP9 => keypad('9', Keysym::PageUp),
+        KEY_KP4 => keypad('4', Keysym::Left),
+        KEY_KP5 => keypad('5', Keysym::NoSymbol),
+        KEY_KP6 => keypad('6', Keysym::Right),
+        KEY_KP1 => keypad('1', Keysym::End),
+        KEY_KP2 => keypad('2', Keysym::Down),
+        KEY_KP3 => keypad('3', Keysym::PageDown),
+        KEY_KP0 => keypad('0', Keysym::Insert),
+        KEY_KPDOT => keypad('.', Keysym::Delete),
+        _ => return None,
+    })
+}
@@ -0,0 +1,336 @@
+//! Layout tables and the layout file format.
+//!
+//! A layout file is plain text. Blank lines and lines starting with `#` are
+//! ignored; every other line is one of:
+//!
+//! ```text
+//! # The layout's name.
+//! name de
+//! # A key and its symbols, one per level.
+//! KEY_Q  q  Q  @
+//! # An extra compose sequence.
+//! compose o e œ
+//! ```
+//!
+//! A key is its evdev name from [`crate::codes`] or its decimal code. The
+//! levels are plain, Shift, AltGr and AltGr+Shift; trailing levels can be
+//! left off. A symbol is a single character, `space`, `U+XXXX`, `dead:X`
+//! for a dead key with accent `X`, or `none` for an empty level.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use core::fmt;
+
+use crate::codes::LAYOUT_KEYS;
+use crate::compose;
+use crate::keysym::Keysym;
+
+/// Levels per key: plain, Shift, AltGr, AltGr+Shift.
+pub const LEVELS: usize = 4;
+
+const US: &str = include_str!("../layouts/us.keymap");
+const GB: &str = include_str!("../layouts/gb.keymap");
+const DE: &str = include_str!("../layouts/de.keymap");
+const DVORAK: &str = include_str!("../layouts/dvorak.keymap");
+
+/// Names of the layouts built into the crate.
+pub const BUILTIN_LAYOUTS: &[&str] = &["us", "gb", "de", "dvorak"];
+
+/// A keyboard layout: which symbol each character key produces at each
+/// level, plus any compose sequences the layout adds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+    name: String,
+    keys: BTreeMap<u16, [Keysym; LEVELS]>,
+    compose: BTreeMap<(char, char), char>,
+    has_altgr: bool,
+}
+
+/// Why a layout file could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// 1-based line number.
+    pub line: usize,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// Not a key name from `codes` or a number.
+    UnknownKey(String),
+    /// A symbol that isn't one character, `space`, `U+XXXX`, `dead:X` or `none`.
+    BadSymbol(String),
+    /// A key line with no symbols, or more than [`LEVELS`].
+    WrongLevelCount,
+    /// A `name` line without a name, or a `compose` line without exactly
+    /// three characters.
+    BadDirective,
+    /// The file has no `name` line.
+    MissingName,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            ParseErrorKind::UnknownKey(key) => write!(f, "unknown key '{}'", key),
+            ParseErrorKind::BadSymbol(symbol) => write!(f, "bad symbol '{}'", symbol),
+            ParseErrorKind::WrongLevelCount => write!(f, "expected 1 to {} symbols", LEVELS),
+            ParseErrorKind::BadDirective => write!(f, "malformed directive"),
+            ParseErrorKind::MissingName => write!(f, "no 'name' line"),
+        }
+    }
+}
+
+impl Keymap {
+    /// Parse a layout file.
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        let mut name = None;
+        let mut keys = BTreeMap::new();
+        let mut compose = BTreeMap::new();
+
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index + 1;
+            let error = |kind| ParseError {
+                line: line_number,
+                kind,
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            let Some(first) = words.next() else {
+                continue;
+            };
+            match first {
+                "name" => {
+                    let value = words.next().ok_or(error(ParseErrorKind::BadDirective))?;
+                    if words.next().is_some() {
+                        return Err(error(ParseErrorKind::BadDirective));
+                    }
+                    name = Some(String::from(value));
+                }
+                "compose" => {
+                    let mut chars = [' '; 3];
+                    for slot in &mut chars {
+                        let word = words.next().ok_or(error(ParseErrorKind::BadDirective))?;
+                        *slot = match parse_symbol(word) {
+                            Some(Keysym::Char(c)) => c,
+                            _ => return Err(error(ParseErrorKind::BadSymbol(word.into()))),
+                        };
+                    }
+                    if words.next().is_some() {
+                        return Err(error(ParseErrorKind::BadDirective));
+                    }
+                    compose.insert((chars[0], chars[1]), chars[2]);
+                }
+                key => {
+                    let code =
+                        key_code(key).ok_or(error(ParseErrorKind::UnknownKey(key.into())))?;
+                    let mut levels = [Keysym::NoSymbol; LEVELS];
+                    let mut count = 0;
+                    for word in words {
+                        if count == LEVELS {
+                            return Err(error(ParseErrorKind::WrongLevelCount));
+                        }
+                        levels[count] = parse_symbol(word)
+                            .ok_or(error(ParseErrorKind::BadSymbol(word.into())))?;
+                        count += 1;
+                    }
+                    if count == 0 {
+                        return Err(error(ParseErrorKind::WrongLevelCount));
+                    }
+                    keys.insert(code, levels);
+                }
+            }
+        }
+
+        let name = name.ok_or(ParseError {
+            line: source.lines().count(),
+            kind: ParseErrorKind::MissingName,
+        })?;
+        let has_altgr = keys
+            .values()
+            .any(|levels| levels[2..].iter().any(|&sym| sym != Keysym::NoSymbol));
+        Ok(Self {
+            name,
+            keys,
+            compose,
+            has_altgr,
+        })
+    }
+
+    /// One of the [`BUILTIN_LAYOUTS`], by name.
+    pub fn builtin(name: &str) -> Option<Self> {
+        let source = match name {
+            "us" => US,
+            "gb" => GB,
+            "de" => DE,
+            "dvorak" => DVORAK,
+            _ => return None,
+        };
+        Some(Self::parse(source).expect("built-in layouts parse"))
+    }
+
+    /// The US layout, the default.
+    pub fn us() -> Self {
+        Self::parse(US).expect("built-in layouts parse")
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether Right Alt selects the third and fourth levels. Layouts that
+    /// define nothing there keep it as an ordinary Alt.
+    pub fn has_altgr(&self) -> bool {
+        self.has_altgr
+    }
+
+    /// The symbols a key produces at each level, if the layout defines it.
+    pub fn levels(&self, code: u16) -> Option<&[Keysym; LEVELS]> {
+        self.keys.get(&code)
+    }
+
+    /// The symbol for `code` at `level`. An empty level falls back to the
+    /// same level without Shift, then without AltGr.
+    pub fn lookup(&self, code: u16, level: usize) -> Keysym {
+        let Some(levels) = self.keys.get(&code) else {
+            return Keysym::NoSymbol;
+        };
+        [level, level & !1, level & 1, 0]
+            .into_iter()
+            .map(|level| levels[level])
+            .find(|&sym| sym != Keysym::NoSymbol)
+            .unwrap_or(Keysym::NoSymbol)
+    }
+
+    /// Combine two characters, using the layout's own sequences first and
+    /// then the built-in table.
+    pub fn compose(&self, first: char, second: char) -> Option<char> {
+        self.compose
+            .get(&(first, second))
+            .copied()
+            .or_else(|| compose::lookup(first, second))
+    }
+}
+
+fn key_code(name: &str) -> Option<u16> {
+    LAYOUT_KEYS
+        .iter()
+        .find(|&&(key, _)| key == name)
+        .map(|&(_, code)| code)
+        .or_else(|| name.parse().ok())
+}
+
+fn parse_symbol(word: &str) -> Option<Keysym> {
+    if word == "none" {
+        return Some(Keysym::NoSymbol);
+    }
+    if word == "space" {
+        return Some(Keysym::Char(' '));
+    }
+    if let Some(accent) = word.strip_prefix("dead:") {
+        return single_char(accent).map(Keysym::Dead);
+    }
+    if let Some(hex) = word.strip_prefix("U+") {
+        return u32::from_str_radix(hex, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .map(Keysym::Char);
+    }
+    single_char(word).map(Keysym::Char)
+}
+
+fn single_char(word: &str) -> Option<char> {
+    let mut chars = word.chars();
+    let c = chars.next()?;
+    chars.next().is_none().then_some(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codes::*;
+
+    #[test]
+    fn builtin_layouts_parse() {
+        for &name in BUILTIN_LAYOUTS {
+            let keymap = Keymap::builtin(name).unwrap();
+            assert_eq!(keymap.name(), name);
+            assert_eq!(keymap.lookup(KEY_SPACE, 0), Keysym::Char(' '));
+        }
+        assert!(Keymap::builtin("xx").is_none());
+    }
+
+    #[test]
+    fn layouts_differ_where_they_should() {
+        let us = Keymap::us();
+        let de = Keymap::builtin("de").unwrap();
+        let dvorak = Keymap::builtin("dvorak").unwrap();
+        let gb = Keymap::builtin("gb").unwrap();
+
+        assert_eq!(us.lookup(KEY_Y, 0), Keysym::Char('y'));
+        assert_eq!(de.lookup(KEY_Y, 0), Keysym::Char('z'));
+        assert_eq!(dvorak.lookup(KEY_S, 0), Keysym::Char('o'));
+        assert_eq!(us.lookup(KEY_3, 1), Keysym::Char('#'));
+        assert_eq!(gb.lookup(KEY_3, 1), Keysym::Char('£'));
+        assert_eq!(de.lookup(KEY_EQUAL, 0), Keysym::Dead('´'));
+        assert_eq!(de.lookup(KEY_Q, 2), Keysym::Char('@'));
+    }
+
+    #[test]
+    fn altgr_only_on_layouts_with_third_level() {
+        assert!(!Keymap::us().has_altgr());
+        assert!(!Keymap::builtin("dvorak").unwrap().has_altgr());
+        assert!(Keymap::builtin("gb").unwrap().has_altgr());
+        assert!(Keymap::builtin("de").unwrap().has_altgr());
+    }
+
+    #[test]
+    fn empty_levels_fall_back() {
+        let gb = Keymap::builtin("gb").unwrap();
+        // AltGr+Shift+4 is undefined; AltGr+4 is the euro sign.
+        assert_eq!(gb.lookup(KEY_4, 3), Keysym::Char('€'));
+        // AltGr+Q is undefined; falls back to plain q.
+        assert_eq!(gb.lookup(KEY_Q, 2), Keysym::Char('q'));
+        assert_eq!(gb.lookup(KEY_Q, 3), Keysym::Char('Q'));
+        assert_eq!(gb.lookup(KEY_ENTER, 0), Keysym::NoSymbol);
+    }
+
+    #[test]
+    fn parses_symbol_forms() {
+        let keymap =
+            Keymap::parse("# test\nname t\n16 U+00E9 none\nKEY_W dead:~ space\ncompose a a å\n")
+                .unwrap();
+        assert_eq!(keymap.lookup(KEY_Q, 0), Keysym::Char('é'));
+        assert_eq!(keymap.levels(KEY_Q).unwrap()[1], Keysym::NoSymbol);
+        assert_eq!(keymap.lookup(KEY_W, 0), Keysym::Dead('~'));
+        assert_eq!(keymap.lookup(KEY_W, 1), Keysym::Char(' '));
+        assert_eq!(keymap.compose('a', 'a'), Some('å'));
+        assert_eq!(keymap.compose('´', 'e'), Some('é'));
+    }
+
+    #[test]
+    fn reports_errors_with_line_numbers() {
+        let err = Keymap::parse("name t\nKEY_NOPE a\n").unwrap_err();
+        assert_eq!(err.line, 2);
+        assert_eq!(err.kind, ParseErrorKind::UnknownKey("KEY_NOPE".into()));
+
+        let err = Keymap::parse("name t\nKEY_A ab\n").unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::BadSymbol("ab".into()));
+
+        let err = Keymap::parse("name t\nKEY_A a b c d e\n").unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::WrongLevelCount);
+
+        let err = Keymap::parse("name t\nKEY_A\n").unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::WrongLevelCount);
+
+        let err = Keymap::parse("KEY_A a\n").unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::MissingName);
+
+        let err = Keymap::parse("name\n").unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::BadDirective);
+    }
+}
@@ -0,0 +1,24 @@
+//! Keyboard layouts for Panda OS.
+//!
+//! Turns the raw evdev key codes a `keyboard:` handle delivers into
+//! [`KeyEvent`]s carrying a [`Keysym`] and the text the key types. A
+//! [`Keymap`] is a layout table (parsed from a layout file, or one of the
+//! [`BUILTIN_LAYOUTS`]); a [`KeyboardState`] applies it while tracking
+//! modifiers, Caps and Num Lock, dead keys and Compose sequences.
+//!
+//! The crate is pure logic; `libpanda::keyboard` re-exports it and adds
+//! loading layouts from the filesystem.
+
+#![no_std]
+
+extern crate alloc;
+
+pub mod codes;
+mod compose;
+mod keysym;
+mod layout;
+mod state;
+
+pub use keysym::Keysym;
+pub use layout::{BUILTIN_LAYOUTS, Keymap, LEVELS, ParseError, ParseErrorKind};
+pub use state::{KeyEvent, KeyValue, KeyboardState, Modifiers};
@@ -0,0 +1,532 @@
+//! Keyboard state: modifiers, locks, and dead key and compose sequences in
+//! progress.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::codes::*;
+use crate::keysym::{self, Keysym};
+use crate::layout::Keymap;
+
+/// Key event value indicating press, release, or repeat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyValue {
+    Release,
+    Press,
+    Repeat,
+}
+
+impl KeyValue {
+    /// Convert from raw u32 value.
+    pub fn from_u32(value: u32) -> Self {
+        match value {
+            0 => KeyValue::Release,
+            1 => KeyValue::Press,
+            2 => KeyValue::Repeat,
+            _ => KeyValue::Release,
+        }
+    }
+}
+
+/// Modifier keys held and locks on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Modifiers(u8);
+
+impl Modifiers {
+    pub const NONE: Self = Self(0);
+    pub const SHIFT: Self = Self(1 << 0);
+    pub const CTRL: Self = Self(1 << 1);
+    pub const ALT: Self = Self(1 << 2);
+    pub const ALTGR: Self = Self(1 << 3);
+    pub const SUPER: Self = Self(1 << 4);
+    pub const CAPS_LOCK: Self = Self(1 << 5);
+    pub const NUM_LOCK: Self = Self(1 << 6);
+
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    pub const fn from_bits(bits: u8) -> Self {
+        Self(bits)
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub fn shift(self) -> bool {
+        self.contains(Self::SHIFT)
+    }
+
+    pub fn ctrl(self) -> bool {
+        self.contains(Self::CTRL)
+    }
+
+    pub fn alt(self) -> bool {
+        self.contains(Self::ALT)
+    }
+
+    fn set(&mut self, other: Self, on: bool) {
+        if on {
+            self.0 |= other.0;
+        } else {
+            self.0 &= !other.0;
+        }
+    }
+
+    fn toggle(&mut self, other: Self) {
+        self.0 ^= other.0;
+    }
+}
+
+/// A decoded key event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyEvent {
+    /// The evdev key code.
+    pub code: u16,
+    pub value: KeyValue,
+    /// What the key means under the current layout and modifiers.
+    pub keysym: Keysym,
+    /// The text the key types: empty for releases, modifiers, navigation
+    /// keys and dead keys; two characters when a dead key's accent can't
+    /// combine with the next letter. Ctrl with a letter gives the control
+    /// character (Ctrl+C is `"\u{3}"`); Enter is `"\n"` and Tab `"\t"`.
+    pub text: String,
+    /// Modifiers and locks after this event.
+    pub modifiers: Modifiers,
+}
+
+impl KeyEvent {
+    /// Whether the key went down (or is auto-repeating).
+    pub fn is_press(&self) -> bool {
+        self.value != KeyValue::Release
+    }
+
+    /// The text as one character, if it is exactly one.
+    pub fn char(&self) -> Option<char> {
+        let mut chars = self.text.chars();
+        let c = chars.next()?;
+        chars.next().is_none().then_some(c)
+    }
+}
+
+/// A multi-key sequence in progress.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Pending {
+    None,
+    /// A dead key was pressed; its accent waits for the next character.
+    Dead(char),
+    /// The Compose key was pressed, followed by these characters.
+    Compose(Vec<char>),
+}
+
+// Physical modifier keys, tracked separately so releasing one Shift while
+// the other is held keeps Shift on.
+const HELD_LEFT_SHIFT: u8 = 1 << 0;
+const HELD_RIGHT_SHIFT: u8 = 1 << 1;
+const HELD_LEFT_CTRL: u8 = 1 << 2;
+const HELD_RIGHT_CTRL: u8 = 1 << 3;
+const HELD_LEFT_ALT: u8 = 1 << 4;
+const HELD_RIGHT_ALT: u8 = 1 << 5;
+const HELD_LEFT_SUPER: u8 = 1 << 6;
+const HELD_RIGHT_SUPER: u8 = 1 << 7;
+
+fn held_bit(code: u16) -> Option<u8> {
+    Some(match code {
+        KEY_LEFTSHIFT => HELD_LEFT_SHIFT,
+        KEY_RIGHTSHIFT => HELD_RIGHT_SHIFT,
+        KEY_LEFTCTRL => HELD_LEFT_CTRL,
+        KEY_RIGHTCTRL => HELD_RIGHT_CTRL,
+        KEY_LEFTALT => HELD_LEFT_ALT,
+        KEY_RIGHTALT => HELD_RIGHT_ALT,
+        KEY_LEFTMETA => HELD_LEFT_SUPER,
+        KEY_RIGHTMETA => HELD_RIGHT_SUPER,
+        _ => return None,
+    })
+}
+
+/// Turns raw key codes into [`KeyEvent`]s under a [`Keymap`].
+///
+/// Feed it every key event from the device, releases included, so that it
+/// can track which modifiers are down.
+pub struct KeyboardState {
+    keymap: Keymap,
+    held: u8,
+    locks: Modifiers,
+    pending: Pending,
+}
+
+impl KeyboardState {
+    pub fn new(keymap: Keymap) -> Self {
+        Self {
+            keymap,
+            held: 0,
+            locks: Modifiers::NONE,
+            pending: Pending::None,
+        }
+    }
+
+    pub fn keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    /// Switch layouts. Held modifiers and locks carry over; a half-typed
+    /// dead key or compose sequence is dropped.
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+        self.pending = Pending::None;
+    }
+
+    /// Forget which keys are held, as when the keyboard focus moves away and
+    /// their releases will go elsewhere. Locks stay on.
+    pub fn release_all(&mut self) {
+        self.held = 0;
+        self.pending = Pending::None;
+    }
+
+    /// The modifiers held and locks on right now.
+    pub fn modifiers(&self) -> Modifiers {
+        let mut modifiers = self.locks;
+        let held = |bits: u8| self.held & bits != 0;
+        modifiers.set(Modifiers::SHIFT, held(HELD_LEFT_SHIFT | HELD_RIGHT_SHIFT));
+        modifiers.set(Modifiers::CTRL, held(HELD_LEFT_CTRL | HELD_RIGHT_CTRL));
+        modifiers.set(Modifiers::SUPER, held(HELD_LEFT_SUPER | HELD_RIGHT_SUPER));
+        if self.keymap.has_altgr() {
+            modifiers.set(Modifiers::ALT, held(HELD_LEFT_ALT));
+            modifiers.set(Modifiers::ALTGR, held(HELD_RIGHT_ALT));
+        } else {
+            modifiers.set(Modifiers::ALT, held(HELD_LEFT_ALT | HELD_RIGHT_ALT));
+        }
+        modifiers
+    }
+
+    /// Decode one event from the device.
+    pub fn process(&mut self, code: u16, value: KeyValue) -> KeyEvent {
+        if let Some(bit) = held_bit(code) {
+            if value == KeyValue::Release {
+                self.held &= !bit;
+            } else {
+                self.held |= bit;
+            }
+        }
+
+        let keysym = self.keysym(code);
+        if value == KeyValue::Press {
+            match keysym {
+                Keysym::CapsLock => self.locks.toggle(Modifiers::CAPS_LOCK),
+                Keysym::NumLock => self.locks.toggle(Modifiers::NUM_LOCK),
+                _ => {}
+            }
+        }
+
+        let text = if value == KeyValue::Release || keysym.is_modifier() {
+            String::new()
+        } else {
+            self.text_for(keysym)
+        };
+
+        KeyEvent {
+            code,
+            value,
+            keysym,
+            text,
+            modifiers: self.modifiers(),
+        }
+    }
+
+    /// The symbol `code` produces with the current modifiers.
+    fn keysym(&self, code: u16) -> Keysym {
+        let modifiers = self.modifiers();
+        match keysym::fixed(code, modifiers.contains(Modifiers::NUM_LOCK)) {
+            Some(Keysym::AltGr) if !self.keymap.has_altgr() => return Keysym::Alt,
+            Some(sym) => return sym,
+            None => {}
+        }
+        let mut level = 0;
+        if modifiers.shift() {
+            level |= 1;
+        }
+        if modifiers.contains(Modifiers::ALTGR) {
+            level |= 2;
+        }
+        // Caps Lock shifts letters, and only letters.
+        if modifiers.contains(Modifiers::CAPS_LOCK)
+            && let Some(levels) = self.keymap.levels(code)
+            && let (Keysym::Char(lower), Keysym::Char(upper)) =
+                (levels[level & 2], levels[level | 1])
+            && lower.is_lowercase()
+            && lower.to_uppercase().eq(core::iter::once(upper))
+        {
+            level ^= 1;
+        }
+        self.keymap.lookup(code, level)
+    }
+
+    /// The text a press of `keysym` types, advancing any dead key or compose
+    /// sequence.
+    fn text_for(&mut self, keysym: Keysym) -> String {
+        let mut text = String::new();
+        match (core::mem::replace(&mut self.pending, Pending::None), keysym) {
+            (Pending::Compose(mut typed), Keysym::Char(c) | Keysym::Dead(c)) => {
+                typed.push(c);
+                if typed.len() == 2 {
+                    // An unknown sequence types nothing, as on other systems.
+                    if let Some(result) = self.keymap.compose(typed[0], typed[1]) {
+                        text.push(result);
+                    }
+                } else {
+                    self.pending = Pending::Compose(typed);
+                }
+            }
+            (Pending::Dead(accent), Keysym::Char(c)) => match self.keymap.compose(accent, c) {
+                Some(result) => text.push(result),
+                None if c == ' ' => text.push(accent),
+                None => {
+                    text.push(accent);
+                    text.push(c);
+                }
+            },
+            (Pending::Dead(first), Keysym::Dead(second)) => {
+                // The same dead key twice types the accent itself.
+                text.push(first);
+                if first != second {
+                    self.pending = Pending::Dead(second);
+                }
+            }
+            (_, Keysym::Dead(accent)) => self.pending = Pending::Dead(accent),
+            (_, Keysym::Compose) => self.pending = Pending::Compose(Vec::new()),
+            (_, Keysym::Char(c)) => text.push(self.control(c)),
+            (_, Keysym::Enter) => text.push('\n'),
+            (_, Keysym::Tab) => text.push('\t'),
+            // Anything else cancels a sequence in progress.
+            _ => {}
+        }
+        text
+    }
+
+    /// `c` as typed with the current modifiers: with Ctrl held, the control
+    /// character for letters and `@[\]^_?`.
+    fn control(&self, c: char) -> char {
+        let modifiers = self.modifiers();
+        if !modifiers.ctrl() || modifiers.contains(Modifiers::ALTGR) {
+            return c;
+        }
+        match c {
+            'a'..='z' | 'A'..='Z' => (c.to_ascii_lowercase() as u8 - b'a' + 1) as char,
+            '@' | ' ' => '\0',
+            '[' => '\u{1b}',
+            '\\' => '\u{1c}',
+            ']' => '\u{1d}',
+            '^' => '\u{1e}',
+            '_' => '\u{1f}',
+            '?' => '\u{7f}',
+            _ => c,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn press(state: &mut KeyboardState, code: u16) -> KeyEvent {
+        state.process(code, KeyValue::Press)
+    }
+
+    fn release(state: &mut KeyboardState, code: u16) -> KeyEvent {
+        state.process(code, KeyValue::Release)
+    }
+
+    fn tap(state: &mut KeyboardState, code: u16) -> String {
+        let text = press(state, code).text;
+        release(state, code);
+        text
+    }
+
+    fn typed(state: &mut KeyboardState, codes: &[u16]) -> String {
+        codes.iter().map(|&code| tap(state, code)).collect()
+    }
+
+    #[test]
+    fn plain_and_shifted_text() {
+        let mut state = KeyboardState::new(Keymap::us());
+        assert_eq!(typed(&mut state, &[KEY_H, KEY_I]), "hi");
+
+        let event = press(&mut state, KEY_LEFTSHIFT);
+        assert_eq!(event.keysym, Keysym::Shift);
+        assert!(event.text.is_empty());
+        assert!(event.modifiers.shift());
+        assert_eq!(typed(&mut state, &[KEY_H, KEY_1]), "H!");
+        release(&mut state, KEY_LEFTSHIFT);
+        assert_eq!(typed(&mut state, &[KEY_H]), "h");
+    }
+
+    #[test]
+    fn both_shifts_tracked_separately() {
+        let mut state = KeyboardState::new(Keymap::us());
+        press(&mut state, KEY_LEFTSHIFT);
+        press(&mut state, KEY_RIGHTSHIFT);
+        release(&mut state, KEY_LEFTSHIFT);
+        assert!(state.modifiers().shift());
+        release(&mut state, KEY_RIGHTSHIFT);
+        assert!(!state.modifiers().shift());
+    }
+
+    #[test]
+    fn caps_lock_shifts_letters_only() {
+        let mut state = KeyboardState::new(Keymap::us());
+        tap(&mut state, KEY_CAPSLOCK);
+        assert!(state.modifiers().contains(Modifiers::CAPS_LOCK));
+        assert_eq!(typed(&mut state, &[KEY_A, KEY_1]), "A1");
+
+        press(&mut state, KEY_LEFTSHIFT);
+        assert_eq!(typed(&mut state, &[KEY_A, KEY_1]), "a!");
+        release(&mut state, KEY_LEFTSHIFT);
+
+        tap(&mut state, KEY_CAPSLOCK);
+        assert_eq!(typed(&mut state, &[KEY_A]), "a");
+    }
+
+    #[test]
+    fn caps_lock_covers_non_ascii_letters() {
+        let mut state = KeyboardState::new(Keymap::builtin("de").unwrap());
+        tap(&mut state, KEY_CAPSLOCK);
+        assert_eq!(typed(&mut state, &[KEY_SEMICOLON, KEY_MINUS]), "Öß");
+    }
+
+    #[test]
+    fn ctrl_gives_control_characters() {
+        let mut state = KeyboardState::new(Keymap::us());
+        press(&mut state, KEY_LEFTCTRL);
+        let event = press(&mut state, KEY_C);
+        assert_eq!(event.keysym, Keysym::Char('c'));
+        assert_eq!(event.text, "\u{3}");
+        assert!(event.modifiers.ctrl());
+        assert_eq!(typed(&mut state, &[KEY_LEFTBRACE]), "\u{1b}");
+    }
+
+    #[test]
+    fn altgr_selects_third_level() {
+        let mut state = KeyboardState::new(Keymap::builtin("gb").unwrap());
+        press(&mut state, KEY_RIGHTALT);
+        assert!(state.modifiers().contains(Modifiers::ALTGR));
+        assert!(!state.modifiers().alt());
+        assert_eq!(typed(&mut state, &[KEY_4, KEY_E]), "€é");
+        press(&mut state, KEY_LEFTSHIFT);
+        assert_eq!(typed(&mut state, &[KEY_E]), "É");
+    }
+
+    #[test]
+    fn right_alt_is_alt_without_third_level() {
+        let mut state = KeyboardState::new(Keymap::us());
+        assert_eq!(press(&mut state, KEY_RIGHTALT).keysym, Keysym::Alt);
+        assert!(state.modifiers().alt());
+        assert!(!state.modifiers().contains(Modifiers::ALTGR));
+        assert_eq!(typed(&mut state, &[KEY_E]), "e");
+    }
+
+    #[test]
+    fn dead_keys_combine() {
+        let mut state = KeyboardState::new(Keymap::builtin("de").unwrap());
+        let event = press(&mut state, KEY_EQUAL);
+        assert_eq!(event.keysym, Keysym::Dead('´'));
+        assert!(event.text.is_empty());
+        release(&mut state, KEY_EQUAL);
+        assert_eq!(typed(&mut state, &[KEY_E]), "é");
+
+        // Accent then space types the accent; the same dead key twice too.
+        assert_eq!(typed(&mut state, &[KEY_GRAVE, KEY_SPACE]), "^");
+        assert_eq!(typed(&mut state, &[KEY_GRAVE, KEY_GRAVE]), "^");
+        // No combination: both characters.
+        assert_eq!(typed(&mut state, &[KEY_GRAVE, KEY_X]), "^x");
+        // Shift on the next letter.
+        tap(&mut state, KEY_GRAVE);
+        press(&mut state, KEY_LEFTSHIFT);
+        assert_eq!(typed(&mut state, &[KEY_O]), "Ô");
+    }
+
+    #[test]
+    fn non_character_key_cancels_dead_key() {
+        let mut state = KeyboardState::new(Keymap::builtin("de").unwrap());
+        tap(&mut state, KEY_EQUAL);
+        assert_eq!(tap(&mut state, KEY_LEFT), "");
+        assert_eq!(typed(&mut state, &[KEY_E]), "e");
+    }
+
+    #[test]
+    fn compose_sequences() {
+        let mut state = KeyboardState::new(Keymap::us());
+        assert_eq!(
+            vec![
+                tap(&mut state, KEY_COMPOSE),
+                tap(&mut state, KEY_S),
+                tap(&mut state, KEY_S),
+            ],
+            vec!["", "", "ß"]
+        );
+
+        tap(&mut state, KEY_COMPOSE);
+        press(&mut state, KEY_LEFTSHIFT);
+        tap(&mut state, KEY_APOSTROPHE);
+        release(&mut state, KEY_LEFTSHIFT);
+        assert_eq!(typed(&mut state, &[KEY_U]), "ü");
+
+        // Either order works, and unknown sequences type nothing.
+        assert_eq!(
+            typed(&mut state, &[KEY_COMPOSE, KEY_E, KEY_APOSTROPHE]),
+            "é"
+        );
+        assert_eq!(typed(&mut state, &[KEY_COMPOSE, KEY_Q, KEY_Q]), "");
+        assert_eq!(typed(&mut state, &[KEY_Q]), "q");
+    }
+
+    #[test]
+    fn keypad_follows_num_lock() {
+        let mut state = KeyboardState::new(Keymap::us());
+        assert_eq!(press(&mut state, KEY_KP8).keysym, Keysym::Up);
+        tap(&mut state, KEY_NUMLOCK);
+        assert_eq!(tap(&mut state, KEY_KP8), "8");
+    }
+
+    #[test]
+    fn special_keys() {
+        let mut state = KeyboardState::new(Keymap::us());
+        assert_eq!(press(&mut state, KEY_ENTER).text, "\n");
+        assert_eq!(press(&mut state, KEY_TAB).text, "\t");
+        let event = press(&mut state, KEY_BACKSPACE);
+        assert_eq!(event.keysym, Keysym::Backspace);
+        assert!(event.text.is_empty());
+        assert_eq!(press(&mut state, KEY_F5).keysym, Keysym::F(5));
+        assert_eq!(release(&mut state, KEY_A).text, "");
+        assert_eq!(state.process(KEY_A, KeyValue::Repeat).text, "a");
+    }
+
+    #[test]
+    fn releasing_everything_keeps_locks() {
+        let mut state = KeyboardState::new(Keymap::us());
+        tap(&mut state, KEY_CAPSLOCK);
+        press(&mut state, KEY_LEFTSHIFT);
+        press(&mut state, KEY_LEFTCTRL);
+        state.release_all();
+        assert_eq!(state.modifiers(), Modifiers::CAPS_LOCK);
+        assert_eq!(typed(&mut state, &[KEY_A]), "A");
+    }
+
+    #[test]
+    fn switching_layouts() {
+        let mut state = KeyboardState::new(Keymap::us());
+        assert_eq!(typed(&mut state, &[KEY_Y]), "y");
+        state.set_keymap(Keymap::builtin("de").unwrap());
+        assert_eq!(state.keymap().name(), "de");
+        assert_eq!(typed(&mut state, &[KEY_Y]), "z");
+    }
+}
@@ -0,0 +1,477 @@
+//! DHCP client (RFC 2131).
+//!
+//! Covers what a single-homed host needs: discover, request, renew, and
+//! start over on a NAK or an expired lease. The client only builds and
+//! parses message payloads; the stack carries them in UDP broadcasts
+//! between ports 68 and 67, which needs no address and no ARP.
+
+use alloc::vec::Vec;
+
+use crate::wire::MacAddr;
+use crate::{Instant, Ipv4Addr};
+
+pub const CLIENT_PORT: u16 = 68;
+pub const SERVER_PORT: u16 = 67;
+
+const BOOTREQUEST: u8 = 1;
+const BOOTREPLY: u8 = 2;
+const MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+/// The fixed BOOTP fields before the magic cookie.
+const BOOTP_LEN: usize = 236;
+/// Requests are padded to the minimum BOOTP message size, which some
+/// servers insist on.
+const MIN_MESSAGE_LEN: usize = 300;
+
+const DHCPDISCOVER: u8 = 1;
+const DHCPOFFER: u8 = 2;
+const DHCPREQUEST: u8 = 3;
+const DHCPACK: u8 = 5;
+const DHCPNAK: u8 = 6;
+
+const OPTION_PAD: u8 = 0;
+const OPTION_SUBNET_MASK: u8 = 1;
+const OPTION_ROUTER: u8 = 3;
+const OPTION_DNS: u8 = 6;
+const OPTION_REQUESTED_IP: u8 = 50;
+const OPTION_LEASE_TIME: u8 = 51;
+const OPTION_MESSAGE_TYPE: u8 = 53;
+const OPTION_SERVER_ID: u8 = 54;
+const OPTION_PARAMETER_LIST: u8 = 55;
+const OPTION_END: u8 = 255;
+
+/// First retransmission interval; doubled on each retry up to
+/// [`MAX_RETRY_MS`].
+const INITIAL_RETRY_MS: u64 = 2_000;
+const MAX_RETRY_MS: u64 = 16_000;
+/// Unanswered requests before going back to discovery.
+const MAX_REQUESTS: u32 = 4;
+/// Lease assumed when the server doesn't say.
+const DEFAULT_LEASE_SECS: u32 = 86_400;
+
+/// An address lease from a DHCP server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lease {
+    pub address: Ipv4Addr,
+    pub prefix_len: u8,
+    pub router: Option<Ipv4Addr>,
+    pub dns: Option<Ipv4Addr>,
+    pub server: Ipv4Addr,
+    pub lease_secs: u32,
+}
+
+/// A change in configuration reported by [`DhcpClient::receive`] or
+/// [`DhcpClient::poll`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DhcpEvent {
+    /// A lease was granted or renewed.
+    Configured(Lease),
+    /// The lease was lost; the address must no longer be used.
+    Deconfigured,
+}
+
+/// A message to broadcast from `src` port 68 to port 67.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outgoing {
+    pub src: Ipv4Addr,
+    pub payload: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Discovering,
+    Requesting {
+        offer: Lease,
+    },
+    Bound {
+        lease: Lease,
+        renew_at: Instant,
+        expires_at: Instant,
+    },
+    Renewing {
+        lease: Lease,
+        expires_at: Instant,
+    },
+}
+
+pub struct DhcpClient {
+    mac: MacAddr,
+    xid: u32,
+    state: State,
+    next_send: Instant,
+    retry_ms: u64,
+    tries: u32,
+}
+
+impl DhcpClient {
+    /// A client that starts discovering on its first poll. `xid` seeds the
+    /// transaction ids, which should differ between hosts on a network.
+    pub fn new(mac: MacAddr, xid: u32) -> Self {
+        Self {
+            mac,
+            xid,
+            state: State::Discovering,
+            next_send: 0,
+            retry_ms: INITIAL_RETRY_MS,
+            tries: 0,
+        }
+    }
+
+    /// The current lease, if bound.
+    pub fn lease(&self) -> Option<Lease> {
+        match self.state {
+            State::Bound { lease, .. } | State::Renewing { lease, .. } => Some(lease),
+            _ => None,
+        }
+    }
+
+    /// Run timers: send or resend whatever the current state calls for.
+    pub fn poll(&mut self, now: Instant) -> (Option<Outgoing>, Option<DhcpEvent>) {
+        match self.state {
+            State::Bound {
+                lease,
+                renew_at,
+                expires_at,
+            } if now >= renew_at => {
+                self.state = State::Renewing { lease, expires_at };
+                self.reset_retries(now);
+            }
+            State::Renewing { expires_at, .. } if now >= expires_at => {
+                self.restart(now);
+                return (self.poll(now).0, Some(DhcpEvent::Deconfigured));
+            }
+            State::Requesting { .. } if self.tries >= MAX_REQUESTS && now >= self.next_send => {
+                self.restart(now);
+            }
+            _ => {}
+        }
+
+        if now < self.next_send {
+            return (None, None);
+        }
+        let outgoing = match self.state {
+            State::Discovering => Outgoing {
+                src: Ipv4Addr::UNSPECIFIED,
+                payload: self.message(DHCPDISCOVER, Ipv4Addr::UNSPECIFIED, None, None),
+            },
+            State::Requesting { offer } => Outgoing {
+                src: Ipv4Addr::UNSPECIFIED,
+                payload: self.message(
+                    DHCPREQUEST,
+                    Ipv4Addr::UNSPECIFIED,
+                    Some(offer.address),
+                    Some(offer.server),
+                ),
+            },
+            // Renewals are broadcast (the rebinding form) so they need no
+            // route or ARP entry for the server.
+            State::Renewing { lease, .. } => Outgoing {
+                src: lease.address,
+                payload: self.message(DHCPREQUEST, lease.address, None, None),
+            },
+            State::Bound { .. } => return (None, None),
+        };
+        self.tries += 1;
+        self.next_send = now + self.retry_ms;
+        self.retry_ms = (self.retry_ms * 2).min(MAX_RETRY_MS);
+        (Some(outgoing), None)
+    }
+
+    /// Handle a message received on port 68.
+    pub fn receive(&mut self, payload: &[u8], now: Instant) -> Option<DhcpEvent> {
+        let reply = Reply::parse(payload)?;
+        if reply.xid != self.xid || reply.mac != self.mac {
+            return None;
+        }
+        match (self.state, reply.message_type) {
+            (State::Discovering, DHCPOFFER) => {
+                self.state = State::Requesting {
+                    offer: reply.lease()?,
+                };
+                self.reset_retries(now);
+                None
+            }
+            (State::Requesting { .. } | State::Renewing { .. }, DHCPACK) => {
+                let lease = reply.lease()?;
+                let lease_ms = lease.lease_secs as u64 * 1000;
+                self.state = State::Bound {
+                    lease,
+                    renew_at: now + lease_ms / 2,
+                    expires_at: now + lease_ms,
+                };
+                Some(DhcpEvent::Configured(lease))
+            }
+            (State::Requesting { .. } | State::Renewing { .. }, DHCPNAK) => {
+                let was_bound = matches!(self.state, State::Renewing { .. });
+                self.restart(now);
+                was_bound.then_some(DhcpEvent::Deconfigured)
+            }
+            _ => None,
+        }
+    }
+
+    fn restart(&mut self, now: Instant) {
+        self.state = State::Discovering;
+        self.xid = self.xid.wrapping_add(1);
+        self.reset_retries(now);
+    }
+
+    fn reset_retries(&mut self, now: Instant) {
+        self.next_send = now;
+        self.retry_ms = INITIAL_RETRY_MS;
+        self.tries = 0;
+    }
+
+    fn message(
+        &self,
+        message_type: u8,
+        ciaddr: Ipv4Addr,
+        requested: Option<Ipv4Addr>,
+        server: Option<Ipv4Addr>,
+    ) -> Vec<u8> {
+        let mut msg = Vec::with_capacity(MIN_MESSAGE_LEN);
+        msg.extend_from_slice(&[BOOTREQUEST, 1, 6, 0]);
+        msg.extend_from_slice(&self.xid.to_be_bytes());
+        // secs, then the broadcast flag: we can't receive unicast yet.
+        msg.extend_from_slice(&[0, 0, 0x80, 0]);
+        msg.extend_from_slice(&ciaddr.0);
+        // yiaddr, siaddr, giaddr
+        msg.extend_from_slice(&[0; 12]);
+        msg.extend_from_slice(&self.mac);
+        msg.resize(BOOTP_LEN, 0);
+        msg.extend_from_slice(&MAGIC_COOKIE);
+
+        msg.extend_from_slice(&[OPTION_MESSAGE_TYPE, 1, message_type]);
+        if let Some(requested) = requested {
+            msg.extend_from_slice(&[OPTION_REQUESTED_IP, 4]);
+            msg.extend_from_slice(&requested.0);
+        }
+        if let Some(server) = server {
+            msg.extend_from_slice(&[OPTION_SERVER_ID, 4]);
+            msg.extend_from_slice(&server.0);
+        }
+        msg.extend_from_slice(&[
+            OPTION_PARAMETER_LIST,
+            4,
+            OPTION_SUBNET_MASK,
+            OPTION_ROUTER,
+            OPTION_DNS,
+            OPTION_LEASE_TIME,
+        ]);
+        msg.push(OPTION_END);
+        if msg.len() < MIN_MESSAGE_LEN {
+            msg.resize(MIN_MESSAGE_LEN, OPTION_PAD);
+        }
+        msg
+    }
+}
+
+/// The parts of a server reply the client uses.
+struct Reply {
+    message_type: u8,
+    xid: u32,
+    mac: MacAddr,
+    yiaddr: Ipv4Addr,
+    subnet_mask: Option<Ipv4Addr>,
+    router: Option<Ipv4Addr>,
+    dns: Option<Ipv4Addr>,
+    server: Option<Ipv4Addr>,
+    lease_secs: Option<u32>,
+}
+
+impl Reply {
+    fn parse(buf: &[u8]) -> Option<Self> {
+        if buf.len() < BOOTP_LEN + MAGIC_COOKIE.len()
+            || buf[0] != BOOTREPLY
+            || buf[BOOTP_LEN..BOOTP_LEN + 4] != MAGIC_COOKIE
+        {
+            return None;
+        }
+        let mut mac = [0; 6];
+        mac.copy_from_slice(&buf[28..34]);
+        let mut reply = Self {
+            message_type: 0,
+            xid: u32::from_be_bytes(buf[4..8].try_into().ok()?),
+            mac,
+            yiaddr: ip(&buf[16..20])?,
+            subnet_mask: None,
+            router: None,
+            dns: None,
+            server: None,
+            lease_secs: None,
+        };
+
+        let mut options = &buf[BOOTP_LEN + 4..];
+        while let Some(&code) = options.first() {
+            match code {
+                OPTION_PAD => {
+                    options = &options[1..];
+                    continue;
+                }
+                OPTION_END => break,
+                _ => {}
+            }
+            let len = *options.get(1)? as usize;
+            let value = options.get(2..2 + len)?;
+            match code {
+                OPTION_MESSAGE_TYPE => reply.message_type = *value.first()?,
+                OPTION_SUBNET_MASK => reply.subnet_mask = ip(value),
+                // Routers and DNS servers are lists; the first is preferred.
+                OPTION_ROUTER => reply.router = ip(value),
+                OPTION_DNS => reply.dns = ip(value),
+                OPTION_SERVER_ID => reply.server = ip(value),
+                OPTION_LEASE_TIME => {
+                    reply.lease_secs = value
+                        .get(..4)
+                        .map(|v| u32::from_be_bytes(v.try_into().unwrap()))
+                }
+                _ => {}
+            }
+            options = &options[2 + len..];
+        }
+        Some(reply)
+    }
+
+    fn lease(&self) -> Option<Lease> {
+        if self.yiaddr.is_unspecified() {
+            return None;
+        }
+        let mask = self.subnet_mask.unwrap_or(Ipv4Addr::new(255, 255, 255, 0));
+        Some(Lease {
+            address: self.yiaddr,
+            prefix_len: mask.to_bits().count_ones() as u8,
+            router: self.router,
+            dns: self.dns,
+            server: self.server?,
+            lease_secs: self.lease_secs.unwrap_or(DEFAULT_LEASE_SECS),
+        })
+    }
+}
+
+fn ip(bytes: &[u8]) -> Option<Ipv4Addr> {
+    Some(Ipv4Addr(bytes.get(..4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub const MAC: MacAddr = [0x52, 0x54, 0, 0x12, 0x34, 0x56];
+    pub const SERVER: Ipv4Addr = Ipv4Addr::new(10, 0, 2, 2);
+    pub const OFFERED: Ipv4Addr = Ipv4Addr::new(10, 0, 2, 15);
+
+    /// What QEMU's user-mode network answers with.
+    pub fn server_reply(request: &[u8], message_type: u8, lease_secs: u32) -> Vec<u8> {
+        let mut reply = request[..BOOTP_LEN].to_vec();
+        reply[0] = BOOTREPLY;
+        reply[16..20].copy_from_slice(&OFFERED.0);
+        reply.extend_from_slice(&MAGIC_COOKIE);
+        reply.extend_from_slice(&[OPTION_MESSAGE_TYPE, 1, message_type]);
+        reply.extend_from_slice(&[OPTION_SERVER_ID, 4, 10, 0, 2, 2]);
+        reply.extend_from_slice(&[OPTION_SUBNET_MASK, 4, 255, 255, 255, 0]);
+        reply.extend_from_slice(&[OPTION_ROUTER, 4, 10, 0, 2, 2]);
+        reply.extend_from_slice(&[OPTION_DNS, 4, 10, 0, 2, 3]);
+        reply.extend_from_slice(&[OPTION_LEASE_TIME, 4]);
+        reply.extend_from_slice(&lease_secs.to_be_bytes());
+        reply.push(OPTION_END);
+        reply
+    }
+
+    fn message_type(msg: &[u8]) -> u8 {
+        assert_eq!(msg[BOOTP_LEN..BOOTP_LEN + 4], MAGIC_COOKIE);
+        assert_eq!(msg[BOOTP_LEN + 4], OPTION_MESSAGE_TYPE);
+        msg[BOOTP_LEN + 6]
+    }
+
+    fn bind(client: &mut DhcpClient, now: Instant) -> Lease {
+        let discover = client.poll(now).0.expect("discover");
+        assert_eq!(message_type(&discover.payload), DHCPDISCOVER);
+        assert!(discover.payload.len() >= MIN_MESSAGE_LEN);
+
+        let offer = server_reply(&discover.payload, DHCPOFFER, 3600);
+        assert_eq!(client.receive(&offer, now), None);
+
+        let request = client.poll(now).0.expect("request");
+        assert_eq!(message_type(&request.payload), DHCPREQUEST);
+
+        let ack = server_reply(&request.payload, DHCPACK, 3600);
+        match client.receive(&ack, now) {
+            Some(DhcpEvent::Configured(lease)) => lease,
+            other => panic!("expected a lease, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn discover_offer_request_ack() {
+        let mut client = DhcpClient::new(MAC, 0x1234);
+        let lease = bind(&mut client, 0);
+        assert_eq!(
+            lease,
+            Lease {
+                address: OFFERED,
+                prefix_len: 24,
+                router: Some(SERVER),
+                dns: Some(Ipv4Addr::new(10, 0, 2, 3)),
+                server: SERVER,
+                lease_secs: 3600,
+            }
+        );
+        assert_eq!(client.lease(), Some(lease));
+        assert_eq!(
+            client.poll(1_000),
+            (None, None),
+            "nothing to send while bound"
+        );
+    }
+
+    #[test]
+    fn discover_is_retransmitted_with_backoff() {
+        let mut client = DhcpClient::new(MAC, 1);
+        assert!(client.poll(0).0.is_some());
+        assert!(client.poll(1_999).0.is_none());
+        assert!(client.poll(2_000).0.is_some());
+        assert!(client.poll(5_999).0.is_none());
+        assert!(client.poll(6_000).0.is_some());
+    }
+
+    #[test]
+    fn replies_for_other_transactions_are_ignored() {
+        let mut client = DhcpClient::new(MAC, 7);
+        let discover = client.poll(0).0.unwrap();
+        let mut offer = server_reply(&discover.payload, DHCPOFFER, 60);
+        offer[4] ^= 0xff;
+        client.receive(&offer, 0);
+        // Still discovering: the next poll (once due) is another DISCOVER.
+        let next = client.poll(2_000).0.unwrap();
+        assert_eq!(message_type(&next.payload), DHCPDISCOVER);
+    }
+
+    #[test]
+    fn renews_at_half_lease_and_expires() {
+        let mut client = DhcpClient::new(MAC, 9);
+        let lease = bind(&mut client, 0);
+
+        let renew = client.poll(1_800_000).0.expect("renewal at T1");
+        assert_eq!(message_type(&renew.payload), DHCPREQUEST);
+        assert_eq!(renew.src, lease.address);
+        assert_eq!(renew.payload[12..16], lease.address.0, "ciaddr");
+
+        // No answer: the lease runs out.
+        let (_, event) = client.poll(3_600_000);
+        assert_eq!(event, Some(DhcpEvent::Deconfigured));
+        assert_eq!(client.lease(), None);
+    }
+
+    #[test]
+    fn nak_restarts_discovery() {
+        let mut client = DhcpClient::new(MAC, 3);
+        bind(&mut client, 0);
+        let renew = client.poll(1_800_000).0.unwrap();
+        let nak = server_reply(&renew.payload, DHCPNAK, 0);
+        assert_eq!(
+            client.receive(&nak, 1_800_000),
+            Some(DhcpEvent::Deconfigured)
+        );
+        let next = client.poll(1_800_000).0.unwrap();
+        assert_eq!(message_type(&next.payload), DHCPDISCOVER);
+    }
+}
@@ -0,0 +1,32 @@
+//! A small TCP/IP stack for Panda OS.
+//!
+//! Ethernet, ARP, IPv4, UDP, TCP and a DHCP client — enough for one host
+//! with one NIC on a LAN. The crate is pure protocol logic with no OS
+//! dependencies: the caller feeds received frames to [`Stack::receive`],
+//! calls [`Stack::poll`] to run timers, and sends whatever
+//! [`Stack::transmit`] hands back. The network service (`userspace/netd`)
+//! drives it against the `net:` scheme and exposes its sockets as the
+//! `tcp:` and `udp:` schemes.
+//!
+//! Time is passed in explicitly as an [`Instant`] in milliseconds, which
+//! keeps every timer deterministic under test.
+
+#![no_std]
+
+extern crate alloc;
+
+#[cfg(test)]
+extern crate std;
+
+pub mod dhcp;
+mod stack;
+pub mod tcp;
+mod udp;
+pub mod wire;
+
+pub use panda_abi::socket::{Ipv4Addr, SocketAddrV4};
+pub use stack::{Config, Ipv4Config, SocketHandle, Stack, StackError};
+pub use tcp::{State as TcpState, TcpError};
+
+/// Milliseconds since some fixed point, as reported by the caller.
+pub type Instant = u64;
@@ -0,0 +1,1042 @@
+//! The interface: one NIC, one IPv4 address, and the sockets using them.
+
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::dhcp::{self, DhcpClient, DhcpEvent};
+use crate::tcp::{self, Segment, TcpSocket};
+use crate::udp::UdpSocket;
+use crate::wire::{
+    self, ArpPacket, EthernetHeader, Ipv4Header, MacAddr, TcpHeader, UdpHeader, tcp_flags,
+};
+use crate::{Instant, Ipv4Addr, SocketAddrV4, TcpError, TcpState};
+
+const TTL: u8 = 64;
+/// How long a learned MAC address is trusted.
+const ARP_CACHE_MS: u64 = 300_000;
+/// Interval between ARP requests for a packet awaiting resolution.
+const ARP_RETRY_MS: u64 = 1_000;
+/// ARP requests sent before the packets waiting on them are dropped.
+const ARP_MAX_TRIES: u32 = 3;
+/// Packets held while a next hop is resolved.
+const ARP_QUEUE_LEN: usize = 16;
+/// Connections a listener holds that the application hasn't accepted yet.
+const MAX_BACKLOG: usize = 16;
+/// Rounds of loopback delivery per poll. Each round lets every socket
+/// answer what the previous one delivered, so a handshake with ourselves
+/// completes within one poll.
+const LOOPBACK_ROUNDS: usize = 16;
+const EPHEMERAL_PORTS: core::ops::RangeInclusive<u16> = 49152..=65535;
+
+/// A static or leased IPv4 configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ipv4Config {
+    pub address: Ipv4Addr,
+    pub prefix_len: u8,
+    pub gateway: Option<Ipv4Addr>,
+    pub dns: Option<Ipv4Addr>,
+}
+
+impl Ipv4Config {
+    fn netmask(&self) -> u32 {
+        match self.prefix_len {
+            0 => 0,
+            len => u32::MAX << (32 - len.min(32) as u32),
+        }
+    }
+
+    /// Whether `ip` is on the local subnet.
+    pub fn contains(&self, ip: Ipv4Addr) -> bool {
+        let mask = self.netmask();
+        ip.to_bits() & mask == self.address.to_bits() & mask
+    }
+
+    /// The subnet's directed broadcast address.
+    pub fn broadcast(&self) -> Ipv4Addr {
+        Ipv4Addr::from_bits(self.address.to_bits() | !self.netmask())
+    }
+}
+
+/// How the interface gets its address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Config {
+    Dhcp,
+    Static(Ipv4Config),
+}
+
+/// Identifies a socket within a [`Stack`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SocketHandle(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackError {
+    /// The interface has no address yet.
+    NotConfigured,
+    /// The port is already bound.
+    AddressInUse,
+    /// The handle doesn't name a socket of the right kind.
+    InvalidSocket,
+    /// The connection can't send or has nothing more to receive.
+    Closed,
+    /// The datagram doesn't fit in one frame.
+    TooLarge,
+    /// No route to the destination: off-subnet with no gateway.
+    NoRoute,
+}
+
+struct Listener {
+    port: u16,
+    /// Connections opened against this listener, oldest first, until
+    /// accepted.
+    pending: Vec<SocketHandle>,
+}
+
+enum Socket {
+    Tcp {
+        socket: TcpSocket,
+        /// Set once the owner has let go; the socket is freed when it
+        /// reaches `Closed`.
+        released: bool,
+    },
+    Listener(Listener),
+    Udp(UdpSocket),
+}
+
+/// An IPv4 packet waiting for its next hop's MAC address.
+struct Unresolved {
+    next_hop: Ipv4Addr,
+    packet: Vec<u8>,
+    retry_at: Instant,
+    tries: u32,
+}
+
+pub struct Stack {
+    mac: MacAddr,
+    ipv4: Option<Ipv4Config>,
+    dhcp: Option<DhcpClient>,
+    arp_cache: BTreeMap<Ipv4Addr, (MacAddr, Instant)>,
+    unresolved: Vec<Unresolved>,
+    sockets: Vec<Option<Socket>>,
+    /// Frames ready for the NIC.
+    tx: VecDeque<Vec<u8>>,
+    /// IPv4 packets addressed to ourselves.
+    loopback: VecDeque<Vec<u8>>,
+    rng: u64,
+    ip_ident: u16,
+    next_port: u16,
+}
+
+impl Stack {
+    /// A stack for the NIC with address `mac`. `seed` feeds the initial
+    /// sequence numbers, DHCP transaction ids and ephemeral ports; it should
+    /// differ between boots.
+    pub fn new(mac: MacAddr, config: Config, seed: u64) -> Self {
+        let mut stack = Self {
+            mac,
+            ipv4: None,
+            dhcp: None,
+            arp_cache: BTreeMap::new(),
+            unresolved: Vec::new(),
+            sockets: Vec::new(),
+            tx: VecDeque::new(),
+            loopback: VecDeque::new(),
+            // xorshift must not start at zero.
+            rng: seed | 1,
+            ip_ident: 0,
+            next_port: 0,
+        };
+        stack.next_port =
+            *EPHEMERAL_PORTS.start() + (stack.random() % EPHEMERAL_PORTS.len() as u64) as u16;
+        match config {
+            Config::Static(ipv4) => stack.ipv4 = Some(ipv4),
+            Config::Dhcp => {
+                let xid = stack.random() as u32;
+                stack.dhcp = Some(DhcpClient::new(mac, xid));
+            }
+        }
+        stack
+    }
+
+    pub fn mac_address(&self) -> MacAddr {
+        self.mac
+    }
+
+    /// The interface's current address, if it has one.
+    pub fn ipv4_config(&self) -> Option<Ipv4Config> {
+        self.ipv4
+    }
+
+    /// Take the next frame to hand to the NIC.
+    pub fn transmit(&mut self) -> Option<Vec<u8>> {
+        self.tx.pop_front()
+    }
+
+    fn random(&mut self) -> u64 {
+        let mut x = self.rng;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.rng = x;
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    // =========================================================================
+    // Receive path
+    // =========================================================================
+
+    /// Handle a frame from the NIC.
+    pub fn receive(&mut self, frame: &[u8], now: Instant) {
+        let Some((eth, payload)) = EthernetHeader::parse(frame) else {
+            return;
+        };
+        if eth.dst != self.mac && eth.dst != wire::BROADCAST_MAC {
+            return;
+        }
+        match eth.ethertype {
+            wire::ETHERTYPE_ARP => self.receive_arp(payload, now),
+            wire::ETHERTYPE_IPV4 => self.receive_ipv4(payload, now),
+            _ => {}
+        }
+    }
+
+    fn receive_arp(&mut self, payload: &[u8], now: Instant) {
+        let Some(arp) = ArpPacket::parse(payload) else {
+            return;
+        };
+        let Some(ipv4) = self.ipv4 else {
+            return;
+        };
+        let for_us = arp.target_ip == ipv4.address;
+        // Learn the sender if it's talking to us or already known (RFC 826).
+        if !arp.sender_ip.is_unspecified()
+            && (for_us || self.arp_cache.contains_key(&arp.sender_ip))
+        {
+            self.learn(arp.sender_ip, arp.sender_mac, now);
+        }
+        if for_us && arp.op == wire::ARP_REQUEST {
+            let reply = ArpPacket {
+                op: wire::ARP_REPLY,
+                sender_mac: self.mac,
+                sender_ip: ipv4.address,
+                target_mac: arp.sender_mac,
+                target_ip: arp.sender_ip,
+            };
+            self.send_arp(arp.sender_mac, &reply);
+        }
+    }
+
+    fn learn(&mut self, ip: Ipv4Addr, mac: MacAddr, now: Instant) {
+        self.arp_cache.insert(ip, (mac, now + ARP_CACHE_MS));
+        let (ready, waiting) = core::mem::take(&mut self.unresolved)
+            .into_iter()
+            .partition(|packet| packet.next_hop == ip);
+        self.unresolved = waiting;
+        for packet in ready {
+            self.emit_frame(mac, wire::ETHERTYPE_IPV4, &packet.packet);
+        }
+    }
+
+    fn receive_ipv4(&mut self, packet: &[u8], now: Instant) {
+        let Some((ip, payload)) = Ipv4Header::parse(packet) else {
+            return;
+        };
+        let to_us = match self.ipv4 {
+            Some(ipv4) => {
+                ip.dst == ipv4.address || ip.dst.is_broadcast() || ip.dst == ipv4.broadcast()
+            }
+            // Without an address only DHCP replies, which may be
+            // broadcast or sent to the address being offered, matter.
+            None => ip.protocol == wire::IP_PROTOCOL_UDP,
+        };
+        if !to_us {
+            return;
+        }
+        match ip.protocol {
+            wire::IP_PROTOCOL_UDP => self.receive_udp(&ip, payload, now),
+            wire::IP_PROTOCOL_TCP if self.ipv4.is_some_and(|ipv4| ip.dst == ipv4.address) => {
+                self.receive_tcp(&ip, payload, now)
+            }
+            _ => {}
+        }
+    }
+
+    fn receive_udp(&mut self, ip: &Ipv4Header, segment: &[u8], now: Instant) {
+        let Some((udp, payload)) = UdpHeader::parse(ip.src, ip.dst, segment) else {
+            return;
+        };
+        if udp.dst_port == dhcp::CLIENT_PORT && udp.src_port == dhcp::SERVER_PORT {
+            if let Some(event) = self.dhcp.as_mut().and_then(|c| c.receive(payload, now)) {
+                self.apply_dhcp(event);
+            }
+            return;
+        }
+        if self.ipv4.is_none() {
+            return;
+        }
+        let src = SocketAddrV4::new(ip.src, udp.src_port);
+        for socket in self.sockets.iter_mut().flatten() {
+            if let Socket::Udp(socket) = socket
+                && socket.port == udp.dst_port
+                && socket.accepts(src)
+            {
+                socket.push(src, payload);
+                return;
+            }
+        }
+    }
+
+    fn receive_tcp(&mut self, ip: &Ipv4Header, segment: &[u8], now: Instant) {
+        let Some((seg, payload)) = TcpHeader::parse(ip.src, ip.dst, segment) else {
+            return;
+        };
+        let local = SocketAddrV4::new(ip.dst, seg.dst_port);
+        let remote = SocketAddrV4::new(ip.src, seg.src_port);
+
+        for socket in self.sockets.iter_mut().flatten() {
+            if let Socket::Tcp { socket, .. } = socket
+                && socket.local == local
+                && socket.remote == remote
+                && socket.state() != TcpState::Closed
+            {
+                socket.process(&seg, payload, now);
+                return;
+            }
+        }
+
+        let is_syn = seg.has(tcp_flags::SYN) && !seg.has(tcp_flags::ACK | tcp_flags::RST);
+        if is_syn && let Some(listener) = self.listener_for(seg.dst_port) {
+            let iss = self.random() as u32;
+            let socket = TcpSocket::accept(local, remote, &seg, iss);
+            let handle = self.insert(Socket::Tcp {
+                socket,
+                released: false,
+            });
+            if let Some(Socket::Listener(listener)) = self.get_mut(listener) {
+                listener.pending.push(handle);
+            }
+            return;
+        }
+
+        if let Some(rst) = tcp::reset_for(&seg, payload.len()) {
+            let segment = rst.build(ip.dst, ip.src, &[]);
+            let _ = self.send_ipv4(ip.src, wire::IP_PROTOCOL_TCP, &segment, now);
+        }
+    }
+
+    /// The listener on `port`, if it has room for another connection.
+    fn listener_for(&self, port: u16) -> Option<SocketHandle> {
+        self.sockets
+            .iter()
+            .enumerate()
+            .find_map(|(i, socket)| match socket {
+                Some(Socket::Listener(listener))
+                    if listener.port == port && listener.pending.len() < MAX_BACKLOG =>
+                {
+                    Some(SocketHandle(i))
+                }
+                _ => None,
+            })
+    }
+
+    fn apply_dhcp(&mut self, event: DhcpEvent) {
+        self.ipv4 = match event {
+            DhcpEvent::Configured(lease) => Some(Ipv4Config {
+                address: lease.address,
+                prefix_len: lease.prefix_len,
+                gateway: lease.router,
+                dns: lease.dns,
+            }),
+            DhcpEvent::Deconfigured => None,
+        };
+    }
+
+    // =========================================================================
+    // Transmit path
+    // =========================================================================
+
+    fn emit_frame(&mut self, dst: MacAddr, ethertype: u16, payload: &[u8]) {
+        let mut frame = vec![0u8; wire::ETHERNET_HEADER_LEN + payload.len()];
+        EthernetHeader {
+            dst,
+            src: self.mac,
+            ethertype,
+        }
+        .emit(&mut frame);
+        frame[wire::ETHERNET_HEADER_LEN..].copy_from_slice(payload);
+        self.tx.push_back(frame);
+    }
+
+    fn send_arp(&mut self, dst: MacAddr, arp: &ArpPacket) {
+        let mut payload = [0u8; wire::ARP_PACKET_LEN];
+        arp.emit(&mut payload);
+        self.emit_frame(dst, wire::ETHERTYPE_ARP, &payload);
+    }
+
+    fn request_mac(&mut self, ip: Ipv4Addr) {
+        let Some(ipv4) = self.ipv4 else {
+            return;
+        };
+        let request = ArpPacket {
+            op: wire::ARP_REQUEST,
+            sender_mac: self.mac,
+            sender_ip: ipv4.address,
+            target_mac: [0; 6],
+            target_ip: ip,
+        };
+        self.send_arp(wire::BROADCAST_MAC, &request);
+    }
+
+    fn ipv4_packet(
+        &mut self,
+        src: Ipv4Addr,
+        dst: Ipv4Addr,
+        protocol: u8,
+        payload: &[u8],
+    ) -> Vec<u8> {
+        let mut packet = vec![0u8; wire::IPV4_HEADER_LEN + payload.len()];
+        self.ip_ident = self.ip_ident.wrapping_add(1);
+        Ipv4Header {
+            src,
+            dst,
+            protocol,
+            ttl: TTL,
+            ident: self.ip_ident,
+        }
+        .emit(payload.len(), &mut packet);
+        packet[wire::IPV4_HEADER_LEN..].copy_from_slice(payload);
+        packet
+    }
+
+    /// Route and send an IPv4 packet from our address.
+    fn send_ipv4(
+        &mut self,
+        dst: Ipv4Addr,
+        protocol: u8,
+        payload: &[u8],
+        now: Instant,
+    ) -> Result<(), StackError> {
+        let ipv4 = self.ipv4.ok_or(StackError::NotConfigured)?;
+        let packet = self.ipv4_packet(ipv4.address, dst, protocol, payload);
+
+        if dst == ipv4.address {
+            self.loopback.push_back(packet);
+            return Ok(());
+        }
+        if dst.is_broadcast() || dst == ipv4.broadcast() {
+            self.emit_frame(wire::BROADCAST_MAC, wire::ETHERTYPE_IPV4, &packet);
+            return Ok(());
+        }
+
+        let next_hop = if ipv4.contains(dst) {
+            dst
+        } else {
+            ipv4.gateway.ok_or(StackError::NoRoute)?
+        };
+        if let Some(&(mac, expires)) = self.arp_cache.get(&next_hop)
+            && now < expires
+        {
+            self.emit_frame(mac, wire::ETHERTYPE_IPV4, &packet);
+            return Ok(());
+        }
+
+        let already_asked = self.unresolved.iter().any(|p| p.next_hop == next_hop);
+        if self.unresolved.len() < ARP_QUEUE_LEN {
+            self.unresolved.push(Unresolved {
+                next_hop,
+                packet,
+                retry_at: now + ARP_RETRY_MS,
+                tries: 1,
+            });
+        }
+        if !already_asked {
+            self.request_mac(next_hop);
+        }
+        Ok(())
+    }
+
+    fn send_segment(&mut self, remote: Ipv4Addr, segment: &Segment, now: Instant) {
+        let Some(ipv4) = self.ipv4 else {
+            return;
+        };
+        let bytes = segment.header.build(ipv4.address, remote, &segment.payload);
+        // Unroutable segments are dropped; the connection times out.
+        let _ = self.send_ipv4(remote, wire::IP_PROTOCOL_TCP, &bytes, now);
+    }
+
+    // =========================================================================
+    // Timers
+    // =========================================================================
+
+    /// Run timers and move pending output into the transmit queue. Call
+    /// after each batch of received frames and periodically otherwise.
+    pub fn poll(&mut self, now: Instant) {
+        self.poll_dhcp(now);
+        self.poll_arp(now);
+        for _ in 0..LOOPBACK_ROUNDS {
+            self.poll_tcp(now);
+            if self.loopback.is_empty() {
+                break;
+            }
+            while let Some(packet) = self.loopback.pop_front() {
+                self.receive_ipv4(&packet, now);
+            }
+        }
+        self.free_closed();
+    }
+
+    fn poll_dhcp(&mut self, now: Instant) {
+        let Some(client) = self.dhcp.as_mut() else {
+            return;
+        };
+        let (outgoing, event) = client.poll(now);
+        if let Some(event) = event {
+            self.apply_dhcp(event);
+        }
+        if let Some(outgoing) = outgoing {
+            let udp = UdpHeader {
+                src_port: dhcp::CLIENT_PORT,
+                dst_port: dhcp::SERVER_PORT,
+            }
+            .build(outgoing.src, Ipv4Addr::BROADCAST, &outgoing.payload);
+            let packet = self.ipv4_packet(
+                outgoing.src,
+                Ipv4Addr::BROADCAST,
+                wire::IP_PROTOCOL_UDP,
+                &udp,
+            );
+            self.emit_frame(wire::BROADCAST_MAC, wire::ETHERTYPE_IPV4, &packet);
+        }
+    }
+
+    fn poll_arp(&mut self, now: Instant) {
+        self.arp_cache.retain(|_, &mut (_, expires)| now < expires);
+
+        let mut ask = Vec::new();
+        self.unresolved.retain_mut(|packet| {
+            if now < packet.retry_at {
+                return true;
+            }
+            if packet.tries >= ARP_MAX_TRIES {
+                return false;
+            }
+            packet.tries += 1;
+            packet.retry_at = now + ARP_RETRY_MS;
+            if !ask.contains(&packet.next_hop) {
+                ask.push(packet.next_hop);
+            }
+            true
+        });
+        for ip in ask {
+            self.request_mac(ip);
+        }
+    }
+
+    fn poll_tcp(&mut self, now: Instant) {
+        let mut out = Vec::new();
+        for i in 0..self.sockets.len() {
+            let Some(Socket::Tcp { socket, .. }) = &mut self.sockets[i] else {
+                continue;
+            };
+            let remote = socket.remote.ip;
+            socket.poll(now, &mut out);
+            for segment in out.drain(..) {
+                self.send_segment(remote, &segment, now);
+            }
+        }
+    }
+
+    /// Free released connections that have finished closing, and
+    /// unaccepted ones that died before being accepted.
+    fn free_closed(&mut self) {
+        let mut dead = Vec::new();
+        for (i, socket) in self.sockets.iter().enumerate() {
+            if let Some(Socket::Tcp { socket, released }) = socket
+                && socket.state() == TcpState::Closed
+                && (*released || self.is_pending(SocketHandle(i)))
+            {
+                dead.push(SocketHandle(i));
+            }
+        }
+        for handle in dead {
+            self.remove(handle);
+        }
+    }
+
+    fn is_pending(&self, handle: SocketHandle) -> bool {
+        self.sockets.iter().flatten().any(|socket| match socket {
+            Socket::Listener(listener) => listener.pending.contains(&handle),
+            _ => false,
+        })
+    }
+
+    // =========================================================================
+    // Socket table
+    // =========================================================================
+
+    fn insert(&mut self, socket: Socket) -> SocketHandle {
+        if let Some(i) = self.sockets.iter().position(Option::is_none) {
+            self.sockets[i] = Some(socket);
+            SocketHandle(i)
+        } else {
+            self.sockets.push(Some(socket));
+            SocketHandle(self.sockets.len() - 1)
+        }
+    }
+
+    fn remove(&mut self, handle: SocketHandle) {
+        if let Some(slot) = self.sockets.get_mut(handle.0) {
+            *slot = None;
+        }
+        for socket in self.sockets.iter_mut().flatten() {
+            if let Socket::Listener(listener) = socket {
+                listener.pending.retain(|&pending| pending != handle);
+            }
+        }
+    }
+
+    fn get_mut(&mut self, handle: SocketHandle) -> Option<&mut Socket> {
+        self.sockets.get_mut(handle.0)?.as_mut()
+    }
+
+    fn tcp(&mut self, handle: SocketHandle) -> Result<&mut TcpSocket, StackError> {
+        match self.get_mut(handle) {
+            Some(Socket::Tcp {
+                socket,
+                released: false,
+            }) => Ok(socket),
+            _ => Err(StackError::InvalidSocket),
+        }
+    }
+
+    fn udp(&mut self, handle: SocketHandle) -> Result<&mut UdpSocket, StackError> {
+        match self.get_mut(handle) {
+            Some(Socket::Udp(socket)) => Ok(socket),
+            _ => Err(StackError::InvalidSocket),
+        }
+    }
+
+    fn port_in_use(&self, port: u16, tcp: bool) -> bool {
+        self.sockets.iter().flatten().any(|socket| match socket {
+            Socket::Tcp { socket, .. } => tcp && socket.local.port == port,
+            Socket::Listener(listener) => tcp && listener.port == port,
+            Socket::Udp(socket) => !tcp && socket.port == port,
+        })
+    }
+
+    fn ephemeral_port(&mut self, tcp: bool) -> Result<u16, StackError> {
+        for _ in EPHEMERAL_PORTS {
+            let port = self.next_port;
+            self.next_port = if port == *EPHEMERAL_PORTS.end() {
+                *EPHEMERAL_PORTS.start()
+            } else {
+                port + 1
+            };
+            if !self.port_in_use(port, tcp) {
+                return Ok(port);
+            }
+        }
+        Err(StackError::AddressInUse)
+    }
+
+    /// Close a socket of any kind. A TCP connection is closed gracefully:
+    /// queued data is still delivered and the FIN exchange runs to
+    /// completion in the background. Connections a listener hasn't handed
+    /// out are reset.
+    pub fn close(&mut self, handle: SocketHandle) {
+        match self.get_mut(handle) {
+            Some(Socket::Tcp { socket, released }) => {
+                socket.close();
+                *released = true;
+            }
+            Some(Socket::Listener(listener)) => {
+                let pending = core::mem::take(&mut listener.pending);
+                for connection in pending {
+                    self.abort(connection);
+                }
+                self.remove(handle);
+            }
+            Some(Socket::Udp(_)) => self.remove(handle),
+            None => {}
+        }
+    }
+
+    /// Reset a TCP connection and free it.
+    pub fn abort(&mut self, handle: SocketHandle) {
+        let Some(Socket::Tcp { socket, .. }) = self.get_mut(handle) else {
+            return;
+        };
+        let remote = socket.remote.ip;
+        let rst = socket.abort();
+        self.remove(handle);
+        if let Some(rst) = rst {
+            // Sent on the next poll's schedule would be too late: the
+            // socket is gone. Any "now" works for the routing decision.
+            self.send_segment(remote, &rst, 0);
+        }
+    }
+
+    // =========================================================================
+    // TCP
+    // =========================================================================
+
+    /// Open a connection to `remote`. The SYN goes out on the next poll;
+    /// watch [`tcp_state`](Self::tcp_state) for the outcome.
+    pub fn tcp_connect(&mut self, remote: SocketAddrV4) -> Result<SocketHandle, StackError> {
+        let ipv4 = self.ipv4.ok_or(StackError::NotConfigured)?;
+        if remote.ip != ipv4.address && !ipv4.contains(remote.ip) && ipv4.gateway.is_none() {
+            return Err(StackError::NoRoute);
+        }
+        let local = SocketAddrV4::new(ipv4.address, self.ephemeral_port(true)?);
+        let iss = self.random() as u32;
+        Ok(self.insert(Socket::Tcp {
+            socket: TcpSocket::connect(local, remote, iss),
+            released: false,
+        }))
+    }
+
+    /// Listen for connections on `port`.
+    pub fn tcp_listen(&mut self, port: u16) -> Result<SocketHandle, StackError> {
+        if port == 0 || self.port_in_use(port, true) {
+            return Err(StackError::AddressInUse);
+        }
+        Ok(self.insert(Socket::Listener(Listener {
+            port,
+            pending: Vec::new(),
+        })))
+    }
+
+    /// Take the oldest fully-open connection from a listener.
+    pub fn tcp_accept(
+        &mut self,
+        listener: SocketHandle,
+    ) -> Result<Option<(SocketHandle, SocketAddrV4)>, StackError> {
+        let Some(Socket::Listener(l)) = self.sockets.get(listener.0).and_then(Option::as_ref)
+        else {
+            return Err(StackError::InvalidSocket);
+        };
+        let ready = l.pending.iter().copied().find(|&handle| {
+            matches!(
+                self.sockets.get(handle.0),
+                Some(Some(Socket::Tcp { socket, .. }))
+                    if !matches!(socket.state(), TcpState::SynReceived | TcpState::Closed)
+            )
+        });
+        let Some(handle) = ready else {
+            return Ok(None);
+        };
+        if let Some(Socket::Listener(l)) = self.get_mut(listener) {
+            l.pending.retain(|&pending| pending != handle);
+        }
+        let remote = self.tcp(handle)?.remote;
+        Ok(Some((handle, remote)))
+    }
+
+    pub fn tcp_state(&mut self, handle: SocketHandle) -> Result<TcpState, StackError> {
+        Ok(self.tcp(handle)?.state())
+    }
+
+    pub fn tcp_error(&mut self, handle: SocketHandle) -> Result<Option<TcpError>, StackError> {
+        Ok(self.tcp(handle)?.error())
+    }
+
+    /// Free space in a connection's send buffer.
+    pub fn tcp_send_capacity(&mut self, handle: SocketHandle) -> Result<usize, StackError> {
+        Ok(self.tcp(handle)?.send_capacity())
+    }
+
+    /// Queue data on a connection. Returns how much fit, or `Closed` if the
+    /// connection can no longer send.
+    pub fn tcp_send(&mut self, handle: SocketHandle, data: &[u8]) -> Result<usize, StackError> {
+        let socket = self.tcp(handle)?;
+        if !socket.may_send() {
+            return Err(StackError::Closed);
+        }
+        Ok(socket.send(data))
+    }
+
+    /// Read received data. Returns 0 if nothing is waiting, or `Closed` once
+    /// the peer has finished sending and everything has been read.
+    pub fn tcp_recv(&mut self, handle: SocketHandle, buf: &mut [u8]) -> Result<usize, StackError> {
+        let socket = self.tcp(handle)?;
+        if !socket.may_recv() {
+            return Err(StackError::Closed);
+        }
+        Ok(socket.recv(buf))
+    }
+
+    // =========================================================================
+    // UDP
+    // =========================================================================
+
+    /// Bind a UDP socket to `port` (0 picks one). With `remote` set the
+    /// socket is connected: it only exchanges datagrams with that peer.
+    pub fn udp_bind(
+        &mut self,
+        port: u16,
+        remote: Option<SocketAddrV4>,
+    ) -> Result<SocketHandle, StackError> {
+        let port = match port {
+            0 => self.ephemeral_port(false)?,
+            port if self.port_in_use(port, false) => return Err(StackError::AddressInUse),
+            port => port,
+        };
+        Ok(self.insert(Socket::Udp(UdpSocket::new(port, remote))))
+    }
+
+    /// The port a UDP socket is bound to.
+    pub fn udp_port(&mut self, handle: SocketHandle) -> Result<u16, StackError> {
+        Ok(self.udp(handle)?.port)
+    }
+
+    /// Send a datagram, to `dst` or to a connected socket's peer.
+    pub fn udp_send(
+        &mut self,
+        handle: SocketHandle,
+        dst: Option<SocketAddrV4>,
+        data: &[u8],
+        now: Instant,
+    ) -> Result<(), StackError> {
+        let ipv4 = self.ipv4.ok_or(StackError::NotConfigured)?;
+        let socket = self.udp(handle)?;
+        let dst = dst.or(socket.remote).ok_or(StackError::NoRoute)?;
+        if data.len() > panda_abi::socket::MAX_DATAGRAM_SIZE {
+            return Err(StackError::TooLarge);
+        }
+        let segment = UdpHeader {
+            src_port: socket.port,
+            dst_port: dst.port,
+        }
+        .build(ipv4.address, dst.ip, data);
+        self.send_ipv4(dst.ip, wire::IP_PROTOCOL_UDP, &segment, now)
+    }
+
+    /// Take the oldest received datagram and its sender.
+    pub fn udp_recv(
+        &mut self,
+        handle: SocketHandle,
+    ) -> Result<Option<(SocketAddrV4, Vec<u8>)>, StackError> {
+        Ok(self.udp(handle)?.pop())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dhcp::tests::{MAC, OFFERED, SERVER, server_reply};
+
+    const PEER_MAC: MacAddr = [0x52, 0x55, 10, 0, 2, 2];
+    const ADDRESS: Ipv4Addr = Ipv4Addr::new(10, 0, 2, 15);
+    const PEER: Ipv4Addr = Ipv4Addr::new(10, 0, 2, 2);
+
+    fn static_config(address: Ipv4Addr) -> Config {
+        Config::Static(Ipv4Config {
+            address,
+            prefix_len: 24,
+            gateway: Some(PEER),
+            dns: None,
+        })
+    }
+
+    /// Two stacks on a wire: frames from each are delivered to the other.
+    fn run(a: &mut Stack, b: &mut Stack, now: Instant) {
+        for _ in 0..64 {
+            a.poll(now);
+            b.poll(now);
+            let from_a: Vec<_> = core::iter::from_fn(|| a.transmit()).collect();
+            let from_b: Vec<_> = core::iter::from_fn(|| b.transmit()).collect();
+            if from_a.is_empty() && from_b.is_empty() {
+                return;
+            }
+            for frame in from_a {
+                b.receive(&frame, now);
+            }
+            for frame in from_b {
+                a.receive(&frame, now);
+            }
+        }
+        panic!("stacks never went quiet");
+    }
+
+    fn pair() -> (Stack, Stack) {
+        (
+            Stack::new(MAC, static_config(ADDRESS), 1),
+            Stack::new(PEER_MAC, static_config(PEER), 2),
+        )
+    }
+
+    #[test]
+    fn tcp_across_the_wire() {
+        let (mut client, mut server) = pair();
+        let listener = server.tcp_listen(80).unwrap();
+        let conn = client.tcp_connect(SocketAddrV4::new(PEER, 80)).unwrap();
+        run(&mut client, &mut server, 0);
+        assert_eq!(client.tcp_state(conn), Ok(TcpState::Established));
+
+        let (accepted, from) = server.tcp_accept(listener).unwrap().unwrap();
+        assert_eq!(from.ip, ADDRESS);
+        assert_eq!(server.tcp_accept(listener), Ok(None));
+
+        client.tcp_send(conn, b"ping").unwrap();
+        run(&mut client, &mut server, 0);
+        let mut buf = [0u8; 16];
+        assert_eq!(server.tcp_recv(accepted, &mut buf), Ok(4));
+        assert_eq!(&buf[..4], b"ping");
+
+        server.close(accepted);
+        run(&mut client, &mut server, 0);
+        assert_eq!(client.tcp_recv(conn, &mut buf), Err(StackError::Closed));
+        client.close(conn);
+        run(&mut client, &mut server, 0);
+        // The server side finished LAST-ACK and was freed.
+        assert_eq!(server.tcp_state(accepted), Err(StackError::InvalidSocket));
+    }
+
+    #[test]
+    fn closed_port_refuses() {
+        let (mut client, mut server) = pair();
+        let conn = client.tcp_connect(SocketAddrV4::new(PEER, 81)).unwrap();
+        run(&mut client, &mut server, 0);
+        assert_eq!(client.tcp_state(conn), Ok(TcpState::Closed));
+        assert_eq!(client.tcp_error(conn), Ok(Some(TcpError::Refused)));
+    }
+
+    #[test]
+    fn loopback_connection_completes_in_one_poll() {
+        let mut stack = Stack::new(MAC, static_config(ADDRESS), 3);
+        let listener = stack.tcp_listen(8080).unwrap();
+        let conn = stack.tcp_connect(SocketAddrV4::new(ADDRESS, 8080)).unwrap();
+        stack.poll(0);
+        assert_eq!(stack.tcp_state(conn), Ok(TcpState::Established));
+        assert!(stack.transmit().is_none(), "loopback never reaches the NIC");
+
+        let (accepted, _) = stack.tcp_accept(listener).unwrap().unwrap();
+        stack.tcp_send(accepted, b"hi").unwrap();
+        stack.poll(0);
+        let mut buf = [0u8; 4];
+        assert_eq!(stack.tcp_recv(conn, &mut buf), Ok(2));
+    }
+
+    #[test]
+    fn udp_loopback_and_connected_filtering() {
+        let mut stack = Stack::new(MAC, static_config(ADDRESS), 4);
+        let server = stack.udp_bind(5000, None).unwrap();
+        assert_eq!(stack.udp_bind(5000, None), Err(StackError::AddressInUse));
+        let client = stack
+            .udp_bind(0, Some(SocketAddrV4::new(ADDRESS, 5000)))
+            .unwrap();
+        let client_port = stack.udp_port(client).unwrap();
+
+        stack.udp_send(client, None, b"query", 0).unwrap();
+        stack.poll(0);
+        let (from, data) = stack.udp_recv(server).unwrap().unwrap();
+        assert_eq!(from, SocketAddrV4::new(ADDRESS, client_port));
+        assert_eq!(data, b"query");
+
+        stack.udp_send(server, Some(from), b"answer", 0).unwrap();
+        stack.poll(0);
+        assert_eq!(stack.udp_recv(client).unwrap().unwrap().1, b"answer");
+
+        // A connected socket ignores other senders.
+        let stranger = stack.udp_bind(0, None).unwrap();
+        stack
+            .udp_send(
+                stranger,
+                Some(SocketAddrV4::new(ADDRESS, client_port)),
+                b"x",
+                0,
+            )
+            .unwrap();
+        stack.poll(0);
+        assert_eq!(stack.udp_recv(client), Ok(None));
+    }
+
+    #[test]
+    fn arp_resolution_holds_packets_until_answered() {
+        let (mut a, mut b) = pair();
+        let sa = a.udp_bind(0, None).unwrap();
+        let sb = b.udp_bind(53, None).unwrap();
+        a.udp_send(sa, Some(SocketAddrV4::new(PEER, 53)), b"held", 0)
+            .unwrap();
+
+        // Only the ARP request goes out at first.
+        let frame = a.transmit().unwrap();
+        let (eth, _) = EthernetHeader::parse(&frame).unwrap();
+        assert_eq!(eth.ethertype, wire::ETHERTYPE_ARP);
+        assert!(a.transmit().is_none());
+
+        b.receive(&frame, 0);
+        let reply = b.transmit().unwrap();
+        a.receive(&reply, 0);
+        let packet = a.transmit().expect("held packet released");
+        b.receive(&packet, 0);
+        assert_eq!(b.udp_recv(sb).unwrap().unwrap().1, b"held");
+    }
+
+    #[test]
+    fn off_subnet_traffic_goes_to_the_gateway() {
+        let mut a = Stack::new(MAC, static_config(ADDRESS), 1);
+        let sa = a.udp_bind(0, None).unwrap();
+        a.udp_send(
+            sa,
+            Some(SocketAddrV4::new(Ipv4Addr::new(8, 8, 8, 8), 53)),
+            b"",
+            0,
+        )
+        .unwrap();
+        let frame = a.transmit().unwrap();
+        let (_, arp) = EthernetHeader::parse(&frame).unwrap();
+        assert_eq!(ArpPacket::parse(arp).unwrap().target_ip, PEER);
+    }
+
+    #[test]
+    fn dhcp_configures_the_interface() {
+        let mut stack = Stack::new(MAC, Config::Dhcp, 5);
+        assert_eq!(stack.ipv4_config(), None);
+        assert_eq!(
+            stack.tcp_connect(SocketAddrV4::new(SERVER, 80)),
+            Err(StackError::NotConfigured)
+        );
+
+        // Play the server: answer each broadcast the client sends.
+        for message_type in [2, 5] {
+            stack.poll(0);
+            let frame = stack.transmit().expect("DHCP broadcast");
+            let (eth, packet) = EthernetHeader::parse(&frame).unwrap();
+            assert_eq!(eth.dst, wire::BROADCAST_MAC);
+            let (ip, segment) = Ipv4Header::parse(packet).unwrap();
+            let (_, request) = UdpHeader::parse(ip.src, ip.dst, segment).unwrap();
+
+            let reply = server_reply(request, message_type, 3600);
+            let udp = UdpHeader {
+                src_port: dhcp::SERVER_PORT,
+                dst_port: dhcp::CLIENT_PORT,
+            }
+            .build(SERVER, Ipv4Addr::BROADCAST, &reply);
+            let mut server = Stack::new(PEER_MAC, static_config(SERVER), 6);
+            let packet =
+                server.ipv4_packet(SERVER, Ipv4Addr::BROADCAST, wire::IP_PROTOCOL_UDP, &udp);
+            server.emit_frame(wire::BROADCAST_MAC, wire::ETHERTYPE_IPV4, &packet);
+            stack.receive(&server.transmit().unwrap(), 0);
+        }
+
+        let config = stack.ipv4_config().expect("leased");
+        assert_eq!(config.address, OFFERED);
+        assert_eq!(config.prefix_len, 24);
+        assert_eq!(config.gateway, Some(SERVER));
+    }
+
+    #[test]
+    fn listener_backlog_and_close() {
+        let (mut client, mut server) = pair();
+        let listener = server.tcp_listen(80).unwrap();
+        assert_eq!(server.tcp_listen(80), Err(StackError::AddressInUse));
+        let conn = client.tcp_connect(SocketAddrV4::new(PEER, 80)).unwrap();
+        run(&mut client, &mut server, 0);
+
+        // Closing the listener resets connections it never handed out.
+        server.close(listener);
+        run(&mut client, &mut server, 0);
+        assert_eq!(client.tcp_error(conn), Ok(Some(TcpError::Reset)));
+    }
+}
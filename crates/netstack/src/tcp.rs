@@ -0,0 +1,799 @@
+//! The TCP connection state machine (RFC 9293), pared down to what a
+//! host on a quiet LAN needs:
+//!
+//! - segments are only accepted in order; anything ahead of `rcv_nxt` is
+//!   dropped and re-acknowledged so the peer retransmits it
+//! - retransmission is go-back-N from the oldest unacknowledged byte, with
+//!   exponential backoff
+//! - no congestion control, Nagle, SACK, timestamps or window scaling
+//!
+//! A [`TcpSocket`] never touches the network itself: [`TcpSocket::process`]
+//! consumes incoming segments and [`TcpSocket::poll`] produces outgoing
+//! ones, which the stack wraps in IPv4.
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+use crate::wire::{self, TcpHeader, tcp_flags};
+use crate::{Instant, SocketAddrV4};
+
+/// Bytes buffered in each direction per connection. Also the largest
+/// window advertised.
+pub const BUFFER_SIZE: usize = 16 * 1024;
+
+/// Segment size offered to peers: the MTU less IPv4 and TCP headers.
+pub const LOCAL_MSS: u16 = (wire::MTU - wire::IPV4_HEADER_LEN - wire::TCP_HEADER_LEN) as u16;
+
+/// Segment size assumed when the peer's SYN carries no MSS option.
+const DEFAULT_MSS: usize = 536;
+
+const INITIAL_RTO_MS: u64 = 1_000;
+const MAX_RTO_MS: u64 = 60_000;
+/// Retransmissions of a SYN before the connection attempt fails.
+const MAX_SYN_RETRIES: u32 = 5;
+/// Retransmissions of data or a FIN before the connection is dropped.
+const MAX_RETRIES: u32 = 8;
+/// How long a connection lingers in TIME-WAIT. Far shorter than the
+/// RFC's 2×MSL, which assumes a WAN's worth of delayed segments.
+const TIME_WAIT_MS: u64 = 4_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    SynSent,
+    SynReceived,
+    Established,
+    FinWait1,
+    FinWait2,
+    CloseWait,
+    Closing,
+    LastAck,
+    TimeWait,
+    Closed,
+}
+
+/// Why a connection closed without the usual FIN exchange.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TcpError {
+    /// The peer refused the connection (RST in reply to our SYN).
+    Refused,
+    /// The peer reset an open connection.
+    Reset,
+    /// Retransmissions went unacknowledged.
+    TimedOut,
+}
+
+/// An outgoing segment. Ports are filled in; the stack adds addresses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub header: TcpHeader,
+    pub payload: Vec<u8>,
+}
+
+/// `a < b` in sequence space.
+fn seq_lt(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) < 0
+}
+
+/// `a <= b` in sequence space.
+fn seq_le(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) <= 0
+}
+
+pub struct TcpSocket {
+    pub(crate) local: SocketAddrV4,
+    pub(crate) remote: SocketAddrV4,
+    state: State,
+    error: Option<TcpError>,
+
+    /// Initial send sequence number; our SYN occupies it.
+    iss: u32,
+    /// Oldest unacknowledged sequence number. Once the SYN is acknowledged,
+    /// `tx[0]` is the byte at this sequence number.
+    snd_una: u32,
+    /// Next sequence number to send.
+    snd_nxt: u32,
+    /// The peer's advertised window.
+    snd_wnd: usize,
+    /// The peer's maximum segment size.
+    mss: usize,
+
+    /// The peer's initial sequence number.
+    irs: u32,
+    /// Next sequence number expected from the peer.
+    rcv_nxt: u32,
+
+    /// Unacknowledged and unsent data.
+    tx: VecDeque<u8>,
+    /// Received data the application hasn't read.
+    rx: VecDeque<u8>,
+
+    close_requested: bool,
+    fin_sent: bool,
+    fin_acked: bool,
+    fin_received: bool,
+    ack_pending: bool,
+
+    rto: u64,
+    retransmit_at: Option<Instant>,
+    retries: u32,
+    time_wait_until: Instant,
+}
+
+impl TcpSocket {
+    fn new(local: SocketAddrV4, remote: SocketAddrV4, iss: u32, state: State) -> Self {
+        Self {
+            local,
+            remote,
+            state,
+            error: None,
+            iss,
+            snd_una: iss,
+            snd_nxt: iss,
+            snd_wnd: 0,
+            mss: DEFAULT_MSS,
+            irs: 0,
+            rcv_nxt: 0,
+            tx: VecDeque::new(),
+            rx: VecDeque::new(),
+            close_requested: false,
+            fin_sent: false,
+            fin_acked: false,
+            fin_received: false,
+            ack_pending: false,
+            rto: INITIAL_RTO_MS,
+            retransmit_at: None,
+            retries: 0,
+            time_wait_until: 0,
+        }
+    }
+
+    /// An active open: the SYN goes out on the next poll.
+    pub(crate) fn connect(local: SocketAddrV4, remote: SocketAddrV4, iss: u32) -> Self {
+        Self::new(local, remote, iss, State::SynSent)
+    }
+
+    /// A passive open in response to `syn`: the SYN-ACK goes out on the
+    /// next poll.
+    pub(crate) fn accept(
+        local: SocketAddrV4,
+        remote: SocketAddrV4,
+        syn: &TcpHeader,
+        iss: u32,
+    ) -> Self {
+        let mut socket = Self::new(local, remote, iss, State::SynReceived);
+        socket.irs = syn.seq;
+        socket.rcv_nxt = syn.seq.wrapping_add(1);
+        socket.snd_wnd = syn.window as usize;
+        socket.mss = syn
+            .mss
+            .map_or(DEFAULT_MSS, |mss| mss.min(LOCAL_MSS) as usize);
+        socket
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Why the connection closed abnormally, if it did.
+    pub fn error(&self) -> Option<TcpError> {
+        self.error
+    }
+
+    /// Whether [`send`](Self::send) can still accept data.
+    pub fn may_send(&self) -> bool {
+        !self.close_requested
+            && matches!(
+                self.state,
+                State::SynSent | State::SynReceived | State::Established | State::CloseWait
+            )
+    }
+
+    /// Whether more data may still arrive or is waiting to be read. False
+    /// once the peer's FIN has been read past, or the connection is dead.
+    pub fn may_recv(&self) -> bool {
+        !self.rx.is_empty() || (!self.fin_received && self.state != State::Closed)
+    }
+
+    /// Free space in the send buffer.
+    pub fn send_capacity(&self) -> usize {
+        BUFFER_SIZE - self.tx.len()
+    }
+
+    /// Queue data for transmission. Returns how much fit.
+    pub fn send(&mut self, data: &[u8]) -> usize {
+        if !self.may_send() {
+            return 0;
+        }
+        let n = data.len().min(self.send_capacity());
+        self.tx.extend(&data[..n]);
+        n
+    }
+
+    /// Take received data. Returns 0 when nothing is waiting.
+    pub fn recv(&mut self, buf: &mut [u8]) -> usize {
+        let window_before = self.window();
+        let n = buf.len().min(self.rx.len());
+        for (dst, src) in buf.iter_mut().zip(self.rx.drain(..n)) {
+            *dst = src;
+        }
+        // Tell a peer that was stalled on a small window that it can go on.
+        if window_before < self.mss && self.window() >= self.mss {
+            self.ack_pending = true;
+        }
+        n
+    }
+
+    /// Close our direction once queued data is sent. Reading continues
+    /// until the peer closes too.
+    pub fn close(&mut self) {
+        match self.state {
+            // Nothing has been agreed with the peer yet.
+            State::SynSent => self.state = State::Closed,
+            _ => self.close_requested = true,
+        }
+    }
+
+    /// Drop the connection at once, returning the RST to send if the peer
+    /// knows about it.
+    pub fn abort(&mut self) -> Option<Segment> {
+        let rst = match self.state {
+            State::SynSent | State::TimeWait | State::Closed => None,
+            _ => Some(self.segment(tcp_flags::RST | tcp_flags::ACK, self.snd_nxt, Vec::new())),
+        };
+        self.state = State::Closed;
+        rst
+    }
+
+    fn window(&self) -> usize {
+        BUFFER_SIZE - self.rx.len()
+    }
+
+    fn segment(&self, flags: u8, seq: u32, payload: Vec<u8>) -> Segment {
+        Segment {
+            header: TcpHeader {
+                src_port: self.local.port,
+                dst_port: self.remote.port,
+                seq,
+                ack: if flags & tcp_flags::ACK != 0 {
+                    self.rcv_nxt
+                } else {
+                    0
+                },
+                flags,
+                window: self.window().min(u16::MAX as usize) as u16,
+                mss: (flags & tcp_flags::SYN != 0).then_some(LOCAL_MSS),
+            },
+            payload,
+        }
+    }
+
+    fn arm_retransmit(&mut self, now: Instant) {
+        if self.retransmit_at.is_none() {
+            self.retransmit_at = Some(now + self.rto);
+        }
+    }
+
+    fn fail(&mut self, error: TcpError) {
+        self.state = State::Closed;
+        self.error = Some(error);
+    }
+
+    /// Handle an incoming segment addressed to this connection.
+    pub(crate) fn process(&mut self, seg: &TcpHeader, payload: &[u8], now: Instant) {
+        match self.state {
+            State::Closed => return,
+            State::SynSent => return self.process_syn_sent(seg),
+            _ => {}
+        }
+
+        if seg.has(tcp_flags::RST) {
+            // Only an in-window reset is believed.
+            if seg.seq == self.rcv_nxt {
+                self.fail(TcpError::Reset);
+            }
+            return;
+        }
+
+        if seg.has(tcp_flags::SYN) {
+            if self.state == State::SynReceived && seg.seq == self.irs {
+                // Our SYN-ACK was lost: send it again.
+                self.snd_nxt = self.snd_una;
+                self.retransmit_at = None;
+            } else {
+                self.ack_pending = true;
+            }
+            return;
+        }
+
+        // Trim anything we've already received. Segments that start beyond
+        // `rcv_nxt` are dropped; the duplicate ACK asks for what's missing.
+        let mut payload = payload;
+        let mut fin = seg.has(tcp_flags::FIN);
+        if seq_lt(self.rcv_nxt, seg.seq) {
+            self.ack_pending = true;
+            return;
+        }
+        let skip = self.rcv_nxt.wrapping_sub(seg.seq) as usize;
+        if skip > 0 {
+            self.ack_pending = true;
+            fin &= skip <= payload.len();
+            payload = &payload[skip.min(payload.len())..];
+        }
+
+        if !seg.has(tcp_flags::ACK) {
+            return;
+        }
+        if self.state == State::SynReceived {
+            if !(seq_lt(self.snd_una, seg.ack) && seq_le(seg.ack, self.snd_nxt)) {
+                return;
+            }
+            self.state = State::Established;
+            // The SYN is acknowledged.
+            self.snd_una = self.snd_una.wrapping_add(1);
+        }
+        self.process_ack(seg, now);
+        if self.state == State::Closed {
+            return;
+        }
+
+        if !payload.is_empty()
+            && matches!(
+                self.state,
+                State::Established | State::FinWait1 | State::FinWait2
+            )
+        {
+            let taken = payload.len().min(self.window());
+            self.rx.extend(&payload[..taken]);
+            self.rcv_nxt = self.rcv_nxt.wrapping_add(taken as u32);
+            self.ack_pending = true;
+            // A FIN past data we couldn't take is not in order yet.
+            fin &= taken == payload.len();
+        }
+
+        if fin && !self.fin_received {
+            self.fin_received = true;
+            self.rcv_nxt = self.rcv_nxt.wrapping_add(1);
+            self.ack_pending = true;
+            match self.state {
+                State::Established => self.state = State::CloseWait,
+                State::FinWait1 => self.state = State::Closing,
+                State::FinWait2 => self.enter_time_wait(now),
+                _ => {}
+            }
+        }
+    }
+
+    fn process_syn_sent(&mut self, seg: &TcpHeader) {
+        let ack_ok = seg.has(tcp_flags::ACK) && seg.ack == self.iss.wrapping_add(1);
+        if seg.has(tcp_flags::RST) {
+            if ack_ok {
+                self.fail(TcpError::Refused);
+            }
+            return;
+        }
+        // Simultaneous open (a bare SYN) isn't supported.
+        if !seg.has(tcp_flags::SYN) || !ack_ok {
+            return;
+        }
+        self.irs = seg.seq;
+        self.rcv_nxt = seg.seq.wrapping_add(1);
+        self.snd_una = seg.ack;
+        self.snd_wnd = seg.window as usize;
+        self.mss = seg
+            .mss
+            .map_or(DEFAULT_MSS, |mss| mss.min(LOCAL_MSS) as usize);
+        self.retransmit_at = None;
+        self.retries = 0;
+        self.ack_pending = true;
+        self.state = State::Established;
+    }
+
+    fn process_ack(&mut self, seg: &TcpHeader, now: Instant) {
+        if seq_lt(self.snd_nxt, seg.ack) {
+            // Acknowledges something we haven't sent.
+            self.ack_pending = true;
+            return;
+        }
+        if seq_le(seg.ack, self.snd_una) {
+            // A duplicate; only the window may have changed.
+            if seg.ack == self.snd_una {
+                self.snd_wnd = seg.window as usize;
+            }
+            return;
+        }
+
+        let mut acked = seg.ack.wrapping_sub(self.snd_una) as usize;
+        let data = acked.min(self.tx.len());
+        self.tx.drain(..data);
+        acked -= data;
+        if acked > 0 && self.fin_sent {
+            self.fin_acked = true;
+        }
+        self.snd_una = seg.ack;
+        self.snd_wnd = seg.window as usize;
+        self.rto = INITIAL_RTO_MS;
+        self.retries = 0;
+        self.retransmit_at = (self.snd_una != self.snd_nxt).then_some(now + self.rto);
+
+        if self.fin_acked {
+            match self.state {
+                State::FinWait1 => self.state = State::FinWait2,
+                State::Closing => self.enter_time_wait(now),
+                State::LastAck => self.state = State::Closed,
+                _ => {}
+            }
+        }
+    }
+
+    fn enter_time_wait(&mut self, now: Instant) {
+        self.state = State::TimeWait;
+        self.time_wait_until = now + TIME_WAIT_MS;
+        self.retransmit_at = None;
+    }
+
+    /// Run timers and append whatever should be sent now to `out`.
+    pub(crate) fn poll(&mut self, now: Instant, out: &mut Vec<Segment>) {
+        match self.state {
+            State::Closed => return,
+            State::TimeWait if now >= self.time_wait_until => {
+                self.state = State::Closed;
+                return;
+            }
+            _ => {}
+        }
+
+        if let Some(at) = self.retransmit_at
+            && now >= at
+        {
+            self.retries += 1;
+            let limit = match self.state {
+                State::SynSent | State::SynReceived => MAX_SYN_RETRIES,
+                _ => MAX_RETRIES,
+            };
+            if self.retries > limit {
+                self.fail(TcpError::TimedOut);
+                return;
+            }
+            self.rto = (self.rto * 2).min(MAX_RTO_MS);
+            self.retransmit_at = None;
+            // Go back to the oldest unacknowledged byte.
+            self.snd_nxt = self.snd_una;
+            if !self.fin_acked {
+                self.fin_sent = false;
+            }
+        }
+
+        match self.state {
+            State::SynSent | State::SynReceived => {
+                if self.snd_nxt == self.iss {
+                    let flags = if self.state == State::SynSent {
+                        tcp_flags::SYN
+                    } else {
+                        tcp_flags::SYN | tcp_flags::ACK
+                    };
+                    out.push(self.segment(flags, self.iss, Vec::new()));
+                    self.snd_nxt = self.iss.wrapping_add(1);
+                    self.ack_pending = false;
+                    self.arm_retransmit(now);
+                }
+            }
+            State::Established
+            | State::CloseWait
+            | State::FinWait1
+            | State::Closing
+            | State::LastAck => self.send_data(now, out),
+            State::FinWait2 | State::TimeWait | State::Closed => {}
+        }
+
+        if self.ack_pending {
+            out.push(self.segment(tcp_flags::ACK, self.snd_nxt, Vec::new()));
+            self.ack_pending = false;
+        }
+    }
+
+    fn send_data(&mut self, now: Instant, out: &mut Vec<Segment>) {
+        while !self.fin_sent {
+            let in_flight = self.snd_nxt.wrapping_sub(self.snd_una) as usize;
+            let unsent = self.tx.len().saturating_sub(in_flight);
+            if unsent == 0 {
+                break;
+            }
+            // A closed window is probed a byte at a time.
+            let room = if self.snd_wnd == 0 && in_flight == 0 {
+                1
+            } else {
+                self.snd_wnd.saturating_sub(in_flight)
+            };
+            let n = unsent.min(room).min(self.mss);
+            if n == 0 {
+                break;
+            }
+            let payload = self.tx.range(in_flight..in_flight + n).copied().collect();
+            out.push(self.segment(tcp_flags::ACK | tcp_flags::PSH, self.snd_nxt, payload));
+            self.snd_nxt = self.snd_nxt.wrapping_add(n as u32);
+            self.ack_pending = false;
+            self.arm_retransmit(now);
+        }
+
+        let all_sent = self.snd_nxt.wrapping_sub(self.snd_una) as usize >= self.tx.len();
+        if self.close_requested && !self.fin_sent && !self.fin_acked && all_sent {
+            out.push(self.segment(tcp_flags::FIN | tcp_flags::ACK, self.snd_nxt, Vec::new()));
+            self.snd_nxt = self.snd_nxt.wrapping_add(1);
+            self.fin_sent = true;
+            self.ack_pending = false;
+            self.arm_retransmit(now);
+            match self.state {
+                State::Established => self.state = State::FinWait1,
+                State::CloseWait => self.state = State::LastAck,
+                _ => {}
+            }
+        }
+    }
+}
+
+/// The reset sent in reply to a segment no connection wants (RFC 9293
+/// §3.10.7.1). Never sent in reply to a reset.
+pub(crate) fn reset_for(seg: &TcpHeader, payload_len: usize) -> Option<TcpHeader> {
+    if seg.has(tcp_flags::RST) {
+        return None;
+    }
+    let (seq, ack, flags) = if seg.has(tcp_flags::ACK) {
+        (seg.ack, 0, tcp_flags::RST)
+    } else {
+        let len =
+            payload_len as u32 + seg.has(tcp_flags::SYN) as u32 + seg.has(tcp_flags::FIN) as u32;
+        (
+            0,
+            seg.seq.wrapping_add(len),
+            tcp_flags::RST | tcp_flags::ACK,
+        )
+    };
+    Some(TcpHeader {
+        src_port: seg.dst_port,
+        dst_port: seg.src_port,
+        seq,
+        ack,
+        flags,
+        window: 0,
+        mss: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Ipv4Addr;
+
+    const CLIENT: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(10, 0, 2, 15), 49152);
+    const SERVER: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(10, 0, 2, 2), 80);
+
+    /// Deliver everything each side has to send until both go quiet.
+    fn exchange(a: &mut TcpSocket, b: &mut TcpSocket, now: Instant) {
+        for _ in 0..64 {
+            let mut from_a = Vec::new();
+            let mut from_b = Vec::new();
+            a.poll(now, &mut from_a);
+            b.poll(now, &mut from_b);
+            if from_a.is_empty() && from_b.is_empty() {
+                return;
+            }
+            for seg in from_a {
+                b.process(&seg.header, &seg.payload, now);
+            }
+            for seg in from_b {
+                a.process(&seg.header, &seg.payload, now);
+            }
+        }
+        panic!("sockets never went quiet");
+    }
+
+    fn handshake() -> (TcpSocket, TcpSocket) {
+        let mut client = TcpSocket::connect(CLIENT, SERVER, 1000);
+        let mut out = Vec::new();
+        client.poll(0, &mut out);
+        let syn = out.pop().unwrap();
+        assert_eq!(syn.header.flags, tcp_flags::SYN);
+        assert_eq!(syn.header.mss, Some(LOCAL_MSS));
+
+        let mut server = TcpSocket::accept(SERVER, CLIENT, &syn.header, 5000);
+        exchange(&mut client, &mut server, 0);
+        assert_eq!(client.state(), State::Established);
+        assert_eq!(server.state(), State::Established);
+        (client, server)
+    }
+
+    #[test]
+    fn three_way_handshake() {
+        handshake();
+    }
+
+    #[test]
+    fn jumbo_mss_is_clamped_to_local() {
+        let mut client = TcpSocket::connect(CLIENT, SERVER, 1000);
+        let mut out = Vec::new();
+        client.poll(0, &mut out);
+        let mut syn = out.pop().unwrap();
+        syn.header.mss = Some(9000);
+
+        let mut server = TcpSocket::accept(SERVER, CLIENT, &syn.header, 5000);
+        server.poll(0, &mut out);
+        let mut syn_ack = out.pop().unwrap();
+        syn_ack.header.mss = Some(9000);
+        client.process(&syn_ack.header, &syn_ack.payload, 0);
+        exchange(&mut client, &mut server, 0);
+        assert_eq!(client.state(), State::Established);
+        assert_eq!(server.state(), State::Established);
+
+        // Neither side sends a segment larger than its own MTU allows.
+        let data = [0u8; 4 * LOCAL_MSS as usize];
+        client.send(&data);
+        server.send(&data);
+        client.poll(0, &mut out);
+        server.poll(0, &mut out);
+        assert!(!out.is_empty());
+        for seg in &out {
+            assert!(seg.payload.len() <= LOCAL_MSS as usize);
+        }
+    }
+
+    #[test]
+    fn data_flows_both_ways() {
+        let (mut client, mut server) = handshake();
+        assert_eq!(client.send(b"GET / HTTP/1.0\r\n\r\n"), 18);
+        exchange(&mut client, &mut server, 0);
+        let mut buf = [0u8; 64];
+        assert_eq!(server.recv(&mut buf), 18);
+        assert_eq!(&buf[..18], b"GET / HTTP/1.0\r\n\r\n");
+
+        server.send(b"hello");
+        exchange(&mut client, &mut server, 0);
+        assert_eq!(client.recv(&mut buf), 5);
+        assert_eq!(&buf[..5], b"hello");
+    }
+
+    #[test]
+    fn large_transfer_is_segmented_and_windowed() {
+        let (mut client, mut server) = handshake();
+        let data: Vec<u8> = (0..BUFFER_SIZE * 2).map(|i| i as u8).collect();
+        let mut sent = 0;
+        let mut received = Vec::new();
+        let mut buf = [0u8; 4096];
+        while received.len() < data.len() {
+            sent += client.send(&data[sent..]);
+            exchange(&mut client, &mut server, 0);
+            loop {
+                let n = server.recv(&mut buf);
+                if n == 0 {
+                    break;
+                }
+                received.extend_from_slice(&buf[..n]);
+            }
+        }
+        assert_eq!(received, data);
+    }
+
+    #[test]
+    fn lost_segment_is_retransmitted() {
+        let (mut client, mut server) = handshake();
+        client.send(b"lost");
+        let mut out = Vec::new();
+        client.poll(0, &mut out);
+        assert_eq!(out.len(), 1, "the data segment, dropped on the floor");
+
+        // Nothing more until the retransmission timer fires.
+        out.clear();
+        client.poll(INITIAL_RTO_MS - 1, &mut out);
+        assert!(out.is_empty());
+        client.poll(INITIAL_RTO_MS, &mut out);
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].payload, b"lost");
+
+        server.process(&out[0].header, &out[0].payload, INITIAL_RTO_MS);
+        let mut buf = [0u8; 8];
+        assert_eq!(server.recv(&mut buf), 4);
+    }
+
+    #[test]
+    fn out_of_order_segment_is_dropped_and_reacked() {
+        let (mut client, mut server) = handshake();
+        client.send(b"first");
+        let mut out = Vec::new();
+        client.poll(0, &mut out);
+        client.send(b"second");
+        client.poll(0, &mut out);
+        assert_eq!(out.len(), 2);
+
+        // The second arrives first and is refused.
+        server.process(&out[1].header, &out[1].payload, 0);
+        let mut buf = [0u8; 16];
+        assert_eq!(server.recv(&mut buf), 0);
+        let mut acks = Vec::new();
+        server.poll(0, &mut acks);
+        assert_eq!(acks[0].header.ack, out[0].header.seq, "duplicate ACK");
+
+        server.process(&out[0].header, &out[0].payload, 0);
+        assert_eq!(server.recv(&mut buf), 5);
+    }
+
+    #[test]
+    fn orderly_close_from_client() {
+        let (mut client, mut server) = handshake();
+        client.send(b"bye");
+        client.close();
+        exchange(&mut client, &mut server, 0);
+        assert_eq!(client.state(), State::FinWait2);
+        assert_eq!(server.state(), State::CloseWait);
+
+        // The server reads to end of stream, then closes its side.
+        let mut buf = [0u8; 8];
+        assert_eq!(server.recv(&mut buf), 3);
+        assert!(!server.may_recv());
+        server.close();
+        exchange(&mut client, &mut server, 0);
+        assert_eq!(server.state(), State::Closed);
+        assert_eq!(client.state(), State::TimeWait);
+
+        let mut out = Vec::new();
+        client.poll(TIME_WAIT_MS, &mut out);
+        assert_eq!(client.state(), State::Closed);
+        assert_eq!(client.error(), None);
+    }
+
+    #[test]
+    fn simultaneous_close() {
+        let (mut client, mut server) = handshake();
+        client.close();
+        server.close();
+        exchange(&mut client, &mut server, 0);
+        assert_eq!(client.state(), State::TimeWait);
+        assert_eq!(server.state(), State::TimeWait);
+    }
+
+    #[test]
+    fn reset_closes_with_error() {
+        let (mut client, mut server) = handshake();
+        let rst = server.abort().unwrap();
+        client.process(&rst.header, &rst.payload, 0);
+        assert_eq!(client.state(), State::Closed);
+        assert_eq!(client.error(), Some(TcpError::Reset));
+        assert!(!client.may_recv() && !client.may_send());
+    }
+
+    #[test]
+    fn refused_connection() {
+        let mut client = TcpSocket::connect(CLIENT, SERVER, 1000);
+        let mut out = Vec::new();
+        client.poll(0, &mut out);
+        let rst = reset_for(&out[0].header, 0).unwrap();
+        client.process(&rst, &[], 0);
+        assert_eq!(client.error(), Some(TcpError::Refused));
+    }
+
+    #[test]
+    fn unanswered_syn_times_out() {
+        let mut client = TcpSocket::connect(CLIENT, SERVER, 1000);
+        let mut now = 0;
+        let mut syns = 0;
+        while client.state() != State::Closed {
+            let mut out = Vec::new();
+            client.poll(now, &mut out);
+            syns += out.len();
+            now += 1_000;
+        }
+        assert_eq!(syns as u32, 1 + MAX_SYN_RETRIES);
+        assert_eq!(client.error(), Some(TcpError::TimedOut));
+    }
+
+    #[test]
+    fn sequence_numbers_wrap() {
+        let mut client = TcpSocket::connect(CLIENT, SERVER, u32::MAX - 1);
+        let mut out = Vec::new();
+        client.poll(0, &mut out);
+        let mut server = TcpSocket::accept(SERVER, CLIENT, &out[0].header, u32::MAX);
+        exchange(&mut client, &mut server, 0);
+        client.send(b"wrapped");
+        exchange(&mut client, &mut server, 0);
+        let mut buf = [0u8; 8];
+        assert_eq!(server.recv(&mut buf), 7);
+    }
+}
@@ -0,0 +1,42 @@
+//! UDP sockets: a bound port and a queue of received datagrams.
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+use crate::SocketAddrV4;
+
+/// Datagrams held for a socket before further arrivals are dropped.
+const RX_QUEUE_LEN: usize = 32;
+
+pub(crate) struct UdpSocket {
+    pub(crate) port: u16,
+    /// Set for a connected socket: the only peer it sends to and accepts
+    /// datagrams from.
+    pub(crate) remote: Option<SocketAddrV4>,
+    rx: VecDeque<(SocketAddrV4, Vec<u8>)>,
+}
+
+impl UdpSocket {
+    pub(crate) fn new(port: u16, remote: Option<SocketAddrV4>) -> Self {
+        Self {
+            port,
+            remote,
+            rx: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn accepts(&self, src: SocketAddrV4) -> bool {
+        self.remote.is_none_or(|remote| remote == src)
+    }
+
+    /// Queue a datagram, dropping it if the queue is full.
+    pub(crate) fn push(&mut self, src: SocketAddrV4, data: &[u8]) {
+        if self.rx.len() < RX_QUEUE_LEN {
+            self.rx.push_back((src, data.to_vec()));
+        }
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<(SocketAddrV4, Vec<u8>)> {
+        self.rx.pop_front()
+    }
+}
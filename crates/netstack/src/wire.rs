@@ -0,0 +1,484 @@
+//! Parsing and emitting the headers the stack speaks: Ethernet II, ARP,
+//! IPv4, UDP and TCP.
+//!
+//! Parsers take the bytes of one layer and return the header plus the
+//! payload it encloses, rejecting anything truncated or with a bad
+//! checksum. Emitters write into a caller-sized buffer; `*_LEN` constants
+//! give the sizes.
+
+use alloc::vec::Vec;
+
+use crate::Ipv4Addr;
+
+pub type MacAddr = [u8; 6];
+
+pub const BROADCAST_MAC: MacAddr = [0xff; 6];
+
+pub const ETHERNET_HEADER_LEN: usize = 14;
+pub const ETHERTYPE_IPV4: u16 = 0x0800;
+pub const ETHERTYPE_ARP: u16 = 0x0806;
+
+pub const ARP_PACKET_LEN: usize = 28;
+pub const ARP_REQUEST: u16 = 1;
+pub const ARP_REPLY: u16 = 2;
+
+pub const IPV4_HEADER_LEN: usize = 20;
+pub const IP_PROTOCOL_TCP: u8 = 6;
+pub const IP_PROTOCOL_UDP: u8 = 17;
+
+/// Largest IPv4 packet carried in one ethernet frame.
+pub const MTU: usize = 1500;
+
+pub const UDP_HEADER_LEN: usize = 8;
+
+/// TCP header without options.
+pub const TCP_HEADER_LEN: usize = 20;
+/// The only option the stack sends: maximum segment size, on SYNs.
+const TCP_MSS_OPTION_LEN: usize = 4;
+
+/// Internet checksum (RFC 1071) of `data`, continuing from a partial `sum`.
+fn checksum_add(mut sum: u32, data: &[u8]) -> u32 {
+    let mut chunks = data.chunks_exact(2);
+    for pair in &mut chunks {
+        sum += u16::from_be_bytes([pair[0], pair[1]]) as u32;
+    }
+    if let [last] = chunks.remainder() {
+        sum += (*last as u32) << 8;
+    }
+    sum
+}
+
+fn checksum_fold(mut sum: u32) -> u16 {
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// Checksum of `data` alone, as used by the IPv4 header.
+pub fn checksum(data: &[u8]) -> u16 {
+    checksum_fold(checksum_add(0, data))
+}
+
+/// Checksum of a TCP or UDP segment including the IPv4 pseudo-header.
+fn transport_checksum(src: Ipv4Addr, dst: Ipv4Addr, protocol: u8, segment: &[u8]) -> u16 {
+    let mut sum = checksum_add(0, &src.0);
+    sum = checksum_add(sum, &dst.0);
+    sum += protocol as u32;
+    sum += segment.len() as u32;
+    checksum_fold(checksum_add(sum, segment))
+}
+
+fn be16(buf: &[u8], at: usize) -> u16 {
+    u16::from_be_bytes([buf[at], buf[at + 1]])
+}
+
+fn be32(buf: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes([buf[at], buf[at + 1], buf[at + 2], buf[at + 3]])
+}
+
+fn ip_at(buf: &[u8], at: usize) -> Ipv4Addr {
+    Ipv4Addr([buf[at], buf[at + 1], buf[at + 2], buf[at + 3]])
+}
+
+fn mac_at(buf: &[u8], at: usize) -> MacAddr {
+    let mut mac = [0; 6];
+    mac.copy_from_slice(&buf[at..at + 6]);
+    mac
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EthernetHeader {
+    pub dst: MacAddr,
+    pub src: MacAddr,
+    pub ethertype: u16,
+}
+
+impl EthernetHeader {
+    pub fn parse(frame: &[u8]) -> Option<(Self, &[u8])> {
+        if frame.len() < ETHERNET_HEADER_LEN {
+            return None;
+        }
+        let header = Self {
+            dst: mac_at(frame, 0),
+            src: mac_at(frame, 6),
+            ethertype: be16(frame, 12),
+        };
+        Some((header, &frame[ETHERNET_HEADER_LEN..]))
+    }
+
+    pub fn emit(&self, buf: &mut [u8]) {
+        buf[0..6].copy_from_slice(&self.dst);
+        buf[6..12].copy_from_slice(&self.src);
+        buf[12..14].copy_from_slice(&self.ethertype.to_be_bytes());
+    }
+}
+
+/// An ARP packet for IPv4 over ethernet; other hardware or protocol types
+/// are rejected by [`ArpPacket::parse`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArpPacket {
+    pub op: u16,
+    pub sender_mac: MacAddr,
+    pub sender_ip: Ipv4Addr,
+    pub target_mac: MacAddr,
+    pub target_ip: Ipv4Addr,
+}
+
+impl ArpPacket {
+    pub fn parse(buf: &[u8]) -> Option<Self> {
+        if buf.len() < ARP_PACKET_LEN
+            || be16(buf, 0) != 1
+            || be16(buf, 2) != ETHERTYPE_IPV4
+            || buf[4] != 6
+            || buf[5] != 4
+        {
+            return None;
+        }
+        Some(Self {
+            op: be16(buf, 6),
+            sender_mac: mac_at(buf, 8),
+            sender_ip: ip_at(buf, 14),
+            target_mac: mac_at(buf, 18),
+            target_ip: ip_at(buf, 24),
+        })
+    }
+
+    pub fn emit(&self, buf: &mut [u8]) {
+        buf[0..2].copy_from_slice(&1u16.to_be_bytes());
+        buf[2..4].copy_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+        buf[4] = 6;
+        buf[5] = 4;
+        buf[6..8].copy_from_slice(&self.op.to_be_bytes());
+        buf[8..14].copy_from_slice(&self.sender_mac);
+        buf[14..18].copy_from_slice(&self.sender_ip.0);
+        buf[18..24].copy_from_slice(&self.target_mac);
+        buf[24..28].copy_from_slice(&self.target_ip.0);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ipv4Header {
+    pub src: Ipv4Addr,
+    pub dst: Ipv4Addr,
+    pub protocol: u8,
+    pub ttl: u8,
+    pub ident: u16,
+}
+
+impl Ipv4Header {
+    /// Parse a packet, returning its payload trimmed to the header's total
+    /// length. Fragments are rejected: nothing the stack talks to needs
+    /// them at a 1500-byte MTU.
+    pub fn parse(packet: &[u8]) -> Option<(Self, &[u8])> {
+        if packet.len() < IPV4_HEADER_LEN || packet[0] >> 4 != 4 {
+            return None;
+        }
+        let header_len = ((packet[0] & 0xf) as usize) * 4;
+        let total_len = be16(packet, 2) as usize;
+        if header_len < IPV4_HEADER_LEN || total_len < header_len || total_len > packet.len() {
+            return None;
+        }
+        if checksum(&packet[..header_len]) != 0 {
+            return None;
+        }
+        let flags_fragment = be16(packet, 6);
+        let more_fragments = flags_fragment & 0x2000 != 0;
+        if more_fragments || flags_fragment & 0x1fff != 0 {
+            return None;
+        }
+        let header = Self {
+            src: ip_at(packet, 12),
+            dst: ip_at(packet, 16),
+            protocol: packet[9],
+            ttl: packet[8],
+            ident: be16(packet, 4),
+        };
+        Some((header, &packet[header_len..total_len]))
+    }
+
+    /// Write a 20-byte header for a packet carrying `payload_len` bytes.
+    pub fn emit(&self, payload_len: usize, buf: &mut [u8]) {
+        let total_len = (IPV4_HEADER_LEN + payload_len) as u16;
+        buf[0] = 0x45;
+        buf[1] = 0;
+        buf[2..4].copy_from_slice(&total_len.to_be_bytes());
+        buf[4..6].copy_from_slice(&self.ident.to_be_bytes());
+        // Don't fragment.
+        buf[6..8].copy_from_slice(&0x4000u16.to_be_bytes());
+        buf[8] = self.ttl;
+        buf[9] = self.protocol;
+        buf[10..12].copy_from_slice(&[0, 0]);
+        buf[12..16].copy_from_slice(&self.src.0);
+        buf[16..20].copy_from_slice(&self.dst.0);
+        let sum = checksum(&buf[..IPV4_HEADER_LEN]);
+        buf[10..12].copy_from_slice(&sum.to_be_bytes());
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UdpHeader {
+    pub src_port: u16,
+    pub dst_port: u16,
+}
+
+impl UdpHeader {
+    /// Parse a datagram carried between `src` and `dst`, verifying its
+    /// checksum unless the sender left it zero.
+    pub fn parse(src: Ipv4Addr, dst: Ipv4Addr, segment: &[u8]) -> Option<(Self, &[u8])> {
+        if segment.len() < UDP_HEADER_LEN {
+            return None;
+        }
+        let len = be16(segment, 4) as usize;
+        if len < UDP_HEADER_LEN || len > segment.len() {
+            return None;
+        }
+        let segment = &segment[..len];
+        if be16(segment, 6) != 0 && transport_checksum(src, dst, IP_PROTOCOL_UDP, segment) != 0 {
+            return None;
+        }
+        let header = Self {
+            src_port: be16(segment, 0),
+            dst_port: be16(segment, 2),
+        };
+        Some((header, &segment[UDP_HEADER_LEN..]))
+    }
+
+    /// Build a complete datagram: header, checksum and payload.
+    pub fn build(&self, src: Ipv4Addr, dst: Ipv4Addr, payload: &[u8]) -> Vec<u8> {
+        let len = UDP_HEADER_LEN + payload.len();
+        let mut segment = Vec::with_capacity(len);
+        segment.extend_from_slice(&self.src_port.to_be_bytes());
+        segment.extend_from_slice(&self.dst_port.to_be_bytes());
+        segment.extend_from_slice(&(len as u16).to_be_bytes());
+        segment.extend_from_slice(&[0, 0]);
+        segment.extend_from_slice(payload);
+        let sum = match transport_checksum(src, dst, IP_PROTOCOL_UDP, &segment) {
+            // Zero means "no checksum"; its ones-complement twin stands in.
+            0 => 0xffff,
+            sum => sum,
+        };
+        segment[6..8].copy_from_slice(&sum.to_be_bytes());
+        segment
+    }
+}
+
+/// TCP header flag bits.
+pub mod tcp_flags {
+    pub const FIN: u8 = 0x01;
+    pub const SYN: u8 = 0x02;
+    pub const RST: u8 = 0x04;
+    pub const PSH: u8 = 0x08;
+    pub const ACK: u8 = 0x10;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TcpHeader {
+    pub src_port: u16,
+    pub dst_port: u16,
+    pub seq: u32,
+    pub ack: u32,
+    pub flags: u8,
+    pub window: u16,
+    /// The maximum segment size option, if present (SYNs only).
+    pub mss: Option<u16>,
+}
+
+impl TcpHeader {
+    pub fn has(&self, flag: u8) -> bool {
+        self.flags & flag != 0
+    }
+
+    /// Parse a segment carried between `src` and `dst`, verifying its
+    /// checksum.
+    pub fn parse(src: Ipv4Addr, dst: Ipv4Addr, segment: &[u8]) -> Option<(Self, &[u8])> {
+        if segment.len() < TCP_HEADER_LEN {
+            return None;
+        }
+        let data_offset = ((segment[12] >> 4) as usize) * 4;
+        if data_offset < TCP_HEADER_LEN || data_offset > segment.len() {
+            return None;
+        }
+        if transport_checksum(src, dst, IP_PROTOCOL_TCP, segment) != 0 {
+            return None;
+        }
+        let header = Self {
+            src_port: be16(segment, 0),
+            dst_port: be16(segment, 2),
+            seq: be32(segment, 4),
+            ack: be32(segment, 8),
+            flags: segment[13],
+            window: be16(segment, 14),
+            mss: parse_mss(&segment[TCP_HEADER_LEN..data_offset]),
+        };
+        Some((header, &segment[data_offset..]))
+    }
+
+    /// Build a complete segment: header, options, checksum and payload.
+    pub fn build(&self, src: Ipv4Addr, dst: Ipv4Addr, payload: &[u8]) -> Vec<u8> {
+        let options_len = if self.mss.is_some() {
+            TCP_MSS_OPTION_LEN
+        } else {
+            0
+        };
+        let header_len = TCP_HEADER_LEN + options_len;
+        let mut segment = Vec::with_capacity(header_len + payload.len());
+        segment.extend_from_slice(&self.src_port.to_be_bytes());
+        segment.extend_from_slice(&self.dst_port.to_be_bytes());
+        segment.extend_from_slice(&self.seq.to_be_bytes());
+        segment.extend_from_slice(&self.ack.to_be_bytes());
+        segment.push(((header_len / 4) as u8) << 4);
+        segment.push(self.flags);
+        segment.extend_from_slice(&self.window.to_be_bytes());
+        // Checksum, then urgent pointer.
+        segment.extend_from_slice(&[0, 0, 0, 0]);
+        if let Some(mss) = self.mss {
+            segment.extend_from_slice(&[2, 4]);
+            segment.extend_from_slice(&mss.to_be_bytes());
+        }
+        segment.extend_from_slice(payload);
+        let sum = transport_checksum(src, dst, IP_PROTOCOL_TCP, &segment);
+        segment[16..18].copy_from_slice(&sum.to_be_bytes());
+        segment
+    }
+}
+
+/// Find the MSS option in a TCP options block.
+fn parse_mss(mut options: &[u8]) -> Option<u16> {
+    while let Some(&kind) = options.first() {
+        match kind {
+            // End of options.
+            0 => return None,
+            // No-op padding.
+            1 => options = &options[1..],
+            _ => {
+                let len = *options.get(1)? as usize;
+                if len < 2 || len > options.len() {
+                    return None;
+                }
+                if kind == 2 && len == 4 {
+                    return Some(be16(options, 2));
+                }
+                options = &options[len..];
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A: Ipv4Addr = Ipv4Addr::new(10, 0, 2, 15);
+    const B: Ipv4Addr = Ipv4Addr::new(10, 0, 2, 2);
+
+    #[test]
+    fn ipv4_header_round_trip() {
+        let header = Ipv4Header {
+            src: A,
+            dst: B,
+            protocol: IP_PROTOCOL_UDP,
+            ttl: 64,
+            ident: 7,
+        };
+        let mut packet = [0u8; IPV4_HEADER_LEN + 3];
+        header.emit(3, &mut packet);
+        packet[IPV4_HEADER_LEN..].copy_from_slice(b"abc");
+
+        let (parsed, payload) = Ipv4Header::parse(&packet).unwrap();
+        assert_eq!(parsed, header);
+        assert_eq!(payload, b"abc");
+
+        packet[12] ^= 1;
+        assert!(
+            Ipv4Header::parse(&packet).is_none(),
+            "bad checksum accepted"
+        );
+    }
+
+    #[test]
+    fn ipv4_payload_is_trimmed_to_total_length() {
+        let header = Ipv4Header {
+            src: A,
+            dst: B,
+            protocol: IP_PROTOCOL_TCP,
+            ttl: 64,
+            ident: 0,
+        };
+        // Ethernet pads short frames; the padding is not payload.
+        let mut packet = [0u8; 46];
+        header.emit(2, &mut packet);
+        let (_, payload) = Ipv4Header::parse(&packet).unwrap();
+        assert_eq!(payload.len(), 2);
+    }
+
+    #[test]
+    fn udp_round_trip_and_checksum() {
+        let header = UdpHeader {
+            src_port: 68,
+            dst_port: 67,
+        };
+        let mut segment = header.build(A, B, b"hello");
+        let (parsed, payload) = UdpHeader::parse(A, B, &segment).unwrap();
+        assert_eq!(parsed, header);
+        assert_eq!(payload, b"hello");
+
+        // The checksum covers the pseudo-header.
+        assert!(UdpHeader::parse(A, Ipv4Addr::BROADCAST, &segment).is_none());
+
+        // A zero checksum means the sender didn't compute one.
+        segment[6..8].copy_from_slice(&[0, 0]);
+        assert!(UdpHeader::parse(A, Ipv4Addr::BROADCAST, &segment).is_some());
+    }
+
+    #[test]
+    fn tcp_round_trip_with_mss() {
+        let header = TcpHeader {
+            src_port: 49152,
+            dst_port: 80,
+            seq: 0xdead_beef,
+            ack: 1,
+            flags: tcp_flags::SYN | tcp_flags::ACK,
+            window: 4096,
+            mss: Some(1460),
+        };
+        let segment = header.build(A, B, b"");
+        assert_eq!(segment.len(), TCP_HEADER_LEN + 4);
+        let (parsed, payload) = TcpHeader::parse(A, B, &segment).unwrap();
+        assert_eq!(parsed, header);
+        assert!(payload.is_empty());
+        assert!(parsed.has(tcp_flags::SYN) && !parsed.has(tcp_flags::FIN));
+    }
+
+    #[test]
+    fn tcp_rejects_corruption() {
+        let header = TcpHeader {
+            src_port: 1,
+            dst_port: 2,
+            seq: 3,
+            ack: 4,
+            flags: tcp_flags::ACK,
+            window: 5,
+            mss: None,
+        };
+        let mut segment = header.build(A, B, b"data");
+        segment[TCP_HEADER_LEN] ^= 0x20;
+        assert!(TcpHeader::parse(A, B, &segment).is_none());
+    }
+
+    #[test]
+    fn arp_round_trip() {
+        let packet = ArpPacket {
+            op: ARP_REQUEST,
+            sender_mac: [0x52, 0x54, 0, 0x12, 0x34, 0x56],
+            sender_ip: A,
+            target_mac: [0; 6],
+            target_ip: B,
+        };
+        let mut buf = [0u8; ARP_PACKET_LEN];
+        packet.emit(&mut buf);
+        assert_eq!(ArpPacket::parse(&buf), Some(packet));
+        assert_eq!(ArpPacket::parse(&buf[..27]), None);
+    }
+}
@@ -0,0 +1,209 @@
+//! Rows to and from comma-separated values.
+//!
+//! The first line holds the column names. A field with a comma, a quote or
+//! a line break in it is quoted, with quotes inside doubled. Fields that
+//! read as numbers become numbers.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use panda_abi::value::{Table, Value};
+
+use crate::value::{parse_number, text};
+use crate::{Error, Row, Rows};
+
+/// Rows as CSV, a line each after a line of column names. A row that is not
+/// a record is one field.
+pub fn to_csv(rows: &Rows) -> String {
+    let mut out = String::new();
+    if !rows.columns().is_empty() {
+        let names: Vec<&str> = rows.columns().iter().map(String::as_str).collect();
+        write_line(&mut out, &names);
+    }
+    for row in rows.iter() {
+        let fields: Vec<String> = match row {
+            Row::Record(cells) => (0..rows.columns().len())
+                .map(|column| cells.get(column).map(text).unwrap_or_default())
+                .collect(),
+            Row::Item(value) => alloc::vec![text(value)],
+        };
+        let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+        write_line(&mut out, &fields);
+    }
+    out
+}
+
+fn write_line(out: &mut String, fields: &[&str]) {
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        if field.contains([',', '"', '\n', '\r']) {
+            out.push('"');
+            out.push_str(&field.replace('"', "\"\""));
+            out.push('"');
+        } else {
+            out.push_str(field);
+        }
+    }
+    out.push('\n');
+}
+
+/// Parse CSV into a table headed by its first line.
+pub fn from_csv(source: &str) -> Result<Value, Error> {
+    let mut lines = Lines {
+        chars: source.chars().peekable(),
+        line: 1,
+    };
+    let Some(headers) = lines.next_record()? else {
+        return Ok(Value::Array(Vec::new()));
+    };
+    let mut cells = Vec::new();
+    loop {
+        let line = lines.line;
+        let Some(record) = lines.next_record()? else {
+            break;
+        };
+        if record.len() != headers.len() {
+            return Err(Error::Csv {
+                line,
+                what: "wrong number of fields",
+            });
+        }
+        cells.extend(
+            record
+                .into_iter()
+                .map(|field| parse_number(&field).unwrap_or(Value::String(field))),
+        );
+    }
+    Ok(Value::Table(Table {
+        cols: headers.len() as u16,
+        headers: Some(headers.into_iter().map(Value::String).collect()),
+        cells,
+    }))
+}
+
+struct Lines<'a> {
+    chars: core::iter::Peekable<core::str::Chars<'a>>,
+    line: usize,
+}
+
+impl Lines<'_> {
+    /// The fields of the next record, skipping blank lines, or `None` at
+    /// the end.
+    fn next_record(&mut self) -> Result<Option<Vec<String>>, Error> {
+        while let Some(ch) = self.chars.next_if(|&ch| ch == '\n' || ch == '\r') {
+            if ch == '\n' {
+                self.line += 1;
+            }
+        }
+        if self.chars.peek().is_none() {
+            return Ok(None);
+        }
+
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        loop {
+            match self.chars.next() {
+                None | Some('\n') => {
+                    fields.push(field);
+                    self.line += 1;
+                    return Ok(Some(fields));
+                }
+                Some('\r') => {}
+                Some(',') => fields.push(core::mem::take(&mut field)),
+                Some('"') if field.is_empty() => self.quoted(&mut field)?,
+                Some(ch) => field.push(ch),
+            }
+        }
+    }
+
+    /// The rest of a quoted field, its opening quote already taken.
+    fn quoted(&mut self, field: &mut String) -> Result<(), Error> {
+        let line = self.line;
+        loop {
+            match self.chars.next() {
+                Some('"') if self.chars.next_if_eq(&'"').is_some() => field.push('"'),
+                Some('"') => return Ok(()),
+                Some(ch) => {
+                    if ch == '\n' {
+                        self.line += 1;
+                    }
+                    field.push(ch);
+                }
+                None => {
+                    return Err(Error::Csv {
+                        line,
+                        what: "unterminated quote",
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn string(s: &str) -> Value {
+        Value::String(s.into())
+    }
+
+    #[test]
+    fn writes_a_line_per_row() {
+        let table = Table::new(
+            2,
+            Some(vec![string("Name"), string("Note")]),
+            vec![string("a"), string("x, \"y\""), string("b"), Value::Int(2)],
+        )
+        .unwrap();
+        assert_eq!(
+            to_csv(&Rows::from(Value::Table(table))),
+            "Name,Note\na,\"x, \"\"y\"\"\"\nb,2\n"
+        );
+    }
+
+    #[test]
+    fn reads_a_table() {
+        let value = from_csv("name,size\r\nhello.txt,17\n\"a,\"\"b\"\"\nc\",1.5\n\n").unwrap();
+        let Value::Table(table) = value else {
+            panic!("not a table");
+        };
+        assert_eq!(table.headers, Some(vec![string("name"), string("size")]));
+        assert_eq!(
+            table.cells,
+            [
+                string("hello.txt"),
+                Value::Int(17),
+                string("a,\"b\"\nc"),
+                Value::Float(1.5),
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trips() {
+        let source = "a,b\n1,\"x\ny\"\n,z\n";
+        assert_eq!(to_csv(&Rows::from(from_csv(source).unwrap())), source);
+    }
+
+    #[test]
+    fn reports_bad_lines() {
+        assert_eq!(
+            from_csv("a,b\n1,2\n3\n"),
+            Err(Error::Csv {
+                line: 3,
+                what: "wrong number of fields"
+            })
+        );
+        assert_eq!(
+            from_csv("a\n\"open\n"),
+            Err(Error::Csv {
+                line: 2,
+                what: "unterminated quote"
+            })
+        );
+    }
+}
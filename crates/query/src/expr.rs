@@ -0,0 +1,358 @@
+//! The expressions `where` filters rows with.
+//!
+//! ```text
+//! expr    := and ('or' and)*
+//! and     := not ('and' not)*
+//! not     := ('not' | '!') not | compare
+//! compare := operand [('==' | '=' | '!=' | '<' | '<=' | '>' | '>=' | '=~' | '!~') operand]
+//! operand := number | 'string' | "string" | true | false | null | word | '(' expr ')'
+//! ```
+//!
+//! A word is a column of the row, found ignoring case, and `a.b` looks up
+//! `b` in the map column `a`; `it` is the whole row. A word that names no
+//! column is just that word, so `type == file` needs no quotes. `=~` is
+//! "contains" and `!~` its opposite, on the values as text.
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::iter::Peekable;
+use core::str::CharIndices;
+
+use panda_abi::value::Value;
+
+use crate::Error;
+use crate::rows::{Row, Rows};
+use crate::value::{compare, parse_number, plain, text, truthy};
+
+/// A parsed expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr(Node);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Literal(Value),
+    Word(String),
+    Not(Box<Node>),
+    And(Box<Node>, Box<Node>),
+    Or(Box<Node>, Box<Node>),
+    Compare(Box<Node>, Op, Box<Node>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+    Excludes,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Literal(Value),
+    Word(String),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+impl Expr {
+    /// Parse an expression.
+    pub fn parse(source: &str) -> Result<Self, Error> {
+        let mut tokens = tokenize(source)?.into_iter().peekable();
+        if tokens.peek().is_none() {
+            return Err(Error::Expression("nothing to evaluate".into()));
+        }
+        let node = or(&mut tokens)?;
+        match tokens.next() {
+            None => Ok(Expr(node)),
+            Some(Token::Close) => Err(Error::Expression("unmatched ')'".into())),
+            Some(token) => Err(Error::Expression(format!(
+                "unexpected {}",
+                describe(&token)
+            ))),
+        }
+    }
+
+    /// Evaluate the expression against one of `rows`.
+    pub fn eval(&self, rows: &Rows, row: &Row) -> Value {
+        eval(&self.0, rows, row)
+    }
+
+    /// Whether the expression holds for one of `rows`.
+    pub fn matches(&self, rows: &Rows, row: &Row) -> bool {
+        truthy(&self.eval(rows, row))
+    }
+}
+
+fn eval(node: &Node, rows: &Rows, row: &Row) -> Value {
+    match node {
+        Node::Literal(value) => value.clone(),
+        Node::Word(word) => lookup(word, rows, row),
+        Node::Not(inner) => Value::Bool(!truthy(&eval(inner, rows, row))),
+        Node::And(a, b) => Value::Bool(truthy(&eval(a, rows, row)) && truthy(&eval(b, rows, row))),
+        Node::Or(a, b) => Value::Bool(truthy(&eval(a, rows, row)) || truthy(&eval(b, rows, row))),
+        Node::Compare(a, op, b) => {
+            let (a, b) = (eval(a, rows, row), eval(b, rows, row));
+            let order = compare(&a, &b);
+            Value::Bool(match op {
+                Op::Eq => order == Ordering::Equal,
+                Op::Ne => order != Ordering::Equal,
+                Op::Lt => order == Ordering::Less,
+                Op::Le => order != Ordering::Greater,
+                Op::Gt => order == Ordering::Greater,
+                Op::Ge => order != Ordering::Less,
+                Op::Contains => text(&a).contains(text(&b).as_str()),
+                Op::Excludes => !text(&a).contains(text(&b).as_str()),
+            })
+        }
+    }
+}
+
+/// A word's value in a row: a column, a path into one, `it`, or the word.
+fn lookup(word: &str, rows: &Rows, row: &Row) -> Value {
+    let mut path = word.split('.');
+    let head = path.next().unwrap_or_default();
+    let value = if head == "it" {
+        rows.item(row)
+    } else {
+        match rows.field(row, head) {
+            Some(value) => value.clone(),
+            None => return Value::String(word.to_string()),
+        }
+    };
+    path.fold(value, |value, key| match plain(&value) {
+        Value::Map(map) => map
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map_or(Value::Null, |(_, value)| value.clone()),
+        _ => Value::Null,
+    })
+}
+
+type Tokens = Peekable<alloc::vec::IntoIter<Token>>;
+
+fn or(tokens: &mut Tokens) -> Result<Node, Error> {
+    let mut node = and(tokens)?;
+    while tokens.next_if_eq(&Token::Or).is_some() {
+        node = Node::Or(Box::new(node), Box::new(and(tokens)?));
+    }
+    Ok(node)
+}
+
+fn and(tokens: &mut Tokens) -> Result<Node, Error> {
+    let mut node = not(tokens)?;
+    while tokens.next_if_eq(&Token::And).is_some() {
+        node = Node::And(Box::new(node), Box::new(not(tokens)?));
+    }
+    Ok(node)
+}
+
+fn not(tokens: &mut Tokens) -> Result<Node, Error> {
+    if tokens.next_if_eq(&Token::Not).is_some() {
+        return Ok(Node::Not(Box::new(not(tokens)?)));
+    }
+    let left = operand(tokens)?;
+    match tokens.peek() {
+        Some(&Token::Op(op)) => {
+            tokens.next();
+            Ok(Node::Compare(
+                Box::new(left),
+                op,
+                Box::new(operand(tokens)?),
+            ))
+        }
+        _ => Ok(left),
+    }
+}
+
+fn operand(tokens: &mut Tokens) -> Result<Node, Error> {
+    match tokens.next() {
+        Some(Token::Literal(value)) => Ok(Node::Literal(value)),
+        Some(Token::Word(word)) => Ok(Node::Word(word)),
+        Some(Token::Open) => {
+            let node = or(tokens)?;
+            match tokens.next() {
+                Some(Token::Close) => Ok(node),
+                _ => Err(Error::Expression("unclosed '('".into())),
+            }
+        }
+        Some(token) => Err(Error::Expression(format!(
+            "expected a value, found {}",
+            describe(&token)
+        ))),
+        None => Err(Error::Expression("expected a value at the end".into())),
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Literal(value) => format!("'{}'", text(value)),
+        Token::Word(word) => format!("'{}'", word),
+        Token::Op(_) => "an operator".into(),
+        Token::And => "'and'".into(),
+        Token::Or => "'or'".into(),
+        Token::Not => "'not'".into(),
+        Token::Open => "'('".into(),
+        Token::Close => "')'".into(),
+    }
+}
+
+/// Whether `ch` ends a word.
+fn is_delimiter(ch: char) -> bool {
+    ch.is_whitespace() || matches!(ch, '(' | ')' | '<' | '>' | '=' | '!' | '\'' | '"')
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, Error> {
+    let mut chars = source.char_indices().peekable();
+    let mut tokens = Vec::new();
+
+    while let Some((start, ch)) = chars.next() {
+        let token = match ch {
+            ch if ch.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '\'' | '"' => Token::Literal(Value::String(quoted(&mut chars, ch)?)),
+            '=' | '!' | '<' | '>' => {
+                let next = chars
+                    .next_if(|&(_, next)| next == '=' || next == '~' && matches!(ch, '=' | '!'))
+                    .map(|(_, next)| next);
+                match (ch, next) {
+                    ('!', None) => Token::Not,
+                    ('!', Some('=')) => Token::Op(Op::Ne),
+                    ('!', Some(_)) => Token::Op(Op::Excludes),
+                    ('=', Some('~')) => Token::Op(Op::Contains),
+                    ('=', _) => Token::Op(Op::Eq),
+                    ('<', None) => Token::Op(Op::Lt),
+                    ('<', Some(_)) => Token::Op(Op::Le),
+                    ('>', None) => Token::Op(Op::Gt),
+                    _ => Token::Op(Op::Ge),
+                }
+            }
+            _ => {
+                let mut end = start + ch.len_utf8();
+                while let Some((i, ch)) = chars.next_if(|&(_, ch)| !is_delimiter(ch)) {
+                    end = i + ch.len_utf8();
+                }
+                word(&source[start..end])
+            }
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+/// A quoted string, its opening quote already taken. `\` escapes the
+/// quote and itself.
+fn quoted(chars: &mut Peekable<CharIndices>, quote: char) -> Result<String, Error> {
+    let mut string = String::new();
+    loop {
+        match chars.next() {
+            Some((_, ch)) if ch == quote => return Ok(string),
+            Some((_, '\\')) => match chars.next_if(|&(_, ch)| ch == quote || ch == '\\') {
+                Some((_, ch)) => string.push(ch),
+                None => string.push('\\'),
+            },
+            Some((_, ch)) => string.push(ch),
+            None => return Err(Error::Expression("unterminated string".into())),
+        }
+    }
+}
+
+/// A bare word: a keyword, a number or a name.
+fn word(word: &str) -> Token {
+    match word {
+        "and" | "&&" => Token::And,
+        "or" | "||" => Token::Or,
+        "not" => Token::Not,
+        "true" => Token::Literal(Value::Bool(true)),
+        "false" => Token::Literal(Value::Bool(false)),
+        "null" => Token::Literal(Value::Null),
+        word => match parse_number(word) {
+            Some(number) => Token::Literal(number),
+            None => Token::Word(word.to_string()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::collections::BTreeMap;
+    use alloc::vec;
+
+    fn record() -> Rows {
+        let mut owner = BTreeMap::new();
+        owner.insert("name".into(), Value::String("root".into()));
+        let mut map = BTreeMap::new();
+        map.insert("Name".into(), Value::String("notes.txt".into()));
+        map.insert("Size".into(), Value::Int(2048));
+        map.insert("owner".into(), Value::Map(owner));
+        Rows::from(Value::Map(map))
+    }
+
+    fn holds(source: &str) -> bool {
+        let rows = record();
+        let row = rows.iter().next().unwrap();
+        Expr::parse(source).unwrap().matches(&rows, row)
+    }
+
+    #[test]
+    fn comparisons() {
+        assert!(holds("size > 1024"));
+        assert!(holds("size>=2048"));
+        assert!(!holds("size < 1024"));
+        assert!(holds("name == notes.txt"));
+        assert!(holds("name = 'notes.txt'"));
+        assert!(holds("name != \"other\""));
+        assert!(holds("name =~ .txt"));
+        assert!(holds("name !~ .bin"));
+        assert!(holds("owner.name == root"));
+    }
+
+    #[test]
+    fn logic_and_precedence() {
+        assert!(holds("size > 1 and name =~ notes or false"));
+        assert!(holds("not size < 1024"));
+        assert!(holds("!(size < 1024 or size > 4096)"));
+        assert!(!holds("false or true and false"));
+        assert!(holds("size"));
+        assert!(!holds("owner.missing"));
+    }
+
+    #[test]
+    fn it_is_the_row() {
+        let rows = Rows::from(Value::Array(vec![Value::Int(5)]));
+        let row = rows.iter().next().unwrap();
+        assert!(Expr::parse("it >= 5").unwrap().matches(&rows, row));
+    }
+
+    #[test]
+    fn syntax_errors() {
+        for source in [
+            "",
+            "size >",
+            "(size > 1",
+            "size > 1)",
+            "> 1",
+            "'open",
+            "a b",
+        ] {
+            assert!(
+                matches!(Expr::parse(source), Err(Error::Expression(_))),
+                "{}",
+                source
+            );
+        }
+    }
+}
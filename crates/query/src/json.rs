@@ -0,0 +1,458 @@
+//! Values to and from JSON.
+//!
+//! Objects become maps, and maps objects. Styling is dropped, a table
+//! becomes an array of objects keyed by its headers (or of arrays, if it
+//! has none), and bytes an array of numbers. A number without a fraction
+//! or exponent that fits is an integer; any other is a float.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use panda_abi::value::Value;
+
+use crate::Error;
+use crate::value::{plain, text};
+
+/// How deeply arrays and objects may nest when parsing.
+const MAX_DEPTH: usize = 128;
+
+/// A value as JSON, on one line.
+pub fn to_json(value: &Value) -> String {
+    let mut out = String::new();
+    write(&mut out, value, None);
+    out
+}
+
+/// A value as JSON, with each element of an array or object on its own line
+/// and indented by two spaces a level.
+pub fn to_json_pretty(value: &Value) -> String {
+    let mut out = String::new();
+    write(&mut out, value, Some(0));
+    out
+}
+
+fn write(out: &mut String, value: &Value, indent: Option<usize>) {
+    match plain(value) {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => {
+            let _ = write!(out, "{}", b);
+        }
+        Value::Int(n) => {
+            let _ = write!(out, "{}", n);
+        }
+        Value::Float(x) if x.is_finite() => {
+            let _ = write!(out, "{}", x);
+        }
+        Value::Float(_) => out.push_str("null"),
+        Value::String(s) => write_string(out, s),
+        Value::Bytes(bytes) => {
+            let items: Vec<Value> = bytes.iter().map(|&b| Value::Int(b as i64)).collect();
+            write_array(out, &items, indent);
+        }
+        Value::Array(items) => write_array(out, items, indent),
+        Value::Map(map) => write_object(out, map.iter().map(|(k, v)| (k.as_str(), v)), indent),
+        Value::Table(table) => {
+            let rows: Vec<Value> = match &table.headers {
+                Some(headers) => table
+                    .row_iter()
+                    .map(|row| {
+                        Value::Map(headers.iter().map(text).zip(row.iter().cloned()).collect())
+                    })
+                    .collect(),
+                None => table
+                    .row_iter()
+                    .map(|row| Value::Array(row.to_vec()))
+                    .collect(),
+            };
+            write_array(out, &rows, indent);
+        }
+        Value::Styled(..) | Value::Link { .. } => unreachable!(),
+    }
+}
+
+/// Start the next element of a container: a comma after the one before,
+/// and in pretty output a new line.
+fn separate(out: &mut String, first: bool, indent: Option<usize>) {
+    if !first {
+        out.push(',');
+    }
+    if let Some(level) = indent {
+        out.push('\n');
+        out.extend(core::iter::repeat_n(' ', (level + 1) * 2));
+    }
+}
+
+fn close(out: &mut String, empty: bool, indent: Option<usize>, bracket: char) {
+    if let (Some(level), false) = (indent, empty) {
+        out.push('\n');
+        out.extend(core::iter::repeat_n(' ', level * 2));
+    }
+    out.push(bracket);
+}
+
+fn write_array(out: &mut String, items: &[Value], indent: Option<usize>) {
+    out.push('[');
+    for (i, item) in items.iter().enumerate() {
+        separate(out, i == 0, indent);
+        write(out, item, indent.map(|level| level + 1));
+    }
+    close(out, items.is_empty(), indent, ']');
+}
+
+fn write_object<'a>(
+    out: &mut String,
+    entries: impl Iterator<Item = (&'a str, &'a Value)>,
+    indent: Option<usize>,
+) {
+    out.push('{');
+    let mut empty = true;
+    for (key, value) in entries {
+        separate(out, empty, indent);
+        empty = false;
+        write_string(out, key);
+        out.push_str(if indent.is_some() { ": " } else { ":" });
+        write(out, value, indent.map(|level| level + 1));
+    }
+    close(out, empty, indent, '}');
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", ch as u32);
+            }
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+}
+
+/// Parse a JSON document.
+pub fn from_json(source: &str) -> Result<Value, Error> {
+    let mut parser = Parser {
+        bytes: source.as_bytes(),
+        pos: 0,
+    };
+    let value = parser.value(0)?;
+    parser.skip_whitespace();
+    if parser.pos < parser.bytes.len() {
+        return Err(parser.error("trailing characters"));
+    }
+    Ok(value)
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, what: &'static str) -> Error {
+        Error::Json {
+            offset: self.pos,
+            what,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    /// Take `expected` if it comes next, after any whitespace.
+    fn eat(&mut self, expected: u8) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(expected) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: Value) -> Result<Value, Error> {
+        if self.bytes[self.pos..].starts_with(word.as_bytes()) {
+            self.pos += word.len();
+            Ok(value)
+        } else {
+            Err(self.error("expected a value"))
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, Error> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("nested too deeply"));
+        }
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => self.object(depth),
+            Some(b'[') => self.array(depth),
+            Some(b'"') => self.string().map(Value::String),
+            Some(b't') => self.keyword("true", Value::Bool(true)),
+            Some(b'f') => self.keyword("false", Value::Bool(false)),
+            Some(b'n') => self.keyword("null", Value::Null),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => Err(self.error("expected a value")),
+            None => Err(self.error("unexpected end")),
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Value, Error> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        if self.eat(b']') {
+            return Ok(Value::Array(items));
+        }
+        loop {
+            items.push(self.value(depth + 1)?);
+            if self.eat(b']') {
+                return Ok(Value::Array(items));
+            }
+            if !self.eat(b',') {
+                return Err(self.error("expected ',' or ']'"));
+            }
+        }
+    }
+
+    fn object(&mut self, depth: usize) -> Result<Value, Error> {
+        self.pos += 1;
+        let mut map = BTreeMap::new();
+        if self.eat(b'}') {
+            return Ok(Value::Map(map));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') {
+                return Err(self.error("expected a key"));
+            }
+            let key = self.string()?;
+            if !self.eat(b':') {
+                return Err(self.error("expected ':'"));
+            }
+            map.insert(key, self.value(depth + 1)?);
+            if self.eat(b'}') {
+                return Ok(Value::Map(map));
+            }
+            if !self.eat(b',') {
+                return Err(self.error("expected ',' or '}'"));
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        self.pos += 1;
+        let mut string = String::new();
+        loop {
+            // Copy the run up to the next quote or escape in one go.
+            let start = self.pos;
+            while let Some(b) = self.peek()
+                && b != b'"'
+                && b != b'\\'
+            {
+                if b < 0x20 {
+                    return Err(self.error("control character in string"));
+                }
+                self.pos += 1;
+            }
+            // The run starts and ends at ASCII, so it is whole UTF-8.
+            string.push_str(core::str::from_utf8(&self.bytes[start..self.pos]).unwrap_or_default());
+            match self.peek() {
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(string);
+                }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    string.push(self.escape()?);
+                }
+                _ => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char, Error> {
+        let b = self
+            .peek()
+            .ok_or_else(|| self.error("unterminated string"))?;
+        self.pos += 1;
+        Ok(match b {
+            b'"' => '"',
+            b'\\' => '\\',
+            b'/' => '/',
+            b'b' => '\u{8}',
+            b'f' => '\u{c}',
+            b'n' => '\n',
+            b'r' => '\r',
+            b't' => '\t',
+            b'u' => {
+                let high = self.hex4()?;
+                let code = if (0xd800..0xdc00).contains(&high) {
+                    // A surrogate pair, whose second half must follow.
+                    if !self.bytes[self.pos..].starts_with(b"\\u") {
+                        return Err(self.error("unpaired surrogate"));
+                    }
+                    self.pos += 2;
+                    let low = self.hex4()?;
+                    if !(0xdc00..0xe000).contains(&low) {
+                        return Err(self.error("unpaired surrogate"));
+                    }
+                    0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+                } else {
+                    high
+                };
+                char::from_u32(code).ok_or_else(|| self.error("unpaired surrogate"))?
+            }
+            _ => {
+                self.pos -= 1;
+                return Err(self.error("unknown escape"));
+            }
+        })
+    }
+
+    fn hex4(&mut self) -> Result<u32, Error> {
+        let digits = self
+            .bytes
+            .get(self.pos..self.pos + 4)
+            .and_then(|digits| core::str::from_utf8(digits).ok())
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .ok_or_else(|| self.error("bad \\u escape"))?;
+        self.pos += 4;
+        Ok(digits)
+    }
+
+    fn number(&mut self) -> Result<Value, Error> {
+        let start = self.pos;
+        let mut integral = true;
+        while let Some(b) = self.peek() {
+            match b {
+                b'0'..=b'9' | b'-' | b'+' => {}
+                b'.' | b'e' | b'E' => integral = false,
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        let text = core::str::from_utf8(&self.bytes[start..self.pos]).unwrap_or_default();
+        if integral && let Ok(n) = text.parse::<i64>() {
+            return Ok(Value::Int(n));
+        }
+        match text.parse::<f64>() {
+            Ok(x) => Ok(Value::Float(x)),
+            Err(_) => {
+                self.pos = start;
+                Err(self.error("bad number"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::boxed::Box;
+    use alloc::vec;
+    use panda_abi::terminal::Style;
+    use panda_abi::value::Table;
+
+    fn string(s: &str) -> Value {
+        Value::String(s.into())
+    }
+
+    #[test]
+    fn writes_values() {
+        let mut map = BTreeMap::new();
+        map.insert(
+            "name".into(),
+            Value::Styled(Style::bold(), Box::new(string("a\"b"))),
+        );
+        map.insert(
+            "sizes".into(),
+            Value::Array(vec![Value::Int(1), Value::Float(2.5)]),
+        );
+        map.insert("none".into(), Value::Null);
+        assert_eq!(
+            to_json(&Value::Map(map)),
+            r#"{"name":"a\"b","none":null,"sizes":[1,2.5]}"#
+        );
+        assert_eq!(to_json(&Value::Float(f64::NAN)), "null");
+        assert_eq!(to_json(&string("tab\there\u{1}")), r#""tab\there\u0001""#);
+    }
+
+    #[test]
+    fn tables_become_arrays_of_objects() {
+        let table = Table::new(
+            2,
+            Some(vec![string("Name"), string("Size")]),
+            vec![string("a"), Value::Int(1), string("b"), Value::Int(2)],
+        )
+        .unwrap();
+        assert_eq!(
+            to_json(&Value::Table(table)),
+            r#"[{"Name":"a","Size":1},{"Name":"b","Size":2}]"#
+        );
+    }
+
+    #[test]
+    fn pretty_output_indents() {
+        let value = from_json(r#"{"a":[1,2],"b":{}}"#).unwrap();
+        assert_eq!(
+            to_json_pretty(&value),
+            "{\n  \"a\": [\n    1,\n    2\n  ],\n  \"b\": {}\n}"
+        );
+    }
+
+    #[test]
+    fn parses_documents() {
+        let value = from_json(
+            r#" {"n": -12, "x": 1.5e2, "s": "\u00e9\ud83d\ude00\n", "l": [true, null]} "#,
+        )
+        .unwrap();
+        let Value::Map(map) = value else {
+            panic!("not a map");
+        };
+        assert_eq!(map["n"], Value::Int(-12));
+        assert_eq!(map["x"], Value::Float(150.0));
+        assert_eq!(map["s"], string("é😀\n"));
+        assert_eq!(map["l"], Value::Array(vec![Value::Bool(true), Value::Null]));
+    }
+
+    #[test]
+    fn round_trips() {
+        let source = r#"[{"a":"x","b":[1,2.25,false]},"y",null]"#;
+        assert_eq!(to_json(&from_json(source).unwrap()), source);
+    }
+
+    #[test]
+    fn reports_where_it_failed() {
+        assert_eq!(
+            from_json("[1, 2"),
+            Err(Error::Json {
+                offset: 5,
+                what: "expected ',' or ']'"
+            })
+        );
+        assert_eq!(
+            from_json("{\"a\" 1}"),
+            Err(Error::Json {
+                offset: 5,
+                what: "expected ':'"
+            })
+        );
+        assert!(from_json("[1] x").is_err());
+        assert!(from_json("\"\\ud800\"").is_err());
+        assert!(from_json(&"[".repeat(1000)).is_err());
+    }
+}
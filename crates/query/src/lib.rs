@@ -0,0 +1,58 @@
+//! Querying structured pipeline data.
+//!
+//! The filter commands (`where`, `select`, `sort-by`, `group-by`, `to`,
+//! `from`, ... in `userspace/filters`) all work on the same thing: the
+//! [`Value`]s a pipeline stage reads, gathered into [`Rows`]. A table's rows
+//! are records keyed by its headers, as are the maps in an array; anything
+//! else is an item that only `it` can look at. [`Expr`] is the small
+//! expression language `where` filters those rows with, and [`json`] and
+//! [`csv`] convert them to and from text.
+//!
+//! The crate is pure logic, unit-tested on the host.
+
+#![no_std]
+
+extern crate alloc;
+
+#[cfg(test)]
+extern crate std;
+
+pub mod csv;
+mod expr;
+pub mod json;
+mod rows;
+pub mod value;
+
+use alloc::string::String;
+use core::fmt;
+
+pub use expr::Expr;
+pub use panda_abi::value::Value;
+pub use rows::{Row, Rows, split};
+
+/// Why a query failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// An expression that does not parse, and why.
+    Expression(String),
+    /// A column the rows do not have.
+    UnknownColumn(String),
+    /// JSON that does not parse, and the byte offset where it stopped.
+    Json { offset: usize, what: &'static str },
+    /// CSV that does not parse, and the line (from 1) where it stopped.
+    Csv { line: usize, what: &'static str },
+    /// A value too large to send even on its own.
+    TooLarge,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Expression(what) => write!(f, "bad expression: {}", what),
+            Error::UnknownColumn(name) => write!(f, "no column '{}'", name),
+            Error::Json { offset, what } => write!(f, "bad JSON at byte {}: {}", offset, what),
+            Error::Csv { line, what } => write!(f, "bad CSV on line {}: {}", line, what),
+            Error::TooLarge => write!(f, "value too large to send"),
+        }
+    }
+}
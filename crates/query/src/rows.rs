@@ -0,0 +1,599 @@
+//! A stage's input gathered into rows.
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem;
+
+use panda_abi::encoding::{Encode, Encoder};
+use panda_abi::value::{Table, Value};
+
+use crate::value::{compare, plain, text};
+use crate::{Error, Expr};
+
+/// The rows of a stage's input, and the columns its records have.
+///
+/// Tables, and the maps among the values pushed or in arrays pushed, are
+/// records; their columns are collected in the order they first appear.
+/// Anything else is an item.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Rows {
+    columns: Vec<String>,
+    /// Whether the columns have names, rather than only the numbers a
+    /// table without headers gets.
+    headed: bool,
+    rows: Vec<Row>,
+}
+
+/// One row of [`Rows`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Row {
+    /// Cells by column. A record from before a column appeared is short of
+    /// it, and has null there.
+    Record(Vec<Value>),
+    /// A value that is not a record, which only `it` sees into.
+    Item(Value),
+}
+
+/// What a column name given to a filter refers to.
+#[derive(Clone, Copy)]
+enum Key {
+    Column(usize),
+    It,
+}
+
+impl Rows {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gather one value of input: a table's rows, an array's elements, or
+    /// the value itself as one row.
+    pub fn push(&mut self, value: Value) {
+        match value {
+            Value::Table(table) => self.push_table(table),
+            Value::Array(items) => {
+                for item in items {
+                    self.push_item(item);
+                }
+            }
+            value => self.push_item(value),
+        }
+    }
+
+    fn push_item(&mut self, value: Value) {
+        match value {
+            Value::Map(map) => {
+                self.headed = true;
+                let mut cells = Vec::new();
+                for (name, value) in map {
+                    let column = self.add_column(name);
+                    if cells.len() <= column {
+                        cells.resize(column + 1, Value::Null);
+                    }
+                    cells[column] = value;
+                }
+                self.rows.push(Row::Record(cells));
+            }
+            value => self.rows.push(Row::Item(value)),
+        }
+    }
+
+    fn push_table(&mut self, table: Table) {
+        let names: Vec<String> = match &table.headers {
+            Some(headers) => {
+                self.headed = true;
+                headers.iter().map(text).collect()
+            }
+            None => (1..=table.cols).map(|n| n.to_string()).collect(),
+        };
+        let columns: Vec<usize> = names
+            .into_iter()
+            .map(|name| self.add_column(name))
+            .collect();
+        let width = columns.iter().max().map_or(0, |&last| last + 1);
+        for row in table.row_iter() {
+            let mut cells = vec![Value::Null; width];
+            for (&column, cell) in columns.iter().zip(row) {
+                cells[column] = cell.clone();
+            }
+            self.rows.push(Row::Record(cells));
+        }
+    }
+
+    /// The index of the column `name`, added if there is none yet.
+    fn add_column(&mut self, name: String) -> usize {
+        match self.columns.iter().position(|column| *column == name) {
+            Some(column) => column,
+            None => {
+                self.columns.push(name);
+                self.columns.len() - 1
+            }
+        }
+    }
+
+    /// The column names, in order.
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Row> {
+        self.rows.iter()
+    }
+
+    /// The index of the column `name`: exactly, or else ignoring case.
+    fn column(&self, name: &str) -> Option<usize> {
+        self.columns
+            .iter()
+            .position(|column| column == name)
+            .or_else(|| {
+                self.columns
+                    .iter()
+                    .position(|column| column.eq_ignore_ascii_case(name))
+            })
+    }
+
+    fn key(&self, name: &str) -> Result<Key, Error> {
+        if name == "it" {
+            return Ok(Key::It);
+        }
+        self.column(name)
+            .map(Key::Column)
+            .ok_or_else(|| Error::UnknownColumn(name.to_string()))
+    }
+
+    fn value(&self, row: &Row, key: Key) -> Value {
+        match key {
+            Key::It => self.item(row),
+            Key::Column(column) => match row {
+                Row::Record(cells) => cells.get(column).cloned().unwrap_or(Value::Null),
+                Row::Item(_) => Value::Null,
+            },
+        }
+    }
+
+    /// A row's value in the column `name`, or `None` if there is no such
+    /// column or the row is an item.
+    pub fn field<'a>(&'a self, row: &'a Row, name: &str) -> Option<&'a Value> {
+        let column = self.column(name)?;
+        match row {
+            Row::Record(cells) => Some(cells.get(column).unwrap_or(&Value::Null)),
+            Row::Item(_) => None,
+        }
+    }
+
+    /// A row as one value: a record as a map of its columns.
+    pub fn item(&self, row: &Row) -> Value {
+        match row {
+            Row::Record(cells) => Value::Map(
+                self.columns
+                    .iter()
+                    .cloned()
+                    .zip(cells.iter().cloned().chain(core::iter::repeat(Value::Null)))
+                    .collect(),
+            ),
+            Row::Item(value) => value.clone(),
+        }
+    }
+
+    /// Keep the rows `expr` holds for.
+    pub fn filter(&mut self, expr: &Expr) {
+        let rows = mem::take(&mut self.rows);
+        self.rows = rows
+            .into_iter()
+            .filter(|row| expr.matches(self, row))
+            .collect();
+    }
+
+    /// Keep only the columns `names`, in that order, making every row a
+    /// record of them.
+    pub fn select(&mut self, names: &[&str]) -> Result<(), Error> {
+        let keys = names
+            .iter()
+            .map(|name| self.key(name))
+            .collect::<Result<Vec<_>, _>>()?;
+        let rows = mem::take(&mut self.rows);
+        self.rows = rows
+            .iter()
+            .map(|row| Row::Record(keys.iter().map(|&key| self.value(row, key)).collect()))
+            .collect();
+        self.columns = keys
+            .iter()
+            .zip(names)
+            .map(|(key, name)| match key {
+                Key::Column(column) => self.columns[*column].clone(),
+                Key::It => name.to_string(),
+            })
+            .collect();
+        self.headed = true;
+        Ok(())
+    }
+
+    /// Sort the rows by the columns `names`, later ones breaking ties in
+    /// earlier ones, or by the rows themselves if there are none. Rows that
+    /// tie keep their order.
+    pub fn sort_by(&mut self, names: &[&str]) -> Result<(), Error> {
+        let mut keys = names
+            .iter()
+            .map(|name| self.key(name))
+            .collect::<Result<Vec<_>, _>>()?;
+        if keys.is_empty() {
+            keys.push(Key::It);
+        }
+        let mut rows: Vec<(Vec<Value>, Row)> = mem::take(&mut self.rows)
+            .into_iter()
+            .map(|row| (keys.iter().map(|&key| self.value(&row, key)).collect(), row))
+            .collect();
+        rows.sort_by(|(a, _), (b, _)| {
+            a.iter()
+                .zip(b)
+                .map(|(a, b)| compare(a, b))
+                .find(|order| order.is_ne())
+                .unwrap_or(core::cmp::Ordering::Equal)
+        });
+        self.rows = rows.into_iter().map(|(_, row)| row).collect();
+        Ok(())
+    }
+
+    pub fn reverse(&mut self) {
+        self.rows.reverse();
+    }
+
+    /// Keep the first `n` rows.
+    pub fn first(&mut self, n: usize) {
+        self.rows.truncate(n);
+    }
+
+    /// Keep the last `n` rows.
+    pub fn last(&mut self, n: usize) {
+        let skip = self.rows.len().saturating_sub(n);
+        self.rows.drain(..skip);
+    }
+
+    /// Group the rows by their value in the column `name`, as a map from
+    /// each value, as text, to the rows that have it.
+    pub fn group_by(self, name: &str) -> Result<Value, Error> {
+        let key = self.key(name)?;
+        let mut groups: BTreeMap<String, Rows> = BTreeMap::new();
+        for row in &self.rows {
+            let group = groups
+                .entry(text(&self.value(row, key)))
+                .or_insert_with(|| Rows {
+                    columns: self.columns.clone(),
+                    headed: self.headed,
+                    rows: Vec::new(),
+                });
+            group.rows.push(row.clone());
+        }
+        Ok(Value::Map(
+            groups
+                .into_iter()
+                .map(|(name, rows)| (name, rows.into_value()))
+                .collect(),
+        ))
+    }
+
+    /// The rows as one value: a table if they are all records, else an
+    /// array with each record as a map.
+    pub fn into_value(self) -> Value {
+        let records = self.rows.iter().all(|row| matches!(row, Row::Record(_)));
+        if !records || self.columns.is_empty() {
+            let items = self.rows.iter().map(|row| self.item(row)).collect();
+            return Value::Array(items);
+        }
+
+        let cols = self.columns.len();
+        let mut cells = Vec::with_capacity(self.rows.len() * cols);
+        for row in self.rows {
+            if let Row::Record(mut row) = row {
+                row.resize(cols, Value::Null);
+                cells.extend(row);
+            }
+        }
+        let headers = self
+            .headed
+            .then(|| self.columns.into_iter().map(Value::String).collect());
+        Value::Table(Table {
+            cols: cols as u16,
+            headers,
+            cells,
+        })
+    }
+}
+
+impl From<Value> for Rows {
+    fn from(value: Value) -> Self {
+        let mut rows = Rows::new();
+        rows.push(value);
+        rows
+    }
+}
+
+fn encoded_len(value: &Value) -> usize {
+    let mut encoder = Encoder::new();
+    value.encode(&mut encoder);
+    encoder.len()
+}
+
+/// Split `value` into pieces that each encode to at most `limit` bytes, so
+/// that each fits in one message: a table into tables of fewer rows, an
+/// array into shorter arrays, and text into runs of whole lines where it
+/// can be.
+pub fn split(value: Value, limit: usize) -> Result<Vec<Value>, Error> {
+    if encoded_len(&value) <= limit {
+        return Ok(vec![value]);
+    }
+    match plain(&value) {
+        Value::Table(table) => {
+            let empty = Table {
+                cols: table.cols,
+                headers: table.headers.clone(),
+                cells: Vec::new(),
+            };
+            let rows = table.row_iter().map(|row| row.to_vec());
+            pieces(
+                rows,
+                encoded_len(&Value::Table(empty.clone())),
+                limit,
+                |cells| {
+                    Value::Table(Table {
+                        cells: cells.concat(),
+                        ..empty.clone()
+                    })
+                },
+            )
+        }
+        Value::Array(items) => {
+            let items = items.iter().map(|item| vec![item.clone()]);
+            pieces(
+                items,
+                encoded_len(&Value::Array(Vec::new())),
+                limit,
+                |items| Value::Array(items.concat()),
+            )
+        }
+        Value::String(text) => {
+            let room = limit.saturating_sub(encoded_len(&Value::String(String::new())));
+            let mut pieces = Vec::new();
+            let mut rest = text.as_str();
+            while rest.len() > room {
+                let mut end = room;
+                while !rest.is_char_boundary(end) {
+                    end -= 1;
+                }
+                if let Some(newline) = rest[..end].rfind('\n') {
+                    end = newline + 1;
+                }
+                if end == 0 {
+                    return Err(Error::TooLarge);
+                }
+                pieces.push(Value::String(rest[..end].to_string()));
+                rest = &rest[end..];
+            }
+            pieces.push(Value::String(rest.to_string()));
+            Ok(pieces)
+        }
+        Value::Bytes(bytes) => {
+            let room = limit.saturating_sub(encoded_len(&Value::Bytes(Vec::new())));
+            if room == 0 {
+                return Err(Error::TooLarge);
+            }
+            Ok(bytes
+                .chunks(room)
+                .map(|chunk| Value::Bytes(chunk.to_vec()))
+                .collect())
+        }
+        _ => Err(Error::TooLarge),
+    }
+}
+
+/// Pack `parts` into as few pieces as fit in `limit`, each made by `make`
+/// and costing `overhead` bytes on top of its parts.
+fn pieces(
+    parts: impl Iterator<Item = Vec<Value>>,
+    overhead: usize,
+    limit: usize,
+    make: impl Fn(Vec<Vec<Value>>) -> Value,
+) -> Result<Vec<Value>, Error> {
+    let mut pieces = Vec::new();
+    let mut piece = Vec::new();
+    let mut size = overhead;
+    for part in parts {
+        let part_size: usize = part.iter().map(encoded_len).sum();
+        if overhead + part_size > limit {
+            return Err(Error::TooLarge);
+        }
+        if size + part_size > limit {
+            pieces.push(make(mem::take(&mut piece)));
+            size = overhead;
+        }
+        piece.push(part);
+        size += part_size;
+    }
+    if !piece.is_empty() {
+        pieces.push(make(piece));
+    }
+    Ok(pieces)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::boxed::Box;
+    use panda_abi::terminal::Style;
+
+    fn string(s: &str) -> Value {
+        Value::String(s.into())
+    }
+
+    /// A listing like `ls` makes.
+    fn listing() -> Value {
+        Value::Table(
+            Table::new(
+                3,
+                Some(vec![string("Name"), string("Type"), string("Size")]),
+                vec![
+                    Value::Styled(Style::bold(), Box::new(string("docs"))),
+                    string("dir"),
+                    string("-"),
+                    string("large.bin"),
+                    string("file"),
+                    Value::Int(8192),
+                    string("hello.txt"),
+                    string("file"),
+                    Value::Int(17),
+                ],
+            )
+            .unwrap(),
+        )
+    }
+
+    fn names(value: &Value) -> Vec<String> {
+        let Value::Table(table) = value else {
+            panic!("not a table: {:?}", value);
+        };
+        table.row_iter().map(|row| text(&row[0])).collect()
+    }
+
+    #[test]
+    fn filter_and_sort_a_table() {
+        let mut rows = Rows::from(listing());
+        rows.filter(&Expr::parse("size > 10 and type == file").unwrap());
+        rows.sort_by(&["name"]).unwrap();
+        let value = rows.into_value();
+        assert_eq!(names(&value), ["hello.txt", "large.bin"]);
+        let Value::Table(table) = value else {
+            unreachable!();
+        };
+        assert_eq!(table.headers.unwrap()[2], string("Size"));
+    }
+
+    #[test]
+    fn sort_by_several_columns() {
+        let mut rows = Rows::from(listing());
+        rows.sort_by(&["type", "size"]).unwrap();
+        assert_eq!(
+            names(&rows.clone().into_value()),
+            ["docs", "hello.txt", "large.bin"]
+        );
+        rows.reverse();
+        assert_eq!(
+            names(&rows.into_value()),
+            ["large.bin", "hello.txt", "docs"]
+        );
+    }
+
+    #[test]
+    fn select_reorders_columns() {
+        let mut rows = Rows::from(listing());
+        rows.select(&["size", "NAME"]).unwrap();
+        assert_eq!(rows.columns(), ["Size", "Name"]);
+        assert_eq!(
+            rows.select(&["owner"]),
+            Err(Error::UnknownColumn("owner".into()))
+        );
+    }
+
+    #[test]
+    fn first_and_last() {
+        let mut rows = Rows::from(listing());
+        rows.last(2);
+        rows.first(1);
+        assert_eq!(names(&rows.into_value()), ["large.bin"]);
+    }
+
+    #[test]
+    fn maps_are_records() {
+        let mut rows = Rows::new();
+        for (name, size) in [("a", 1), ("b", 2)] {
+            let mut map = BTreeMap::new();
+            map.insert("name".into(), string(name));
+            map.insert("size".into(), Value::Int(size));
+            rows.push(Value::Array(vec![Value::Map(map)]));
+        }
+        let mut extra = BTreeMap::new();
+        extra.insert("owner".into(), string("root"));
+        rows.push(Value::Map(extra));
+        assert_eq!(rows.columns(), ["name", "size", "owner"]);
+        let Value::Table(table) = rows.into_value() else {
+            panic!("not a table");
+        };
+        assert_eq!(table.rows(), 3);
+        assert_eq!(table.get(0, 2), Some(&Value::Null));
+        assert_eq!(table.get(2, 2), Some(&string("root")));
+    }
+
+    #[test]
+    fn items_stay_items() {
+        let mut rows = Rows::from(Value::Array(vec![
+            Value::Int(3),
+            Value::Int(1),
+            Value::Int(2),
+        ]));
+        rows.filter(&Expr::parse("it != 2").unwrap());
+        rows.sort_by(&[]).unwrap();
+        assert_eq!(
+            rows.into_value(),
+            Value::Array(vec![Value::Int(1), Value::Int(3)])
+        );
+    }
+
+    #[test]
+    fn group_by_value() {
+        let groups = Rows::from(listing()).group_by("type").unwrap();
+        let Value::Map(groups) = groups else {
+            panic!("not a map");
+        };
+        assert_eq!(groups.keys().collect::<Vec<_>>(), ["dir", "file"]);
+        assert_eq!(names(&groups["file"]), ["large.bin", "hello.txt"]);
+    }
+
+    #[test]
+    fn split_fits_messages() {
+        let cells = (0..200)
+            .flat_map(|n| [Value::Int(n), string("some text")])
+            .collect();
+        let table =
+            Value::Table(Table::new(2, Some(vec![string("n"), string("s")]), cells).unwrap());
+        let pieces = split(table, 512).unwrap();
+        assert!(pieces.len() > 1);
+        let mut rows = 0;
+        for piece in &pieces {
+            assert!(encoded_len(piece) <= 512);
+            let Value::Table(piece) = piece else {
+                panic!("not a table");
+            };
+            rows += piece.rows();
+        }
+        assert_eq!(rows, 200);
+        assert_eq!(split(Value::Int(1), 4), Err(Error::TooLarge));
+    }
+
+    #[test]
+    fn split_text_at_lines() {
+        let text = "line of text\n".repeat(100);
+        let pieces = split(string(&text), 512).unwrap();
+        assert!(pieces.len() > 1);
+        let mut joined = String::new();
+        for piece in &pieces {
+            assert!(encoded_len(piece) <= 512);
+            let Value::String(piece) = piece else {
+                panic!("not a string");
+            };
+            assert!(piece.ends_with('\n'));
+            joined.push_str(piece);
+        }
+        assert_eq!(joined, text);
+        let pieces = split(string(&"é".repeat(400)), 512).unwrap();
+        assert_eq!(pieces.len(), 2);
+    }
+}
@@ -0,0 +1,205 @@
+//! What a value means to a query, whatever styling it is displayed with.
+
+use alloc::string::{String, ToString};
+use core::cmp::Ordering;
+
+use panda_abi::value::Value;
+
+use crate::json;
+
+/// The value under any `Styled` and `Link` wrappers.
+pub fn plain(value: &Value) -> &Value {
+    match value {
+        Value::Styled(_, inner) | Value::Link { inner, .. } => plain(inner),
+        _ => value,
+    }
+}
+
+/// The value as text: strings as they are, numbers and booleans as they
+/// print, null as nothing and containers as JSON.
+pub fn text(value: &Value) -> String {
+    match plain(value) {
+        Value::Null => String::new(),
+        Value::Bool(b) => b.to_string(),
+        Value::Int(n) => n.to_string(),
+        Value::Float(x) => x.to_string(),
+        Value::String(s) => s.clone(),
+        Value::Bytes(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+        value => json::to_json(value),
+    }
+}
+
+/// A number written as text: an integer if it is one, else a float. Only
+/// digits, signs, a point and an exponent count, so `inf` and `nan` stay
+/// words.
+pub fn parse_number(text: &str) -> Option<Value> {
+    if let Ok(n) = text.parse::<i64>() {
+        return Some(Value::Int(n));
+    }
+    let numeric = text.bytes().any(|b| b.is_ascii_digit())
+        && text
+            .bytes()
+            .all(|b| b.is_ascii_digit() || matches!(b, b'.' | b'-' | b'+' | b'e' | b'E'));
+    if !numeric {
+        return None;
+    }
+    text.parse::<f64>().ok().map(Value::Float)
+}
+
+/// Whether a value counts as true: false, null, zero and anything empty
+/// do not.
+pub fn truthy(value: &Value) -> bool {
+    match plain(value) {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Int(n) => *n != 0,
+        Value::Float(x) => *x != 0.0,
+        Value::String(s) => !s.is_empty(),
+        Value::Bytes(bytes) => !bytes.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Map(map) => !map.is_empty(),
+        Value::Table(table) => table.rows() > 0,
+        Value::Styled(..) | Value::Link { .. } => unreachable!(),
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Number {
+    Int(i64),
+    Float(f64),
+}
+
+impl Number {
+    fn of(value: &Value) -> Option<Self> {
+        match value {
+            Value::Int(n) => Some(Number::Int(*n)),
+            Value::Float(x) => Some(Number::Float(*x)),
+            _ => None,
+        }
+    }
+
+    fn as_f64(self) -> f64 {
+        match self {
+            Number::Int(n) => n as f64,
+            Number::Float(x) => x,
+        }
+    }
+
+    fn cmp(self, other: Self) -> Ordering {
+        match (self, other) {
+            (Number::Int(a), Number::Int(b)) => a.cmp(&b),
+            (a, b) => {
+                let (a, b) = (a.as_f64(), b.as_f64());
+                a.partial_cmp(&b)
+                    .unwrap_or_else(|| a.is_nan().cmp(&b.is_nan()))
+            }
+        }
+    }
+}
+
+/// Where a kind of value sorts among the others.
+fn rank(value: &Value) -> u8 {
+    match value {
+        Value::Null => 0,
+        Value::Bool(_) => 1,
+        Value::Int(_) | Value::Float(_) => 2,
+        Value::String(_) => 3,
+        Value::Bytes(_) => 4,
+        Value::Array(_) => 5,
+        Value::Map(_) => 6,
+        Value::Table(_) => 7,
+        Value::Styled(..) | Value::Link { .. } => unreachable!(),
+    }
+}
+
+/// Order two values, ignoring styling. Numbers compare by value, and a
+/// string that is a number compares with a number as one. Different kinds
+/// of value sort null, booleans, numbers, strings, bytes, arrays, maps,
+/// tables.
+pub fn compare(a: &Value, b: &Value) -> Ordering {
+    let (a, b) = (plain(a), plain(b));
+    let number = |value: &Value, other: &Value| match value {
+        Value::String(s) if Number::of(other).is_some() => {
+            parse_number(s).and_then(|n| Number::of(&n))
+        }
+        value => Number::of(value),
+    };
+    if let (Some(x), Some(y)) = (number(a, b), number(b, a)) {
+        return x.cmp(y);
+    }
+    match (a, b) {
+        (Value::Bool(x), Value::Bool(y)) => x.cmp(y),
+        (Value::String(x), Value::String(y)) => x.cmp(y),
+        (Value::Bytes(x), Value::Bytes(y)) => x.cmp(y),
+        (Value::Array(x), Value::Array(y)) => x
+            .iter()
+            .zip(y)
+            .map(|(x, y)| compare(x, y))
+            .find(|order| order.is_ne())
+            .unwrap_or(x.len().cmp(&y.len())),
+        (a, b) if rank(a) != rank(b) => rank(a).cmp(&rank(b)),
+        // Maps and tables only compare as text.
+        (a, b) => text(a).cmp(&text(b)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::boxed::Box;
+    use panda_abi::terminal::Style;
+
+    fn string(s: &str) -> Value {
+        Value::String(s.into())
+    }
+
+    #[test]
+    fn styling_is_ignored() {
+        let bold = Value::Styled(Style::bold(), Box::new(string("docs")));
+        assert_eq!(plain(&bold), &string("docs"));
+        assert_eq!(text(&bold), "docs");
+        assert_eq!(compare(&bold, &string("docs")), Ordering::Equal);
+    }
+
+    #[test]
+    fn numbers_compare_by_value() {
+        assert_eq!(compare(&Value::Int(2), &Value::Float(10.0)), Ordering::Less);
+        assert_eq!(compare(&Value::Int(1), &Value::Float(1.0)), Ordering::Equal);
+        assert_eq!(
+            compare(&string("2048"), &Value::Int(1024)),
+            Ordering::Greater
+        );
+        // Two strings are still compared as strings.
+        assert_eq!(compare(&string("10"), &string("9")), Ordering::Less);
+    }
+
+    #[test]
+    fn kinds_sort_in_rank_order() {
+        assert_eq!(compare(&Value::Null, &Value::Bool(false)), Ordering::Less);
+        assert_eq!(compare(&Value::Int(99), &string("-")), Ordering::Less);
+        assert_eq!(
+            compare(&string("inf"), &Value::Float(1.0)),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn numbers_in_text() {
+        assert_eq!(parse_number("42"), Some(Value::Int(42)));
+        assert_eq!(parse_number("-1.5e3"), Some(Value::Float(-1500.0)));
+        assert_eq!(parse_number("inf"), None);
+        assert_eq!(parse_number("4K"), None);
+        assert_eq!(parse_number(""), None);
+    }
+
+    #[test]
+    fn truth() {
+        assert!(truthy(&Value::Int(3)));
+        assert!(!truthy(&string("")));
+        assert!(!truthy(&Value::Null));
+        assert!(truthy(&Value::Styled(
+            Style::bold(),
+            Box::new(Value::Bool(true))
+        )));
+    }
+}
@@ -241,10 +241,9 @@ pub const EVENT_DEVICE_IRQ: u32 = 1 << 8;
 // =============================================================================
 // Operation codes
 //
-// Device operations (0xA_0000 - 0xA_FFFF). Defined here for ABI completeness;
-// only OP_DEVICE_SUBSCRIBE and OP_DEVICE_CLAIM have kernel handlers today
-// (see panda-kernel/src/syscall/device.rs). The rest are reserved pending the
-// IOMMU-dependent Phase 6 work.
+// Device operations (0xA_0000 - 0xA_FFFF). OP_DEVICE_MAP_MMIO and
+// OP_DEVICE_SUBSCRIBE_IRQ are defined for ABI completeness but have no
+// kernel handler yet (see panda-kernel/src/syscall/device.rs).
 // =============================================================================
 
 /// Subscribe to device add/remove events for a bus type + match filter:
@@ -259,15 +258,30 @@ pub const OP_DEVICE_CLAIM: u32 = 0xA_0001;
 /// (requires IOMMU-aware cleanup, Phase 6).
 pub const OP_DEVICE_MAP_MMIO: u32 = 0xA_0002;
 /// Allocate IOMMU-mapped DMA memory for a claimed device:
-/// `(device_handle, size: usize) -> (virt_addr, iova)`. Not yet implemented.
+/// `(device_handle, size: usize, info: *mut DmaAllocInfo) -> 0`. The memory
+/// is contiguous in the caller's address space and at `iova` in the
+/// device's, not necessarily in physical memory. `NotSupported` without an
+/// IOMMU.
 pub const OP_DMA_ALLOC: u32 = 0xA_0003;
 /// Free memory allocated by `OP_DMA_ALLOC`:
-/// `(device_handle, virt_addr, size) -> ()`. Not yet implemented.
+/// `(device_handle, virt_addr, size) -> 0`.
 pub const OP_DMA_FREE: u32 = 0xA_0004;
 /// Subscribe to IRQ events for a claimed device:
 /// `(device_handle, mailbox_handle) -> ()`. Not yet implemented.
 pub const OP_DEVICE_SUBSCRIBE_IRQ: u32 = 0xA_0005;
 
+/// Result of `OP_DMA_ALLOC`, written through its `info` pointer.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DmaAllocInfo {
+    /// Address of the allocation in the caller's address space.
+    pub addr: usize,
+    /// Address the device must use for the same memory.
+    pub iova: u64,
+}
+
+const _: () = assert!(core::mem::size_of::<DmaAllocInfo>() == 16);
+
 #[cfg(test)]
 mod tests {
     use super::*;
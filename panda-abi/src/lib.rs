@@ -13,8 +13,10 @@ extern crate alloc;
 
 pub mod device;
 pub mod encoding;
+pub mod net;
 pub mod path;
 pub mod scheme_protocol;
+pub mod socket;
 pub mod terminal;
 pub mod value;
 
@@ -308,6 +310,32 @@ pub enum Operation {
     /// Flush a damaged rectangle to the display: (rect_ptr) -> 0 or error.
     /// `rect_ptr` points to a [`SurfaceRect`], or is 0 for a full-screen flush.
     DisplayFlush = 0x6_1002,
+    /// Load the hardware cursor's image: (image_ptr, hot_x, hot_y) -> 0 or error.
+    /// `image_ptr` points to a [`DISPLAY_CURSOR_SIZE`]-pixel square of ARGB
+    /// pixels, or is 0 to hide the cursor.
+    DisplayCursorSet = 0x6_1003,
+    /// Move the hardware cursor's hotspot to a screen position: (x, y) -> 0 or error.
+    DisplayCursorMove = 0x6_1004,
+    /// List the display's modes: (modes_ptr, capacity) -> mode count or error.
+    /// `modes_ptr` points to `capacity` [`DisplayMode`]s.
+    DisplayModes = 0x6_1005,
+    /// Switch the display to one of its modes: (width, height) -> 0 or error.
+    DisplaySetMode = 0x6_1006,
+
+    // Network device operations (0x6_2000 - 0x6_2FFF)
+    /// Get a network device's info: (info_ptr) -> 0 or error.
+    /// `info_ptr` points to a [`NetInfo`] (MAC address, maximum frame size).
+    NetInfo = 0x6_2000,
+    /// Attach a frame ring: (buffer_handle) -> 0 or error.
+    /// The buffer is laid out as described in [`net`].
+    NetRing = 0x6_2001,
+    /// Transmit the frames queued in the ring: () -> frames sent or error.
+    NetKick = 0x6_2002,
+
+    // Pointer device operations (0x6_3000 - 0x6_3FFF)
+    /// Get a pointer device's info: (info_ptr) -> 0 or error.
+    /// `info_ptr` points to a [`PointerInfo`] (relative or absolute, axis ranges).
+    PointerInfo = 0x6_3000,
 
     // Mailbox operations (0x7_0000 - 0x7_0FFF)
     /// Create a new mailbox: () -> mailbox_handle
@@ -316,6 +344,8 @@ pub enum Operation {
     MailboxWait = 0x7_0001,
     /// Poll for an event on any attached handle (non-blocking): (mailbox) -> (handle, events) or (0, 0)
     MailboxPoll = 0x7_0002,
+    /// Attach an open handle to a mailbox: (mailbox, handle, event_mask) -> 0 or error
+    MailboxAttach = 0x7_0003,
 
     // Channel operations (0x7_1000 - 0x7_1FFF)
     /// Create a channel pair: (out_handles_ptr) -> 0 or error
@@ -384,9 +414,18 @@ impl Operation {
             0x6_1000 => Some(Self::DisplayInfo),
             0x6_1001 => Some(Self::DisplayMap),
             0x6_1002 => Some(Self::DisplayFlush),
+            0x6_1003 => Some(Self::DisplayCursorSet),
+            0x6_1004 => Some(Self::DisplayCursorMove),
+            0x6_1005 => Some(Self::DisplayModes),
+            0x6_1006 => Some(Self::DisplaySetMode),
+            0x6_2000 => Some(Self::NetInfo),
+            0x6_2001 => Some(Self::NetRing),
+            0x6_2002 => Some(Self::NetKick),
+            0x6_3000 => Some(Self::PointerInfo),
             0x7_0000 => Some(Self::MailboxCreate),
             0x7_0001 => Some(Self::MailboxWait),
             0x7_0002 => Some(Self::MailboxPoll),
+            0x7_0003 => Some(Self::MailboxAttach),
             0x7_1000 => Some(Self::ChannelCreate),
             0x7_1001 => Some(Self::ChannelSend),
             0x7_1002 => Some(Self::ChannelRecv),
@@ -422,6 +461,20 @@ pub const OP_PROCESS_GET_PID: u32 = Operation::ProcessGetPid as u32;
 pub const OP_PROCESS_WAIT: u32 = Operation::ProcessWait as u32;
 /// Signal process: (signal) -> 0 or error
 pub const OP_PROCESS_SIGNAL: u32 = Operation::ProcessSignal as u32;
+
+// Signals for OP_PROCESS_SIGNAL. A process is never asked: these are what
+// the kernel does to it. Programs that want a say get a message first, such
+// as `terminal::Event::Signal` from the terminal.
+/// End the process. It exits with `SIGNAL_EXIT_BASE + SIGNAL_TERM`.
+pub const SIGNAL_TERM: u32 = 0;
+/// End the process. It exits with `SIGNAL_EXIT_BASE + SIGNAL_KILL`.
+pub const SIGNAL_KILL: u32 = 1;
+/// Stop scheduling the process until it gets `SIGNAL_CONTINUE`.
+pub const SIGNAL_STOP: u32 = 2;
+/// Resume a process stopped by `SIGNAL_STOP`.
+pub const SIGNAL_CONTINUE: u32 = 3;
+/// Added to the signal to give the exit code of a process it ended.
+pub const SIGNAL_EXIT_BASE: i32 = 128;
 /// Set program break: (new_brk) -> current_brk or error
 /// If new_brk is 0, returns current break without changing it.
 /// Pages are allocated on demand via page faults.
@@ -523,6 +576,47 @@ pub const OP_DISPLAY_MAP: u32 = Operation::DisplayMap as u32;
 /// Flush a damaged rectangle to the display: (rect_ptr) -> 0 or error.
 /// `rect_ptr` points to a [`SurfaceRect`], or is 0 to flush the whole screen.
 pub const OP_DISPLAY_FLUSH: u32 = Operation::DisplayFlush as u32;
+/// Load the hardware cursor's image: (image_ptr, hot_x, hot_y) -> 0 or error.
+/// `image_ptr` points to `DISPLAY_CURSOR_SIZE * DISPLAY_CURSOR_SIZE` ARGB
+/// pixels, or is 0 to hide the cursor. Fails with `NotSupported` if the
+/// display has no cursor plane.
+pub const OP_DISPLAY_CURSOR_SET: u32 = Operation::DisplayCursorSet as u32;
+/// Move the hardware cursor's hotspot to a screen position: (x, y) -> 0 or error.
+pub const OP_DISPLAY_CURSOR_MOVE: u32 = Operation::DisplayCursorMove as u32;
+/// List the display's modes: (modes_ptr, capacity) -> mode count or error.
+/// Writes up to `capacity` [`DisplayMode`]s and returns how many modes there
+/// are in all, which may be more.
+pub const OP_DISPLAY_MODES: u32 = Operation::DisplayModes as u32;
+/// Switch the display to a mode `OP_DISPLAY_MODES` lists: (width, height) ->
+/// 0 or error. Unmaps the caller's `OP_DISPLAY_MAP` mappings, replaces the
+/// framebuffer and posts `EVENT_DISPLAY_CHANGED`.
+pub const OP_DISPLAY_SET_MODE: u32 = Operation::DisplaySetMode as u32;
+
+// Network device operations (0x6_2000 - 0x6_2FFF)
+//
+// These act on a handle opened from the `net:` scheme
+// (`net:/pci/network/0`). Frames move through a shared buffer attached with
+// `OP_NET_RING`, laid out as described in [`net`]: the kernel writes
+// received frames straight into it, and `OP_NET_KICK` sends those queued.
+/// Get a network device's info: (info_ptr) -> 0 or error.
+/// Writes a [`NetInfo`] (MAC address, maximum frame size).
+pub const OP_NET_INFO: u32 = Operation::NetInfo as u32;
+/// Attach a frame ring: (buffer_handle) -> 0 or error.
+/// The buffer must hold at least [`net::RING_SIZE`] bytes; its header is
+/// reset, and any ring attached before is let go.
+pub const OP_NET_RING: u32 = Operation::NetRing as u32;
+/// Transmit the frames queued in the ring: () -> frames sent or error.
+/// Stops early, leaving the rest queued, when the device's queue is full.
+pub const OP_NET_KICK: u32 = Operation::NetKick as u32;
+
+// Pointer device operations (0x6_3000 - 0x6_3FFF)
+//
+// These act on a handle opened from the `pointer:` scheme
+// (`pointer:/pci/input/0`). Events are read with `OP_FILE_READ`, one 8-byte
+// evdev record (type, code, value) per read, as on a `keyboard:` handle.
+/// Get a pointer device's info: (info_ptr) -> 0 or error.
+/// Writes a [`PointerInfo`].
+pub const OP_POINTER_INFO: u32 = Operation::PointerInfo as u32;
 
 // Mailbox operations (0x7_0000 - 0x7_0FFF)
 /// Create a new mailbox: () -> mailbox_handle
@@ -531,6 +625,8 @@ pub const OP_MAILBOX_CREATE: u32 = Operation::MailboxCreate as u32;
 pub const OP_MAILBOX_WAIT: u32 = Operation::MailboxWait as u32;
 /// Poll for an event on any attached handle (non-blocking): (mailbox) -> (handle, events) or (0, 0)
 pub const OP_MAILBOX_POLL: u32 = Operation::MailboxPoll as u32;
+/// Attach an open handle to a mailbox: (mailbox, handle, event_mask) -> 0 or error
+pub const OP_MAILBOX_ATTACH: u32 = Operation::MailboxAttach as u32;
 
 /// Result structure for mailbox wait/poll operations.
 ///
@@ -640,6 +736,14 @@ pub const MAX_MESSAGE_SIZE: usize = 4096;
 /// which read directly into SharedBuffers without kernel bounce buffers.
 pub const MAX_FILE_IO_SIZE: usize = 1024 * 1024;
 
+/// Largest ethernet frame a `net:` handle sends or receives: a 14-byte
+/// header plus a 1500-byte payload. The frame check sequence is added and
+/// stripped by the device.
+pub const NET_MAX_FRAME_SIZE: usize = 1514;
+
+/// Smallest frame a `net:` handle will transmit: the ethernet header alone.
+pub const NET_MIN_FRAME_SIZE: usize = 14;
+
 /// Maximum size for a shared buffer allocation (16 MB).
 /// This limits physical frame allocation per buffer to prevent
 /// a single syscall from exhausting kernel memory.
@@ -722,6 +826,14 @@ impl EventFlags {
     /// `OP_DISPLAY_INFO` and re-map the framebuffer.
     pub const DISPLAY_CHANGED: Self = Self(1 << 5);
 
+    // Network events (bit 6)
+    /// A `net:` handle's ring has at least one received frame waiting.
+    pub const NET_RX: Self = Self(1 << 6);
+
+    // Pointer events (bit 7)
+    /// A `pointer:` handle has at least one complete report waiting.
+    pub const POINTER: Self = Self(1 << 7);
+
     /// Check if channel readable flag is set.
     #[inline]
     pub const fn is_channel_readable(self) -> bool {
@@ -758,6 +870,18 @@ impl EventFlags {
         self.0 & Self::DISPLAY_CHANGED.0 != 0
     }
 
+    /// Check if network frame received flag is set.
+    #[inline]
+    pub const fn is_net_rx(self) -> bool {
+        self.0 & Self::NET_RX.0 != 0
+    }
+
+    /// Check if pointer event flag is set.
+    #[inline]
+    pub const fn is_pointer(self) -> bool {
+        self.0 & Self::POINTER.0 != 0
+    }
+
     /// Combine flags with bitwise OR.
     #[inline]
     pub const fn or(self, other: Self) -> Self {
@@ -802,6 +926,18 @@ pub const EVENT_KEYBOARD_KEY: u32 = EventFlags::KEYBOARD_KEY.0;
 /// mapping refers to the old framebuffer.
 pub const EVENT_DISPLAY_CHANGED: u32 = EventFlags::DISPLAY_CHANGED.0;
 
+// Network events (bit 6)
+/// A `net:` handle's ring has received frames waiting. Posted once per
+/// batch of frames written to the ring, not once per frame: take frames
+/// until the ring is empty.
+pub const EVENT_NET_RX: u32 = EventFlags::NET_RX.0;
+
+// Pointer events (bit 7)
+/// A `pointer:` handle has events waiting. Posted once per `SYN_REPORT`
+/// (one motion or button report), not once per event: drain the handle with
+/// non-blocking reads until one returns 0.
+pub const EVENT_POINTER: u32 = EventFlags::POINTER.0;
+
 // Keyboard event encoding helpers
 /// Shift for key code in event flags.
 pub const EVENT_KEY_CODE_SHIFT: u32 = 8;
@@ -1372,6 +1508,41 @@ pub struct SurfaceInfoOut {
     pub stride: u32,
 }
 
+/// Network device info returned by `OP_NET_INFO`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NetInfo {
+    /// The device's ethernet (MAC) address.
+    pub mac: [u8; 6],
+    /// Largest frame the device accepts, ethernet header included.
+    pub max_frame_size: u16,
+}
+
+const _: () = assert!(core::mem::size_of::<NetInfo>() == 8);
+
+/// [`PointerInfo::flags`] bit: the device reports absolute positions
+/// (`EV_ABS`, a tablet) rather than relative motion (`EV_REL`, a mouse).
+pub const POINTER_ABSOLUTE: u32 = 1 << 0;
+/// [`PointerInfo::flags`] bit: the device has a scroll wheel (`REL_WHEEL`).
+pub const POINTER_WHEEL: u32 = 1 << 1;
+
+/// Pointer device info returned by `OP_POINTER_INFO`.
+///
+/// The axis ranges are only meaningful with [`POINTER_ABSOLUTE`] set; they
+/// are the device's own units, to be scaled to the screen by the reader.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PointerInfo {
+    /// [`POINTER_ABSOLUTE`] and [`POINTER_WHEEL`].
+    pub flags: u32,
+    pub x_min: u32,
+    pub x_max: u32,
+    pub y_min: u32,
+    pub y_max: u32,
+}
+
+const _: () = assert!(core::mem::size_of::<PointerInfo>() == 20);
+
 /// Parameters for blit operation.
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
@@ -1400,6 +1571,29 @@ pub struct FillParams {
     pub colour: u32,
 }
 
+/// Side of the square image `OP_DISPLAY_CURSOR_SET` takes, in pixels.
+pub const DISPLAY_CURSOR_SIZE: u32 = 64;
+
+/// [`DisplayMode::flags`] bit: the mode the monitor's EDID prefers.
+pub const DISPLAY_MODE_PREFERRED: u32 = 1 << 0;
+/// [`DisplayMode::flags`] bit: the mode the display is in.
+pub const DISPLAY_MODE_CURRENT: u32 = 1 << 1;
+
+/// Most modes `OP_DISPLAY_MODES` reports for one display.
+pub const MAX_DISPLAY_MODES: usize = 32;
+
+/// One mode a display can be switched to, written by `OP_DISPLAY_MODES`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DisplayMode {
+    pub width: u32,
+    pub height: u32,
+    /// [`DISPLAY_MODE_PREFERRED`] and [`DISPLAY_MODE_CURRENT`].
+    pub flags: u32,
+}
+
+const _: () = assert!(core::mem::size_of::<DisplayMode>() == 12);
+
 /// Rectangle for flush operation.
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
@@ -0,0 +1,88 @@
+//! The frame ring a `net:` handle moves ethernet frames through.
+//!
+//! A driver allocates a shared buffer of at least [`RING_SIZE`] bytes and
+//! attaches it with `OP_NET_RING`. The buffer starts with a [`RingHeader`],
+//! followed by [`RING_SLOTS`] receive slots and then as many transmit slots,
+//! each [`SLOT_SIZE`] bytes: the frame's length as a little-endian `u32`,
+//! then the frame itself, ethernet header included and no frame check
+//! sequence.
+//!
+//! ```text
+//! rx_head   kernel   frames written to the receive slots
+//! rx_tail   driver   frames taken from them
+//! tx_head   driver   frames queued in the transmit slots
+//! tx_tail   kernel   frames sent from them
+//! ```
+//!
+//! The counters run freely and wrap; counter `n` is slot `n % RING_SLOTS`.
+//! Each side only writes its own counters, and advances one only after the
+//! slot it covers is filled in (or read). The kernel posts `EVENT_NET_RX`
+//! after writing a batch of received frames, and sends the queued frames on
+//! `OP_NET_KICK`. A frame that arrives to a full receive ring is dropped.
+
+use core::sync::atomic::AtomicU32;
+
+use crate::NET_MAX_FRAME_SIZE;
+
+/// Frames each direction of the ring holds.
+pub const RING_SLOTS: usize = 32;
+
+/// Bytes per slot. Slots never straddle a page.
+pub const SLOT_SIZE: usize = 2048;
+
+/// Bytes reserved for the header, so the slots start on a page.
+pub const HEADER_SIZE: usize = 4096;
+
+/// Bytes of a slot before the frame: its length.
+pub const SLOT_LEN_SIZE: usize = 4;
+
+/// Smallest buffer `OP_NET_RING` accepts.
+pub const RING_SIZE: usize = HEADER_SIZE + 2 * RING_SLOTS * SLOT_SIZE;
+
+const _: () = assert!(SLOT_LEN_SIZE + NET_MAX_FRAME_SIZE <= SLOT_SIZE);
+const _: () = assert!(4096 % SLOT_SIZE == 0);
+
+/// The start of the ring buffer.
+#[repr(C)]
+#[derive(Debug, Default)]
+pub struct RingHeader {
+    pub rx_head: AtomicU32,
+    pub rx_tail: AtomicU32,
+    pub tx_head: AtomicU32,
+    pub tx_tail: AtomicU32,
+}
+
+const _: () = assert!(core::mem::size_of::<RingHeader>() <= HEADER_SIZE);
+
+/// Offset of the receive slot for counter value `n`.
+pub const fn rx_slot(n: u32) -> usize {
+    HEADER_SIZE + (n as usize % RING_SLOTS) * SLOT_SIZE
+}
+
+/// Offset of the transmit slot for counter value `n`.
+pub const fn tx_slot(n: u32) -> usize {
+    HEADER_SIZE + (RING_SLOTS + n as usize % RING_SLOTS) * SLOT_SIZE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slots_fill_the_ring_without_overlapping() {
+        assert_eq!(rx_slot(0), HEADER_SIZE);
+        assert_eq!(rx_slot(RING_SLOTS as u32), rx_slot(0));
+        assert_eq!(tx_slot(0), rx_slot(RING_SLOTS as u32 - 1) + SLOT_SIZE);
+        assert_eq!(tx_slot(RING_SLOTS as u32 - 1) + SLOT_SIZE, RING_SIZE);
+        assert_eq!(tx_slot(u32::MAX), tx_slot(u32::MAX % RING_SLOTS as u32));
+    }
+
+    #[test]
+    fn slots_stay_within_a_page() {
+        for n in 0..RING_SLOTS as u32 {
+            for offset in [rx_slot(n), tx_slot(n)] {
+                assert_eq!(offset / 4096, (offset + SLOT_SIZE - 1) / 4096);
+            }
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! Addresses and channel framing for the `tcp:` and `udp:` schemes.
+//!
+//! Both schemes are served by the network service (`userspace/netd`) and
+//! reached with `OP_ENVIRONMENT_CONNECT`, which hands the caller a channel.
+//! The path after the scheme name picks what the channel carries:
+//!
+//! ```text
+//! tcp:<ip>:<port>     a stream to a remote host. Each message is a chunk
+//!                     of the byte stream; closing either end of the
+//!                     channel closes the connection.
+//! tcp:/listen/<port>  a listener. Each accepted connection arrives as one
+//!                     ADDRESS_LEN-byte message (the peer's address) with
+//!                     the connection's stream channel attached.
+//! udp:<ip>:<port>     a connected datagram socket. Each message is one
+//!                     datagram.
+//! udp:/bind/<port>    an unconnected datagram socket. Each message is one
+//!                     datagram prefixed with an ADDRESS_LEN-byte address:
+//!                     the sender on receive, the destination on send.
+//! ```
+//!
+//! Addresses on the wire are the four IPv4 octets followed by the port in
+//! network byte order.
+
+use core::fmt;
+
+/// Encoded size of a [`SocketAddrV4`].
+pub const ADDRESS_LEN: usize = 6;
+
+/// Largest UDP payload that fits in one ethernet frame without
+/// fragmentation (1500-byte MTU less the IPv4 and UDP headers).
+pub const MAX_DATAGRAM_SIZE: usize = 1472;
+
+/// An IPv4 address.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Ipv4Addr(pub [u8; 4]);
+
+impl Ipv4Addr {
+    /// `0.0.0.0`
+    pub const UNSPECIFIED: Self = Self([0, 0, 0, 0]);
+    /// `255.255.255.255`
+    pub const BROADCAST: Self = Self([255, 255, 255, 255]);
+
+    pub const fn new(a: u8, b: u8, c: u8, d: u8) -> Self {
+        Self([a, b, c, d])
+    }
+
+    pub const fn octets(self) -> [u8; 4] {
+        self.0
+    }
+
+    pub const fn to_bits(self) -> u32 {
+        u32::from_be_bytes(self.0)
+    }
+
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits.to_be_bytes())
+    }
+
+    pub fn is_unspecified(self) -> bool {
+        self == Self::UNSPECIFIED
+    }
+
+    pub fn is_broadcast(self) -> bool {
+        self == Self::BROADCAST
+    }
+
+    /// Parse dotted-quad notation (`10.0.2.15`).
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut octets = [0u8; 4];
+        let mut parts = s.split('.');
+        for octet in &mut octets {
+            let part = parts.next()?;
+            // Reject "", "+1" and leading zeros, which u8::from_str accepts
+            // or other parsers read as octal.
+            if part.is_empty()
+                || !part.bytes().all(|b| b.is_ascii_digit())
+                || (part.len() > 1 && part.starts_with('0'))
+            {
+                return None;
+            }
+            *octet = part.parse().ok()?;
+        }
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(Self(octets))
+    }
+}
+
+impl fmt::Display for Ipv4Addr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d] = self.0;
+        write!(f, "{}.{}.{}.{}", a, b, c, d)
+    }
+}
+
+/// An IPv4 address and port.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SocketAddrV4 {
+    pub ip: Ipv4Addr,
+    pub port: u16,
+}
+
+impl SocketAddrV4 {
+    pub const fn new(ip: Ipv4Addr, port: u16) -> Self {
+        Self { ip, port }
+    }
+
+    /// Parse `<ip>:<port>`.
+    pub fn parse(s: &str) -> Option<Self> {
+        let (ip, port) = s.rsplit_once(':')?;
+        Some(Self {
+            ip: Ipv4Addr::parse(ip)?,
+            port: parse_port(port)?,
+        })
+    }
+
+    /// Encode into the first [`ADDRESS_LEN`] bytes of `buf`.
+    pub fn encode(&self, buf: &mut [u8]) -> Option<()> {
+        let buf = buf.get_mut(..ADDRESS_LEN)?;
+        buf[..4].copy_from_slice(&self.ip.0);
+        buf[4..].copy_from_slice(&self.port.to_be_bytes());
+        Some(())
+    }
+
+    /// Decode from the first [`ADDRESS_LEN`] bytes of `buf`.
+    pub fn decode(buf: &[u8]) -> Option<Self> {
+        let buf = buf.get(..ADDRESS_LEN)?;
+        Some(Self {
+            ip: Ipv4Addr([buf[0], buf[1], buf[2], buf[3]]),
+            port: u16::from_be_bytes([buf[4], buf[5]]),
+        })
+    }
+}
+
+impl fmt::Display for SocketAddrV4 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.ip, self.port)
+    }
+}
+
+/// What a `tcp:` or `udp:` connect path asks for. Which variants a scheme
+/// accepts is up to the scheme: `Listen` is TCP-only, `Bind` UDP-only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SocketPath {
+    /// `<ip>:<port>`
+    Remote(SocketAddrV4),
+    /// `/listen/<port>`
+    Listen(u16),
+    /// `/bind/<port>`
+    Bind(u16),
+}
+
+impl SocketPath {
+    pub fn parse(path: &str) -> Option<Self> {
+        if let Some(port) = path.strip_prefix("/listen/") {
+            return parse_port(port).map(Self::Listen);
+        }
+        if let Some(port) = path.strip_prefix("/bind/") {
+            return parse_port(port).map(Self::Bind);
+        }
+        SocketAddrV4::parse(path).map(Self::Remote)
+    }
+}
+
+fn parse_port(s: &str) -> Option<u16> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::ToString;
+
+    #[test]
+    fn ipv4_round_trips_through_text() {
+        let ip = Ipv4Addr::parse("10.0.2.15").unwrap();
+        assert_eq!(ip, Ipv4Addr::new(10, 0, 2, 15));
+        assert_eq!(ip.to_string(), "10.0.2.15");
+        assert_eq!(Ipv4Addr::from_bits(ip.to_bits()), ip);
+    }
+
+    #[test]
+    fn ipv4_rejects_malformed_text() {
+        for bad in [
+            "",
+            "10.0.2",
+            "10.0.2.15.1",
+            "10.0.2.256",
+            "10.0.02.1",
+            "10..2.1",
+            "+1.0.0.0",
+        ] {
+            assert_eq!(Ipv4Addr::parse(bad), None, "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn socket_addr_encoding() {
+        let addr = SocketAddrV4::parse("10.0.2.2:8080").unwrap();
+        let mut buf = [0u8; ADDRESS_LEN];
+        addr.encode(&mut buf).unwrap();
+        assert_eq!(buf, [10, 0, 2, 2, 0x1f, 0x90]);
+        assert_eq!(SocketAddrV4::decode(&buf), Some(addr));
+        assert_eq!(addr.to_string(), "10.0.2.2:8080");
+        assert_eq!(SocketAddrV4::decode(&buf[..5]), None);
+    }
+
+    #[test]
+    fn socket_paths() {
+        assert_eq!(
+            SocketPath::parse("10.0.2.2:80"),
+            Some(SocketPath::Remote(SocketAddrV4::new(
+                Ipv4Addr::new(10, 0, 2, 2),
+                80
+            )))
+        );
+        assert_eq!(
+            SocketPath::parse("/listen/8080"),
+            Some(SocketPath::Listen(8080))
+        );
+        assert_eq!(SocketPath::parse("/bind/0"), Some(SocketPath::Bind(0)));
+        assert_eq!(SocketPath::parse("/listen/70000"), None);
+        assert_eq!(SocketPath::parse("10.0.2.2"), None);
+        assert_eq!(SocketPath::parse("/connect"), None);
+    }
+}
@@ -331,6 +331,8 @@ pub enum TerminalQuery {
     Capabilities = 1,
     /// Query cursor position
     CursorPosition = 2,
+    /// Ask for a connection to the compositor that may capture the screen
+    CaptureConnection = 3,
 }
 
 impl Encode for TerminalQuery {
@@ -345,6 +347,7 @@ impl Decode for TerminalQuery {
             0 => Ok(Self::Size),
             1 => Ok(Self::Capabilities),
             2 => Ok(Self::CursorPosition),
+            3 => Ok(Self::CaptureConnection),
             _ => Err(DecodeError::InvalidValue),
         }
     }
@@ -746,6 +749,9 @@ pub enum QueryResponse {
     Capabilities(TerminalCapabilities),
     /// Cursor position
     CursorPosition { row: u16, col: u16 },
+    /// A connection to the compositor that may capture the screen, as the
+    /// message's handle attachment, if `granted`
+    CaptureConnection { granted: bool },
 }
 
 impl Encode for QueryResponse {
@@ -765,6 +771,10 @@ impl Encode for QueryResponse {
                 enc.write_u16(*row);
                 enc.write_u16(*col);
             }
+            QueryResponse::CaptureConnection { granted } => {
+                enc.write_u8(3);
+                enc.write_bool(*granted);
+            }
         }
     }
 }
@@ -786,6 +796,10 @@ impl Decode for QueryResponse {
                 let col = dec.read_u16()?;
                 Ok(QueryResponse::CursorPosition { row, col })
             }
+            3 => {
+                let granted = dec.read_bool()?;
+                Ok(QueryResponse::CaptureConnection { granted })
+            }
             _ => Err(DecodeError::InvalidValue),
         }
     }
@@ -5,9 +5,9 @@
 //! subscription/replay mechanism that notifies driver processes of
 //! arrivals and removals. See `plans/device-driver-model.md`.
 //!
-//! IOMMU-independent by design: this module only tracks claim ownership and
-//! posts events. Actual hardware access (MMIO mapping, DMA, IRQ routing) is
-//! Phase 6, gated on IOMMU support.
+//! This module only tracks claim ownership and posts events. DMA isolation
+//! for claimed devices is `iommu::manager`'s job; MMIO mapping and IRQ
+//! routing are still to come.
 
 pub mod pci;
 pub mod subscription;
@@ -298,6 +298,14 @@ impl DeviceRegistry {
         log::info!("device: released device {} (was owned by {:?})", device_id, owner);
     }
 
+    /// Undo a claim that could not be completed (e.g. the device could not
+    /// be given an IOMMU domain). Unlike [`release`](Self::release), no
+    /// `EVENT_DEVICE_REMOVED` is posted: as far as subscribers are
+    /// concerned, the device never changed hands.
+    pub fn abandon_claim(&mut self, device_id: DeviceId) {
+        self.owners.remove(&device_id);
+    }
+
     /// Release every device claimed by `pid`. Called on process exit.
     pub fn release_all_owned_by(&mut self, pid: ProcessId) {
         let owned: Vec<DeviceId> = self
@@ -318,6 +326,22 @@ impl DeviceRegistry {
     pub fn owner(&self, device_id: DeviceId) -> Option<ProcessId> {
         self.owners.get(&device_id).copied()
     }
+
+    /// Bus-specific information for a known device.
+    pub fn info(&self, device_id: DeviceId) -> Option<DeviceInfo> {
+        self.devices.get(&device_id).copied()
+    }
+
+    /// Addresses of every known PCI device.
+    pub fn pci_addresses(&self) -> Vec<PciAddress> {
+        self.devices
+            .values()
+            .filter_map(|info| match info {
+                DeviceInfo::Pci { address, .. } => Some(*address),
+                _ => None,
+            })
+            .collect()
+    }
 }
 
 impl Default for DeviceRegistry {
@@ -335,6 +359,7 @@ pub static DEVICE_REGISTRY: Spinlock<DeviceRegistry> = Spinlock::new(DeviceRegis
 /// the driver needing to explicitly release anything.
 pub fn release_all_owned_by(pid: ProcessId) {
     DEVICE_REGISTRY.lock().release_all_owned_by(pid);
+    crate::iommu::manager::detach_all_owned_by(pid);
 }
 
 #[cfg(test)]
@@ -3,11 +3,26 @@ pub mod virtio_block;
 pub mod virtio_gpu;
 mod virtio_hal;
 pub mod virtio_keyboard;
+pub mod virtio_net;
+pub mod virtio_pointer;
+
+use alloc::vec::Vec;
 
 use log::debug;
+use spinning_top::Spinlock;
 
 use crate::device;
-use crate::pci::{self, device::PciDevice};
+use crate::pci::{self, device::PciDevice, device::PciDeviceAddress};
+
+/// PCI devices [`init`] handed to a kernel driver. The driver's DMA runs
+/// through them, so they can't be claimed from userspace (see
+/// `syscall::device::handle_device_claim`).
+static KERNEL_BOUND: Spinlock<Vec<PciDeviceAddress>> = Spinlock::new(Vec::new());
+
+/// Whether a kernel driver drives the PCI device at `address`.
+pub fn is_kernel_bound(address: PciDeviceAddress) -> bool {
+    KERNEL_BOUND.lock().contains(&address)
+}
 
 pub fn init() {
     // Register enumerated devices with the device driver model's registry
@@ -26,22 +41,27 @@ pub fn init() {
             pci_device.subclass()
         );
 
-        match (
+        let driver: Option<fn(PciDevice)> = match (
             pci_device.vendor_id(),
             pci_device.device_id(),
             pci_device.subclass(),
         ) {
             // Virtio Block (legacy device ID 0x1001, modern transitional 0x1042)
-            (0x1AF4, 0x1001, _) | (0x1AF4, 0x1042, _) => {
-                virtio_block::init_from_pci_device(pci_device)
-            }
+            (0x1AF4, 0x1001, _) | (0x1AF4, 0x1042, _) => Some(virtio_block::init_from_pci_device),
+            // Virtio Net (legacy device ID 0x1000, modern transitional 0x1041)
+            (0x1AF4, 0x1000, _) | (0x1AF4, 0x1041, _) => Some(virtio_net::init_from_pci_device),
             // Virtio GPU
-            (0x1AF4, 0x1050, _) => virtio_gpu::init_from_pci_device(pci_device),
+            (0x1AF4, 0x1050, _) => Some(virtio_gpu::init_from_pci_device),
             // Virtio Input - keyboard (subclass 0x00)
-            (0x1AF4, 0x1052, 0x00) => virtio_keyboard::init_from_pci_device(pci_device),
-            // Virtio Input - mouse (subclass 0x02) - skip for now
-            (0x1AF4, 0x1052, 0x02) => {}
-            _ => {}
+            (0x1AF4, 0x1052, 0x00) => Some(virtio_keyboard::init_from_pci_device),
+            // Virtio Input - mouse (subclass 0x02), tablet and any other
+            // pointing device; the driver probes which kind it is
+            (0x1AF4, 0x1052, _) => Some(virtio_pointer::init_from_pci_device),
+            _ => None,
+        };
+        if let Some(init) = driver {
+            KERNEL_BOUND.lock().push(address);
+            init(pci_device);
         }
     });
 }
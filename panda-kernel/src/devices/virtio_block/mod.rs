@@ -461,8 +461,9 @@ pub fn get_device(address: &DeviceAddress) -> Option<VirtioBlockDevice> {
         .map(VirtioBlockDevice::new)
 }
 
-/// The interrupt vector used for virtio block MSI-X interrupts.
-const VIRTIO_BLOCK_MSIX_VECTOR: u8 = 0x30;
+/// The interrupt vector used for virtio block MSI-X interrupts, the one
+/// after virtio-net's.
+const VIRTIO_BLOCK_MSIX_VECTOR: u8 = interrupts::MSI_BASE_VECTOR + 2;
 
 /// Initialize a virtio block device from a PCI device.
 pub fn init_from_pci_device(pci_device: PciDevice) {
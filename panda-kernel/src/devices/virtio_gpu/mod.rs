@@ -1,3 +1,5 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
 use log::trace;
 use spinning_top::RwSpinlock;
 use virtio_drivers::{
@@ -9,6 +11,7 @@ use virtio_drivers::{
 };
 use x86_64::VirtAddr;
 
+use crate::device_address::DeviceAddress;
 use crate::pci::device::{PciDevice, PciDeviceAddress};
 use crate::resource::init_framebuffer;
 
@@ -66,21 +69,66 @@ struct VirtioGpuDevice {
     gpu: VirtIOGpu<VirtioHal, PciTransport>,
     framebuffer: VirtAddr,
     resolution: (u32, u32),
+    /// Where the cursor's hotspot was last moved to.
+    cursor_position: (u32, u32),
+    /// The EDID's preferred resolution when it was last read, `None` if the
+    /// device reported no EDID (nothing connected).
+    preferred: Option<(u32, u32)>,
 }
 
-static VIRTIO_GPU_DEVICE: RwSpinlock<Option<VirtioGpuDevice>> = RwSpinlock::new(None);
+/// Every virtio-gpu device, keyed by address. Each drives one head: its
+/// first scanout.
+static VIRTIO_GPU_DEVICES: RwSpinlock<BTreeMap<DeviceAddress, VirtioGpuDevice>> =
+    RwSpinlock::new(BTreeMap::new());
+
+/// Resolutions offered besides the EDID's preferred one. virtio-gpu scans
+/// out whatever size of resource it is given, so these are the common
+/// monitor modes rather than a list the device reports.
+const STANDARD_MODES: [(u32, u32); 12] = [
+    (640, 480),
+    (800, 600),
+    (1024, 768),
+    (1280, 720),
+    (1280, 800),
+    (1280, 1024),
+    (1366, 768),
+    (1440, 900),
+    (1600, 900),
+    (1680, 1050),
+    (1920, 1080),
+    (1920, 1200),
+];
+
+/// A mode a head can be switched to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mode {
+    pub width: u32,
+    pub height: u32,
+    /// The monitor's EDID prefers it.
+    pub preferred: bool,
+    /// The head is in it.
+    pub current: bool,
+}
 
 pub fn init_from_pci_device(pci_device: PciDevice) {
+    let pci_address = pci_device.address();
+    let address = DeviceAddress::Pci {
+        bus: pci_address.bus,
+        device: pci_address.slot,
+        function: pci_address.function,
+    };
+
     let mut root = PciRoot::new(pci_device.clone());
-    let device_function: DeviceFunction = pci_device.address().into();
+    let device_function: DeviceFunction = pci_address.into();
     let transport = PciTransport::new::<VirtioHal, PciDevice>(&mut root, device_function)
         .expect("Could not create PCI transport for Virtio GPU device");
     let mut gpu = VirtIOGpu::<VirtioHal, PciTransport>::new(transport)
         .expect("Could not initialize Virtio GPU device");
 
     // Use the EDID preferred resolution (DTD1) if available.
-    let (width, height) = gpu.edid_preferred_resolution().unwrap_or((1920, 1080));
-    log::info!("Display resolution: {}x{}", width, height);
+    let preferred = gpu.edid_preferred_resolution();
+    let (width, height) = preferred.unwrap_or((1920, 1080));
+    log::info!("Display {}: resolution {}x{}", address, width, height);
 
     let framebuffer = gpu
         .change_resolution(width, height)
@@ -89,32 +137,68 @@ pub fn init_from_pci_device(pci_device: PciDevice) {
 
     // Record the framebuffer's location and geometry for the `display:`
     // scheme.
-    init_framebuffer(framebuffer.as_mut_ptr(), width, height);
-
-    let mut device = VIRTIO_GPU_DEVICE.write();
-    *device = Some(VirtioGpuDevice {
-        gpu,
-        framebuffer,
-        resolution: (width, height),
-    });
+    init_framebuffer(address.clone(), framebuffer.as_mut_ptr(), width, height);
+
+    VIRTIO_GPU_DEVICES.write().insert(
+        address,
+        VirtioGpuDevice {
+            gpu,
+            framebuffer,
+            resolution: (width, height),
+            cursor_position: (0, 0),
+            preferred,
+        },
+    );
 }
 
-/// Flush the framebuffer to the display.
-pub fn flush_framebuffer() {
-    let mut device = VIRTIO_GPU_DEVICE.write();
-    if let Some(ref mut dev) = *device {
+/// Flush a head's framebuffer to the display.
+pub fn flush_framebuffer(address: &DeviceAddress) {
+    let mut devices = VIRTIO_GPU_DEVICES.write();
+    if let Some(dev) = devices.get_mut(address) {
         dev.gpu.flush().ok();
     }
 }
 
-/// Change the display resolution at runtime.
+/// The modes a head offers: the EDID's preferred resolution, the common
+/// ones, and the one it is in, each once.
+pub fn modes(address: &DeviceAddress) -> Vec<Mode> {
+    let devices = VIRTIO_GPU_DEVICES.read();
+    let Some(dev) = devices.get(address) else {
+        return Vec::new();
+    };
+
+    let mut modes: Vec<Mode> = Vec::new();
+    let sizes = dev
+        .preferred
+        .into_iter()
+        .chain(STANDARD_MODES)
+        .chain(core::iter::once(dev.resolution));
+    for (width, height) in sizes {
+        if modes.iter().any(|m| (m.width, m.height) == (width, height)) {
+            continue;
+        }
+        modes.push(Mode {
+            width,
+            height,
+            preferred: dev.preferred == Some((width, height)),
+            current: dev.resolution == (width, height),
+        });
+    }
+    modes
+}
+
+/// Change a head's resolution at runtime.
 ///
 /// Tears down the existing GPU framebuffer resource, creates a new one at the
-/// specified dimensions, and updates the global framebuffer region for the
+/// specified dimensions, and updates the head's framebuffer region for the
 /// `display:` scheme.
-pub fn change_resolution(width: u32, height: u32) -> Result<(), &'static str> {
-    let mut device = VIRTIO_GPU_DEVICE.write();
-    let dev = device.as_mut().ok_or("GPU not initialized")?;
+pub fn change_resolution(
+    address: &DeviceAddress,
+    width: u32,
+    height: u32,
+) -> Result<(), &'static str> {
+    let mut devices = VIRTIO_GPU_DEVICES.write();
+    let dev = devices.get_mut(address).ok_or("GPU not initialized")?;
 
     let framebuffer = dev
         .gpu
@@ -124,12 +208,79 @@ pub fn change_resolution(width: u32, height: u32) -> Result<(), &'static str> {
     dev.framebuffer = VirtAddr::new(framebuffer_ptr as u64);
     dev.resolution = (width, height);
 
-    init_framebuffer(framebuffer_ptr, width, height);
+    init_framebuffer(address.clone(), framebuffer_ptr, width, height);
 
-    // Tell the display owner (if any) that its mode info and framebuffer
+    // Tell the head's owner (if any) that its mode info and framebuffer
     // mapping are stale: it must re-query OP_DISPLAY_INFO and re-issue
     // OP_DISPLAY_MAP.
-    crate::resource::notify_display_changed();
+    crate::resource::notify_display_changed(address);
 
     Ok(())
 }
+
+/// Read a head's EDID again, returning whether its preferred resolution
+/// changed since the last read — a monitor was plugged in, unplugged or
+/// swapped for another.
+///
+/// The driver has no config-change interrupt to hear this from, so the
+/// `display:` scheme polls it while someone owns the head.
+pub fn edid_changed(address: &DeviceAddress) -> bool {
+    let mut devices = VIRTIO_GPU_DEVICES.write();
+    let Some(dev) = devices.get_mut(address) else {
+        return false;
+    };
+    let preferred = dev.gpu.edid_preferred_resolution();
+    if preferred == dev.preferred {
+        return false;
+    }
+    log::info!(
+        "Display {}: EDID preferred mode is now {:?}",
+        address,
+        preferred
+    );
+    dev.preferred = preferred;
+    true
+}
+
+/// Load an image into a head's cursor plane, or hide the cursor with `None`.
+///
+/// `image` is `DISPLAY_CURSOR_SIZE` square, in the framebuffer's pixel
+/// format. The device scans the cursor out on top of the framebuffer
+/// itself (the cursor queue's `UPDATE_CURSOR`), so moving it never touches
+/// the framebuffer. The cursor queue has no "hide" command; hiding uploads
+/// a fully transparent image instead.
+pub fn set_cursor(
+    address: &DeviceAddress,
+    image: Option<&[u8]>,
+    hot_x: u32,
+    hot_y: u32,
+) -> Result<(), &'static str> {
+    let mut devices = VIRTIO_GPU_DEVICES.write();
+    let dev = devices.get_mut(address).ok_or("GPU not initialized")?;
+
+    let size = panda_abi::DISPLAY_CURSOR_SIZE as usize;
+    let transparent;
+    let image = match image {
+        Some(image) => image,
+        None => {
+            transparent = alloc::vec![0u8; size * size * 4];
+            &transparent
+        }
+    };
+    let (x, y) = dev.cursor_position;
+    dev.gpu
+        .setup_cursor(image, x, y, hot_x, hot_y)
+        .map_err(|_| "GPU cursor update failed")
+}
+
+/// Move a head's cursor hotspot to a screen position (the cursor queue's
+/// `MOVE_CURSOR`).
+pub fn move_cursor(address: &DeviceAddress, x: u32, y: u32) -> Result<(), &'static str> {
+    let mut devices = VIRTIO_GPU_DEVICES.write();
+    let dev = devices.get_mut(address).ok_or("GPU not initialized")?;
+
+    dev.cursor_position = (x, y);
+    dev.gpu
+        .move_cursor(x, y)
+        .map_err(|_| "GPU cursor move failed")
+}
@@ -158,8 +158,10 @@ pub fn get_keyboard(address: &DeviceAddress) -> Option<Arc<Spinlock<VirtioKeyboa
 
 /// IRQ handler for virtio keyboard interrupts
 extern "x86-interrupt" fn keyboard_irq_handler(_stack_frame: InterruptStackFrame) {
-    // Poll all keyboards for new events
+    // Poll all keyboards for new events, and the pointers, which may share
+    // this IRQ line (see `virtio_pointer::pointer_irq_handler`)
     poll_all();
+    super::virtio_pointer::poll_all();
     // Send end-of-interrupt
     apic::eoi();
 }
@@ -0,0 +1,363 @@
+//! Virtio network device driver.
+//!
+//! Moves raw ethernet frames between the device and the `net:` scheme (see
+//! `resource::net`); there is no protocol stack in the kernel. Frames go
+//! through the ring the handle's owner attached (`panda_abi::net`): the
+//! interrupt handler copies received frames out of the virtqueue straight
+//! into its receive slots, and `OP_NET_KICK` copies queued frames from its
+//! transmit slots into the virtqueue. The ring is written through the
+//! kernel's own mapping of its pages, so this works whatever page table is
+//! active, and needs no allocation. Transmission is synchronous and happens
+//! in the kicking syscall.
+
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU32, Ordering};
+use log::debug;
+use panda_abi::net::{self as ring, RingHeader};
+use spinning_top::{RwSpinlock, Spinlock};
+use virtio_drivers::{
+    device::net::VirtIONet,
+    transport::pci::{PciTransport, bus::PciRoot},
+};
+use x86_64::structures::idt::InterruptStackFrame;
+
+use crate::{
+    apic::{self, ioapic},
+    device_address::DeviceAddress,
+    interrupts::{self, IrqHandlerFunc},
+    pci::{VirtioCommonConfig, device::PciDevice},
+    process::waker::IoWaker,
+    resource::{MailboxRef, SharedBuffer},
+};
+
+use super::virtio_block::MsixPciTransport;
+use super::virtio_hal::VirtioHal;
+
+/// The interrupt vector used for virtio-net MSI-X interrupts, the one
+/// after the IOMMU's fault vector.
+const VIRTIO_NET_MSIX_VECTOR: u8 = interrupts::MSI_BASE_VECTOR + 1;
+
+/// Descriptors per virtqueue.
+const QUEUE_SIZE: usize = 16;
+
+/// Size of each receive buffer handed to the device: the largest frame
+/// plus the virtio-net header, rounded up.
+const RX_BUFFER_LEN: usize = 2048;
+
+const MAX_FRAME_SIZE: usize = panda_abi::NET_MAX_FRAME_SIZE;
+
+/// Why a frame ring could not be used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetError {
+    /// No ring is attached.
+    NoRing,
+    /// The buffer is smaller than `net::RING_SIZE`.
+    RingTooSmall,
+    /// A queued frame is shorter than an ethernet header or longer than
+    /// `NET_MAX_FRAME_SIZE`. It is dropped.
+    InvalidFrame,
+    /// The device reported an error.
+    IoError,
+}
+
+/// The kernel's side of a `net:` frame ring.
+///
+/// The counters the kernel owns are kept here and only copied out to the
+/// header, so a driver scribbling on them cannot confuse the kernel; the
+/// driver's counters are read back each time and only trusted as far as
+/// they are consistent with the kernel's.
+struct FrameRing {
+    buffer: Arc<SharedBuffer>,
+    rx_head: u32,
+    tx_tail: u32,
+}
+
+impl FrameRing {
+    fn new(buffer: Arc<SharedBuffer>) -> Result<Self, NetError> {
+        if buffer.kernel_address(ring::RING_SIZE - 1).is_none() {
+            return Err(NetError::RingTooSmall);
+        }
+        let ring = Self {
+            buffer,
+            rx_head: 0,
+            tx_tail: 0,
+        };
+        let header = ring.header();
+        for counter in [
+            &header.rx_head,
+            &header.rx_tail,
+            &header.tx_head,
+            &header.tx_tail,
+        ] {
+            counter.store(0, Ordering::Release);
+        }
+        Ok(ring)
+    }
+
+    fn header(&self) -> &RingHeader {
+        let addr = self.buffer.kernel_address(0).expect("checked in new");
+        // SAFETY: the header lies within the first page, which the buffer
+        // keeps mapped in the kernel heap for as long as `self` holds it,
+        // and every field is an atomic, so the driver writing to it at the
+        // same time is allowed.
+        unsafe { &*addr.as_ptr::<RingHeader>() }
+    }
+
+    /// The slot at `offset`: its length field and the frame bytes after it.
+    fn slot(&self, offset: usize) -> (&AtomicU32, *mut u8) {
+        let addr = self.buffer.kernel_address(offset).expect("checked in new");
+        // SAFETY: as for the header; slots are aligned and never straddle
+        // a page (`panda_abi::net`).
+        let len = unsafe { AtomicU32::from_ptr(addr.as_mut_ptr()) };
+        let data = unsafe { addr.as_mut_ptr::<u8>().add(ring::SLOT_LEN_SIZE) };
+        (len, data)
+    }
+
+    /// Received frames the driver has not taken yet, or `None` if its
+    /// counter makes no sense.
+    fn rx_pending(&self) -> Option<u32> {
+        let tail = self.header().rx_tail.load(Ordering::Acquire);
+        let pending = self.rx_head.wrapping_sub(tail);
+        (pending <= ring::RING_SLOTS as u32).then_some(pending)
+    }
+
+    /// Write a received frame into the next slot. Returns false, dropping
+    /// the frame, if the ring is full.
+    fn push(&mut self, frame: &[u8]) -> bool {
+        if !self
+            .rx_pending()
+            .is_some_and(|pending| pending < ring::RING_SLOTS as u32)
+        {
+            return false;
+        }
+        let len = frame.len().min(MAX_FRAME_SIZE);
+        let (slot_len, data) = self.slot(ring::rx_slot(self.rx_head));
+        // SAFETY: `data` has room for `MAX_FRAME_SIZE` bytes of the slot.
+        unsafe { core::ptr::copy_nonoverlapping(frame.as_ptr(), data, len) };
+        slot_len.store(len as u32, Ordering::Relaxed);
+        self.rx_head = self.rx_head.wrapping_add(1);
+        self.header().rx_head.store(self.rx_head, Ordering::Release);
+        true
+    }
+
+    /// The next queued frame to send, as the slot's length and data, or
+    /// `None` if nothing is queued (or the driver's counter makes no
+    /// sense).
+    fn next_tx(&self) -> Option<(usize, *const u8)> {
+        let head = self.header().tx_head.load(Ordering::Acquire);
+        let queued = head.wrapping_sub(self.tx_tail);
+        if queued == 0 || queued > ring::RING_SLOTS as u32 {
+            return None;
+        }
+        let (slot_len, data) = self.slot(ring::tx_slot(self.tx_tail));
+        Some((slot_len.load(Ordering::Relaxed) as usize, data))
+    }
+
+    /// Mark the frame [`next_tx`](Self::next_tx) returned as done with.
+    fn pop_tx(&mut self) {
+        self.tx_tail = self.tx_tail.wrapping_add(1);
+        self.header().tx_tail.store(self.tx_tail, Ordering::Release);
+    }
+}
+
+/// A virtio network device
+pub struct VirtioNet {
+    device: VirtIONet<VirtioHal, MsixPciTransport, QUEUE_SIZE>,
+    /// The current `net:` handle's ring, once it has attached one. Frames
+    /// received without one are dropped.
+    ring: Option<FrameRing>,
+    waker: Arc<IoWaker>,
+    address: DeviceAddress,
+    mac: [u8; 6],
+    /// Mailboxes of the current `net:` handle, told about received frames.
+    mailboxes: Vec<MailboxRef>,
+}
+
+impl VirtioNet {
+    /// The device's ethernet address.
+    pub fn mac_address(&self) -> [u8; 6] {
+        self.mac
+    }
+
+    /// Get the device address
+    pub fn address(&self) -> &DeviceAddress {
+        &self.address
+    }
+
+    /// Get the waker for this device
+    pub fn waker(&self) -> Arc<IoWaker> {
+        self.waker.clone()
+    }
+
+    /// Check if received frames are waiting in the ring
+    pub fn has_frames(&self) -> bool {
+        self.ring
+            .as_ref()
+            .is_some_and(|ring| ring.rx_pending().is_some_and(|pending| pending > 0))
+    }
+
+    /// Attach a mailbox to receive `EVENT_NET_RX`.
+    pub fn attach_mailbox(&mut self, mailbox_ref: MailboxRef) {
+        self.mailboxes.push(mailbox_ref);
+    }
+
+    /// Move frames through `buffer` from now on, resetting its header.
+    pub fn attach_ring(&mut self, buffer: Arc<SharedBuffer>) -> Result<(), NetError> {
+        self.ring = Some(FrameRing::new(buffer)?);
+        Ok(())
+    }
+
+    /// Forget the previous owner's mailboxes and ring. Called when a `net:`
+    /// handle is opened or closed.
+    pub fn reset(&mut self) {
+        self.mailboxes.clear();
+        self.ring = None;
+        self.waker.clear();
+    }
+
+    /// Transmit the frames queued in the ring, waiting for the device to
+    /// take each. Returns how many were sent; any left once the device's
+    /// queue is full stay queued for the next kick. A malformed frame is
+    /// dropped and reported, after the frames before it have gone.
+    pub fn kick(&mut self) -> Result<usize, NetError> {
+        self.poll();
+        let ring = self.ring.as_mut().ok_or(NetError::NoRing)?;
+        let mut sent = 0;
+        while let Some((len, data)) = ring.next_tx() {
+            if !(panda_abi::NET_MIN_FRAME_SIZE..=MAX_FRAME_SIZE).contains(&len) {
+                ring.pop_tx();
+                return Err(NetError::InvalidFrame);
+            }
+            if !self.device.can_send() {
+                break;
+            }
+            let mut tx = self.device.new_tx_buffer(len);
+            // SAFETY: `data` is a slot's frame bytes, and `len` fits in it.
+            let frame = unsafe { core::slice::from_raw_parts(data, len) };
+            tx.packet_mut().copy_from_slice(frame);
+            ring.pop_tx();
+            self.device.send(tx).map_err(|_| NetError::IoError)?;
+            sent += 1;
+        }
+        Ok(sent)
+    }
+
+    /// Move completed receive buffers into the ring and hand them back to
+    /// the device (called from IRQ handler)
+    pub fn poll(&mut self) {
+        let mut received = false;
+        while let Ok(buffer) = self.device.receive() {
+            if let Some(ring) = &mut self.ring {
+                received |= ring.push(buffer.packet());
+            }
+            if let Err(err) = self.device.recycle_rx_buffer(buffer) {
+                debug!(
+                    "virtio-net {}: failed to recycle rx buffer: {:?}",
+                    self.address, err
+                );
+            }
+        }
+
+        self.device.ack_interrupt();
+
+        if received {
+            for mailbox in &self.mailboxes {
+                mailbox.post_event(panda_abi::EVENT_NET_RX);
+            }
+            self.waker.wake();
+        }
+    }
+}
+
+/// Registry of network devices by device address
+static NET_DEVICES: RwSpinlock<BTreeMap<DeviceAddress, Arc<Spinlock<VirtioNet>>>> =
+    RwSpinlock::new(BTreeMap::new());
+
+/// Get a network device by its device address
+pub fn get_device(address: &DeviceAddress) -> Option<Arc<Spinlock<VirtioNet>>> {
+    NET_DEVICES.read().get(address).cloned()
+}
+
+/// IRQ handler for virtio-net interrupts
+extern "x86-interrupt" fn net_irq_handler(_stack_frame: InterruptStackFrame) {
+    poll_all_nonblocking();
+    apic::eoi();
+}
+
+/// Poll every device that isn't locked. A device busy in a syscall is
+/// caught up by its next kick, or when its handle's events are polled.
+fn poll_all_nonblocking() {
+    let devices = NET_DEVICES.read();
+    for device in devices.values() {
+        if let Some(mut dev) = device.try_lock() {
+            dev.poll();
+        }
+    }
+}
+
+/// Initialize a virtio network device from a PCI device
+pub fn init_from_pci_device(pci_device: PciDevice) {
+    let pci_address = pci_device.address();
+    let address = DeviceAddress::Pci {
+        bus: pci_address.bus,
+        device: pci_address.slot,
+        function: pci_address.function,
+    };
+
+    debug!("Initializing virtio network device at {}", address);
+
+    // MSI-X must be enabled before the transport sets up the queues
+    let msix_cap = pci_device.enable_msix();
+    let virtio_common_config = VirtioCommonConfig::find(&pci_device);
+
+    if let Some(ref cap) = msix_cap {
+        cap.configure_entry(0, VIRTIO_NET_MSIX_VECTOR, 0);
+        interrupts::set_interrupt_handler(
+            VIRTIO_NET_MSIX_VECTOR,
+            Some(net_irq_handler as IrqHandlerFunc),
+        );
+    }
+
+    let mut root = PciRoot::new(pci_device.clone());
+    let device_function = pci_address.into();
+    let inner_transport = PciTransport::new::<VirtioHal, PciDevice>(&mut root, device_function)
+        .expect("Could not create PCI transport for virtio network device");
+    let transport = MsixPciTransport::new(inner_transport, virtio_common_config, 0);
+
+    let device =
+        VirtIONet::<VirtioHal, MsixPciTransport, QUEUE_SIZE>::new(transport, RX_BUFFER_LEN)
+            .expect("Could not initialize virtio network device");
+    let mac = device.mac_address();
+
+    debug!(
+        "Virtio network device {}: MAC {:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+        address, mac[0], mac[1], mac[2], mac[3], mac[4], mac[5]
+    );
+
+    let net = VirtioNet {
+        device,
+        ring: None,
+        waker: IoWaker::new(),
+        address: address.clone(),
+        mac,
+        mailboxes: Vec::new(),
+    };
+    NET_DEVICES
+        .write()
+        .insert(address, Arc::new(Spinlock::new(net)));
+
+    // Fall back to the legacy INTx line if MSI-X isn't available
+    if msix_cap.is_none() {
+        let irq_line = pci_device.interrupt_line();
+        let irq_pin = pci_device.interrupt_pin();
+        if irq_pin != 0 && irq_line != 0 && irq_line != 0xFF {
+            debug!("Registering legacy IRQ handler for IRQ {}", irq_line);
+            interrupts::set_irq_handler(irq_line, Some(net_irq_handler as IrqHandlerFunc));
+            ioapic::configure_irq(irq_line, 0x20 + irq_line);
+        }
+    }
+
+    debug!("Virtio network device initialized");
+}
@@ -0,0 +1,234 @@
+//! Virtio pointer driver: mice (relative motion) and tablets (absolute
+//! position).
+//!
+//! Both are virtio-input devices like the keyboard; what tells them apart is
+//! which event types the device advertises, probed once at init. Events are
+//! kept in their raw evdev form (`EV_REL`/`EV_ABS`/`EV_KEY`/`EV_SYN`) and
+//! read through the `pointer:` scheme (see `resource::pointer`). Attached
+//! mailboxes get `EVENT_POINTER` once per `SYN_REPORT`, i.e. once per
+//! complete motion or button report rather than once per axis.
+
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use log::debug;
+use spinning_top::{RwSpinlock, Spinlock};
+use virtio_drivers::{
+    device::input::VirtIOInput,
+    transport::pci::{PciTransport, bus::PciRoot},
+};
+use x86_64::structures::idt::InterruptStackFrame;
+
+use crate::{
+    apic::{self, ioapic},
+    device_address::DeviceAddress,
+    interrupts::{self, IrqHandlerFunc},
+    pci::device::PciDevice,
+    process::waker::IoWaker,
+    resource::MailboxRef,
+};
+
+use super::virtio_hal::VirtioHal;
+use super::virtio_keyboard::InputEvent;
+
+// Event types and codes from the evdev encoding (see libpanda::pointer).
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const EV_ABS: u16 = 0x03;
+const SYN_REPORT: u16 = 0x00;
+const ABS_X: u8 = 0x00;
+const ABS_Y: u8 = 0x01;
+const REL_WHEEL: u16 = 0x08;
+
+/// Events held for the reader before the oldest is dropped. A report is
+/// typically four or five events, so this is several dozen reports.
+const MAX_BUFFERED_EVENTS: usize = 256;
+
+/// A virtio mouse or tablet.
+pub struct VirtioPointer {
+    device: VirtIOInput<VirtioHal, PciTransport>,
+    info: panda_abi::PointerInfo,
+    buffer: VecDeque<InputEvent>,
+    waker: Arc<IoWaker>,
+    address: DeviceAddress,
+    /// Mailboxes attached to this pointer for event delivery.
+    mailboxes: Vec<MailboxRef>,
+}
+
+impl VirtioPointer {
+    /// Whether the device reports absolute positions, and their range.
+    pub fn info(&self) -> panda_abi::PointerInfo {
+        self.info
+    }
+
+    /// Pop an event from the buffer.
+    pub fn pop_event(&mut self) -> Option<InputEvent> {
+        self.buffer.pop_front()
+    }
+
+    /// Check if the buffer has events.
+    pub fn has_events(&self) -> bool {
+        !self.buffer.is_empty()
+    }
+
+    /// Get the waker for this pointer.
+    pub fn waker(&self) -> Arc<IoWaker> {
+        self.waker.clone()
+    }
+
+    /// Get the device address.
+    pub fn address(&self) -> &DeviceAddress {
+        &self.address
+    }
+
+    /// Attach a mailbox to receive pointer events.
+    pub fn attach_mailbox(&mut self, mailbox_ref: MailboxRef) {
+        self.mailboxes.push(mailbox_ref);
+    }
+
+    /// Poll the device for new events (called from IRQ handler).
+    pub fn poll(&mut self) {
+        let mut reports = 0;
+        while let Some(event) = self.device.pop_pending_event() {
+            if !matches!(event.event_type, EV_SYN | EV_KEY | EV_REL | EV_ABS) {
+                continue;
+            }
+            if self.buffer.len() == MAX_BUFFERED_EVENTS {
+                self.buffer.pop_front();
+            }
+            self.buffer.push_back(InputEvent {
+                event_type: event.event_type,
+                code: event.code,
+                value: event.value,
+            });
+            if event.event_type == EV_SYN && event.code == SYN_REPORT {
+                reports += 1;
+            }
+        }
+
+        self.device.ack_interrupt();
+
+        if reports > 0 {
+            for mailbox in &self.mailboxes {
+                mailbox.post_event(panda_abi::EVENT_POINTER);
+            }
+        }
+        if self.has_events() {
+            self.waker.wake();
+        }
+    }
+}
+
+/// Work out what kind of pointer `device` is from the event types it
+/// advertises.
+fn probe(device: &mut VirtIOInput<VirtioHal, PciTransport>) -> panda_abi::PointerInfo {
+    let mut info = panda_abi::PointerInfo::default();
+
+    let has_abs = device
+        .ev_bits(EV_ABS as u8)
+        .is_ok_and(|bits| bits.iter().any(|&b| b != 0));
+    if has_abs && let (Ok(x), Ok(y)) = (device.abs_info(ABS_X), device.abs_info(ABS_Y)) {
+        info.flags |= panda_abi::POINTER_ABSOLUTE;
+        info.x_min = x.min;
+        info.x_max = x.max;
+        info.y_min = y.min;
+        info.y_max = y.max;
+    }
+
+    let wheel_byte = (REL_WHEEL / 8) as usize;
+    let has_wheel = device.ev_bits(EV_REL as u8).is_ok_and(|bits| {
+        bits.get(wheel_byte)
+            .is_some_and(|b| b & (1 << (REL_WHEEL % 8)) != 0)
+    });
+    if has_wheel {
+        info.flags |= panda_abi::POINTER_WHEEL;
+    }
+
+    info
+}
+
+/// Registry of pointers by device address.
+static POINTERS: RwSpinlock<BTreeMap<DeviceAddress, Arc<Spinlock<VirtioPointer>>>> =
+    RwSpinlock::new(BTreeMap::new());
+
+/// Get a pointer by its device address.
+pub fn get_pointer(address: &DeviceAddress) -> Option<Arc<Spinlock<VirtioPointer>>> {
+    POINTERS.read().get(address).cloned()
+}
+
+/// IRQ handler for virtio pointer interrupts.
+///
+/// Input devices can share a legacy IRQ line, and whichever driver
+/// registers last owns the vector, so this polls the keyboards too (and the
+/// keyboard's handler polls the pointers).
+extern "x86-interrupt" fn pointer_irq_handler(_stack_frame: InterruptStackFrame) {
+    poll_all();
+    super::virtio_keyboard::poll_all();
+    apic::eoi();
+}
+
+/// Initialize a virtio mouse or tablet from a PCI device.
+pub fn init_from_pci_device(pci_device: PciDevice) {
+    let pci_address = pci_device.address();
+    let address = DeviceAddress::Pci {
+        bus: pci_address.bus,
+        device: pci_address.slot,
+        function: pci_address.function,
+    };
+
+    let irq_line = pci_device.interrupt_line();
+    let irq_pin = pci_device.interrupt_pin();
+
+    debug!(
+        "Initializing virtio pointer at {} (IRQ line={}, pin={})",
+        address, irq_line, irq_pin
+    );
+
+    let mut root = PciRoot::new(pci_device.clone());
+    let device_function = pci_address.into();
+    let transport = PciTransport::new::<VirtioHal, PciDevice>(&mut root, device_function)
+        .expect("Could not create PCI transport for virtio pointer");
+
+    let mut device = VirtIOInput::<VirtioHal, PciTransport>::new(transport)
+        .expect("Could not initialize virtio pointer");
+    let info = probe(&mut device);
+
+    debug!(
+        "Virtio pointer at {} is {}",
+        address,
+        if info.flags & panda_abi::POINTER_ABSOLUTE != 0 {
+            "absolute"
+        } else {
+            "relative"
+        }
+    );
+
+    let pointer = VirtioPointer {
+        device,
+        info,
+        buffer: VecDeque::with_capacity(MAX_BUFFERED_EVENTS),
+        waker: IoWaker::new(),
+        address: address.clone(),
+        mailboxes: Vec::new(),
+    };
+
+    POINTERS
+        .write()
+        .insert(address, Arc::new(Spinlock::new(pointer)));
+
+    if irq_pin != 0 && irq_line != 0 && irq_line != 0xFF {
+        debug!("Registering pointer IRQ handler for IRQ {}", irq_line);
+        interrupts::set_irq_handler(irq_line, Some(pointer_irq_handler as IrqHandlerFunc));
+        let vector = 0x20 + irq_line;
+        ioapic::configure_irq(irq_line, vector);
+    }
+}
+
+/// Poll all pointers for new events (called from IRQ handler).
+pub fn poll_all() {
+    let pointers = POINTERS.read();
+    for pointer in pointers.values() {
+        pointer.lock().poll();
+    }
+}
@@ -119,6 +119,16 @@ impl Handle {
         self.resource.as_display()
     }
 
+    /// Get this handle's resource as an exclusively-owned network device.
+    pub fn as_net(&self) -> Option<&crate::resource::NetDevice> {
+        self.resource.as_net()
+    }
+
+    /// Get this handle's resource as a pointer device.
+    pub fn as_pointer(&self) -> Option<&crate::resource::PointerDevice> {
+        self.resource.as_pointer()
+    }
+
     /// Get a waker for blocking on this handle.
     pub fn waker(&self) -> Option<Arc<IoWaker>> {
         self.resource.waker()
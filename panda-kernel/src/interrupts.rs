@@ -42,6 +42,11 @@ pub fn set_page_fault_handler(handler: Option<PageFaultHandlerFunc>) {
 /// IRQ base vector offset (IRQs 0-15 map to vectors 0x20-0x2F)
 const IRQ_BASE_VECTOR: u8 = 0x20;
 
+/// First vector past those the IOAPIC's 24 lines map to (0x20-0x37). MSI
+/// and MSI-X sources take fixed vectors from here, so a device falling back
+/// to a legacy INTx line can never land on one of them.
+pub const MSI_BASE_VECTOR: u8 = IRQ_BASE_VECTOR + 24;
+
 /// Set a handler for an IRQ line (0-255).
 ///
 /// IRQ lines are mapped to interrupt vectors starting at 0x20.
@@ -0,0 +1,75 @@
+//! Locating the DMAR (and IVRS) ACPI tables.
+//!
+//! The `acpi` crate only finds and maps tables; the sub-table structures are
+//! parsed by `iommu::vtd::dmar` from the raw bytes copied out here.
+
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+use ::acpi::AcpiTable;
+use ::acpi::sdt::{SdtHeader, Signature};
+use ::iommu::vtd::dmar::{self, DmarError, DmarTable};
+
+/// A table known only by its header; the body follows in the mapping.
+#[repr(C, packed)]
+struct Dmar {
+    header: SdtHeader,
+}
+
+unsafe impl AcpiTable for Dmar {
+    const SIGNATURE: Signature = Signature::DMAR;
+
+    fn header(&self) -> &SdtHeader {
+        &self.header
+    }
+}
+
+#[repr(C, packed)]
+struct Ivrs {
+    header: SdtHeader,
+}
+
+unsafe impl AcpiTable for Ivrs {
+    const SIGNATURE: Signature = Signature::IVRS;
+
+    fn header(&self) -> &SdtHeader {
+        &self.header
+    }
+}
+
+/// Why the DMAR table could not be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DmarReadError {
+    /// Firmware did not provide a DMAR table (no VT-d).
+    NotPresent,
+    /// The table was present but malformed.
+    Parse(DmarError),
+}
+
+/// Find the DMAR table and parse it.
+pub fn read_dmar() -> Result<DmarTable, DmarReadError> {
+    let bytes = table_bytes::<Dmar>().ok_or(DmarReadError::NotPresent)?;
+    dmar::parse(&bytes).map_err(DmarReadError::Parse)
+}
+
+/// Whether firmware describes an AMD-Vi IOMMU.
+pub fn ivrs_present() -> bool {
+    table_bytes::<Ivrs>().is_some()
+}
+
+/// Copy a whole table (header included) out of its ACPI mapping.
+fn table_bytes<T: AcpiTable>() -> Option<Vec<u8>> {
+    let bytes = RefCell::new(None);
+    crate::acpi::with_table::<T>(|table| {
+        let Some(table) = table else {
+            return;
+        };
+        let length = table.header().length as usize;
+        let base = table.get_ref() as *const T as *const u8;
+        // Safety: the acpi crate maps the full `length` bytes of a table,
+        // header included, for as long as the mapping is borrowed.
+        let slice = unsafe { core::slice::from_raw_parts(base, length) };
+        *bytes.borrow_mut() = Some(slice.to_vec());
+    });
+    bytes.into_inner()
+}
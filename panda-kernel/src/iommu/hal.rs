@@ -0,0 +1,70 @@
+//! Kernel implementations of the `iommu` crate's hardware abstraction traits.
+
+use alloc::collections::BTreeMap;
+
+use ::iommu::{FrameAllocator, MmioAccess};
+use spinning_top::Spinlock;
+use x86_64::PhysAddr;
+
+use crate::memory::{self, Frame, PhysicalMapping};
+
+/// Page table frames handed to the IOMMU, keyed by physical address. The
+/// crate only gives back the physical address on free, so the `Frame`
+/// guards live here until then.
+static TABLE_FRAMES: Spinlock<BTreeMap<u64, Frame>> = Spinlock::new(BTreeMap::new());
+
+/// Allocates IOMMU page table frames from the kernel heap.
+#[derive(Clone, Copy)]
+pub struct KernelFrameAllocator;
+
+// Safety: `allocate_frame` returns 4 KB-aligned, exclusively owned frames
+// whose heap address is valid for 4096 bytes; the `Frame` is kept alive in
+// `TABLE_FRAMES` until `dealloc_frame`.
+unsafe impl FrameAllocator for KernelFrameAllocator {
+    fn alloc_frame(&self) -> Option<(u64, *mut u8)> {
+        let frame = memory::allocate_frame();
+        let phys = frame.start_address().as_u64();
+        let virt = frame.virtual_address().as_mut_ptr();
+        TABLE_FRAMES.lock().insert(phys, frame);
+        Some((phys, virt))
+    }
+
+    unsafe fn dealloc_frame(&self, phys: u64) {
+        TABLE_FRAMES.lock().remove(&phys);
+    }
+}
+
+/// Number of page table frames currently owned by the IOMMU.
+pub fn table_frame_count() -> usize {
+    TABLE_FRAMES.lock().len()
+}
+
+/// Register access for one remapping unit through a kernel MMIO mapping.
+pub struct KernelMmioAccess(PhysicalMapping);
+
+impl KernelMmioAccess {
+    /// Map `size` bytes of registers at physical address `base`.
+    pub fn new(base: u64, size: usize) -> Self {
+        Self(PhysicalMapping::new(PhysAddr::new(base), size))
+    }
+}
+
+// Safety: `PhysicalMapping` accesses are volatile and bounds-checked against
+// the mapped register region.
+unsafe impl MmioAccess for KernelMmioAccess {
+    unsafe fn read_u32(&self, offset: usize) -> u32 {
+        self.0.read(offset)
+    }
+
+    unsafe fn write_u32(&self, offset: usize, val: u32) {
+        self.0.write(offset, val)
+    }
+
+    unsafe fn read_u64(&self, offset: usize) -> u64 {
+        self.0.read(offset)
+    }
+
+    unsafe fn write_u64(&self, offset: usize, val: u64) {
+        self.0.write(offset, val)
+    }
+}
@@ -0,0 +1,303 @@
+//! Per-device DMA domains for claimed devices.
+//!
+//! `OP_DEVICE_CLAIM` moves a PCI device out of the passthrough domain into
+//! a fresh, empty domain of its own. From then on the device can only reach
+//! memory its driver allocated with `OP_DMA_ALLOC`, each allocation mapped
+//! at an I/O virtual address (IOVA) handed back to the driver for its
+//! descriptors. When the owning process exits, every allocation is first
+//! unmapped from the device's domain, which then blocks all of its DMA, so
+//! a device still running cannot write to that memory as it is freed. Only
+//! then does the device go back to the passthrough domain, where, like any
+//! unclaimed device, it can reach everything below 4 GiB again.
+
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use ::iommu::IovaAllocator;
+use log::{info, warn};
+use spinning_top::Spinlock;
+use x86_64::VirtAddr;
+
+use crate::device::{DEVICE_REGISTRY, DeviceId, DeviceInfo};
+use crate::pci::device::PciDeviceAddress;
+use crate::process::{Process, ProcessId};
+use crate::resource::{BufferError, SharedBuffer};
+
+use super::{DomainId, IommuDomain, IommuError, IommuFlags, PciAddress};
+
+const PAGE_SIZE: usize = ::iommu::PAGE_SIZE as usize;
+
+/// Start of the IOVA space. Page zero stays unmapped so a zeroed
+/// descriptor faults rather than hitting a live buffer.
+const IOVA_START: u64 = PAGE_SIZE as u64;
+
+/// End of the IOVA space. Staying below 4 GiB keeps every IOVA usable by
+/// devices limited to 32-bit DMA addresses.
+const IOVA_END: u64 = 1 << 32;
+
+/// Why a DMA allocation request failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DmaError {
+    /// No IOMMU, or the device is not one that gets its own domain.
+    NotSupported,
+    /// The calling process has not claimed the device.
+    NotOwner,
+    /// No allocation of that size at that address.
+    InvalidAddress,
+    /// The backing memory could not be allocated.
+    Buffer(BufferError),
+    /// The IOMMU rejected the mapping or ran out of IOVA space.
+    Iommu(IommuError),
+}
+
+/// Why a claimed device could not be given a domain of its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttachError {
+    /// A kernel driver drives the device, and its DMA must keep reaching
+    /// the kernel's memory.
+    KernelDriver,
+    /// The IOMMU could not create the domain or move the device into it.
+    Iommu(IommuError),
+}
+
+impl From<IommuError> for AttachError {
+    fn from(err: IommuError) -> Self {
+        Self::Iommu(err)
+    }
+}
+
+/// One `OP_DMA_ALLOC` allocation.
+struct DmaAllocation {
+    iova: u64,
+    /// Page-rounded size.
+    size: usize,
+    buffer: Arc<SharedBuffer>,
+}
+
+/// A claimed device's private domain and the memory mapped into it.
+struct DeviceDomain {
+    owner: ProcessId,
+    device: PciAddress,
+    domain: IommuDomain,
+    iova: IovaAllocator,
+    /// Keyed by the allocation's address in the owner's address space.
+    allocations: BTreeMap<usize, DmaAllocation>,
+}
+
+static DOMAINS: Spinlock<BTreeMap<DeviceId, DeviceDomain>> = Spinlock::new(BTreeMap::new());
+
+/// Give a newly claimed device a domain of its own. Refuses a device a
+/// kernel driver drives, whose DMA an empty domain would cut off. Otherwise
+/// does nothing when DMA remapping is disabled or the device isn't on PCI.
+pub fn attach(device_id: DeviceId, owner: ProcessId) -> Result<(), AttachError> {
+    let Some(DeviceInfo::Pci { address, .. }) = DEVICE_REGISTRY.lock().info(device_id) else {
+        return Ok(());
+    };
+    if crate::devices::is_kernel_bound(PciDeviceAddress {
+        segment: address.segment,
+        bus: address.bus,
+        slot: address.device,
+        function: address.function,
+    }) {
+        return Err(AttachError::KernelDriver);
+    }
+    let Some(iommu) = super::get() else {
+        return Ok(());
+    };
+    let device = super::to_iommu_address(address);
+
+    let domain = IommuDomain::new(iommu)?;
+    iommu.assign_device(domain.id(), device)?;
+
+    let mut iova = IovaAllocator::new(IOVA_START, IOVA_END);
+    for (base, size) in super::reserved_regions() {
+        iova.reserve(base, size);
+    }
+
+    info!(
+        "IOMMU: device {} ({}) isolated in domain {}",
+        device_id,
+        device,
+        domain.id()
+    );
+    DOMAINS.lock().insert(
+        device_id,
+        DeviceDomain {
+            owner,
+            device,
+            domain,
+            iova,
+            allocations: BTreeMap::new(),
+        },
+    );
+    Ok(())
+}
+
+/// Return every device owned by `pid` to the passthrough domain and free
+/// its DMA memory. Called on process exit, via `device::release_all_owned_by`.
+pub fn detach_all_owned_by(pid: ProcessId) {
+    let detached: Vec<DeviceDomain> = {
+        let mut domains = DOMAINS.lock();
+        let ids: Vec<DeviceId> = domains
+            .iter()
+            .filter(|(_, entry)| entry.owner == pid)
+            .map(|(&id, _)| id)
+            .collect();
+        ids.iter().filter_map(|id| domains.remove(id)).collect()
+    };
+    for entry in detached {
+        restore(entry);
+    }
+}
+
+/// The domain a claimed device is isolated in, if any.
+pub fn domain_of(device_id: DeviceId) -> Option<DomainId> {
+    DOMAINS
+        .lock()
+        .get(&device_id)
+        .map(|entry| entry.domain.id())
+}
+
+fn restore(entry: DeviceDomain) {
+    // Log anything the departing driver's device tripped over.
+    super::drain_faults();
+
+    let DeviceDomain {
+        device,
+        domain,
+        allocations,
+        ..
+    } = entry;
+    let iommu = domain.iommu();
+
+    // Empty the domain while the device is still in it, so anything it
+    // keeps doing is blocked; the IOTLB flush on unmap waits for DMA
+    // already in flight to drain.
+    for allocation in allocations.values() {
+        iommu.unmap(domain.id(), allocation.iova, allocation.size);
+    }
+    info!(
+        "IOMMU: {} blocked in domain {}, freeing {} DMA allocation(s)",
+        device,
+        domain.id(),
+        allocations.len()
+    );
+    drop(allocations);
+
+    if let Some(passthrough) = super::passthrough_domain()
+        && let Err(err) = iommu.assign_device(passthrough, device)
+    {
+        // Destroying the domain below still blocks the device.
+        warn!(
+            "IOMMU: could not return {} to passthrough: {:?}",
+            device, err
+        );
+    }
+    drop(domain);
+}
+
+/// Allocate `size` bytes of DMA memory for `device_id`, mapped into `proc`
+/// (which must own the device) and into the device's domain. Returns the
+/// address in `proc` and the IOVA the device must use.
+pub fn dma_alloc(
+    proc: &mut Process,
+    device_id: DeviceId,
+    size: usize,
+) -> Result<(usize, u64), DmaError> {
+    check_owner(proc, device_id)?;
+    let mut domains = DOMAINS.lock();
+    let entry = domains.get_mut(&device_id).ok_or(DmaError::NotSupported)?;
+
+    let (buffer, vaddr) = SharedBuffer::alloc(proc, size).map_err(DmaError::Buffer)?;
+    let pages: Vec<u64> = buffer.frame_addresses().map(|addr| addr.as_u64()).collect();
+    let mapped_size = pages.len() * PAGE_SIZE;
+
+    let Some(iova) = entry.iova.alloc(mapped_size as u64, PAGE_SIZE as u64) else {
+        proc.free_buffer_vaddr(VirtAddr::new(vaddr as u64), pages.len());
+        return Err(DmaError::Iommu(IommuError::OutOfMemory));
+    };
+
+    if let Err(err) = map_pages(&entry.domain, iova, &pages) {
+        entry.iova.free(iova, mapped_size as u64);
+        proc.free_buffer_vaddr(VirtAddr::new(vaddr as u64), pages.len());
+        return Err(DmaError::Iommu(err));
+    }
+
+    entry.allocations.insert(
+        vaddr,
+        DmaAllocation {
+            iova,
+            size: mapped_size,
+            buffer,
+        },
+    );
+    Ok((vaddr, iova))
+}
+
+/// Free an allocation made by [`dma_alloc`]. The device loses access before
+/// the memory is released.
+pub fn dma_free(
+    proc: &mut Process,
+    device_id: DeviceId,
+    vaddr: usize,
+    size: usize,
+) -> Result<(), DmaError> {
+    check_owner(proc, device_id)?;
+    let mut domains = DOMAINS.lock();
+    let entry = domains.get_mut(&device_id).ok_or(DmaError::NotSupported)?;
+
+    let allocated = entry
+        .allocations
+        .get(&vaddr)
+        .map(|allocation| allocation.size);
+    if allocated != Some(size.next_multiple_of(PAGE_SIZE)) {
+        return Err(DmaError::InvalidAddress);
+    }
+    let allocation = entry.allocations.remove(&vaddr).expect("checked above");
+
+    entry
+        .domain
+        .iommu()
+        .unmap(entry.domain.id(), allocation.iova, allocation.size);
+    entry.iova.free(allocation.iova, allocation.size as u64);
+    proc.free_buffer_vaddr(VirtAddr::new(vaddr as u64), allocation.size / PAGE_SIZE);
+    drop(allocation.buffer);
+    Ok(())
+}
+
+fn check_owner(proc: &Process, device_id: DeviceId) -> Result<(), DmaError> {
+    if DEVICE_REGISTRY.lock().owner(device_id) == Some(proc.id()) {
+        Ok(())
+    } else {
+        Err(DmaError::NotOwner)
+    }
+}
+
+/// Map `pages` at consecutive IOVAs from `iova`, one `map` call per
+/// physically contiguous run. Unmaps everything on failure.
+fn map_pages(domain: &IommuDomain, iova: u64, pages: &[u64]) -> Result<(), IommuError> {
+    let iommu = domain.iommu();
+    let mut mapped = 0;
+    while mapped < pages.len() {
+        let start = pages[mapped];
+        let run = pages[mapped..]
+            .iter()
+            .enumerate()
+            .take_while(|&(i, &phys)| phys == start + (i * PAGE_SIZE) as u64)
+            .count();
+        let offset = (mapped * PAGE_SIZE) as u64;
+        if let Err(err) = iommu.map(
+            domain.id(),
+            iova + offset,
+            start,
+            run * PAGE_SIZE,
+            IommuFlags::READ_WRITE,
+        ) {
+            iommu.unmap(domain.id(), iova, mapped * PAGE_SIZE);
+            return Err(err);
+        }
+        mapped += run;
+    }
+    Ok(())
+}
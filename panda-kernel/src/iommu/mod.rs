@@ -0,0 +1,205 @@
+//! DMA remapping.
+//!
+//! At boot every PCI device is placed in a passthrough domain, so
+//! kernel-resident drivers see no change. A device claimed by a userspace
+//! driver is moved into a domain of its own (see [`manager`]) in which only
+//! the memory that driver allocated with `OP_DMA_ALLOC` is reachable.
+//!
+//! The hardware-specific work lives in the `iommu` crate; this module
+//! supplies its frames and register mappings, finds the units through ACPI,
+//! and logs translation faults. See `plans/iommu.md`.
+
+pub mod dmar_acpi;
+mod hal;
+pub mod manager;
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use ::iommu::vtd::{VtdIommu, VtdUnit};
+use log::{info, warn};
+use spinning_top::RwSpinlock;
+use x86_64::structures::idt::InterruptStackFrame;
+
+pub use ::iommu::{
+    DomainId, FaultKind, Iommu, IommuDomain, IommuError, IommuFault, IommuFlags, PciAddress,
+};
+pub use hal::table_frame_count;
+
+use crate::device::DEVICE_REGISTRY;
+use crate::interrupts::{self, IrqHandlerFunc};
+use dmar_acpi::DmarReadError;
+use hal::{KernelFrameAllocator, KernelMmioAccess};
+
+/// Interrupt vector for VT-d fault events.
+const FAULT_VECTOR: u8 = interrupts::MSI_BASE_VECTOR;
+
+/// Size of one remapping unit's register set.
+const REGISTER_SIZE: usize = 4096;
+
+/// Identity-mapped range of the passthrough domain when the hardware can't
+/// bypass translation outright. Kernel DMA buffers come from the heap, which
+/// sits well inside the first 4 GiB.
+const PASSTHROUGH_LIMIT: u64 = 4 << 30;
+
+/// The IOMMU in use, once [`init`] has enabled translation.
+struct Active {
+    iommu: &'static dyn Iommu,
+    /// Domain every unclaimed device sits in.
+    passthrough: DomainId,
+    /// Firmware-reserved `(base, size)` ranges mapped into every domain,
+    /// which must therefore never be handed out as IOVAs.
+    reserved: Vec<(u64, u64)>,
+}
+
+static ACTIVE: RwSpinlock<Option<Active>> = RwSpinlock::new(None);
+
+/// Set by the fault interrupt; the records themselves are collected by
+/// [`drain_faults`], outside interrupt context.
+static FAULT_PENDING: AtomicBool = AtomicBool::new(false);
+
+/// Find the platform IOMMU and enable translation with every known PCI
+/// device in the passthrough domain. Must run after `devices::init()` has
+/// registered the PCI devices.
+pub fn init() {
+    let table = match dmar_acpi::read_dmar() {
+        Ok(table) => table,
+        Err(DmarReadError::NotPresent) => {
+            if dmar_acpi::ivrs_present() {
+                info!("IOMMU: AMD-Vi detected but not yet supported");
+            } else {
+                info!("IOMMU: no DMAR table, DMA remapping disabled");
+            }
+            return;
+        }
+        Err(DmarReadError::Parse(err)) => {
+            warn!(
+                "IOMMU: malformed DMAR table ({:?}), DMA remapping disabled",
+                err
+            );
+            return;
+        }
+    };
+
+    let mut units = Vec::with_capacity(table.drhds.len());
+    for drhd in table.drhds {
+        let base = drhd.register_base;
+        let mmio = KernelMmioAccess::new(base, REGISTER_SIZE);
+        match VtdUnit::new(KernelFrameAllocator, mmio, drhd) {
+            Ok(unit) => units.push(unit),
+            Err(err) => {
+                warn!(
+                    "IOMMU: VT-d unit at {:#x} failed to initialise: {:?}",
+                    base, err
+                );
+                return;
+            }
+        }
+    }
+    if units.is_empty() {
+        warn!("IOMMU: DMAR table lists no remapping units");
+        return;
+    }
+
+    let reserved: Vec<(u64, u64)> = table
+        .rmrrs
+        .iter()
+        .map(|rmrr| (rmrr.base, rmrr.size()))
+        .collect();
+    let vtd = VtdIommu::new(KernelFrameAllocator, units, table.rmrrs);
+    let devices: Vec<PciAddress> = DEVICE_REGISTRY
+        .lock()
+        .pci_addresses()
+        .into_iter()
+        .map(to_iommu_address)
+        .collect();
+    let passthrough = match vtd.init_passthrough_domain(PASSTHROUGH_LIMIT, &devices) {
+        Ok(domain) => domain,
+        Err(err) => {
+            warn!("IOMMU: failed to enable translation: {:?}", err);
+            return;
+        }
+    };
+
+    interrupts::set_interrupt_handler(FAULT_VECTOR, Some(fault_irq_handler as IrqHandlerFunc));
+    // Fixed delivery to the BSP, the same routing MSI-X devices use.
+    vtd.enable_fault_interrupt(0xFEE0_0000, FAULT_VECTOR as u32);
+
+    info!(
+        "IOMMU: VT-d enabled, {} unit(s), {} device(s) in passthrough, {} reserved region(s)",
+        vtd.unit_count(),
+        devices.len(),
+        reserved.len()
+    );
+    *ACTIVE.write() = Some(Active {
+        iommu: Box::leak(Box::new(vtd)),
+        passthrough,
+        reserved,
+    });
+}
+
+/// The active IOMMU, if DMA remapping is enabled.
+pub fn get() -> Option<&'static dyn Iommu> {
+    ACTIVE.read().as_ref().map(|active| active.iommu)
+}
+
+/// The domain unclaimed devices are assigned to.
+pub fn passthrough_domain() -> Option<DomainId> {
+    ACTIVE.read().as_ref().map(|active| active.passthrough)
+}
+
+/// Firmware-reserved `(base, size)` ranges present in every domain.
+pub fn reserved_regions() -> Vec<(u64, u64)> {
+    ACTIVE
+        .read()
+        .as_ref()
+        .map(|active| active.reserved.clone())
+        .unwrap_or_default()
+}
+
+/// Whether a fault interrupt has arrived since the last [`drain_faults`].
+pub fn fault_pending() -> bool {
+    FAULT_PENDING.load(Ordering::Acquire)
+}
+
+/// Log any faults signalled since the last check. Cheap when there are
+/// none; called from the DMA syscalls so faults surface promptly.
+pub fn poll_faults() {
+    if fault_pending() {
+        drain_faults();
+    }
+}
+
+/// Collect and log every recorded translation fault.
+pub fn drain_faults() -> Vec<IommuFault> {
+    FAULT_PENDING.store(false, Ordering::Release);
+    let Some(iommu) = get() else {
+        return Vec::new();
+    };
+    let faults = iommu.take_faults();
+    for fault in &faults {
+        warn!(
+            "IOMMU: blocked DMA {:?} from {} at IOVA {:#x} (reason {:#x})",
+            fault.kind, fault.device, fault.iova, fault.reason
+        );
+    }
+    faults
+}
+
+/// Convert the ABI's PCI address to the `iommu` crate's.
+pub(crate) fn to_iommu_address(address: panda_abi::device::PciAddress) -> PciAddress {
+    PciAddress {
+        segment: address.segment,
+        bus: address.bus,
+        device: address.device,
+        function: address.function,
+    }
+}
+
+/// The fault interrupt only flags the fault: collecting the records
+/// allocates, which is not allowed in interrupt context.
+extern "x86-interrupt" fn fault_irq_handler(_stack_frame: InterruptStackFrame) {
+    FAULT_PENDING.store(true, Ordering::Release);
+    crate::apic::eoi();
+}
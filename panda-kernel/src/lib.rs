@@ -22,6 +22,7 @@ pub mod devices;
 pub mod executor;
 pub mod handle;
 pub mod interrupts;
+pub mod iommu;
 pub mod logging;
 pub mod memory;
 pub mod pci;
@@ -63,7 +64,7 @@ pub fn init() -> x86_64::PhysAddr {
 }
 
 /// Continue kernel initialization after higher-half jump.
-/// This initializes ACPI, syscall, interrupts, APIC, PCI, and devices.
+/// This initializes ACPI, syscall, interrupts, APIC, PCI, devices, and the IOMMU.
 pub fn init_after_higher_half_jump(acpi2_rsdp: x86_64::PhysAddr) {
     acpi::init(acpi2_rsdp);
     memory::smap::enable();
@@ -72,6 +73,7 @@ pub fn init_after_higher_half_jump(acpi2_rsdp: x86_64::PhysAddr) {
     apic::init();
     pci::init();
     devices::init();
+    iommu::init();
 }
 
 pub fn breakpoint() {
@@ -127,6 +127,9 @@ pub struct Process {
     /// Callee-saved registers captured at yield time. Used by the resume path
     /// to restore rbx/rbp/r12-r15 before sysretq.
     yield_callee_saved: Option<crate::syscall::CalleeSavedRegs>,
+    /// Stopped by `SIGNAL_STOP`. A stopped process stays Blocked, whatever
+    /// would otherwise wake it, until `SIGNAL_CONTINUE`.
+    stopped: bool,
 }
 
 impl Process {
@@ -200,6 +203,7 @@ impl Process {
             buffer_free_ranges,
             pending_syscall: None,
             yield_callee_saved: None,
+            stopped: false,
         })
     }
 
@@ -213,6 +217,11 @@ impl Process {
         self.info.set_exit_code(code);
     }
 
+    /// Physical address of this process's page table.
+    pub fn page_table_phys(&self) -> x86_64::PhysAddr {
+        self.context.page_table_phys()
+    }
+
     /// Get the IP, SP, and page table address needed for exec.
     /// Used by scheduler to exec after releasing locks.
     pub fn exec_params(&self) -> (VirtAddr, VirtAddr, x86_64::PhysAddr, Option<&SavedState>) {
@@ -236,6 +245,14 @@ impl Process {
         self.state = runnable;
     }
 
+    pub(crate) fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    pub(crate) fn set_stopped(&mut self, stopped: bool) {
+        self.stopped = stopped;
+    }
+
     pub fn reset_last_scheduled(&mut self) {
         self.last_scheduled = RTC::now();
     }
@@ -327,6 +344,17 @@ impl Process {
         self.mappings.push(mapping);
     }
 
+    /// Remove the mapping [`Self::add_mapping`] registered at `base`, if
+    /// any. Dropping the result unmaps it from the loaded page table, so
+    /// this process must be the CURRENT one.
+    pub fn remove_mapping(&mut self, base: VirtAddr) -> Option<Mapping> {
+        let index = self
+            .mappings
+            .iter()
+            .position(|mapping| mapping.base_virtual_address() == base)?;
+        Some(self.mappings.swap_remove(index))
+    }
+
     /// Allocate a virtual address range for a buffer.
     /// Uses first-fit allocation from the free list.
     /// Returns None if out of buffer space.
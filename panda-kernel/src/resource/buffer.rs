@@ -62,7 +62,7 @@ use alloc::sync::{Arc, Weak};
 use alloc::vec::Vec;
 use core::sync::atomic::{AtomicUsize, Ordering};
 
-use x86_64::VirtAddr;
+use x86_64::{PhysAddr, VirtAddr};
 
 use crate::memory::{self, Frame, Mapping, MappingBacking, MemoryMappingOptions, map_external};
 use crate::memory::smap;
@@ -266,6 +266,22 @@ impl SharedBuffer {
         self.owner
     }
 
+    /// Physical address of each backing page, in buffer order. Used to map
+    /// the buffer into a device's IOMMU domain (`iommu::manager`).
+    pub fn frame_addresses(&self) -> impl Iterator<Item = PhysAddr> + '_ {
+        self.frames.iter().map(|frame| frame.start_address())
+    }
+
+    /// Kernel address of the byte at `offset`, or `None` past the last
+    /// page. Each page is also mapped in the kernel heap, so unlike
+    /// `as_slice` this is valid whichever page table is active, even in an
+    /// interrupt handler; only the page containing `offset` is guaranteed
+    /// to follow it. Used for the `net:` frame ring (`devices::virtio_net`).
+    pub fn kernel_address(&self, offset: usize) -> Option<VirtAddr> {
+        let frame = self.frames.get(offset / 4096)?;
+        Some(frame.virtual_address() + (offset % 4096) as u64)
+    }
+
     /// Map each frame individually into the CURRENT process's address space
     /// (whichever page table is active — see the module-level "Process-context
     /// safety" doc comment) at consecutive pages starting at `vaddr`.
@@ -346,4 +346,8 @@ impl Resource for ChannelEndpoint {
     fn poll_events(&self) -> u32 {
         ChannelEndpoint::poll_events(self)
     }
+
+    fn attach_mailbox(&self, mailbox_ref: MailboxRef) {
+        ChannelEndpoint::attach_mailbox(self, mailbox_ref);
+    }
 }
@@ -7,8 +7,11 @@
 //! [`crate::devices::claims`]; a second open (from anywhere) fails with
 //! `Busy` until the owning handle is closed or the owning process exits.
 //!
+//! Each display device is one head, with its own path
+//! (`display:/pci/display/1` is the second), framebuffer, modes and owner.
+//!
 //! Because holding a [`DisplayDevice`] handle *is* the proof of exclusive
-//! ownership, the three operations below apply no further permission check: a
+//! ownership, the operations below apply no further permission check: a
 //! process that does not own the display simply has no handle to send them
 //! to.
 //!
@@ -17,14 +20,16 @@
 //! driver service registering the same interface, and this file goes away
 //! unchanged from the client's point of view.
 
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
 use spinning_top::Spinlock;
 use x86_64::VirtAddr;
 
 use crate::device_address::DeviceAddress;
 use crate::devices::claims::ClaimGuard;
+use crate::devices::virtio_gpu::{self, Mode};
 use crate::memory::{MemoryMappingOptions, map_external, virtual_address_to_physical};
-use crate::pci::{self, DeviceClass};
-use crate::process::Process;
+use crate::process::{Process, ProcessId};
 
 use super::{MailboxRef, Resource};
 
@@ -59,30 +64,51 @@ pub struct SurfaceInfo {
 pub enum SurfaceError {
     /// Invalid coordinates or dimensions
     InvalidBounds,
+    /// The driver rejected the operation
+    DeviceFailed,
 }
 
-/// The kernel virtual base address and geometry of the framebuffer, if a
-/// display driver has initialized one.
+/// How often an owned head's EDID is read again, to notice a monitor
+/// being plugged, unplugged or swapped.
+const EDID_POLL_INTERVAL_MS: u64 = 1000;
+
+/// The kernel virtual base address and geometry of a head's framebuffer.
 struct FramebufferRegion {
     base: VirtAddr,
     info: SurfaceInfo,
 }
 
-static FRAMEBUFFER_REGION: Spinlock<Option<FramebufferRegion>> = Spinlock::new(None);
+/// What the `display:` scheme knows about one head.
+struct Head {
+    region: FramebufferRegion,
+    /// The mailbox of the head's owner, for posting `EVENT_DISPLAY_CHANGED`
+    /// from the driver (which has no access to the owner's handle table).
+    ///
+    /// There is at most one owner per head by construction — that is what
+    /// the claim table guarantees — so a single slot cannot be ambiguous.
+    /// It is cleared when the owning resource drops.
+    owner: Option<MailboxRef>,
+    /// A [`DisplayDevice`] holds the head.
+    owned: bool,
+    /// An EDID watcher task is running for the head.
+    watched: bool,
+}
 
-/// Record the framebuffer's location and geometry.
+/// Every head a display driver has initialized, keyed by device address.
+static HEADS: Spinlock<BTreeMap<DeviceAddress, Head>> = Spinlock::new(BTreeMap::new());
+
+/// Record a head's framebuffer location and geometry.
 ///
 /// Called by the display driver (currently virtio-gpu) whenever it
-/// allocates or replaces the framebuffer. This is the only place that
-/// knows the raw framebuffer address; [`DisplayDevice::new`] reads it back
-/// to build the `display:` scheme's exclusive owner.
-pub fn init_framebuffer(framebuffer: *mut u8, width: u32, height: u32) {
+/// allocates or replaces a framebuffer. This is the only place that knows
+/// the raw framebuffer address; [`DisplayDevice`] reads it back to serve
+/// the `display:` scheme's exclusive owner.
+pub fn init_framebuffer(address: DeviceAddress, framebuffer: *mut u8, width: u32, height: u32) {
     let format = PixelFormat::ARGB8888;
     let stride = match format {
         PixelFormat::ARGB8888 => width * 4,
     };
-
-    *FRAMEBUFFER_REGION.lock() = Some(FramebufferRegion {
+    let region = FramebufferRegion {
         base: VirtAddr::new(framebuffer as u64),
         info: SurfaceInfo {
             width,
@@ -90,24 +116,40 @@ pub fn init_framebuffer(framebuffer: *mut u8, width: u32, height: u32) {
             format,
             stride,
         },
-    });
+    };
+
+    let mut heads = HEADS.lock();
+    match heads.get_mut(&address) {
+        Some(head) => head.region = region,
+        None => {
+            heads.insert(
+                address,
+                Head {
+                    region,
+                    owner: None,
+                    owned: false,
+                    watched: false,
+                },
+            );
+        }
+    }
 }
 
-fn framebuffer_region() -> Option<(VirtAddr, SurfaceInfo)> {
-    FRAMEBUFFER_REGION
+fn framebuffer_region(address: &DeviceAddress) -> Option<(VirtAddr, SurfaceInfo)> {
+    HEADS
         .lock()
-        .as_ref()
-        .map(|region| (region.base, region.info))
+        .get(address)
+        .map(|head| (head.region.base, head.region.info))
 }
 
-/// The device address of the system's display, if one exists.
+/// Whether `address` is a display device with a framebuffer: one the
+/// `display:` scheme can open.
 ///
-/// Single source of truth for "which device is *the* display": every
-/// caller that needs the display's device address resolves it through this
-/// function, so they can never end up claiming different addresses and
-/// silently coexisting.
-pub fn device_address() -> Option<DeviceAddress> {
-    pci::get_device_by_class(DeviceClass::Display.code(), 0)
+/// Single source of truth for "which devices are displays": every caller
+/// that needs to know resolves it through this function, so a path can
+/// never claim a device no display driver drives.
+pub fn is_display(address: &DeviceAddress) -> bool {
+    HEADS.lock().contains_key(address)
 }
 
 /// Errors that can occur while mapping the framebuffer into a process.
@@ -123,48 +165,88 @@ pub enum DisplayError {
 /// `close()` — or dropping the owner's handle table at process exit —
 /// releases the display with no dedicated cleanup path.
 pub struct DisplayDevice {
-    /// Kernel virtual base address of the framebuffer pages.
-    framebuffer: VirtAddr,
-    info: SurfaceInfo,
+    address: DeviceAddress,
     _claim: ClaimGuard,
-    /// Mailbox to notify on `EVENT_DISPLAY_CHANGED`, if the owner attached one.
-    mailbox: Spinlock<Option<MailboxRef>>,
+    /// The process, base address and page count of each framebuffer
+    /// mapping [`Self::map_into_process`] has made, until
+    /// [`Self::unmap_from_process`] removes them. Display handles can't be
+    /// transferred (see [`super::is_transferable`]), so the process is
+    /// always the owner; keying by it keeps one process from ever unmapping
+    /// addresses in another's table should that change.
+    mapped: Spinlock<Vec<(ProcessId, VirtAddr, usize)>>,
 }
 
-/// The mailbox of the current display owner, for posting
-/// `EVENT_DISPLAY_CHANGED` from the driver (which has no access to the
-/// owner's handle table).
-///
-/// There is at most one display owner at a time by construction — that is
-/// what the claim table guarantees — so a single global slot is sufficient
-/// and cannot be ambiguous. It is cleared when the owning resource drops.
-static OWNER_MAILBOX: Spinlock<Option<MailboxRef>> = Spinlock::new(None);
-
 impl DisplayDevice {
-    /// Create a display resource for the framebuffer described by the
-    /// current global framebuffer region, taking ownership of `claim`.
+    /// Create a display resource for the head at `address`, taking
+    /// ownership of `claim`.
     ///
-    /// Returns `None` if no framebuffer has been initialized (no display
-    /// driver bound), in which case the caller should report `NotFound` and
-    /// let `claim` drop, releasing the claim it took.
-    pub fn new(claim: ClaimGuard) -> Option<Self> {
-        let (framebuffer, info) = framebuffer_region()?;
+    /// Returns `None` if no framebuffer has been initialized there (no
+    /// display driver bound), in which case the caller should report
+    /// `NotFound` and let `claim` drop, releasing the claim it took.
+    ///
+    /// Starts the head's EDID watcher unless one is still running for a
+    /// previous owner. This runs in the opening syscall's future, where no
+    /// scheduler lock is held and a kernel task can be spawned.
+    pub fn new(address: DeviceAddress, claim: ClaimGuard) -> Option<Self> {
+        let start_watcher = {
+            let mut heads = HEADS.lock();
+            let head = heads.get_mut(&address)?;
+            head.owned = true;
+            !core::mem::replace(&mut head.watched, true)
+        };
+        if start_watcher {
+            crate::executor::spawn(watch_edid(address.clone()));
+        }
+
         Some(Self {
-            framebuffer,
-            info,
+            address,
             _claim: claim,
-            mailbox: Spinlock::new(None),
+            mapped: Spinlock::new(Vec::new()),
         })
     }
 
+    /// The framebuffer's current base address and mode: a mode change
+    /// replaces both.
+    fn region(&self) -> (VirtAddr, SurfaceInfo) {
+        framebuffer_region(&self.address).expect("display head without a framebuffer")
+    }
+
     /// Mode information for `OP_DISPLAY_INFO`.
     pub fn info(&self) -> SurfaceInfo {
-        self.info
+        self.region().1
+    }
+
+    /// The modes `OP_DISPLAY_MODES` lists.
+    pub fn modes(&self) -> Vec<Mode> {
+        virtio_gpu::modes(&self.address)
+    }
+
+    /// Whether `width` x `height` is one of [`Self::modes`].
+    pub fn has_mode(&self, width: u32, height: u32) -> bool {
+        self.modes()
+            .iter()
+            .any(|mode| (mode.width, mode.height) == (width, height))
+    }
+
+    /// Switch to one of [`Self::modes`]. The driver replaces the framebuffer
+    /// and posts `EVENT_DISPLAY_CHANGED` to the owner, which must re-query
+    /// the mode and re-map.
+    ///
+    /// The driver frees the old framebuffer, so the owner's mappings of it
+    /// must already be gone: call [`Self::unmap_from_process`] first.
+    pub fn set_mode(&self, width: u32, height: u32) -> Result<(), SurfaceError> {
+        if !self.has_mode(width, height) {
+            return Err(SurfaceError::InvalidBounds);
+        }
+        debug_assert!(self.mapped.lock().is_empty());
+
+        virtio_gpu::change_resolution(&self.address, width, height)
+            .map_err(|_| SurfaceError::DeviceFailed)
     }
 
     /// Size of the framebuffer in bytes, rounded up to whole pages.
-    fn mapped_size(&self) -> usize {
-        let bytes = self.info.stride as usize * self.info.height as usize;
+    fn mapped_size(info: &SurfaceInfo) -> usize {
+        let bytes = info.stride as usize * info.height as usize;
         bytes.div_ceil(4096) * 4096
     }
 
@@ -182,22 +264,22 @@ impl DisplayDevice {
     /// so the mapping is removed automatically at process exit.
     ///
     /// Mode changes are the one case that can invalidate the mapping: the
-    /// driver replaces the framebuffer allocation, and the owner is told to
-    /// re-map via `EVENT_DISPLAY_CHANGED` (see [`notify_display_changed`]).
-    /// Until it does, its stale mapping still points at memory the GPU
-    /// driver has released — the "keep the old pages alive until the owner
-    /// unmaps" refinement in plans/userspace-compositor.md (risk 5) needs
-    /// frame ownership to move out of the virtio HAL and is not implemented
-    /// here.
+    /// driver frees the framebuffer allocation and makes a new one. Every
+    /// mapping made here is recorded, so [`Self::unmap_from_process`] can
+    /// tear them all down before the mode set and before the handle is
+    /// closed; the owner re-maps after `EVENT_DISPLAY_CHANGED` (see
+    /// [`notify_display_changed`]) and faults on the old address rather
+    /// than writing to released memory.
     pub fn map_into_process(&self, process: &mut Process) -> Result<usize, DisplayError> {
-        let size = self.mapped_size();
+        let (framebuffer, info) = self.region();
+        let size = Self::mapped_size(&info);
         let num_pages = size / 4096;
 
         let vaddr = process
             .alloc_buffer_vaddr(num_pages)
             .ok_or(DisplayError::MappingFailed)?;
 
-        let phys = virtual_address_to_physical(self.framebuffer);
+        let phys = virtual_address_to_physical(framebuffer);
         let mapping = map_external(
             phys,
             vaddr,
@@ -209,10 +291,27 @@ impl DisplayDevice {
             },
         );
         process.add_mapping(mapping);
+        self.mapped.lock().push((process.id(), vaddr, num_pages));
 
         Ok(vaddr.as_u64() as usize)
     }
 
+    /// Remove every framebuffer mapping [`Self::map_into_process`] made in
+    /// `process` (which must be the CURRENT process, since dropping a
+    /// mapping walks the loaded page table) and free its virtual addresses.
+    pub fn unmap_from_process(&self, process: &mut Process) {
+        let mut mapped = self.mapped.lock();
+        let id = process.id();
+        mapped.retain(|&(owner, vaddr, num_pages)| {
+            if owner != id {
+                return true;
+            }
+            drop(process.remove_mapping(vaddr));
+            process.free_buffer_vaddr(vaddr, num_pages);
+            false
+        });
+    }
+
     /// Forward a damaged rectangle to the driver.
     ///
     /// `region` is validated against the display bounds and then handed to
@@ -222,26 +321,62 @@ impl DisplayDevice {
     /// and future-proofing of the interface rather than as a bandwidth
     /// optimisation.
     pub fn flush(&self, region: Option<Rect>) -> Result<(), SurfaceError> {
+        let info = self.info();
         if let Some(rect) = region {
             let within = rect
                 .x
                 .checked_add(rect.width)
                 .zip(rect.y.checked_add(rect.height))
-                .map(|(right, bottom)| right <= self.info.width && bottom <= self.info.height)
+                .map(|(right, bottom)| right <= info.width && bottom <= info.height)
                 .unwrap_or(false);
             if !within {
                 return Err(SurfaceError::InvalidBounds);
             }
         }
 
-        crate::devices::virtio_gpu::flush_framebuffer();
+        virtio_gpu::flush_framebuffer(&self.address);
         Ok(())
     }
+
+    /// Load the hardware cursor's image, or hide it with `None`.
+    ///
+    /// `image` must be exactly `DISPLAY_CURSOR_SIZE` square, and the hotspot
+    /// must fall inside it.
+    pub fn set_cursor(
+        &self,
+        image: Option<&[u8]>,
+        hot_x: u32,
+        hot_y: u32,
+    ) -> Result<(), SurfaceError> {
+        let size = panda_abi::DISPLAY_CURSOR_SIZE;
+        if image.is_some_and(|image| image.len() != (size * size * 4) as usize)
+            || hot_x >= size
+            || hot_y >= size
+        {
+            return Err(SurfaceError::InvalidBounds);
+        }
+
+        virtio_gpu::set_cursor(&self.address, image, hot_x, hot_y)
+            .map_err(|_| SurfaceError::DeviceFailed)
+    }
+
+    /// Move the hardware cursor's hotspot to a position on the screen.
+    pub fn move_cursor(&self, x: u32, y: u32) -> Result<(), SurfaceError> {
+        let info = self.info();
+        if x >= info.width || y >= info.height {
+            return Err(SurfaceError::InvalidBounds);
+        }
+
+        virtio_gpu::move_cursor(&self.address, x, y).map_err(|_| SurfaceError::DeviceFailed)
+    }
 }
 
 impl Drop for DisplayDevice {
     fn drop(&mut self) {
-        *OWNER_MAILBOX.lock() = None;
+        if let Some(head) = HEADS.lock().get_mut(&self.address) {
+            head.owner = None;
+            head.owned = false;
+        }
     }
 }
 
@@ -259,17 +394,51 @@ impl Resource for DisplayDevice {
     }
 
     fn attach_mailbox(&self, mailbox_ref: MailboxRef) {
-        *self.mailbox.lock() = Some(mailbox_ref.clone());
-        *OWNER_MAILBOX.lock() = Some(mailbox_ref);
+        if let Some(head) = HEADS.lock().get_mut(&self.address) {
+            head.owner = Some(mailbox_ref);
+        }
+    }
+}
+
+/// Post `EVENT_DISPLAY_CHANGED` to a head's owner's mailbox, if any.
+///
+/// Called by the display driver after a successful mode change, and by
+/// [`watch_edid`] when the monitor changes; the owner responds by
+/// re-querying `OP_DISPLAY_INFO` (and `OP_DISPLAY_MODES`) and re-issuing
+/// `OP_DISPLAY_MAP`.
+pub fn notify_display_changed(address: &DeviceAddress) {
+    if let Some(owner) = HEADS
+        .lock()
+        .get(address)
+        .and_then(|head| head.owner.as_ref())
+    {
+        owner.post_event(panda_abi::EVENT_DISPLAY_CHANGED);
     }
 }
 
-/// Post `EVENT_DISPLAY_CHANGED` to the display owner's mailbox, if any.
+/// Poll a head's EDID for as long as it is owned, posting
+/// `EVENT_DISPLAY_CHANGED` when the monitor changes.
 ///
-/// Called by the display driver after a successful mode change; the owner
-/// responds by re-querying `OP_DISPLAY_INFO` and re-issuing `OP_DISPLAY_MAP`.
-pub fn notify_display_changed() {
-    if let Some(mailbox) = OWNER_MAILBOX.lock().as_ref() {
-        mailbox.post_event(panda_abi::EVENT_DISPLAY_CHANGED);
+/// virtio-gpu announces hotplug with a config-change interrupt the driver
+/// does not handle, so a once-a-second poll stands in for it. The task
+/// ends once the head has no owner; the next open starts another.
+async fn watch_edid(address: DeviceAddress) {
+    loop {
+        crate::executor::sleep::sleep_ms(EDID_POLL_INTERVAL_MS).await;
+
+        {
+            let mut heads = HEADS.lock();
+            let Some(head) = heads.get_mut(&address) else {
+                return;
+            };
+            if !head.owned {
+                head.watched = false;
+                return;
+            }
+        }
+
+        if virtio_gpu::edid_changed(&address) {
+            notify_display_changed(&address);
+        }
     }
 }
@@ -9,6 +9,8 @@ use crate::process::waker::IoWaker;
 pub enum Event {
     /// Key press/release event.
     Key(KeyEvent),
+    /// A raw input event of any type (pointer motion, buttons, sync).
+    Input(InputEvent),
 }
 
 /// A keyboard key event.
@@ -20,6 +22,17 @@ pub struct KeyEvent {
     pub value: u32,
 }
 
+/// A raw evdev input event, passed through to the reader unchanged.
+#[derive(Debug, Clone, Copy)]
+pub struct InputEvent {
+    /// Event type (`EV_SYN`, `EV_KEY`, `EV_REL`, `EV_ABS`).
+    pub event_type: u16,
+    /// Event code, e.g. `REL_X` or `BTN_LEFT`.
+    pub code: u16,
+    /// Event value: a delta, a position, or 0/1 for a button.
+    pub value: u32,
+}
+
 /// Interface for event-producing resources.
 ///
 /// Implemented by keyboard, mouse, timers, network sockets, etc.
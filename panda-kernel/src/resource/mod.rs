@@ -14,6 +14,8 @@ mod display;
 mod event_source;
 pub(crate) mod initrd;
 mod mailbox;
+mod net;
+mod pointer;
 mod process;
 pub(crate) mod scheme;
 mod spawn_handle;
@@ -24,12 +26,14 @@ pub use channel::{ChannelEndpoint, ChannelError};
 pub use char_output::{CharOutError, CharacterOutput};
 pub use directory::{DirEntry, Directory};
 pub use display::{
-    DisplayDevice, DisplayError, PixelFormat, Rect, SurfaceError, SurfaceInfo,
-    device_address as display_device_address, init_framebuffer, notify_display_changed,
+    DisplayDevice, DisplayError, PixelFormat, Rect, SurfaceError, SurfaceInfo, init_framebuffer,
+    is_display, notify_display_changed,
 };
-pub use event_source::{Event, EventSource, KeyEvent};
+pub use event_source::{Event, EventSource, InputEvent, KeyEvent};
 pub use initrd::InitrdScheme;
 pub use mailbox::{Mailbox, MailboxRef};
+pub use net::NetDevice;
+pub use pointer::PointerDevice;
 pub use process::Process as ProcessInterface;
 pub use scheme::{
     ConsoleScheme, DirectoryResource, FileScheme, KeyboardResource, KeyboardScheme, OpenError,
@@ -94,6 +98,18 @@ pub trait Resource: Send + Sync {
         None
     }
 
+    /// Get this resource as an exclusively-owned network device (the `net:`
+    /// scheme), for the `OP_NET_*` operations.
+    fn as_net(&self) -> Option<&NetDevice> {
+        None
+    }
+
+    /// Get this resource as a pointer device (the `pointer:` scheme), for
+    /// `OP_POINTER_INFO`.
+    fn as_pointer(&self) -> Option<&PointerDevice> {
+        None
+    }
+
     /// Get a waker for blocking on this resource, if applicable.
     fn waker(&self) -> Option<Arc<IoWaker>> {
         None
@@ -0,0 +1,98 @@
+//! The network device resource: raw ethernet frames on an exclusively
+//! owned NIC.
+//!
+//! This is the kernel side of the `net:` scheme (`net:/pci/network/0`), the
+//! interface a userspace network stack drives. Opening it claims the device
+//! via [`crate::devices::claims`], so only one process sees the frames.
+//!
+//! Frames move through a shared buffer the owner attaches with
+//! `OP_NET_RING`, laid out as in `panda_abi::net`: received frames are
+//! written into it from the interrupt handler, and an attached mailbox gets
+//! `EVENT_NET_RX` when some arrive; `OP_NET_KICK` transmits the frames
+//! queued in it. Nothing is copied through a syscall's arguments.
+//! `OP_NET_INFO` reports the MAC address.
+
+use alloc::sync::Arc;
+use spinning_top::Spinlock;
+
+use crate::devices::claims::ClaimGuard;
+use crate::devices::virtio_net::{NetError, VirtioNet};
+use crate::process::waker::IoWaker;
+
+use super::{MailboxRef, Resource, SharedBuffer};
+
+/// An exclusively-owned network device.
+///
+/// Holds the device's [`ClaimGuard`] for the lifetime of the resource; the
+/// next owner starts without a ring.
+pub struct NetDevice {
+    device: Arc<Spinlock<VirtioNet>>,
+    _claim: ClaimGuard,
+}
+
+impl NetDevice {
+    /// Wrap `device`, taking ownership of its `claim`. Frames received
+    /// before a ring is attached are discarded.
+    pub fn new(device: Arc<Spinlock<VirtioNet>>, claim: ClaimGuard) -> Self {
+        device.lock().reset();
+        Self {
+            device,
+            _claim: claim,
+        }
+    }
+
+    /// The device's ethernet address.
+    pub fn mac_address(&self) -> [u8; 6] {
+        self.device.lock().mac_address()
+    }
+
+    /// Move frames through `buffer` from now on.
+    pub fn attach_ring(&self, buffer: Arc<SharedBuffer>) -> Result<(), NetError> {
+        self.device.lock().attach_ring(buffer)
+    }
+
+    /// Transmit the frames queued in the ring.
+    pub fn kick(&self) -> Result<usize, NetError> {
+        self.device.lock().kick()
+    }
+}
+
+impl Drop for NetDevice {
+    fn drop(&mut self) {
+        self.device.lock().reset();
+    }
+}
+
+impl Resource for NetDevice {
+    fn handle_type(&self) -> panda_abi::HandleType {
+        // Opened through a scheme, like the other device files
+        panda_abi::HandleType::File
+    }
+
+    fn as_net(&self) -> Option<&NetDevice> {
+        Some(self)
+    }
+
+    fn waker(&self) -> Option<Arc<IoWaker>> {
+        Some(self.device.lock().waker())
+    }
+
+    fn supported_events(&self) -> u32 {
+        panda_abi::EVENT_NET_RX
+    }
+
+    fn poll_events(&self) -> u32 {
+        let mut device = self.device.lock();
+        // Pick up anything the interrupt handler left while we held the lock.
+        device.poll();
+        if device.has_frames() {
+            panda_abi::EVENT_NET_RX
+        } else {
+            0
+        }
+    }
+
+    fn attach_mailbox(&self, mailbox_ref: MailboxRef) {
+        self.device.lock().attach_mailbox(mailbox_ref);
+    }
+}
@@ -0,0 +1,87 @@
+//! The pointer device resource: raw motion and button events from a mouse or
+//! tablet.
+//!
+//! This is the kernel side of the `pointer:` scheme (`pointer:/pci/input/0`).
+//! Like a `keyboard:` handle it is not claimed, so every open shares the
+//! device's one event queue. Each `OP_FILE_READ` returns one 8-byte evdev
+//! record (type, code, value); a report is a run of `EV_REL`/`EV_ABS`/
+//! `EV_KEY` records ended by `EV_SYN`/`SYN_REPORT`. Reads block unless
+//! `FILE_NONBLOCK` is given, and an attached mailbox receives
+//! `EVENT_POINTER` per report. `OP_POINTER_INFO` says whether positions are
+//! relative or absolute.
+
+use alloc::sync::Arc;
+use spinning_top::Spinlock;
+
+use crate::devices::virtio_pointer::VirtioPointer;
+use crate::process::waker::IoWaker;
+
+use super::event_source::{Event, EventSource, InputEvent};
+use super::{MailboxRef, Resource};
+
+/// An open pointer device.
+pub struct PointerDevice {
+    device: Arc<Spinlock<VirtioPointer>>,
+}
+
+impl PointerDevice {
+    pub fn new(device: Arc<Spinlock<VirtioPointer>>) -> Self {
+        Self { device }
+    }
+
+    /// Whether the device is relative or absolute, and its axis ranges.
+    pub fn info(&self) -> panda_abi::PointerInfo {
+        self.device.lock().info()
+    }
+}
+
+impl Resource for PointerDevice {
+    fn handle_type(&self) -> panda_abi::HandleType {
+        // Events are read like a file, as with the keyboard
+        panda_abi::HandleType::File
+    }
+
+    fn as_event_source(&self) -> Option<&dyn EventSource> {
+        Some(self)
+    }
+
+    fn as_pointer(&self) -> Option<&PointerDevice> {
+        Some(self)
+    }
+
+    fn waker(&self) -> Option<Arc<IoWaker>> {
+        Some(self.device.lock().waker())
+    }
+
+    fn supported_events(&self) -> u32 {
+        panda_abi::EVENT_POINTER
+    }
+
+    fn poll_events(&self) -> u32 {
+        if self.device.lock().has_events() {
+            panda_abi::EVENT_POINTER
+        } else {
+            0
+        }
+    }
+
+    fn attach_mailbox(&self, mailbox_ref: MailboxRef) {
+        self.device.lock().attach_mailbox(mailbox_ref);
+    }
+}
+
+impl EventSource for PointerDevice {
+    fn poll(&self) -> Option<Event> {
+        self.device.lock().pop_event().map(|event| {
+            Event::Input(InputEvent {
+                event_type: event.event_type,
+                code: event.code,
+                value: event.value,
+            })
+        })
+    }
+
+    fn waker(&self) -> Arc<IoWaker> {
+        self.device.lock().waker()
+    }
+}
@@ -37,3 +37,13 @@ pub enum ProcessError {
     /// Permission denied.
     PermissionDenied,
 }
+
+impl From<ProcessError> for panda_abi::ErrorCode {
+    fn from(err: ProcessError) -> Self {
+        match err {
+            ProcessError::NotSupported => panda_abi::ErrorCode::NotSupported,
+            ProcessError::NotFound => panda_abi::ErrorCode::NotFound,
+            ProcessError::PermissionDenied => panda_abi::ErrorCode::PermissionDenied,
+        }
+    }
+}
@@ -3,6 +3,8 @@
 //! Resources are identified by URIs with a scheme and path:
 //! - `file:/initrd/init` -> File via existing VFS/mount system
 //! - `console:/serial/0` -> Serial console device
+//! - `net:/pci/network/0` -> Raw ethernet frames on a network device
+//! - `pointer:/pci/input/0` -> Motion and button events from a mouse or tablet
 //!
 //! The scheme identifies the resource type, and the path is the address
 //! within that scheme's namespace.
@@ -20,6 +22,8 @@ use crate::device_path;
 use crate::devices::claims::{ClaimGuard, ClaimOwner};
 use crate::devices::virtio_block;
 use crate::devices::virtio_keyboard::{self, VirtioKeyboard};
+use crate::devices::virtio_net;
+use crate::devices::virtio_pointer;
 use crate::process::waker::IoWaker;
 use crate::vfs;
 
@@ -424,11 +428,13 @@ impl EventSource for KeyboardResource {
 // Display Scheme - exclusive display ownership
 // =============================================================================
 
-/// Scheme handler for display devices (`display:/pci/display/0`).
+/// Scheme handler for display devices (`display:/pci/display/0`, and
+/// `display:/pci/display/1` onwards for further heads).
 ///
 /// Opening a display claims it exclusively (see
 /// `crate::resource::display`): a second open fails with `Busy` until the
-/// owning handle is closed or the owning process exits.
+/// owning handle is closed or the owning process exits. Each head is
+/// claimed on its own.
 pub struct DisplayScheme;
 
 #[async_trait]
@@ -438,17 +444,17 @@ impl SchemeHandler for DisplayScheme {
         // "/pci/display/0" or the raw "/pci/00:02.0".
         let address = device_path::resolve(path).ok_or(OpenError::NotFound)?;
 
-        // Only the device the framebuffer was initialized from can be opened
-        // as a display; any other PCI device resolved by this path is not a
+        // Only devices a framebuffer was initialized from can be opened as
+        // displays; any other PCI device resolved by this path is not a
         // display this kernel can drive.
-        if super::display_device_address().as_ref() != Some(&address) {
+        if !super::is_display(&address) {
             return Err(OpenError::NotFound);
         }
 
-        let claim = crate::devices::claims::claim(address, ClaimOwner::Display)
+        let claim = crate::devices::claims::claim(address.clone(), ClaimOwner::Display)
             .map_err(|_| OpenError::Busy)?;
 
-        let display = super::DisplayDevice::new(claim).ok_or(OpenError::NotFound)?;
+        let display = super::DisplayDevice::new(address, claim).ok_or(OpenError::NotFound)?;
         Ok(Box::new(display))
     }
 
@@ -525,6 +531,59 @@ impl super::VfsFile for BlockDeviceResource {
     }
 }
 
+// =============================================================================
+// Net Scheme - raw ethernet frame access
+// =============================================================================
+
+/// Scheme handler for network devices (`net:/pci/network/0`).
+///
+/// Opening a device claims it exclusively (see `crate::resource::net`): a
+/// second open fails with `Busy` until the owning handle is closed or the
+/// owning process exits.
+pub struct NetScheme;
+
+#[async_trait]
+impl SchemeHandler for NetScheme {
+    async fn open(&self, path: &str) -> Result<Box<dyn Resource>, OpenError> {
+        // Resolve path like "/pci/network/0" or "/pci/00:05.0"
+        let address = device_path::resolve(path).ok_or(OpenError::NotFound)?;
+        let device = virtio_net::get_device(&address).ok_or(OpenError::NotFound)?;
+
+        let claim = crate::devices::claims::claim(address, ClaimOwner::RawOpen)
+            .map_err(|_| OpenError::Busy)?;
+
+        Ok(Box::new(super::NetDevice::new(device, claim)))
+    }
+
+    async fn readdir(&self, path: &str) -> Option<Vec<DirEntry>> {
+        device_path::list(path)
+    }
+}
+
+// =============================================================================
+// Pointer Scheme - virtio mouse and tablet access
+// =============================================================================
+
+/// Scheme handler for pointer devices (`pointer:/pci/input/0`).
+///
+/// Like the keyboard, a pointer is not claimed: every open reads from the
+/// same event queue.
+pub struct PointerScheme;
+
+#[async_trait]
+impl SchemeHandler for PointerScheme {
+    async fn open(&self, path: &str) -> Result<Box<dyn Resource>, OpenError> {
+        // Resolve path like "/pci/input/0" or "/pci/00:02.0"
+        let address = device_path::resolve(path).ok_or(OpenError::NotFound)?;
+        let device = virtio_pointer::get_pointer(&address).ok_or(OpenError::NotFound)?;
+        Ok(Box::new(super::PointerDevice::new(device)))
+    }
+
+    async fn readdir(&self, path: &str) -> Option<Vec<DirEntry>> {
+        device_path::list(path)
+    }
+}
+
 // =============================================================================
 // Scheme Scheme - meta-scheme registry enumeration
 // =============================================================================
@@ -1016,5 +1075,7 @@ pub fn init() {
     register_scheme("keyboard", Arc::new(KeyboardScheme));
     register_scheme("display", Arc::new(DisplayScheme));
     register_scheme("block", Arc::new(BlockScheme));
+    register_scheme("net", Arc::new(NetScheme));
+    register_scheme("pointer", Arc::new(PointerScheme));
     register_scheme("scheme", Arc::new(SchemeScheme));
 }
@@ -9,6 +9,7 @@ use crate::process::info::ProcessInfo;
 use crate::process::waker::IoWaker;
 use crate::resource::process::{Process, ProcessError};
 use crate::resource::{ChannelEndpoint, MailboxRef, Resource};
+use crate::scheduler;
 
 /// A handle returned from spawn() that combines channel and process info.
 ///
@@ -92,9 +93,34 @@ impl Process for SpawnHandle {
         self.process_info.exit_code()
     }
 
-    fn signal(&self, _signal: u32) -> Result<(), ProcessError> {
-        // TODO: Implement signal delivery
-        Err(ProcessError::NotSupported)
+    fn signal(&self, signal: u32) -> Result<(), ProcessError> {
+        let pid = self.process_info.pid();
+        if self.process_info.has_exited() {
+            return Err(ProcessError::NotFound);
+        }
+        // The handle can be passed on, even to the child itself, but a
+        // process is not torn down or stopped from inside its own syscall.
+        if pid == scheduler::current_process_id() {
+            return Err(ProcessError::PermissionDenied);
+        }
+
+        match signal {
+            panda_abi::SIGNAL_TERM | panda_abi::SIGNAL_KILL => {
+                // Torn down under its own page table, not the caller's: see
+                // `scheduler::remove_process`.
+                scheduler::remove_process(pid);
+                self.process_info
+                    .set_exit_code(panda_abi::SIGNAL_EXIT_BASE + signal as i32);
+                Ok(())
+            }
+            panda_abi::SIGNAL_STOP => scheduler::stop_process(pid)
+                .then_some(())
+                .ok_or(ProcessError::NotFound),
+            panda_abi::SIGNAL_CONTINUE => scheduler::continue_process(pid)
+                .then_some(())
+                .ok_or(ProcessError::NotFound),
+            _ => Err(ProcessError::NotSupported),
+        }
     }
 
     fn waker(&self) -> Arc<IoWaker> {
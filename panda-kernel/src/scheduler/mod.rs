@@ -301,7 +301,7 @@ impl Scheduler {
                     // been woken by something else (or exited) since the
                     // deadline was registered.
                     if let Some(process) = self.processes.get(&pid) {
-                        if process.state() == ProcessState::Blocked {
+                        if process.state() == ProcessState::Blocked && !process.is_stopped() {
                             self.change_state(pid, ProcessState::Runnable);
                         }
                     }
@@ -311,6 +311,36 @@ impl Scheduler {
         count
     }
 
+    /// Stop scheduling a process until [`continue_process`](Self::continue_process).
+    /// It is left Blocked, and anything that would wake it in the meantime
+    /// is ignored. Returns `false` if there is no such process, or if it is
+    /// the one running (a process cannot stop itself).
+    pub fn stop_process(&mut self, pid: ProcessId) -> bool {
+        let Some(process) = self.processes.get_mut(&pid) else {
+            return false;
+        };
+        if process.state() == ProcessState::Running {
+            return false;
+        }
+        process.set_stopped(true);
+        self.change_state(pid, ProcessState::Blocked)
+    }
+
+    /// Let a stopped process run again. It is made Runnable even if it was
+    /// blocked on a syscall: the syscall's future is polled again and, if
+    /// it is still not ready, blocks it as before. Returns `false` if there
+    /// is no such process.
+    pub fn continue_process(&mut self, pid: ProcessId) -> bool {
+        let Some(process) = self.processes.get_mut(&pid) else {
+            return false;
+        };
+        if process.is_stopped() {
+            process.set_stopped(false);
+            self.change_state(pid, ProcessState::Runnable);
+        }
+        true
+    }
+
     /// Get the next deadline time (for timer calculation).
     pub fn next_deadline(&self) -> Option<u64> {
         self.deadline_tracker.next_deadline()
@@ -621,8 +651,27 @@ pub fn remove_process(pid: ProcessId) {
 
     // with_scheduler_mut's internal guard is dropped before it returns, so the
     // process is guaranteed to be dropped outside the scheduler lock here.
-    let process = with_scheduler_mut(|scheduler| scheduler.remove_process(pid));
+    let Some(process) = with_scheduler_mut(|scheduler| scheduler.remove_process(pid)) else {
+        return;
+    };
+
+    // Dropping the process unmaps and frees its memory by walking the
+    // *loaded* page table. Exits and faults run under the process's own, but
+    // a kill runs in the signaller's syscall, so switch to the victim's page
+    // table for the teardown, as `Process::from_elf_data` does to load one.
+    let saved_page_table = crate::memory::current_page_table_phys();
+    let page_table = process.page_table_phys();
+    if page_table == saved_page_table {
+        drop(process);
+        return;
+    }
+    unsafe {
+        crate::memory::switch_page_table(page_table);
+    }
     drop(process);
+    unsafe {
+        crate::memory::switch_page_table(saved_page_table);
+    }
 }
 
 /// Get the currently running process ID.
@@ -746,7 +795,17 @@ pub fn register_deadline(entity: SchedulableEntity, deadline_ms: u64) {
     with_scheduler_mut(|scheduler| scheduler.register_deadline(entity, deadline_ms));
 }
 
-/// Wake a blocked process, making it runnable again.
+/// Stop a process. See [`Scheduler::stop_process`].
+pub fn stop_process(pid: ProcessId) -> bool {
+    with_scheduler_mut(|scheduler| scheduler.stop_process(pid))
+}
+
+/// Resume a stopped process. See [`Scheduler::continue_process`].
+pub fn continue_process(pid: ProcessId) -> bool {
+    with_scheduler_mut(|scheduler| scheduler.continue_process(pid))
+}
+
+/// Wake a blocked process, making it runnable again, unless it is stopped.
 /// Called by wakers when data becomes available.
 ///
 /// If the process no longer exists (e.g., it was removed while a waker was
@@ -755,7 +814,7 @@ pub fn wake_process(pid: ProcessId) {
     with_scheduler_mut(|scheduler| {
         // Only wake if the process exists and is blocked
         if let Some(process) = scheduler.processes.get(&pid) {
-            if process.state() == ProcessState::Blocked {
+            if process.state() == ProcessState::Blocked && !process.is_stopped() {
                 scheduler.change_state(pid, ProcessState::Runnable);
                 debug!("Woke process {:?}", pid);
             }
@@ -1,12 +1,12 @@
-//! Device subscribe/claim syscall handlers (`OP_DEVICE_SUBSCRIBE`,
-//! `OP_DEVICE_CLAIM`).
+//! Device syscall handlers (`OP_DEVICE_SUBSCRIBE`, `OP_DEVICE_CLAIM`,
+//! `OP_DMA_ALLOC`, `OP_DMA_FREE`).
 //!
-//! These are the only two `OP_DEVICE_*` operations with real kernel
-//! handlers in this phase — `OP_DEVICE_MAP_MMIO`, `OP_DMA_ALLOC`,
-//! `OP_DMA_FREE`, and `OP_DEVICE_SUBSCRIBE_IRQ` are defined in `panda_abi`
-//! for ABI completeness but require IOMMU support (Phase 6) to implement
-//! safely, so they are not dispatched here (see `syscall/mod.rs`, which
-//! falls through to `NotSupported` for them, same as any unrecognised op).
+//! `OP_DEVICE_MAP_MMIO` and `OP_DEVICE_SUBSCRIBE_IRQ` are defined in
+//! `panda_abi` for ABI completeness but not dispatched yet (see
+//! `syscall/mod.rs`, which falls through to `NotSupported` for them, same
+//! as any unrecognised op). DMA goes through the claimed device's IOMMU
+//! domain (`iommu::manager`), so it is `NotSupported` when the platform has
+//! no IOMMU.
 //!
 //! Known gap: `OP_DEVICE_SUBSCRIBE`'s replay (and later `EVENT_DEVICE_ADDED`
 //! from `device::pci::register_enumerated_devices` / future hotplug) posts
@@ -23,15 +23,17 @@ use alloc::boxed::Box;
 use alloc::sync::Arc;
 
 use panda_abi::HandleType;
-use panda_abi::device::BusType;
+use panda_abi::device::{BusType, DmaAllocInfo};
 
 use crate::device::{ClaimError, DEVICE_REGISTRY};
+use crate::iommu::manager::{self, AttachError, DmaError};
+use crate::iommu::{self, IommuError};
 use crate::process::ProcessId;
-use crate::resource::{MailboxRef, Resource};
+use crate::resource::{BufferError, MailboxRef, Resource};
 use crate::scheduler;
 
 use super::helpers::attach_to_mailbox;
-use super::user_ptr::{SyscallFuture, SyscallResult, UserAccess, UserSlice};
+use super::user_ptr::{SyscallFuture, SyscallResult, UserAccess, UserPtr, UserSlice};
 
 /// Opaque handle installed in the caller's table when `OP_DEVICE_SUBSCRIBE`
 /// succeeds. Carries no state of its own today — see the module doc comment
@@ -118,14 +120,100 @@ pub fn handle_device_subscribe(
 /// `handle` carries the raw token value (not a resource in the caller's
 /// handle table — see `device::DeviceRegistry`). Consumes the token; on
 /// success returns the claimed device's `DeviceId` as the "owned device
-/// handle" (a real `Device`-typed resource handle, and the MMIO/DMA/IRQ
-/// syscalls that act on it, are Phase 6).
+/// handle" (a real `Device`-typed resource handle is still to come).
+///
+/// A claimed PCI device is moved into an IOMMU domain of its own, where it
+/// can reach only memory allocated with `OP_DMA_ALLOC`. If that fails the
+/// claim is undone and the error returned; a device a kernel driver drives
+/// is refused with `Busy`.
 pub fn handle_device_claim(handle: u64) -> SyscallFuture {
     let pid: ProcessId = scheduler::current_process_id();
-    let result = match DEVICE_REGISTRY.lock().claim(handle, pid) {
-        Ok(device_id) => SyscallResult::ok(device_id as isize),
+    let claimed = DEVICE_REGISTRY.lock().claim(handle, pid);
+    let result = match claimed {
+        Ok(device_id) => match manager::attach(device_id, pid) {
+            Ok(()) => SyscallResult::ok(device_id as isize),
+            Err(AttachError::KernelDriver) => {
+                DEVICE_REGISTRY.lock().abandon_claim(device_id);
+                SyscallResult::err(panda_abi::ErrorCode::Busy)
+            }
+            Err(AttachError::Iommu(err)) => {
+                log::warn!("device: could not isolate device {}: {:?}", device_id, err);
+                DEVICE_REGISTRY.lock().abandon_claim(device_id);
+                SyscallResult::err(iommu_error_code(err))
+            }
+        },
         Err(ClaimError::InvalidToken) => SyscallResult::err(panda_abi::ErrorCode::InvalidHandle),
         Err(ClaimError::AlreadyClaimed) => SyscallResult::err(panda_abi::ErrorCode::Busy),
     };
     Box::pin(core::future::ready(result))
 }
+
+/// Handle `OP_DMA_ALLOC(device_handle, size, info_ptr)`.
+///
+/// Allocates `size` bytes (rounded up to whole pages), mapped into the
+/// caller and into the claimed device's IOMMU domain, and writes the
+/// mapping's address and IOVA to `info_ptr` as a `DmaAllocInfo`. Returns 0.
+pub fn handle_dma_alloc(
+    ua: &UserAccess,
+    device: u64,
+    size: usize,
+    info_ptr: usize,
+) -> SyscallFuture {
+    iommu::poll_faults();
+    let result = scheduler::with_current_process(|proc| manager::dma_alloc(proc, device, size));
+    let result = match result {
+        Ok((addr, iova)) => {
+            let info = DmaAllocInfo { addr, iova };
+            match ua.write_user(UserPtr::new(info_ptr), &info) {
+                Ok(()) => SyscallResult::ok(0),
+                Err(_) => {
+                    // Don't leak an allocation the caller can't learn about.
+                    let _ = scheduler::with_current_process(|proc| {
+                        manager::dma_free(proc, device, addr, size)
+                    });
+                    SyscallResult::err(panda_abi::ErrorCode::InvalidArgument)
+                }
+            }
+        }
+        Err(err) => SyscallResult::err(dma_error_code(err)),
+    };
+    Box::pin(core::future::ready(result))
+}
+
+/// Handle `OP_DMA_FREE(device_handle, virt_addr, size)`.
+///
+/// `virt_addr` and `size` must be exactly as returned by / passed to
+/// `OP_DMA_ALLOC`. The device loses access before the memory is freed.
+pub fn handle_dma_free(device: u64, virt_addr: usize, size: usize) -> SyscallFuture {
+    iommu::poll_faults();
+    let result = scheduler::with_current_process(|proc| {
+        manager::dma_free(proc, device, virt_addr, size)
+    });
+    let result = match result {
+        Ok(()) => SyscallResult::ok(0),
+        Err(err) => SyscallResult::err(dma_error_code(err)),
+    };
+    Box::pin(core::future::ready(result))
+}
+
+fn dma_error_code(err: DmaError) -> panda_abi::ErrorCode {
+    match err {
+        DmaError::NotSupported => panda_abi::ErrorCode::NotSupported,
+        DmaError::NotOwner => panda_abi::ErrorCode::PermissionDenied,
+        DmaError::InvalidAddress | DmaError::Buffer(BufferError::InvalidSize) => {
+            panda_abi::ErrorCode::InvalidArgument
+        }
+        DmaError::Buffer(_) => panda_abi::ErrorCode::NoSpace,
+        DmaError::Iommu(err) => iommu_error_code(err),
+    }
+}
+
+fn iommu_error_code(err: IommuError) -> panda_abi::ErrorCode {
+    match err {
+        IommuError::OutOfMemory | IommuError::DomainsExhausted => panda_abi::ErrorCode::NoSpace,
+        IommuError::InvalidAddress | IommuError::AlreadyMapped => {
+            panda_abi::ErrorCode::InvalidArgument
+        }
+        _ => panda_abi::ErrorCode::IoError,
+    }
+}
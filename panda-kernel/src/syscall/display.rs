@@ -9,10 +9,10 @@
 
 use alloc::boxed::Box;
 
-use crate::resource::Rect;
+use crate::resource::{Rect, SurfaceError};
 use crate::scheduler;
 
-use super::user_ptr::{SyscallFuture, SyscallResult, UserAccess, UserPtr};
+use super::user_ptr::{SyscallFuture, SyscallResult, UserAccess, UserPtr, UserSlice};
 
 /// Handle `OP_DISPLAY_INFO`: write the display's mode info to `info_ptr`.
 ///
@@ -126,6 +126,155 @@ pub fn handle_flush(
     }
 }
 
+/// Handle `OP_DISPLAY_CURSOR_SET`: load the hardware cursor's image from
+/// `image_ptr`, or hide the cursor if it is 0.
+pub fn handle_cursor_set(
+    ua: &UserAccess,
+    handle: u64,
+    image_ptr: usize,
+    hot_x: u32,
+    hot_y: u32,
+) -> SyscallFuture {
+    let size = panda_abi::DISPLAY_CURSOR_SIZE as usize;
+    let image = if image_ptr == 0 {
+        None
+    } else {
+        match ua.read(UserSlice::new(image_ptr, size * size * 4)) {
+            Ok(image) => Some(image),
+            Err(_) => return err(panda_abi::ErrorCode::InvalidArgument),
+        }
+    };
+
+    let result = scheduler::with_current_process(|proc| {
+        let resource = proc
+            .handles()
+            .get(handle)
+            .ok_or(panda_abi::ErrorCode::InvalidHandle)?;
+        let display = resource
+            .as_display()
+            .ok_or(panda_abi::ErrorCode::InvalidHandle)?;
+
+        display
+            .set_cursor(image.as_deref(), hot_x, hot_y)
+            .map_err(surface_error)
+    });
+
+    match result {
+        Ok(()) => Box::pin(core::future::ready(SyscallResult::ok(0))),
+        Err(code) => err(code),
+    }
+}
+
+/// Handle `OP_DISPLAY_CURSOR_MOVE`: move the hardware cursor's hotspot.
+pub fn handle_cursor_move(handle: u64, x: u32, y: u32) -> SyscallFuture {
+    let result = scheduler::with_current_process(|proc| {
+        let resource = proc
+            .handles()
+            .get(handle)
+            .ok_or(panda_abi::ErrorCode::InvalidHandle)?;
+        let display = resource
+            .as_display()
+            .ok_or(panda_abi::ErrorCode::InvalidHandle)?;
+
+        display.move_cursor(x, y).map_err(surface_error)
+    });
+
+    match result {
+        Ok(()) => Box::pin(core::future::ready(SyscallResult::ok(0))),
+        Err(code) => err(code),
+    }
+}
+
+/// Handle `OP_DISPLAY_MODES`: write up to `capacity` of the display's modes
+/// to `modes_ptr` and return how many it has.
+pub fn handle_modes(
+    ua: &UserAccess,
+    handle: u64,
+    modes_ptr: usize,
+    capacity: usize,
+) -> SyscallFuture {
+    if modes_ptr == 0 && capacity != 0 {
+        return err(panda_abi::ErrorCode::InvalidArgument);
+    }
+
+    let result = scheduler::with_current_process(|proc| {
+        let resource = proc
+            .handles()
+            .get(handle)
+            .ok_or(panda_abi::ErrorCode::InvalidHandle)?;
+        let display = resource
+            .as_display()
+            .ok_or(panda_abi::ErrorCode::InvalidHandle)?;
+        Ok(display.modes())
+    });
+
+    let modes = match result {
+        Ok(modes) => modes,
+        Err(code) => return err(code),
+    };
+    let count = modes.len().min(panda_abi::MAX_DISPLAY_MODES);
+    let size = core::mem::size_of::<panda_abi::DisplayMode>();
+    for (index, mode) in modes.iter().take(count.min(capacity)).enumerate() {
+        let mut flags = 0;
+        if mode.preferred {
+            flags |= panda_abi::DISPLAY_MODE_PREFERRED;
+        }
+        if mode.current {
+            flags |= panda_abi::DISPLAY_MODE_CURRENT;
+        }
+        let out = panda_abi::DisplayMode {
+            width: mode.width,
+            height: mode.height,
+            flags,
+        };
+        if ua
+            .write_user(UserPtr::new(modes_ptr + index * size), &out)
+            .is_err()
+        {
+            return err(panda_abi::ErrorCode::InvalidArgument);
+        }
+    }
+    Box::pin(core::future::ready(SyscallResult::ok(count as isize)))
+}
+
+/// Handle `OP_DISPLAY_SET_MODE`: switch the display to one of its modes.
+pub fn handle_set_mode(handle: u64, width: u32, height: u32) -> SyscallFuture {
+    // The mode change posts `EVENT_DISPLAY_CHANGED`, which may wake the
+    // owner, so it runs on an owned `Arc` to the resource rather than under
+    // the scheduler lock `with_current_process` holds.
+    let resource = scheduler::with_current_process(|proc| {
+        proc.handles()
+            .get(handle)
+            .map(|handle| handle.resource_arc())
+    });
+    let Some(resource) = resource else {
+        return err(panda_abi::ErrorCode::InvalidHandle);
+    };
+    let Some(display) = resource.as_display() else {
+        return err(panda_abi::ErrorCode::InvalidHandle);
+    };
+    if !display.has_mode(width, height) {
+        return err(panda_abi::ErrorCode::InvalidArgument);
+    }
+
+    // The driver frees the old framebuffer, so the owner's mappings of it go
+    // first. The owner is the caller, so its page table is the one loaded.
+    scheduler::with_current_process(|proc| display.unmap_from_process(proc));
+    let result = display.set_mode(width, height).map_err(surface_error);
+
+    match result {
+        Ok(()) => Box::pin(core::future::ready(SyscallResult::ok(0))),
+        Err(code) => err(code),
+    }
+}
+
+fn surface_error(error: SurfaceError) -> panda_abi::ErrorCode {
+    match error {
+        SurfaceError::InvalidBounds => panda_abi::ErrorCode::InvalidArgument,
+        SurfaceError::DeviceFailed => panda_abi::ErrorCode::IoError,
+    }
+}
+
 fn err(code: panda_abi::ErrorCode) -> SyscallFuture {
     Box::pin(core::future::ready(SyscallResult::err(code)))
 }
@@ -209,17 +209,22 @@ pub fn handle_spawn(ua: &UserAccess, params_ptr: UserPtr<panda_abi::SpawnParams>
 
     debug!("SPAWN: uri={}", uri);
 
-    // Get stdin/stdout resources from parent's handle table (if specified)
+    // Get stdin/stdout resources from parent's handle table (if specified),
+    // with the parent's offset, so a file opened for appending is appended to
     let stdin_resource = if stdin_handle != 0 {
         scheduler::with_current_process(|proc| {
-            proc.handles().get(stdin_handle).map(|h| h.resource_arc())
+            proc.handles()
+                .get(stdin_handle)
+                .map(|h| (h.resource_arc(), h.offset()))
         })
     } else {
         None
     };
     let stdout_resource = if stdout_handle != 0 {
         scheduler::with_current_process(|proc| {
-            proc.handles().get(stdout_handle).map(|h| h.resource_arc())
+            proc.handles()
+                .get(stdout_handle)
+                .map(|h| (h.resource_arc(), h.offset()))
         })
     } else {
         None
@@ -265,15 +270,17 @@ pub fn handle_spawn(ua: &UserAccess, params_ptr: UserPtr<panda_abi::SpawnParams>
             .insert_at(panda_abi::HANDLE_PARENT, Arc::new(child_endpoint));
 
         // Set up stdin/stdout if specified by parent
-        if let Some(stdin_res) = stdin_resource {
-            process
-                .handles_mut()
-                .insert_at(panda_abi::HANDLE_STDIN, stdin_res);
-        }
-        if let Some(stdout_res) = stdout_resource {
-            process
-                .handles_mut()
-                .insert_at(panda_abi::HANDLE_STDOUT, stdout_res);
+        for (id, stdio) in [
+            (panda_abi::HANDLE_STDIN, stdin_resource),
+            (panda_abi::HANDLE_STDOUT, stdout_resource),
+        ] {
+            if let Some((resource, offset)) = stdio {
+                let handles = process.handles_mut();
+                handles.insert_at(id, resource);
+                if let Some(handle) = handles.get_mut(id) {
+                    handle.set_offset(offset);
+                }
+            }
         }
 
         scheduler::add_process(process);
@@ -158,18 +158,10 @@ fn handle_read_sync(handle_id: u64, dst: UserSlice, flags: u32) -> SyscallFuture
 
             if let Some(event_source) = handle.as_event_source() {
                 if let Some(event) = event_source.poll() {
-                    let event_bytes = match event {
-                        crate::resource::Event::Key(key) => {
-                            // struct InputEvent { event_type: u16, code: u16, value: u32 }
-                            let mut bytes = [0u8; 8];
-                            bytes[0..2].copy_from_slice(&0x01u16.to_ne_bytes()); // EV_KEY
-                            bytes[2..4].copy_from_slice(&key.code.to_ne_bytes());
-                            bytes[4..8].copy_from_slice(&key.value.to_ne_bytes());
-                            bytes.to_vec()
-                        }
-                    };
+                    let mut event_bytes = event_to_bytes(event);
                     let n = event_bytes.len().min(dst.len());
-                    Some(Some((n as isize, event_bytes[..n].to_vec())))
+                    event_bytes.truncate(n);
+                    Some(Some((n as isize, event_bytes)))
                 } else {
                     Some(None) // No event available
                 }
@@ -209,17 +201,9 @@ fn handle_read_sync(handle_id: u64, dst: UserSlice, flags: u32) -> SyscallFuture
                 };
 
                 let event_to_result = |event: crate::resource::Event| {
-                    let event_bytes = match event {
-                        crate::resource::Event::Key(key) => {
-                            let mut bytes = [0u8; 8];
-                            bytes[0..2].copy_from_slice(&0x01u16.to_ne_bytes());
-                            bytes[2..4].copy_from_slice(&key.code.to_ne_bytes());
-                            bytes[4..8].copy_from_slice(&key.value.to_ne_bytes());
-                            bytes.to_vec()
-                        }
-                    };
-                    let n = event_bytes.len().min(dst.len());
-                    let data = event_bytes[..n].to_vec();
+                    let mut data = event_to_bytes(event);
+                    let n = data.len().min(dst.len());
+                    data.truncate(n);
                     SyscallResult::write_back(n as isize, data, dst)
                 };
 
@@ -250,6 +234,28 @@ fn handle_read_sync(handle_id: u64, dst: UserSlice, flags: u32) -> SyscallFuture
     }
 }
 
+/// Serialize an event for a read. Anything past the end of the caller's
+/// buffer is dropped.
+fn event_to_bytes(event: crate::resource::Event) -> alloc::vec::Vec<u8> {
+    match event {
+        crate::resource::Event::Key(key) => {
+            // struct InputEvent { event_type: u16, code: u16, value: u32 }
+            let mut bytes = [0u8; 8];
+            bytes[0..2].copy_from_slice(&0x01u16.to_ne_bytes()); // EV_KEY
+            bytes[2..4].copy_from_slice(&key.code.to_ne_bytes());
+            bytes[4..8].copy_from_slice(&key.value.to_ne_bytes());
+            bytes.to_vec()
+        }
+        crate::resource::Event::Input(input) => {
+            let mut bytes = [0u8; 8];
+            bytes[0..2].copy_from_slice(&input.event_type.to_ne_bytes());
+            bytes[2..4].copy_from_slice(&input.code.to_ne_bytes());
+            bytes[4..8].copy_from_slice(&input.value.to_ne_bytes());
+            bytes.to_vec()
+        }
+    }
+}
+
 /// Handle file write operation.
 ///
 /// For VFS files, this is async and may yield to the scheduler if I/O is needed.
@@ -541,8 +547,15 @@ pub fn handle_stat(handle_id: u64, stat_ptr: usize) -> SyscallFuture {
 
 /// Handle file close operation.
 pub fn handle_close(handle_id: u64) -> SyscallFuture {
-    let removed =
-        scheduler::with_current_process(|proc| proc.handles_mut().remove(handle_id).is_some());
+    let removed = scheduler::with_current_process(|proc| {
+        // A display's framebuffer mappings go with its handle: the next
+        // owner may change the mode, which frees the framebuffer.
+        let resource = proc.handles().get(handle_id).map(|h| h.resource_arc());
+        if let Some(display) = resource.as_ref().and_then(|r| r.as_display()) {
+            display.unmap_from_process(proc);
+        }
+        proc.handles_mut().remove(handle_id).is_some()
+    });
     if removed {
         Box::pin(core::future::ready(SyscallResult::ok(0)))
     } else {
@@ -10,7 +10,9 @@ use panda_abi::HandleType;
 use crate::resource::Mailbox;
 use crate::scheduler;
 
-use super::helpers::{downcast_or_invalid, resolve_resource};
+use super::helpers::{
+    attach_to_mailbox, complete_mailbox_attach, downcast_or_invalid, resolve_resource,
+};
 use super::poll_fn;
 use super::user_ptr::{SyscallFuture, SyscallResult, UserAccess, UserPtr};
 
@@ -123,3 +125,36 @@ pub fn handle_poll(ua: &UserAccess, mailbox_handle: u64, out_ptr: usize) -> Sysc
         ))),
     }
 }
+
+/// Handle mailbox attach operation.
+/// Attaches a handle that is already open to a mailbox, as `open` and `spawn`
+/// do for the handles they create. Used for handles that arrive some other
+/// way, such as a channel returned by `connect`.
+///
+/// Arguments:
+/// - mailbox_handle: The mailbox handle
+/// - target: The handle to attach
+/// - event_mask: The events to deliver for `target`
+///
+/// Returns 0 on success, `InvalidHandle` if either handle is invalid, or
+/// `NotSupported` if `target` never posts any of the events in `event_mask`.
+/// Events already pending on `target` are posted straight away, so a caller
+/// attaching a channel with queued messages still hears about them.
+pub fn handle_attach(mailbox_handle: u64, target: u64, event_mask: u32) -> SyscallFuture {
+    let result = scheduler::with_current_process(|proc| {
+        let handle = proc.handles().get(target)?;
+        if handle.supported_events() & event_mask == 0 {
+            return Some(SyscallResult::err(panda_abi::ErrorCode::NotSupported));
+        }
+        let pending = handle.poll_events() & event_mask;
+        let mailbox = attach_to_mailbox(proc, mailbox_handle, target, event_mask)?;
+        complete_mailbox_attach(proc, mailbox, target);
+        if pending != 0 {
+            mailbox.post_event(target, pending);
+        }
+        Some(SyscallResult::ok(0))
+    });
+    Box::pin(core::future::ready(result.unwrap_or_else(|| {
+        SyscallResult::err(panda_abi::ErrorCode::InvalidHandle)
+    })))
+}
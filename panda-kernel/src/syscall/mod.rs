@@ -17,6 +17,8 @@ mod file;
 pub mod gdt;
 mod helpers;
 mod mailbox;
+mod net;
+mod pointer;
 mod process;
 mod scheme;
 pub(crate) mod user_ptr;
@@ -217,7 +219,7 @@ fn build_future(
         // Process operations (yield and exit are handled above as diverging)
         OP_PROCESS_GET_PID => Ok(process::handle_get_pid()),
         OP_PROCESS_WAIT => Ok(process::handle_wait(handle)),
-        OP_PROCESS_SIGNAL => Ok(process::handle_signal()),
+        OP_PROCESS_SIGNAL => Ok(process::handle_signal(handle, arg0 as u32)),
         OP_PROCESS_BRK => Ok(process::handle_brk(arg0)),
         OP_PROCESS_SLEEP => Ok(process::handle_sleep(arg0 as u64)),
 
@@ -262,11 +264,42 @@ fn build_future(
                 None
             },
         )),
+        OP_DISPLAY_CURSOR_SET => Ok(display::handle_cursor_set(
+            ua,
+            handle,
+            arg0,
+            arg1 as u32,
+            arg2 as u32,
+        )),
+        OP_DISPLAY_CURSOR_MOVE => Ok(display::handle_cursor_move(
+            handle,
+            arg0 as u32,
+            arg1 as u32,
+        )),
+        OP_DISPLAY_MODES => Ok(display::handle_modes(ua, handle, arg0, arg1)),
+        OP_DISPLAY_SET_MODE => Ok(display::handle_set_mode(
+            handle,
+            arg0 as u32,
+            arg1 as u32,
+        )),
+
+        // Network device operations
+        OP_NET_INFO => Ok(net::handle_info(ua, handle, user_ptr::UserPtr::new(arg0))),
+        OP_NET_RING => Ok(net::handle_ring(handle, arg0 as u64)),
+        OP_NET_KICK => Ok(net::handle_kick(handle)),
+
+        // Pointer device operations
+        OP_POINTER_INFO => Ok(pointer::handle_info(
+            ua,
+            handle,
+            user_ptr::UserPtr::new(arg0),
+        )),
 
         // Mailbox operations
         OP_MAILBOX_CREATE => Ok(mailbox::handle_create()),
         OP_MAILBOX_WAIT => Ok(mailbox::handle_wait(ua, handle, arg0)),
         OP_MAILBOX_POLL => Ok(mailbox::handle_poll(ua, handle, arg0)),
+        OP_MAILBOX_ATTACH => Ok(mailbox::handle_attach(handle, arg0 as u64, arg1 as u32)),
 
         // Channel operations
         OP_CHANNEL_CREATE => Ok(channel::handle_create(ua, arg0)),
@@ -276,13 +309,17 @@ fn build_future(
         // Scheme operations
         OP_SCHEME_REGISTER => Ok(scheme::handle_register(ua, arg0, arg1)),
 
-        // Device operations (only SUBSCRIBE/CLAIM are implemented; the rest
-        // are reserved pending IOMMU support and fall through to
-        // NotSupported below, same as any unrecognised operation).
+        // Device operations (MAP_MMIO and SUBSCRIBE_IRQ are still reserved
+        // and fall through to NotSupported below, same as any unrecognised
+        // operation).
         panda_abi::device::OP_DEVICE_SUBSCRIBE => {
             Ok(self::device::handle_device_subscribe(ua, arg0, arg1, arg2, arg3))
         }
         panda_abi::device::OP_DEVICE_CLAIM => Ok(self::device::handle_device_claim(handle)),
+        panda_abi::device::OP_DMA_ALLOC => {
+            Ok(self::device::handle_dma_alloc(ua, handle, arg0, arg1))
+        }
+        panda_abi::device::OP_DMA_FREE => Ok(self::device::handle_dma_free(handle, arg0, arg1)),
 
         _ => {
             error!("Unknown operation: {:#x}", operation);
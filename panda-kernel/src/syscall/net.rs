@@ -0,0 +1,108 @@
+//! Network device syscall handlers (`OP_NET_*`).
+//!
+//! These operate on a handle opened from the `net:` scheme. Frames move
+//! through a ring in a shared buffer (`panda_abi::net`), so only attaching
+//! the ring and asking for its queued frames to be sent take a syscall.
+
+#![deny(unsafe_code)]
+
+use alloc::boxed::Box;
+
+use crate::devices::virtio_net::NetError;
+use crate::scheduler;
+
+use super::user_ptr::{SyscallFuture, SyscallResult, UserAccess, UserPtr};
+
+/// Handle `OP_NET_INFO`: write the device's MAC address and maximum frame
+/// size to `info_ptr`.
+pub fn handle_info(
+    ua: &UserAccess,
+    handle: u64,
+    info_ptr: UserPtr<panda_abi::NetInfo>,
+) -> SyscallFuture {
+    if info_ptr.addr() == 0 {
+        return err(panda_abi::ErrorCode::InvalidArgument);
+    }
+
+    let result = scheduler::with_current_process(|proc| {
+        let resource = proc
+            .handles()
+            .get(handle)
+            .ok_or(panda_abi::ErrorCode::InvalidHandle)?;
+        let net = resource
+            .as_net()
+            .ok_or(panda_abi::ErrorCode::InvalidHandle)?;
+
+        Ok(panda_abi::NetInfo {
+            mac: net.mac_address(),
+            max_frame_size: panda_abi::NET_MAX_FRAME_SIZE as u16,
+        })
+    });
+
+    match result {
+        Ok(info) => {
+            if ua.write_user(info_ptr, &info).is_err() {
+                return err(panda_abi::ErrorCode::InvalidArgument);
+            }
+            Box::pin(core::future::ready(SyscallResult::ok(0)))
+        }
+        Err(code) => err(code),
+    }
+}
+
+/// Handle `OP_NET_RING`: move frames through the buffer `buffer_handle`
+/// from now on.
+pub fn handle_ring(handle: u64, buffer_handle: u64) -> SyscallFuture {
+    let result = scheduler::with_current_process(|proc| {
+        let handles = proc.handles();
+        let resource = handles
+            .get(handle)
+            .ok_or(panda_abi::ErrorCode::InvalidHandle)?;
+        let net = resource
+            .as_net()
+            .ok_or(panda_abi::ErrorCode::InvalidHandle)?;
+        let buffer = handles
+            .get(buffer_handle)
+            .and_then(|buffer| buffer.resource_arc().as_shared_buffer())
+            .ok_or(panda_abi::ErrorCode::InvalidHandle)?;
+        net.attach_ring(buffer).map_err(to_error_code)
+    });
+
+    match result {
+        Ok(()) => Box::pin(core::future::ready(SyscallResult::ok(0))),
+        Err(code) => err(code),
+    }
+}
+
+/// Handle `OP_NET_KICK`: transmit the frames queued in the ring, returning
+/// how many went.
+pub fn handle_kick(handle: u64) -> SyscallFuture {
+    let result = scheduler::with_current_process(|proc| {
+        let resource = proc
+            .handles()
+            .get(handle)
+            .ok_or(panda_abi::ErrorCode::InvalidHandle)?;
+        let net = resource
+            .as_net()
+            .ok_or(panda_abi::ErrorCode::InvalidHandle)?;
+        net.kick().map_err(to_error_code)
+    });
+
+    match result {
+        Ok(sent) => Box::pin(core::future::ready(SyscallResult::ok(sent as isize))),
+        Err(code) => err(code),
+    }
+}
+
+fn to_error_code(error: NetError) -> panda_abi::ErrorCode {
+    match error {
+        NetError::NoRing | NetError::RingTooSmall | NetError::InvalidFrame => {
+            panda_abi::ErrorCode::InvalidArgument
+        }
+        NetError::IoError => panda_abi::ErrorCode::IoError,
+    }
+}
+
+fn err(code: panda_abi::ErrorCode) -> SyscallFuture {
+    Box::pin(core::future::ready(SyscallResult::err(code)))
+}
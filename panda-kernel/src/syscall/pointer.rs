@@ -0,0 +1,50 @@
+//! Pointer device syscall handlers (`OP_POINTER_*`).
+//!
+//! These operate on a handle opened from the `pointer:` scheme. Events are
+//! read with the ordinary file operations (see `syscall/file.rs`); only the
+//! device description needs an operation of its own.
+
+#![deny(unsafe_code)]
+
+use alloc::boxed::Box;
+
+use crate::scheduler;
+
+use super::user_ptr::{SyscallFuture, SyscallResult, UserAccess, UserPtr};
+
+/// Handle `OP_POINTER_INFO`: write whether the device is relative or
+/// absolute, and its axis ranges, to `info_ptr`.
+pub fn handle_info(
+    ua: &UserAccess,
+    handle: u64,
+    info_ptr: UserPtr<panda_abi::PointerInfo>,
+) -> SyscallFuture {
+    if info_ptr.addr() == 0 {
+        return err(panda_abi::ErrorCode::InvalidArgument);
+    }
+
+    let result = scheduler::with_current_process(|proc| {
+        let resource = proc
+            .handles()
+            .get(handle)
+            .ok_or(panda_abi::ErrorCode::InvalidHandle)?;
+        let pointer = resource
+            .as_pointer()
+            .ok_or(panda_abi::ErrorCode::InvalidHandle)?;
+        Ok(pointer.info())
+    });
+
+    match result {
+        Ok(info) => {
+            if ua.write_user(info_ptr, &info).is_err() {
+                return err(panda_abi::ErrorCode::InvalidArgument);
+            }
+            Box::pin(core::future::ready(SyscallResult::ok(0)))
+        }
+        Err(code) => err(code),
+    }
+}
+
+fn err(code: panda_abi::ErrorCode) -> SyscallFuture {
+    Box::pin(core::future::ready(SyscallResult::err(code)))
+}
@@ -88,10 +88,19 @@ pub fn handle_sleep(duration_ms: u64) -> SyscallFuture {
 }
 
 /// Handle process signal operation.
-pub fn handle_signal() -> SyscallFuture {
-    Box::pin(core::future::ready(SyscallResult::err(
-        panda_abi::ErrorCode::NotSupported,
-    )))
+///
+/// Signals take effect at once (see `SIGNAL_*` in `panda_abi`), so this
+/// never blocks.
+pub fn handle_signal(handle_id: u64, signal: u32) -> SyscallFuture {
+    let resource = resolve_resource(handle_id, |h| h.as_process().is_some());
+    let result = match downcast_or_invalid(&resource, |r| r.as_process()) {
+        Some(process_iface) => match process_iface.signal(signal) {
+            Ok(()) => SyscallResult::ok(0),
+            Err(err) => SyscallResult::err(err.into()),
+        },
+        None => SyscallResult::err(panda_abi::ErrorCode::InvalidHandle),
+    };
+    Box::pin(core::future::ready(result))
 }
 
 /// Handle process brk operation.
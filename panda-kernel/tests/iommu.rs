@@ -0,0 +1,148 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+use panda_abi::device::{BusType, PciDeviceId};
+use panda_kernel::device::{self, DEVICE_REGISTRY};
+use panda_kernel::iommu::{self, IommuDomain, IommuError, IommuFlags, dmar_acpi, manager};
+use panda_kernel::memory;
+use panda_kernel::process::ProcessId;
+use panda_kernel::resource::{Mailbox, MailboxRef};
+
+panda_kernel::test_harness!(
+    dmar_table_present,
+    translation_enabled,
+    domain_map_unmap_frees_tables,
+    overlapping_map_rejected,
+    claimed_device_gets_own_domain,
+    process_exit_restores_passthrough,
+    kernel_driver_device_refused
+);
+
+/// QEMU's `intel-iommu` device publishes a DMAR table with one DRHD.
+fn dmar_table_present() {
+    let table = dmar_acpi::read_dmar().expect("DMAR table should be present");
+    assert!(!table.drhds.is_empty(), "expected at least one DRHD");
+    for drhd in &table.drhds {
+        assert_ne!(drhd.register_base, 0);
+    }
+}
+
+fn translation_enabled() {
+    assert!(iommu::get().is_some(), "IOMMU should be active");
+    assert!(iommu::passthrough_domain().is_some());
+    assert!(
+        iommu::drain_faults().is_empty(),
+        "no DMA should have faulted during boot"
+    );
+}
+
+/// Mapping allocates page table frames; dropping the domain returns them.
+fn domain_map_unmap_frees_tables() {
+    let iommu = iommu::get().unwrap();
+    let before = iommu::table_frame_count();
+
+    let frame = memory::allocate_frame();
+    let phys = frame.start_address().as_u64();
+    let domain = IommuDomain::new(iommu).expect("create domain");
+    iommu
+        .map(domain.id(), 0x10_0000, phys, 4096, IommuFlags::READ_WRITE)
+        .expect("map");
+    assert!(iommu::table_frame_count() > before);
+
+    iommu.unmap(domain.id(), 0x10_0000, 4096);
+    drop(domain);
+    assert_eq!(iommu::table_frame_count(), before);
+}
+
+fn overlapping_map_rejected() {
+    let iommu = iommu::get().unwrap();
+    let frame = memory::allocate_frame();
+    let phys = frame.start_address().as_u64();
+    let domain = IommuDomain::new(iommu).expect("create domain");
+
+    iommu
+        .map(domain.id(), 0x20_0000, phys, 4096, IommuFlags::READ)
+        .expect("first map");
+    assert_eq!(
+        iommu.map(domain.id(), 0x20_0000, phys, 4096, IommuFlags::READ),
+        Err(IommuError::AlreadyMapped)
+    );
+}
+
+/// Claim the first PCI device matching `id` through the global registry.
+fn claim_pci(pid: ProcessId, id: PciDeviceId) -> u64 {
+    let ptr = &id as *const _ as *const u8;
+    let match_bytes: Vec<u8> =
+        unsafe { core::slice::from_raw_parts(ptr, core::mem::size_of_val(&id)) }.to_vec();
+
+    let mailbox = Mailbox::new();
+    let mailbox_ref = MailboxRef::new(&mailbox, 0x2000_0000_0000_0001);
+    let replayed = DEVICE_REGISTRY
+        .lock()
+        .subscribe(BusType::Pci, match_bytes, pid, mailbox_ref);
+    let (_, token) = *replayed.first().expect("device should be present");
+    DEVICE_REGISTRY.lock().claim(token, pid).expect("claim")
+}
+
+/// Claim QEMU's `pci-testdev` (1B36:0005), which no kernel driver binds.
+/// `setup-kernel-test.sh` adds it for this test only.
+fn claim_testdev(pid: ProcessId) -> u64 {
+    claim_pci(
+        pid,
+        PciDeviceId {
+            vendor_id: 0x1B36,
+            device_id: 0x0005,
+            class: 0,
+            class_mask: 0,
+        },
+    )
+}
+
+fn claimed_device_gets_own_domain() {
+    let pid = ProcessId::new();
+    let device_id = claim_testdev(pid);
+    manager::attach(device_id, pid).expect("attach");
+
+    let domain = manager::domain_of(device_id).expect("claimed device should have a domain");
+    assert_ne!(Some(domain), iommu::passthrough_domain());
+
+    device::release_all_owned_by(pid);
+}
+
+fn process_exit_restores_passthrough() {
+    let pid = ProcessId::new();
+    let device_id = claim_testdev(pid);
+    manager::attach(device_id, pid).expect("attach");
+    let before = manager::domain_of(device_id);
+    assert!(before.is_some());
+
+    device::release_all_owned_by(pid);
+    assert_eq!(manager::domain_of(device_id), None);
+    assert_eq!(DEVICE_REGISTRY.lock().owner(device_id), None);
+}
+
+/// The virtio mouse is driven by the kernel's pointer driver, so it must
+/// not be moved out of the passthrough domain.
+fn kernel_driver_device_refused() {
+    let pid = ProcessId::new();
+    let device_id = claim_pci(
+        pid,
+        PciDeviceId {
+            vendor_id: 0x1AF4,
+            device_id: 0x1052,
+            class: 0x0902,
+            class_mask: 0xFFFF,
+        },
+    );
+    assert_eq!(
+        manager::attach(device_id, pid),
+        Err(manager::AttachError::KernelDriver)
+    );
+    assert_eq!(manager::domain_of(device_id), None);
+
+    device::release_all_owned_by(pid);
+}
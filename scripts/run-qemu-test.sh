@@ -31,6 +31,7 @@ fi
 QEMU_CMD=(
     qemu-system-x86_64 -nodefaults
     -machine q35 -m 1G
+    -device intel-iommu,aw-bits=48
     -cpu qemu64,+smap
     -serial stdio
     -boot menu=off
@@ -52,6 +53,38 @@ if [ -f "$BUILD_DIR/test-disk.img" ]; then
     QEMU_CMD+=(-device "virtio-blk-pci,drive=blk0")
 fi
 
+# Add a virtio-net device on an isolated user-mode network if the test asked
+# for one (needs-net marker file). QEMU's gateway at 10.0.2.2 still answers,
+# as do any forwarding rules the marker file lists (one option per line,
+# e.g. guestfwd=tcp:10.0.2.100:7-cmd:cat).
+if [ -f "$BUILD_DIR/needs-net" ]; then
+    NETDEV="user,id=net0,restrict=on"
+    while read -r option || [ -n "$option" ]; do
+        [ -n "$option" ] && NETDEV+=",$option"
+    done < "$BUILD_DIR/needs-net"
+    QEMU_CMD+=(-netdev "$NETDEV")
+    QEMU_CMD+=(-device "virtio-net-pci,netdev=net0")
+fi
+
+# Add a virtio tablet (absolute pointer) if the test asked for one
+# (needs-tablet marker file). It goes after the fixed devices so the mouse
+# and keyboard keep their PCI addresses.
+if [ -f "$BUILD_DIR/needs-tablet" ]; then
+    QEMU_CMD+=(-device virtio-tablet-pci)
+fi
+
+# Add a second display (needs-second-display marker file), which becomes
+# display:/pci/display/1.
+if [ -f "$BUILD_DIR/needs-second-display" ]; then
+    QEMU_CMD+=(-device virtio-gpu-pci)
+fi
+
+# Add a PCI device no kernel driver binds (needs-testdev marker file), for
+# tests that claim a device for themselves.
+if [ -f "$BUILD_DIR/needs-testdev" ]; then
+    QEMU_CMD+=(-device pci-testdev)
+fi
+
 # For screenshot tests, use monitor socket instead of isa-debug-exit
 if [ $SCREENSHOT_TEST -eq 1 ]; then
     MONITOR_SOCK="/tmp/qemu-test-$$.sock"
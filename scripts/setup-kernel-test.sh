@@ -28,3 +28,8 @@ echo 'fs0:\efi\boot\bootx64.efi' > "$BUILD_DIR/efi/boot/startup.nsh"
 if [ "$TEST_NAME" = "block" ]; then
     dd if=/dev/zero of="$BUILD_DIR/test-disk.img" bs=1M count=1 2>/dev/null
 fi
+
+# Add a PCI device no kernel driver binds, for the IOMMU test to claim
+if [ "$TEST_NAME" = "iommu" ]; then
+    touch "$BUILD_DIR/needs-testdev"
+fi
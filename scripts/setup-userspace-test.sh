@@ -52,6 +52,22 @@ if [ -f "$TEST_SRC_DIR/needs-block" ]; then
     dd if=/dev/zero of="$BUILD_DIR/test-disk.img" bs=1M count=1 2>/dev/null
 fi
 
+# Attach a network device (triggered by needs-net marker file, whose
+# contents, if any, are extra -netdev options such as guestfwd rules)
+if [ -f "$TEST_SRC_DIR/needs-net" ]; then
+    cp "$TEST_SRC_DIR/needs-net" "$BUILD_DIR/needs-net"
+fi
+
+# Attach an absolute pointer (triggered by needs-tablet marker file)
+if [ -f "$TEST_SRC_DIR/needs-tablet" ]; then
+    touch "$BUILD_DIR/needs-tablet"
+fi
+
+# Attach a second display (triggered by needs-second-display marker file)
+if [ -f "$TEST_SRC_DIR/needs-second-display" ]; then
+    touch "$BUILD_DIR/needs-second-display"
+fi
+
 # Create ext2 disk (triggered by needs-ext2 marker file)
 if [ -f "$TEST_SRC_DIR/needs-ext2" ]; then
     dd if=/dev/zero of="$BUILD_DIR/test-disk.img" bs=1M count=10 2>/dev/null
@@ -0,0 +1,239 @@
+//! The clipboard service.
+//!
+//! Holds one [`Value`] and serves it as the `clipboard:` scheme, through
+//! two files:
+//!
+//! - `clipboard:/value` reads the value's encoding (`Value::to_bytes`), and
+//!   takes a new one when written.
+//! - `clipboard:/text` reads the value as plain text, and takes UTF-8 text
+//!   (stored as a `Value::String`) when written.
+//!
+//! A handle reads the value as it was when the handle was opened. What is
+//! written replaces the value once the handle is closed, so a value larger
+//! than one transfer arrives whole; a handle that writes more than
+//! `libpanda::clipboard::MAX_SIZE` is refused and replaces nothing.
+//! `libpanda::clipboard` wraps both files.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use libpanda::clipboard::MAX_SIZE;
+use libpanda::environment;
+use libpanda::scheme::SchemeProvider;
+use panda_abi::scheme_protocol::{ReaddirEntry, Request};
+use panda_abi::value::Value;
+use panda_abi::{ErrorCode, MAX_MESSAGE_SIZE};
+
+/// The scheme the service registers.
+pub const SCHEME: &str = "clipboard";
+
+/// How many handles are kept open at once. The kernel's `Close` for a
+/// dropped handle is best-effort, so past this the oldest is taken to be
+/// closed already and forgotten.
+const MAX_OPEN: usize = 64;
+
+/// The two ways of reading and writing the value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Value,
+    Text,
+}
+
+impl Format {
+    fn from_path(path: &str) -> Option<Self> {
+        match path {
+            "/value" => Some(Self::Value),
+            "/text" => Some(Self::Text),
+            _ => None,
+        }
+    }
+}
+
+/// An open handle on one of the files.
+struct Open {
+    id: u64,
+    format: Format,
+    /// The value as it was at open, in the file's format.
+    contents: Vec<u8>,
+    /// How much of `contents` has been read.
+    read: usize,
+    /// What has been written, to replace the value at close.
+    written: Option<Vec<u8>>,
+    /// More than `MAX_SIZE` was written, so the value is left alone.
+    too_large: bool,
+}
+
+/// The text a value reads as from `clipboard:/text`: strings as they are,
+/// bytes as UTF-8, the contents of styled text and links, and one line per
+/// item of arrays, maps and tables.
+pub fn text_of(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Bool(b) => b.to_string(),
+        Value::Int(i) => i.to_string(),
+        Value::Float(f) => f.to_string(),
+        Value::String(s) => s.clone(),
+        Value::Bytes(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+        Value::Styled(_, inner) | Value::Link { inner, .. } => text_of(inner),
+        Value::Array(items) => lines(items.iter().map(text_of)),
+        Value::Map(map) => lines(
+            map.iter()
+                .map(|(key, value)| format!("{}: {}", key, text_of(value))),
+        ),
+        Value::Table(table) => lines(table.row_iter().map(|row| {
+            let cells: Vec<String> = row.iter().map(text_of).collect();
+            cells.join("\t")
+        })),
+    }
+}
+
+fn lines(items: impl Iterator<Item = String>) -> String {
+    items.collect::<Vec<_>>().join("\n")
+}
+
+pub struct ClipboardService {
+    provider: SchemeProvider,
+    value: Value,
+    open: Vec<Open>,
+    next_id: u64,
+}
+
+impl ClipboardService {
+    /// Register the `clipboard:` scheme, with nothing on the clipboard.
+    pub fn new() -> libpanda::error::Result<Self> {
+        let provider = SchemeProvider::register(SCHEME)?;
+        environment::log("clipboard: registered the clipboard: scheme");
+        Ok(Self {
+            provider,
+            value: Value::Null,
+            open: Vec::new(),
+            next_id: 1,
+        })
+    }
+
+    /// Serve forever.
+    pub fn run(&mut self) -> ! {
+        let mut buf = [0u8; MAX_MESSAGE_SIZE];
+        loop {
+            // Only a provider the kernel has dropped fails here, and it
+            // can't be registered again from this process.
+            if let Ok(request) = self.provider.recv(&mut buf) {
+                self.handle(request);
+            }
+        }
+    }
+
+    /// Serve any requests already waiting. Returns whether there were any.
+    pub fn poll(&mut self) -> bool {
+        let mut buf = [0u8; MAX_MESSAGE_SIZE];
+        let mut busy = false;
+        while let Ok(Some(request)) = self.provider.try_recv(&mut buf) {
+            self.handle(request);
+            busy = true;
+        }
+        busy
+    }
+
+    fn handle(&mut self, request: Request<'_>) {
+        let provider = &self.provider;
+        match request {
+            Request::Open { request_id, path } => {
+                let Some(format) = Format::from_path(path) else {
+                    let _ = provider.reply_open_err(request_id, ErrorCode::NotFound);
+                    return;
+                };
+                let contents = match format {
+                    Format::Value => self.value.to_bytes(),
+                    Format::Text => text_of(&self.value).into_bytes(),
+                };
+                let id = self.next_id;
+                self.next_id += 1;
+                if self.open.len() == MAX_OPEN {
+                    // Ids only grow, so the first is the oldest.
+                    self.open.remove(0);
+                }
+                self.open.push(Open {
+                    id,
+                    format,
+                    contents,
+                    read: 0,
+                    written: None,
+                    too_large: false,
+                });
+                let _ = provider.reply_open_ok(request_id, id);
+            }
+            Request::Readdir { request_id, path } => {
+                if path == "/" || path.is_empty() {
+                    let entries = ["value", "text"].map(|name| ReaddirEntry {
+                        name,
+                        is_dir: false,
+                    });
+                    let _ = provider.reply_readdir_ok(request_id, &entries);
+                } else {
+                    let _ = provider.reply_readdir_err(request_id, ErrorCode::NotFound);
+                }
+            }
+            Request::Read {
+                request_id,
+                resource_id,
+                len,
+            } => {
+                let Some(open) = self.open.iter_mut().find(|open| open.id == resource_id) else {
+                    let _ = provider.reply_read_err(request_id, ErrorCode::InvalidHandle);
+                    return;
+                };
+                let start = open.read;
+                let end = (start + len as usize).min(open.contents.len());
+                open.read = end;
+                let _ = provider.reply_read_ok(request_id, &open.contents[start..end]);
+            }
+            Request::Write {
+                request_id,
+                resource_id,
+                data,
+            } => {
+                let Some(open) = self.open.iter_mut().find(|open| open.id == resource_id) else {
+                    let _ = provider.reply_write_err(request_id, ErrorCode::InvalidHandle);
+                    return;
+                };
+                let written = open.written.get_or_insert_default();
+                if open.too_large || written.len() + data.len() > MAX_SIZE {
+                    // Nothing more is kept; the close changes nothing.
+                    open.too_large = true;
+                    open.written = None;
+                    let _ = provider.reply_write_err(request_id, ErrorCode::NoSpace);
+                    return;
+                }
+                written.extend_from_slice(data);
+                let _ = provider.reply_write_ok(request_id, data.len() as u32);
+            }
+            Request::Close {
+                request_id,
+                resource_id,
+            } => {
+                if let Some(index) = self.open.iter().position(|open| open.id == resource_id) {
+                    let open = self.open.remove(index);
+                    if let Some(written) = open.written {
+                        self.value = match open.format {
+                            // Anything that isn't a value is kept as bytes.
+                            Format::Value => {
+                                Value::from_bytes(&written).unwrap_or(Value::Bytes(written))
+                            }
+                            Format::Text => {
+                                Value::String(String::from_utf8_lossy(&written).into_owned())
+                            }
+                        };
+                    }
+                }
+                let _ = self.provider.reply_close_ok(request_id);
+            }
+            Request::Connect { request_id, .. } => {
+                let _ = provider.reply_connect_err(request_id, ErrorCode::NotSupported);
+            }
+        }
+    }
+}
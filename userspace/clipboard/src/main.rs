@@ -0,0 +1,16 @@
+#![no_std]
+#![no_main]
+
+use clipboard::ClipboardService;
+use libpanda::environment;
+
+libpanda::main! {
+    let mut service = match ClipboardService::new() {
+        Ok(service) => service,
+        Err(_) => {
+            environment::log("clipboard: could not register the clipboard: scheme");
+            return 1;
+        }
+    };
+    service.run()
+}
@@ -0,0 +1,241 @@
+//! Client buffer pixel formats and their conversion to BGRA.
+//!
+//! The compositor composites in BGRA8888, so a buffer in any other format
+//! is converted a row at a time as it is drawn. The conversions live here,
+//! next to the wire constants, so clients that write those formats agree
+//! with the compositor about what the bytes mean.
+//!
+//! Every format is tightly packed: rows are `width` pixels with no padding.
+//! [`FORMAT_NV12`] is planar — a `width` x `height` plane of luma followed
+//! by a half-resolution plane of interleaved Cb/Cr pairs — and so needs an
+//! even width and height. Its samples are BT.601 limited range.
+
+use crate::message::{FORMAT_BGRA8888, FORMAT_NV12, FORMAT_RGB565, FORMAT_XRGB8888};
+
+/// The shape of a client buffer: its format and size in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferLayout {
+    pub format: u8,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl BufferLayout {
+    /// A layout, or `None` if the format is unknown, the buffer is empty,
+    /// or an NV12 buffer has an odd side.
+    pub fn new(format: u8, width: u32, height: u32) -> Option<Self> {
+        let known = matches!(
+            format,
+            FORMAT_BGRA8888 | FORMAT_XRGB8888 | FORMAT_RGB565 | FORMAT_NV12
+        );
+        if !known || width == 0 || height == 0 {
+            return None;
+        }
+        if format == FORMAT_NV12 && (!width.is_multiple_of(2) || !height.is_multiple_of(2)) {
+            return None;
+        }
+        let layout = Self {
+            format,
+            width,
+            height,
+        };
+        layout.checked_size().map(|_| layout)
+    }
+
+    /// The number of bytes the buffer occupies.
+    pub fn size(&self) -> usize {
+        self.checked_size().unwrap_or(0)
+    }
+
+    fn checked_size(&self) -> Option<usize> {
+        let pixels = (self.width as usize).checked_mul(self.height as usize)?;
+        match self.format {
+            FORMAT_BGRA8888 | FORMAT_XRGB8888 => pixels.checked_mul(4),
+            FORMAT_RGB565 => pixels.checked_mul(2),
+            // A byte of luma per pixel, then a Cb/Cr pair per 2x2 block.
+            FORMAT_NV12 => pixels.checked_add(pixels / 2),
+            _ => None,
+        }
+    }
+
+    /// Whether every pixel is opaque whatever the buffer holds.
+    pub fn is_opaque(&self) -> bool {
+        self.format != FORMAT_BGRA8888
+    }
+
+    /// Convert the `out.len() / 4` pixels starting at `(x, y)` to BGRA.
+    ///
+    /// `data` must be at least [`size`](Self::size) bytes and the run must
+    /// lie within the buffer.
+    pub fn read_row(&self, data: &[u8], x: u32, y: u32, out: &mut [u8]) {
+        let start = y as usize * self.width as usize + x as usize;
+        match self.format {
+            FORMAT_BGRA8888 => out.copy_from_slice(&data[start * 4..start * 4 + out.len()]),
+            FORMAT_XRGB8888 => {
+                out.copy_from_slice(&data[start * 4..start * 4 + out.len()]);
+                for pixel in out.chunks_exact_mut(4) {
+                    pixel[3] = 255;
+                }
+            }
+            FORMAT_RGB565 => {
+                let src = &data[start * 2..];
+                for (pixel, bytes) in out.chunks_exact_mut(4).zip(src.chunks_exact(2)) {
+                    pixel
+                        .copy_from_slice(&rgb565_to_bgra(u16::from_le_bytes([bytes[0], bytes[1]])));
+                }
+            }
+            FORMAT_NV12 => {
+                let luma = &data[start..];
+                let chroma_row = self.chroma_offset(0, y);
+                for (i, pixel) in out.chunks_exact_mut(4).enumerate() {
+                    let uv = chroma_row + ((x as usize + i) & !1);
+                    pixel.copy_from_slice(&yuv_to_bgra(luma[i], data[uv], data[uv + 1]));
+                }
+            }
+            _ => out.fill(0),
+        }
+    }
+
+    /// Set the `count` pixels starting at `(x, y)` to the BGRA `colour`.
+    ///
+    /// NV12 shares a chroma sample between each 2x2 block, so this also
+    /// recolours the neighbours of the run that share its blocks.
+    pub fn fill_row(&self, data: &mut [u8], x: u32, y: u32, count: u32, colour: [u8; 4]) {
+        let start = y as usize * self.width as usize + x as usize;
+        let count = count as usize;
+        match self.format {
+            FORMAT_BGRA8888 | FORMAT_XRGB8888 => {
+                for pixel in data[start * 4..(start + count) * 4].chunks_exact_mut(4) {
+                    pixel.copy_from_slice(&colour);
+                }
+            }
+            FORMAT_RGB565 => {
+                let value = bgra_to_rgb565(colour).to_le_bytes();
+                for pixel in data[start * 2..(start + count) * 2].chunks_exact_mut(2) {
+                    pixel.copy_from_slice(&value);
+                }
+            }
+            FORMAT_NV12 => {
+                let (luma, cb, cr) = bgra_to_yuv(colour);
+                data[start..start + count].fill(luma);
+                let first = self.chroma_offset(x, y);
+                let last = self.chroma_offset(x + count as u32 - 1, y);
+                for pair in data[first..last + 2].chunks_exact_mut(2) {
+                    pair.copy_from_slice(&[cb, cr]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Where the Cb/Cr pair covering pixel `(x, y)` starts.
+    fn chroma_offset(&self, x: u32, y: u32) -> usize {
+        let width = self.width as usize;
+        width * self.height as usize + (y as usize / 2) * width + (x as usize & !1)
+    }
+}
+
+/// Expand a little-endian RGB565 pixel to opaque BGRA, replicating the top
+/// bits so that full-scale channels stay full-scale.
+pub fn rgb565_to_bgra(pixel: u16) -> [u8; 4] {
+    let r = ((pixel >> 11) & 0x1F) as u8;
+    let g = ((pixel >> 5) & 0x3F) as u8;
+    let b = (pixel & 0x1F) as u8;
+    [
+        (b << 3) | (b >> 2),
+        (g << 2) | (g >> 4),
+        (r << 3) | (r >> 2),
+        255,
+    ]
+}
+
+/// Truncate a BGRA pixel to RGB565, dropping its alpha.
+pub fn bgra_to_rgb565(pixel: [u8; 4]) -> u16 {
+    let [b, g, r, _] = pixel.map(u16::from);
+    ((r >> 3) << 11) | ((g >> 2) << 5) | (b >> 3)
+}
+
+/// Convert a BT.601 limited-range sample to opaque BGRA.
+pub fn yuv_to_bgra(y: u8, cb: u8, cr: u8) -> [u8; 4] {
+    let c = 298 * (y as i32 - 16);
+    let d = cb as i32 - 128;
+    let e = cr as i32 - 128;
+    let clamp = |value: i32| ((value + 128) >> 8).clamp(0, 255) as u8;
+    [
+        clamp(c + 516 * d),
+        clamp(c - 100 * d - 208 * e),
+        clamp(c + 409 * e),
+        255,
+    ]
+}
+
+/// Convert a BGRA pixel to a BT.601 limited-range `(y, cb, cr)` sample,
+/// dropping its alpha.
+pub fn bgra_to_yuv(pixel: [u8; 4]) -> (u8, u8, u8) {
+    let [b, g, r, _] = pixel.map(i32::from);
+    let y = ((66 * r + 129 * g + 25 * b + 128) >> 8) + 16;
+    let cb = ((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128;
+    let cr = ((112 * r - 94 * g - 18 * b + 128) >> 8) + 128;
+    (y as u8, cb as u8, cr as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [u8; 4] = [0, 0, 255, 255];
+
+    fn close(a: [u8; 4], b: [u8; 4]) -> bool {
+        a.iter().zip(b.iter()).all(|(a, b)| a.abs_diff(*b) <= 2)
+    }
+
+    #[test]
+    fn layouts_reject_unknown_formats_and_odd_nv12() {
+        assert!(BufferLayout::new(0, 4, 4).is_none());
+        assert!(BufferLayout::new(FORMAT_BGRA8888, 0, 4).is_none());
+        assert!(BufferLayout::new(FORMAT_NV12, 3, 4).is_none());
+        assert!(BufferLayout::new(FORMAT_BGRA8888, u32::MAX, u32::MAX).is_none());
+        assert_eq!(BufferLayout::new(FORMAT_RGB565, 3, 5).unwrap().size(), 30);
+        assert_eq!(BufferLayout::new(FORMAT_NV12, 4, 2).unwrap().size(), 12);
+    }
+
+    #[test]
+    fn xrgb_reads_as_opaque() {
+        let layout = BufferLayout::new(FORMAT_XRGB8888, 1, 1).unwrap();
+        let mut out = [0; 4];
+        layout.read_row(&[1, 2, 3, 0], 0, 0, &mut out);
+        assert_eq!(out, [1, 2, 3, 255]);
+    }
+
+    #[test]
+    fn rgb565_keeps_full_scale_channels() {
+        assert_eq!(rgb565_to_bgra(0xF800), RED);
+        assert_eq!(rgb565_to_bgra(0x07E0), [0, 255, 0, 255]);
+        assert_eq!(rgb565_to_bgra(0xFFFF), [255, 255, 255, 255]);
+        assert_eq!(bgra_to_rgb565(RED), 0xF800);
+    }
+
+    #[test]
+    fn yuv_black_white_and_red() {
+        assert_eq!(yuv_to_bgra(16, 128, 128), [0, 0, 0, 255]);
+        assert_eq!(yuv_to_bgra(235, 128, 128), [255, 255, 255, 255]);
+        assert_eq!(bgra_to_yuv([255, 255, 255, 255]), (235, 128, 128));
+        let (y, cb, cr) = bgra_to_yuv(RED);
+        assert!(close(yuv_to_bgra(y, cb, cr), RED));
+    }
+
+    #[test]
+    fn filled_rows_read_back() {
+        let green = [0, 255, 0, 255];
+        for format in [FORMAT_XRGB8888, FORMAT_RGB565, FORMAT_NV12] {
+            let layout = BufferLayout::new(format, 4, 4).unwrap();
+            let mut data = [0u8; 64];
+            layout.fill_row(&mut data, 0, 2, 4, green);
+            let mut out = [0u8; 16];
+            layout.read_row(&data, 0, 2, &mut out);
+            for pixel in out.chunks_exact(4) {
+                assert!(close(pixel.try_into().unwrap(), green), "format {format}");
+            }
+        }
+    }
+}
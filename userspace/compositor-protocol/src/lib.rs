@@ -5,14 +5,21 @@
 //! userspace service (plans/userspace-compositor.md).
 //!
 //! It also owns the one canonical [`alpha_blend`] implementation, shared by
-//! the compositor and by client-side drawing code.
+//! the compositor and by client-side drawing code, and the conversions
+//! from the other buffer formats to BGRA.
 
 #![no_std]
 
 mod blend;
+mod format;
 mod message;
 mod rect;
 
 pub use blend::{alpha_blend, is_region_opaque};
-pub use message::{Event, FORMAT_BGRA8888, MAX_FORMATS, MAX_FRAME_SIZE, Request};
-pub use rect::Rect;
+pub use format::{BufferLayout, bgra_to_rgb565, bgra_to_yuv, rgb565_to_bgra, yuv_to_bgra};
+pub use message::{
+    CAPTURE_BAD_BUFFER, CAPTURE_DENIED, CAPTURE_NO_DISPLAY, CAPTURE_NO_SUCH_WINDOW, CAPTURE_SCREEN,
+    Event, FORMAT_BGRA8888, FORMAT_NV12, FORMAT_RGB565, FORMAT_XRGB8888, MAX_CURSOR_SIZE,
+    MAX_FORMATS, MAX_FRAME_SIZE, MAX_TITLE_LEN, Request,
+};
+pub use rect::{Rect, master_stack};
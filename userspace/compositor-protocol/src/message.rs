@@ -4,7 +4,9 @@
 //! `panda_abi::scheme_protocol`: a one-byte tag followed by fixed-width
 //! fields. Every frame fits in a single channel message — pixels never
 //! travel over the channel, only buffer handles attached to
-//! [`Request::AttachBuffer`].
+//! [`Request::AttachBuffer`], [`Request::SetCursor`] and
+//! [`Request::Capture`], and the channel attached to
+//! [`Event::CaptureShared`].
 //!
 //! Requests are not correlated by id: a client's requests are processed in
 //! the order it sent them, so the reply to the *n*th `CreateWindow` is the
@@ -12,9 +14,17 @@
 
 use crate::Rect;
 
-/// BGRA byte order, 8 bits per channel (little-endian ARGB8888) — the only
-/// format the compositor accepts today.
+/// BGRA byte order, 8 bits per channel (little-endian ARGB8888) — the
+/// format the compositor composites in.
 pub const FORMAT_BGRA8888: u8 = 1;
+/// As [`FORMAT_BGRA8888`], but the alpha byte is ignored and every pixel is
+/// opaque, so the compositor never blends it.
+pub const FORMAT_XRGB8888: u8 = 2;
+/// 16-bit little-endian pixels: 5 bits of red, 6 of green, 5 of blue.
+pub const FORMAT_RGB565: u8 = 3;
+/// Planar YUV 4:2:0: a plane of luma followed by interleaved Cb/Cr at half
+/// resolution (see [`BufferLayout`](crate::BufferLayout)).
+pub const FORMAT_NV12: u8 = 4;
 
 const TAG_CREATE_WINDOW: u8 = 1;
 const TAG_ATTACH_BUFFER: u8 = 2;
@@ -24,21 +34,68 @@ const TAG_FILL: u8 = 5;
 const TAG_SET_VISIBLE: u8 = 6;
 const TAG_MOVE: u8 = 7;
 const TAG_DESTROY_WINDOW: u8 = 8;
+const TAG_RAISE: u8 = 9;
+const TAG_LOWER: u8 = 10;
+const TAG_SET_ALWAYS_ON_TOP: u8 = 11;
+const TAG_SET_TITLE: u8 = 12;
+const TAG_SET_DECORATED: u8 = 13;
+const TAG_RESIZE: u8 = 14;
+const TAG_ACK_CONFIGURE: u8 = 15;
+const TAG_SET_CURSOR: u8 = 16;
+const TAG_RESET_CURSOR: u8 = 17;
+const TAG_CAPTURE: u8 = 18;
+const TAG_STOP_CAPTURE: u8 = 19;
+const TAG_SHARE_CAPTURE: u8 = 20;
 
 const TAG_DISPLAY_FORMATS: u8 = 1;
 const TAG_WINDOW_CREATED: u8 = 2;
 const TAG_FRAME_DONE: u8 = 3;
 const TAG_BUFFER_RELEASED: u8 = 4;
 const TAG_CLOSED: u8 = 5;
+const TAG_KEY: u8 = 6;
+const TAG_POINTER_MOTION: u8 = 7;
+const TAG_POINTER_BUTTON: u8 = 8;
+const TAG_POINTER_WHEEL: u8 = 9;
+const TAG_FOCUS_IN: u8 = 10;
+const TAG_FOCUS_OUT: u8 = 11;
+const TAG_CONFIGURE: u8 = 12;
+const TAG_CAPTURED: u8 = 13;
+const TAG_CAPTURE_FAILED: u8 = 14;
+const TAG_CAPTURE_SHARED: u8 = 15;
 
 /// Upper bound on the format list in a `DisplayFormats` greeting.
 pub const MAX_FORMATS: usize = 16;
 
+/// Upper bound, in bytes of UTF-8, on a window title.
+pub const MAX_TITLE_LEN: usize = 128;
+
+/// Upper bound, in pixels, on each side of a cursor image.
+pub const MAX_CURSOR_SIZE: u32 = 64;
+
+/// The `window` of a [`Request::Capture`] that captures the whole screen.
+pub const CAPTURE_SCREEN: u64 = 0;
+
+/// Why a capture failed, as reported by [`Event::CaptureFailed`]: the
+/// connection may only capture its own windows.
+pub const CAPTURE_DENIED: u8 = 1;
+/// The window to capture does not exist (or has gone away).
+pub const CAPTURE_NO_SUCH_WINDOW: u8 = 2;
+/// The buffer was missing or could not be mapped.
+pub const CAPTURE_BAD_BUFFER: u8 = 3;
+/// There is no screen to capture: the compositor is running headless.
+pub const CAPTURE_NO_DISPLAY: u8 = 4;
+
 /// `Fill` — tag, window, rect, colour — is the longest fixed-size frame.
 const LONGEST_FIXED_FRAME: usize = 1 + 8 + 16 + 4;
-/// `DisplayFormats` is the only variable-length frame, bounded by
-/// [`MAX_FORMATS`].
-const LONGEST_VARIABLE_FRAME: usize = 1 + 4 + 4 + 1 + MAX_FORMATS;
+/// `DisplayFormats` and `SetTitle` are the variable-length frames, bounded
+/// by [`MAX_FORMATS`] and [`MAX_TITLE_LEN`].
+const LONGEST_FORMATS_FRAME: usize = 1 + 4 + 4 + 1 + MAX_FORMATS;
+const LONGEST_TITLE_FRAME: usize = 1 + 8 + 1 + MAX_TITLE_LEN;
+const LONGEST_VARIABLE_FRAME: usize = if LONGEST_FORMATS_FRAME > LONGEST_TITLE_FRAME {
+    LONGEST_FORMATS_FRAME
+} else {
+    LONGEST_TITLE_FRAME
+};
 
 /// The largest frame any message in this protocol encodes to, so callers can
 /// size a buffer without guessing.
@@ -54,6 +111,12 @@ fn u32_at(buf: &[u8], offset: usize) -> Option<u32> {
     ))
 }
 
+fn i32_at(buf: &[u8], offset: usize) -> Option<i32> {
+    Some(i32::from_le_bytes(
+        buf.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
 fn u64_at(buf: &[u8], offset: usize) -> Option<u64> {
     Some(u64::from_le_bytes(
         buf.get(offset..offset + 8)?.try_into().ok()?,
@@ -78,12 +141,12 @@ fn rect_at(buf: &[u8], offset: usize) -> Option<Rect> {
 
 /// A message sent by a client to the compositor.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum Request {
+pub enum Request<'a> {
     /// Allocate a window. Answered with [`Event::WindowCreated`].
     CreateWindow,
     /// Attach the shared buffer carried as this message's handle attachment
-    /// to `window`. Resizing a window is an `AttachBuffer` with new
-    /// dimensions. The compositor names each attachment by a per-window
+    /// to `window`. A buffer of a new size resizes the window when it is
+    /// committed. The compositor names each attachment by a per-window
     /// sequence number, starting at 0, which both sides can count and which
     /// [`Event::BufferReleased`] reports back.
     AttachBuffer {
@@ -108,11 +171,73 @@ pub enum Request {
     Move { window: u64, x: u32, y: u32 },
     /// Destroy a window and release its buffers.
     DestroyWindow { window: u64 },
+    /// Put a window on top of the others in its layer.
+    Raise { window: u64 },
+    /// Put a window beneath the others in its layer.
+    Lower { window: u64 },
+    /// Move a window into (or out of) the always-on-top layer, which is
+    /// stacked above every ordinary window.
+    SetAlwaysOnTop { window: u64, on_top: bool },
+    /// Set the title shown in the window's decorations, at most
+    /// [`MAX_TITLE_LEN`] bytes.
+    SetTitle { window: u64, title: &'a str },
+    /// Ask the compositor to draw a title bar, with a close button, above
+    /// the window's buffer. `Move` then places the title bar's top-left
+    /// corner, and pointer events over the title bar go to the compositor
+    /// rather than the client.
+    SetDecorated { window: u64, decorated: bool },
+    /// Ask for the window to be resized. Answered with
+    /// [`Event::Configure`], possibly for a different size than asked for.
+    Resize {
+        window: u64,
+        width: u32,
+        height: u32,
+    },
+    /// The client has seen the [`Event::Configure`] with this serial, and
+    /// its next commit carries a buffer of that size.
+    AckConfigure { window: u64, serial: u32 },
+    /// Show the image in the shared buffer carried as this message's
+    /// handle attachment as the pointer while it is over the window.
+    /// The image is `width` x `height` pixels of [`FORMAT_BGRA8888`], each
+    /// side at most [`MAX_CURSOR_SIZE`], and `(hot_x, hot_y)` is the pixel
+    /// that points. The compositor copies the image, so the buffer may be
+    /// reused or freed straight away. A 0 x 0 image, with no attachment,
+    /// hides the pointer over the window.
+    SetCursor {
+        window: u64,
+        width: u32,
+        height: u32,
+        hot_x: u32,
+        hot_y: u32,
+    },
+    /// Go back to the compositor's own pointer over the window.
+    ResetCursor { window: u64 },
+    /// Copy the composited screen (for [`CAPTURE_SCREEN`]) or a window's
+    /// latched buffer into the shared buffer carried as this message's
+    /// handle attachment, `width` x `height` pixels of [`FORMAT_BGRA8888`].
+    /// Answered with [`Event::Captured`] once the copy is made, after the
+    /// next frame, or [`Event::CaptureFailed`]. A `continuous` capture
+    /// copies again on every frame that changes what it shows, until
+    /// [`Request::StopCapture`]. A client has at most one capture; a new
+    /// one replaces it.
+    Capture {
+        window: u64,
+        width: u32,
+        height: u32,
+        continuous: bool,
+    },
+    /// End the client's capture, if it has one.
+    StopCapture,
+    /// Ask for a new connection that may capture the screen and other
+    /// clients' windows, to hand to another process. Answered with
+    /// [`Event::CaptureShared`], or `CaptureFailed` with [`CAPTURE_DENIED`]
+    /// if this connection may not capture them itself.
+    ShareCapture,
 }
 
-impl Request {
+impl<'a> Request<'a> {
     /// Encode into `buf`, returning the frame length, or `None` if `buf` is
-    /// too small.
+    /// too small (or the title exceeds [`MAX_TITLE_LEN`]).
     pub fn encode(self, buf: &mut [u8]) -> Option<usize> {
         match self {
             Request::CreateWindow => {
@@ -200,12 +325,122 @@ impl Request {
                 buf[1..9].copy_from_slice(&window.to_le_bytes());
                 Some(total)
             }
+            Request::Raise { window } => Self::encode_flag(buf, TAG_RAISE, window, None),
+            Request::Lower { window } => Self::encode_flag(buf, TAG_LOWER, window, None),
+            Request::SetAlwaysOnTop { window, on_top } => {
+                Self::encode_flag(buf, TAG_SET_ALWAYS_ON_TOP, window, Some(on_top))
+            }
+            Request::SetTitle { window, title } => {
+                if title.len() > MAX_TITLE_LEN {
+                    return None;
+                }
+                let total = 1 + 8 + 1 + title.len();
+                if buf.len() < total {
+                    return None;
+                }
+                buf[0] = TAG_SET_TITLE;
+                buf[1..9].copy_from_slice(&window.to_le_bytes());
+                buf[9] = title.len() as u8;
+                buf[10..total].copy_from_slice(title.as_bytes());
+                Some(total)
+            }
+            Request::SetDecorated { window, decorated } => {
+                Self::encode_flag(buf, TAG_SET_DECORATED, window, Some(decorated))
+            }
+            Request::Resize {
+                window,
+                width,
+                height,
+            } => {
+                let total = 1 + 8 + 4 + 4;
+                if buf.len() < total {
+                    return None;
+                }
+                buf[0] = TAG_RESIZE;
+                buf[1..9].copy_from_slice(&window.to_le_bytes());
+                buf[9..13].copy_from_slice(&width.to_le_bytes());
+                buf[13..17].copy_from_slice(&height.to_le_bytes());
+                Some(total)
+            }
+            Request::AckConfigure { window, serial } => {
+                let total = 1 + 8 + 4;
+                if buf.len() < total {
+                    return None;
+                }
+                buf[0] = TAG_ACK_CONFIGURE;
+                buf[1..9].copy_from_slice(&window.to_le_bytes());
+                buf[9..13].copy_from_slice(&serial.to_le_bytes());
+                Some(total)
+            }
+            Request::SetCursor {
+                window,
+                width,
+                height,
+                hot_x,
+                hot_y,
+            } => {
+                let total = 1 + 8 + 4 + 4 + 4 + 4;
+                if buf.len() < total {
+                    return None;
+                }
+                buf[0] = TAG_SET_CURSOR;
+                buf[1..9].copy_from_slice(&window.to_le_bytes());
+                buf[9..13].copy_from_slice(&width.to_le_bytes());
+                buf[13..17].copy_from_slice(&height.to_le_bytes());
+                buf[17..21].copy_from_slice(&hot_x.to_le_bytes());
+                buf[21..25].copy_from_slice(&hot_y.to_le_bytes());
+                Some(total)
+            }
+            Request::ResetCursor { window } => {
+                Self::encode_flag(buf, TAG_RESET_CURSOR, window, None)
+            }
+            Request::Capture {
+                window,
+                width,
+                height,
+                continuous,
+            } => {
+                let total = 1 + 8 + 4 + 4 + 1;
+                if buf.len() < total {
+                    return None;
+                }
+                buf[0] = TAG_CAPTURE;
+                buf[1..9].copy_from_slice(&window.to_le_bytes());
+                buf[9..13].copy_from_slice(&width.to_le_bytes());
+                buf[13..17].copy_from_slice(&height.to_le_bytes());
+                buf[17] = continuous as u8;
+                Some(total)
+            }
+            Request::StopCapture => {
+                *buf.first_mut()? = TAG_STOP_CAPTURE;
+                Some(1)
+            }
+            Request::ShareCapture => {
+                *buf.first_mut()? = TAG_SHARE_CAPTURE;
+                Some(1)
+            }
         }
     }
 
-    /// Decode a frame. Returns `None` on a truncated or unknown frame — a
-    /// client is untrusted input, so nothing is guessed.
-    pub fn decode(buf: &[u8]) -> Option<Request> {
+    /// Requests that name a window and at most one flag share a layout:
+    /// tag, window, then the flag byte if there is one.
+    fn encode_flag(buf: &mut [u8], tag: u8, window: u64, flag: Option<bool>) -> Option<usize> {
+        let total = 1 + 8 + flag.is_some() as usize;
+        if buf.len() < total {
+            return None;
+        }
+        buf[0] = tag;
+        buf[1..9].copy_from_slice(&window.to_le_bytes());
+        if let Some(flag) = flag {
+            buf[9] = flag as u8;
+        }
+        Some(total)
+    }
+
+    /// Decode a frame, borrowing from `buf`. Returns `None` on a truncated
+    /// or unknown frame, or a title that is not UTF-8 — a client is
+    /// untrusted input, so nothing is guessed.
+    pub fn decode(buf: &'a [u8]) -> Option<Request<'a>> {
         match *buf.first()? {
             TAG_CREATE_WINDOW => Some(Request::CreateWindow),
             TAG_ATTACH_BUFFER => Some(Request::AttachBuffer {
@@ -238,6 +473,57 @@ impl Request {
             TAG_DESTROY_WINDOW => Some(Request::DestroyWindow {
                 window: u64_at(buf, 1)?,
             }),
+            TAG_RAISE => Some(Request::Raise {
+                window: u64_at(buf, 1)?,
+            }),
+            TAG_LOWER => Some(Request::Lower {
+                window: u64_at(buf, 1)?,
+            }),
+            TAG_SET_ALWAYS_ON_TOP => Some(Request::SetAlwaysOnTop {
+                window: u64_at(buf, 1)?,
+                on_top: *buf.get(9)? != 0,
+            }),
+            TAG_SET_TITLE => {
+                let len = *buf.get(9)? as usize;
+                if len > MAX_TITLE_LEN {
+                    return None;
+                }
+                Some(Request::SetTitle {
+                    window: u64_at(buf, 1)?,
+                    title: core::str::from_utf8(buf.get(10..10 + len)?).ok()?,
+                })
+            }
+            TAG_SET_DECORATED => Some(Request::SetDecorated {
+                window: u64_at(buf, 1)?,
+                decorated: *buf.get(9)? != 0,
+            }),
+            TAG_RESIZE => Some(Request::Resize {
+                window: u64_at(buf, 1)?,
+                width: u32_at(buf, 9)?,
+                height: u32_at(buf, 13)?,
+            }),
+            TAG_ACK_CONFIGURE => Some(Request::AckConfigure {
+                window: u64_at(buf, 1)?,
+                serial: u32_at(buf, 9)?,
+            }),
+            TAG_SET_CURSOR => Some(Request::SetCursor {
+                window: u64_at(buf, 1)?,
+                width: u32_at(buf, 9)?,
+                height: u32_at(buf, 13)?,
+                hot_x: u32_at(buf, 17)?,
+                hot_y: u32_at(buf, 21)?,
+            }),
+            TAG_RESET_CURSOR => Some(Request::ResetCursor {
+                window: u64_at(buf, 1)?,
+            }),
+            TAG_CAPTURE => Some(Request::Capture {
+                window: u64_at(buf, 1)?,
+                width: u32_at(buf, 9)?,
+                height: u32_at(buf, 13)?,
+                continuous: *buf.get(17)? != 0,
+            }),
+            TAG_STOP_CAPTURE => Some(Request::StopCapture),
+            TAG_SHARE_CAPTURE => Some(Request::ShareCapture),
             _ => None,
         }
     }
@@ -246,8 +532,9 @@ impl Request {
 /// A message sent by the compositor to a client.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Event<'a> {
-    /// Sent once when a client connects: the screen geometry it will be
-    /// composited onto and the pixel formats its buffers may use.
+    /// Sent when a client connects, and again whenever the screen changes
+    /// size: the screen geometry it will be composited onto and the pixel
+    /// formats its buffers may use.
     DisplayFormats {
         width: u32,
         height: u32,
@@ -262,6 +549,64 @@ pub enum Event<'a> {
     BufferReleased { window: u64, buffer: u64 },
     /// The window is gone (destroyed by the client, or by the compositor).
     Closed { window: u64 },
+    /// A key went down, came up or auto-repeated while `window` had the
+    /// keyboard focus. `code` is an evdev key code and `value` is 0 for a
+    /// release, 1 for a press and 2 for a repeat, exactly as a `keyboard:`
+    /// device reports them; turning them into text is up to the client.
+    Key { window: u64, code: u16, value: u32 },
+    /// The pointer moved to `(x, y)`, relative to the window's top-left
+    /// corner. Sent to the window under the pointer, or to the window
+    /// holding the pointer grab while a button is down — which is why the
+    /// position can fall outside the window.
+    PointerMotion { window: u64, x: i32, y: i32 },
+    /// A pointer button (an evdev `BTN_*` code) was pressed or released
+    /// with the pointer at window-relative `(x, y)`.
+    PointerButton {
+        window: u64,
+        x: i32,
+        y: i32,
+        button: u16,
+        pressed: bool,
+    },
+    /// The wheel turned `delta` clicks (positive away from the user) with
+    /// the pointer at window-relative `(x, y)`.
+    PointerWheel {
+        window: u64,
+        x: i32,
+        y: i32,
+        delta: i32,
+    },
+    /// The window now has the keyboard focus. Keys held down when focus
+    /// arrived are not replayed.
+    FocusIn { window: u64 },
+    /// The window has lost the keyboard focus. The client should forget
+    /// any keys it thinks are held: their releases go to the new focus.
+    FocusOut { window: u64 },
+    /// The compositor wants the window's buffer to be `width` x `height`,
+    /// because the client asked with [`Request::Resize`] or the user is
+    /// resizing it. The client draws a buffer of that size, sends
+    /// [`Request::AckConfigure`] with the same serial, then attaches and
+    /// commits it. Until then the old buffer stays on screen. No further
+    /// `Configure` is sent for the window before the ack; the compositor
+    /// keeps only the latest size it wants in the meantime.
+    Configure {
+        window: u64,
+        serial: u32,
+        width: u32,
+        height: u32,
+    },
+    /// The client's capture buffer now holds frame `frame`: `width` x
+    /// `height` pixels from its top-left corner, with the buffer's own
+    /// width as the stride. The image is cut to the buffer if the source
+    /// is larger. The pointer is never part of a capture.
+    Captured { frame: u64, width: u32, height: u32 },
+    /// The client's capture failed, or ended because its window went away;
+    /// `reason` is one of the `CAPTURE_*` codes.
+    CaptureFailed { reason: u8 },
+    /// The connection asked for by [`Request::ShareCapture`]: its channel
+    /// is this message's handle attachment, and starts with its own
+    /// `DisplayFormats`.
+    CaptureShared,
 }
 
 impl<'a> Event<'a> {
@@ -296,9 +641,135 @@ impl<'a> Event<'a> {
                 Self::encode_two(buf, TAG_BUFFER_RELEASED, window, buffer)
             }
             Event::Closed { window } => Self::encode_one(buf, TAG_CLOSED, window),
+            Event::Key {
+                window,
+                code,
+                value,
+            } => {
+                let total = 1 + 8 + 2 + 4;
+                if buf.len() < total {
+                    return None;
+                }
+                buf[0] = TAG_KEY;
+                buf[1..9].copy_from_slice(&window.to_le_bytes());
+                buf[9..11].copy_from_slice(&code.to_le_bytes());
+                buf[11..15].copy_from_slice(&value.to_le_bytes());
+                Some(total)
+            }
+            Event::PointerMotion { window, x, y } => {
+                Self::encode_pointer(buf, TAG_POINTER_MOTION, window, x, y, &[])
+            }
+            Event::PointerButton {
+                window,
+                x,
+                y,
+                button,
+                pressed,
+            } => {
+                let [low, high] = button.to_le_bytes();
+                let extra = [low, high, pressed as u8];
+                Self::encode_pointer(buf, TAG_POINTER_BUTTON, window, x, y, &extra)
+            }
+            Event::PointerWheel {
+                window,
+                x,
+                y,
+                delta,
+            } => Self::encode_pointer(buf, TAG_POINTER_WHEEL, window, x, y, &delta.to_le_bytes()),
+            Event::FocusIn { window } => Self::encode_one(buf, TAG_FOCUS_IN, window),
+            Event::FocusOut { window } => Self::encode_one(buf, TAG_FOCUS_OUT, window),
+            Event::Configure {
+                window,
+                serial,
+                width,
+                height,
+            } => {
+                let total = 1 + 8 + 4 + 4 + 4;
+                if buf.len() < total {
+                    return None;
+                }
+                buf[0] = TAG_CONFIGURE;
+                buf[1..9].copy_from_slice(&window.to_le_bytes());
+                buf[9..13].copy_from_slice(&serial.to_le_bytes());
+                buf[13..17].copy_from_slice(&width.to_le_bytes());
+                buf[17..21].copy_from_slice(&height.to_le_bytes());
+                Some(total)
+            }
+            Event::Captured {
+                frame,
+                width,
+                height,
+            } => {
+                let total = 1 + 8 + 4 + 4;
+                if buf.len() < total {
+                    return None;
+                }
+                buf[0] = TAG_CAPTURED;
+                buf[1..9].copy_from_slice(&frame.to_le_bytes());
+                buf[9..13].copy_from_slice(&width.to_le_bytes());
+                buf[13..17].copy_from_slice(&height.to_le_bytes());
+                Some(total)
+            }
+            Event::CaptureFailed { reason } => {
+                let total = 1 + 1;
+                if buf.len() < total {
+                    return None;
+                }
+                buf[0] = TAG_CAPTURE_FAILED;
+                buf[1] = reason;
+                Some(total)
+            }
+            Event::CaptureShared => {
+                *buf.first_mut()? = TAG_CAPTURE_SHARED;
+                Some(1)
+            }
+        }
+    }
+
+    /// The window this event is about, for every event but the
+    /// connection-wide `DisplayFormats` and the capture events.
+    pub fn window(&self) -> Option<u64> {
+        match *self {
+            Event::DisplayFormats { .. }
+            | Event::Captured { .. }
+            | Event::CaptureFailed { .. }
+            | Event::CaptureShared => None,
+            Event::WindowCreated { window }
+            | Event::FrameDone { window, .. }
+            | Event::BufferReleased { window, .. }
+            | Event::Closed { window }
+            | Event::Key { window, .. }
+            | Event::PointerMotion { window, .. }
+            | Event::PointerButton { window, .. }
+            | Event::PointerWheel { window, .. }
+            | Event::FocusIn { window }
+            | Event::FocusOut { window }
+            | Event::Configure { window, .. } => Some(window),
         }
     }
 
+    /// Pointer events share a layout: tag, window, x, y, then the
+    /// event-specific fields in `extra`.
+    fn encode_pointer(
+        buf: &mut [u8],
+        tag: u8,
+        window: u64,
+        x: i32,
+        y: i32,
+        extra: &[u8],
+    ) -> Option<usize> {
+        let total = 1 + 8 + 4 + 4 + extra.len();
+        if buf.len() < total {
+            return None;
+        }
+        buf[0] = tag;
+        buf[1..9].copy_from_slice(&window.to_le_bytes());
+        buf[9..13].copy_from_slice(&x.to_le_bytes());
+        buf[13..17].copy_from_slice(&y.to_le_bytes());
+        buf[17..total].copy_from_slice(extra);
+        Some(total)
+    }
+
     fn encode_one(buf: &mut [u8], tag: u8, value: u64) -> Option<usize> {
         let total = 1 + 8;
         if buf.len() < total {
@@ -346,6 +817,50 @@ impl<'a> Event<'a> {
             TAG_CLOSED => Some(Event::Closed {
                 window: u64_at(buf, 1)?,
             }),
+            TAG_KEY => Some(Event::Key {
+                window: u64_at(buf, 1)?,
+                code: u16::from_le_bytes(buf.get(9..11)?.try_into().ok()?),
+                value: u32_at(buf, 11)?,
+            }),
+            TAG_POINTER_MOTION => Some(Event::PointerMotion {
+                window: u64_at(buf, 1)?,
+                x: i32_at(buf, 9)?,
+                y: i32_at(buf, 13)?,
+            }),
+            TAG_POINTER_BUTTON => Some(Event::PointerButton {
+                window: u64_at(buf, 1)?,
+                x: i32_at(buf, 9)?,
+                y: i32_at(buf, 13)?,
+                button: u16::from_le_bytes(buf.get(17..19)?.try_into().ok()?),
+                pressed: *buf.get(19)? != 0,
+            }),
+            TAG_POINTER_WHEEL => Some(Event::PointerWheel {
+                window: u64_at(buf, 1)?,
+                x: i32_at(buf, 9)?,
+                y: i32_at(buf, 13)?,
+                delta: i32_at(buf, 17)?,
+            }),
+            TAG_FOCUS_IN => Some(Event::FocusIn {
+                window: u64_at(buf, 1)?,
+            }),
+            TAG_FOCUS_OUT => Some(Event::FocusOut {
+                window: u64_at(buf, 1)?,
+            }),
+            TAG_CONFIGURE => Some(Event::Configure {
+                window: u64_at(buf, 1)?,
+                serial: u32_at(buf, 9)?,
+                width: u32_at(buf, 13)?,
+                height: u32_at(buf, 17)?,
+            }),
+            TAG_CAPTURED => Some(Event::Captured {
+                frame: u64_at(buf, 1)?,
+                width: u32_at(buf, 9)?,
+                height: u32_at(buf, 13)?,
+            }),
+            TAG_CAPTURE_FAILED => Some(Event::CaptureFailed {
+                reason: *buf.get(1)?,
+            }),
+            TAG_CAPTURE_SHARED => Some(Event::CaptureShared),
             _ => None,
         }
     }
@@ -401,6 +916,72 @@ mod tests {
             y: 200,
         });
         round_trip_request(Request::DestroyWindow { window: 3 });
+        round_trip_request(Request::Raise { window: 3 });
+        round_trip_request(Request::Lower { window: 3 });
+        round_trip_request(Request::SetAlwaysOnTop {
+            window: 3,
+            on_top: true,
+        });
+        round_trip_request(Request::SetTitle {
+            window: 3,
+            title: "panda — terminal",
+        });
+        round_trip_request(Request::SetTitle {
+            window: 3,
+            title: "",
+        });
+        round_trip_request(Request::SetDecorated {
+            window: 3,
+            decorated: true,
+        });
+        round_trip_request(Request::Resize {
+            window: 3,
+            width: 1024,
+            height: 768,
+        });
+        round_trip_request(Request::AckConfigure {
+            window: 3,
+            serial: 7,
+        });
+        round_trip_request(Request::SetCursor {
+            window: 3,
+            width: 16,
+            height: 24,
+            hot_x: 1,
+            hot_y: 2,
+        });
+        round_trip_request(Request::ResetCursor { window: 3 });
+        round_trip_request(Request::Capture {
+            window: CAPTURE_SCREEN,
+            width: 1024,
+            height: 768,
+            continuous: true,
+        });
+        round_trip_request(Request::StopCapture);
+        round_trip_request(Request::ShareCapture);
+    }
+
+    #[test]
+    fn titles_are_bounded_and_must_be_utf8() {
+        let mut buf = [0u8; MAX_FRAME_SIZE];
+        let long = [b'x'; MAX_TITLE_LEN + 1];
+        assert_eq!(
+            Request::SetTitle {
+                window: 1,
+                title: core::str::from_utf8(&long).unwrap(),
+            }
+            .encode(&mut buf),
+            None
+        );
+
+        let len = Request::SetTitle {
+            window: 1,
+            title: "ok",
+        }
+        .encode(&mut buf)
+        .expect("encode");
+        buf[10] = 0xFF;
+        assert_eq!(Request::decode(&buf[..len]), None);
     }
 
     #[test]
@@ -423,12 +1004,75 @@ mod tests {
                 buffer: 1,
             },
             Event::Closed { window: 5 },
+            Event::Key {
+                window: 5,
+                code: 30,
+                value: 2,
+            },
+            Event::PointerMotion {
+                window: 5,
+                x: -3,
+                y: 400,
+            },
+            Event::PointerButton {
+                window: 5,
+                x: 10,
+                y: 20,
+                button: 0x110,
+                pressed: true,
+            },
+            Event::PointerWheel {
+                window: 5,
+                x: 10,
+                y: 20,
+                delta: -1,
+            },
+            Event::FocusIn { window: 5 },
+            Event::FocusOut { window: 5 },
+            Event::Configure {
+                window: 5,
+                serial: 7,
+                width: 1024,
+                height: 768,
+            },
+            Event::Captured {
+                frame: 42,
+                width: 1024,
+                height: 768,
+            },
+            Event::CaptureFailed {
+                reason: CAPTURE_DENIED,
+            },
+            Event::CaptureShared,
         ] {
             let len = event.encode(&mut buf).expect("encode");
             assert_eq!(Event::decode(&buf[..len]), Some(event));
         }
     }
 
+    #[test]
+    fn input_events_name_their_window() {
+        assert_eq!(
+            Event::Key {
+                window: 9,
+                code: 1,
+                value: 1,
+            }
+            .window(),
+            Some(9)
+        );
+        assert_eq!(Event::FocusOut { window: 4 }.window(), Some(4));
+        assert_eq!(
+            Event::DisplayFormats {
+                width: 1,
+                height: 1,
+                formats: &[],
+            }
+            .window(),
+            None
+        );
+    }
+
     #[test]
     fn truncated_frames_are_rejected() {
         let mut buf = [0u8; MAX_FRAME_SIZE];
@@ -443,14 +1087,23 @@ mod tests {
             assert_eq!(Request::decode(&buf[..short]), None, "len {short}");
         }
 
-        let len = Event::FrameDone {
-            window: 1,
-            frame: 1,
-        }
-        .encode(&mut buf)
-        .expect("encode");
-        for short in 0..len {
-            assert_eq!(Event::decode(&buf[..short]), None, "len {short}");
+        for event in [
+            Event::FrameDone {
+                window: 1,
+                frame: 1,
+            },
+            Event::PointerButton {
+                window: 1,
+                x: 1,
+                y: 1,
+                button: 0x110,
+                pressed: false,
+            },
+        ] {
+            let len = event.encode(&mut buf).expect("encode");
+            for short in 0..len {
+                assert_eq!(Event::decode(&buf[..short]), None, "len {short}");
+            }
         }
     }
 
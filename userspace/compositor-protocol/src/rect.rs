@@ -3,6 +3,10 @@
 //! Ported from the in-kernel `resource::Rect` (deleted in Phase 5 of
 //! plans/userspace-compositor.md) so that damage tracking is expressed in
 //! userspace types on both sides of the wire.
+//!
+//! The compositor's layout policies are here too, as pure functions from
+//! an area and a window count to rectangles, so they can be tested without
+//! a compositor.
 
 /// An axis-aligned rectangle in pixels.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -77,3 +81,149 @@ impl Rect {
         self.width == 0 || self.height == 0
     }
 }
+
+impl Rect {
+    /// Split into a left part `width` pixels wide (at most the whole) and
+    /// the rest.
+    pub fn split_left(&self, width: u32) -> (Rect, Rect) {
+        let width = width.min(self.width);
+        let left = Rect { width, ..*self };
+        let right = Rect {
+            x: self.x + width,
+            width: self.width - width,
+            ..*self
+        };
+        (left, right)
+    }
+
+    /// The `index`th of `count` bands of equal height, top to bottom. The
+    /// bottom band takes the pixels that don't divide evenly.
+    pub fn band(&self, index: u32, count: u32) -> Rect {
+        let count = count.max(1);
+        let index = index.min(count - 1);
+        let height = self.height / count;
+        let y = self.y + height * index;
+        let height = if index == count - 1 {
+            self.y + self.height - y
+        } else {
+            height
+        };
+        Rect { y, height, ..*self }
+    }
+}
+
+/// Where the `index`th of `count` tiled windows goes in the master/stack
+/// layout: the first window, the master, takes the left `master_percent`
+/// of `area`, and the rest share the right-hand column in bands. A single
+/// window takes the whole area. `None` if `index` is out of range.
+pub fn master_stack(area: Rect, master_percent: u32, count: usize, index: usize) -> Option<Rect> {
+    if index >= count {
+        return None;
+    }
+    if count == 1 {
+        return Some(area);
+    }
+    let master_width = (area.width as u64 * master_percent.min(100) as u64 / 100) as u32;
+    let (master, stack) = area.split_left(master_width);
+    if index == 0 {
+        return Some(master);
+    }
+    Some(stack.band(index as u32 - 1, count as u32 - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: Rect = Rect {
+        x: 0,
+        y: 0,
+        width: 800,
+        height: 600,
+    };
+
+    #[test]
+    fn a_single_window_fills_the_area() {
+        assert_eq!(master_stack(SCREEN, 50, 1, 0), Some(SCREEN));
+        assert_eq!(master_stack(SCREEN, 50, 1, 1), None);
+        assert_eq!(master_stack(SCREEN, 50, 0, 0), None);
+    }
+
+    #[test]
+    fn the_master_takes_its_share_and_the_stack_the_rest() {
+        let master = master_stack(SCREEN, 60, 3, 0).unwrap();
+        assert_eq!(
+            master,
+            Rect {
+                width: 480,
+                ..SCREEN
+            }
+        );
+        let top = master_stack(SCREEN, 60, 3, 1).unwrap();
+        let bottom = master_stack(SCREEN, 60, 3, 2).unwrap();
+        assert_eq!(
+            top,
+            Rect {
+                x: 480,
+                y: 0,
+                width: 320,
+                height: 300
+            }
+        );
+        assert_eq!(
+            bottom,
+            Rect {
+                x: 480,
+                y: 300,
+                width: 320,
+                height: 300
+            }
+        );
+    }
+
+    #[test]
+    fn tiles_cover_the_area_without_overlapping() {
+        let area = Rect {
+            x: 10,
+            y: 20,
+            width: 333,
+            height: 301,
+        };
+        for count in 1..8 {
+            let tiles: [Option<Rect>; 8] =
+                core::array::from_fn(|i| master_stack(area, 55, count, i));
+            let tiles = &tiles[..count];
+            let covered: u32 = tiles
+                .iter()
+                .map(|t| t.unwrap().width * t.unwrap().height)
+                .sum();
+            assert_eq!(covered, area.width * area.height, "{count} windows");
+            for (i, a) in tiles.iter().enumerate() {
+                assert_eq!(a.unwrap().intersection(&area), *a);
+                for b in &tiles[i + 1..] {
+                    assert!(!a.unwrap().intersects(&b.unwrap()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bands_give_the_remainder_to_the_last() {
+        let area = Rect {
+            x: 0,
+            y: 5,
+            width: 10,
+            height: 10,
+        };
+        assert_eq!(area.band(0, 3), Rect { height: 3, ..area });
+        assert_eq!(
+            area.band(2, 3),
+            Rect {
+                y: 11,
+                height: 4,
+                ..area
+            }
+        );
+        assert_eq!(area.split_left(20).1.width, 0);
+    }
+}
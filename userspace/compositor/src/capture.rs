@@ -0,0 +1,80 @@
+//! Screen and window capture.
+//!
+//! A client hands the compositor a shared buffer and asks for the screen,
+//! or one window, to be copied into it. The screen is copied from the
+//! target after a frame has been composited, with the pixels a software
+//! cursor covers put back, so a capture looks the same whichever cursor
+//! plane is in use. A window is copied from the buffer it last committed,
+//! so it is captured whole even where other windows cover it, and
+//! converted to BGRA whatever format the client drew it in.
+
+use crate::cursor::CursorPlane;
+use crate::manager::Attachment;
+use crate::target::Target;
+
+/// What a capture copies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Screen,
+    Window(u64),
+}
+
+/// A capture in progress, owned by one client.
+pub struct Capture {
+    /// The client, as the server numbers them.
+    pub owner: u64,
+    pub source: Source,
+    /// Where the pixels go.
+    pub buffer: Attachment,
+    /// Copy again on every frame that changes the source, rather than once.
+    pub continuous: bool,
+    /// Nothing has been copied yet.
+    pub fresh: bool,
+}
+
+/// Copy as much of the screen as fits into `dst`, leaving out a software
+/// cursor. Returns the size copied.
+pub fn copy_screen<T: Target>(
+    target: &T,
+    cursor: &CursorPlane,
+    dst: &mut Attachment,
+) -> (u32, u32) {
+    let width = target.width().min(dst.width);
+    let height = target.height().min(dst.height);
+    let stride = dst.width as usize * 4;
+    let row_bytes = width as usize * 4;
+    let data = dst.as_mut_slice();
+    for y in 0..height {
+        let start = y as usize * stride;
+        target.read_row(0, y, &mut data[start..start + row_bytes]);
+    }
+
+    if let Some((rect, saved)) = cursor.covered() {
+        let saved_stride = rect.width as usize * 4;
+        for y in rect.y..(rect.y + rect.height).min(height) {
+            for x in rect.x..(rect.x + rect.width).min(width) {
+                let from = (y - rect.y) as usize * saved_stride + (x - rect.x) as usize * 4;
+                let to = y as usize * stride + x as usize * 4;
+                data[to..to + 4].copy_from_slice(&saved[from..from + 4]);
+            }
+        }
+    }
+    (width, height)
+}
+
+/// Copy as much of a window's `size` pixels from `src` as fits into `dst`,
+/// converting them to BGRA. Returns the size copied.
+pub fn copy_window(src: &Attachment, size: (u32, u32), dst: &mut Attachment) -> (u32, u32) {
+    let width = size.0.min(src.width).min(dst.width);
+    let height = size.1.min(src.height).min(dst.height);
+    let layout = src.layout();
+    let dst_stride = dst.width as usize * 4;
+    let row_bytes = width as usize * 4;
+    let pixels = src.as_slice();
+    let data = dst.as_mut_slice();
+    for y in 0..height {
+        let to = y as usize * dst_stride;
+        layout.read_row(pixels, 0, y, &mut data[to..to + row_bytes]);
+    }
+    (width, height)
+}
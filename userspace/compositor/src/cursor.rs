@@ -0,0 +1,331 @@
+//! The pointer cursor.
+//!
+//! The cursor is a plane of its own, on top of the composited windows rather
+//! than part of them, so moving it never repaints a window. If the display
+//! has a cursor plane in hardware (virtio-gpu's cursor queue), the image is
+//! handed to the display, which scans it out over the framebuffer. Otherwise
+//! the compositor draws it itself: it saves the screen pixels under the
+//! cursor, blends the image over them, and puts them back before the cursor
+//! moves or changes.
+
+use alloc::vec::Vec;
+use compositor_protocol::{MAX_CURSOR_SIZE, Rect, alpha_blend};
+
+use crate::target::Target;
+
+/// The built-in arrow: `X` is the outline, `.` the fill.
+const ARROW: [&str; 19] = [
+    "X           ",
+    "XX          ",
+    "X.X         ",
+    "X..X        ",
+    "X...X       ",
+    "X....X      ",
+    "X.....X     ",
+    "X......X    ",
+    "X.......X   ",
+    "X........X  ",
+    "X.........X ",
+    "X..........X",
+    "X......XXXXX",
+    "X...X..X    ",
+    "X..XX..X    ",
+    "X.X  X..X   ",
+    "XX   X..X   ",
+    "X     X..X  ",
+    "       XX   ",
+];
+const ARROW_OUTLINE: u32 = 0xFF000000;
+const ARROW_FILL: u32 = 0xFFFFFFFF;
+
+/// A cursor image: BGRA pixels and the hotspot, the pixel that points.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CursorImage {
+    pub width: u32,
+    pub height: u32,
+    pub hotspot: (u32, u32),
+    pixels: Vec<u8>,
+}
+
+impl CursorImage {
+    /// Wrap `width * height` BGRA pixels, rejecting an image that is empty,
+    /// larger than [`MAX_CURSOR_SIZE`] on a side, the wrong length, or whose
+    /// hotspot falls outside it.
+    pub fn new(width: u32, height: u32, hotspot: (u32, u32), pixels: Vec<u8>) -> Option<Self> {
+        let valid = (1..=MAX_CURSOR_SIZE).contains(&width)
+            && (1..=MAX_CURSOR_SIZE).contains(&height)
+            && pixels.len() == (width * height * 4) as usize
+            && hotspot.0 < width
+            && hotspot.1 < height;
+        valid.then_some(Self {
+            width,
+            height,
+            hotspot,
+            pixels,
+        })
+    }
+
+    /// The compositor's own pointer, shown wherever a client hasn't set one.
+    pub fn arrow() -> Self {
+        let width = ARROW[0].len() as u32;
+        let height = ARROW.len() as u32;
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for row in ARROW {
+            for c in row.bytes() {
+                let colour = match c {
+                    b'X' => ARROW_OUTLINE,
+                    b'.' => ARROW_FILL,
+                    _ => 0,
+                };
+                pixels.extend_from_slice(&colour.to_le_bytes());
+            }
+        }
+        Self {
+            width,
+            height,
+            hotspot: (0, 0),
+            pixels,
+        }
+    }
+
+    /// The image's BGRA pixels, row by row.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let offset = ((y * self.width + x) * 4) as usize;
+        self.pixels[offset..offset + 4].try_into().unwrap()
+    }
+}
+
+/// What the cursor plane last put on screen.
+#[derive(Clone, Copy)]
+struct Shown {
+    /// The image's id, or `None` for a hidden pointer.
+    image: Option<u64>,
+    pointer: (u32, u32),
+    /// The screen area a software cursor covers, if any of it is visible.
+    rect: Option<Rect>,
+}
+
+/// The cursor plane: in hardware if the display has one, otherwise drawn
+/// over the composited windows.
+pub struct CursorPlane {
+    hardware: bool,
+    shown: Option<Shown>,
+    /// The screen pixels a software cursor covers, row by row.
+    saved: Vec<u8>,
+}
+
+impl CursorPlane {
+    pub fn new(hardware: bool) -> Self {
+        Self {
+            hardware,
+            shown: None,
+            saved: Vec::new(),
+        }
+    }
+
+    /// Whether showing the image with id `image` (or hiding the pointer,
+    /// for `None`) at `pointer` would change anything.
+    pub fn needs_update(&self, image: Option<u64>, pointer: (u32, u32)) -> bool {
+        self.shown
+            .is_none_or(|shown| shown.image != image || shown.pointer != pointer)
+    }
+
+    /// Whether a software cursor is drawn over any of `rect`.
+    pub fn overlaps(&self, rect: &Rect) -> bool {
+        self.shown
+            .and_then(|shown| shown.rect)
+            .is_some_and(|covered| covered.intersects(rect))
+    }
+
+    /// The screen area a software cursor is drawn over and the pixels it
+    /// hides there, row by row.
+    pub fn covered(&self) -> Option<(Rect, &[u8])> {
+        let rect = self.shown?.rect?;
+        Some((rect, &self.saved))
+    }
+
+    /// Take a software cursor off the screen, restoring what was under it.
+    /// Returns the area restored, which the caller must flush.
+    pub fn erase<T: Target>(&mut self, target: &mut T) -> Option<Rect> {
+        if self.hardware {
+            return None;
+        }
+        let rect = self.shown.take()?.rect?;
+        let row_bytes = rect.width as usize * 4;
+        for (y, row) in (rect.y..rect.y + rect.height).zip(self.saved.chunks(row_bytes)) {
+            target.write_row(rect.x, y, rect.width, row);
+        }
+        Some(rect)
+    }
+
+    /// Show `image` (an id and the image, or `None` to hide the pointer)
+    /// with its hotspot at `pointer`. A software cursor is only drawn over a
+    /// screen it has been [erased](Self::erase) from; the area drawn is
+    /// returned for the caller to flush.
+    pub fn show<T: Target>(
+        &mut self,
+        target: &mut T,
+        image: Option<(u64, &CursorImage)>,
+        pointer: (u32, u32),
+    ) -> Option<Rect> {
+        let id = image.map(|(id, _)| id);
+        if self.hardware {
+            let previous = self.shown;
+            if previous.is_none_or(|shown| shown.image != id) {
+                target.set_hardware_cursor(image.map(|(_, image)| image));
+            }
+            if previous.is_none_or(|shown| shown.pointer != pointer) {
+                target.move_hardware_cursor(pointer.0, pointer.1);
+            }
+            self.shown = Some(Shown {
+                image: id,
+                pointer,
+                rect: None,
+            });
+            return None;
+        }
+
+        if self.shown.is_some() {
+            return None;
+        }
+        let rect = image.and_then(|(_, image)| draw(target, image, pointer, &mut self.saved));
+        self.shown = Some(Shown {
+            image: id,
+            pointer,
+            rect,
+        });
+        rect
+    }
+}
+
+/// Blend `image` over the target with its hotspot at `pointer`, saving the
+/// pixels it covers into `saved`. Returns the on-screen area it covers.
+fn draw<T: Target>(
+    target: &mut T,
+    image: &CursorImage,
+    pointer: (u32, u32),
+    saved: &mut Vec<u8>,
+) -> Option<Rect> {
+    // The image's top-left corner can be off the top or left of the screen.
+    let left = pointer.0 as i64 - image.hotspot.0 as i64;
+    let top = pointer.1 as i64 - image.hotspot.1 as i64;
+    let x0 = left.max(0) as u32;
+    let y0 = top.max(0) as u32;
+    let x1 = ((left + image.width as i64) as u32).min(target.width());
+    let y1 = ((top + image.height as i64) as u32).min(target.height());
+    if x0 >= x1 || y0 >= y1 {
+        return None;
+    }
+    let rect = Rect {
+        x: x0,
+        y: y0,
+        width: x1 - x0,
+        height: y1 - y0,
+    };
+
+    saved.clear();
+    for y in y0..y1 {
+        for x in x0..x1 {
+            saved.extend_from_slice(&target.get_pixel(x, y));
+        }
+    }
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let src = image.pixel((x as i64 - left) as u32, (y as i64 - top) as u32);
+            if src[3] == 0 {
+                continue;
+            }
+            let dst = target.get_pixel(x, y);
+            target.set_pixel(x, y, alpha_blend(src, dst));
+        }
+    }
+    Some(rect)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::target::MemoryTarget;
+    use alloc::vec;
+
+    fn software_target() -> MemoryTarget {
+        let mut target = MemoryTarget::new(32, 32);
+        target.hardware_cursor = false;
+        target.fill(
+            &Rect {
+                x: 0,
+                y: 0,
+                width: 32,
+                height: 32,
+            },
+            0xFF112233,
+        );
+        target
+    }
+
+    #[test]
+    fn the_arrow_is_a_valid_image() {
+        let arrow = CursorImage::arrow();
+        let pixels = arrow.pixels.clone();
+        assert!(CursorImage::new(arrow.width, arrow.height, arrow.hotspot, pixels).is_some());
+        assert_eq!(arrow.pixel(0, 0), ARROW_OUTLINE.to_le_bytes());
+        assert_eq!(arrow.pixel(1, 2), ARROW_FILL.to_le_bytes());
+    }
+
+    #[test]
+    fn images_are_bounded_and_hold_their_hotspot() {
+        let pixels = |w: u32, h: u32| vec![0u8; (w * h * 4) as usize];
+        assert!(CursorImage::new(0, 0, (0, 0), Vec::new()).is_none());
+        assert!(CursorImage::new(65, 1, (0, 0), pixels(65, 1)).is_none());
+        assert!(CursorImage::new(4, 4, (4, 0), pixels(4, 4)).is_none());
+        assert!(CursorImage::new(4, 4, (0, 0), pixels(4, 3)).is_none());
+        assert!(CursorImage::new(4, 4, (3, 3), pixels(4, 4)).is_some());
+    }
+
+    #[test]
+    fn a_software_cursor_restores_what_it_covered() {
+        let mut target = software_target();
+        let before = target.pixels.clone();
+        let image = CursorImage::new(2, 2, (1, 1), vec![0xFF; 16]).unwrap();
+        let mut plane = CursorPlane::new(false);
+
+        let drawn = plane.show(&mut target, Some((1, &image)), (10, 10));
+        assert_eq!(
+            drawn,
+            Some(Rect {
+                x: 9,
+                y: 9,
+                width: 2,
+                height: 2
+            })
+        );
+        assert_eq!(target.get_pixel(9, 9), [0xFF; 4]);
+        assert!(!plane.needs_update(Some(1), (10, 10)));
+        assert!(plane.needs_update(Some(1), (11, 10)));
+
+        assert_eq!(plane.erase(&mut target), drawn);
+        assert_eq!(target.pixels, before);
+    }
+
+    #[test]
+    fn a_software_cursor_is_clipped_to_the_screen() {
+        let mut target = software_target();
+        let image = CursorImage::new(4, 4, (2, 2), vec![0xFF; 64]).unwrap();
+        let mut plane = CursorPlane::new(false);
+
+        let drawn = plane.show(&mut target, Some((1, &image)), (0, 31));
+        assert_eq!(
+            drawn,
+            Some(Rect {
+                x: 0,
+                y: 29,
+                width: 2,
+                height: 3
+            })
+        );
+    }
+}
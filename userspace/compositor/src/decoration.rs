@@ -0,0 +1,139 @@
+//! Server-side window decorations.
+//!
+//! A decorated window gets a title bar, drawn by the compositor directly
+//! above the client's buffer, showing the window's title and a close
+//! button. The title bar is part of the window's frame for stacking and
+//! hit-testing, but the client never sees it: its buffer and its pointer
+//! coordinates start below the bar.
+//!
+//! A decorated window can also be resized by dragging the bottom-right
+//! corner of its buffer. The grip is not drawn; presses on it simply go to
+//! the compositor instead of the client.
+
+use compositor_protocol::Rect;
+
+use crate::font::{self, GLYPH_ADVANCE, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::target::Target;
+
+/// Height of the title bar above a decorated window's buffer.
+pub const TITLE_BAR_HEIGHT: u32 = 24;
+
+/// Title bar of the window with the keyboard focus (Nord frost blue).
+pub const ACTIVE_COLOUR: u32 = 0xFF5E81AC;
+/// Title bar of every other window (Nord polar night).
+pub const INACTIVE_COLOUR: u32 = 0xFF3B4252;
+/// Title text (Nord snow storm).
+pub const TEXT_COLOUR: u32 = 0xFFECEFF4;
+/// The close button (Nord aurora red).
+pub const CLOSE_COLOUR: u32 = 0xFFBF616A;
+
+/// Glyphs are drawn at twice the font's size.
+const TEXT_SCALE: u32 = 2;
+/// Space between the title bar's left edge and the title.
+const TEXT_INSET: u32 = 8;
+const CLOSE_SIZE: u32 = 16;
+const CLOSE_INSET: u32 = (TITLE_BAR_HEIGHT - CLOSE_SIZE) / 2;
+/// Inset of the cross from the close button's edges.
+const CROSS_INSET: u32 = 4;
+/// Side of the square resize grip.
+const RESIZE_GRIP: u32 = 12;
+
+/// The title bar of a window whose frame starts at `origin` and whose
+/// buffer is `width` pixels wide.
+pub fn title_bar(origin: (u32, u32), width: u32) -> Rect {
+    Rect {
+        x: origin.0,
+        y: origin.1,
+        width,
+        height: TITLE_BAR_HEIGHT,
+    }
+}
+
+/// The close button at the right-hand end of a title bar.
+pub fn close_button(bar: &Rect) -> Rect {
+    let size = CLOSE_SIZE.min(bar.width);
+    Rect {
+        x: bar.x + bar.width.saturating_sub(CLOSE_INSET + size),
+        y: bar.y + CLOSE_INSET,
+        width: size,
+        height: size,
+    }
+}
+
+/// The resize grip in the bottom-right corner of a window's buffer.
+pub fn resize_grip(content: &Rect) -> Rect {
+    let width = RESIZE_GRIP.min(content.width);
+    let height = RESIZE_GRIP.min(content.height);
+    Rect {
+        x: content.x + content.width - width,
+        y: content.y + content.height - height,
+        width,
+        height,
+    }
+}
+
+/// Draw the part of a title bar that falls inside `clip`.
+pub fn draw<T: Target>(target: &mut T, bar: &Rect, title: &str, focused: bool, clip: &Rect) {
+    let Some(visible) = bar.intersection(clip) else {
+        return;
+    };
+    let background = if focused {
+        ACTIVE_COLOUR
+    } else {
+        INACTIVE_COLOUR
+    };
+    target.fill(&visible, background);
+
+    let close = close_button(bar);
+    let text_y = bar.y + (TITLE_BAR_HEIGHT - GLYPH_HEIGHT * TEXT_SCALE) / 2;
+    let text_end = close.x.saturating_sub(TEXT_INSET);
+    let mut text_x = bar.x + TEXT_INSET;
+    for c in title.chars() {
+        if text_x + GLYPH_WIDTH * TEXT_SCALE > text_end {
+            break;
+        }
+        draw_glyph(target, c, text_x, text_y, clip);
+        text_x += GLYPH_ADVANCE * TEXT_SCALE;
+    }
+
+    fill_clipped(target, &close, CLOSE_COLOUR, clip);
+    let cross = close.width.saturating_sub(2 * CROSS_INSET);
+    for i in 0..cross {
+        let y = close.y + CROSS_INSET + i;
+        for x in [
+            close.x + CROSS_INSET + i,
+            close.x + CROSS_INSET + cross - 1 - i,
+        ] {
+            let pixel = Rect {
+                x,
+                y,
+                width: 1,
+                height: 1,
+            };
+            fill_clipped(target, &pixel, TEXT_COLOUR, clip);
+        }
+    }
+}
+
+fn draw_glyph<T: Target>(target: &mut T, c: char, x: u32, y: u32, clip: &Rect) {
+    for row in 0..GLYPH_HEIGHT {
+        for column in 0..GLYPH_WIDTH {
+            if !font::pixel(c, column, row) {
+                continue;
+            }
+            let dot = Rect {
+                x: x + column * TEXT_SCALE,
+                y: y + row * TEXT_SCALE,
+                width: TEXT_SCALE,
+                height: TEXT_SCALE,
+            };
+            fill_clipped(target, &dot, TEXT_COLOUR, clip);
+        }
+    }
+}
+
+fn fill_clipped<T: Target>(target: &mut T, rect: &Rect, colour: u32, clip: &Rect) {
+    if let Some(rect) = rect.intersection(clip) {
+        target.fill(&rect, colour);
+    }
+}
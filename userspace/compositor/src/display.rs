@@ -1,15 +1,27 @@
-//! The compositing target: the framebuffer mapped from the `display:`
-//! scheme.
+//! The compositing target: the framebuffers mapped from the `display:`
+//! scheme, one per head.
 
+use alloc::format;
+use alloc::vec;
+use alloc::vec::Vec;
 use compositor_protocol::Rect;
+use libpanda::mailbox::Mailbox;
 use libpanda::{Handle, environment, sys};
-use panda_abi::{ErrorCode, SurfaceInfoOut, SurfaceRect};
+use panda_abi::{
+    DISPLAY_CURSOR_SIZE, DisplayMode, EVENT_DISPLAY_CHANGED, ErrorCode, MAX_DISPLAY_MODES,
+    SurfaceInfoOut, SurfaceRect,
+};
 
+use crate::cursor::CursorImage;
 use crate::target::Target;
 
-/// The exclusively-claimed display the compositor presents to.
+/// The first display the compositor claims; further heads follow as
+/// `display:/pci/display/1` and onwards.
 pub const DISPLAY_PATH: &str = "display:/pci/display/0";
 
+/// The most heads the compositor drives at once.
+pub const MAX_HEADS: usize = 4;
+
 /// A mapped framebuffer.
 pub struct Framebuffer {
     handle: Handle,
@@ -17,41 +29,108 @@ pub struct Framebuffer {
     width: u32,
     height: u32,
     stride: u32,
+    /// The display accepted `OP_DISPLAY_CURSOR_SET`.
+    hardware_cursor: bool,
 }
 
 impl Framebuffer {
-    /// Claim the display, query its mode and map its framebuffer.
+    /// Claim the first display, query its mode and map its framebuffer.
     ///
     /// `Busy` here is expected until the in-kernel compositor is deleted
     /// (Phase 5 of plans/userspace-compositor.md): it holds a permanent
     /// claim on the same display.
     pub fn open() -> Result<Self, ErrorCode> {
-        let handle = environment::open(DISPLAY_PATH, 0, 0)?;
+        Self::open_path(DISPLAY_PATH, None)
+    }
+
+    /// Claim the display at `path`, query its mode and map its framebuffer.
+    /// `EVENT_DISPLAY_CHANGED` for it arrives at `mailbox`, if given.
+    pub fn open_path(path: &str, mailbox: Option<&Mailbox>) -> Result<Self, ErrorCode> {
+        let (mailbox, event_mask) = match mailbox {
+            Some(mailbox) => (mailbox.handle().as_raw(), EVENT_DISPLAY_CHANGED),
+            None => (0, 0),
+        };
+        let handle = environment::open(path, mailbox, event_mask)?;
+
+        let mut framebuffer = Self {
+            handle,
+            pixels: core::ptr::null_mut(),
+            width: 0,
+            height: 0,
+            stride: 0,
+            hardware_cursor: false,
+        };
+        // Dropping `framebuffer` on failure closes the handle.
+        framebuffer.remap()?;
+
+        // Hiding the cursor is harmless, and tells us whether the display
+        // has a cursor plane at all.
+        framebuffer.hardware_cursor = sys::display::cursor_set(handle, None, 0, 0) >= 0;
 
+        Ok(framebuffer)
+    }
+
+    /// Claim every display there is, up to [`MAX_HEADS`], in path order.
+    ///
+    /// Fails as [`Self::open`] does if the first display cannot be claimed;
+    /// a later one that cannot is left out.
+    pub fn open_all(mailbox: Option<&Mailbox>) -> Result<Vec<Self>, ErrorCode> {
+        let mut heads = vec![Self::open_path(DISPLAY_PATH, mailbox)?];
+        for index in 1..MAX_HEADS {
+            match Self::open_path(&format!("display:/pci/display/{}", index), mailbox) {
+                Ok(head) => heads.push(head),
+                Err(ErrorCode::NotFound) => break,
+                Err(_) => environment::log("compositor: could not open a further display"),
+            }
+        }
+        Ok(heads)
+    }
+
+    /// The `display:` handle, which names the head in mailbox events.
+    pub fn handle(&self) -> Handle {
+        self.handle
+    }
+
+    /// Query the display's mode and map its framebuffer, as after
+    /// `EVENT_DISPLAY_CHANGED`: a mode change replaces the framebuffer, and
+    /// the old mapping must no longer be drawn into.
+    pub fn remap(&mut self) -> Result<(), ErrorCode> {
         let mut info = SurfaceInfoOut {
             width: 0,
             height: 0,
             format: 0,
             stride: 0,
         };
-        if sys::display::info(handle, &mut info) < 0 {
-            let _ = sys::file::close(handle);
+        if sys::display::info(self.handle, &mut info) < 0 {
             return Err(ErrorCode::IoError);
         }
 
-        let mapped = sys::display::map(handle);
+        let mapped = sys::display::map(self.handle);
         if mapped < 0 {
-            let _ = sys::file::close(handle);
             return Err(ErrorCode::IoError);
         }
 
-        Ok(Self {
-            handle,
-            pixels: mapped as *mut u8,
-            width: info.width,
-            height: info.height,
-            stride: info.stride,
-        })
+        self.pixels = mapped as *mut u8;
+        self.width = info.width;
+        self.height = info.height;
+        self.stride = info.stride;
+        Ok(())
+    }
+
+    /// The modes the display can be switched to.
+    pub fn modes(&self) -> Vec<DisplayMode> {
+        let mut modes = vec![DisplayMode::default(); MAX_DISPLAY_MODES];
+        let count = sys::display::modes(self.handle, &mut modes);
+        modes.truncate(count.max(0) as usize);
+        modes
+    }
+
+    /// Switch the display to one of its [modes](Self::modes). The kernel
+    /// unmaps the old framebuffer before replacing it, so the new one is
+    /// mapped straight away rather than waiting for `EVENT_DISPLAY_CHANGED`.
+    pub fn set_mode(&mut self, width: u32, height: u32) -> Result<(), ErrorCode> {
+        libpanda::error::from_syscall(sys::display::set_mode(self.handle, width, height))?;
+        self.remap()
     }
 
     fn offset(&self, x: u32, y: u32) -> isize {
@@ -115,6 +194,19 @@ impl Target for Framebuffer {
         }
     }
 
+    fn read_row(&self, x: u32, y: u32, dst: &mut [u8]) {
+        if y >= self.height || x >= self.width {
+            return;
+        }
+        let width = (dst.len() / 4).min((self.width - x) as usize);
+        // SAFETY: the source row is clipped to the mapped framebuffer and
+        // `dst` has room for `width` pixels.
+        unsafe {
+            let ptr = self.pixels.offset(self.offset(x, y));
+            core::ptr::copy_nonoverlapping(ptr, dst.as_mut_ptr(), width * 4);
+        }
+    }
+
     fn set_pixel(&mut self, x: u32, y: u32, pixel: [u8; 4]) {
         if x >= self.width || y >= self.height {
             return;
@@ -135,4 +227,30 @@ impl Target for Framebuffer {
         };
         sys::display::flush(self.handle, Some(&rect));
     }
+
+    fn has_hardware_cursor(&self) -> bool {
+        self.hardware_cursor
+    }
+
+    fn set_hardware_cursor(&mut self, image: Option<&CursorImage>) {
+        let Some(image) = image else {
+            sys::display::cursor_set(self.handle, None, 0, 0);
+            return;
+        };
+        // The plane takes a fixed-size square; pad the image with
+        // transparent pixels. Both are BGRA in memory.
+        let size = DISPLAY_CURSOR_SIZE as usize;
+        let mut plane = vec![0u8; size * size * 4];
+        let row_bytes = image.width as usize * 4;
+        for (row, src) in image.pixels().chunks(row_bytes).enumerate() {
+            let start = row * size * 4;
+            plane[start..start + row_bytes].copy_from_slice(src);
+        }
+        let (hot_x, hot_y) = image.hotspot;
+        sys::display::cursor_set(self.handle, Some(&plane), hot_x, hot_y);
+    }
+
+    fn move_hardware_cursor(&mut self, x: u32, y: u32) {
+        sys::display::cursor_move(self.handle, x, y);
+    }
 }
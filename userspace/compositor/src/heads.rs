@@ -0,0 +1,347 @@
+//! One screen over several displays.
+//!
+//! [`Heads`] is the target the compositor composites into when it drives
+//! more than one display (`display:/pci/display/0`, `/1`, ...). The window
+//! manager sees a single screen; each head shows its part of it, or all of
+//! it, depending on the [`Arrangement`].
+
+use alloc::vec::Vec;
+use compositor_protocol::Rect;
+
+use crate::cursor::CursorImage;
+use crate::target::Target;
+
+/// How the heads share the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arrangement {
+    /// Side by side, left to right in head order: the screen is as wide as
+    /// all of them and as tall as the tallest. Below a shorter head there
+    /// is no screen to show, and drawing there is dropped.
+    Span,
+    /// Each head shows the same screen, as large as the smallest head, from
+    /// its top left corner.
+    Mirror,
+}
+
+/// Several targets composited as one.
+pub struct Heads<T: Target> {
+    heads: Vec<T>,
+    arrangement: Arrangement,
+    /// The cursor image the hardware cursor planes show, kept to load into
+    /// the plane of whichever head the pointer moves onto.
+    cursor: Option<CursorImage>,
+    /// The head whose plane shows the cursor, when spanning.
+    cursor_head: Option<usize>,
+}
+
+impl<T: Target> Heads<T> {
+    /// Composite into `heads`, of which there must be at least one.
+    pub fn new(heads: Vec<T>, arrangement: Arrangement) -> Self {
+        assert!(!heads.is_empty(), "a screen needs a head");
+        Self {
+            heads,
+            arrangement,
+            cursor: None,
+            cursor_head: None,
+        }
+    }
+
+    pub fn arrangement(&self) -> Arrangement {
+        self.arrangement
+    }
+
+    pub fn heads(&self) -> &[T] {
+        &self.heads
+    }
+
+    /// The heads, to change. A head that changes size changes the screen:
+    /// the caller tells the window manager (see
+    /// `WindowManager::screen_changed`).
+    pub fn heads_mut(&mut self) -> &mut [T] {
+        self.cursor_head = None;
+        &mut self.heads
+    }
+
+    /// Each head with the screen area it shows.
+    fn areas(&self) -> impl Iterator<Item = (usize, Rect)> + '_ {
+        let (width, height) = (self.width(), self.height());
+        let mut left = 0;
+        self.heads.iter().enumerate().map(move |(index, head)| {
+            let area = match self.arrangement {
+                Arrangement::Span => Rect {
+                    x: left,
+                    y: 0,
+                    width: head.width(),
+                    height: head.height(),
+                },
+                Arrangement::Mirror => Rect {
+                    x: 0,
+                    y: 0,
+                    width,
+                    height,
+                },
+            };
+            left += head.width();
+            (index, area)
+        })
+    }
+
+    /// The head showing screen position `(x, y)` (the first, when
+    /// mirroring) and the position on it.
+    fn head_at(&self, x: u32, y: u32) -> Option<(usize, u32, u32)> {
+        self.areas()
+            .find(|(_, area)| area.contains(x, y))
+            .map(|(index, area)| (index, x - area.x, y - area.y))
+    }
+}
+
+/// The part of `rect` within `area`, relative to `area`.
+fn clip(rect: &Rect, area: &Rect) -> Option<Rect> {
+    let part = rect.intersection(area)?;
+    Some(Rect {
+        x: part.x - area.x,
+        y: part.y - area.y,
+        ..part
+    })
+}
+
+impl<T: Target> Target for Heads<T> {
+    fn width(&self) -> u32 {
+        let widths = self.heads.iter().map(|head| head.width());
+        match self.arrangement {
+            Arrangement::Span => widths.sum(),
+            Arrangement::Mirror => widths.min().unwrap_or(0),
+        }
+    }
+
+    fn height(&self) -> u32 {
+        let heights = self.heads.iter().map(|head| head.height());
+        match self.arrangement {
+            Arrangement::Span => heights.max().unwrap_or(0),
+            Arrangement::Mirror => heights.min().unwrap_or(0),
+        }
+    }
+
+    fn fill(&mut self, rect: &Rect, colour: u32) {
+        let areas: Vec<_> = self.areas().collect();
+        for (index, area) in areas {
+            if let Some(part) = clip(rect, &area) {
+                self.heads[index].fill(&part, colour);
+            }
+        }
+    }
+
+    fn write_row(&mut self, x: u32, y: u32, width: u32, src: &[u8]) {
+        let row = Rect {
+            x,
+            y,
+            width,
+            height: 1,
+        };
+        let areas: Vec<_> = self.areas().collect();
+        for (index, area) in areas {
+            if let Some(part) = clip(&row, &area) {
+                let start = (part.x + area.x - x) as usize * 4;
+                let bytes = &src[start..start + part.width as usize * 4];
+                self.heads[index].write_row(part.x, part.y, part.width, bytes);
+            }
+        }
+    }
+
+    fn get_pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        match self.head_at(x, y) {
+            Some((index, x, y)) => self.heads[index].get_pixel(x, y),
+            None => [0; 4],
+        }
+    }
+
+    fn read_row(&self, x: u32, y: u32, dst: &mut [u8]) {
+        let row = Rect {
+            x,
+            y,
+            width: (dst.len() / 4) as u32,
+            height: 1,
+        };
+        for (index, area) in self.areas() {
+            if let Some(part) = clip(&row, &area) {
+                let start = (part.x + area.x - x) as usize * 4;
+                let bytes = &mut dst[start..start + part.width as usize * 4];
+                self.heads[index].read_row(part.x, part.y, bytes);
+            }
+            if self.arrangement == Arrangement::Mirror {
+                break;
+            }
+        }
+    }
+
+    fn set_pixel(&mut self, x: u32, y: u32, pixel: [u8; 4]) {
+        let areas: Vec<_> = self.areas().collect();
+        for (index, area) in areas {
+            if area.contains(x, y) {
+                self.heads[index].set_pixel(x - area.x, y - area.y, pixel);
+            }
+        }
+    }
+
+    fn flush(&mut self, rect: &Rect) {
+        let areas: Vec<_> = self.areas().collect();
+        for (index, area) in areas {
+            if let Some(part) = clip(rect, &area) {
+                self.heads[index].flush(&part);
+            }
+        }
+    }
+
+    /// Only if every head has one: a software cursor is drawn into the
+    /// screen, so it crosses between heads by itself.
+    fn has_hardware_cursor(&self) -> bool {
+        self.heads.iter().all(|head| head.has_hardware_cursor())
+    }
+
+    fn set_hardware_cursor(&mut self, image: Option<&CursorImage>) {
+        self.cursor = image.cloned();
+        match self.arrangement {
+            Arrangement::Span => {
+                if let Some(index) = self.cursor_head {
+                    self.heads[index].set_hardware_cursor(image);
+                }
+            }
+            Arrangement::Mirror => {
+                for head in &mut self.heads {
+                    head.set_hardware_cursor(image);
+                }
+            }
+        }
+    }
+
+    /// When spanning, the cursor shows on the head under it alone, moving
+    /// its image from plane to plane as it crosses between heads.
+    fn move_hardware_cursor(&mut self, x: u32, y: u32) {
+        if self.arrangement == Arrangement::Mirror {
+            for head in &mut self.heads {
+                head.move_hardware_cursor(x, y);
+            }
+            return;
+        }
+
+        let Some((index, x, y)) = self.head_at(x, y) else {
+            return;
+        };
+        if self.cursor_head != Some(index) {
+            if let Some(previous) = self.cursor_head {
+                self.heads[previous].set_hardware_cursor(None);
+            }
+            self.heads[index].move_hardware_cursor(x, y);
+            self.heads[index].set_hardware_cursor(self.cursor.as_ref());
+            self.cursor_head = Some(index);
+        } else {
+            self.heads[index].move_hardware_cursor(x, y);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::target::MemoryTarget;
+    use alloc::vec;
+
+    fn span() -> Heads<MemoryTarget> {
+        Heads::new(
+            vec![MemoryTarget::new(4, 3), MemoryTarget::new(2, 2)],
+            Arrangement::Span,
+        )
+    }
+
+    #[test]
+    fn spans_heads_side_by_side() {
+        let mut heads = span();
+        assert_eq!((heads.width(), heads.height()), (6, 3));
+
+        heads.fill(
+            &Rect {
+                x: 3,
+                y: 1,
+                width: 2,
+                height: 2,
+            },
+            0x11223344,
+        );
+        let colour = 0x11223344u32.to_le_bytes();
+        assert_eq!(heads.heads()[0].get_pixel(3, 2), colour);
+        assert_eq!(heads.heads()[1].get_pixel(0, 1), colour);
+        assert_eq!(heads.heads()[1].get_pixel(1, 1), [0; 4]);
+        // Below the shorter head there is nothing.
+        assert_eq!(heads.get_pixel(4, 2), [0; 4]);
+        assert_eq!(heads.get_pixel(3, 2), colour);
+    }
+
+    #[test]
+    fn splits_rows_and_flushes_between_heads() {
+        let mut heads = span();
+        let row: Vec<u8> = (0..6 * 4).map(|byte| byte as u8).collect();
+        heads.write_row(0, 0, 6, &row);
+        let mut back = vec![0u8; 6 * 4];
+        heads.read_row(0, 0, &mut back);
+        assert_eq!(back, row);
+        assert_eq!(heads.heads()[1].get_pixel(1, 0), [20, 21, 22, 23]);
+
+        heads.flush(&Rect {
+            x: 2,
+            y: 0,
+            width: 3,
+            height: 3,
+        });
+        assert_eq!(
+            heads.heads()[0].flushed,
+            [Rect {
+                x: 2,
+                y: 0,
+                width: 2,
+                height: 3
+            }]
+        );
+        assert_eq!(
+            heads.heads()[1].flushed,
+            [Rect {
+                x: 0,
+                y: 0,
+                width: 1,
+                height: 2
+            }]
+        );
+    }
+
+    #[test]
+    fn mirrors_the_smallest_screen() {
+        let mut heads = Heads::new(
+            vec![MemoryTarget::new(4, 3), MemoryTarget::new(3, 4)],
+            Arrangement::Mirror,
+        );
+        assert_eq!((heads.width(), heads.height()), (3, 3));
+        heads.set_pixel(2, 2, [1, 2, 3, 4]);
+        heads.set_pixel(3, 0, [9; 4]);
+        for head in heads.heads() {
+            assert_eq!(head.get_pixel(2, 2), [1, 2, 3, 4]);
+            assert_eq!(head.get_pixel(3, 0), [0; 4]);
+        }
+    }
+
+    #[test]
+    fn moves_the_hardware_cursor_between_heads() {
+        let mut heads = span();
+        assert!(heads.has_hardware_cursor());
+        heads.set_hardware_cursor(Some(&CursorImage::arrow()));
+        heads.move_hardware_cursor(1, 1);
+        assert!(heads.heads()[0].cursor_size.is_some());
+        assert_eq!(heads.heads()[1].cursor_size, None);
+
+        heads.move_hardware_cursor(5, 0);
+        assert_eq!(heads.heads()[0].cursor_size, None);
+        assert!(heads.heads()[1].cursor_size.is_some());
+        assert_eq!(heads.heads()[1].cursor_position, (1, 0));
+
+        heads.heads_mut()[1].hardware_cursor = false;
+        assert!(!heads.has_hardware_cursor());
+    }
+}
@@ -0,0 +1,101 @@
+//! The input devices the compositor reads and hands to the window manager.
+//!
+//! The compositor is the one process that opens keyboards and pointers:
+//! clients get their input as compositor `Event`s for the window that has
+//! the focus (keys) or is under the pointer (everything else), so two
+//! graphical clients never race each other for the same device.
+
+use alloc::format;
+use alloc::vec::Vec;
+use compositor_protocol::Event;
+use libpanda::keyboard::RawInputEvent;
+use libpanda::pointer::{BTN_LEFT, BTN_MIDDLE, BTN_RIGHT, Buttons, PointerDevice};
+use libpanda::{Handle, environment, file};
+
+use crate::manager::WindowManager;
+use crate::target::Target;
+
+/// How many `/pci/input/N` devices to try at startup. The VM's keyboard,
+/// mouse and tablet are always among the first few.
+const MAX_INPUT_DEVICES: usize = 8;
+
+/// The buttons forwarded to clients, with their evdev codes.
+const BUTTONS: [(Buttons, u16); 3] = [
+    (Buttons::LEFT, BTN_LEFT),
+    (Buttons::RIGHT, BTN_RIGHT),
+    (Buttons::MIDDLE, BTN_MIDDLE),
+];
+
+/// Every keyboard and pointer the compositor found.
+pub struct Input {
+    keyboards: Vec<Handle>,
+    pointers: Vec<PointerDevice>,
+}
+
+impl Input {
+    /// Open every input device as a keyboard and as a pointer; each one
+    /// only opens as whichever it is.
+    pub fn open() -> Self {
+        let mut keyboards = Vec::new();
+        let mut pointers = Vec::new();
+        for index in 0..MAX_INPUT_DEVICES {
+            if let Ok(handle) = environment::open(&format!("keyboard:/pci/input/{}", index), 0, 0) {
+                keyboards.push(handle);
+            }
+            if let Ok(device) = PointerDevice::open(&format!("pointer:/pci/input/{}", index)) {
+                pointers.push(device);
+            }
+        }
+        environment::log(&format!(
+            "compositor: {} keyboard(s), {} pointer(s)",
+            keyboards.len(),
+            pointers.len()
+        ));
+        Self {
+            keyboards,
+            pointers,
+        }
+    }
+
+    /// Read everything the devices have queued, without blocking, and
+    /// return the events the window manager routed to windows.
+    pub fn poll<T: Target>(&mut self, manager: &mut WindowManager<T>) -> Vec<Event<'static>> {
+        let mut events = Vec::new();
+
+        let mut buf = [0u8; size_of::<RawInputEvent>()];
+        for &keyboard in &self.keyboards {
+            while file::try_read(keyboard, &mut buf) == buf.len() as isize {
+                // SAFETY: `buf` holds exactly one `RawInputEvent`, a
+                // `repr(C)` struct of plain integers, and the read may be
+                // unaligned.
+                let event = unsafe { (buf.as_ptr() as *const RawInputEvent).read_unaligned() };
+                events.extend(manager.key(event.code, event.value));
+            }
+        }
+
+        for pointer in &mut self.pointers {
+            while let Ok(Some(report)) = pointer.try_read_event() {
+                if let Some((x, y)) = report.position {
+                    let (width, height) = manager.screen_size();
+                    if width > 0 && height > 0 {
+                        let (x, y) = pointer.scale(x, y, width, height);
+                        events.extend(manager.move_pointer(x, y));
+                    }
+                } else if report.dx != 0 || report.dy != 0 {
+                    events.extend(manager.move_pointer_by(report.dx, report.dy));
+                }
+                for (button, code) in BUTTONS {
+                    if report.pressed.contains(button) {
+                        events.extend(manager.pointer_button(code, true));
+                    }
+                    if report.released.contains(button) {
+                        events.extend(manager.pointer_button(code, false));
+                    }
+                }
+                events.extend(manager.pointer_wheel(report.wheel));
+            }
+        }
+
+        events
+    }
+}
@@ -5,17 +5,25 @@
 //! an ordinary client of the `display:` scheme.
 //!
 //! The `os` feature (on by default) pulls in everything that talks to the
-//! kernel. Without it only [`manager`] compiles, so the window-management
-//! logic can be unit-tested on the host.
+//! kernel. Without it only [`manager`] and the modules it draws with
+//! compile, so the window-management logic can be unit-tested on the host.
 
 #![cfg_attr(not(test), no_std)]
 
 extern crate alloc;
 
+pub mod capture;
+pub mod cursor;
+pub mod decoration;
+pub mod font;
+pub mod heads;
 pub mod manager;
 pub mod target;
+pub mod workspace;
 
 #[cfg(feature = "os")]
 pub mod display;
 #[cfg(feature = "os")]
+pub mod input;
+#[cfg(feature = "os")]
 pub mod server;
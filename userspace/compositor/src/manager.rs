@@ -6,15 +6,57 @@
 //! back-to-front with an opaque row-copy fast path — but a window's pixels
 //! now live in a buffer the *client* allocated and the compositor mapped,
 //! rather than in a `Vec<u8>` the kernel owned and clients copied into.
+//!
+//! The window list is the stacking order, back to front. Windows that asked
+//! to be always on top form a layer above all the others, and raising or
+//! lowering a window moves it within its own layer.
+//!
+//! It also decides where input goes. The keyboard focus follows the window
+//! most recently shown or clicked, and a click raises the window too;
+//! pointer events go to the topmost visible window under the pointer,
+//! except that a window keeps receiving them while a button pressed over it
+//! is held. Presses on a decorated window's title bar are the compositor's
+//! own: dragging the bar moves the window and clicking the close button
+//! destroys it.
+//!
+//! Resizing is a handshake. The compositor sends `Configure` with the size
+//! it wants, the client acknowledges it and commits a buffer of that size,
+//! and until that commit the old buffer stays on screen. A window has at
+//! most one unacknowledged `Configure`; sizes wanted in the meantime (a
+//! resize drag produces many) collapse into the latest, sent on the ack.
+//!
+//! The pointer is drawn last, by the [cursor plane](crate::cursor), in the
+//! image the window under it asked for — or the compositor's own arrow.
+//!
+//! Windows are spread over [workspaces](crate::workspace), of which only the
+//! current one is shown. A tiling workspace places and sizes its windows
+//! itself, through the same `Configure` handshake, and ignores their
+//! clients' moves and resizes.
+//!
+//! After each frame, [captures](crate::capture) copy the screen or a window
+//! into a client's buffer if what they show has changed.
 
+use alloc::string::String;
+use alloc::vec;
 use alloc::vec::Vec;
-use compositor_protocol::{Event, FORMAT_BGRA8888, Rect, Request, alpha_blend, is_region_opaque};
-
+use compositor_protocol::{
+    BufferLayout, CAPTURE_NO_DISPLAY, CAPTURE_NO_SUCH_WINDOW, Event, FORMAT_BGRA8888, Rect,
+    Request, alpha_blend, is_region_opaque, master_stack,
+};
+use keymap::Keymap;
+
+use crate::capture::{self, Capture, Source};
+use crate::cursor::{CursorImage, CursorPlane};
+use crate::decoration::{self, TITLE_BAR_HEIGHT};
 use crate::target::Target;
+use crate::workspace::{Action, Filtered, Layout, MASTER_PERCENT, Shortcuts, WORKSPACES};
 
 /// Background colour (Nord dark grey), as in the kernel compositor.
 pub const BACKGROUND_COLOUR: u32 = 0xFF2E3440;
 
+/// The smallest size the compositor configures a window to.
+pub const MIN_WINDOW_SIZE: u32 = 16;
+
 /// A client buffer mapped into the compositor's address space.
 ///
 /// The compositor holds its own handle to the underlying shared buffer, so
@@ -27,11 +69,13 @@ pub struct Attachment {
     len: usize,
     pub width: u32,
     pub height: u32,
+    /// One of the `FORMAT_*` constants.
+    pub format: u8,
 }
 
 impl Attachment {
     /// Wrap a mapped client buffer, rejecting one that is too small for the
-    /// geometry it claims or that uses an unsupported format.
+    /// geometry it claims or that uses an unknown format.
     ///
     /// # Safety
     ///
@@ -45,11 +89,8 @@ impl Attachment {
         height: u32,
         format: u8,
     ) -> Option<Self> {
-        if format != FORMAT_BGRA8888 {
-            return None;
-        }
-        let needed = (width as usize).checked_mul(height as usize)?.checked_mul(4)?;
-        if needed == 0 || len < needed {
+        let layout = BufferLayout::new(format, width, height)?;
+        if len < layout.size() {
             return None;
         }
         Some(Self {
@@ -58,16 +99,25 @@ impl Attachment {
             len,
             width,
             height,
+            format,
         })
     }
 
-    fn as_slice(&self) -> &[u8] {
+    pub(crate) fn layout(&self) -> BufferLayout {
+        BufferLayout {
+            format: self.format,
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    pub(crate) fn as_slice(&self) -> &[u8] {
         // SAFETY: `new` is an unsafe constructor whose contract is that
         // `data`/`len` describe a live mapping for this object's lifetime.
         unsafe { core::slice::from_raw_parts(self.data, self.len) }
     }
 
-    fn as_mut_slice(&mut self) -> &mut [u8] {
+    pub(crate) fn as_mut_slice(&mut self) -> &mut [u8] {
         // SAFETY: as `as_slice`, and `&mut self` gives exclusive access.
         unsafe { core::slice::from_raw_parts_mut(self.data, self.len) }
     }
@@ -77,9 +127,28 @@ impl Attachment {
 pub struct Window {
     pub id: u64,
     pub visible: bool,
+    /// Top-left corner of the frame: the title bar if the window is
+    /// decorated, otherwise the buffer.
     pub position: (u32, u32),
     /// Size of the latched buffer; `(0, 0)` until the first commit.
     pub size: (u32, u32),
+    /// Shown in the title bar.
+    pub title: String,
+    /// The compositor draws a title bar above the buffer.
+    pub decorated: bool,
+    /// Stacked in the layer above every ordinary window, and shown on
+    /// every workspace.
+    pub always_on_top: bool,
+    /// The workspace the window is on.
+    pub workspace: usize,
+    /// The size most recently sent in a `Configure`.
+    pub requested: Option<(u32, u32)>,
+    /// The serial of a `Configure` the client hasn't acknowledged yet.
+    unacked: Option<u32>,
+    /// A size wanted while a `Configure` was unacknowledged.
+    deferred: Option<(u32, u32)>,
+    /// What the pointer looks like over the window's buffer.
+    cursor: WindowCursor,
     /// Attached but not yet committed.
     pending: Option<Attachment>,
     /// The buffer being composited.
@@ -91,18 +160,98 @@ pub struct Window {
 }
 
 impl Window {
+    /// Where the buffer's top-left corner is on screen.
+    fn content_origin(&self) -> (u32, u32) {
+        let bar = if self.decorated { TITLE_BAR_HEIGHT } else { 0 };
+        (self.position.0, self.position.1.saturating_add(bar))
+    }
+
+    /// The buffer's rectangle on screen.
     fn rect(&self) -> Rect {
+        let (x, y) = self.content_origin();
         Rect {
-            x: self.position.0,
-            y: self.position.1,
+            x,
+            y,
             width: self.size.0,
             height: self.size.1,
         }
     }
 
+    /// The buffer and the title bar above it, if any.
+    fn frame(&self) -> Rect {
+        let content = self.rect();
+        Rect {
+            x: self.position.0,
+            y: self.position.1,
+            width: content.width,
+            height: content.height + (content.y - self.position.1),
+        }
+    }
+
+    fn title_bar(&self) -> Option<Rect> {
+        self.decorated
+            .then(|| decoration::title_bar(self.position, self.size.0))
+    }
+
+    fn close_button(&self) -> Option<Rect> {
+        self.title_bar().map(|bar| decoration::close_button(&bar))
+    }
+
+    fn resize_grip(&self) -> Option<Rect> {
+        let content = self.rect();
+        (self.decorated && !content.is_empty()).then(|| decoration::resize_grip(&content))
+    }
+
     fn content_mut(&mut self) -> Option<&mut Attachment> {
         self.pending.as_mut().or(self.latched.as_mut())
     }
+
+    /// Whether the window is shown while `workspace` is the current one.
+    fn is_shown(&self, workspace: usize) -> bool {
+        self.visible && (self.always_on_top || self.workspace == workspace)
+    }
+
+    /// Whether the window is on screen at screen position `(x, y)`.
+    fn shows(&self, workspace: usize, x: u32, y: u32) -> bool {
+        self.is_shown(workspace) && self.latched.is_some() && self.frame().contains(x, y)
+    }
+}
+
+/// A press on a window's title bar, which lasts until the last pointer
+/// button is released.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DecorationGrab {
+    /// Dragging the title bar: the window follows the pointer, keeping the
+    /// pointer `offset` from its top-left corner.
+    Move { window: u64, offset: (u32, u32) },
+    /// Pressed the close button; releasing over it closes the window.
+    Close { window: u64 },
+    /// Dragging the resize grip: the window is configured to `size` plus
+    /// however far the pointer has moved from `start`.
+    Resize {
+        window: u64,
+        start: (u32, u32),
+        size: (u32, u32),
+    },
+}
+
+impl DecorationGrab {
+    fn window(&self) -> u64 {
+        match *self {
+            DecorationGrab::Move { window, .. }
+            | DecorationGrab::Close { window }
+            | DecorationGrab::Resize { window, .. } => window,
+        }
+    }
+}
+
+/// What the pointer looks like over a window.
+enum WindowCursor {
+    /// The compositor's arrow.
+    Default,
+    Hidden,
+    /// An image the client set, with an id unique among cursor images.
+    Custom(u64, CursorImage),
 }
 
 /// The compositor's window stack and damage state.
@@ -114,16 +263,62 @@ pub struct WindowManager<T: Target> {
     target: Option<T>,
     next_window_id: u64,
     frame: u64,
+    /// The window key events go to.
+    focused: Option<u64>,
+    /// Where the pointer is, in screen coordinates.
+    pointer: (u32, u32),
+    /// The window a held button was pressed over, which gets every pointer
+    /// event until the last button is released.
+    grab: Option<u64>,
+    /// How many pointer buttons are held.
+    buttons_held: u32,
+    /// A held press on a title bar, which the client never hears about.
+    decoration_grab: Option<DecorationGrab>,
+    /// The serial of the next `Configure`.
+    next_serial: u32,
+    /// Shows the pointer, over everything else.
+    cursor: CursorPlane,
+    /// The arrow, whose id is 0.
+    default_cursor: CursorImage,
+    /// The id of the next image a client sets.
+    next_cursor_id: u64,
+    /// At most one per client.
+    captures: Vec<Capture>,
+    /// `Captured`/`CaptureFailed` events, with the client each is for.
+    capture_events: Vec<(u64, Event<'static>)>,
+    /// The workspace being shown.
+    workspace: usize,
+    layouts: [Layout; WORKSPACES],
+    /// Every window, in the order a tiling workspace lays them out: the
+    /// order they were created in, except as promoted.
+    tile_order: Vec<u64>,
+    shortcuts: Shortcuts,
 }
 
 impl<T: Target> WindowManager<T> {
     pub fn new(target: Option<T>) -> Self {
+        let hardware_cursor = target.as_ref().is_some_and(|t| t.has_hardware_cursor());
         let mut manager = Self {
             windows: Vec::new(),
             dirty_regions: Vec::new(),
             target,
             next_window_id: 1,
             frame: 0,
+            focused: None,
+            pointer: (0, 0),
+            grab: None,
+            buttons_held: 0,
+            decoration_grab: None,
+            next_serial: 1,
+            cursor: CursorPlane::new(hardware_cursor),
+            default_cursor: CursorImage::arrow(),
+            next_cursor_id: 1,
+            captures: Vec::new(),
+            capture_events: Vec::new(),
+            workspace: 0,
+            layouts: [Layout::Floating; WORKSPACES],
+            tile_order: Vec::new(),
+            shortcuts: Shortcuts::new(),
         };
 
         if let Some(target) = manager.target.as_mut() {
@@ -148,15 +343,574 @@ impl<T: Target> WindowManager<T> {
         }
     }
 
+    /// The target, to change: to map a display again after its mode
+    /// changed, say. Call [`Self::screen_changed`] afterwards.
+    pub fn target_mut(&mut self) -> Option<&mut T> {
+        self.target.as_mut()
+    }
+
+    /// Take up the target's new geometry after a mode change: redraw the
+    /// whole screen, bring the pointer and any window left off screen back
+    /// onto it, and lay the tiling workspaces out again.
+    pub fn screen_changed(&mut self) -> Vec<Event<'static>> {
+        // Whatever a software cursor saved went with the old framebuffer.
+        let hardware_cursor = self
+            .target
+            .as_ref()
+            .is_some_and(|t| t.has_hardware_cursor());
+        self.cursor = CursorPlane::new(hardware_cursor);
+
+        let (width, height) = self.screen_size();
+        let screen = Rect {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        };
+        if let Some(target) = self.target.as_mut() {
+            target.fill(&screen, BACKGROUND_COLOUR);
+        }
+        self.dirty_regions.clear();
+        self.mark_dirty(screen);
+
+        self.pointer = (
+            self.pointer.0.min(width.saturating_sub(1)),
+            self.pointer.1.min(height.saturating_sub(1)),
+        );
+        for window in &mut self.windows {
+            let (x, y) = window.position;
+            if x >= width || y >= height {
+                let frame = window.frame();
+                window.position = (
+                    x.min(width.saturating_sub(frame.width)),
+                    y.min(height.saturating_sub(frame.height)),
+                );
+            }
+        }
+
+        let mut events = Vec::new();
+        for workspace in 0..WORKSPACES {
+            events.extend(self.retile(workspace));
+        }
+        events
+    }
+
     /// The number of completed frames.
     pub fn frame(&self) -> u64 {
         self.frame
     }
 
+    fn window(&self, id: u64) -> Option<&Window> {
+        self.windows.iter().find(|w| w.id == id)
+    }
+
     fn window_mut(&mut self, id: u64) -> Option<&mut Window> {
         self.windows.iter_mut().find(|w| w.id == id)
     }
 
+    /// The window IDs in stacking order, back to front.
+    pub fn stacking_order(&self) -> Vec<u64> {
+        self.windows.iter().map(|w| w.id).collect()
+    }
+
+    /// The index a window in the given layer is inserted at to be the top
+    /// (or bottom) of that layer.
+    fn layer_edge(&self, always_on_top: bool, top: bool) -> usize {
+        let first_on_top = self
+            .windows
+            .iter()
+            .position(|w| w.always_on_top)
+            .unwrap_or(self.windows.len());
+        match (always_on_top, top) {
+            (false, false) => 0,
+            (false, true) | (true, false) => first_on_top,
+            (true, true) => self.windows.len(),
+        }
+    }
+
+    /// Move a window to the top (or bottom) of its layer.
+    fn restack(&mut self, id: u64, top: bool) {
+        let Some(index) = self.windows.iter().position(|w| w.id == id) else {
+            return;
+        };
+        let window = self.windows.remove(index);
+        let at = self.layer_edge(window.always_on_top, top);
+        let (visible, frame) = (window.is_shown(self.workspace), window.frame());
+        self.windows.insert(at, window);
+        if at != index && visible {
+            self.mark_dirty(frame);
+        }
+    }
+
+    /// Move a window's frame to a screen position.
+    fn move_window(&mut self, id: u64, x: u32, y: u32) {
+        let workspace = self.workspace;
+        if let Some(w) = self.window_mut(id) {
+            let old = w.frame();
+            w.position = (x, y);
+            let new = w.frame();
+            if w.is_shown(workspace) {
+                self.mark_dirty(old);
+                self.mark_dirty(new);
+            }
+        }
+    }
+
+    /// Ask a window's client to resize it, clamped to the screen. Sent at
+    /// once unless a previous `Configure` is still unacknowledged, in which
+    /// case it is sent when that one is.
+    pub fn configure(&mut self, window: u64, width: u32, height: u32) -> Vec<Event<'static>> {
+        let mut events = Vec::new();
+        let (screen_width, screen_height) = self.screen_size();
+        let clamp = |value: u32, screen: u32| {
+            let value = value.max(MIN_WINDOW_SIZE);
+            if screen == 0 {
+                value
+            } else {
+                value.min(screen.max(MIN_WINDOW_SIZE))
+            }
+        };
+        let size = (clamp(width, screen_width), clamp(height, screen_height));
+        let serial = self.next_serial;
+        let Some(w) = self.window_mut(window) else {
+            return events;
+        };
+        if w.unacked.is_some() {
+            w.deferred = Some(size);
+            return events;
+        }
+        if w.requested == Some(size) {
+            return events;
+        }
+        w.unacked = Some(serial);
+        w.requested = Some(size);
+        self.next_serial = serial.wrapping_add(1);
+        events.push(Event::Configure {
+            window,
+            serial,
+            width: size.0,
+            height: size.1,
+        });
+        events
+    }
+
+    /// The workspace being shown.
+    pub fn workspace(&self) -> usize {
+        self.workspace
+    }
+
+    /// How a workspace places its windows.
+    pub fn layout(&self, workspace: usize) -> Layout {
+        self.layouts.get(workspace).copied().unwrap_or_default()
+    }
+
+    /// Whether a window is placed and sized by its workspace's tiling
+    /// rather than by its client. Always-on-top windows float.
+    fn tiles(&self, window: &Window) -> bool {
+        window.visible && !window.always_on_top && self.layouts[window.workspace] == Layout::Tiling
+    }
+
+    fn is_tiled(&self, id: u64) -> bool {
+        self.window(id).is_some_and(|w| self.tiles(w))
+    }
+
+    /// Carry out a keyboard shortcut.
+    fn run(&mut self, action: Action) -> Vec<Event<'static>> {
+        match action {
+            Action::Switch(workspace) => self.switch_workspace(workspace),
+            Action::Send(workspace) => match self.focused {
+                Some(window) => self.send_to_workspace(window, workspace),
+                None => Vec::new(),
+            },
+            Action::ToggleTiling => {
+                let layout = match self.layouts[self.workspace] {
+                    Layout::Floating => Layout::Tiling,
+                    Layout::Tiling => Layout::Floating,
+                };
+                self.set_layout(self.workspace, layout)
+            }
+            Action::Promote => match self.focused {
+                Some(window) => self.promote(window),
+                None => Vec::new(),
+            },
+            Action::FocusNext => self.cycle_focus(true),
+            Action::FocusPrevious => self.cycle_focus(false),
+        }
+    }
+
+    /// Show another workspace. The focus goes to the topmost window there.
+    pub fn switch_workspace(&mut self, workspace: usize) -> Vec<Event<'static>> {
+        let mut events = Vec::new();
+        if workspace >= WORKSPACES || workspace == self.workspace {
+            return events;
+        }
+        self.workspace = workspace;
+        // A press in progress may be on a window that is no longer shown.
+        self.grab = None;
+        self.decoration_grab = None;
+        let (width, height) = self.screen_size();
+        self.mark_dirty(Rect {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        });
+        self.refocus(&mut events);
+        events
+    }
+
+    /// Move a window to another workspace, retiling both.
+    pub fn send_to_workspace(&mut self, window: u64, workspace: usize) -> Vec<Event<'static>> {
+        let mut events = Vec::new();
+        let current = self.workspace;
+        let Some(w) = self.window_mut(window) else {
+            return events;
+        };
+        if workspace >= WORKSPACES || w.workspace == workspace {
+            return events;
+        }
+        let was_shown = w.is_shown(current);
+        let from = w.workspace;
+        w.workspace = workspace;
+        let (frame, still_shown) = (w.frame(), w.is_shown(current));
+        if was_shown && !still_shown {
+            self.mark_dirty(frame);
+            if self.focused == Some(window) {
+                self.refocus(&mut events);
+            }
+        }
+        events.extend(self.retile(from));
+        events.extend(self.retile(workspace));
+        events
+    }
+
+    /// Change how a workspace places its windows. Tiling configures every
+    /// window on it at once; going back to floating leaves them where they
+    /// were tiled.
+    pub fn set_layout(&mut self, workspace: usize, layout: Layout) -> Vec<Event<'static>> {
+        match self.layouts.get_mut(workspace) {
+            Some(current) if *current != layout => *current = layout,
+            _ => return Vec::new(),
+        }
+        self.retile(workspace)
+    }
+
+    /// Make a window its workspace's master: the first in the tiling order.
+    pub fn promote(&mut self, window: u64) -> Vec<Event<'static>> {
+        let Some(index) = self.tile_order.iter().position(|&id| id == window) else {
+            return Vec::new();
+        };
+        self.tile_order.remove(index);
+        self.tile_order.insert(0, window);
+        match self.window(window) {
+            Some(w) => self.retile(w.workspace),
+            None => Vec::new(),
+        }
+    }
+
+    /// Focus and raise the next (or previous) window shown on the current
+    /// workspace, in tiling order.
+    fn cycle_focus(&mut self, forward: bool) -> Vec<Event<'static>> {
+        let shown: Vec<u64> = self
+            .tile_order
+            .iter()
+            .copied()
+            .filter(|&id| self.window(id).is_some_and(|w| w.is_shown(self.workspace)))
+            .collect();
+        if shown.is_empty() {
+            return Vec::new();
+        }
+        let next = match self
+            .focused
+            .and_then(|f| shown.iter().position(|&id| id == f))
+        {
+            Some(index) if forward => (index + 1) % shown.len(),
+            Some(index) => (index + shown.len() - 1) % shown.len(),
+            None => 0,
+        };
+        let window = shown[next];
+        self.restack(window, true);
+        self.set_focus(Some(window))
+    }
+
+    /// Lay out a tiling workspace's windows again, moving them and asking
+    /// their clients for the new sizes. Does nothing to a floating
+    /// workspace, or without a display.
+    fn retile(&mut self, workspace: usize) -> Vec<Event<'static>> {
+        let mut events = Vec::new();
+        let (width, height) = self.screen_size();
+        if self.layout(workspace) != Layout::Tiling || width == 0 || height == 0 {
+            return events;
+        }
+        let tiled: Vec<u64> = self
+            .tile_order
+            .iter()
+            .copied()
+            .filter(|&id| {
+                self.window(id)
+                    .is_some_and(|w| w.workspace == workspace && self.tiles(w))
+            })
+            .collect();
+        let screen = Rect {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        };
+        for (index, &id) in tiled.iter().enumerate() {
+            let Some(tile) = master_stack(screen, MASTER_PERCENT, tiled.len(), index) else {
+                continue;
+            };
+            let decorated = self.window(id).is_some_and(|w| w.decorated);
+            let bar = if decorated { TITLE_BAR_HEIGHT } else { 0 };
+            self.move_window(id, tile.x, tile.y);
+            events.extend(self.configure(id, tile.width, tile.height.saturating_sub(bar)));
+        }
+        events
+    }
+
+    /// Repaint a window's title bar, which changes colour with the focus.
+    fn mark_title_bar_dirty(&mut self, id: u64) {
+        let bar = self
+            .window(id)
+            .filter(|w| w.is_shown(self.workspace))
+            .and_then(Window::title_bar);
+        if let Some(bar) = bar {
+            self.mark_dirty(bar);
+        }
+    }
+
+    /// The window with the keyboard focus.
+    pub fn focused(&self) -> Option<u64> {
+        self.focused
+    }
+
+    /// The pointer's screen position.
+    pub fn pointer_position(&self) -> (u32, u32) {
+        self.pointer
+    }
+
+    /// The topmost visible window at screen position `(x, y)`.
+    pub fn window_at(&self, x: u32, y: u32) -> Option<u64> {
+        self.windows
+            .iter()
+            .rev()
+            .find(|w| w.shows(self.workspace, x, y))
+            .map(|w| w.id)
+    }
+
+    /// Move the keyboard focus to `window` (or to no window), telling the
+    /// windows that lose and gain it.
+    pub fn set_focus(&mut self, window: Option<u64>) -> Vec<Event<'static>> {
+        let mut events = Vec::new();
+        if self.focused == window {
+            return events;
+        }
+        if let Some(old) = self.focused {
+            events.push(Event::FocusOut { window: old });
+            self.mark_title_bar_dirty(old);
+        }
+        self.focused = window;
+        if let Some(new) = window {
+            events.push(Event::FocusIn { window: new });
+            self.mark_title_bar_dirty(new);
+        }
+        events
+    }
+
+    /// After the focused window is hidden or destroyed, pass the focus to
+    /// the topmost window still showing, if any.
+    fn refocus(&mut self, events: &mut Vec<Event<'static>>) {
+        let next = self
+            .windows
+            .iter()
+            .rev()
+            .find(|w| w.is_shown(self.workspace))
+            .map(|w| w.id);
+        let exists = |id| self.windows.iter().any(|w| w.id == id);
+        if self.focused.is_some_and(|id| !exists(id)) {
+            // The old focus no longer exists, so it isn't told it lost focus.
+            self.focused = None;
+        }
+        events.extend(self.set_focus(next));
+    }
+
+    /// Use `keymap` to tell which keys the shortcuts are on.
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.shortcuts.set_keymap(keymap);
+    }
+
+    /// Route a key event from a keyboard to the focused window, unless it
+    /// is part of one of the compositor's own shortcuts.
+    pub fn key(&mut self, code: u16, value: u32) -> Vec<Event<'static>> {
+        let mut events = Vec::new();
+        match self.shortcuts.key(code, value) {
+            Filtered::Forward => {}
+            Filtered::Swallow => return events,
+            Filtered::Run(action) => return self.run(action),
+        }
+        if let Some(window) = self.focused {
+            events.push(Event::Key {
+                window,
+                code,
+                value,
+            });
+        }
+        events
+    }
+
+    /// The window pointer events go to: the grabbing window while a button
+    /// is held, otherwise the one under the pointer — unless the pointer is
+    /// over its title bar, which belongs to the compositor.
+    fn pointer_target(&self) -> Option<(u64, i32, i32)> {
+        if self.decoration_grab.is_some() {
+            return None;
+        }
+        let (px, py) = self.pointer;
+        let window = match self.grab {
+            Some(id) => self.window(id)?,
+            None => {
+                let window = self.window(self.window_at(px, py)?)?;
+                if !window.rect().contains(px, py) {
+                    return None;
+                }
+                window
+            }
+        };
+        let (x, y) = window.content_origin();
+        Some((window.id, px as i32 - x as i32, py as i32 - y as i32))
+    }
+
+    /// Move the pointer to a screen position, clamped to the screen.
+    pub fn move_pointer(&mut self, x: u32, y: u32) -> Vec<Event<'static>> {
+        let (width, height) = self.screen_size();
+        let clamped = (
+            x.min(width.saturating_sub(1)),
+            y.min(height.saturating_sub(1)),
+        );
+        let mut events = Vec::new();
+        if clamped == self.pointer {
+            return events;
+        }
+        self.pointer = clamped;
+        match self.decoration_grab {
+            Some(DecorationGrab::Move { window, offset }) => {
+                let x = clamped.0.saturating_sub(offset.0);
+                let y = clamped.1.saturating_sub(offset.1);
+                self.move_window(window, x, y);
+            }
+            Some(DecorationGrab::Resize {
+                window,
+                start,
+                size,
+            }) => {
+                let dx = clamped.0 as i32 - start.0 as i32;
+                let dy = clamped.1 as i32 - start.1 as i32;
+                let width = size.0.saturating_add_signed(dx);
+                let height = size.1.saturating_add_signed(dy);
+                events.extend(self.configure(window, width, height));
+            }
+            _ => {}
+        }
+        if let Some((window, x, y)) = self.pointer_target() {
+            events.push(Event::PointerMotion { window, x, y });
+        }
+        events
+    }
+
+    /// Move the pointer by a relative amount, as a mouse reports it.
+    pub fn move_pointer_by(&mut self, dx: i32, dy: i32) -> Vec<Event<'static>> {
+        let x = self.pointer.0.saturating_add_signed(dx);
+        let y = self.pointer.1.saturating_add_signed(dy);
+        self.move_pointer(x, y)
+    }
+
+    /// Press or release a pointer button. Pressing over a window focuses
+    /// and raises it, and grabs the pointer for it (or for its title bar)
+    /// until every button is released.
+    pub fn pointer_button(&mut self, button: u16, pressed: bool) -> Vec<Event<'static>> {
+        let mut events = Vec::new();
+        if pressed {
+            if self.buttons_held == 0 {
+                self.press(&mut events);
+            }
+            self.buttons_held += 1;
+        }
+        if let Some((window, x, y)) = self.pointer_target() {
+            events.push(Event::PointerButton {
+                window,
+                x,
+                y,
+                button,
+                pressed,
+            });
+        }
+        if !pressed {
+            self.buttons_held = self.buttons_held.saturating_sub(1);
+            if self.buttons_held == 0 {
+                self.grab = None;
+                if let Some(DecorationGrab::Close { window }) = self.decoration_grab.take() {
+                    let (x, y) = self.pointer;
+                    let over = self.window(window).and_then(Window::close_button);
+                    if over.is_some_and(|button| button.contains(x, y)) {
+                        self.destroy(window, &mut events);
+                    }
+                }
+            }
+        }
+        events
+    }
+
+    /// The first button went down: focus and raise whatever is under the
+    /// pointer, and decide who the press belongs to.
+    fn press(&mut self, events: &mut Vec<Event<'static>>) {
+        let (x, y) = self.pointer;
+        let Some(id) = self.window_at(x, y) else {
+            return;
+        };
+        events.extend(self.set_focus(Some(id)));
+        self.restack(id, true);
+        let tiled = self.is_tiled(id);
+        let Some(window) = self.window(id) else {
+            return;
+        };
+        if window.close_button().is_some_and(|b| b.contains(x, y)) {
+            self.decoration_grab = Some(DecorationGrab::Close { window: id });
+        } else if window.title_bar().is_some_and(|b| b.contains(x, y)) {
+            // A tiled window can't be dragged, but its title bar is still
+            // the compositor's.
+            if !tiled {
+                let offset = (x - window.position.0, y - window.position.1);
+                self.decoration_grab = Some(DecorationGrab::Move { window: id, offset });
+            }
+        } else if window.resize_grip().is_some_and(|b| b.contains(x, y)) && !tiled {
+            self.decoration_grab = Some(DecorationGrab::Resize {
+                window: id,
+                start: (x, y),
+                size: window.size,
+            });
+        } else {
+            self.grab = Some(id);
+        }
+    }
+
+    /// Turn the scroll wheel over whatever window is under the pointer.
+    pub fn pointer_wheel(&mut self, delta: i32) -> Vec<Event<'static>> {
+        let mut events = Vec::new();
+        if delta == 0 {
+            return events;
+        }
+        if let Some((window, x, y)) = self.pointer_target() {
+            events.push(Event::PointerWheel {
+                window,
+                x,
+                y,
+                delta,
+            });
+        }
+        events
+    }
+
     /// Mark a screen-space rectangle as dirty, coalescing it with an
     /// overlapping or touching region if there is one.
     pub fn mark_dirty(&mut self, rect: Rect) {
@@ -188,16 +942,29 @@ impl<T: Target> WindowManager<T> {
             Request::CreateWindow => {
                 let id = self.next_window_id;
                 self.next_window_id += 1;
-                self.windows.push(Window {
-                    id,
-                    visible: false,
-                    position: (0, 0),
-                    size: (0, 0),
-                    pending: None,
-                    latched: None,
-                    pending_damage: Vec::new(),
-                    awaiting_frame: false,
-                });
+                let at = self.layer_edge(false, true);
+                self.windows.insert(
+                    at,
+                    Window {
+                        id,
+                        visible: false,
+                        position: (0, 0),
+                        size: (0, 0),
+                        title: String::new(),
+                        decorated: false,
+                        always_on_top: false,
+                        workspace: self.workspace,
+                        requested: None,
+                        unacked: None,
+                        deferred: None,
+                        cursor: WindowCursor::Default,
+                        pending: None,
+                        latched: None,
+                        pending_damage: Vec::new(),
+                        awaiting_frame: false,
+                    },
+                );
+                self.tile_order.push(id);
                 events.push(Event::WindowCreated { window: id });
             }
 
@@ -224,6 +991,7 @@ impl<T: Target> WindowManager<T> {
             }
 
             Request::Commit { window } => {
+                let workspace = self.workspace;
                 let Some(w) = self.window_mut(window) else {
                     return events;
                 };
@@ -232,6 +1000,7 @@ impl<T: Target> WindowManager<T> {
                     return events;
                 }
 
+                let old_frame = w.frame();
                 if let Some(pending) = w.pending.take() {
                     w.size = (pending.width, pending.height);
                     if let Some(released) = w.latched.replace(pending) {
@@ -245,15 +1014,30 @@ impl<T: Target> WindowManager<T> {
                 w.awaiting_frame = true;
 
                 let damage: Vec<Rect> = w.pending_damage.drain(..).collect();
-                let (origin_x, origin_y) = w.position;
-                let visible = w.visible;
+                let (origin_x, origin_y) = w.content_origin();
+                let visible = w.is_shown(workspace);
                 let window_rect = w.rect();
+                let frame = w.frame();
 
                 if visible {
-                    if damage.is_empty() {
+                    if frame != old_frame {
+                        // A resize: the title bar is redrawn at the new
+                        // width, and whatever the old frame covered shows
+                        // through.
+                        self.mark_dirty(old_frame);
+                        self.mark_dirty(frame);
+                    } else if damage.is_empty() {
                         self.mark_dirty(window_rect);
                     } else {
-                        for rect in damage {
+                        let bounds = Rect {
+                            x: 0,
+                            y: 0,
+                            width: window_rect.width,
+                            height: window_rect.height,
+                        };
+                        // Damage is window-relative and may overhang the
+                        // buffer; only what the buffer covers is repainted.
+                        for rect in damage.iter().filter_map(|rect| rect.intersection(&bounds)) {
                             self.mark_dirty(Rect {
                                 x: origin_x + rect.x,
                                 y: origin_y + rect.y,
@@ -279,101 +1063,372 @@ impl<T: Target> WindowManager<T> {
             }
 
             Request::SetVisible { window, visible } => {
+                let workspace = self.workspace;
                 if let Some(w) = self.window_mut(window) {
                     if w.visible != visible {
                         w.visible = visible;
-                        let rect = w.rect();
-                        self.mark_dirty(rect);
+                        let rect = w.frame();
+                        let on = w.workspace;
+                        let here = w.always_on_top || on == workspace;
+                        // A window that appears takes the focus, unless it
+                        // is on another workspace; one that disappears
+                        // hands it on.
+                        if here {
+                            self.mark_dirty(rect);
+                        }
+                        if visible && here {
+                            events.extend(self.set_focus(Some(window)));
+                        } else if self.focused == Some(window) {
+                            self.refocus(&mut events);
+                        }
+                        events.extend(self.retile(on));
                     }
                 }
             }
 
             Request::Move { window, x, y } => {
-                if let Some(w) = self.window_mut(window) {
-                    let old = w.rect();
-                    w.position = (x, y);
-                    let new = w.rect();
+                if !self.is_tiled(window) {
+                    self.move_window(window, x, y);
+                }
+            }
+
+            Request::DestroyWindow { window } => self.destroy(window, &mut events),
+
+            Request::Raise { window } => self.restack(window, true),
+
+            Request::Lower { window } => self.restack(window, false),
+
+            Request::SetAlwaysOnTop { window, on_top } => {
+                if let Some(w) = self.window_mut(window)
+                    && w.always_on_top != on_top
+                {
+                    w.always_on_top = on_top;
+                    let (on, frame) = (w.workspace, w.frame());
                     if w.visible {
-                        self.mark_dirty(old);
-                        self.mark_dirty(new);
+                        // It is now shown on every workspace, or no longer.
+                        self.mark_dirty(frame);
                     }
+                    self.restack(window, true);
+                    events.extend(self.retile(on));
                 }
             }
 
-            Request::DestroyWindow { window } => {
-                let Some(index) = self.windows.iter().position(|w| w.id == window) else {
-                    return events;
-                };
-                let removed = self.windows.remove(index);
-                for buffer in [removed.pending.as_ref(), removed.latched.as_ref()]
-                    .into_iter()
-                    .flatten()
+            Request::SetTitle { window, title } => {
+                if let Some(w) = self.window_mut(window)
+                    && w.title != title
                 {
-                    events.push(Event::BufferReleased {
-                        window,
-                        buffer: buffer.id,
-                    });
+                    w.title = String::from(title);
+                    self.mark_title_bar_dirty(window);
                 }
-                events.push(Event::Closed { window });
-                if removed.visible && !removed.rect().is_empty() {
-                    let rect = removed.rect();
-                    self.mark_dirty(rect);
+            }
+
+            Request::Resize {
+                window,
+                width,
+                height,
+            } => {
+                if !self.is_tiled(window) {
+                    events.extend(self.configure(window, width, height));
                 }
             }
-        }
 
-        events
-    }
+            Request::AckConfigure { window, serial } => {
+                if let Some(w) = self.window_mut(window)
+                    && w.unacked == Some(serial)
+                {
+                    w.unacked = None;
+                    if let Some((width, height)) = w.deferred.take() {
+                        events.extend(self.configure(window, width, height));
+                    }
+                }
+            }
 
-    /// Run one compositor tick: composite every dirty region, present it,
-    /// and report the commits the tick consumed.
+            Request::SetDecorated { window, decorated } => {
+                let workspace = self.workspace;
+                if let Some(w) = self.window_mut(window)
+                    && w.decorated != decorated
+                {
+                    let old = w.frame();
+                    w.decorated = decorated;
+                    let new = w.frame();
+                    let on = w.workspace;
+                    if w.is_shown(workspace) {
+                        self.mark_dirty(old);
+                        self.mark_dirty(new);
+                    }
+                    // A tile's title bar comes out of the buffer's height.
+                    events.extend(self.retile(on));
+                }
+            }
+
+            Request::SetCursor {
+                window,
+                width,
+                height,
+                ..
+            } => {
+                // An image comes with a buffer the caller maps and passes
+                // to `set_cursor`; only hiding the pointer is handled here.
+                if width == 0 || height == 0 {
+                    self.set_cursor(window, None);
+                }
+            }
+
+            Request::ResetCursor { window } => {
+                if let Some(w) = self.window_mut(window) {
+                    w.cursor = WindowCursor::Default;
+                }
+            }
+
+            // Captures belong to a client, not a window, so the caller
+            // handles them with `start_capture` and `stop_capture`, and
+            // shares connections itself.
+            Request::Capture { .. } | Request::StopCapture | Request::ShareCapture => {}
+        }
+
+        events
+    }
+
+    /// Start capturing `source` into `buffer` for the client `owner`,
+    /// replacing any capture it already had. The first copy is made at the
+    /// end of the next tick. Fails with a `CAPTURE_*` code if there is
+    /// nothing to capture.
+    pub fn start_capture(
+        &mut self,
+        owner: u64,
+        source: Source,
+        buffer: Attachment,
+        continuous: bool,
+    ) -> Result<(), u8> {
+        match source {
+            Source::Screen if self.target.is_none() => return Err(CAPTURE_NO_DISPLAY),
+            Source::Window(id) if self.window(id).is_none() => return Err(CAPTURE_NO_SUCH_WINDOW),
+            _ => {}
+        }
+        self.stop_capture(owner);
+        self.captures.push(Capture {
+            owner,
+            source,
+            buffer,
+            continuous,
+            fresh: true,
+        });
+        Ok(())
+    }
+
+    /// End the client's capture. Returns whether it had one.
+    pub fn stop_capture(&mut self, owner: u64) -> bool {
+        let before = self.captures.len();
+        self.captures.retain(|capture| capture.owner != owner);
+        self.captures.len() != before
+    }
+
+    /// Take the capture events produced since the last call, each with the
+    /// client it is for. A client's capture is over once it has been sent
+    /// `CaptureFailed`, or `Captured` for a capture that isn't continuous.
+    pub fn take_capture_events(&mut self) -> Vec<(u64, Event<'static>)> {
+        core::mem::take(&mut self.capture_events)
+    }
+
+    /// Make the copies due this frame. `painted` says whether the screen
+    /// changed, and `committed` lists the windows whose commits it showed.
+    fn run_captures(&mut self, painted: bool, committed: &[u64]) {
+        let frame = self.frame;
+        let Self {
+            windows,
+            target,
+            cursor,
+            captures,
+            capture_events,
+            ..
+        } = self;
+
+        captures.retain_mut(|capture| {
+            let copied = match capture.source {
+                Source::Screen => {
+                    let Some(target) = target.as_ref() else {
+                        capture_events.push((
+                            capture.owner,
+                            Event::CaptureFailed {
+                                reason: CAPTURE_NO_DISPLAY,
+                            },
+                        ));
+                        return false;
+                    };
+                    (capture.fresh || painted)
+                        .then(|| capture::copy_screen(target, cursor, &mut capture.buffer))
+                }
+                Source::Window(id) => {
+                    let Some(window) = windows.iter().find(|w| w.id == id) else {
+                        capture_events.push((
+                            capture.owner,
+                            Event::CaptureFailed {
+                                reason: CAPTURE_NO_SUCH_WINDOW,
+                            },
+                        ));
+                        return false;
+                    };
+                    // A window with nothing committed yet is copied once it
+                    // has something to show.
+                    let due = capture.fresh || committed.contains(&id);
+                    window
+                        .latched
+                        .as_ref()
+                        .filter(|_| due)
+                        .map(|src| capture::copy_window(src, window.size, &mut capture.buffer))
+                }
+            };
+
+            let Some((width, height)) = copied else {
+                return true;
+            };
+            capture.fresh = false;
+            capture_events.push((
+                capture.owner,
+                Event::Captured {
+                    frame,
+                    width,
+                    height,
+                },
+            ));
+            capture.continuous
+        });
+    }
+
+    /// Set the pointer's image over a window's buffer, or hide the pointer
+    /// there with `None`.
+    pub fn set_cursor(&mut self, window: u64, image: Option<CursorImage>) {
+        let cursor = match image {
+            Some(image) => {
+                let id = self.next_cursor_id;
+                self.next_cursor_id += 1;
+                WindowCursor::Custom(id, image)
+            }
+            None => WindowCursor::Hidden,
+        };
+        if let Some(w) = self.window_mut(window) {
+            w.cursor = cursor;
+        }
+    }
+
+    /// Destroy a window, whether its client asked or its close button was
+    /// clicked, releasing its buffers.
+    fn destroy(&mut self, window: u64, events: &mut Vec<Event<'static>>) {
+        let Some(index) = self.windows.iter().position(|w| w.id == window) else {
+            return;
+        };
+        let removed = self.windows.remove(index);
+        for buffer in [removed.pending.as_ref(), removed.latched.as_ref()]
+            .into_iter()
+            .flatten()
+        {
+            events.push(Event::BufferReleased {
+                window,
+                buffer: buffer.id,
+            });
+        }
+        events.push(Event::Closed { window });
+        if removed.is_shown(self.workspace) && !removed.frame().is_empty() {
+            self.mark_dirty(removed.frame());
+        }
+        self.tile_order.retain(|&id| id != window);
+        if self.grab == Some(window) {
+            self.grab = None;
+        }
+        if self
+            .decoration_grab
+            .is_some_and(|grab| grab.window() == window)
+        {
+            self.decoration_grab = None;
+        }
+        if self.focused == Some(window) {
+            self.refocus(events);
+        }
+        events.extend(self.retile(removed.workspace));
+    }
+
+    /// Run one compositor tick: composite every dirty region, present it,
+    /// make the copies captures are due, and report the commits the tick
+    /// consumed.
     pub fn tick(&mut self) -> Vec<Event<'static>> {
-        self.composite();
+        let painted = self.composite();
         self.frame += 1;
 
         let frame = self.frame;
         let mut events = Vec::new();
+        let mut committed = Vec::new();
         for window in &mut self.windows {
             if window.awaiting_frame {
                 window.awaiting_frame = false;
+                committed.push(window.id);
                 events.push(Event::FrameDone {
                     window: window.id,
                     frame,
                 });
             }
         }
+        if !self.captures.is_empty() {
+            self.run_captures(painted, &committed);
+        }
         events
     }
 
-    fn composite(&mut self) {
-        if self.dirty_regions.is_empty() {
-            return;
-        }
-
-        let Some(target) = self.target.as_mut() else {
+    /// Composite every dirty region, then bring the cursor up to date, and
+    /// flush everything that changed. Returns whether any window or the
+    /// background was repainted.
+    fn composite(&mut self) -> bool {
+        let hovered = self.pointer_target().map(|(window, _, _)| window);
+        let pointer = self.pointer;
+        let Self {
+            windows,
+            dirty_regions,
+            target,
+            focused,
+            cursor,
+            default_cursor,
+            workspace,
+            ..
+        } = self;
+
+        let Some(target) = target.as_mut() else {
             // No display: drop the damage rather than accumulating it
             // forever. Whatever a client draws while headless is simply
             // never presented.
-            self.dirty_regions.clear();
-            return;
+            dirty_regions.clear();
+            return false;
         };
 
+        let image = cursor_image(windows, default_cursor, hovered);
+        let cursor_changed = cursor.needs_update(image.map(|(id, _)| id), pointer);
+        let painted = !dirty_regions.is_empty();
+        if !painted && !cursor_changed {
+            return false;
+        }
+
         let screen = Rect {
             x: 0,
             y: 0,
             width: target.width(),
             height: target.height(),
         };
+        let focused = *focused;
+        let mut flushes = Vec::new();
 
-        for i in 0..self.dirty_regions.len() {
-            let Some(dirty_rect) = self.dirty_regions[i].intersection(&screen) else {
+        // A software cursor must come off the screen before anything is
+        // painted under it, or it would be saved as the background.
+        if cursor_changed || dirty_regions.iter().any(|rect| cursor.overlaps(rect)) {
+            flushes.extend(cursor.erase(target));
+        }
+
+        for dirty_rect in dirty_regions.iter() {
+            let Some(dirty_rect) = dirty_rect.intersection(&screen) else {
                 continue;
             };
 
             target.fill(&dirty_rect, BACKGROUND_COLOUR);
 
-            for window in &self.windows {
-                if !window.visible || window.size.0 == 0 || window.size.1 == 0 {
+            for window in windows.iter() {
+                if !window.is_shown(*workspace) || window.size.0 == 0 || window.size.1 == 0 {
                     continue;
                 }
 
@@ -381,28 +1436,52 @@ impl<T: Target> WindowManager<T> {
                     continue;
                 };
 
+                if let Some(bar) = window.title_bar() {
+                    let active = focused == Some(window.id);
+                    decoration::draw(target, &bar, &window.title, active, &dirty_rect);
+                }
+
                 let Some(clip_rect) = window.rect().intersection(&dirty_rect) else {
                     continue;
                 };
 
                 composite_window(
                     target,
-                    window.position,
+                    window.content_origin(),
                     window.size,
-                    buffer.as_slice(),
+                    buffer,
                     &clip_rect,
                 );
             }
 
-            target.flush(&dirty_rect);
+            flushes.push(dirty_rect);
         }
+        dirty_regions.clear();
 
-        self.dirty_regions.clear();
+        flushes.extend(cursor.show(target, image, pointer));
+        for rect in &flushes {
+            target.flush(rect);
+        }
+        painted
+    }
+}
+
+/// The pointer's image, with its id, when it is over `hovered` (or over no
+/// window's buffer); `None` if that window hid it.
+fn cursor_image<'a>(
+    windows: &'a [Window],
+    default: &'a CursorImage,
+    hovered: Option<u64>,
+) -> Option<(u64, &'a CursorImage)> {
+    let window = hovered.and_then(|id| windows.iter().find(|w| w.id == id));
+    match window.map(|w| &w.cursor) {
+        None | Some(WindowCursor::Default) => Some((0, default)),
+        Some(WindowCursor::Hidden) => None,
+        Some(WindowCursor::Custom(id, image)) => Some((*id, image)),
     }
 }
 
 fn fill_buffer(buffer: &mut Attachment, rect: &Rect, colour: u32) {
-    let stride = buffer.width;
     let bounds = Rect {
         x: 0,
         y: 0,
@@ -412,16 +1491,10 @@ fn fill_buffer(buffer: &mut Attachment, rect: &Rect, colour: u32) {
     let Some(rect) = rect.intersection(&bounds) else {
         return;
     };
-    let bytes = colour.to_le_bytes();
+    let layout = buffer.layout();
     let data = buffer.as_mut_slice();
     for y in rect.y..rect.y + rect.height {
-        for x in rect.x..rect.x + rect.width {
-            let offset = ((y * stride + x) * 4) as usize;
-            if offset + 4 > data.len() {
-                continue;
-            }
-            data[offset..offset + 4].copy_from_slice(&bytes);
-        }
+        layout.fill_row(data, rect.x, y, rect.width, colour.to_le_bytes());
     }
 }
 
@@ -429,12 +1502,28 @@ fn composite_window<T: Target>(
     target: &mut T,
     window_pos: (u32, u32),
     window_size: (u32, u32),
-    buffer: &[u8],
+    buffer: &Attachment,
     clip_rect: &Rect,
 ) {
     let src_x = clip_rect.x.saturating_sub(window_pos.0);
     let src_y = clip_rect.y.saturating_sub(window_pos.1);
+    let layout = buffer.layout();
 
+    // Other formats are converted a row at a time; none of them has alpha,
+    // so every row can be copied straight to the target.
+    if layout.format != FORMAT_BGRA8888 {
+        if src_x + clip_rect.width > layout.width || src_y + clip_rect.height > layout.height {
+            return;
+        }
+        let mut row = vec![0u8; clip_rect.width as usize * 4];
+        for y in 0..clip_rect.height {
+            layout.read_row(buffer.as_slice(), src_x, src_y + y, &mut row);
+            target.write_row(clip_rect.x, clip_rect.y + y, clip_rect.width, &row);
+        }
+        return;
+    }
+
+    let buffer = buffer.as_slice();
     let opaque = is_region_opaque(
         buffer,
         src_x,
@@ -494,6 +1583,8 @@ mod tests {
     use super::*;
     use crate::target::MemoryTarget;
     use alloc::vec;
+    use compositor_protocol::{FORMAT_NV12, FORMAT_RGB565, FORMAT_XRGB8888};
+    use keymap::codes::{KEY_1, KEY_2, KEY_ENTER, KEY_J, KEY_LEFTMETA, KEY_LEFTSHIFT, KEY_T};
 
     /// Backing store for a client buffer, kept alive for the duration of a
     /// test the way a real client's shared buffer is kept alive by the
@@ -502,6 +1593,7 @@ mod tests {
         pixels: Vec<u8>,
         width: u32,
         height: u32,
+        format: u8,
     }
 
     impl ClientBuffer {
@@ -514,6 +1606,17 @@ mod tests {
                 pixels,
                 width,
                 height,
+                format: FORMAT_BGRA8888,
+            }
+        }
+
+        /// A buffer in another format, holding exactly `pixels`.
+        fn with_format(width: u32, height: u32, format: u8, pixels: Vec<u8>) -> Self {
+            Self {
+                pixels,
+                width,
+                height,
+                format,
             }
         }
 
@@ -527,7 +1630,7 @@ mod tests {
                     self.pixels.len(),
                     self.width,
                     self.height,
-                    FORMAT_BGRA8888,
+                    self.format,
                 )
             }
             .expect("valid attachment")
@@ -545,6 +1648,35 @@ mod tests {
         }
     }
 
+    /// Attach `client` to a new window at `(x, y)`, show it and commit.
+    fn show_window(
+        manager: &mut WindowManager<MemoryTarget>,
+        client: &mut ClientBuffer,
+        x: u32,
+        y: u32,
+    ) -> u64 {
+        let window = create_window(manager);
+        manager.handle_request(
+            Request::AttachBuffer {
+                window,
+                width: client.width,
+                height: client.height,
+                format: client.format,
+            },
+            Some(client.attach(0)),
+        );
+        manager.handle_request(Request::Move { window, x, y }, None);
+        manager.handle_request(
+            Request::SetVisible {
+                window,
+                visible: true,
+            },
+            None,
+        );
+        manager.handle_request(Request::Commit { window }, None);
+        window
+    }
+
     #[test]
     fn dirty_regions_coalesce_when_overlapping_or_adjacent() {
         let mut manager = manager(100, 100);
@@ -946,6 +2078,85 @@ mod tests {
         assert!(attachment.is_none());
     }
 
+    #[test]
+    fn an_nv12_buffer_needs_room_for_both_planes() {
+        let mut pixels = vec![0u8; 4 * 4];
+        // SAFETY: as above.
+        let attachment =
+            unsafe { Attachment::new(0, pixels.as_mut_ptr(), pixels.len(), 4, 4, FORMAT_NV12) };
+        assert!(attachment.is_none());
+    }
+
+    #[test]
+    fn an_xrgb_window_is_opaque_whatever_its_alpha_byte() {
+        let mut manager = manager(16, 16);
+        let mut client = ClientBuffer::with_format(2, 2, FORMAT_XRGB8888, vec![9; 16]);
+        show_window(&mut manager, &mut client, 4, 4);
+        manager.tick();
+
+        let target = manager.target.as_ref().unwrap();
+        assert_eq!(target.get_pixel(5, 5), [9, 9, 9, 255]);
+    }
+
+    #[test]
+    fn an_rgb565_window_is_expanded_to_bgra() {
+        let mut manager = manager(16, 16);
+        let red = 0xF800u16.to_le_bytes();
+        let blue = 0x001Fu16.to_le_bytes();
+        let pixels = [red, blue, red, blue].concat();
+        let mut client = ClientBuffer::with_format(2, 2, FORMAT_RGB565, pixels);
+        show_window(&mut manager, &mut client, 0, 0);
+        manager.tick();
+
+        let target = manager.target.as_ref().unwrap();
+        assert_eq!(target.get_pixel(0, 1), [0, 0, 255, 255]);
+        assert_eq!(target.get_pixel(1, 1), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn an_nv12_window_is_converted_from_bt601() {
+        let mut manager = manager(16, 16);
+        // A 4x2 buffer: white on the left, black on the right, then one
+        // neutral Cb/Cr pair for each 2x2 block.
+        let pixels = vec![235, 235, 16, 16, 235, 235, 16, 16, 128, 128, 128, 128];
+        let mut client = ClientBuffer::with_format(4, 2, FORMAT_NV12, pixels);
+        show_window(&mut manager, &mut client, 2, 2);
+        manager.tick();
+
+        let target = manager.target.as_ref().unwrap();
+        assert_eq!(target.get_pixel(3, 3), [255, 255, 255, 255]);
+        assert_eq!(target.get_pixel(4, 3), [0, 0, 0, 255]);
+        // The window covers only its own pixels.
+        assert_eq!(target.get_pixel(6, 3), BACKGROUND_COLOUR.to_le_bytes());
+    }
+
+    #[test]
+    fn fill_encodes_into_the_buffer_format() {
+        let mut manager = manager(16, 16);
+        let mut client = ClientBuffer::with_format(4, 4, FORMAT_RGB565, vec![0; 32]);
+        let window = show_window(&mut manager, &mut client, 0, 0);
+        manager.handle_request(
+            Request::Fill {
+                window,
+                rect: Rect {
+                    x: 0,
+                    y: 0,
+                    width: 2,
+                    height: 1,
+                },
+                colour: 0xFF00FF00,
+            },
+            None,
+        );
+        manager.handle_request(Request::Commit { window }, None);
+        manager.tick();
+
+        assert_eq!(client.pixels[..4], [0xE0, 0x07, 0xE0, 0x07]);
+        let target = manager.target.as_ref().unwrap();
+        assert_eq!(target.get_pixel(1, 0), [0, 255, 0, 255]);
+        assert_eq!(target.get_pixel(2, 0), [0, 0, 0, 255]);
+    }
+
     #[test]
     fn a_commit_with_no_attached_buffer_does_nothing() {
         let mut manager = manager(64, 64);
@@ -995,4 +2206,940 @@ mod tests {
         );
         assert_eq!(manager.screen_size(), (0, 0));
     }
+
+    #[test]
+    fn a_window_that_is_shown_takes_the_keyboard_focus() {
+        let mut manager = manager(64, 64);
+        assert!(manager.key(30, 1).is_empty());
+
+        let mut first = ClientBuffer::new(8, 8, [1, 1, 1, 255]);
+        let mut second = ClientBuffer::new(8, 8, [2, 2, 2, 255]);
+        let a = show_window(&mut manager, &mut first, 0, 0);
+        assert_eq!(manager.focused(), Some(a));
+
+        let b = create_window(&mut manager);
+        assert_eq!(
+            manager.handle_request(
+                Request::SetVisible {
+                    window: b,
+                    visible: true,
+                },
+                None,
+            ),
+            vec![Event::FocusOut { window: a }, Event::FocusIn { window: b }]
+        );
+        manager.handle_request(
+            Request::AttachBuffer {
+                window: b,
+                width: 8,
+                height: 8,
+                format: FORMAT_BGRA8888,
+            },
+            Some(second.attach(0)),
+        );
+        assert_eq!(
+            manager.key(30, 1),
+            vec![Event::Key {
+                window: b,
+                code: 30,
+                value: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn hiding_or_destroying_the_focused_window_passes_the_focus_on() {
+        let mut manager = manager(64, 64);
+        let mut first = ClientBuffer::new(8, 8, [1, 1, 1, 255]);
+        let mut second = ClientBuffer::new(8, 8, [2, 2, 2, 255]);
+        let a = show_window(&mut manager, &mut first, 0, 0);
+        let b = show_window(&mut manager, &mut second, 16, 0);
+
+        assert_eq!(
+            manager.handle_request(
+                Request::SetVisible {
+                    window: b,
+                    visible: false,
+                },
+                None,
+            ),
+            vec![Event::FocusOut { window: b }, Event::FocusIn { window: a }]
+        );
+
+        let events = manager.handle_request(Request::DestroyWindow { window: a }, None);
+        assert!(!events.contains(&Event::FocusOut { window: a }));
+        assert_eq!(manager.focused(), None);
+        assert!(manager.key(30, 1).is_empty());
+    }
+
+    #[test]
+    fn pointer_events_go_to_the_topmost_window_under_the_pointer() {
+        let mut manager = manager(64, 64);
+        let mut below = ClientBuffer::new(32, 32, [1, 1, 1, 255]);
+        let mut above = ClientBuffer::new(16, 16, [2, 2, 2, 255]);
+        let a = show_window(&mut manager, &mut below, 0, 0);
+        let b = show_window(&mut manager, &mut above, 8, 8);
+
+        assert_eq!(manager.window_at(10, 10), Some(b));
+        assert_eq!(manager.window_at(2, 2), Some(a));
+        assert_eq!(manager.window_at(40, 40), None);
+
+        assert_eq!(
+            manager.move_pointer(10, 12),
+            vec![Event::PointerMotion {
+                window: b,
+                x: 2,
+                y: 4,
+            }]
+        );
+        assert_eq!(
+            manager.move_pointer_by(-8, -8),
+            vec![Event::PointerMotion {
+                window: a,
+                x: 2,
+                y: 4,
+            }]
+        );
+        // Over the background nobody hears about it.
+        assert!(manager.move_pointer(40, 40).is_empty());
+        assert!(manager.pointer_wheel(1).is_empty());
+    }
+
+    #[test]
+    fn the_pointer_stays_on_screen() {
+        let mut manager = manager(64, 48);
+        manager.move_pointer_by(-10, -10);
+        assert_eq!(manager.pointer_position(), (0, 0));
+        manager.move_pointer(1000, 1000);
+        assert_eq!(manager.pointer_position(), (63, 47));
+    }
+
+    #[test]
+    fn clicking_focuses_and_grabs_the_pointer_until_release() {
+        let mut manager = manager(64, 64);
+        let mut first = ClientBuffer::new(16, 16, [1, 1, 1, 255]);
+        let mut second = ClientBuffer::new(16, 16, [2, 2, 2, 255]);
+        let a = show_window(&mut manager, &mut first, 0, 0);
+        let b = show_window(&mut manager, &mut second, 32, 0);
+        assert_eq!(manager.focused(), Some(b));
+
+        manager.move_pointer(4, 4);
+        assert_eq!(
+            manager.pointer_button(0x110, true),
+            vec![
+                Event::FocusOut { window: b },
+                Event::FocusIn { window: a },
+                Event::PointerButton {
+                    window: a,
+                    x: 4,
+                    y: 4,
+                    button: 0x110,
+                    pressed: true,
+                },
+            ]
+        );
+
+        // Dragging over the other window: still `a`, in `a`'s coordinates.
+        assert_eq!(
+            manager.move_pointer(36, 4),
+            vec![Event::PointerMotion {
+                window: a,
+                x: 36,
+                y: 4,
+            }]
+        );
+        assert_eq!(
+            manager.pointer_button(0x110, false),
+            vec![Event::PointerButton {
+                window: a,
+                x: 36,
+                y: 4,
+                button: 0x110,
+                pressed: false,
+            }]
+        );
+        assert_eq!(
+            manager.pointer_wheel(-1),
+            vec![Event::PointerWheel {
+                window: b,
+                x: 4,
+                y: 4,
+                delta: -1,
+            }]
+        );
+        assert_eq!(manager.focused(), Some(a));
+    }
+
+    fn decorate(manager: &mut WindowManager<MemoryTarget>, window: u64) {
+        manager.handle_request(
+            Request::SetDecorated {
+                window,
+                decorated: true,
+            },
+            None,
+        );
+    }
+
+    #[test]
+    fn raising_and_lowering_restacks_a_window() {
+        let mut manager = manager(64, 64);
+        let mut first = ClientBuffer::new(16, 16, [1, 1, 1, 255]);
+        let mut second = ClientBuffer::new(16, 16, [2, 2, 2, 255]);
+        let a = show_window(&mut manager, &mut first, 0, 0);
+        let b = show_window(&mut manager, &mut second, 8, 8);
+        assert_eq!(manager.stacking_order(), vec![a, b]);
+        assert_eq!(manager.window_at(10, 10), Some(b));
+
+        manager.handle_request(Request::Raise { window: a }, None);
+        assert_eq!(manager.stacking_order(), vec![b, a]);
+        assert_eq!(manager.window_at(10, 10), Some(a));
+        manager.tick();
+        assert_eq!(
+            manager.target.as_ref().unwrap().get_pixel(10, 10),
+            [1, 1, 1, 255]
+        );
+
+        manager.handle_request(Request::Lower { window: a }, None);
+        assert_eq!(manager.stacking_order(), vec![a, b]);
+    }
+
+    #[test]
+    fn always_on_top_windows_stay_above_the_rest() {
+        let mut manager = manager(64, 64);
+        let mut first = ClientBuffer::new(16, 16, [1, 1, 1, 255]);
+        let mut second = ClientBuffer::new(16, 16, [2, 2, 2, 255]);
+        let a = show_window(&mut manager, &mut first, 0, 0);
+        let b = show_window(&mut manager, &mut second, 0, 0);
+
+        manager.handle_request(
+            Request::SetAlwaysOnTop {
+                window: a,
+                on_top: true,
+            },
+            None,
+        );
+        assert_eq!(manager.stacking_order(), vec![b, a]);
+
+        // Neither raising an ordinary window nor creating one puts it above
+        // the always-on-top layer, and lowering stays within the layer.
+        manager.handle_request(Request::Raise { window: b }, None);
+        let c = create_window(&mut manager);
+        manager.handle_request(Request::Lower { window: a }, None);
+        assert_eq!(manager.stacking_order(), vec![b, c, a]);
+
+        manager.handle_request(
+            Request::SetAlwaysOnTop {
+                window: a,
+                on_top: false,
+            },
+            None,
+        );
+        manager.handle_request(Request::Lower { window: a }, None);
+        assert_eq!(manager.stacking_order(), vec![a, b, c]);
+    }
+
+    #[test]
+    fn clicking_a_window_raises_it() {
+        let mut manager = manager(64, 64);
+        let mut first = ClientBuffer::new(16, 16, [1, 1, 1, 255]);
+        let mut second = ClientBuffer::new(16, 16, [2, 2, 2, 255]);
+        let a = show_window(&mut manager, &mut first, 0, 0);
+        let b = show_window(&mut manager, &mut second, 8, 8);
+
+        manager.move_pointer(2, 2);
+        manager.pointer_button(0x110, true);
+        manager.pointer_button(0x110, false);
+        assert_eq!(manager.stacking_order(), vec![b, a]);
+        assert_eq!(manager.window_at(10, 10), Some(a));
+    }
+
+    #[test]
+    fn a_decorated_window_has_a_title_bar_above_its_buffer() {
+        let mut manager = manager(64, 64);
+        let mut client = ClientBuffer::new(32, 16, [9, 9, 9, 255]);
+        let window = show_window(&mut manager, &mut client, 0, 0);
+        decorate(&mut manager, window);
+        manager.tick();
+
+        let target = manager.target.as_ref().unwrap();
+        assert_eq!(
+            target.get_pixel(0, 0),
+            decoration::ACTIVE_COLOUR.to_le_bytes()
+        );
+        assert_eq!(target.get_pixel(0, TITLE_BAR_HEIGHT), [9, 9, 9, 255]);
+        // The buffer moved down by the bar's height.
+        assert_eq!(
+            target.get_pixel(0, TITLE_BAR_HEIGHT + 16),
+            BACKGROUND_COLOUR.to_le_bytes()
+        );
+
+        // The title bar is hit, but the client only hears about its buffer.
+        assert_eq!(manager.window_at(2, 2), Some(window));
+        assert!(manager.move_pointer(2, 2).is_empty());
+        assert_eq!(
+            manager.move_pointer(2, TITLE_BAR_HEIGHT + 1),
+            vec![Event::PointerMotion { window, x: 2, y: 1 }]
+        );
+    }
+
+    #[test]
+    fn the_title_bar_shows_the_title_and_the_focus() {
+        let mut manager = manager(64, 64);
+        let mut first = ClientBuffer::new(48, 8, [1, 1, 1, 255]);
+        let mut second = ClientBuffer::new(8, 8, [2, 2, 2, 255]);
+        let a = show_window(&mut manager, &mut first, 0, 0);
+        decorate(&mut manager, a);
+        manager.tick();
+        // The top of an 'I' sits where the text starts: 8 pixels in, 5
+        // down, with the font scaled by two.
+        let text = decoration::TEXT_COLOUR.to_le_bytes();
+        assert_ne!(manager.target.as_ref().unwrap().get_pixel(10, 5), text);
+
+        manager.handle_request(
+            Request::SetTitle {
+                window: a,
+                title: "I",
+            },
+            None,
+        );
+        manager.tick();
+        assert_eq!(manager.target.as_ref().unwrap().get_pixel(10, 5), text);
+
+        // Focus moving to another window repaints the bar as inactive.
+        show_window(&mut manager, &mut second, 56, 56);
+        manager.tick();
+        assert_eq!(
+            manager.target.as_ref().unwrap().get_pixel(0, 0),
+            decoration::INACTIVE_COLOUR.to_le_bytes()
+        );
+    }
+
+    #[test]
+    fn dragging_the_title_bar_moves_the_window() {
+        let mut manager = manager(128, 128);
+        let mut client = ClientBuffer::new(32, 16, [9, 9, 9, 255]);
+        let window = show_window(&mut manager, &mut client, 10, 10);
+        decorate(&mut manager, window);
+
+        manager.move_pointer(15, 12);
+        assert!(manager.pointer_button(0x110, true).is_empty());
+        assert!(manager.move_pointer(45, 52).is_empty());
+        assert!(manager.pointer_button(0x110, false).is_empty());
+        manager.tick();
+
+        assert_eq!(manager.window_at(40, 50), Some(window));
+        assert_eq!(manager.window_at(12, 12), None);
+        let target = manager.target.as_ref().unwrap();
+        assert_eq!(target.get_pixel(40, 50 + TITLE_BAR_HEIGHT), [9, 9, 9, 255]);
+
+        // Once released, motion no longer drags.
+        manager.move_pointer(100, 100);
+        assert_eq!(manager.window_at(40, 50), Some(window));
+    }
+
+    #[test]
+    fn the_close_button_closes_the_window_when_released_over_it() {
+        let mut manager = manager(64, 64);
+        let mut client = ClientBuffer::new(32, 16, [9, 9, 9, 255]);
+        let window = show_window(&mut manager, &mut client, 0, 0);
+        decorate(&mut manager, window);
+        let close = decoration::close_button(&decoration::title_bar((0, 0), 32));
+
+        // Pressing and sliding off cancels.
+        manager.move_pointer(close.x + 1, close.y + 1);
+        manager.pointer_button(0x110, true);
+        manager.move_pointer(2, 2);
+        assert!(manager.pointer_button(0x110, false).is_empty());
+        assert_eq!(manager.window_at(2, 2), Some(window));
+
+        manager.move_pointer(close.x + 1, close.y + 1);
+        assert!(manager.pointer_button(0x110, true).is_empty());
+        assert_eq!(
+            manager.pointer_button(0x110, false),
+            vec![
+                Event::BufferReleased { window, buffer: 0 },
+                Event::Closed { window },
+            ]
+        );
+        assert_eq!(manager.stacking_order(), vec![]);
+        assert_eq!(manager.focused(), None);
+    }
+
+    #[test]
+    fn a_resize_is_configured_once_until_acknowledged() {
+        let mut manager = manager(256, 256);
+        let mut client = ClientBuffer::new(16, 16, [1, 1, 1, 255]);
+        let window = show_window(&mut manager, &mut client, 0, 0);
+
+        let resize = |width, height| Request::Resize {
+            window,
+            width,
+            height,
+        };
+        assert_eq!(
+            manager.handle_request(resize(64, 32), None),
+            vec![Event::Configure {
+                window,
+                serial: 1,
+                width: 64,
+                height: 32,
+            }]
+        );
+        // Until the ack, later sizes collapse into the latest.
+        assert!(manager.handle_request(resize(80, 40), None).is_empty());
+        assert!(manager.handle_request(resize(96, 48), None).is_empty());
+        // An ack for some other serial is ignored.
+        let ack = |serial| Request::AckConfigure { window, serial };
+        assert!(manager.handle_request(ack(9), None).is_empty());
+        assert_eq!(
+            manager.handle_request(ack(1), None),
+            vec![Event::Configure {
+                window,
+                serial: 2,
+                width: 96,
+                height: 48,
+            }]
+        );
+        assert!(manager.handle_request(ack(2), None).is_empty());
+        // Asking again for the size already configured is a no-op.
+        assert!(manager.handle_request(resize(96, 48), None).is_empty());
+    }
+
+    #[test]
+    fn configured_sizes_are_clamped_to_the_screen() {
+        let mut manager = manager(64, 48);
+        let window = create_window(&mut manager);
+        assert_eq!(
+            manager.configure(window, 1000, 0),
+            vec![Event::Configure {
+                window,
+                serial: 1,
+                width: 64,
+                height: MIN_WINDOW_SIZE,
+            }]
+        );
+    }
+
+    #[test]
+    fn the_old_buffer_stays_on_screen_until_the_resized_one_is_committed() {
+        let mut manager = manager(64, 64);
+        let mut small = ClientBuffer::new(8, 8, [1, 1, 1, 255]);
+        let mut large = ClientBuffer::new(16, 16, [2, 2, 2, 255]);
+        let window = show_window(&mut manager, &mut small, 0, 0);
+        manager.tick();
+
+        manager.handle_request(
+            Request::Resize {
+                window,
+                width: 16,
+                height: 16,
+            },
+            None,
+        );
+        manager.handle_request(Request::AckConfigure { window, serial: 1 }, None);
+        manager.handle_request(
+            Request::AttachBuffer {
+                window,
+                width: 16,
+                height: 16,
+                format: FORMAT_BGRA8888,
+            },
+            Some(large.attach(1)),
+        );
+        // Damage against the old geometry, sent before the resize commit,
+        // must not leave stale pixels behind.
+        manager.handle_request(
+            Request::Damage {
+                window,
+                rect: Rect {
+                    x: 0,
+                    y: 0,
+                    width: 4,
+                    height: 4,
+                },
+            },
+            None,
+        );
+        manager.tick();
+        assert_eq!(
+            manager.target.as_ref().unwrap().get_pixel(4, 4),
+            [1, 1, 1, 255]
+        );
+        assert_eq!(
+            manager.target.as_ref().unwrap().get_pixel(12, 12),
+            BACKGROUND_COLOUR.to_le_bytes()
+        );
+
+        manager.handle_request(Request::Commit { window }, None);
+        manager.tick();
+        let target = manager.target.as_ref().unwrap();
+        assert_eq!(target.get_pixel(4, 4), [2, 2, 2, 255]);
+        assert_eq!(target.get_pixel(12, 12), [2, 2, 2, 255]);
+    }
+
+    #[test]
+    fn damage_beyond_the_buffer_is_not_repainted() {
+        let mut manager = manager(64, 64);
+        let mut client = ClientBuffer::new(8, 8, [1, 1, 1, 255]);
+        let window = show_window(&mut manager, &mut client, 0, 0);
+        manager.tick();
+        manager.target.as_mut().unwrap().flushed.clear();
+
+        manager.handle_request(
+            Request::Damage {
+                window,
+                rect: Rect {
+                    x: 4,
+                    y: 4,
+                    width: 100,
+                    height: 100,
+                },
+            },
+            None,
+        );
+        manager.handle_request(Request::Commit { window }, None);
+        manager.tick();
+        assert_eq!(
+            manager.target.as_ref().unwrap().flushed,
+            vec![Rect {
+                x: 4,
+                y: 4,
+                width: 4,
+                height: 4,
+            }]
+        );
+    }
+
+    #[test]
+    fn dragging_the_resize_grip_configures_the_window() {
+        let mut manager = manager(256, 256);
+        let mut client = ClientBuffer::new(32, 32, [1, 1, 1, 255]);
+        let window = show_window(&mut manager, &mut client, 0, 0);
+        decorate(&mut manager, window);
+        let corner = (31, TITLE_BAR_HEIGHT + 31);
+
+        manager.move_pointer(corner.0, corner.1);
+        assert!(manager.pointer_button(0x110, true).is_empty());
+        assert_eq!(
+            manager.move_pointer(corner.0 + 10, corner.1 + 20),
+            vec![Event::Configure {
+                window,
+                serial: 1,
+                width: 42,
+                height: 52,
+            }]
+        );
+        // Not acknowledged yet, so the drag only records the latest size.
+        assert!(
+            manager
+                .move_pointer(corner.0 + 30, corner.1 + 30)
+                .is_empty()
+        );
+        assert!(manager.pointer_button(0x110, false).is_empty());
+        assert_eq!(
+            manager.handle_request(Request::AckConfigure { window, serial: 1 }, None),
+            vec![Event::Configure {
+                window,
+                serial: 2,
+                width: 62,
+                height: 62,
+            }]
+        );
+    }
+
+    fn software_cursor_manager(width: u32, height: u32) -> WindowManager<MemoryTarget> {
+        let mut target = MemoryTarget::new(width, height);
+        target.hardware_cursor = false;
+        WindowManager::new(Some(target))
+    }
+
+    #[test]
+    fn moving_a_software_cursor_repaints_only_the_cursor() {
+        let mut manager = software_cursor_manager(100, 100);
+        let mut client = ClientBuffer::new(100, 100, [7, 7, 7, 255]);
+        show_window(&mut manager, &mut client, 0, 0);
+        manager.move_pointer(10, 10);
+        manager.tick();
+        let target = manager.target.as_mut().unwrap();
+        assert_eq!(target.get_pixel(10, 10), [0, 0, 0, 255]);
+        target.flushed.clear();
+
+        manager.move_pointer(50, 50);
+        manager.tick();
+
+        let arrow = CursorImage::arrow();
+        let at = |x, y| Rect {
+            x,
+            y,
+            width: arrow.width,
+            height: arrow.height,
+        };
+        let target = manager.target.as_ref().unwrap();
+        assert_eq!(target.flushed, vec![at(10, 10), at(50, 50)]);
+        assert_eq!(target.get_pixel(10, 10), [7, 7, 7, 255]);
+        assert_eq!(target.get_pixel(50, 50), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn windows_repainted_under_a_software_cursor_leave_it_on_top() {
+        let mut manager = software_cursor_manager(100, 100);
+        let mut client = ClientBuffer::new(100, 100, [7, 7, 7, 255]);
+        let window = show_window(&mut manager, &mut client, 0, 0);
+        manager.move_pointer(10, 10);
+        manager.tick();
+
+        for pixel in client.pixels.chunks_mut(4) {
+            pixel.copy_from_slice(&[9, 9, 9, 255]);
+        }
+        manager.handle_request(
+            Request::Damage {
+                window,
+                rect: Rect {
+                    x: 0,
+                    y: 0,
+                    width: 100,
+                    height: 100,
+                },
+            },
+            None,
+        );
+        manager.handle_request(Request::Commit { window }, None);
+        manager.tick();
+        let target = manager.target.as_ref().unwrap();
+        assert_eq!(target.get_pixel(10, 10), [0, 0, 0, 255]);
+
+        // What the cursor saved is the repainted window, not the old one.
+        manager.move_pointer(50, 50);
+        manager.tick();
+        let target = manager.target.as_ref().unwrap();
+        assert_eq!(target.get_pixel(10, 10), [9, 9, 9, 255]);
+    }
+
+    #[test]
+    fn the_cursor_is_the_one_set_for_the_window_under_it() {
+        let mut manager = manager(100, 100);
+        let mut client = ClientBuffer::new(40, 40, [7, 7, 7, 255]);
+        let window = show_window(&mut manager, &mut client, 0, 0);
+        let image = CursorImage::new(8, 8, (4, 4), vec![0xFF; 8 * 8 * 4]).unwrap();
+        manager.set_cursor(window, Some(image));
+
+        manager.move_pointer(20, 20);
+        manager.tick();
+        let target = manager.target.as_ref().unwrap();
+        assert_eq!(target.cursor_size, Some((8, 8)));
+        assert_eq!(target.cursor_position, (20, 20));
+
+        manager.move_pointer(60, 60);
+        manager.tick();
+        let arrow = CursorImage::arrow();
+        let target = manager.target.as_ref().unwrap();
+        assert_eq!(target.cursor_size, Some((arrow.width, arrow.height)));
+
+        let hide = Request::SetCursor {
+            window,
+            width: 0,
+            height: 0,
+            hot_x: 0,
+            hot_y: 0,
+        };
+        manager.handle_request(hide, None);
+        manager.move_pointer(20, 20);
+        manager.tick();
+        assert_eq!(manager.target.as_ref().unwrap().cursor_size, None);
+
+        manager.handle_request(Request::ResetCursor { window }, None);
+        manager.tick();
+        let target = manager.target.as_ref().unwrap();
+        assert_eq!(target.cursor_size, Some((arrow.width, arrow.height)));
+    }
+
+    fn pixel_at(buffer: &ClientBuffer, x: u32, y: u32) -> [u8; 4] {
+        let offset = ((y * buffer.width + x) * 4) as usize;
+        buffer.pixels[offset..offset + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn a_screen_capture_copies_the_next_frame_once() {
+        let mut manager = manager(20, 20);
+        let mut client = ClientBuffer::new(10, 10, [1, 2, 3, 255]);
+        show_window(&mut manager, &mut client, 5, 5);
+        let mut capture = ClientBuffer::new(20, 20, [0; 4]);
+        manager
+            .start_capture(7, Source::Screen, capture.attach(0), false)
+            .unwrap();
+
+        manager.tick();
+        let events = manager.take_capture_events();
+        assert_eq!(
+            events,
+            vec![(
+                7,
+                Event::Captured {
+                    frame: manager.frame(),
+                    width: 20,
+                    height: 20
+                }
+            )]
+        );
+        assert_eq!(pixel_at(&capture, 5, 5), [1, 2, 3, 255]);
+        assert_eq!(pixel_at(&capture, 0, 0), BACKGROUND_COLOUR.to_le_bytes());
+
+        manager.tick();
+        assert!(manager.take_capture_events().is_empty());
+        assert!(!manager.stop_capture(7));
+    }
+
+    #[test]
+    fn a_screen_capture_leaves_out_a_software_cursor() {
+        let mut manager = software_cursor_manager(20, 20);
+        let mut client = ClientBuffer::new(20, 20, [1, 2, 3, 255]);
+        show_window(&mut manager, &mut client, 0, 0);
+        manager.move_pointer(10, 10);
+        let mut capture = ClientBuffer::new(20, 20, [0; 4]);
+        manager
+            .start_capture(7, Source::Screen, capture.attach(0), false)
+            .unwrap();
+
+        manager.tick();
+        let target = manager.target.as_ref().unwrap();
+        assert_eq!(target.get_pixel(10, 10), [0, 0, 0, 255]);
+        assert_eq!(pixel_at(&capture, 10, 10), [1, 2, 3, 255]);
+    }
+
+    #[test]
+    fn a_continuous_window_capture_copies_each_commit() {
+        let mut manager = manager(20, 20);
+        let mut client = ClientBuffer::new(4, 4, [1, 2, 3, 255]);
+        let window = show_window(&mut manager, &mut client, 18, 18);
+        let mut capture = ClientBuffer::new(2, 8, [0; 4]);
+        manager
+            .start_capture(7, Source::Window(window), capture.attach(0), true)
+            .unwrap();
+
+        // Copied whole, though most of the window is off screen, but cut
+        // to the capture buffer.
+        manager.tick();
+        let captured = |frame| {
+            vec![(
+                7,
+                Event::Captured {
+                    frame,
+                    width: 2,
+                    height: 4,
+                },
+            )]
+        };
+        assert_eq!(manager.take_capture_events(), captured(manager.frame()));
+        assert_eq!(pixel_at(&capture, 1, 3), [1, 2, 3, 255]);
+
+        manager.tick();
+        assert!(manager.take_capture_events().is_empty());
+
+        for pixel in client.pixels.chunks_mut(4) {
+            pixel.copy_from_slice(&[4, 5, 6, 255]);
+        }
+        manager.handle_request(Request::Commit { window }, None);
+        manager.tick();
+        assert_eq!(manager.take_capture_events(), captured(manager.frame()));
+        assert_eq!(pixel_at(&capture, 1, 3), [4, 5, 6, 255]);
+        assert!(manager.stop_capture(7));
+    }
+
+    #[test]
+    fn a_window_capture_is_converted_to_bgra() {
+        let mut manager = manager(20, 20);
+        let pixels = 0x07E0u16.to_le_bytes().repeat(4);
+        let mut client = ClientBuffer::with_format(2, 2, FORMAT_RGB565, pixels);
+        let window = show_window(&mut manager, &mut client, 0, 0);
+        let mut capture = ClientBuffer::new(4, 4, [0; 4]);
+        manager
+            .start_capture(7, Source::Window(window), capture.attach(0), false)
+            .unwrap();
+        manager.tick();
+
+        assert_eq!(pixel_at(&capture, 1, 1), [0, 255, 0, 255]);
+        assert_eq!(pixel_at(&capture, 2, 2), [0; 4]);
+    }
+
+    #[test]
+    fn capturing_a_missing_window_or_screen_fails() {
+        let mut manager = manager(20, 20);
+        let mut capture = ClientBuffer::new(4, 4, [0; 4]);
+        let missing = manager.start_capture(7, Source::Window(99), capture.attach(0), false);
+        assert_eq!(missing, Err(CAPTURE_NO_SUCH_WINDOW));
+
+        // A window that has committed nothing is captured once it does, so
+        // this capture is still waiting when the window goes away.
+        let window = create_window(&mut manager);
+        manager
+            .start_capture(7, Source::Window(window), capture.attach(0), false)
+            .unwrap();
+        manager.tick();
+        assert!(manager.take_capture_events().is_empty());
+        manager.handle_request(Request::DestroyWindow { window }, None);
+        manager.tick();
+        let failed = Event::CaptureFailed {
+            reason: CAPTURE_NO_SUCH_WINDOW,
+        };
+        assert_eq!(manager.take_capture_events(), vec![(7, failed)]);
+
+        let mut headless = WindowManager::<MemoryTarget>::new(None);
+        let screen = headless.start_capture(7, Source::Screen, capture.attach(0), false);
+        assert_eq!(screen, Err(CAPTURE_NO_DISPLAY));
+    }
+
+    /// Press and release `code` with Super held, returning what the press
+    /// produced.
+    fn super_chord(manager: &mut WindowManager<MemoryTarget>, code: u16) -> Vec<Event<'static>> {
+        manager.key(KEY_LEFTMETA, 1);
+        let events = manager.key(code, 1);
+        assert!(manager.key(code, 0).is_empty());
+        manager.key(KEY_LEFTMETA, 0);
+        events
+    }
+
+    #[test]
+    fn switching_workspaces_hides_windows_and_moves_the_focus() {
+        let mut manager = manager(64, 64);
+        let mut first = ClientBuffer::new(8, 8, [1, 1, 1, 255]);
+        let mut second = ClientBuffer::new(8, 8, [2, 2, 2, 255]);
+        let a = show_window(&mut manager, &mut first, 0, 0);
+
+        assert_eq!(
+            super_chord(&mut manager, KEY_2),
+            vec![Event::FocusOut { window: a }]
+        );
+        assert_eq!(manager.workspace(), 1);
+        assert_eq!(manager.focused(), None);
+        assert!(manager.window_at(1, 1).is_none());
+
+        let b = show_window(&mut manager, &mut second, 0, 0);
+        assert_eq!(manager.window(b).unwrap().workspace, 1);
+        assert_eq!(
+            super_chord(&mut manager, KEY_1),
+            vec![Event::FocusOut { window: b }, Event::FocusIn { window: a }]
+        );
+        assert_eq!(manager.window_at(1, 1), Some(a));
+    }
+
+    #[test]
+    fn a_window_can_be_sent_to_another_workspace() {
+        let mut manager = manager(64, 64);
+        let mut first = ClientBuffer::new(8, 8, [1, 1, 1, 255]);
+        let mut second = ClientBuffer::new(8, 8, [2, 2, 2, 255]);
+        let a = show_window(&mut manager, &mut first, 0, 0);
+        let b = show_window(&mut manager, &mut second, 0, 0);
+
+        manager.key(KEY_LEFTSHIFT, 1);
+        assert_eq!(
+            super_chord(&mut manager, KEY_2),
+            vec![Event::FocusOut { window: b }, Event::FocusIn { window: a }]
+        );
+        assert_eq!(manager.workspace(), 0);
+        assert_eq!(manager.window(b).unwrap().workspace, 1);
+        assert_eq!(manager.window_at(1, 1), Some(a));
+    }
+
+    #[test]
+    fn tiling_places_and_sizes_windows_itself() {
+        let mut manager = manager(200, 100);
+        let mut first = ClientBuffer::new(8, 8, [1, 1, 1, 255]);
+        let mut second = ClientBuffer::new(8, 8, [2, 2, 2, 255]);
+        let a = show_window(&mut manager, &mut first, 30, 30);
+        let b = show_window(&mut manager, &mut second, 40, 40);
+
+        let configure = |window, serial, width, height| Event::Configure {
+            window,
+            serial,
+            width,
+            height,
+        };
+        assert_eq!(
+            super_chord(&mut manager, KEY_T),
+            vec![configure(a, 1, 110, 100), configure(b, 2, 90, 100)]
+        );
+        assert_eq!(manager.layout(0), Layout::Tiling);
+        assert_eq!(manager.window(a).unwrap().position, (0, 0));
+        assert_eq!(manager.window(b).unwrap().position, (110, 0));
+
+        // The client no longer decides where its window goes or how big
+        // it is.
+        manager.handle_request(
+            Request::Move {
+                window: b,
+                x: 5,
+                y: 5,
+            },
+            None,
+        );
+        let resize = Request::Resize {
+            window: b,
+            width: 20,
+            height: 20,
+        };
+        assert!(manager.handle_request(resize, None).is_empty());
+        assert_eq!(manager.window(b).unwrap().position, (110, 0));
+
+        // Closing the master gives its place to the stack.
+        manager.handle_request(Request::DestroyWindow { window: a }, None);
+        assert_eq!(manager.window(b).unwrap().position, (0, 0));
+    }
+
+    #[test]
+    fn promoting_and_cycling_follow_the_tiling_order() {
+        let mut manager = manager(200, 100);
+        let mut first = ClientBuffer::new(8, 8, [1, 1, 1, 255]);
+        let mut second = ClientBuffer::new(8, 8, [2, 2, 2, 255]);
+        let a = show_window(&mut manager, &mut first, 0, 0);
+        let b = show_window(&mut manager, &mut second, 0, 0);
+        super_chord(&mut manager, KEY_T);
+
+        super_chord(&mut manager, KEY_ENTER);
+        assert_eq!(manager.window(b).unwrap().position, (0, 0));
+        assert_eq!(manager.window(a).unwrap().position, (110, 0));
+
+        assert_eq!(
+            super_chord(&mut manager, KEY_J),
+            vec![Event::FocusOut { window: b }, Event::FocusIn { window: a }]
+        );
+        assert_eq!(
+            super_chord(&mut manager, KEY_J),
+            vec![Event::FocusOut { window: a }, Event::FocusIn { window: b }]
+        );
+    }
+
+    #[test]
+    fn a_mode_change_brings_windows_and_the_pointer_back_on_screen() {
+        let mut manager = manager(200, 100);
+        let mut client = ClientBuffer::new(8, 8, [1, 1, 1, 255]);
+        let a = show_window(&mut manager, &mut client, 150, 80);
+        manager.move_pointer(190, 90);
+        manager.tick();
+
+        *manager.target_mut().unwrap() = MemoryTarget::new(100, 50);
+        assert!(manager.screen_changed().is_empty());
+        assert_eq!(manager.screen_size(), (100, 50));
+        assert_eq!(manager.pointer, (99, 49));
+        let frame = manager.window(a).unwrap().frame();
+        assert!(frame.x + frame.width <= 100 && frame.y + frame.height <= 50);
+
+        manager.tick();
+        let target = manager.target.as_ref().unwrap();
+        assert_eq!(target.get_pixel(0, 0), BACKGROUND_COLOUR.to_le_bytes());
+        assert_eq!(target.get_pixel(frame.x + 4, frame.y + frame.height - 1), [1, 1, 1, 255]);
+
+        // A tiling workspace is laid out again at the new size.
+        manager.set_layout(0, Layout::Tiling);
+        manager.handle_request(Request::AckConfigure { window: a, serial: 1 }, None);
+        *manager.target_mut().unwrap() = MemoryTarget::new(60, 40);
+        assert_eq!(
+            manager.screen_changed(),
+            vec![Event::Configure {
+                window: a,
+                serial: 2,
+                width: 60,
+                height: 40
+            }]
+        );
+    }
 }
@@ -1,36 +1,70 @@
 //! The compositor process: client connections and the frame loop.
 
+use alloc::format;
 use alloc::vec::Vec;
-use compositor_protocol::{Event, FORMAT_BGRA8888, MAX_FRAME_SIZE, Request};
+use compositor_protocol::{
+    BufferLayout, CAPTURE_BAD_BUFFER, CAPTURE_DENIED, CAPTURE_SCREEN, Event, FORMAT_BGRA8888,
+    FORMAT_NV12, FORMAT_RGB565, FORMAT_XRGB8888, MAX_CURSOR_SIZE, MAX_FRAME_SIZE, Request,
+};
+use libpanda::mailbox::Mailbox;
 use libpanda::scheme::SchemeProvider;
-use libpanda::{buffer, environment, ipc::Channel};
+use libpanda::{Handle, buffer, env, environment, ipc::Channel, keyboard};
 use panda_abi::ErrorCode;
 use panda_abi::scheme_protocol::Request as SchemeRequest;
 
+use crate::capture::Source;
+use crate::cursor::CursorImage;
 use crate::display::Framebuffer;
+use crate::heads::{Arrangement, Heads};
+use crate::input::Input;
 use crate::manager::{Attachment, WindowManager};
 
 /// The name clients connect to over `environment::connect` to reach this
 /// compositor — see [`Compositor::serve_connects`].
 pub const SCHEME_NAME: &str = "compositor";
 
+/// The buffer formats advertised in `DisplayFormats`, preferred first.
+pub const FORMATS: [u8; 4] = [FORMAT_BGRA8888, FORMAT_XRGB8888, FORMAT_RGB565, FORMAT_NV12];
+
 /// Frame interval in milliseconds (~60 fps), as in the kernel compositor.
 pub const REFRESH_INTERVAL_MS: u64 = 16;
 
+/// The environment variable that picks how several displays share the
+/// screen: `span` (the default) or `mirror`.
+pub const HEADS_VARIABLE: &str = "COMPOSITOR_HEADS";
+
 /// A connected client and the windows it owns.
 struct Client {
+    /// Names the client to the window manager, which tracks captures by
+    /// client.
+    id: u64,
     channel: Channel,
     windows: Vec<u64>,
     /// Next attach sequence number per window.
     buffer_ids: Vec<(u64, u64)>,
+    /// May capture the screen and other clients' windows, not just its own.
+    may_capture: bool,
+    /// The buffer of the client's capture, held until the capture ends,
+    /// and whether the capture is continuous.
+    capture: Option<(Handle, bool)>,
 }
 
 impl Client {
-    fn new(channel: Channel) -> Self {
+    fn new(id: u64, channel: Channel, may_capture: bool) -> Self {
         Self {
+            id,
             channel,
             windows: Vec::new(),
             buffer_ids: Vec::new(),
+            may_capture,
+            capture: None,
+        }
+    }
+
+    /// Let go of the capture buffer.
+    fn end_capture(&mut self) {
+        if let Some((handle, _)) = self.capture.take() {
+            let _ = libpanda::sys::buffer::free(handle);
         }
     }
 
@@ -60,12 +94,26 @@ impl Client {
         // so events are dropped rather than blocking on a full queue.
         let _ = self.channel.try_send(&frame[..len]);
     }
+
+    /// As [`Client::send`], with `attach` as the event's attachment.
+    fn send_with_handle(&self, event: Event<'_>, attach: Handle) {
+        let mut frame = [0u8; MAX_FRAME_SIZE];
+        let Some(len) = event.encode(&mut frame) else {
+            environment::log("compositor: could not encode an event");
+            return;
+        };
+        let _ = self.channel.try_send_with_handle(&frame[..len], attach);
+    }
 }
 
 /// The compositor service.
 pub struct Compositor {
-    manager: WindowManager<Framebuffer>,
+    manager: WindowManager<Heads<Framebuffer>>,
+    /// Hears `EVENT_DISPLAY_CHANGED` from the displays.
+    display_events: Option<Mailbox>,
     clients: Vec<Client>,
+    next_client_id: u64,
+    input: Input,
     /// This compositor's own endpoint of the `compositor:` scheme
     /// registration, if it managed to register one. `None` for a test
     /// compositor that only ever gets clients handed to it directly (e.g.
@@ -75,8 +123,8 @@ pub struct Compositor {
 }
 
 impl Compositor {
-    /// Claim the display, register the `compositor:` scheme, and start
-    /// serving.
+    /// Claim the displays, open the input devices, register the
+    /// `compositor:` scheme, and start serving.
     ///
     /// If the display is unavailable the compositor still runs: it serves
     /// windows and skips presentation (Risk 1 of the plan). Until the
@@ -90,10 +138,14 @@ impl Compositor {
     /// clients handed to it directly, which is how every current test
     /// stands one up.
     pub fn new() -> Self {
-        let target = match Framebuffer::open() {
-            Ok(framebuffer) => {
-                environment::log("compositor: claimed the display");
-                Some(framebuffer)
+        let display_events = Mailbox::create().ok();
+        let target = match Framebuffer::open_all(display_events.as_ref()) {
+            Ok(framebuffers) => {
+                environment::log(&format!(
+                    "compositor: claimed {} display(s)",
+                    framebuffers.len()
+                ));
+                Some(Heads::new(framebuffers, arrangement()))
             }
             Err(ErrorCode::Busy) => {
                 environment::log("compositor: display is busy, running without output");
@@ -116,9 +168,15 @@ impl Compositor {
             }
         };
 
+        let mut manager = WindowManager::new(target);
+        manager.set_keymap(keyboard::system_keymap());
+
         Self {
-            manager: WindowManager::new(target),
+            manager,
+            display_events,
             clients: Vec::new(),
+            next_client_id: 1,
+            input: Input::open(),
             provider,
         }
     }
@@ -132,7 +190,8 @@ impl Compositor {
     /// This is how a process gets a channel to the compositor without being
     /// spawned by it — e.g. `init` spawns the compositor and the terminal as
     /// independent siblings, and the terminal reaches the compositor with
-    /// `environment::connect("compositor:/connect")`. Any other
+    /// `environment::connect("compositor:/connect")`. Such a connection may
+    /// capture only its own windows, whatever path it names. Any other
     /// scheme-provider request kind (`Open`/`Readdir`/`Read`/`Write`) has no
     /// meaning for this scheme and is answered with an error rather than
     /// left to hang the caller.
@@ -166,7 +225,7 @@ impl Compositor {
                         // connecting process keeps its own duplicate,
                         // installed by the kernel when it received the
                         // attachment (see docs/IPC.md "Handle transfer").
-                        self.add_client(Channel::from_typed(server_handle));
+                        self.add_client(Channel::from_typed(server_handle), false);
                     }
                     Err(_) => {
                         environment::log(
@@ -196,14 +255,59 @@ impl Compositor {
         self.provider = Some(provider);
     }
 
+    /// Map the displays whose mode or monitor changed again, and lay the
+    /// screen out anew at its new size, telling every client that size.
+    fn serve_displays(&mut self) {
+        let Some(mailbox) = self.display_events else {
+            return;
+        };
+        let mut changed = Vec::new();
+        while let Some((handle, events)) = mailbox.try_recv() {
+            if events.is_display_changed() {
+                changed.push(handle);
+            }
+        }
+        let Some(heads) = self.manager.target_mut() else {
+            return;
+        };
+        if changed.is_empty() {
+            return;
+        }
+
+        for head in heads.heads_mut() {
+            if changed.contains(&head.handle()) && head.remap().is_err() {
+                environment::log("compositor: could not map a changed display again");
+            }
+        }
+        for event in self.manager.screen_changed() {
+            self.dispatch(event);
+        }
+
+        let (width, height) = self.manager.screen_size();
+        environment::log(&format!(
+            "compositor: the screen is now {}x{}",
+            width,
+            height
+        ));
+        for client in &self.clients {
+            client.send(Event::DisplayFormats {
+                width,
+                height,
+                formats: &FORMATS,
+            });
+        }
+    }
+
     /// Accept a client connection and greet it with `DisplayFormats`.
-    pub fn add_client(&mut self, channel: Channel) {
+    /// `may_capture` lets it capture the screen and other clients' windows.
+    pub fn add_client(&mut self, channel: Channel, may_capture: bool) {
         let (width, height) = self.manager.screen_size();
-        let client = Client::new(channel);
+        let client = Client::new(self.next_client_id, channel, may_capture);
+        self.next_client_id += 1;
         client.send(Event::DisplayFormats {
             width,
             height,
-            formats: &[FORMAT_BGRA8888],
+            formats: &FORMATS,
         });
         self.clients.push(client);
     }
@@ -231,6 +335,37 @@ impl Compositor {
                         height,
                         format,
                     } => map_attachment(&mut self.clients[index], window, width, height, format, attached),
+                    Request::SetCursor {
+                        window,
+                        width,
+                        height,
+                        hot_x,
+                        hot_y,
+                    } if width != 0 && height != 0 => {
+                        if let Some(image) = copy_cursor(width, height, (hot_x, hot_y), attached) {
+                            self.manager.set_cursor(window, Some(image));
+                        }
+                        continue;
+                    }
+                    Request::Capture {
+                        window,
+                        width,
+                        height,
+                        continuous,
+                    } => {
+                        self.start_capture(index, window, width, height, continuous, attached);
+                        continue;
+                    }
+                    Request::StopCapture => {
+                        let client = &mut self.clients[index];
+                        self.manager.stop_capture(client.id);
+                        client.end_capture();
+                        continue;
+                    }
+                    Request::ShareCapture => {
+                        self.share_capture(index);
+                        continue;
+                    }
                     _ => None,
                 };
 
@@ -239,38 +374,139 @@ impl Compositor {
                     if let Event::WindowCreated { window } = event {
                         self.clients[index].windows.push(window);
                     }
-                    if let Event::Closed { window } = event {
-                        self.clients[index].windows.retain(|&w| w != window);
-                    }
-                    self.clients[index].send(event);
+                    // Not necessarily to this client: showing or destroying
+                    // a window can move the focus between clients.
+                    self.dispatch(event);
                 }
             }
         }
     }
 
-    /// Composite one frame and report the commits it consumed.
-    fn tick(&mut self) {
-        for event in self.manager.tick() {
-            let window = match event {
-                Event::FrameDone { window, .. } => window,
-                _ => continue,
+    /// Start the capture a client asked for, or tell it why not.
+    fn start_capture(
+        &mut self,
+        index: usize,
+        window: u64,
+        width: u32,
+        height: u32,
+        continuous: bool,
+        attached: Option<Handle>,
+    ) {
+        let client = &mut self.clients[index];
+        self.manager.stop_capture(client.id);
+        client.end_capture();
+
+        let source = match window {
+            CAPTURE_SCREEN => Source::Screen,
+            window => Source::Window(window),
+        };
+        let allowed = client.may_capture
+            || matches!(source, Source::Window(w) if client.windows.contains(&w));
+        let result = if !allowed {
+            Err(CAPTURE_DENIED)
+        } else {
+            match attached.and_then(|handle| map_capture(width, height, handle)) {
+                Some(buffer) => self
+                    .manager
+                    .start_capture(client.id, source, buffer, continuous),
+                None => Err(CAPTURE_BAD_BUFFER),
+            }
+        };
+
+        match (result, attached) {
+            (Ok(()), Some(handle)) => client.capture = Some((handle, continuous)),
+            (result, handle) => {
+                if let Err(reason) = result {
+                    client.send(Event::CaptureFailed { reason });
+                }
+                if let Some(handle) = handle {
+                    let _ = libpanda::sys::buffer::free(handle);
+                }
+            }
+        }
+    }
+
+    /// Give a client that may capture a new connection that may too, or
+    /// tell it why not.
+    fn share_capture(&mut self, index: usize) {
+        if !self.clients[index].may_capture {
+            self.clients[index].send(Event::CaptureFailed {
+                reason: CAPTURE_DENIED,
+            });
+            return;
+        }
+        let Ok((server_handle, client_handle)) = libpanda::ipc::create_pair() else {
+            environment::log("compositor: could not create a channel pair to share capture");
+            return;
+        };
+        let shared = Channel::from_typed(client_handle);
+        self.clients[index].send_with_handle(Event::CaptureShared, shared.untyped_handle());
+        // As in `serve_connects`: the client now holds its own duplicate of
+        // `shared`, and this process's copy closes when it drops.
+        self.add_client(Channel::from_typed(server_handle), true);
+    }
+
+    /// Send each capture event to its client, letting go of the buffers of
+    /// captures that are over.
+    fn dispatch_captures(&mut self) {
+        for (owner, event) in self.manager.take_capture_events() {
+            let Some(client) = self.clients.iter_mut().find(|client| client.id == owner) else {
+                continue;
             };
-            if let Some(client) = self
-                .clients
-                .iter()
-                .find(|client| client.windows.contains(&window))
-            {
-                client.send(event);
+            client.send(event);
+            let over = match event {
+                Event::Captured { .. } => client.capture.is_some_and(|(_, continuous)| !continuous),
+                _ => true,
+            };
+            if over {
+                client.end_capture();
             }
         }
     }
 
+    /// Send an event to the client that owns its window.
+    fn dispatch(&mut self, event: Event<'_>) {
+        let Some(window) = event.window() else {
+            return;
+        };
+        let Some(client) = self
+            .clients
+            .iter_mut()
+            .find(|client| client.windows.contains(&window))
+        else {
+            return;
+        };
+        client.send(event);
+        if let Event::Closed { window } = event {
+            client.windows.retain(|&w| w != window);
+        }
+    }
+
+    /// Route whatever the keyboards and pointers have reported since the
+    /// last frame.
+    fn serve_input(&mut self) {
+        for event in self.input.poll(&mut self.manager) {
+            self.dispatch(event);
+        }
+    }
+
+    /// Composite one frame and report the commits it consumed and the
+    /// captures it made.
+    fn tick(&mut self) {
+        for event in self.manager.tick() {
+            self.dispatch(event);
+        }
+        self.dispatch_captures();
+    }
+
     /// Run the frame loop, ticking forever when `ticks` is `None`.
     pub fn run(&mut self, ticks: Option<u64>) {
         let mut remaining = ticks;
         loop {
             self.serve_connects();
+            self.serve_displays();
             self.serve_clients();
+            self.serve_input();
             self.tick();
 
             if let Some(left) = remaining.as_mut() {
@@ -285,6 +521,14 @@ impl Compositor {
     }
 }
 
+/// How the displays share the screen, from [`HEADS_VARIABLE`].
+fn arrangement() -> Arrangement {
+    match env::get(HEADS_VARIABLE).as_deref() {
+        Some("mirror") => Arrangement::Mirror,
+        _ => Arrangement::Span,
+    }
+}
+
 /// Map a client's attached buffer handle into this process and validate it
 /// against the geometry the client declared.
 fn map_attachment(
@@ -308,9 +552,11 @@ fn map_attachment(
     // The client declares the geometry; the kernel guarantees the mapping
     // is at least as large as the buffer, so validating against the
     // declared size is the check that matters (Risk 2 of the plan).
-    let len = (width as usize)
-        .checked_mul(height as usize)
-        .and_then(|pixels| pixels.checked_mul(4))?;
+    let Some(layout) = BufferLayout::new(format, width, height) else {
+        environment::log("compositor: rejecting an invalid buffer attachment");
+        return None;
+    };
+    let len = layout.size();
 
     let id = client.next_buffer_id(window);
     // SAFETY: `address` was just returned by a successful OP_BUFFER_MAP for
@@ -323,9 +569,60 @@ fn map_attachment(
     attachment
 }
 
-/// Run the compositor: claim the display, register the `compositor:`
-/// scheme, add `Channel::parent()` as a client if this process has one, and
-/// enter the frame loop.
+/// Map the buffer a client wants a capture copied into. Unlike a window's
+/// buffer it is written, not read, but the same declared geometry bounds
+/// it.
+fn map_capture(width: u32, height: u32, handle: Handle) -> Option<Attachment> {
+    let Ok(address) = buffer::map(handle) else {
+        environment::log("compositor: could not map a capture buffer");
+        return None;
+    };
+    let len = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|pixels| pixels.checked_mul(4))?;
+    // SAFETY: `address` was just returned by a successful OP_BUFFER_MAP for
+    // this process, and the compositor holds `handle` until the capture
+    // ends, so the frames stay alive.
+    unsafe { Attachment::new(0, address as *mut u8, len, width, height, FORMAT_BGRA8888) }
+}
+
+/// Copy a client's cursor image out of its attached buffer, then let the
+/// buffer go: unlike a window's buffer, the compositor doesn't keep it.
+fn copy_cursor(
+    width: u32,
+    height: u32,
+    hotspot: (u32, u32),
+    attached: Option<libpanda::Handle>,
+) -> Option<CursorImage> {
+    let Some(handle) = attached else {
+        environment::log("compositor: SetCursor arrived without a buffer handle");
+        return None;
+    };
+
+    let image = match buffer::map(handle) {
+        Ok(address) if width <= MAX_CURSOR_SIZE && height <= MAX_CURSOR_SIZE => {
+            let len = (width * height * 4) as usize;
+            // SAFETY: `address` was just returned by a successful
+            // OP_BUFFER_MAP for this process and, as for a window's buffer,
+            // the client's declared geometry is what bounds the read. The
+            // mapping outlives the handle (see `buffer::map`), so the copy
+            // is taken before the handle is freed.
+            let pixels = unsafe { core::slice::from_raw_parts(address as *const u8, len) };
+            CursorImage::new(width, height, hotspot, pixels.to_vec())
+        }
+        _ => None,
+    };
+    let _ = libpanda::sys::buffer::free(handle);
+
+    if image.is_none() {
+        environment::log("compositor: rejecting an invalid cursor image");
+    }
+    image
+}
+
+/// Run the compositor: claim the displays, open the input devices, register
+/// the `compositor:` scheme, add `Channel::parent()` as a client if this process has one, and
+/// enter the frame loop. The parent started the compositor, so it may capture.
 ///
 /// The `Channel::parent()` client exists for tests that spawn a compositor
 /// directly and want a channel to it without going through scheme discovery
@@ -341,7 +638,7 @@ pub fn run(ticks: Option<u64>) {
 
     let mut compositor = Compositor::new();
     if let Some(parent) = Channel::parent() {
-        compositor.add_client(parent);
+        compositor.add_client(parent, true);
     }
 
     environment::log("compositor: entering the frame loop");
@@ -2,6 +2,8 @@
 
 use compositor_protocol::Rect;
 
+use crate::cursor::CursorImage;
+
 /// A BGRA render target with a flushable damage region.
 ///
 /// The compositor's real target is the mapped framebuffer; the trait exists
@@ -21,11 +23,30 @@ pub trait Target {
     /// Read a pixel; out-of-bounds reads yield a transparent pixel.
     fn get_pixel(&self, x: u32, y: u32) -> [u8; 4];
 
+    /// Copy `dst.len() / 4` BGRA pixels out of a row, starting at `x`.
+    fn read_row(&self, x: u32, y: u32, dst: &mut [u8]) {
+        for (i, pixel) in dst.chunks_exact_mut(4).enumerate() {
+            pixel.copy_from_slice(&self.get_pixel(x + i as u32, y));
+        }
+    }
+
     /// Write a pixel; out-of-bounds writes are dropped.
     fn set_pixel(&mut self, x: u32, y: u32, pixel: [u8; 4]);
 
     /// Present a damaged region.
     fn flush(&mut self, rect: &Rect);
+
+    /// Whether the display scans out a cursor plane of its own. Without one
+    /// the compositor draws the cursor into the target.
+    fn has_hardware_cursor(&self) -> bool {
+        false
+    }
+
+    /// Load `image` into the hardware cursor plane, or hide the cursor.
+    fn set_hardware_cursor(&mut self, _image: Option<&CursorImage>) {}
+
+    /// Move the hardware cursor's hotspot to a screen position.
+    fn move_hardware_cursor(&mut self, _x: u32, _y: u32) {}
 }
 
 /// A `Target` over a plain byte buffer, used by the unit tests and by
@@ -39,6 +60,13 @@ pub struct MemoryTarget {
     pub width: u32,
     pub height: u32,
     pub flushed: alloc::vec::Vec<Rect>,
+    /// Pretend to have a hardware cursor plane, so the pixels are the
+    /// composited windows alone. Clear it to have the cursor drawn in.
+    pub hardware_cursor: bool,
+    /// The size of the image last loaded into the hardware cursor plane.
+    pub cursor_size: Option<(u32, u32)>,
+    /// Where the hardware cursor was last moved to.
+    pub cursor_position: (u32, u32),
 }
 
 #[cfg(any(test, feature = "test-target"))]
@@ -49,6 +77,9 @@ impl MemoryTarget {
             width,
             height,
             flushed: alloc::vec::Vec::new(),
+            hardware_cursor: true,
+            cursor_size: None,
+            cursor_position: (0, 0),
         }
     }
 
@@ -93,6 +124,14 @@ impl Target for MemoryTarget {
         self.pixels[offset..offset + 4].try_into().unwrap()
     }
 
+    fn read_row(&self, x: u32, y: u32, dst: &mut [u8]) {
+        if y >= self.height || x as usize + dst.len() / 4 > self.width as usize {
+            return;
+        }
+        let offset = self.offset(x, y);
+        dst.copy_from_slice(&self.pixels[offset..offset + dst.len()]);
+    }
+
     fn set_pixel(&mut self, x: u32, y: u32, pixel: [u8; 4]) {
         if x >= self.width || y >= self.height {
             return;
@@ -104,4 +143,16 @@ impl Target for MemoryTarget {
     fn flush(&mut self, rect: &Rect) {
         self.flushed.push(*rect);
     }
+
+    fn has_hardware_cursor(&self) -> bool {
+        self.hardware_cursor
+    }
+
+    fn set_hardware_cursor(&mut self, image: Option<&CursorImage>) {
+        self.cursor_size = image.map(|image| (image.width, image.height));
+    }
+
+    fn move_hardware_cursor(&mut self, x: u32, y: u32) {
+        self.cursor_position = (x, y);
+    }
 }
@@ -0,0 +1,193 @@
+//! Virtual workspaces and the keyboard shortcuts that drive them.
+//!
+//! Every window belongs to one of [`WORKSPACES`] workspaces, and only the
+//! current workspace's windows are shown — except always-on-top windows,
+//! which are shown on all of them. A workspace either leaves its windows
+//! where their clients put them or tiles them (see
+//! [`compositor_protocol::master_stack`]).
+//!
+//! The shortcuts are chords on the Super key, which the compositor keeps
+//! for itself: the keys of a chord never reach a client, though Super
+//! itself does. They go by what the keys mean under the keymap, not where
+//! they are: Super+T is whichever key types `t`, and a workspace's number
+//! is whichever key has that digit on it, shifted or not.
+//!
+//! | Keys               | Action                                        |
+//! |--------------------|-----------------------------------------------|
+//! | Super+1 … Super+4  | Switch to that workspace                      |
+//! | Super+Shift+1 … 4  | Send the focused window to that workspace     |
+//! | Super+T            | Toggle tiling on the current workspace        |
+//! | Super+Enter        | Make the focused window the master            |
+//! | Super+J / Super+K  | Focus the next / previous window              |
+
+use alloc::vec::Vec;
+use keymap::{KeyValue, KeyboardState, Keymap, Keysym, Modifiers};
+
+/// How many workspaces there are.
+pub const WORKSPACES: usize = 4;
+
+/// The share of a tiled workspace's width the master window gets.
+pub const MASTER_PERCENT: u32 = 55;
+
+/// How a workspace places its windows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Layout {
+    /// Where their clients asked.
+    #[default]
+    Floating,
+    /// Master/stack tiling over the whole screen.
+    Tiling,
+}
+
+/// Something a shortcut asks the window manager to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Show this workspace.
+    Switch(usize),
+    /// Move the focused window to this workspace.
+    Send(usize),
+    ToggleTiling,
+    /// Move the focused window to the front of the tiling order.
+    Promote,
+    /// Move the focus through the current workspace's windows.
+    FocusNext,
+    FocusPrevious,
+}
+
+/// What to do with a key event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filtered {
+    /// Route it to the focused window.
+    Forward,
+    /// Drop it: it belongs to a shortcut.
+    Swallow,
+    /// Drop it and carry out the shortcut.
+    Run(Action),
+}
+
+/// Recognises shortcuts in the stream of key events.
+pub struct Shortcuts {
+    /// Which modifiers are held, and what keys mean.
+    keyboard: KeyboardState,
+    /// Keys pressed as part of a shortcut and not yet released, whose
+    /// repeats and release are swallowed too.
+    held: Vec<u16>,
+}
+
+impl Shortcuts {
+    /// Shortcuts on the US layout, until [`set_keymap`](Self::set_keymap)
+    /// says otherwise.
+    pub fn new() -> Self {
+        Self {
+            keyboard: KeyboardState::new(Keymap::us()),
+            held: Vec::new(),
+        }
+    }
+
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keyboard.set_keymap(keymap);
+    }
+
+    /// Look at one key event (`value` is 0 for a release, 1 for a press
+    /// and 2 for a repeat).
+    pub fn key(&mut self, code: u16, value: u32) -> Filtered {
+        let event = self.keyboard.process(code, KeyValue::from_u32(value));
+
+        if let Some(index) = self.held.iter().position(|&held| held == code) {
+            if event.value == KeyValue::Release {
+                self.held.swap_remove(index);
+            }
+            return Filtered::Swallow;
+        }
+        if event.value != KeyValue::Press || !event.modifiers.contains(Modifiers::SUPER) {
+            return Filtered::Forward;
+        }
+
+        let action = if let Some(workspace) = self.workspace_key(code) {
+            if event.modifiers.shift() {
+                Action::Send(workspace)
+            } else {
+                Action::Switch(workspace)
+            }
+        } else {
+            match event.keysym {
+                Keysym::Enter => Action::Promote,
+                Keysym::Char(ch) => match ch.to_ascii_lowercase() {
+                    't' => Action::ToggleTiling,
+                    'j' => Action::FocusNext,
+                    'k' => Action::FocusPrevious,
+                    _ => return Filtered::Forward,
+                },
+                _ => return Filtered::Forward,
+            }
+        };
+        self.held.push(code);
+        Filtered::Run(action)
+    }
+
+    /// The workspace whose number is on key `code`, at any level: Shift
+    /// picks what the chord does, so it can't also pick the digit.
+    fn workspace_key(&self, code: u16) -> Option<usize> {
+        let levels = self.keyboard.keymap().levels(code)?;
+        levels.iter().find_map(|&keysym| match keysym {
+            Keysym::Char(digit @ '1'..='9') => {
+                let workspace = digit as usize - '1' as usize;
+                (workspace < WORKSPACES).then_some(workspace)
+            }
+            _ => None,
+        })
+    }
+}
+
+impl Default for Shortcuts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use keymap::codes::{
+        KEY_1, KEY_2, KEY_4, KEY_A, KEY_K, KEY_LEFTMETA, KEY_LEFTSHIFT, KEY_RIGHTMETA, KEY_T,
+    };
+
+    #[test]
+    fn keys_without_super_are_forwarded() {
+        let mut shortcuts = Shortcuts::new();
+        assert_eq!(shortcuts.key(KEY_1, 1), Filtered::Forward);
+        assert_eq!(shortcuts.key(KEY_1, 0), Filtered::Forward);
+    }
+
+    #[test]
+    fn a_chord_is_swallowed_until_its_key_is_released() {
+        let mut shortcuts = Shortcuts::new();
+        assert_eq!(shortcuts.key(KEY_LEFTMETA, 1), Filtered::Forward);
+        assert_eq!(shortcuts.key(KEY_2, 1), Filtered::Run(Action::Switch(1)));
+        assert_eq!(shortcuts.key(KEY_2, 2), Filtered::Swallow);
+        // Super can be let go first; the chord's key is still swallowed.
+        assert_eq!(shortcuts.key(KEY_LEFTMETA, 0), Filtered::Forward);
+        assert_eq!(shortcuts.key(KEY_2, 0), Filtered::Swallow);
+        assert_eq!(shortcuts.key(KEY_2, 1), Filtered::Forward);
+    }
+
+    #[test]
+    fn shift_sends_instead_of_switching() {
+        let mut shortcuts = Shortcuts::new();
+        shortcuts.key(KEY_RIGHTMETA, 1);
+        shortcuts.key(KEY_LEFTSHIFT, 1);
+        assert_eq!(shortcuts.key(KEY_4, 1), Filtered::Run(Action::Send(3)));
+        assert_eq!(shortcuts.key(KEY_T, 1), Filtered::Run(Action::ToggleTiling));
+        assert_eq!(shortcuts.key(KEY_A, 1), Filtered::Forward);
+    }
+
+    #[test]
+    fn letters_follow_the_keymap() {
+        let mut shortcuts = Shortcuts::new();
+        shortcuts.set_keymap(Keymap::builtin("dvorak").unwrap());
+        shortcuts.key(KEY_LEFTMETA, 1);
+        // On Dvorak, `t` is where QWERTY has `k`, and `y` where it has `t`.
+        assert_eq!(shortcuts.key(KEY_K, 1), Filtered::Run(Action::ToggleTiling));
+        assert_eq!(shortcuts.key(KEY_T, 1), Filtered::Forward);
+    }
+}
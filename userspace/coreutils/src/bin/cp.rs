@@ -0,0 +1,50 @@
+//! Copy files, and with `-r` directories and everything in them. With
+//! `-v` a table of what was copied where is sent on.
+
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+use coreutils::{Args, fail, fs, output, table, usage};
+use panda_abi::value::Value;
+
+const USAGE: &str = "cp [-rv] <source>... <dest>";
+
+libpanda::main! { |args|
+    let Ok(args) = Args::parse(&args, "rv", "") else {
+        return usage(USAGE);
+    };
+    let Some((dest, sources)) = args
+        .operands
+        .split_last()
+        .filter(|(_, sources)| !sources.is_empty())
+    else {
+        return usage(USAGE);
+    };
+    let targets = match fs::targets(sources, dest) {
+        Ok(targets) => targets,
+        Err(err) => return fail("cp", err),
+    };
+
+    let mut status = 0;
+    let mut copied = Vec::new();
+    for (from, to) in targets {
+        if let Err(err) = fs::copy(&from, &to, args.has('r'), &mut copied) {
+            status = fail("cp", err);
+        }
+    }
+
+    if args.has('v') {
+        let cells = copied
+            .into_iter()
+            .flat_map(|(from, to)| [Value::String(from), Value::String(to)])
+            .collect();
+        if output("cp", table(&["From", "To"], cells)) != 0 {
+            return 1;
+        }
+    }
+    status
+}
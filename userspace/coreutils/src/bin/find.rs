@@ -0,0 +1,104 @@
+//! Walk directories, sending on a table of every path under them, the
+//! starting points included, with what each is and its size. `-name`
+//! keeps those whose name matches a pattern with `*` and `?` in it, and
+//! `-type f` or `-type d` only files or directories.
+
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use coreutils::{fail, fs, matches, output, table, usage};
+use panda_abi::value::Value;
+
+const USAGE: &str = "find [path...] [-name <pattern>] [-type f|d]";
+
+/// What a path must be like to be found.
+#[derive(Default)]
+struct Test {
+    name: Option<String>,
+    is_dir: Option<bool>,
+}
+
+/// Add `path`, which is at `uri` and named `name`, to `cells` if it passes
+/// `test`, then everything under it if it is a directory. A directory that
+/// cannot be read is reported and walked past.
+fn walk(
+    path: &str,
+    uri: &str,
+    name: &str,
+    is_dir: bool,
+    test: &Test,
+    cells: &mut Vec<Value>,
+) -> i32 {
+    let mut status = 0;
+    let found = test
+        .name
+        .as_deref()
+        .is_none_or(|pattern| matches(pattern, name))
+        && test.is_dir.is_none_or(|want| want == is_dir);
+    if found {
+        match fs::stat(uri) {
+            Ok(stat) => cells.extend([
+                Value::String(String::from(path)),
+                Value::String(String::from(if is_dir { "dir" } else { "file" })),
+                Value::Int(stat.size as i64),
+            ]),
+            Err(err) => status = fail("find", err),
+        }
+    }
+    if is_dir {
+        match fs::read_dir(uri) {
+            Ok(entries) => {
+                for (name, is_dir) in entries {
+                    let path = fs::join(path, &name);
+                    let uri = fs::join(uri, &name);
+                    status |= walk(&path, &uri, &name, is_dir, test, cells);
+                }
+            }
+            Err(err) => status = fail("find", err),
+        }
+    }
+    status
+}
+
+libpanda::main! { |args|
+    let mut args = args.iter().skip(1).peekable();
+    let mut paths = Vec::new();
+    while let Some(path) = args.next_if(|arg| !arg.starts_with('-')) {
+        paths.push(path.clone());
+    }
+    let mut test = Test::default();
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next().map(String::as_str)) {
+            ("-name", Some(pattern)) => test.name = Some(String::from(pattern)),
+            ("-type", Some("f")) => test.is_dir = Some(false),
+            ("-type", Some("d")) => test.is_dir = Some(true),
+            _ => return usage(USAGE),
+        }
+    }
+    if paths.is_empty() {
+        paths.push(String::from("."));
+    }
+
+    let mut status = 0;
+    let mut cells = Vec::new();
+    for path in &paths {
+        let uri = fs::uri(path);
+        match fs::stat(&uri) {
+            Ok(stat) => {
+                let name = fs::split(&uri).map_or("", |(_, name)| name);
+                status |= walk(path, &uri, name, stat.is_dir, &test, &mut cells);
+            }
+            Err(err) => status = fail("find", err),
+        }
+    }
+
+    if output("find", table(&["Path", "Type", "Size"], cells)) != 0 {
+        return 1;
+    }
+    status
+}
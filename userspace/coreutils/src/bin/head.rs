@@ -0,0 +1,55 @@
+//! The first lines of files, ten unless `-n` says otherwise. On its own
+//! stdin it takes the first lines of text, or the first rows of anything
+//! else.
+
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc::string::String;
+
+use coreutils::{Args, Input, fail, fs, input, output, usage};
+use panda_abi::value::Value;
+
+const USAGE: &str = "head [-n <count>] [file...]";
+
+/// The first `count` lines of `text`.
+fn lines(text: &str, count: usize) -> String {
+    text.split_inclusive('\n').take(count).collect()
+}
+
+libpanda::main! { |args|
+    let Ok(args) = Args::parse(&args, "", "n") else {
+        return usage(USAGE);
+    };
+    let count = match args.value('n').map(str::parse::<usize>) {
+        None => 10,
+        Some(Ok(count)) => count,
+        Some(Err(_)) => return usage(USAGE),
+    };
+
+    if args.operands.is_empty() {
+        return match input() {
+            Input::Text(text) => output("head", Value::String(lines(&text, count))),
+            Input::Rows(mut rows) => {
+                rows.first(count);
+                output("head", rows.into_value())
+            }
+        };
+    }
+
+    let mut status = 0;
+    for path in &args.operands {
+        match fs::read(&fs::uri(path)) {
+            Ok(bytes) => {
+                let text = String::from_utf8_lossy(&bytes);
+                if output("head", Value::String(lines(&text, count))) != 0 {
+                    return 1;
+                }
+            }
+            Err(err) => status = fail("head", err),
+        }
+    }
+    status
+}
@@ -0,0 +1,47 @@
+//! Make directories. `-p` makes any they are in that are missing too, and
+//! is content with a directory that is already there. With `-v` a table
+//! of the directories made is sent on.
+
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use coreutils::{Args, fail, fs, output, table, usage};
+use panda_abi::value::Value;
+
+const USAGE: &str = "mkdir [-pv] <dir>...";
+
+libpanda::main! { |args|
+    let Ok(args) = Args::parse(&args, "pv", "") else {
+        return usage(USAGE);
+    };
+    if args.operands.is_empty() {
+        return usage(USAGE);
+    }
+
+    let mut status = 0;
+    let mut made = Vec::new();
+    for path in &args.operands {
+        let uri = fs::uri(path);
+        let result = if args.has('p') {
+            fs::make_dirs(&uri, &mut made)
+        } else {
+            fs::make_dir(&uri).map(|()| made.push(String::from(&uri)))
+        };
+        if let Err(err) = result {
+            status = fail("mkdir", err);
+        }
+    }
+
+    if args.has('v') {
+        let cells = made.into_iter().map(Value::String).collect();
+        if output("mkdir", table(&["Path"], cells)) != 0 {
+            return 1;
+        }
+    }
+    status
+}
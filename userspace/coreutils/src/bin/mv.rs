@@ -0,0 +1,49 @@
+//! Move files and directories. There is no rename, so each is copied
+//! whole and then removed. With `-v` a table of what went where is sent
+//! on.
+
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+use coreutils::{Args, fail, fs, output, table, usage};
+use panda_abi::value::Value;
+
+const USAGE: &str = "mv [-v] <source>... <dest>";
+
+libpanda::main! { |args|
+    let Ok(args) = Args::parse(&args, "v", "") else {
+        return usage(USAGE);
+    };
+    let Some((dest, sources)) = args
+        .operands
+        .split_last()
+        .filter(|(_, sources)| !sources.is_empty())
+    else {
+        return usage(USAGE);
+    };
+    let targets = match fs::targets(sources, dest) {
+        Ok(targets) => targets,
+        Err(err) => return fail("mv", err),
+    };
+
+    let mut status = 0;
+    let mut cells = Vec::new();
+    for (from, to) in targets {
+        // The original only goes once all of it has been copied.
+        let moved = fs::copy(&from, &to, true, &mut Vec::new())
+            .and_then(|()| fs::remove(&from, true, &mut Vec::new()));
+        match moved {
+            Ok(()) => cells.extend([Value::String(from), Value::String(to)]),
+            Err(err) => status = fail("mv", err),
+        }
+    }
+
+    if args.has('v') && output("mv", table(&["From", "To"], cells)) != 0 {
+        return 1;
+    }
+    status
+}
@@ -0,0 +1,42 @@
+//! Remove files, and with `-r` directories and everything in them. `-f`
+//! says nothing of paths that are not there. With `-v` a table of what
+//! was removed is sent on.
+
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+use coreutils::{Args, fail, fs, output, table, usage};
+use panda_abi::value::Value;
+
+const USAGE: &str = "rm [-rfv] <path>...";
+
+libpanda::main! { |args|
+    let Ok(args) = Args::parse(&args, "rfv", "") else {
+        return usage(USAGE);
+    };
+    if args.operands.is_empty() && !args.has('f') {
+        return usage(USAGE);
+    }
+
+    let mut status = 0;
+    let mut removed = Vec::new();
+    for path in &args.operands {
+        match fs::remove(&fs::uri(path), args.has('r'), &mut removed) {
+            Ok(()) => {}
+            Err(err) if err.is_not_found() && args.has('f') => {}
+            Err(err) => status = fail("rm", err),
+        }
+    }
+
+    if args.has('v') {
+        let cells = removed.into_iter().map(Value::String).collect();
+        if output("rm", table(&["Path"], cells)) != 0 {
+            return 1;
+        }
+    }
+    status
+}
@@ -0,0 +1,42 @@
+//! Describe files and directories: a table with a row for each path, of
+//! what it is and its size in bytes.
+
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use coreutils::{Args, fail, fs, output, table, usage};
+use panda_abi::value::Value;
+
+const USAGE: &str = "stat <path>...";
+
+libpanda::main! { |args|
+    let Ok(args) = Args::parse(&args, "", "") else {
+        return usage(USAGE);
+    };
+    if args.operands.is_empty() {
+        return usage(USAGE);
+    }
+
+    let mut status = 0;
+    let mut cells = Vec::new();
+    for path in &args.operands {
+        match fs::stat(&fs::uri(path)) {
+            Ok(stat) => cells.extend([
+                Value::String(path.clone()),
+                Value::String(String::from(if stat.is_dir { "dir" } else { "file" })),
+                Value::Int(stat.size as i64),
+            ]),
+            Err(err) => status = fail("stat", err),
+        }
+    }
+
+    if !cells.is_empty() && output("stat", table(&["Path", "Type", "Size"], cells)) != 0 {
+        return 1;
+    }
+    status
+}
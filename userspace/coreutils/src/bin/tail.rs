@@ -0,0 +1,57 @@
+//! The last lines of files, ten unless `-n` says otherwise. On its own
+//! stdin it takes the last lines of text, or the last rows of anything
+//! else.
+
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use coreutils::{Args, Input, fail, fs, input, output, usage};
+use panda_abi::value::Value;
+
+const USAGE: &str = "tail [-n <count>] [file...]";
+
+/// The last `count` lines of `text`.
+fn lines(text: &str, count: usize) -> String {
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    lines[lines.len().saturating_sub(count)..].concat()
+}
+
+libpanda::main! { |args|
+    let Ok(args) = Args::parse(&args, "", "n") else {
+        return usage(USAGE);
+    };
+    let count = match args.value('n').map(str::parse::<usize>) {
+        None => 10,
+        Some(Ok(count)) => count,
+        Some(Err(_)) => return usage(USAGE),
+    };
+
+    if args.operands.is_empty() {
+        return match input() {
+            Input::Text(text) => output("tail", Value::String(lines(&text, count))),
+            Input::Rows(mut rows) => {
+                rows.last(count);
+                output("tail", rows.into_value())
+            }
+        };
+    }
+
+    let mut status = 0;
+    for path in &args.operands {
+        match fs::read(&fs::uri(path)) {
+            Ok(bytes) => {
+                let text = String::from_utf8_lossy(&bytes);
+                if output("tail", Value::String(lines(&text, count))) != 0 {
+                    return 1;
+                }
+            }
+            Err(err) => status = fail("tail", err),
+        }
+    }
+    status
+}
@@ -0,0 +1,37 @@
+//! Make empty files. There are no timestamps to bring up to date, so a
+//! file that is already there is left alone.
+
+#![no_std]
+#![no_main]
+
+use coreutils::{Args, fail, fs, usage};
+use libpanda::{ErrorCode, environment, file};
+
+const USAGE: &str = "touch <file>...";
+
+libpanda::main! { |args|
+    let Ok(args) = Args::parse(&args, "", "") else {
+        return usage(USAGE);
+    };
+    if args.operands.is_empty() {
+        return usage(USAGE);
+    }
+
+    let mut status = 0;
+    for path in &args.operands {
+        let created = fs::with_parent(&fs::uri(path), |dir, name| {
+            match environment::create(dir, name, 0o644, 0) {
+                Ok(handle) => {
+                    file::close(handle);
+                    Ok(())
+                }
+                Err(ErrorCode::AlreadyExists) => Ok(()),
+                Err(err) => Err(err),
+            }
+        });
+        if let Err(err) = created {
+            status = fail("touch", err);
+        }
+    }
+    status
+}
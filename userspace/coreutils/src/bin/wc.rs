@@ -0,0 +1,65 @@
+//! Count the lines, words and bytes in files, or in stdin without any,
+//! as a table with a row for each and a total if there is more than one.
+
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use coreutils::{Args, fail, fs, output, table, usage};
+use panda_abi::value::Value;
+
+const USAGE: &str = "wc [file...]";
+
+/// How many lines, words and bytes there are in `bytes`.
+fn count(bytes: &[u8]) -> [i64; 3] {
+    let lines = bytes.iter().filter(|&&b| b == b'\n').count();
+    let words = bytes
+        .split(|b| b.is_ascii_whitespace())
+        .filter(|word| !word.is_empty())
+        .count();
+    [lines as i64, words as i64, bytes.len() as i64]
+}
+
+fn row(cells: &mut Vec<Value>, name: &str, counts: [i64; 3]) {
+    cells.push(Value::String(String::from(name)));
+    cells.extend(counts.map(Value::Int));
+}
+
+libpanda::main! { |args|
+    let Ok(args) = Args::parse(&args, "", "") else {
+        return usage(USAGE);
+    };
+
+    let mut cells = Vec::new();
+    if args.operands.is_empty() {
+        row(&mut cells, "-", count(filters::text().as_bytes()));
+        return output("wc", table(&["Name", "Lines", "Words", "Bytes"], cells));
+    }
+
+    let mut status = 0;
+    let mut total = [0; 3];
+    for path in &args.operands {
+        match fs::read(&fs::uri(path)) {
+            Ok(bytes) => {
+                let counts = count(&bytes);
+                for (total, n) in total.iter_mut().zip(counts) {
+                    *total += n;
+                }
+                row(&mut cells, path, counts);
+            }
+            Err(err) => status = fail("wc", err),
+        }
+    }
+    if args.operands.len() > 1 {
+        row(&mut cells, "total", total);
+    }
+
+    if output("wc", table(&["Name", "Lines", "Words", "Bytes"], cells)) != 0 {
+        return 1;
+    }
+    status
+}
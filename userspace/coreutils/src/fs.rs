@@ -0,0 +1,290 @@
+//! File operations on URIs, built from the calls libpanda has.
+//!
+//! Files are created and removed through a handle on the directory they
+//! are in. There is no rename and no truncation, so moving a file is
+//! copying it and removing the original, and replacing one is unlinking
+//! it and creating it again.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use libpanda::buffer::Buffer;
+use libpanda::io::File;
+use libpanda::{DirEntry, ErrorCode, Handle, env, environment, file};
+use panda_abi::{DIRENT_NAME_MAX, FileStat};
+
+/// How much is copied at a time.
+const COPY_SIZE: usize = 64 * 1024;
+
+/// What went wrong, and on which path.
+#[derive(Debug)]
+pub struct Error {
+    pub path: String,
+    pub kind: Kind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Io(ErrorCode),
+    /// A file copied or moved onto itself.
+    SameFile,
+    /// A directory copied or moved into itself.
+    IntoItself,
+    /// A root, which has no directory to be removed from.
+    Root,
+}
+
+impl Error {
+    fn new(path: &str, kind: Kind) -> Self {
+        Error {
+            path: String::from(path),
+            kind,
+        }
+    }
+
+    /// The error code a call on `path` failed with.
+    pub fn at(path: &str) -> impl FnOnce(ErrorCode) -> Self + '_ {
+        move |code| Error::new(path, Kind::Io(code))
+    }
+
+    /// Whether the path was not there.
+    pub fn is_not_found(&self) -> bool {
+        self.kind == Kind::Io(ErrorCode::NotFound)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            Kind::Io(code) => write!(f, "{}: {}", self.path, code),
+            Kind::SameFile => write!(f, "{}: is the file itself", self.path),
+            Kind::IntoItself => write!(f, "{}: is inside the directory itself", self.path),
+            Kind::Root => write!(f, "{}: is a root", self.path),
+        }
+    }
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// `path` as a URI, resolved against the working directory and without a
+/// trailing `/`.
+pub fn uri(path: &str) -> String {
+    let mut uri = env::resolve_path(path);
+    while uri.ends_with('/') && !uri.ends_with(":/") {
+        uri.pop();
+    }
+    uri
+}
+
+/// The directory `uri` is in and its name there, or `None` for a root.
+pub fn split(uri: &str) -> Option<(&str, &str)> {
+    let slash = uri.rfind('/')?;
+    let name = &uri[slash + 1..];
+    if name.is_empty() {
+        return None;
+    }
+    let dir = &uri[..slash];
+    let dir = if dir.ends_with(':') {
+        &uri[..=slash]
+    } else {
+        dir
+    };
+    Some((dir, name))
+}
+
+/// `name` in the directory `dir`.
+pub fn join(dir: &str, name: &str) -> String {
+    if dir.ends_with('/') {
+        format!("{}{}", dir, name)
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+/// Call `f` with a handle on the directory `uri` is in and its name there.
+pub fn with_parent<T>(
+    uri: &str,
+    f: impl FnOnce(Handle, &str) -> core::result::Result<T, ErrorCode>,
+) -> Result<T> {
+    let (dir, name) = split(uri).ok_or_else(|| Error::new(uri, Kind::Root))?;
+    let dir = environment::opendir(dir).map_err(Error::at(uri))?;
+    let result = f(dir, name);
+    file::close(dir);
+    result.map_err(Error::at(uri))
+}
+
+/// What is at `uri`: its size and whether it is a directory.
+pub fn stat(uri: &str) -> Result<FileStat> {
+    environment::stat(uri).map_err(Error::at(uri))
+}
+
+/// Everything in a file.
+pub fn read(uri: &str) -> Result<Vec<u8>> {
+    File::read_all(uri).map_err(Error::at(uri))
+}
+
+/// The names in the directory `uri`, in order, each with whether it is a
+/// directory itself.
+pub fn read_dir(uri: &str) -> Result<Vec<(String, bool)>> {
+    let dir = environment::opendir(uri).map_err(Error::at(uri))?;
+    let mut entries = Vec::new();
+    let mut entry = DirEntry {
+        name_len: 0,
+        is_dir: false,
+        name: [0u8; DIRENT_NAME_MAX],
+    };
+    let result = loop {
+        match file::readdir(dir, &mut entry) {
+            0 => break Ok(()),
+            n if n < 0 => break Err(ErrorCode::from_isize(n).unwrap_or(ErrorCode::IoError)),
+            _ => entries.push((String::from(entry.name()), entry.is_dir)),
+        }
+    };
+    file::close(dir);
+    result.map_err(Error::at(uri))?;
+    entries.sort();
+    Ok(entries)
+}
+
+/// Make the directory `uri`.
+pub fn make_dir(uri: &str) -> Result<()> {
+    with_parent(uri, |dir, name| environment::mkdir(dir, name, 0o755))
+}
+
+/// Make the directory `uri` and any it is in that are missing, adding
+/// those made to `made`. A directory already there is left as it is.
+pub fn make_dirs(uri: &str, made: &mut Vec<String>) -> Result<()> {
+    match environment::stat(uri) {
+        Ok(stat) if stat.is_dir => return Ok(()),
+        Ok(_) => return Err(Error::new(uri, Kind::Io(ErrorCode::NotDirectory))),
+        Err(_) => {}
+    }
+    if let Some((dir, _)) = split(uri) {
+        make_dirs(dir, made)?;
+    }
+    make_dir(uri)?;
+    made.push(String::from(uri));
+    Ok(())
+}
+
+/// An empty file at `uri`, replacing any file there, open for writing.
+pub fn create(uri: &str) -> Result<Handle> {
+    with_parent(uri, |dir, name| {
+        match environment::unlink(dir, name) {
+            Ok(()) | Err(ErrorCode::NotFound) => {}
+            Err(err) => return Err(err),
+        }
+        environment::create(dir, name, 0o644, 0)
+    })
+}
+
+/// Copy the file `from` to `to`, replacing any file there.
+pub fn copy_file(from: &str, to: &str) -> Result<()> {
+    let source = environment::open(from, 0, 0).map_err(Error::at(from))?;
+    let result = create(to).and_then(|target| {
+        let result = pump(source, target).map_err(Error::at(to));
+        file::close(target);
+        result
+    });
+    file::close(source);
+    result
+}
+
+/// Move the rest of `source` into `target` through a shared buffer, so
+/// the data never has to be copied through this process.
+fn pump(source: Handle, target: Handle) -> core::result::Result<(), ErrorCode> {
+    let mut buffer = Buffer::alloc(COPY_SIZE).ok_or(ErrorCode::NoSpace)?;
+    loop {
+        let len = buffer.read_from(source).ok_or(ErrorCode::IoError)?;
+        if len == 0 {
+            return Ok(());
+        }
+        if buffer.write_to(target, len) != Some(len) {
+            return Err(ErrorCode::IoError);
+        }
+    }
+}
+
+/// Copy `from` to `to`, adding each copy made to `copied`. A directory is
+/// only copied, with everything in it, if `recursive`; it is merged into
+/// a directory already at `to`.
+pub fn copy(
+    from: &str,
+    to: &str,
+    recursive: bool,
+    copied: &mut Vec<(String, String)>,
+) -> Result<()> {
+    if from == to {
+        return Err(Error::new(to, Kind::SameFile));
+    }
+    if !stat(from)?.is_dir {
+        copy_file(from, to)?;
+        copied.push((String::from(from), String::from(to)));
+    } else if !recursive {
+        return Err(Error::new(from, Kind::Io(ErrorCode::IsDirectory)));
+    } else if to
+        .strip_prefix(from)
+        .is_some_and(|rest| rest.starts_with('/'))
+    {
+        return Err(Error::new(to, Kind::IntoItself));
+    } else {
+        match make_dir(to) {
+            Ok(()) => {}
+            Err(err) if err.kind == Kind::Io(ErrorCode::AlreadyExists) => {
+                if !stat(to)?.is_dir {
+                    return Err(Error::new(to, Kind::Io(ErrorCode::NotDirectory)));
+                }
+            }
+            Err(err) => return Err(err),
+        }
+        copied.push((String::from(from), String::from(to)));
+        for (name, _) in read_dir(from)? {
+            copy(&join(from, &name), &join(to, &name), true, copied)?;
+        }
+    }
+    Ok(())
+}
+
+/// Remove `uri`, adding each path removed to `removed`. A directory is
+/// only removed, with everything in it, if `recursive`.
+pub fn remove(uri: &str, recursive: bool, removed: &mut Vec<String>) -> Result<()> {
+    if split(uri).is_none() {
+        return Err(Error::new(uri, Kind::Root));
+    }
+    if !stat(uri)?.is_dir {
+        with_parent(uri, environment::unlink)?;
+    } else if !recursive {
+        return Err(Error::new(uri, Kind::Io(ErrorCode::IsDirectory)));
+    } else {
+        for (name, _) in read_dir(uri)? {
+            remove(&join(uri, &name), true, removed)?;
+        }
+        with_parent(uri, environment::rmdir)?;
+    }
+    removed.push(String::from(uri));
+    Ok(())
+}
+
+/// Where each of `sources` goes when copied or moved to `dest`: into it
+/// if it is a directory, else to it, which only one source can be.
+pub fn targets(sources: &[String], dest: &str) -> Result<Vec<(String, String)>> {
+    let dest = uri(dest);
+    let into = environment::stat(&dest).is_ok_and(|stat| stat.is_dir);
+    if !into && sources.len() > 1 {
+        return Err(Error::new(&dest, Kind::Io(ErrorCode::NotDirectory)));
+    }
+    sources
+        .iter()
+        .map(|source| {
+            let from = uri(source);
+            let to = match split(&from) {
+                Some((_, name)) if into => join(&dest, name),
+                Some(_) => dest.clone(),
+                None => return Err(Error::new(&from, Kind::Root)),
+            };
+            Ok((from, to))
+        })
+        .collect()
+}
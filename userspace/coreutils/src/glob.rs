@@ -0,0 +1,37 @@
+//! Shell-style name patterns, as `find -name` takes them.
+
+use alloc::vec::Vec;
+
+/// Whether `name` matches `pattern`, in which `*` stands for any run of
+/// characters and `?` for any one.
+pub fn matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Where to pick up after the last `*` if what followed it stops
+    // matching: the pattern just past it, and how far into the name it
+    // has been taken to reach.
+    let mut star = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, n));
+                p += 1;
+            }
+            Some(&ch) if ch == '?' || ch == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((after, reached)) => {
+                    p = after;
+                    n = reached + 1;
+                    star = Some((after, reached + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&ch| ch == '*')
+}
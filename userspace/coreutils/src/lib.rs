@@ -0,0 +1,139 @@
+//! What the file utilities share: reading their command lines and input,
+//! and building the tables they answer with.
+//!
+//! Each utility goes on past a path it cannot deal with, reports it through
+//! `terminal::error` as `command: path: error`, and exits with 1 at the
+//! end. Sending results and reporting failures is done as the filters do
+//! it, so a utility's output can be piped straight into `where` or
+//! `sort-by`.
+
+#![no_std]
+
+extern crate alloc;
+
+pub mod fs;
+mod glob;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use libpanda::terminal;
+use panda_abi::value::{Table, Value};
+use query::Rows;
+
+pub use filters::{fail, output};
+pub use glob::matches;
+
+/// A command line split into options and operands.
+pub struct Args {
+    flags: Vec<char>,
+    values: Vec<(char, String)>,
+    pub operands: Vec<String>,
+}
+
+impl Args {
+    /// Split the arguments after the command's name. `flags` are the
+    /// letters that stand alone, and may be run together as in `-rf`;
+    /// `valued` are those that take a value, as in `-n 5` or `-n5`.
+    /// Everything after `--`, and `-` itself, is an operand.
+    pub fn parse(args: &[String], flags: &str, valued: &str) -> Result<Self, String> {
+        let mut parsed = Args {
+            flags: Vec::new(),
+            values: Vec::new(),
+            operands: Vec::new(),
+        };
+        let mut args = args.iter().skip(1);
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                parsed.operands.extend(args.cloned());
+                break;
+            }
+            let Some(letters) = arg.strip_prefix('-').filter(|letters| !letters.is_empty()) else {
+                parsed.operands.push(arg.clone());
+                continue;
+            };
+            for (i, letter) in letters.char_indices() {
+                if flags.contains(letter) {
+                    parsed.flags.push(letter);
+                } else if valued.contains(letter) {
+                    let rest = &letters[i + letter.len_utf8()..];
+                    let value = match rest {
+                        "" => args
+                            .next()
+                            .cloned()
+                            .ok_or_else(|| format!("option -{} needs a value", letter))?,
+                        rest => String::from(rest),
+                    };
+                    parsed.values.push((letter, value));
+                    break;
+                } else {
+                    return Err(format!("unknown option -{}", letter));
+                }
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Whether the flag was given.
+    pub fn has(&self, flag: char) -> bool {
+        self.flags.contains(&flag)
+    }
+
+    /// The value given for an option, the last one if it was repeated.
+    pub fn value(&self, option: char) -> Option<&str> {
+        self.values
+            .iter()
+            .rev()
+            .find(|(letter, _)| *letter == option)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Report a command line that makes no sense and return the exit status.
+pub fn usage(usage: &str) -> i32 {
+    terminal::error(&format!("Usage: {}", usage));
+    1
+}
+
+/// What came in on stdin: text if every value was text, as it is from a
+/// file or from `cat`, else the values as rows.
+pub enum Input {
+    Text(String),
+    Rows(Rows),
+}
+
+/// Read all of stdin.
+pub fn input() -> Input {
+    let values = filters::read_all();
+    if values
+        .iter()
+        .all(|value| matches!(value, Value::String(_) | Value::Bytes(_)))
+    {
+        let mut text = String::new();
+        for value in values {
+            match value {
+                Value::String(s) => text.push_str(&s),
+                Value::Bytes(bytes) => text.push_str(&String::from_utf8_lossy(&bytes)),
+                _ => unreachable!(),
+            }
+        }
+        return Input::Text(text);
+    }
+    let mut rows = Rows::new();
+    for value in values {
+        rows.push(value);
+    }
+    Input::Rows(rows)
+}
+
+/// A table with the given column names, its cells row by row.
+pub fn table(headers: &[&str], cells: Vec<Value>) -> Value {
+    let headers = headers
+        .iter()
+        .map(|header| Value::String(String::from(*header)))
+        .collect::<Vec<_>>();
+    let table = Table::new(headers.len() as u16, Some(headers), cells)
+        .expect("cells fill the table's rows");
+    Value::Table(table)
+}
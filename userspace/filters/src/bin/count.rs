@@ -0,0 +1,9 @@
+#![no_std]
+#![no_main]
+
+use panda_abi::value::Value;
+
+libpanda::main! {
+    let rows = filters::rows();
+    filters::output("count", Value::Int(rows.len() as i64))
+}
@@ -0,0 +1,17 @@
+#![no_std]
+#![no_main]
+
+libpanda::main! { |args|
+    let count = match args.get(1).map(|arg| arg.parse::<usize>()) {
+        None => 1,
+        Some(Ok(count)) => count,
+        Some(Err(_)) => {
+            libpanda::terminal::error("Usage: first [count]");
+            return 1;
+        }
+    };
+
+    let mut rows = filters::rows();
+    rows.first(count);
+    filters::output("first", rows.into_value())
+}
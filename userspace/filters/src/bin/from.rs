@@ -0,0 +1,21 @@
+#![no_std]
+#![no_main]
+
+use libpanda::terminal;
+use query::{csv, json};
+
+libpanda::main! { |args|
+    let parse = match args.get(1).map(|arg| arg.as_str()) {
+        Some("json") => json::from_json,
+        Some("csv") => csv::from_csv,
+        _ => {
+            terminal::error("Usage: from json|csv");
+            return 1;
+        }
+    };
+
+    match parse(&filters::text()) {
+        Ok(value) => filters::output("from", value),
+        Err(err) => filters::fail("from", err),
+    }
+}
@@ -0,0 +1,16 @@
+#![no_std]
+#![no_main]
+
+use libpanda::terminal;
+
+libpanda::main! { |args|
+    if args.len() != 2 {
+        terminal::error("Usage: group-by <column>");
+        return 1;
+    }
+
+    match filters::rows().group_by(&args[1]) {
+        Ok(groups) => filters::output("group-by", groups),
+        Err(err) => filters::fail("group-by", err),
+    }
+}
@@ -0,0 +1,17 @@
+#![no_std]
+#![no_main]
+
+libpanda::main! { |args|
+    let count = match args.get(1).map(|arg| arg.parse::<usize>()) {
+        None => 1,
+        Some(Ok(count)) => count,
+        Some(Err(_)) => {
+            libpanda::terminal::error("Usage: last [count]");
+            return 1;
+        }
+    };
+
+    let mut rows = filters::rows();
+    rows.last(count);
+    filters::output("last", rows.into_value())
+}
@@ -0,0 +1,21 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use libpanda::terminal;
+
+libpanda::main! { |args|
+    if args.len() < 2 {
+        terminal::error("Usage: select <column>...");
+        return 1;
+    }
+
+    let names: Vec<&str> = args[1..].iter().map(|arg| arg.as_str()).collect();
+    let mut rows = filters::rows();
+    if let Err(err) = rows.select(&names) {
+        return filters::fail("select", err);
+    }
+    filters::output("select", rows.into_value())
+}
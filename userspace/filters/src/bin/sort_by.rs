@@ -0,0 +1,25 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+libpanda::main! { |args|
+    // Sorts by the rows themselves if no column is given.
+    let reverse = args[1..].iter().any(|arg| arg == "-r" || arg == "--reverse");
+    let names: Vec<&str> = args[1..]
+        .iter()
+        .map(|arg| arg.as_str())
+        .filter(|arg| *arg != "-r" && *arg != "--reverse")
+        .collect();
+
+    let mut rows = filters::rows();
+    if let Err(err) = rows.sort_by(&names) {
+        return filters::fail("sort-by", err);
+    }
+    if reverse {
+        rows.reverse();
+    }
+    filters::output("sort-by", rows.into_value())
+}
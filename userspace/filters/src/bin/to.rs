@@ -0,0 +1,18 @@
+#![no_std]
+#![no_main]
+
+use libpanda::terminal;
+use panda_abi::value::Value;
+use query::{Rows, csv, json};
+
+libpanda::main! { |args|
+    let text = match args.get(1).map(|arg| arg.as_str()) {
+        Some("json") => json::to_json_pretty(&filters::value()) + "\n",
+        Some("csv") => csv::to_csv(&Rows::from(filters::value())),
+        _ => {
+            terminal::error("Usage: to json|csv");
+            return 1;
+        }
+    };
+    filters::output("to", Value::String(text))
+}
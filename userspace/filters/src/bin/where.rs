@@ -0,0 +1,25 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use libpanda::terminal;
+use query::Expr;
+
+libpanda::main! { |args|
+    if args.len() < 2 {
+        terminal::error("Usage: where <expression>");
+        return 1;
+    }
+
+    let source = args[1..].iter().map(|arg| arg.as_str()).collect::<Vec<_>>().join(" ");
+    let expr = match Expr::parse(&source) {
+        Ok(expr) => expr,
+        Err(err) => return filters::fail("where", err),
+    };
+
+    let mut rows = filters::rows();
+    rows.filter(&expr);
+    filters::output("where", rows.into_value())
+}
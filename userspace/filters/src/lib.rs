@@ -0,0 +1,90 @@
+//! What the filter commands share: gathering their input, sending their
+//! result on, and reporting what went wrong.
+//!
+//! Each command reads every value on its stdin before it writes anything,
+//! so a table that arrived in several pieces is filtered, sorted or
+//! counted as one. The result goes on in as many messages as it takes.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Display;
+
+use libpanda::stdio::{output_value, read_value};
+use libpanda::terminal;
+use panda_abi::MAX_MESSAGE_SIZE;
+use panda_abi::value::Value;
+use query::{Rows, split};
+
+/// The most a piece of output may take, leaving room for the request that
+/// carries it to the terminal when the command is not in a pipeline.
+const PIECE_SIZE: usize = MAX_MESSAGE_SIZE - 64;
+
+/// Every value on stdin, until it ends. A command run on its own has none.
+pub fn read_all() -> Vec<Value> {
+    let mut values = Vec::new();
+    while let Ok(Some(value)) = read_value() {
+        values.push(value);
+    }
+    values
+}
+
+/// The input gathered into rows.
+pub fn rows() -> Rows {
+    let mut rows = Rows::new();
+    for value in read_all() {
+        rows.push(value);
+    }
+    rows
+}
+
+/// The input as one value: the value itself if there is only one, else
+/// all of them gathered into rows.
+pub fn value() -> Value {
+    let mut values = read_all();
+    if values.len() == 1 {
+        return values.remove(0);
+    }
+    let mut rows = Rows::new();
+    for value in values {
+        rows.push(value);
+    }
+    rows.into_value()
+}
+
+/// The input as text, from a file or from strings and bytes in a pipeline.
+pub fn text() -> String {
+    let mut text = String::new();
+    for value in read_all() {
+        match value {
+            Value::String(s) => text.push_str(&s),
+            Value::Bytes(bytes) => text.push_str(&String::from_utf8_lossy(&bytes)),
+            value => text.push_str(&query::value::text(&value)),
+        }
+    }
+    text
+}
+
+/// Send `value` on, split to fit, and return the exit status.
+pub fn output(command: &str, value: Value) -> i32 {
+    let pieces = match split(value, PIECE_SIZE) {
+        Ok(pieces) => pieces,
+        Err(err) => return fail(command, err),
+    };
+    for piece in pieces {
+        if output_value(&piece).is_err() {
+            return fail(command, "error writing output");
+        }
+    }
+    0
+}
+
+/// Report an error as `command: error` and return the exit status.
+pub fn fail(command: &str, error: impl Display) -> i32 {
+    terminal::error(&format!("{}: {}", command, error));
+    1
+}
@@ -6,7 +6,8 @@ extern crate alloc;
 mod driver_registry;
 
 use driver_registry::DriverRegistry;
-use libpanda::environment;
+use libpanda::ipc::Channel;
+use libpanda::{environment, graphics, terminal};
 
 libpanda::main! {
     // Phase 5a (plans/device-driver-model.md): scan the initrd for driver
@@ -42,19 +43,44 @@ libpanda::main! {
     // comes up without output. On startup it registers the `compositor:`
     // scheme (OP_SCHEME_REGISTER, landed as roadmap M2) so other processes
     // can reach it without being one of its children.
-    let Ok(_compositor_handle) = environment::spawn("file:/mnt/compositor") else {
+    let Ok(compositor_handle) = environment::spawn("file:/mnt/compositor") else {
         environment::log("init: failed to spawn compositor");
         return 1;
     };
 
+    // As the compositor's spawner, init may capture the screen; only the
+    // terminal gets that right from it (see docs/COMPOSITOR.md).
+    let capture = Channel::from_handle_borrowed(compositor_handle)
+        .and_then(|compositor| graphics::share_capture(&compositor).ok());
+    if capture.is_none() {
+        environment::log("init: could not get a capture connection");
+    }
+
+    // Spawn the network service. It claims the NIC and registers the `tcp:`
+    // and `udp:` schemes (see docs/NETWORKING.md). A machine without a
+    // network device just has no sockets, so failing here isn't fatal.
+    if environment::spawn("file:/mnt/netd").is_err() {
+        environment::log("init: failed to spawn netd");
+    }
+
+    // Spawn the clipboard service, which registers the `clipboard:` scheme
+    // (see libpanda::clipboard). Without it copy and paste do nothing, but
+    // everything else works.
+    if environment::spawn("file:/mnt/clipboard").is_err() {
+        environment::log("init: failed to spawn clipboard");
+    }
+
     // Spawn the terminal as init's own child, same as the compositor — it
     // gets its channel to the compositor by opening the `compositor:`
     // scheme (environment::connect), not from being spawned by it. See
     // `compositor::server::Compositor::serve_connects`.
-    let Ok(_terminal_handle) = environment::spawn("file:/mnt/terminal") else {
+    let Ok(terminal_handle) = environment::spawn("file:/mnt/terminal") else {
         environment::log("init: failed to spawn terminal");
         return 1;
     };
+    if terminal::send_capture_connection(terminal_handle, capture.as_ref()).is_err() {
+        environment::log("init: could not hand the terminal its capture connection");
+    }
 
     // Init's job is done - the terminal and compositor found each other via
     // scheme discovery.
@@ -0,0 +1,68 @@
+//! The clipboard, served by the clipboard service as the `clipboard:`
+//! scheme.
+//!
+//! The clipboard holds one [`Value`]: what one program puts there, another
+//! gets back with its type intact. [`get_text`] and [`set_text`] are for
+//! programs that only deal in text.
+//!
+//! ```no_run
+//! use libpanda::clipboard;
+//! use panda_abi::value::Value;
+//!
+//! clipboard::set(&Value::Int(42)).unwrap();
+//! assert_eq!(clipboard::get().unwrap(), Value::Int(42));
+//! // Anything reads as text.
+//! assert_eq!(clipboard::get_text().unwrap(), "42");
+//! ```
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use panda_abi::ErrorCode;
+use panda_abi::value::Value;
+
+use crate::error::Result;
+use crate::io::{File, Read, Write};
+
+/// The clipboard's value, encoded with `Value::to_bytes`.
+pub const VALUE_PATH: &str = "clipboard:/value";
+
+/// The clipboard's value as plain text.
+pub const TEXT_PATH: &str = "clipboard:/text";
+
+/// The most that can be put on the clipboard, in bytes as written: a
+/// larger value is refused with `NoSpace` and the clipboard keeps what it
+/// had.
+pub const MAX_SIZE: usize = 1024 * 1024;
+
+/// What is on the clipboard: `Value::Null` if nothing has been put there.
+///
+/// Fails with `NotFound` if the clipboard service isn't running.
+pub fn get() -> Result<Value> {
+    let mut bytes = Vec::new();
+    File::open(VALUE_PATH)?.read_to_end(&mut bytes)?;
+    Value::from_bytes(&bytes).map_err(|_| ErrorCode::Protocol)
+}
+
+/// Put a value on the clipboard, replacing what was there.
+///
+/// Fails with `NoSpace` if the value is larger than [`MAX_SIZE`] encoded.
+pub fn set(value: &Value) -> Result<()> {
+    // The service takes the value once the file is closed, as it drops.
+    File::open(VALUE_PATH)?.write_all(&value.to_bytes())
+}
+
+/// What is on the clipboard, as text: strings as they are, and anything
+/// else as the clipboard service writes it out.
+pub fn get_text() -> Result<String> {
+    let mut text = String::new();
+    File::open(TEXT_PATH)?.read_to_string(&mut text)?;
+    Ok(text)
+}
+
+/// Put text on the clipboard, as a `Value::String`.
+///
+/// Fails with `NoSpace` if the text is larger than [`MAX_SIZE`].
+pub fn set_text(text: &str) -> Result<()> {
+    File::open(TEXT_PATH)?.write_all(text.as_bytes())
+}
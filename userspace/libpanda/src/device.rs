@@ -2,25 +2,24 @@
 //! and the `OP_DEVICE_*` syscall wrappers.
 //!
 //! See `plans/device-driver-model.md`. Of the six syscall wrappers below,
-//! only [`device_subscribe`] and [`device_claim`] round-trip to a working
-//! kernel handler (`panda-kernel/src/syscall/device.rs`). The other four —
-//! [`device_map_mmio`], [`dma_alloc`], [`dma_free`], and
-//! [`device_subscribe_irq`] — return `ErrorCode::NotSupported` without
-//! issuing a syscall: their kernel-side implementations require IOMMU
-//! support (a separate, later task), and there is no dispatch for their
-//! `OP_*` codes yet. Returning early here (rather than dispatching to a
-//! syscall that would just hit the kernel's default `NotSupported` arm)
-//! keeps the failure obvious and avoids a partially-wired code path that
-//! looks more complete than it is.
+//! four round-trip to a working kernel handler
+//! (`panda-kernel/src/syscall/device.rs`). The other two —
+//! [`device_map_mmio`] and [`device_subscribe_irq`] — return
+//! `ErrorCode::NotSupported` without issuing a syscall, since there is no
+//! dispatch for their `OP_*` codes yet. Returning early here (rather than
+//! dispatching to a syscall that would just hit the kernel's default
+//! `NotSupported` arm) keeps the failure obvious and avoids a
+//! partially-wired code path that looks more complete than it is.
 
 // `panda_abi::device::Handle` is just `u64`, distinct from (and not
 // re-exported over) `crate::Handle`, libpanda's typed handle wrapper.
 pub use panda_abi::device::{
-    AcpiDeviceId, AcpiPath, BusType, DeviceEvent, DeviceIdentity, EVENT_DEVICE_ADDED,
-    EVENT_DEVICE_IRQ, EVENT_DEVICE_REMOVED, IoPortAddress, IoPortDeviceId, OP_DEVICE_CLAIM,
-    OP_DEVICE_MAP_MMIO, OP_DEVICE_SUBSCRIBE, OP_DEVICE_SUBSCRIBE_IRQ, OP_DMA_ALLOC, OP_DMA_FREE,
-    PCI_MATCH_ANY, PciAddress, PciDeviceId, USB_MATCH_CLASS, USB_MATCH_PRODUCT,
-    USB_MATCH_PROTOCOL, USB_MATCH_SUBCLASS, USB_MATCH_VENDOR, UsbAddress, UsbDeviceId,
+    AcpiDeviceId, AcpiPath, BusType, DeviceEvent, DeviceIdentity, DmaAllocInfo,
+    EVENT_DEVICE_ADDED, EVENT_DEVICE_IRQ, EVENT_DEVICE_REMOVED, IoPortAddress, IoPortDeviceId,
+    OP_DEVICE_CLAIM, OP_DEVICE_MAP_MMIO, OP_DEVICE_SUBSCRIBE, OP_DEVICE_SUBSCRIBE_IRQ,
+    OP_DMA_ALLOC, OP_DMA_FREE, PCI_MATCH_ANY, PciAddress, PciDeviceId, USB_MATCH_CLASS,
+    USB_MATCH_PRODUCT, USB_MATCH_PROTOCOL, USB_MATCH_SUBCLASS, USB_MATCH_VENDOR, UsbAddress,
+    UsbDeviceId,
 };
 
 use crate::Handle;
@@ -56,23 +55,25 @@ pub fn device_map_mmio(_device: Handle, _bar_index: u32) -> Result<MmioRegion> {
     Err(panda_abi::ErrorCode::NotSupported)
 }
 
-/// Allocate IOMMU-mapped, physically contiguous DMA memory for a claimed
-/// device, returning `(virt_addr, iova)`.
+/// Allocate DMA memory for a claimed device, returning `(virt_addr, iova)`.
 ///
-/// **Not implemented**: requires IOMMU support (Phase 6). Always returns
-/// `NotSupported`.
+/// The memory is zeroed, page-granular, and contiguous both at `virt_addr`
+/// and at `iova` — the address to program into the device — but not
+/// necessarily in physical memory. The device can reach nothing else.
+/// Returns `NotSupported` when the platform has no IOMMU.
 #[inline(always)]
-pub fn dma_alloc(_device: Handle, _size: usize) -> Result<(usize, u64)> {
-    Err(panda_abi::ErrorCode::NotSupported)
+pub fn dma_alloc(device: Handle, size: usize) -> Result<(usize, u64)> {
+    let mut info = DmaAllocInfo { addr: 0, iova: 0 };
+    error::from_syscall_unit(sys::device::dma_alloc(device, size, &mut info))?;
+    Ok((info.addr, info.iova))
 }
 
-/// Free memory allocated by [`dma_alloc`].
-///
-/// **Not implemented**: requires IOMMU support (Phase 6). Always returns
-/// `NotSupported`.
+/// Free memory allocated by [`dma_alloc`]; `virt_addr` and `size` must
+/// match the allocation. The device loses access before the memory is
+/// released.
 #[inline(always)]
-pub fn dma_free(_device: Handle, _virt_addr: usize, _size: usize) -> Result<()> {
-    Err(panda_abi::ErrorCode::NotSupported)
+pub fn dma_free(device: Handle, virt_addr: usize, size: usize) -> Result<()> {
+    error::from_syscall_unit(sys::device::dma_free(device, virt_addr, size))
 }
 
 /// Subscribe to IRQ events (`EVENT_DEVICE_IRQ`) for a claimed device.
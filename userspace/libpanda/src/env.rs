@@ -21,14 +21,30 @@
 //!     // ...
 //! }
 //! ```
+//!
+//! The working directory lives here too. Like the variables, a child starts
+//! in its parent's, and relative paths given to `environment::open`,
+//! `opendir` and `stat` are resolved against it.
 
+use alloc::format;
 use alloc::string::String;
+use alloc::vec;
 use alloc::vec::Vec;
+use panda_abi::ErrorCode;
+use panda_abi::path::canonicalize_path_to_buf;
 use spinning_top::RwSpinlock;
 
+use crate::error::Result;
+
 /// Global environment storage protected by a read-write spinlock.
 static ENV: RwSpinlock<Vec<(String, String)>> = RwSpinlock::new(Vec::new());
 
+/// The working directory as a URI, or empty for the root of `file:`.
+static CWD: RwSpinlock<String> = RwSpinlock::new(String::new());
+
+/// The working directory a process starts in if its parent gave none.
+const ROOT: &str = "file:/";
+
 /// Initialise the environment from the startup message.
 ///
 /// This is called by the `main!` macro during startup. User code should not
@@ -84,3 +100,75 @@ pub fn remove(key: &str) -> Option<String> {
 pub fn vars() -> Vec<(String, String)> {
     ENV.read().clone()
 }
+
+/// The working directory, as a URI such as `file:/mnt`.
+pub fn current_dir() -> String {
+    let cwd = CWD.read();
+    if cwd.is_empty() {
+        String::from(ROOT)
+    } else {
+        cwd.clone()
+    }
+}
+
+/// Change the working directory. `path` is resolved like any other path
+/// (see [`resolve_path`]) and must name a directory.
+pub fn set_current_dir(path: &str) -> Result<()> {
+    let uri = resolve_path(path);
+    if !crate::environment::stat(&uri)?.is_dir {
+        return Err(ErrorCode::NotDirectory);
+    }
+    *CWD.write() = uri;
+    Ok(())
+}
+
+/// Set the working directory from the startup message, without checking it.
+///
+/// This is called by the `main!` macro during startup. User code should not
+/// call this directly.
+pub fn init_current_dir(cwd: String) {
+    *CWD.write() = cwd;
+}
+
+/// Turn `path` into a URI. A URI (`scheme:path`) is returned as it is; an
+/// absolute path is on the working directory's scheme, and a relative path
+/// is also under the working directory. `.` and `..` are resolved.
+///
+/// # Examples
+///
+/// ```
+/// use libpanda::env::resolve_path;
+///
+/// // In the initial working directory, `file:/`:
+/// assert_eq!(resolve_path("file:/initrd/hello"), "file:/initrd/hello");
+/// assert_eq!(resolve_path("/mnt/./a.txt"), "file:/mnt/a.txt");
+/// assert_eq!(resolve_path("mnt/sub/../a.txt"), "file:/mnt/a.txt");
+/// assert_eq!(resolve_path(".."), "file:/");
+/// ```
+pub fn resolve_path(path: &str) -> String {
+    if has_scheme(path) {
+        return String::from(path);
+    }
+
+    let cwd = current_dir();
+    let (scheme, dir) = cwd.split_once(':').unwrap_or(("file", "/"));
+    let joined = if path.starts_with('/') {
+        String::from(path)
+    } else {
+        format!("{}/{}", dir, path)
+    };
+
+    // A canonical path is never longer than the path it came from, plus
+    // the `/` an empty one becomes.
+    let mut buf = vec![0u8; joined.len() + 1];
+    let canonical = canonicalize_path_to_buf(&joined, &mut buf).unwrap_or(&joined);
+    format!("{}:{}", scheme, canonical)
+}
+
+/// Whether `path` starts with a scheme, as in `file:/a` or `keyboard:/pci`.
+fn has_scheme(path: &str) -> bool {
+    match path.split_once(':') {
+        Some((scheme, _)) => !scheme.is_empty() && !scheme.contains('/'),
+        None => false,
+    }
+}
@@ -3,6 +3,7 @@
 //! The environment handle provides access to system-level operations
 //! like opening files, spawning processes, and logging.
 
+use crate::env;
 use crate::error::{self, Result};
 use crate::handle::Handle;
 use crate::process::ChildBuilder;
@@ -11,7 +12,8 @@ use panda_abi::*;
 
 /// Open a file by path.
 ///
-/// Returns a file handle on success.
+/// Returns a file handle on success. A path without a scheme is resolved
+/// against the working directory (see [`env::resolve_path`](crate::env::resolve_path)).
 ///
 /// To attach the handle to a mailbox for event notifications, pass the
 /// mailbox handle and event mask. Pass `(0, 0)` for no mailbox attachment.
@@ -39,7 +41,8 @@ use panda_abi::*;
 /// ```
 #[inline(always)]
 pub fn open(path: &str, mailbox: u64, event_mask: u32) -> Result<Handle> {
-    error::from_syscall_handle(sys::env::open(path, mailbox, event_mask))
+    let path = env::resolve_path(path);
+    error::from_syscall_handle(sys::env::open(&path, mailbox, event_mask))
 }
 
 /// Spawn a new process from an executable path.
@@ -115,10 +118,12 @@ pub fn time() -> isize {
 
 /// Open a directory for iteration.
 ///
-/// Returns a directory handle on success.
+/// Returns a directory handle on success. Like [`open`], a path without a
+/// scheme is resolved against the working directory.
 #[inline(always)]
 pub fn opendir(path: &str) -> Result<Handle> {
-    error::from_syscall_handle(sys::env::opendir(path))
+    let path = env::resolve_path(path);
+    error::from_syscall_handle(sys::env::opendir(&path))
 }
 
 /// Signal that the test is ready for screenshot capture.
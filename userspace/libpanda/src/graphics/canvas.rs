@@ -0,0 +1,503 @@
+//! Anti-aliased 2D drawing onto a [`PixelBuffer`].
+
+use alloc::vec::Vec;
+
+use super::path::Polyline;
+use super::raster::{Coverage, ceil, floor, sqrt};
+use super::{Colour, Path, PixelBuffer, Point, Rect, Transform};
+
+/// A drawing context over a [`PixelBuffer`].
+///
+/// Shapes are given in canvas coordinates, which the current [`Transform`]
+/// maps to pixels, and are anti-aliased and blended over what is already
+/// there. Drawing is limited to the clip rectangle. [`save`](Self::save)
+/// and [`restore`](Self::restore) bracket changes to either, as in an HTML
+/// canvas.
+///
+/// Every drawing method returns the rectangle of pixels it may have
+/// changed, ready to hand to [`Window::blit_region`](super::Window::blit_region)
+/// or to a damage tracker.
+///
+/// # Example
+/// ```no_run
+/// use libpanda::graphics::{Canvas, Colour, PixelBuffer, Point, Window};
+///
+/// let mut buffer = PixelBuffer::new(200, 100).unwrap();
+/// buffer.clear(Colour::WHITE);
+/// let mut canvas = Canvas::new(&mut buffer);
+/// canvas.fill_rounded_rect(Point::new(10.0, 10.0), 180.0, 80.0, 12.0, Colour::rgb(40, 80, 160));
+/// canvas.save();
+/// canvas.translate(100.0, 50.0);
+/// canvas.rotate(0.3);
+/// canvas.draw_line(Point::new(-40.0, 0.0), Point::new(40.0, 0.0), 3.0, Colour::WHITE);
+/// canvas.restore();
+///
+/// let mut window = Window::new(200, 100).unwrap();
+/// window.blit(&buffer, 0, 0).unwrap();
+/// ```
+pub struct Canvas<'a> {
+    buffer: &'a mut PixelBuffer,
+    state: State,
+    saved: Vec<State>,
+}
+
+#[derive(Clone, Copy)]
+struct State {
+    transform: Transform,
+    clip: Rect,
+}
+
+impl<'a> Canvas<'a> {
+    /// Draw onto `buffer`, untransformed and clipped only to its edges.
+    pub fn new(buffer: &'a mut PixelBuffer) -> Self {
+        let clip = Rect::from_size(buffer.width(), buffer.height());
+        Self {
+            buffer,
+            state: State {
+                transform: Transform::IDENTITY,
+                clip,
+            },
+            saved: Vec::new(),
+        }
+    }
+
+    /// The buffer being drawn on.
+    pub fn buffer(&mut self) -> &mut PixelBuffer {
+        self.buffer
+    }
+
+    /// Remember the transform and clip, to go back to with
+    /// [`restore`](Self::restore).
+    pub fn save(&mut self) {
+        self.saved.push(self.state);
+    }
+
+    /// Go back to the transform and clip at the matching
+    /// [`save`](Self::save). Does nothing if there is none.
+    pub fn restore(&mut self) {
+        if let Some(state) = self.saved.pop() {
+            self.state = state;
+        }
+    }
+
+    /// The current transform.
+    pub fn transform(&self) -> Transform {
+        self.state.transform
+    }
+
+    /// Replace the current transform.
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.state.transform = transform;
+    }
+
+    /// Apply `transform` to everything drawn from now on, before the
+    /// current transform.
+    pub fn concat(&mut self, transform: &Transform) {
+        self.state.transform = transform.then(&self.state.transform);
+    }
+
+    /// Move the origin.
+    pub fn translate(&mut self, x: f32, y: f32) {
+        self.concat(&Transform::translation(x, y));
+    }
+
+    /// Scale about the origin.
+    pub fn scale(&mut self, x: f32, y: f32) {
+        self.concat(&Transform::scaling(x, y));
+    }
+
+    /// Rotate about the origin, clockwise on screen.
+    pub fn rotate(&mut self, radians: f32) {
+        self.concat(&Transform::rotation(radians));
+    }
+
+    /// The clip rectangle, in pixels.
+    pub fn clip(&self) -> Rect {
+        self.state.clip
+    }
+
+    /// Narrow the clip to a rectangle in canvas coordinates. Under a
+    /// rotation the clip is the pixel-aligned box around the rotated
+    /// rectangle.
+    pub fn clip_rect(&mut self, origin: Point, width: f32, height: f32) {
+        let Point { x, y } = origin;
+        let corners = [
+            Point::new(x, y),
+            Point::new(x + width, y),
+            Point::new(x, y + height),
+            Point::new(x + width, y + height),
+        ]
+        .map(|p| self.state.transform.apply(p));
+        // Round to the nearest pixel edge rather than outwards, so that
+        // clips meeting at a fractional edge don't both claim its pixels.
+        let round = |value: f32| floor(value + 0.5);
+        let bounds = bounds_of(corners.iter())
+            .map(|(x0, y0, x1, y1)| pixel_rect(round(x0), round(y0), round(x1), round(y1)));
+        self.state.clip = match bounds {
+            Some(rect) => intersect(self.state.clip, rect),
+            None => Rect::default(),
+        };
+    }
+
+    /// Fill a path. Overlapping subpaths wound the same way merge; one
+    /// wound against another cuts a hole in it.
+    pub fn fill_path(&mut self, path: &Path, colour: Colour) -> Rect {
+        let polygons: Vec<Vec<Point>> = path
+            .flatten(&self.state.transform)
+            .into_iter()
+            .map(|line| line.points)
+            .collect();
+        self.fill_polygons(&polygons, colour)
+    }
+
+    /// Draw along a path with a line `width` canvas units wide, with round
+    /// joins and square-cut ends.
+    pub fn stroke_path(&mut self, path: &Path, width: f32, colour: Colour) -> Rect {
+        let half = width * self.state.transform.scale_factor() / 2.0;
+        let mut polygons = Vec::new();
+        for line in path.flatten(&self.state.transform) {
+            stroke_polyline(&line, half, &mut polygons);
+        }
+        self.fill_polygons(&polygons, colour)
+    }
+
+    /// Draw a straight line.
+    pub fn draw_line(&mut self, from: Point, to: Point, width: f32, colour: Colour) -> Rect {
+        let mut path = Path::new();
+        path.move_to(from).line_to(to);
+        self.stroke_path(&path, width, colour)
+    }
+
+    /// Fill a rectangle.
+    pub fn fill_rect(&mut self, origin: Point, width: f32, height: f32, colour: Colour) -> Rect {
+        self.fill_path(&Path::rect(origin, width, height), colour)
+    }
+
+    /// Fill a rectangle with rounded corners.
+    pub fn fill_rounded_rect(
+        &mut self,
+        origin: Point,
+        width: f32,
+        height: f32,
+        radius: f32,
+        colour: Colour,
+    ) -> Rect {
+        self.fill_path(&Path::rounded_rect(origin, width, height, radius), colour)
+    }
+
+    /// Outline a rectangle with rounded corners. The line is centred on
+    /// the rectangle's edge.
+    pub fn stroke_rounded_rect(
+        &mut self,
+        origin: Point,
+        width: f32,
+        height: f32,
+        radius: f32,
+        line_width: f32,
+        colour: Colour,
+    ) -> Rect {
+        let half = line_width / 2.0;
+        let outer = Path::rounded_rect(
+            Point::new(origin.x - half, origin.y - half),
+            width + line_width,
+            height + line_width,
+            radius + half,
+        );
+        let inner = Path::rounded_rect(
+            Point::new(origin.x + half, origin.y + half),
+            (width - line_width).max(0.0),
+            (height - line_width).max(0.0),
+            radius - half,
+        );
+        self.fill_ring(&outer, &inner, colour)
+    }
+
+    /// Fill a circle.
+    pub fn fill_circle(&mut self, centre: Point, radius: f32, colour: Colour) -> Rect {
+        self.fill_path(&Path::circle(centre, radius), colour)
+    }
+
+    /// Outline a circle. The line is centred on the circle.
+    pub fn stroke_circle(
+        &mut self,
+        centre: Point,
+        radius: f32,
+        width: f32,
+        colour: Colour,
+    ) -> Rect {
+        let half = width / 2.0;
+        let outer = Path::circle(centre, radius + half);
+        let inner = Path::circle(centre, (radius - half).max(0.0));
+        self.fill_ring(&outer, &inner, colour)
+    }
+
+    /// Fill a polygon.
+    pub fn fill_polygon(&mut self, points: &[Point], colour: Colour) -> Rect {
+        self.fill_path(&Path::polygon(points), colour)
+    }
+
+    /// Draw `image` with its top-left corner at `origin`, blending by its
+    /// alpha. Under a scale or rotation each pixel takes the nearest of the
+    /// image's.
+    pub fn draw_image(&mut self, image: &PixelBuffer, origin: Point) -> Rect {
+        let (width, height) = (image.width() as f32, image.height() as f32);
+        let transform = Transform::translation(origin.x, origin.y).then(&self.state.transform);
+        let Some(inverse) = transform.invert() else {
+            return Rect::default();
+        };
+        let corners = [
+            Point::new(0.0, 0.0),
+            Point::new(width, 0.0),
+            Point::new(0.0, height),
+            Point::new(width, height),
+        ]
+        .map(|p| transform.apply(p));
+        let Some(area) = self.device_bounds(corners.iter()) else {
+            return Rect::default();
+        };
+
+        for py in area.y..area.bottom() {
+            for px in area.x..area.right() {
+                let source = inverse.apply(Point::new(px as f32 + 0.5, py as f32 + 0.5));
+                if source.x < 0.0 || source.y < 0.0 || source.x >= width || source.y >= height {
+                    continue;
+                }
+                let colour = image.get_pixel(source.x as u32, source.y as u32);
+                self.blend(px, py, colour, 255);
+            }
+        }
+        area
+    }
+
+    /// Fill `outer` less `inner`, which must be built the same way round.
+    fn fill_ring(&mut self, outer: &Path, inner: &Path, colour: Colour) -> Rect {
+        let transform = self.state.transform;
+        let mut polygons: Vec<Vec<Point>> = outer
+            .flatten(&transform)
+            .into_iter()
+            .map(|line| line.points)
+            .collect();
+        polygons.extend(inner.flatten(&transform).into_iter().map(|line| {
+            let mut points = line.points;
+            points.reverse();
+            points
+        }));
+        self.fill_polygons(&polygons, colour)
+    }
+
+    /// Rasterise polygons in pixel coordinates and blend `colour` through
+    /// their coverage.
+    fn fill_polygons(&mut self, polygons: &[Vec<Point>], colour: Colour) -> Rect {
+        let Some(area) = self.device_bounds(polygons.iter().flatten()) else {
+            return Rect::default();
+        };
+        let mut coverage = Coverage::new(area.x as i32, area.y as i32, area.width, area.height);
+        for polygon in polygons {
+            coverage.add_polygon(polygon);
+        }
+        coverage.for_each(|x, y, cover| self.blend(x, y, colour, cover));
+        area
+    }
+
+    /// The clipped pixel box around `points`, or `None` if it is empty.
+    fn device_bounds<'p>(&self, points: impl Iterator<Item = &'p Point>) -> Option<Rect> {
+        let (x0, y0, x1, y1) = bounds_of(points)?;
+        let rect = intersect(
+            self.state.clip,
+            pixel_rect(floor(x0), floor(y0), ceil(x1), ceil(y1)),
+        );
+        (rect.width > 0 && rect.height > 0).then_some(rect)
+    }
+
+    /// Blend `colour`, scaled by `coverage` out of 255, into one pixel,
+    /// which must be inside the clip.
+    #[inline]
+    fn blend(&mut self, x: u32, y: u32, colour: Colour, coverage: u8) {
+        let alpha = ((colour.a() as u32 * coverage as u32 + 127) / 255) as u8;
+        match alpha {
+            0 => {}
+            255 => self.buffer.set_pixel(x, y, colour),
+            _ => self.buffer.blend_pixel(x, y, colour.with_alpha(alpha)),
+        }
+    }
+}
+
+#[cfg(feature = "text")]
+impl Canvas<'_> {
+    /// Draw one line of `text` at `size` pixels, starting at `origin` on
+    /// its baseline.
+    ///
+    /// Glyphs are placed by the transform but drawn upright and unscaled;
+    /// use a larger `size` rather than scaling the canvas.
+    pub fn fill_text(
+        &mut self,
+        font: &super::Font,
+        text: &str,
+        size: f32,
+        origin: Point,
+        colour: Colour,
+    ) -> Rect {
+        let layout = font.layout(text, size, None);
+        let top = Point::new(origin.x, origin.y - layout.ascent());
+        self.draw_layout(font, &layout, top, colour)
+    }
+
+    /// Draw laid-out text with the top-left of its first line at `origin`.
+    pub fn draw_layout(
+        &mut self,
+        font: &super::Font,
+        layout: &super::TextLayout,
+        origin: Point,
+        colour: Colour,
+    ) -> Rect {
+        let mut damage: Option<Rect> = None;
+        for glyph in layout.glyphs() {
+            let pen = self
+                .state
+                .transform
+                .apply(Point::new(origin.x + glyph.x, origin.y + glyph.y));
+            let (pen_x, pen_y) = (floor(pen.x + 0.5) as i32, floor(pen.y + 0.5) as i32);
+            let clip = self.state.clip;
+            let painted = font.with_glyph(glyph.index, layout.size(), |metrics, bitmap| {
+                let left = pen_x + metrics.xmin;
+                let top = pen_y - metrics.height as i32 - metrics.ymin;
+                let area = intersect_signed(clip, left, top, metrics.width, metrics.height)?;
+                for y in area.y..area.bottom() {
+                    let row = (y as i32 - top) as usize * metrics.width;
+                    for x in area.x..area.right() {
+                        let cover = bitmap[row + (x as i32 - left) as usize];
+                        if cover > 0 {
+                            self.blend(x, y, colour, cover);
+                        }
+                    }
+                }
+                Some(area)
+            });
+            if let Some(area) = painted {
+                damage = Some(match damage {
+                    Some(rect) => union(rect, area),
+                    None => area,
+                });
+            }
+        }
+        damage.unwrap_or_default()
+    }
+}
+
+/// Outline one flattened subpath `half` pixels either side, adding the
+/// pieces to `polygons`. Every piece is wound the same way, so where they
+/// overlap they merge rather than cancel.
+fn stroke_polyline(line: &Polyline, half: f32, polygons: &mut Vec<Vec<Point>>) {
+    let points = &line.points;
+    let segments = if line.closed {
+        points.len()
+    } else {
+        points.len() - 1
+    };
+    for i in 0..segments {
+        let (a, b) = (points[i], points[(i + 1) % points.len()]);
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let length = sqrt(dx * dx + dy * dy);
+        if length == 0.0 {
+            continue;
+        }
+        let (nx, ny) = (-dy / length * half, dx / length * half);
+        polygons.push(wound_positively(alloc::vec![
+            Point::new(a.x + nx, a.y + ny),
+            Point::new(b.x + nx, b.y + ny),
+            Point::new(b.x - nx, b.y - ny),
+            Point::new(a.x - nx, a.y - ny),
+        ]));
+    }
+
+    // Round joins, wherever two segments meet.
+    let joins = if line.closed {
+        0..points.len()
+    } else {
+        1..points.len() - 1
+    };
+    for i in joins {
+        polygons.push(wound_positively(disc(points[i], half)));
+    }
+}
+
+/// A polygon approximating a circle, in pixel coordinates.
+fn disc(centre: Point, radius: f32) -> Vec<Point> {
+    let mut points = Vec::new();
+    for line in Path::circle(centre, radius).flatten(&Transform::IDENTITY) {
+        points = line.points;
+    }
+    points
+}
+
+/// `points`, reversed if need be so that its signed area is positive.
+fn wound_positively(mut points: Vec<Point>) -> Vec<Point> {
+    let mut area = 0.0;
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        area += a.x * b.y - b.x * a.y;
+    }
+    if area < 0.0 {
+        points.reverse();
+    }
+    points
+}
+
+/// The `(left, top, right, bottom)` extent of `points`.
+fn bounds_of<'p>(points: impl Iterator<Item = &'p Point>) -> Option<(f32, f32, f32, f32)> {
+    points.fold(None, |bounds, p| {
+        Some(match bounds {
+            None => (p.x, p.y, p.x, p.y),
+            Some((x0, y0, x1, y1)) => (x0.min(p.x), y0.min(p.y), x1.max(p.x), y1.max(p.y)),
+        })
+    })
+}
+
+/// The pixels between whole-pixel edges, less any left of or above zero.
+fn pixel_rect(left: f32, top: f32, right: f32, bottom: f32) -> Rect {
+    let clamp = |value: f32| value.clamp(0.0, u32::MAX as f32) as u32;
+    let (x, y) = (clamp(left), clamp(top));
+    Rect::new(
+        x,
+        y,
+        clamp(right).saturating_sub(x),
+        clamp(bottom).saturating_sub(y),
+    )
+}
+
+fn intersect(a: Rect, b: Rect) -> Rect {
+    let x = a.x.max(b.x);
+    let y = a.y.max(b.y);
+    let right = a.right().min(b.right());
+    let bottom = a.bottom().min(b.bottom());
+    Rect::new(x, y, right.saturating_sub(x), bottom.saturating_sub(y))
+}
+
+#[cfg(feature = "text")]
+fn union(a: Rect, b: Rect) -> Rect {
+    let x = a.x.min(b.x);
+    let y = a.y.min(b.y);
+    Rect::new(
+        x,
+        y,
+        a.right().max(b.right()) - x,
+        a.bottom().max(b.bottom()) - y,
+    )
+}
+
+/// The part of `clip` covered by a `width` x `height` box at `(x, y)`,
+/// which may lie partly left of or above zero.
+#[cfg(feature = "text")]
+fn intersect_signed(clip: Rect, x: i32, y: i32, width: usize, height: usize) -> Option<Rect> {
+    let right = x + width as i32;
+    let bottom = y + height as i32;
+    if right <= 0 || bottom <= 0 {
+        return None;
+    }
+    let (left, top) = (x.max(0) as u32, y.max(0) as u32);
+    let rect = intersect(
+        clip,
+        Rect::new(left, top, right as u32 - left, bottom as u32 - top),
+    );
+    (rect.width > 0 && rect.height > 0).then_some(rect)
+}
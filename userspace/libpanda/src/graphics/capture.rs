@@ -0,0 +1,208 @@
+//! Capturing the screen or a window through the compositor.
+//!
+//! The compositor copies into a shared buffer the client allocates and
+//! attaches to a `Capture` request, after it has composited a frame. A
+//! connection that may capture can copy anything; an ordinary window
+//! connection only its own windows (see [`Window::capture`]). [`capture`]
+//! and [`Recording`] get such a connection from the terminal they run in;
+//! docs/COMPOSITOR.md says who else holds one.
+//!
+//! [`Window::capture`]: super::Window::capture
+
+use compositor_protocol::{
+    CAPTURE_BAD_BUFFER, CAPTURE_DENIED, CAPTURE_NO_DISPLAY, CAPTURE_NO_SUCH_WINDOW, CAPTURE_SCREEN,
+    Event, MAX_FRAME_SIZE, Request,
+};
+
+use crate::error::Result;
+use crate::graphics::PixelBuffer;
+use crate::graphics::surface::{Connection, PendingEvent};
+use crate::ipc::Channel;
+use crate::terminal;
+use panda_abi::ErrorCode;
+
+/// What to capture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureSource {
+    /// The composited screen, without the pointer.
+    Screen,
+    /// A window's last committed buffer, whole even where it is covered or
+    /// off screen. Named by [`Window::id`](super::Window::id).
+    Window(u64),
+}
+
+/// A frame copied into a [`Recording`]'s buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CaptureFrame {
+    /// The compositor's frame number.
+    pub frame: u64,
+    /// The size of the image, from the buffer's top-left corner.
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Copy the screen or a window into a new buffer the size of the image,
+/// over a connection asked of the terminal. `PermissionDenied` if it has
+/// none to give.
+///
+/// # Example
+/// ```no_run
+/// use libpanda::graphics::{CaptureSource, capture};
+///
+/// let screen = capture(CaptureSource::Screen).unwrap();
+/// let corner = screen.get_pixel(0, 0);
+/// ```
+pub fn capture(source: CaptureSource) -> Result<PixelBuffer> {
+    capture_on(capture_connection()?, source)
+}
+
+/// [`capture`] over `channel`, a connection to the compositor such as
+/// [`share_capture`] returns.
+pub fn capture_on(channel: Channel, source: CaptureSource) -> Result<PixelBuffer> {
+    capture_with(&mut Connection::from_channel(channel)?, source)
+}
+
+/// Ask the compositor, over `channel`, for a new connection with the same
+/// right to capture, to hand to another process. `channel` must be one only
+/// capture requests use: any other event read off it is dropped.
+/// `PermissionDenied` if `channel` may not capture the screen itself.
+pub fn share_capture(channel: &Channel) -> Result<Channel> {
+    let mut frame = [0u8; MAX_FRAME_SIZE];
+    let len = Request::ShareCapture
+        .encode(&mut frame)
+        .ok_or(ErrorCode::InvalidArgument)?;
+    channel.send(&frame[..len])?;
+    loop {
+        let (len, attached) = channel.recv_with_handle(&mut frame)?;
+        match Event::decode(&frame[..len]) {
+            Some(Event::CaptureShared) => {
+                return attached
+                    .and_then(Channel::from_handle)
+                    .ok_or(ErrorCode::Protocol);
+            }
+            Some(Event::CaptureFailed { .. }) => return Err(ErrorCode::PermissionDenied),
+            _ => {}
+        }
+    }
+}
+
+/// [`capture`] over an existing connection.
+pub(super) fn capture_with(
+    connection: &mut Connection,
+    source: CaptureSource,
+) -> Result<PixelBuffer> {
+    let buffer = screen_buffer(connection)?;
+    start(connection, source, &buffer, false)?;
+    let frame = next_frame(connection)?;
+
+    let mut image = PixelBuffer::new(frame.width, frame.height)?;
+    let stride = buffer.width() as usize;
+    let width = frame.width as usize;
+    for (y, row) in image.pixels_mut().chunks_exact_mut(width).enumerate() {
+        row.copy_from_slice(&buffer.pixels()[y * stride..y * stride + width]);
+    }
+    Ok(image)
+}
+
+/// A continuous capture: the compositor copies the source again on every
+/// frame that changes it, until the recording is dropped.
+pub struct Recording {
+    connection: Connection,
+    buffer: PixelBuffer,
+}
+
+impl Recording {
+    /// Start recording `source`.
+    pub fn start(source: CaptureSource) -> Result<Self> {
+        let connection = Connection::from_channel(capture_connection()?)?;
+        let buffer = screen_buffer(&connection)?;
+        start(&connection, source, &buffer, true)?;
+        Ok(Self { connection, buffer })
+    }
+
+    /// Block until the next frame has been copied into [`buffer`].
+    ///
+    /// The compositor may be writing the frame after it while the caller
+    /// reads this one; a recording that must not tear should copy the
+    /// image out first.
+    ///
+    /// [`buffer`]: Recording::buffer
+    pub fn next_frame(&mut self) -> Result<CaptureFrame> {
+        next_frame(&mut self.connection)
+    }
+
+    /// The buffer frames are copied into. It is the size of the screen; a
+    /// frame's image is at its top-left corner.
+    pub fn buffer(&self) -> &PixelBuffer {
+        &self.buffer
+    }
+}
+
+impl Drop for Recording {
+    fn drop(&mut self) {
+        let _ = self.connection.send(Request::StopCapture);
+    }
+}
+
+/// A connection that may capture, from the terminal.
+fn capture_connection() -> Result<Channel> {
+    terminal::capture_connection().ok_or(ErrorCode::PermissionDenied)
+}
+
+/// A buffer big enough for anything on screen.
+fn screen_buffer(connection: &Connection) -> Result<PixelBuffer> {
+    let (width, height) = connection.screen_size();
+    if width == 0 || height == 0 {
+        return Err(ErrorCode::NotSupported);
+    }
+    PixelBuffer::new(width, height)
+}
+
+fn start(
+    connection: &Connection,
+    source: CaptureSource,
+    buffer: &PixelBuffer,
+    continuous: bool,
+) -> Result<()> {
+    let window = match source {
+        CaptureSource::Screen => CAPTURE_SCREEN,
+        CaptureSource::Window(id) => id,
+    };
+    connection.send_with_handle(
+        Request::Capture {
+            window,
+            width: buffer.width(),
+            height: buffer.height(),
+            continuous,
+        },
+        buffer.handle(),
+    )
+}
+
+fn next_frame(connection: &mut Connection) -> Result<CaptureFrame> {
+    let event = connection.wait_for(|event| {
+        matches!(
+            event,
+            PendingEvent::Captured { .. } | PendingEvent::CaptureFailed { .. }
+        )
+    })?;
+    match event {
+        PendingEvent::Captured {
+            frame,
+            width,
+            height,
+        } => Ok(CaptureFrame {
+            frame,
+            width,
+            height,
+        }),
+        PendingEvent::CaptureFailed { reason } => Err(match reason {
+            CAPTURE_DENIED => ErrorCode::PermissionDenied,
+            CAPTURE_NO_SUCH_WINDOW => ErrorCode::NotFound,
+            CAPTURE_BAD_BUFFER => ErrorCode::InvalidArgument,
+            CAPTURE_NO_DISPLAY => ErrorCode::NotSupported,
+            _ => ErrorCode::Protocol,
+        }),
+        _ => Err(ErrorCode::Protocol),
+    }
+}
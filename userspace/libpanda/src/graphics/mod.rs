@@ -1,13 +1,28 @@
 //! Graphics types and abstractions.
 //!
 //! This module provides high-level abstractions for graphics operations,
-//! including colours, rectangles, surfaces, and pixel buffers.
-
+//! including colours, rectangles, surfaces, pixel buffers, capturing the
+//! screen, and a [`Canvas`] for anti-aliased shapes, images and (with the
+//! `text` feature) text. Images are decoded by the `image` crate, whose
+//! types are re-exported here.
+
+mod canvas;
+mod capture;
+mod path;
 mod pixels;
+mod raster;
 mod surface;
-
-pub use pixels::PixelBuffer;
-pub use surface::{Window, WindowBuilder, screen_size};
+#[cfg(feature = "text")]
+mod text;
+
+pub use canvas::Canvas;
+pub use capture::{CaptureFrame, CaptureSource, Recording, capture, capture_on, share_capture};
+pub use image::{Error as ImageError, Format as ImageFormat, Image};
+pub use path::{Path, Point, Transform};
+pub use pixels::{PixelBuffer, PixelFormat};
+pub use surface::{Window, WindowBuilder, WindowEvent, screen_size};
+#[cfg(feature = "text")]
+pub use text::{Font, LineMetrics, PositionedGlyph, TextLayout};
 
 /// A 32-bit ARGB colour.
 ///
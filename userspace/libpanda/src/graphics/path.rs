@@ -0,0 +1,426 @@
+//! Paths, and the affine transforms that place them on a canvas.
+
+use alloc::vec::Vec;
+
+use super::raster::{ceil, sin_cos, sqrt};
+
+/// How far, in pixels, a flattened curve may stray from the true one.
+const TOLERANCE: f32 = 0.05;
+
+/// A point in canvas coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Point {
+    /// Create a point.
+    #[inline]
+    pub const fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+}
+
+/// An affine transform, taking `(x, y)` to
+/// `(a·x + c·y + e, b·x + d·y + f)`.
+///
+/// # Example
+/// ```
+/// use libpanda::graphics::{Point, Transform};
+///
+/// // Scale by two about the origin, then move right by ten.
+/// let t = Transform::scaling(2.0, 2.0).then(&Transform::translation(10.0, 0.0));
+/// assert_eq!(t.apply(Point::new(1.0, 1.0)), Point::new(12.0, 2.0));
+/// assert_eq!(t.invert().unwrap().apply(Point::new(12.0, 2.0)), Point::new(1.0, 1.0));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub e: f32,
+    pub f: f32,
+}
+
+impl Transform {
+    /// The transform that changes nothing.
+    pub const IDENTITY: Self = Self::new(1.0, 0.0, 0.0, 1.0, 0.0, 0.0);
+
+    /// Create a transform from its six coefficients.
+    #[inline]
+    pub const fn new(a: f32, b: f32, c: f32, d: f32, e: f32, f: f32) -> Self {
+        Self { a, b, c, d, e, f }
+    }
+
+    /// A move by `(x, y)`.
+    #[inline]
+    pub const fn translation(x: f32, y: f32) -> Self {
+        Self::new(1.0, 0.0, 0.0, 1.0, x, y)
+    }
+
+    /// A scale about the origin.
+    #[inline]
+    pub const fn scaling(x: f32, y: f32) -> Self {
+        Self::new(x, 0.0, 0.0, y, 0.0, 0.0)
+    }
+
+    /// A rotation about the origin, clockwise on screen (y grows down).
+    pub fn rotation(radians: f32) -> Self {
+        let (sin, cos) = sin_cos(radians);
+        Self::new(cos, sin, -sin, cos, 0.0, 0.0)
+    }
+
+    /// This transform followed by `next`.
+    pub fn then(&self, next: &Transform) -> Self {
+        Self::new(
+            next.a * self.a + next.c * self.b,
+            next.b * self.a + next.d * self.b,
+            next.a * self.c + next.c * self.d,
+            next.b * self.c + next.d * self.d,
+            next.a * self.e + next.c * self.f + next.e,
+            next.b * self.e + next.d * self.f + next.f,
+        )
+    }
+
+    /// Transform a point.
+    #[inline]
+    pub fn apply(&self, point: Point) -> Point {
+        Point::new(
+            self.a * point.x + self.c * point.y + self.e,
+            self.b * point.x + self.d * point.y + self.f,
+        )
+    }
+
+    /// The transform that undoes this one, or `None` if it collapses the
+    /// plane onto a line.
+    pub fn invert(&self) -> Option<Self> {
+        let det = self.a * self.d - self.b * self.c;
+        if det == 0.0 {
+            return None;
+        }
+        Some(Self::new(
+            self.d / det,
+            -self.b / det,
+            -self.c / det,
+            self.a / det,
+            (self.c * self.f - self.d * self.e) / det,
+            (self.b * self.e - self.a * self.f) / det,
+        ))
+    }
+
+    /// How much the transform stretches lengths, on average.
+    pub(crate) fn scale_factor(&self) -> f32 {
+        sqrt((self.a * self.d - self.b * self.c).abs())
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Command {
+    MoveTo(Point),
+    LineTo(Point),
+    QuadTo(Point, Point),
+    CubicTo(Point, Point, Point),
+    Arc {
+        centre: Point,
+        radius: Point,
+        start: f32,
+        sweep: f32,
+    },
+    Close,
+}
+
+/// A shape to fill or stroke on a [`Canvas`](super::Canvas): one or more
+/// subpaths of lines and curves.
+///
+/// Curves are kept as curves until the path is drawn, then flattened
+/// finely enough for the transform they are drawn with.
+///
+/// # Example
+/// ```
+/// use libpanda::graphics::{Path, Point};
+///
+/// // A triangle with a rounded top.
+/// let mut path = Path::new();
+/// path.move_to(Point::new(0.0, 40.0));
+/// path.quad_to(Point::new(20.0, -10.0), Point::new(40.0, 40.0));
+/// path.close();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Path {
+    commands: Vec<Command>,
+}
+
+/// A flattened subpath.
+pub(crate) struct Polyline {
+    pub(crate) points: Vec<Point>,
+    pub(crate) closed: bool,
+}
+
+impl Path {
+    /// Create an empty path.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a new subpath at `point`.
+    pub fn move_to(&mut self, point: Point) -> &mut Self {
+        self.commands.push(Command::MoveTo(point));
+        self
+    }
+
+    /// Add a straight line to `point`.
+    pub fn line_to(&mut self, point: Point) -> &mut Self {
+        self.commands.push(Command::LineTo(point));
+        self
+    }
+
+    /// Add a quadratic Bézier curve to `point`.
+    pub fn quad_to(&mut self, control: Point, point: Point) -> &mut Self {
+        self.commands.push(Command::QuadTo(control, point));
+        self
+    }
+
+    /// Add a cubic Bézier curve to `point`.
+    pub fn cubic_to(&mut self, control1: Point, control2: Point, point: Point) -> &mut Self {
+        self.commands
+            .push(Command::CubicTo(control1, control2, point));
+        self
+    }
+
+    /// Add an elliptical arc around `centre`, from angle `start` through
+    /// `sweep` radians (positive is clockwise on screen). A line joins the
+    /// pen to the start of the arc, as in an HTML canvas.
+    pub fn arc(
+        &mut self,
+        centre: Point,
+        radius_x: f32,
+        radius_y: f32,
+        start: f32,
+        sweep: f32,
+    ) -> &mut Self {
+        self.commands.push(Command::Arc {
+            centre,
+            radius: Point::new(radius_x, radius_y),
+            start,
+            sweep,
+        });
+        self
+    }
+
+    /// Close the current subpath with a line back to its start.
+    pub fn close(&mut self) -> &mut Self {
+        self.commands.push(Command::Close);
+        self
+    }
+
+    /// A rectangle.
+    pub fn rect(origin: Point, width: f32, height: f32) -> Self {
+        let Point { x, y } = origin;
+        Self::polygon(&[
+            Point::new(x, y),
+            Point::new(x + width, y),
+            Point::new(x + width, y + height),
+            Point::new(x, y + height),
+        ])
+    }
+
+    /// A rectangle whose corners are quarter circles of `radius`, which is
+    /// limited to half the shorter side.
+    pub fn rounded_rect(origin: Point, width: f32, height: f32, radius: f32) -> Self {
+        use core::f32::consts::{FRAC_PI_2, PI};
+
+        let r = radius.min(width / 2.0).min(height / 2.0).max(0.0);
+        if r == 0.0 {
+            return Self::rect(origin, width, height);
+        }
+        let Point { x, y } = origin;
+        let mut path = Self::new();
+        path.move_to(Point::new(x + r, y));
+        path.arc(
+            Point::new(x + width - r, y + r),
+            r,
+            r,
+            -FRAC_PI_2,
+            FRAC_PI_2,
+        );
+        path.arc(
+            Point::new(x + width - r, y + height - r),
+            r,
+            r,
+            0.0,
+            FRAC_PI_2,
+        );
+        path.arc(
+            Point::new(x + r, y + height - r),
+            r,
+            r,
+            FRAC_PI_2,
+            FRAC_PI_2,
+        );
+        path.arc(Point::new(x + r, y + r), r, r, PI, FRAC_PI_2);
+        path.close();
+        path
+    }
+
+    /// An ellipse.
+    pub fn ellipse(centre: Point, radius_x: f32, radius_y: f32) -> Self {
+        let mut path = Self::new();
+        path.move_to(Point::new(centre.x + radius_x, centre.y));
+        path.arc(centre, radius_x, radius_y, 0.0, core::f32::consts::TAU);
+        path.close();
+        path
+    }
+
+    /// A circle.
+    pub fn circle(centre: Point, radius: f32) -> Self {
+        Self::ellipse(centre, radius, radius)
+    }
+
+    /// A closed polygon through `points`.
+    pub fn polygon(points: &[Point]) -> Self {
+        let mut path = Self::new();
+        if let Some((first, rest)) = points.split_first() {
+            path.move_to(*first);
+            for &point in rest {
+                path.line_to(point);
+            }
+            path.close();
+        }
+        path
+    }
+
+    /// The subpaths in device coordinates, curves flattened.
+    pub(crate) fn flatten(&self, transform: &Transform) -> Vec<Polyline> {
+        let scale = transform.scale_factor();
+        let mut lines = Vec::new();
+        let mut points: Vec<Point> = Vec::new();
+        // Where the pen is and where its subpath started, in path
+        // coordinates; `None` before the first point.
+        let mut pen: Option<Point> = None;
+        let mut start = Point::default();
+
+        let mut finish = |points: &mut Vec<Point>, closed: bool| {
+            if points.len() > 1 {
+                lines.push(Polyline {
+                    points: core::mem::take(points),
+                    closed,
+                });
+            } else {
+                points.clear();
+            }
+        };
+
+        for command in &self.commands {
+            // Drawing on from a closed subpath starts a new one where the
+            // closed one began.
+            if points.is_empty()
+                && !matches!(command, Command::MoveTo(_))
+                && let Some(pen) = pen
+            {
+                points.push(transform.apply(pen));
+            }
+            match *command {
+                Command::MoveTo(point) => {
+                    finish(&mut points, false);
+                    points.push(transform.apply(point));
+                    pen = Some(point);
+                    start = point;
+                }
+                Command::LineTo(point) => {
+                    points.push(transform.apply(point));
+                    pen = Some(point);
+                }
+                Command::QuadTo(control, point) => {
+                    let from = pen.unwrap_or(control);
+                    let length = distance(from, control) + distance(control, point);
+                    let steps = curve_steps(length * scale);
+                    for step in 1..=steps {
+                        let t = step as f32 / steps as f32;
+                        let u = 1.0 - t;
+                        let p = Point::new(
+                            u * u * from.x + 2.0 * u * t * control.x + t * t * point.x,
+                            u * u * from.y + 2.0 * u * t * control.y + t * t * point.y,
+                        );
+                        points.push(transform.apply(p));
+                    }
+                    pen = Some(point);
+                }
+                Command::CubicTo(control1, control2, point) => {
+                    let from = pen.unwrap_or(control1);
+                    let length = distance(from, control1)
+                        + distance(control1, control2)
+                        + distance(control2, point);
+                    let steps = curve_steps(length * scale);
+                    for step in 1..=steps {
+                        let t = step as f32 / steps as f32;
+                        let u = 1.0 - t;
+                        let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+                        let p = Point::new(
+                            a * from.x + b * control1.x + c * control2.x + d * point.x,
+                            a * from.y + b * control1.y + c * control2.y + d * point.y,
+                        );
+                        points.push(transform.apply(p));
+                    }
+                    pen = Some(point);
+                }
+                Command::Arc {
+                    centre,
+                    radius,
+                    start: angle,
+                    sweep,
+                } => {
+                    // Chords of angle θ stray r·(1 − cos(θ/2)) ≈ r·θ²/8
+                    // from the circle.
+                    let r = radius.x.abs().max(radius.y.abs()) * scale;
+                    let step_angle = sqrt(8.0 * TOLERANCE / r.max(TOLERANCE));
+                    let steps = (ceil(sweep.abs() / step_angle) as usize).clamp(1, 1024);
+                    let (step_sin, step_cos) = sin_cos(sweep / steps as f32);
+                    let (mut sin, mut cos) = sin_cos(angle);
+                    for _ in 0..=steps {
+                        let p = Point::new(centre.x + radius.x * cos, centre.y + radius.y * sin);
+                        points.push(transform.apply(p));
+                        (sin, cos) = (
+                            sin * step_cos + cos * step_sin,
+                            cos * step_cos - sin * step_sin,
+                        );
+                    }
+                    if pen.is_none() {
+                        let (sin, cos) = sin_cos(angle);
+                        start = Point::new(centre.x + radius.x * cos, centre.y + radius.y * sin);
+                    }
+                    let (sin, cos) = sin_cos(angle + sweep);
+                    pen = Some(Point::new(
+                        centre.x + radius.x * cos,
+                        centre.y + radius.y * sin,
+                    ));
+                }
+                Command::Close => {
+                    finish(&mut points, true);
+                    if pen.is_some() {
+                        pen = Some(start);
+                    }
+                }
+            }
+        }
+        finish(&mut points, false);
+        lines
+    }
+}
+
+fn distance(a: Point, b: Point) -> f32 {
+    sqrt((b.x - a.x) * (b.x - a.x) + (b.y - a.y) * (b.y - a.y))
+}
+
+/// How many lines to flatten a Bézier curve into, from the length of its
+/// control polygon in pixels.
+fn curve_steps(length: f32) -> usize {
+    (ceil(sqrt(length / TOLERANCE) / 2.0) as usize).clamp(1, 256)
+}
@@ -1,10 +1,54 @@
 //! Pixel buffer for graphics rendering.
 
+use compositor_protocol::{
+    BufferLayout, FORMAT_BGRA8888, FORMAT_NV12, FORMAT_RGB565, FORMAT_XRGB8888,
+};
+
 use crate::error::{self, Result};
-use crate::graphics::{Colour, Rect};
+use crate::graphics::{Colour, Image, Rect};
 use crate::handle::Handle;
+use crate::io::File;
 use crate::sys;
-use panda_abi::BufferAllocInfo;
+use panda_abi::{BufferAllocInfo, ErrorCode};
+
+/// How a [`PixelBuffer`] lays out its pixels. Every format is tightly
+/// packed, with no padding between rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PixelFormat {
+    /// 32-bit `0xAARRGGBB`, as [`Colour`] stores it. The default.
+    #[default]
+    Bgra8888,
+    /// As `Bgra8888`, but the alpha byte is ignored: the buffer is opaque,
+    /// and the compositor copies it without blending.
+    Xrgb8888,
+    /// 16-bit pixels with 5 bits of red, 6 of green and 5 of blue.
+    Rgb565,
+    /// Planar YUV 4:2:0: a byte of luma per pixel, then a half-resolution
+    /// plane of interleaved Cb/Cr pairs. Width and height must be even.
+    Nv12,
+}
+
+impl PixelFormat {
+    /// The compositor protocol's code for the format.
+    pub fn code(self) -> u8 {
+        match self {
+            Self::Bgra8888 => FORMAT_BGRA8888,
+            Self::Xrgb8888 => FORMAT_XRGB8888,
+            Self::Rgb565 => FORMAT_RGB565,
+            Self::Nv12 => FORMAT_NV12,
+        }
+    }
+
+    /// Whether pixels are 32-bit words that [`PixelBuffer::pixels`] can
+    /// hand out.
+    fn is_32bit(self) -> bool {
+        matches!(self, Self::Bgra8888 | Self::Xrgb8888)
+    }
+
+    pub(crate) fn layout(self, width: u32, height: u32) -> Result<BufferLayout> {
+        BufferLayout::new(self.code(), width, height).ok_or(ErrorCode::InvalidArgument)
+    }
+}
 
 /// A pixel buffer for graphics operations.
 ///
@@ -22,20 +66,46 @@ use panda_abi::BufferAllocInfo;
 /// let mut window = Window::new(100, 100).unwrap();
 /// window.blit(&buffer, 0, 0).unwrap();
 /// ```
+///
+/// The drawing methods work in every [`PixelFormat`], converting each
+/// [`Colour`]; in `Nv12` a pixel shares its chroma with the rest of its 2x2
+/// block, so drawing one recolours its neighbours' chroma too.
 pub struct PixelBuffer {
     handle: Handle,
     ptr: *mut u32,
     width: u32,
     height: u32,
+    format: PixelFormat,
+    len: usize,
 }
 
 impl PixelBuffer {
     /// Create a new pixel buffer with the given dimensions.
     pub fn new(width: u32, height: u32) -> Result<Self> {
-        let size = (width as usize) * (height as usize) * 4;
+        Self::with_format(width, height, PixelFormat::Bgra8888)
+    }
+
+    /// Create a new pixel buffer in `format`. Fails with `InvalidArgument`
+    /// for an empty buffer or an `Nv12` one with an odd side.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use libpanda::graphics::{Colour, PixelBuffer, PixelFormat, Window};
+    ///
+    /// let mut frame = PixelBuffer::with_format(64, 48, PixelFormat::Rgb565).unwrap();
+    /// frame.clear(Colour::GREEN);
+    /// let mut window = Window::builder()
+    ///     .size(64, 48)
+    ///     .format(PixelFormat::Rgb565)
+    ///     .build()
+    ///     .unwrap();
+    /// window.blit(&frame, 0, 0).unwrap();
+    /// ```
+    pub fn with_format(width: u32, height: u32, format: PixelFormat) -> Result<Self> {
+        let len = format.layout(width, height)?.size();
         let mut info = BufferAllocInfo { addr: 0, size: 0 };
 
-        let result = sys::buffer::alloc(size, Some(&mut info));
+        let result = sys::buffer::alloc(len, Some(&mut info));
         if result < 0 {
             return Err(error::from_code(result));
         }
@@ -45,9 +115,40 @@ impl PixelBuffer {
             ptr: info.addr as *mut u32,
             width,
             height,
+            format,
+            len,
         })
     }
 
+    /// Copy a decoded image into a new `Bgra8888` buffer.
+    pub fn from_image(image: &Image) -> Result<Self> {
+        let mut buffer = Self::new(image.width(), image.height())?;
+        buffer.pixels_mut().copy_from_slice(image.pixels());
+        Ok(buffer)
+    }
+
+    /// Decode a PNG, BMP or netpbm image into a new `Bgra8888` buffer.
+    /// Fails with `InvalidArgument` if `data` isn't one the `image` crate
+    /// can decode.
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        let image = image::decode(data).map_err(|_| ErrorCode::InvalidArgument)?;
+        Self::from_image(&image)
+    }
+
+    /// Load and decode an image file, as [`decode`](Self::decode).
+    ///
+    /// # Example
+    /// ```no_run
+    /// use libpanda::graphics::{PixelBuffer, Window};
+    ///
+    /// let picture = PixelBuffer::load("file:/mnt/pictures/panda.png").unwrap();
+    /// let mut window = Window::new(picture.width(), picture.height()).unwrap();
+    /// window.blit(&picture, 0, 0).unwrap();
+    /// ```
+    pub fn load(path: &str) -> Result<Self> {
+        Self::decode(&File::read_all(path)?)
+    }
+
     /// Get the buffer width in pixels.
     #[inline]
     pub fn width(&self) -> u32 {
@@ -60,6 +161,12 @@ impl PixelBuffer {
         self.height
     }
 
+    /// Get the pixel format.
+    #[inline]
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
     /// Get the underlying buffer handle (for blitting).
     #[inline]
     pub fn handle(&self) -> Handle {
@@ -67,37 +174,62 @@ impl PixelBuffer {
     }
 
     /// Get the pixel data as a slice.
+    ///
+    /// Empty unless the format is 32-bit; use [`as_bytes`](Self::as_bytes)
+    /// for the others.
     pub fn pixels(&self) -> &[u32] {
-        unsafe { core::slice::from_raw_parts(self.ptr, (self.width * self.height) as usize) }
+        unsafe { core::slice::from_raw_parts(self.ptr, self.word_count()) }
     }
 
     /// Get the pixel data as a mutable slice.
+    ///
+    /// Empty unless the format is 32-bit, as [`pixels`](Self::pixels).
     pub fn pixels_mut(&mut self) -> &mut [u32] {
-        unsafe { core::slice::from_raw_parts_mut(self.ptr, (self.width * self.height) as usize) }
+        unsafe { core::slice::from_raw_parts_mut(self.ptr, self.word_count()) }
     }
 
-    /// Get the raw bytes of the buffer.
-    pub fn as_bytes(&self) -> &[u8] {
-        unsafe {
-            core::slice::from_raw_parts(
-                self.ptr as *const u8,
-                (self.width * self.height * 4) as usize,
-            )
+    fn word_count(&self) -> usize {
+        if self.format.is_32bit() {
+            (self.width * self.height) as usize
+        } else {
+            0
         }
     }
 
+    /// Get the raw bytes of the buffer, laid out as its format says.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+
     /// Get the raw bytes of the buffer mutably.
     pub fn as_bytes_mut(&mut self) -> &mut [u8] {
-        unsafe {
-            core::slice::from_raw_parts_mut(
-                self.ptr as *mut u8,
-                (self.width * self.height * 4) as usize,
-            )
+        unsafe { core::slice::from_raw_parts_mut(self.ptr as *mut u8, self.len) }
+    }
+
+    fn layout(&self) -> BufferLayout {
+        BufferLayout {
+            format: self.format.code(),
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// Set `count` pixels of row `y` from `x`, encoding `colour` in the
+    /// buffer's format. The run must lie within the buffer.
+    fn fill_span(&mut self, x: u32, y: u32, count: u32, colour: Colour) {
+        if count > 0 {
+            let layout = self.layout();
+            let bgra = colour.as_u32().to_le_bytes();
+            layout.fill_row(self.as_bytes_mut(), x, y, count, bgra);
         }
     }
 
     /// Clear the entire buffer with a colour.
     pub fn clear(&mut self, colour: Colour) {
+        if !self.format.is_32bit() {
+            self.fill_rect(Rect::from_size(self.width, self.height), colour);
+            return;
+        }
         let pixels = self.pixels_mut();
         let value = colour.as_u32();
         for pixel in pixels.iter_mut() {
@@ -111,6 +243,10 @@ impl PixelBuffer {
     #[inline]
     pub fn set_pixel(&mut self, x: u32, y: u32, colour: Colour) {
         if x < self.width && y < self.height {
+            if !self.format.is_32bit() {
+                self.fill_span(x, y, 1, colour);
+                return;
+            }
             let index = (y * self.width + x) as usize;
             unsafe {
                 *self.ptr.add(index) = colour.as_u32();
@@ -120,10 +256,16 @@ impl PixelBuffer {
 
     /// Get a single pixel.
     ///
-    /// Returns transparent black if coordinates are out of bounds.
+    /// Returns transparent black if coordinates are out of bounds. A pixel
+    /// in a format without alpha reads back opaque.
     #[inline]
     pub fn get_pixel(&self, x: u32, y: u32) -> Colour {
         if x < self.width && y < self.height {
+            if self.format != PixelFormat::Bgra8888 {
+                let mut bgra = [0; 4];
+                self.layout().read_row(self.as_bytes(), x, y, &mut bgra);
+                return Colour(u32::from_le_bytes(bgra));
+            }
             let index = (y * self.width + x) as usize;
             unsafe { Colour(*self.ptr.add(index)) }
         } else {
@@ -141,6 +283,13 @@ impl PixelBuffer {
         let x_end = rect.right().min(self.width);
         let y_end = rect.bottom().min(self.height);
 
+        if !self.format.is_32bit() {
+            for y in y_start..y_end {
+                self.fill_span(x_start, y, x_end.saturating_sub(x_start), colour);
+            }
+            return;
+        }
+
         for y in y_start..y_end {
             let row_start = (y * self.width + x_start) as usize;
             let row_end = (y * self.width + x_end) as usize;
@@ -159,6 +308,10 @@ impl PixelBuffer {
         }
         let x_end = (x + length).min(self.width);
         let x_start = x.min(self.width);
+        if !self.format.is_32bit() {
+            self.fill_span(x_start, y, x_end.saturating_sub(x_start), colour);
+            return;
+        }
         let value = colour.as_u32();
 
         for xi in x_start..x_end {
@@ -176,6 +329,12 @@ impl PixelBuffer {
         }
         let y_end = (y + length).min(self.height);
         let y_start = y.min(self.height);
+        if !self.format.is_32bit() {
+            for yi in y_start..y_end {
+                self.fill_span(x, yi, 1, colour);
+            }
+            return;
+        }
         let value = colour.as_u32();
 
         for yi in y_start..y_end {
@@ -0,0 +1,219 @@
+//! Anti-aliased scan conversion for [`Canvas`](super::Canvas).
+//!
+//! Polygons are rasterised by signed-area accumulation: every edge adds, to
+//! each pixel it crosses, the share of that pixel's area it sweeps out, and
+//! a running sum along each row turns those deltas into exact coverage.
+//! Edges of opposite direction cancel, so a subpath wound the other way
+//! cuts a hole, and coverage saturates where like-wound subpaths overlap.
+//!
+//! There is no `libm` in `no_std`, so the handful of float functions the
+//! canvas needs are here too.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::Point;
+
+/// Coverage accumulated over a rectangle of pixels.
+pub(crate) struct Coverage {
+    x: i32,
+    y: i32,
+    width: usize,
+    height: usize,
+    /// Two spare columns per row take the writes of edges that run along
+    /// the right-hand side.
+    stride: usize,
+    area: Vec<f32>,
+}
+
+impl Coverage {
+    /// Accumulate over the `width` x `height` pixels whose top-left is at
+    /// `(x, y)`.
+    pub(crate) fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        let stride = width as usize + 2;
+        Self {
+            x,
+            y,
+            width: width as usize,
+            height: height as usize,
+            stride,
+            area: vec![0.0; stride * height as usize],
+        }
+    }
+
+    /// Add a closed polygon.
+    pub(crate) fn add_polygon(&mut self, points: &[Point]) {
+        if points.len() < 3 {
+            return;
+        }
+        let mut previous = points[points.len() - 1];
+        for &point in points {
+            self.add_edge(previous, point);
+            previous = point;
+        }
+    }
+
+    /// Add one edge, clipping it to the rectangle. The parts of an edge
+    /// left or right of the rectangle are pushed onto its sides, where they
+    /// still open or close the rows they cross.
+    fn add_edge(&mut self, from: Point, to: Point) {
+        let from = Point::new(from.x - self.x as f32, from.y - self.y as f32);
+        let to = Point::new(to.x - self.x as f32, to.y - self.y as f32);
+        let right = self.width as f32;
+
+        let mut splits = [0.0, 1.0, 1.0, 1.0];
+        let mut count = 1;
+        for side in [0.0, right] {
+            if (from.x - side) * (to.x - side) < 0.0 {
+                splits[count] = (side - from.x) / (to.x - from.x);
+                count += 1;
+            }
+        }
+        splits[1..count].sort_by(|a, b| a.total_cmp(b));
+        splits[count] = 1.0;
+
+        let at = |t: f32| {
+            Point::new(
+                (from.x + (to.x - from.x) * t).clamp(0.0, right),
+                from.y + (to.y - from.y) * t,
+            )
+        };
+        for pair in splits[..=count].windows(2) {
+            self.add_clipped_edge(at(pair[0]), at(pair[1]));
+        }
+    }
+
+    /// Add an edge that lies within the rectangle's columns.
+    fn add_clipped_edge(&mut self, from: Point, to: Point) {
+        if from.y == to.y {
+            return;
+        }
+        let (direction, top, bottom) = if from.y < to.y {
+            (1.0, from, to)
+        } else {
+            (-1.0, to, from)
+        };
+        let (right, height) = (self.width as f32, self.height as f32);
+        if bottom.y <= 0.0 || top.y >= height {
+            return;
+        }
+        let dxdy = (bottom.x - top.x) / (bottom.y - top.y);
+        let start = top.y.max(0.0);
+        let end = bottom.y.min(height);
+        // Rounding can carry x a hair past the sides; keep it within them
+        // so every write lands in the row.
+        let mut x = (top.x + (start - top.y) * dxdy).clamp(0.0, right);
+
+        let mut row = floor(start) as usize;
+        while (row as f32) < end {
+            let row_top = (row as f32).max(start);
+            let row_bottom = ((row + 1) as f32).min(end);
+            let dy = row_bottom - row_top;
+            let next_x = (x + dxdy * dy).clamp(0.0, right);
+            let d = dy * direction;
+            let line = &mut self.area[row * self.stride..(row + 1) * self.stride];
+
+            let (x0, x1) = if x < next_x { (x, next_x) } else { (next_x, x) };
+            let x0_floor = floor(x0);
+            let x0i = x0_floor as usize;
+            let x1_ceil = ceil(x1);
+            let x1i = x1_ceil as usize;
+            if x1i <= x0i + 1 {
+                // The edge stays within one pixel on this row.
+                let mid = 0.5 * (x + next_x) - x0_floor;
+                line[x0i] += d - d * mid;
+                line[x0i + 1] += d * mid;
+            } else {
+                let s = 1.0 / (x1 - x0);
+                let x0f = x0 - x0_floor;
+                let a0 = 0.5 * s * (1.0 - x0f) * (1.0 - x0f);
+                let x1f = x1 - x1_ceil + 1.0;
+                let am = 0.5 * s * x1f * x1f;
+                line[x0i] += d * a0;
+                if x1i == x0i + 2 {
+                    line[x0i + 1] += d * (1.0 - a0 - am);
+                } else {
+                    let a1 = s * (1.5 - x0f);
+                    line[x0i + 1] += d * (a1 - a0);
+                    for cell in &mut line[x0i + 2..x1i - 1] {
+                        *cell += d * s;
+                    }
+                    let a2 = a1 + (x1i - x0i - 3) as f32 * s;
+                    line[x1i - 1] += d * (1.0 - a2 - am);
+                }
+                line[x1i] += d * am;
+            }
+            x = next_x;
+            row += 1;
+        }
+    }
+
+    /// Call `f(x, y, coverage)` for every pixel the polygons touch, with
+    /// coverage from 1 to 255.
+    pub(crate) fn for_each(&self, mut f: impl FnMut(u32, u32, u8)) {
+        for (row, line) in self.area.chunks_exact(self.stride).enumerate() {
+            let mut sum = 0.0;
+            for (column, delta) in line[..self.width].iter().enumerate() {
+                sum += delta;
+                let coverage = (sum.abs().min(1.0) * 255.0 + 0.5) as u8;
+                if coverage > 0 {
+                    f(
+                        (self.x + column as i32) as u32,
+                        (self.y + row as i32) as u32,
+                        coverage,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// The largest integer not greater than `value`.
+pub(crate) fn floor(value: f32) -> f32 {
+    let truncated = value as i64 as f32;
+    if truncated > value {
+        truncated - 1.0
+    } else {
+        truncated
+    }
+}
+
+/// The smallest integer not less than `value`.
+pub(crate) fn ceil(value: f32) -> f32 {
+    -floor(-value)
+}
+
+/// The square root of `value`, which must not be negative.
+pub(crate) fn sqrt(value: f32) -> f32 {
+    if value <= 0.0 {
+        return 0.0;
+    }
+    // Halving the exponent gives a first guess within a factor of two;
+    // Newton's method then doubles the correct bits each step.
+    let mut guess = f32::from_bits((value.to_bits() >> 1) + 0x1fbd_1df5);
+    for _ in 0..3 {
+        guess = 0.5 * (guess + value / guess);
+    }
+    guess
+}
+
+/// The sine and cosine of `radians`.
+pub(crate) fn sin_cos(radians: f32) -> (f32, f32) {
+    use core::f32::consts::{FRAC_PI_2, PI, TAU};
+
+    // Reduce to [-π, π], then to [-π/2, π/2] where the series converge
+    // quickly, remembering whether the cosine changed sign.
+    let mut x = radians - TAU * floor(radians / TAU + 0.5);
+    let mut flip = 1.0;
+    if x > FRAC_PI_2 {
+        x = PI - x;
+        flip = -1.0;
+    } else if x < -FRAC_PI_2 {
+        x = -PI - x;
+        flip = -1.0;
+    }
+    let x2 = x * x;
+    let sin = x * (1.0 - x2 / 6.0 * (1.0 - x2 / 20.0 * (1.0 - x2 / 42.0 * (1.0 - x2 / 72.0))));
+    let cos = 1.0 - x2 / 2.0 * (1.0 - x2 / 12.0 * (1.0 - x2 / 30.0 * (1.0 - x2 / 56.0)));
+    (sin, cos * flip)
+}
@@ -11,18 +11,64 @@
 //! channel. `blit` copies into the buffer currently being drawn into and
 //! tracks damage; `flush` sends the accumulated `Damage` and a `Commit`,
 //! then waits for `FrameDone`.
+//!
+//! Input arrives over the same channel: the compositor owns the keyboards
+//! and pointers and sends each window its keys (while it has the focus) and
+//! pointer events (while the pointer is over it) — see [`WindowEvent`] and
+//! [`Window::poll_event`].
 
 use alloc::rc::Rc;
+use alloc::string::String;
 use alloc::vec::Vec;
 use core::cell::RefCell;
 
-use compositor_protocol::{Event, FORMAT_BGRA8888, MAX_FRAME_SIZE, Request};
+use compositor_protocol::{Event, MAX_FRAME_SIZE, MAX_TITLE_LEN, Request};
 
 use crate::buffer::Buffer;
 use crate::error::Result;
-use crate::graphics::{Colour, PixelBuffer, Rect};
+use crate::graphics::capture::{self, CaptureSource};
+use crate::graphics::{Colour, PixelBuffer, PixelFormat, Rect};
 use crate::ipc::Channel;
-use panda_abi::ErrorCode;
+use crate::keyboard::KeyValue;
+use crate::mailbox::Mailbox;
+use panda_abi::{EVENT_CHANNEL_CLOSED, EVENT_CHANNEL_READABLE, ErrorCode};
+
+/// Input for a window, as sent by the compositor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowEvent {
+    /// A key event while the window has the focus. `code` is an evdev key
+    /// code (`keyboard::KEY_*`); feed it to a `keyboard::KeyboardState` to
+    /// get text.
+    Key { code: u16, value: KeyValue },
+    /// The pointer moved to `(x, y)`, relative to the window. Outside the
+    /// window while a button pressed over it is held.
+    PointerMotion { x: i32, y: i32 },
+    /// A pointer button (`pointer::BTN_*`) went down or up at `(x, y)`.
+    PointerButton {
+        x: i32,
+        y: i32,
+        button: u16,
+        pressed: bool,
+    },
+    /// The scroll wheel turned `delta` clicks at `(x, y)`.
+    PointerWheel { x: i32, y: i32, delta: i32 },
+    /// The window gained the keyboard focus.
+    FocusIn,
+    /// The window lost the keyboard focus; keys still held will not report
+    /// their release.
+    FocusOut,
+    /// The compositor wants the window resized: pass this to
+    /// [`Window::ack_configure`], redraw at the new size and flush.
+    Configure {
+        serial: u32,
+        width: u32,
+        height: u32,
+    },
+}
+
+/// How many input events a connection keeps for windows that aren't
+/// reading them; beyond this the oldest are dropped.
+const MAX_PENDING_INPUT: usize = 256;
 
 /// An event the connection read but that didn't match what the caller was
 /// waiting for, kept around for a later wait to consume.
@@ -30,15 +76,19 @@ use panda_abi::ErrorCode;
 /// Owned (not borrowing from a receive buffer) so it can outlive the frame
 /// it was decoded from and sit in a shared queue.
 #[derive(Clone, Copy)]
-enum PendingEvent {
+pub(super) enum PendingEvent {
     WindowCreated { window: u64 },
     FrameDone { window: u64, frame: u64 },
     BufferReleased { window: u64, buffer: u64 },
     Closed { window: u64 },
+    Input { window: u64, event: WindowEvent },
+    Captured { frame: u64, width: u32, height: u32 },
+    CaptureFailed { reason: u8 },
 }
 
 impl PendingEvent {
     fn from_event(event: Event<'_>) -> Option<Self> {
+        let input = |window, event| Some(Self::Input { window, event });
         match event {
             Event::DisplayFormats { .. } => None,
             Event::WindowCreated { window } => Some(Self::WindowCreated { window }),
@@ -47,8 +97,73 @@ impl PendingEvent {
                 Some(Self::BufferReleased { window, buffer })
             }
             Event::Closed { window } => Some(Self::Closed { window }),
+            Event::Key {
+                window,
+                code,
+                value,
+            } => input(
+                window,
+                WindowEvent::Key {
+                    code,
+                    value: KeyValue::from_u32(value),
+                },
+            ),
+            Event::PointerMotion { window, x, y } => {
+                input(window, WindowEvent::PointerMotion { x, y })
+            }
+            Event::PointerButton {
+                window,
+                x,
+                y,
+                button,
+                pressed,
+            } => input(
+                window,
+                WindowEvent::PointerButton {
+                    x,
+                    y,
+                    button,
+                    pressed,
+                },
+            ),
+            Event::PointerWheel {
+                window,
+                x,
+                y,
+                delta,
+            } => input(window, WindowEvent::PointerWheel { x, y, delta }),
+            Event::FocusIn { window } => input(window, WindowEvent::FocusIn),
+            Event::FocusOut { window } => input(window, WindowEvent::FocusOut),
+            Event::Configure {
+                window,
+                serial,
+                width,
+                height,
+            } => input(
+                window,
+                WindowEvent::Configure {
+                    serial,
+                    width,
+                    height,
+                },
+            ),
+            Event::Captured {
+                frame,
+                width,
+                height,
+            } => Some(Self::Captured {
+                frame,
+                width,
+                height,
+            }),
+            Event::CaptureFailed { reason } => Some(Self::CaptureFailed { reason }),
+            Event::CaptureShared => None,
         }
     }
+
+    fn is_input(&self) -> bool {
+        matches!(self, Self::Input { .. })
+    }
 }
 
 /// The shared connection to the compositor: one channel, fanned out to
@@ -59,7 +174,7 @@ impl PendingEvent {
 /// *this* window's reply may belong to another window (or to a different
 /// kind of event for the same window) — those get stashed in `pending`
 /// rather than dropped.
-struct Connection {
+pub(super) struct Connection {
     channel: Channel,
     pending: Vec<PendingEvent>,
     screen: (u32, u32),
@@ -103,7 +218,7 @@ impl Connection {
     /// not a sibling reachable by scheme, so it must hand in the channel it
     /// already has (`Channel::parent()`, from the compositor's point of
     /// view) explicitly — see [`WindowBuilder::channel`].
-    fn from_channel(channel: Channel) -> Result<Self> {
+    pub(super) fn from_channel(channel: Channel) -> Result<Self> {
         let mut frame = [0u8; MAX_FRAME_SIZE];
         let len = channel.recv(&mut frame)?;
         let screen = match Event::decode(&frame[..len]) {
@@ -118,13 +233,19 @@ impl Connection {
         })
     }
 
-    fn send(&self, request: Request) -> Result<()> {
+    /// The screen geometry last reported: at connect time, or when the
+    /// screen changed size since.
+    pub(super) fn screen_size(&self) -> (u32, u32) {
+        self.screen
+    }
+
+    pub(super) fn send(&self, request: Request) -> Result<()> {
         let mut frame = [0u8; MAX_FRAME_SIZE];
         let len = request.encode(&mut frame).ok_or(ErrorCode::InvalidArgument)?;
         self.channel.send(&frame[..len])
     }
 
-    fn send_with_handle(&self, request: Request, handle: crate::Handle) -> Result<()> {
+    pub(super) fn send_with_handle(&self, request: Request, handle: crate::Handle) -> Result<()> {
         let mut frame = [0u8; MAX_FRAME_SIZE];
         let len = request.encode(&mut frame).ok_or(ErrorCode::InvalidArgument)?;
         self.channel.send_with_handle(&frame[..len], handle)
@@ -132,7 +253,10 @@ impl Connection {
 
     /// Block until an event matching `matches` arrives, checking already
     /// -queued events first.
-    fn wait_for<F: Fn(&PendingEvent) -> bool>(&mut self, matches: F) -> Result<PendingEvent> {
+    pub(super) fn wait_for<F: Fn(&PendingEvent) -> bool>(
+        &mut self,
+        matches: F,
+    ) -> Result<PendingEvent> {
         if let Some(index) = self.pending.iter().position(|event| matches(event)) {
             return Ok(self.pending.remove(index));
         }
@@ -140,31 +264,64 @@ impl Connection {
         let mut frame = [0u8; MAX_FRAME_SIZE];
         loop {
             let len = self.channel.recv(&mut frame)?;
-            let Some(event) = Event::decode(&frame[..len]) else {
-                continue;
-            };
-            let Some(event) = PendingEvent::from_event(event) else {
+            let Some(event) = self.receive(&frame[..len]) else {
                 continue;
             };
             if matches(&event) {
                 return Ok(event);
             }
-            self.pending.push(event);
+            self.stash(event);
         }
     }
 
-    /// Drain any queued events without blocking, checking for a `Closed`
-    /// belonging to `window`.
-    fn poll_closed(&mut self, window: u64) -> bool {
+    /// Keep an event for a later wait, bounding how much unread input
+    /// piles up.
+    fn stash(&mut self, event: PendingEvent) {
+        if event.is_input()
+            && self.pending.iter().filter(|e| e.is_input()).count() >= MAX_PENDING_INPUT
+            && let Some(oldest) = self.pending.iter().position(PendingEvent::is_input)
+        {
+            self.pending.remove(oldest);
+        }
+        self.pending.push(event);
+    }
+
+    /// Queue every event waiting on the channel, without blocking.
+    fn drain(&mut self) {
         let mut frame = [0u8; MAX_FRAME_SIZE];
         while let Ok(Some(len)) = self.channel.try_recv(&mut frame) {
-            let Some(event) = Event::decode(&frame[..len]) else {
-                continue;
-            };
-            if let Some(event) = PendingEvent::from_event(event) {
-                self.pending.push(event);
+            if let Some(event) = self.receive(&frame[..len]) {
+                self.stash(event);
             }
         }
+    }
+
+    /// Decode an event, taking note of the screen's new size if the
+    /// compositor reports one.
+    fn receive(&mut self, frame: &[u8]) -> Option<PendingEvent> {
+        let event = Event::decode(frame)?;
+        if let Event::DisplayFormats { width, height, .. } = event {
+            self.screen = (width, height);
+        }
+        PendingEvent::from_event(event)
+    }
+
+    /// Take the oldest queued input event for `window`.
+    fn take_input(&mut self, window: u64) -> Option<WindowEvent> {
+        let index = self
+            .pending
+            .iter()
+            .position(|e| matches!(e, PendingEvent::Input { window: w, .. } if *w == window))?;
+        match self.pending.remove(index) {
+            PendingEvent::Input { event, .. } => Some(event),
+            _ => None,
+        }
+    }
+
+    /// Drain any queued events without blocking, checking for a `Closed`
+    /// belonging to `window`.
+    fn poll_closed(&mut self, window: u64) -> bool {
+        self.drain();
         self.pending
             .iter()
             .any(|event| matches!(event, PendingEvent::Closed { window: w } if *w == window))
@@ -186,11 +343,8 @@ struct Slot {
 }
 
 impl Slot {
-    fn new(width: u32, height: u32) -> Result<Self> {
-        let size = (width as usize)
-            .checked_mul(height as usize)
-            .and_then(|pixels| pixels.checked_mul(4))
-            .ok_or(ErrorCode::InvalidArgument)?;
+    fn new(width: u32, height: u32, format: PixelFormat) -> Result<Self> {
+        let size = format.layout(width, height)?.size();
         let buffer = Buffer::alloc(size).ok_or(ErrorCode::IoError)?;
         Ok(Self {
             buffer,
@@ -216,10 +370,19 @@ pub struct Window {
     x: u32,
     y: u32,
     slots: Vec<Slot>,
+    /// The format of every slot's buffer.
+    format: PixelFormat,
     /// Index into `slots` currently being drawn into.
     current: usize,
     visible: bool,
+    /// Whether the window has the keyboard focus, as of the last
+    /// `FocusIn`/`FocusOut` read.
+    focused: bool,
     damage: Vec<compositor_protocol::Rect>,
+    /// The attach sequence number the compositor gives the next
+    /// `AttachBuffer`. Counted per window, like the compositor does, so it
+    /// survives the slots being reallocated by a resize.
+    next_attach_id: u64,
 }
 
 fn to_protocol_rect(rect: Rect) -> compositor_protocol::Rect {
@@ -231,6 +394,28 @@ fn to_protocol_rect(rect: Rect) -> compositor_protocol::Rect {
     }
 }
 
+/// A rectangle of one plane of pixels to copy between buffers, in pixels.
+#[derive(Clone, Copy)]
+struct PlaneCopy {
+    src_stride: usize,
+    dst_stride: usize,
+    src: (usize, usize),
+    dst: (usize, usize),
+    size: (usize, usize),
+}
+
+impl PlaneCopy {
+    fn run(&self, src: &[u8], dst: &mut [u8], bytes_per_pixel: usize) {
+        let row_bytes = self.size.0 * bytes_per_pixel;
+        for row in 0..self.size.1 {
+            let src_offset = ((self.src.1 + row) * self.src_stride + self.src.0) * bytes_per_pixel;
+            let dst_offset = ((self.dst.1 + row) * self.dst_stride + self.dst.0) * bytes_per_pixel;
+            dst[dst_offset..dst_offset + row_bytes]
+                .copy_from_slice(&src[src_offset..src_offset + row_bytes]);
+        }
+    }
+}
+
 impl Window {
     /// Create a window with the given size (single-buffered, hidden until
     /// shown).
@@ -243,6 +428,11 @@ impl Window {
         WindowBuilder::new()
     }
 
+    /// The compositor's id for the window, as a capture names it.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
     /// The window's position.
     pub fn position(&self) -> (u32, u32) {
         (self.x, self.y)
@@ -266,6 +456,68 @@ impl Window {
         self.connection.borrow_mut().poll_closed(self.id)
     }
 
+    /// Whether the window has the keyboard focus, as of the last event
+    /// read with [`Window::poll_event`] or [`Window::wait_event`].
+    pub fn has_focus(&self) -> bool {
+        self.focused
+    }
+
+    /// The next input event for this window, if one has arrived. Never
+    /// blocks.
+    pub fn poll_event(&mut self) -> Option<WindowEvent> {
+        let event = {
+            let mut connection = self.connection.borrow_mut();
+            connection.take_input(self.id).or_else(|| {
+                connection.drain();
+                connection.take_input(self.id)
+            })
+        }?;
+        self.note_focus(event);
+        Some(event)
+    }
+
+    /// Block until an input event arrives for this window.
+    pub fn wait_event(&mut self) -> Result<WindowEvent> {
+        let window = self.id;
+        let event = self
+            .connection
+            .borrow_mut()
+            .wait_for(|event| matches!(event, PendingEvent::Input { window: w, .. } if *w == window))?;
+        let PendingEvent::Input { event, .. } = event else {
+            unreachable!()
+        };
+        self.note_focus(event);
+        Ok(event)
+    }
+
+    fn note_focus(&mut self, event: WindowEvent) {
+        match event {
+            WindowEvent::FocusIn => self.focused = true,
+            WindowEvent::FocusOut => self.focused = false,
+            _ => {}
+        }
+    }
+
+    /// Have `mailbox` report when the compositor sends this window's
+    /// connection something, so an event loop can wait for input alongside
+    /// its other handles. The events arrive on [`Window::event_handle`];
+    /// read them with [`Window::poll_event`].
+    ///
+    /// A process's windows share one connection, so attaching any of them
+    /// covers them all.
+    pub fn attach_mailbox(&self, mailbox: &Mailbox) -> Result<()> {
+        mailbox.attach(
+            self.event_handle(),
+            EVENT_CHANNEL_READABLE | EVENT_CHANNEL_CLOSED,
+        )
+    }
+
+    /// The handle of the connection to the compositor, as a mailbox reports
+    /// it.
+    pub fn event_handle(&self) -> crate::Handle {
+        self.connection.borrow().channel.untyped_handle()
+    }
+
     /// Move the window's top-left corner to a screen position.
     pub fn set_position(&mut self, x: u32, y: u32) -> Result<()> {
         self.x = x;
@@ -275,15 +527,44 @@ impl Window {
             .send(Request::Move { window: self.id, x, y })
     }
 
-    /// Resize the window. Takes effect on the next `flush`, which
-    /// re-attaches the (now differently sized) buffer.
+    /// Resize the window's buffers. Takes effect on the next `flush`, which
+    /// attaches and commits the (now differently sized) buffer; the old one
+    /// stays on screen until then. The new buffers start out blank, and
+    /// damage recorded against the old size is dropped.
+    ///
+    /// Most windows should use [`Window::request_size`] instead and let the
+    /// compositor decide.
     pub fn set_size(&mut self, width: u32, height: u32) -> Result<()> {
         for slot in &mut self.slots {
-            *slot = Slot::new(width, height)?;
+            *slot = Slot::new(width, height, self.format)?;
         }
+        self.damage.clear();
         Ok(())
     }
 
+    /// Ask the compositor to resize the window. Nothing changes until the
+    /// answering [`WindowEvent::Configure`] arrives.
+    pub fn request_size(&mut self, width: u32, height: u32) -> Result<()> {
+        self.connection.borrow().send(Request::Resize {
+            window: self.id,
+            width,
+            height,
+        })
+    }
+
+    /// Accept a [`WindowEvent::Configure`]: resize the buffers (as
+    /// [`Window::set_size`]) and tell the compositor the next flush carries
+    /// the new size.
+    pub fn ack_configure(&mut self, serial: u32, width: u32, height: u32) -> Result<()> {
+        if (width, height) != self.size() {
+            self.set_size(width, height)?;
+        }
+        self.connection.borrow().send(Request::AckConfigure {
+            window: self.id,
+            serial,
+        })
+    }
+
     /// Show or hide the window.
     pub fn set_visible(&mut self, visible: bool) -> Result<()> {
         self.visible = visible;
@@ -303,6 +584,99 @@ impl Window {
         self.set_visible(false)
     }
 
+    /// Put the window on top of the others in its layer.
+    pub fn raise(&mut self) -> Result<()> {
+        self.connection
+            .borrow()
+            .send(Request::Raise { window: self.id })
+    }
+
+    /// Put the window beneath the others in its layer.
+    pub fn lower(&mut self) -> Result<()> {
+        self.connection
+            .borrow()
+            .send(Request::Lower { window: self.id })
+    }
+
+    /// Keep the window above every ordinary window, or stop doing so.
+    pub fn set_always_on_top(&mut self, on_top: bool) -> Result<()> {
+        self.connection.borrow().send(Request::SetAlwaysOnTop {
+            window: self.id,
+            on_top,
+        })
+    }
+
+    /// Set the title shown in the window's decorations. Titles longer than
+    /// [`MAX_TITLE_LEN`] bytes are cut short at a character boundary.
+    pub fn set_title(&mut self, title: &str) -> Result<()> {
+        let mut end = title.len().min(MAX_TITLE_LEN);
+        while !title.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.connection.borrow().send(Request::SetTitle {
+            window: self.id,
+            title: &title[..end],
+        })
+    }
+
+    /// Ask the compositor to draw a title bar with a close button above the
+    /// window. The title bar is outside the window's buffer: `set_position`
+    /// then places the title bar, and a click on the close button closes
+    /// the window as if by `DestroyWindow`.
+    pub fn set_decorated(&mut self, decorated: bool) -> Result<()> {
+        self.connection.borrow().send(Request::SetDecorated {
+            window: self.id,
+            decorated,
+        })
+    }
+
+    /// Show `image` as the pointer while it is over the window, with the
+    /// pixel at `(hot_x, hot_y)` pointing. The compositor copies the image,
+    /// so the buffer can be reused or dropped straight away. Images larger
+    /// than [`compositor_protocol::MAX_CURSOR_SIZE`] on a side are rejected,
+    /// and the image must be [`PixelFormat::Bgra8888`].
+    pub fn set_cursor(&mut self, image: &PixelBuffer, hot_x: u32, hot_y: u32) -> Result<()> {
+        if image.format() != PixelFormat::Bgra8888 {
+            return Err(ErrorCode::InvalidArgument);
+        }
+        self.connection.borrow().send_with_handle(
+            Request::SetCursor {
+                window: self.id,
+                width: image.width(),
+                height: image.height(),
+                hot_x,
+                hot_y,
+            },
+            image.handle(),
+        )
+    }
+
+    /// Hide the pointer while it is over the window.
+    pub fn hide_cursor(&mut self) -> Result<()> {
+        self.connection.borrow().send(Request::SetCursor {
+            window: self.id,
+            width: 0,
+            height: 0,
+            hot_x: 0,
+            hot_y: 0,
+        })
+    }
+
+    /// Go back to the compositor's own pointer over the window.
+    pub fn reset_cursor(&mut self) -> Result<()> {
+        self.connection
+            .borrow()
+            .send(Request::ResetCursor { window: self.id })
+    }
+
+    /// Copy the screen or a window into a new buffer through this window's
+    /// connection, once the compositor has finished its next frame. Any
+    /// client may capture its own windows; see [`capture`](super::capture)
+    /// for the rest.
+    pub fn capture(&mut self, source: CaptureSource) -> Result<PixelBuffer> {
+        capture::capture_with(&mut self.connection.borrow_mut(), source)
+    }
+
     /// Fill a rectangle of the window with a solid colour.
     ///
     /// Handled compositor-side against the latched content (the plan's
@@ -326,7 +700,8 @@ impl Window {
     /// `(x, y)`, recording the region as damaged.
     ///
     /// This is a local memcpy — nothing is sent to the compositor until
-    /// [`Window::flush`].
+    /// [`Window::flush`]. `buffer` must be in the window's format (see
+    /// [`WindowBuilder::format`]); an `Nv12` blit must be 2x2-aligned.
     pub fn blit(&mut self, buffer: &PixelBuffer, x: u32, y: u32) -> Result<()> {
         let full = Rect::from_size(buffer.width(), buffer.height());
         self.blit_region(buffer, x, y, full)
@@ -365,17 +740,46 @@ impl Window {
             return Err(ErrorCode::InvalidArgument);
         }
 
+        if buffer.format() != self.format {
+            return Err(ErrorCode::InvalidArgument);
+        }
+
         let src = buffer.as_bytes();
         let dst = slot.buffer.as_mut_slice();
-        let dst_stride = slot.width as usize * 4;
-        let src_stride = buffer.width() as usize * 4;
-        let row_bytes = width as usize * 4;
-        for row in 0..height as usize {
-            let dst_offset = (dst_y as usize + row) * dst_stride + dst_x as usize * 4;
-            let src_offset =
-                (src_rect.y as usize + row) * src_stride + src_rect.x as usize * 4;
-            dst[dst_offset..dst_offset + row_bytes]
-                .copy_from_slice(&src[src_offset..src_offset + row_bytes]);
+        let copy = PlaneCopy {
+            src_stride: buffer.width() as usize,
+            dst_stride: slot.width as usize,
+            src: (src_rect.x as usize, src_rect.y as usize),
+            dst: (dst_x as usize, dst_y as usize),
+            size: (width as usize, height as usize),
+        };
+        if self.format == PixelFormat::Nv12 {
+            // Chroma covers 2x2 blocks, which can't be split.
+            let all_even = [src_rect.x, src_rect.y, dst_x, dst_y, width, height]
+                .iter()
+                .all(|n| n.is_multiple_of(2));
+            if !all_even {
+                return Err(ErrorCode::InvalidArgument);
+            }
+            let src_luma = buffer.width() as usize * buffer.height() as usize;
+            let dst_luma = slot.width as usize * slot.height as usize;
+            copy.run(src, dst, 1);
+            // A chroma row is as many bytes as a luma row, for two rows
+            // of pixels.
+            let chroma = PlaneCopy {
+                src: (copy.src.0, copy.src.1 / 2),
+                dst: (copy.dst.0, copy.dst.1 / 2),
+                size: (copy.size.0, copy.size.1 / 2),
+                ..copy
+            };
+            chroma.run(&src[src_luma..], &mut dst[dst_luma..], 1);
+        } else {
+            let bytes_per_pixel = if self.format == PixelFormat::Rgb565 {
+                2
+            } else {
+                4
+            };
+            copy.run(src, dst, bytes_per_pixel);
         }
 
         self.damage
@@ -452,20 +856,21 @@ impl Window {
         let slot = &mut self.slots[self.current];
         let handle = slot.buffer.handle();
         let (width, height) = (slot.width, slot.height);
-        let next_id = slot.attach_id.map(|id| id + 1).unwrap_or(0);
+        let next_id = self.next_attach_id;
 
         self.connection.borrow().send_with_handle(
             Request::AttachBuffer {
                 window: self.id,
                 width,
                 height,
-                format: FORMAT_BGRA8888,
+                format: self.format.code(),
             },
             handle,
         )?;
 
         slot.attach_id = Some(next_id);
         slot.released = false;
+        self.next_attach_id += 1;
         Ok(())
     }
 
@@ -517,6 +922,9 @@ pub struct WindowBuilder {
     y: u32,
     visible: bool,
     double_buffered: bool,
+    format: PixelFormat,
+    title: String,
+    decorated: bool,
     channel: Option<Channel>,
 }
 
@@ -530,6 +938,9 @@ impl WindowBuilder {
             y: 0,
             visible: true,
             double_buffered: false,
+            format: PixelFormat::Bgra8888,
+            title: String::new(),
+            decorated: false,
             channel: None,
         }
     }
@@ -563,6 +974,26 @@ impl WindowBuilder {
         self
     }
 
+    /// Draw the window in `format` rather than `Bgra8888`. Only buffers of
+    /// the same format can be blitted into it.
+    pub fn format(mut self, format: PixelFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Set the window's title.
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = String::from(title);
+        self
+    }
+
+    /// Have the compositor draw a title bar above the window (see
+    /// [`Window::set_decorated`]).
+    pub fn decorated(mut self, decorated: bool) -> Self {
+        self.decorated = decorated;
+        self
+    }
+
     /// Connect over an explicit channel instead of the `compositor:` scheme.
     ///
     /// Only needed by tests that spawn the compositor themselves (see
@@ -600,7 +1031,7 @@ fn build_with_connection(
     let slot_count = if options.double_buffered { 2 } else { 1 };
     let mut slots = Vec::with_capacity(slot_count);
     for _ in 0..slot_count {
-        slots.push(Slot::new(options.width, options.height)?);
+        slots.push(Slot::new(options.width, options.height, options.format)?);
     }
 
     let mut window = Window {
@@ -609,11 +1040,20 @@ fn build_with_connection(
         x: options.x,
         y: options.y,
         slots,
+        format: options.format,
         current: 0,
         visible: options.visible,
+        focused: false,
         damage: Vec::new(),
+        next_attach_id: 0,
     };
 
+    if !options.title.is_empty() {
+        window.set_title(&options.title)?;
+    }
+    if options.decorated {
+        window.set_decorated(true)?;
+    }
     window.set_position(options.x, options.y)?;
     window.set_visible(options.visible)?;
 
@@ -0,0 +1,325 @@
+//! Fonts and text layout.
+//!
+//! A [`Font`] rasterises glyphs once per size and keeps them, so every
+//! layout and canvas that uses it shares one glyph cache. Layout handles
+//! what simple scripts need: kerning, tabs, line breaks and wrapping, and
+//! combining marks stacked over the character before them. There is no
+//! reordering or contextual shaping, so right-to-left and complex scripts
+//! come out in logical order, unjoined.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+use fontdue::FontSettings;
+use panda_abi::ErrorCode;
+
+use super::raster::floor;
+use crate::error::Result;
+
+/// Spaces to a tab stop.
+const TAB_WIDTH: f32 = 4.0;
+
+/// A glyph's placement relative to the pen, in whole pixels.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct GlyphMetrics {
+    pub(crate) xmin: i32,
+    pub(crate) ymin: i32,
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+}
+
+struct Glyph {
+    metrics: GlyphMetrics,
+    coverage: Vec<u8>,
+}
+
+/// A TrueType or OpenType font.
+///
+/// # Example
+/// ```no_run
+/// use libpanda::graphics::{Canvas, Colour, Font, PixelBuffer, Point};
+///
+/// # fn font_file() -> &'static [u8] { &[] }
+/// let font = Font::from_bytes(font_file()).unwrap();
+/// let layout = font.layout("Hello, wörld — wrapped to fit", 16.0, Some(120.0));
+///
+/// let mut buffer = PixelBuffer::new(layout.width() as u32 + 1, layout.height() as u32 + 1).unwrap();
+/// buffer.clear(Colour::WHITE);
+/// Canvas::new(&mut buffer).draw_layout(&font, &layout, Point::new(0.0, 0.0), Colour::BLACK);
+/// ```
+pub struct Font {
+    inner: fontdue::Font,
+    /// Rasterised glyphs by glyph index and size (as `f32` bits).
+    glyphs: RefCell<BTreeMap<(u16, u32), Glyph>>,
+}
+
+/// Vertical measurements of a font at one size, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineMetrics {
+    /// From the baseline up to the top of the tallest glyphs.
+    pub ascent: f32,
+    /// From the baseline down to the bottom of the lowest glyphs.
+    pub descent: f32,
+    /// From one baseline to the next.
+    pub line_height: f32,
+}
+
+/// A glyph placed by [`Font::layout`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionedGlyph {
+    /// The character it shows.
+    pub ch: char,
+    /// The font's index for the glyph.
+    pub index: u16,
+    /// Where the pen is: `x` from the left of the layout, `y` down from
+    /// its top to the glyph's baseline.
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Text broken into lines and placed glyph by glyph.
+#[derive(Debug, Clone, Default)]
+pub struct TextLayout {
+    glyphs: Vec<PositionedGlyph>,
+    size: f32,
+    width: f32,
+    lines: usize,
+    metrics: Option<LineMetrics>,
+}
+
+impl TextLayout {
+    /// The glyphs, in text order.
+    pub fn glyphs(&self) -> &[PositionedGlyph] {
+        &self.glyphs
+    }
+
+    /// The size the text was laid out at.
+    pub fn size(&self) -> f32 {
+        self.size
+    }
+
+    /// The width of the widest line.
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    /// The height of all the lines.
+    pub fn height(&self) -> f32 {
+        self.lines as f32 * self.metrics.map_or(0.0, |m| m.line_height)
+    }
+
+    /// How many lines the text took.
+    pub fn lines(&self) -> usize {
+        self.lines
+    }
+
+    /// How far the first baseline is below the top.
+    pub fn ascent(&self) -> f32 {
+        self.metrics.map_or(0.0, |m| m.ascent)
+    }
+}
+
+impl Font {
+    /// Parse a font file. Fails with `InvalidArgument` if it isn't one.
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let inner = fontdue::Font::from_bytes(data, FontSettings::default())
+            .map_err(|_| ErrorCode::InvalidArgument)?;
+        Ok(Self {
+            inner,
+            glyphs: RefCell::new(BTreeMap::new()),
+        })
+    }
+
+    /// The font's vertical measurements at `size` pixels.
+    pub fn line_metrics(&self, size: f32) -> LineMetrics {
+        match self.inner.horizontal_line_metrics(size) {
+            Some(m) => LineMetrics {
+                ascent: m.ascent,
+                descent: -m.descent,
+                line_height: m.new_line_size,
+            },
+            // No horizontal header: assume the em square.
+            None => LineMetrics {
+                ascent: size,
+                descent: 0.0,
+                line_height: size,
+            },
+        }
+    }
+
+    /// How far `ch` moves the pen at `size` pixels.
+    pub fn advance(&self, ch: char, size: f32) -> f32 {
+        self.inner.metrics(ch, size).advance_width
+    }
+
+    /// The width of `text` set on one line at `size` pixels.
+    pub fn measure(&self, text: &str, size: f32) -> f32 {
+        self.layout(text, size, None).width
+    }
+
+    /// Lay out `text` at `size` pixels.
+    ///
+    /// Lines end at each `\n` and, given a `max_width`, wherever the next
+    /// word would overflow; a word wider than a whole line is broken
+    /// between characters. Pairs are kerned, tabs advance to the next stop
+    /// every four spaces, and other control and zero-width characters take
+    /// no room.
+    pub fn layout(&self, text: &str, size: f32, max_width: Option<f32>) -> TextLayout {
+        let metrics = self.line_metrics(size);
+        let tab = TAB_WIDTH * self.advance(' ', size);
+        let mut glyphs: Vec<PositionedGlyph> = Vec::new();
+        let mut width: f32 = 0.0;
+        let mut line = 0;
+        let mut pen = 0.0;
+        // The glyph before the pen, for kerning and for marks to sit on.
+        let mut previous: Option<usize> = None;
+        // Where the ink on the current line ends, leaving out trailing
+        // spaces.
+        let mut ink = 0.0;
+        // Where the current line may break: the first glyph after the last
+        // space, the pen there, and the ink before the space.
+        let mut line_start = 0;
+        let mut break_at: Option<(usize, f32, f32)> = None;
+
+        for ch in text.chars() {
+            match ch {
+                '\n' => {
+                    width = width.max(ink);
+                    line += 1;
+                    pen = 0.0;
+                    ink = 0.0;
+                    previous = None;
+                    line_start = glyphs.len();
+                    break_at = None;
+                    continue;
+                }
+                '\t' => {
+                    pen = if tab > 0.0 {
+                        (floor(pen / tab) + 1.0) * tab
+                    } else {
+                        pen
+                    };
+                    previous = None;
+                    break_at = Some((glyphs.len(), pen, ink));
+                    continue;
+                }
+                _ if ch.is_control() || is_zero_width(ch) => continue,
+                _ => {}
+            }
+
+            let index = self.inner.lookup_glyph_index(ch);
+
+            if is_combining(ch)
+                && let Some(base) = previous
+            {
+                // Centre the mark's ink over the base's.
+                let base_glyph = glyphs[base];
+                let base_ink = self.inner.metrics_indexed(base_glyph.index, size);
+                let mark_ink = self.inner.metrics_indexed(index, size);
+                let base_centre = base_ink.xmin as f32 + base_ink.width as f32 / 2.0;
+                let mark_centre = mark_ink.xmin as f32 + mark_ink.width as f32 / 2.0;
+                glyphs.push(PositionedGlyph {
+                    ch,
+                    index,
+                    x: base_glyph.x + base_centre - mark_centre,
+                    y: base_glyph.y,
+                });
+                continue;
+            }
+
+            if let Some(previous) = previous {
+                pen += self
+                    .inner
+                    .horizontal_kern_indexed(glyphs[previous].index, index, size)
+                    .unwrap_or(0.0);
+            }
+            let advance = self.inner.metrics_indexed(index, size).advance_width;
+
+            if let Some(max_width) = max_width
+                && ch != ' '
+                && pen + advance > max_width
+                && glyphs.len() > line_start
+            {
+                // Carry the unfinished word down to a new line, or break
+                // it here if it has the line to itself.
+                let (from, shift, line_ink) = break_at.unwrap_or((glyphs.len(), pen, ink));
+                width = width.max(line_ink);
+                line += 1;
+                let down = metrics.line_height;
+                for glyph in &mut glyphs[from..] {
+                    glyph.x -= shift;
+                    glyph.y += down;
+                }
+                pen -= shift;
+                ink = (ink - shift).max(0.0);
+                line_start = from;
+                break_at = None;
+            }
+
+            glyphs.push(PositionedGlyph {
+                ch,
+                index,
+                x: pen,
+                y: metrics.ascent + line as f32 * metrics.line_height,
+            });
+            if ch == ' ' {
+                break_at = Some((glyphs.len(), pen + advance, ink));
+            } else {
+                ink = pen + advance;
+            }
+            pen += advance;
+            previous = Some(glyphs.len() - 1);
+        }
+
+        TextLayout {
+            glyphs,
+            size,
+            width: width.max(ink),
+            lines: line + 1,
+            metrics: Some(metrics),
+        }
+    }
+
+    /// Call `f` with the rasterised glyph `index` at `size`, rasterising
+    /// it the first time.
+    pub(crate) fn with_glyph<R>(
+        &self,
+        index: u16,
+        size: f32,
+        f: impl FnOnce(GlyphMetrics, &[u8]) -> R,
+    ) -> R {
+        let mut glyphs = self.glyphs.borrow_mut();
+        let glyph = glyphs.entry((index, size.to_bits())).or_insert_with(|| {
+            let (m, coverage) = self.inner.rasterize_indexed(index, size);
+            Glyph {
+                metrics: GlyphMetrics {
+                    xmin: m.xmin,
+                    ymin: m.ymin,
+                    width: m.width,
+                    height: m.height,
+                },
+                coverage,
+            }
+        });
+        f(glyph.metrics, &glyph.coverage)
+    }
+}
+
+/// Whether `ch` is a combining mark that sits on the character before it.
+fn is_combining(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{0300}'..='\u{036F}'
+            | '\u{0483}'..='\u{0489}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{FE20}'..='\u{FE2F}'
+    )
+}
+
+/// Whether `ch` is a format character that takes no room and has no ink.
+fn is_zero_width(ch: char) -> bool {
+    matches!(ch, '\u{200B}'..='\u{200F}' | '\u{2060}' | '\u{FEFF}')
+}
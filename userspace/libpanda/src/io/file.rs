@@ -3,6 +3,7 @@
 use alloc::string::String;
 use alloc::vec::Vec;
 
+use crate::env;
 use crate::error::{self, Result};
 use crate::handle::{FileHandle, Handle};
 use crate::io::{Read, Seek, SeekFrom, Write};
@@ -29,7 +30,8 @@ pub struct File {
 }
 
 impl File {
-    /// Open a file by path.
+    /// Open a file by path, resolved against the working directory if it
+    /// has no scheme (see `env::resolve_path`).
     ///
     /// # Example
     /// ```no_run
@@ -38,7 +40,7 @@ impl File {
     /// let file = File::open("file:/initrd/hello.txt").unwrap();
     /// ```
     pub fn open(path: &str) -> Result<Self> {
-        let result = sys::env::open(path, 0, 0);
+        let result = sys::env::open(&env::resolve_path(path), 0, 0);
         if result < 0 {
             Err(error::from_code(result))
         } else {
@@ -62,7 +64,7 @@ impl File {
     /// ).unwrap();
     /// ```
     pub fn open_with_mailbox(path: &str, mailbox: u64, event_mask: u32) -> Result<Self> {
-        let result = sys::env::open(path, mailbox, event_mask);
+        let result = sys::env::open(&env::resolve_path(path), mailbox, event_mask);
         if result < 0 {
             Err(error::from_code(result))
         } else {
@@ -149,6 +149,13 @@ impl Channel {
         send_with_handle(self.handle.into(), msg, attach)
     }
 
+    /// Try to send a message with an attached handle (non-blocking).
+    ///
+    /// Returns `Err(ErrorCode::WouldBlock)` if the queue is full.
+    pub fn try_send_with_handle(&self, msg: &[u8], attach: Handle) -> Result<()> {
+        try_send_with_handle(self.handle.into(), msg, attach)
+    }
+
     /// Receive a message (blocking if queue is empty), reporting any handle
     /// attached to it.
     ///
@@ -237,6 +244,19 @@ pub fn send_with_handle(handle: Handle, msg: &[u8], attach: Handle) -> Result<()
     }
 }
 
+/// Send a message on a channel with an attached handle (non-blocking).
+///
+/// Returns `Err(ErrorCode::WouldBlock)` if the queue is full.
+#[inline(always)]
+pub fn try_send_with_handle(handle: Handle, msg: &[u8], attach: Handle) -> Result<()> {
+    let result = sys::channel::try_send_msg_with_handle(handle, msg, attach);
+    if result < 0 {
+        Err(error::from_code(result))
+    } else {
+        Ok(())
+    }
+}
+
 /// Receive a message from a channel (blocking if queue empty).
 ///
 /// Returns the number of bytes received on success.
@@ -10,7 +10,7 @@ mod channel;
 
 pub use channel::{
     Channel, create_pair, recv, recv_with_handle, send, send_with_handle, try_recv,
-    try_recv_with_handle, try_send,
+    try_recv_with_handle, try_send, try_send_with_handle,
 };
 
 // Re-export mailbox types for convenience
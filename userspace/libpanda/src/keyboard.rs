@@ -4,6 +4,31 @@
 //! events. The virtio-input keyboard device uses the evdev event encoding
 //! (originally defined by Linux). Scan codes correspond to the `KEY_*` constants
 //! from `input-event-codes.h`.
+//!
+//! Layout-aware decoding comes from the `keymap` crate: a [`KeyboardState`]
+//! turns raw codes into [`KeyEvent`]s under a [`Keymap`], tracking modifiers,
+//! locks, dead keys and Compose sequences.
+//!
+//! ```no_run
+//! use libpanda::keyboard::{self, KeyValue, KeyboardState};
+//!
+//! let mut state = KeyboardState::new(keyboard::system_keymap());
+//! // For each RawInputEvent read from a `keyboard:` handle:
+//! # let (code, value) = (keyboard::KEY_A, 1);
+//! let event = state.process(code, KeyValue::from_u32(value));
+//! if !event.text.is_empty() {
+//!     // insert event.text
+//! }
+//! ```
+
+use alloc::format;
+use alloc::string::String;
+
+use panda_abi::ErrorCode;
+
+use crate::env;
+use crate::error::Result;
+use crate::io::File;
 
 /// Raw keyboard input event structure (matches kernel's input event layout).
 #[repr(C)]
@@ -14,93 +39,44 @@ pub struct RawInputEvent {
     pub value: u32,
 }
 
-/// Key event value indicating press, release, or repeat.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum KeyValue {
-    Release,
-    Press,
-    Repeat,
+pub use keymap::codes::*;
+pub use keymap::{
+    BUILTIN_LAYOUTS, KeyEvent, KeyValue, KeyboardState, Keymap, Keysym, Modifiers, ParseError,
+    ParseErrorKind,
+};
+
+pub const KEY_I_: u16 = KEY_I; // Alias
+
+/// Directory the system layouts are installed in.
+pub const KEYMAP_DIR: &str = "file:/mnt/keymaps";
+
+/// Load a layout file.
+///
+/// Fails with the file's error if it can't be read, or
+/// `ErrorCode::InvalidArgument` if it isn't a valid layout.
+pub fn load_keymap(path: &str) -> Result<Keymap> {
+    let source = File::read_to_string_path(path)?;
+    Keymap::parse(&source).map_err(|_| ErrorCode::InvalidArgument)
 }
 
-impl KeyValue {
-    /// Convert from raw u32 value.
-    pub fn from_u32(value: u32) -> Self {
-        match value {
-            0 => KeyValue::Release,
-            1 => KeyValue::Press,
-            2 => KeyValue::Repeat,
-            _ => KeyValue::Release,
-        }
+/// The layout selected by the `KEYMAP` environment variable.
+///
+/// `KEYMAP` is either a layout name, looked up in [`KEYMAP_DIR`] and then
+/// among the [`BUILTIN_LAYOUTS`], or a path to a layout file. Unset or
+/// unusable, it falls back to the US layout.
+pub fn system_keymap() -> Keymap {
+    let name = env::get("KEYMAP").unwrap_or_else(|| String::from("us"));
+    if name.contains(':') || name.contains('/') {
+        return load_keymap(&name).unwrap_or_else(|_| Keymap::us());
     }
+    load_keymap(&format!("{}/{}.keymap", KEYMAP_DIR, name))
+        .ok()
+        .or_else(|| Keymap::builtin(&name))
+        .unwrap_or_else(Keymap::us)
 }
 
-// Virtio-input key codes (evdev encoding).
-// The virtio-input spec adopted the evdev event encoding (originally defined
-// by Linux) as its standard. These values match the KEY_* constants from
-// input-event-codes.h — panda-os does not depend on Linux itself.
-// Reference: https://github.com/torvalds/linux/blob/master/include/uapi/linux/input-event-codes.h
-pub const KEY_RESERVED: u16 = 0;
-pub const KEY_ESC: u16 = 1;
-pub const KEY_1: u16 = 2;
-pub const KEY_2: u16 = 3;
-pub const KEY_3: u16 = 4;
-pub const KEY_4: u16 = 5;
-pub const KEY_5: u16 = 6;
-pub const KEY_6: u16 = 7;
-pub const KEY_7: u16 = 8;
-pub const KEY_8: u16 = 9;
-pub const KEY_9: u16 = 10;
-pub const KEY_0: u16 = 11;
-pub const KEY_MINUS: u16 = 12;
-pub const KEY_EQUAL: u16 = 13;
-pub const KEY_BACKSPACE: u16 = 14;
-pub const KEY_TAB: u16 = 15;
-pub const KEY_Q: u16 = 16;
-pub const KEY_W: u16 = 17;
-pub const KEY_E: u16 = 18;
-pub const KEY_R: u16 = 19;
-pub const KEY_T: u16 = 20;
-pub const KEY_Y: u16 = 21;
-pub const KEY_U: u16 = 22;
-pub const KEY_I: u16 = 23;
-pub const KEY_O: u16 = 24;
-pub const KEY_P: u16 = 25;
-pub const KEY_LEFTBRACE: u16 = 26;
-pub const KEY_RIGHTBRACE: u16 = 27;
-pub const KEY_ENTER: u16 = 28;
-pub const KEY_LEFTCTRL: u16 = 29;
-pub const KEY_A: u16 = 30;
-pub const KEY_S: u16 = 31;
-pub const KEY_D: u16 = 32;
-pub const KEY_F: u16 = 33;
-pub const KEY_G: u16 = 34;
-pub const KEY_H: u16 = 35;
-pub const KEY_I_: u16 = 23; // Alias
-pub const KEY_J: u16 = 36;
-pub const KEY_K: u16 = 37;
-pub const KEY_L: u16 = 38;
-pub const KEY_SEMICOLON: u16 = 39;
-pub const KEY_APOSTROPHE: u16 = 40;
-pub const KEY_GRAVE: u16 = 41;
-pub const KEY_LEFTSHIFT: u16 = 42;
-pub const KEY_BACKSLASH: u16 = 43;
-pub const KEY_Z: u16 = 44;
-pub const KEY_X: u16 = 45;
-pub const KEY_C: u16 = 46;
-pub const KEY_V: u16 = 47;
-pub const KEY_B: u16 = 48;
-pub const KEY_N: u16 = 49;
-pub const KEY_M: u16 = 50;
-pub const KEY_COMMA: u16 = 51;
-pub const KEY_DOT: u16 = 52;
-pub const KEY_SLASH: u16 = 53;
-pub const KEY_RIGHTSHIFT: u16 = 54;
-pub const KEY_KPASTERISK: u16 = 55;
-pub const KEY_LEFTALT: u16 = 56;
-pub const KEY_SPACE: u16 = 57;
-pub const KEY_CAPSLOCK: u16 = 58;
-
-/// Convert a key code to a character, with optional shift modifier.
+/// Convert a key code to a character on the US layout, with optional shift
+/// modifier. Use a [`KeyboardState`] to honour the user's layout.
 ///
 /// Returns `None` for keys that don't produce printable characters
 /// (like Enter, Backspace, Shift, etc.).
@@ -34,6 +34,7 @@ pub mod ipc;
 
 // High-level modules (these use sys:: internally)
 pub mod buffer;
+pub mod clipboard;
 pub mod device;
 pub mod env;
 pub mod environment;
@@ -42,12 +43,17 @@ pub mod handle;
 pub mod heap;
 pub mod keyboard;
 pub mod mailbox;
+pub mod net;
+pub mod pointer;
 pub mod print;
 pub mod process;
 pub mod scheme;
+pub mod socket;
 pub mod startup;
 pub mod stdio;
 pub mod terminal;
+#[cfg(feature = "testing")]
+pub mod testing;
 
 // Re-export ipc::channel functions at top level for convenience
 pub use ipc::{create_pair, recv, recv_with_handle, send, send_with_handle, try_recv, try_send};
@@ -126,10 +132,13 @@ macro_rules! __main_impl {
         #[unsafe(no_mangle)]
         extern "C" fn _start() -> ! {
             // Receive startup arguments and environment from parent
-            let (__args, __env) = $crate::startup::receive_startup();
+            let (__args, __env, __cwd) = $crate::startup::receive_startup();
 
             // Initialize the environment module
             $crate::env::init(__env);
+            if let Some(cwd) = __cwd {
+                $crate::env::init_current_dir(cwd);
+            }
 
             #[allow(unused_variables)]
             let $args: $crate::Vec<$crate::String> = __args;
@@ -38,6 +38,14 @@ impl Mailbox {
         self.handle
     }
 
+    /// Attach a handle that is already open, such as a channel returned by
+    /// `environment::connect`, so its events (those in `event_mask`) arrive
+    /// here. Handles from `open` and `spawn` are attached when created.
+    #[inline(always)]
+    pub fn attach(&self, handle: Handle, event_mask: u32) -> Result<()> {
+        error::from_syscall(sys::mailbox::attach(self.handle, handle, event_mask)).map(|_| ())
+    }
+
     /// Wait for the next event (blocking).
     ///
     /// Returns `(handle, events)` when an event is available.
@@ -116,6 +124,25 @@ impl Events {
         self.0 & EVENT_PROCESS_EXITED != 0
     }
 
+    /// Check if a network device has received frames to read.
+    #[inline(always)]
+    pub fn is_net_rx(&self) -> bool {
+        self.0 & EVENT_NET_RX != 0
+    }
+
+    /// Check if a pointer device has a report to read.
+    #[inline(always)]
+    pub fn is_pointer(&self) -> bool {
+        self.0 & EVENT_POINTER != 0
+    }
+
+    /// Check if a display's mode or monitor changed, so its mode must be
+    /// queried and its framebuffer mapped again.
+    #[inline(always)]
+    pub fn is_display_changed(&self) -> bool {
+        self.0 & EVENT_DISPLAY_CHANGED != 0
+    }
+
     /// Iterate over all set events.
     ///
     /// This yields each event that is set in the flags.
@@ -168,6 +195,7 @@ const EVENT_TYPES: &[(u32, Event)] = &[
     ),
     (EVENT_PROCESS_EXITED, Event::Process(ProcessEvent::Exited)),
     (EVENT_KEYBOARD_KEY, Event::Input(InputEvent::Keyboard)),
+    (EVENT_POINTER, Event::Input(InputEvent::Pointer)),
 ];
 
 /// Iterator over events in an [`Events`] set.
@@ -205,8 +233,9 @@ impl Iterator for EventIter {
 pub enum InputEvent {
     /// Keyboard input available - read from keyboard handle to get key data.
     Keyboard,
-    /// Mouse input available - read from mouse handle to get mouse data.
-    Mouse,
+    /// Pointer input available - read from the pointer handle to get
+    /// motion and button data.
+    Pointer,
 }
 
 /// Channel events.
@@ -232,7 +261,7 @@ pub enum ProcessEvent {
 /// For handling multiple simultaneous events, use [`Events`] directly.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Event {
-    /// Input device events (keyboard, mouse).
+    /// Input device events (keyboard, pointer).
     Input(InputEvent),
     /// Channel events (readable, writable, closed).
     Channel(ChannelEvent),
@@ -0,0 +1,194 @@
+//! Raw ethernet frame access to a network device.
+//!
+//! A [`NetDevice`] is an exclusively owned `net:` handle: only one process
+//! can hold a given device open, and it sees every frame the device
+//! receives. Frames move through a ring in a buffer shared with the kernel
+//! ([`panda_abi::net`]): received frames are written straight into it, and
+//! [`try_recv`](NetDevice::try_recv) takes them out without a syscall.
+//! Each [`queue`](NetDevice::queue) puts one complete frame (ethernet
+//! header included, no frame check sequence) in it to transmit, and
+//! [`flush`](NetDevice::flush) has the kernel send everything queued;
+//! [`send`](NetDevice::send) does both.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use libpanda::mailbox::Mailbox;
+//! use libpanda::net::NetDevice;
+//!
+//! let mailbox = Mailbox::default();
+//! let nic = NetDevice::open_with_mailbox("net:/pci/network/0", &mailbox).unwrap();
+//! let mut frame = [0u8; panda_abi::NET_MAX_FRAME_SIZE];
+//! loop {
+//!     let (_, events) = mailbox.recv();
+//!     if events.is_net_rx() {
+//!         while let Ok(Some(len)) = nic.try_recv(&mut frame) {
+//!             // handle frame[..len]
+//!         }
+//!     }
+//! }
+//! ```
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use crate::buffer::Buffer;
+use crate::environment;
+use crate::error::{self, Result};
+use crate::handle::Handle;
+use crate::mailbox::Mailbox;
+use crate::sys;
+use panda_abi::net::{self as ring, RingHeader};
+use panda_abi::*;
+
+/// An open network device. Closed on drop.
+pub struct NetDevice {
+    handle: Handle,
+    info: NetInfo,
+    /// Keeps the ring's memory alive; it is only reached through `base`.
+    _ring: Buffer,
+    base: *mut u8,
+}
+
+impl NetDevice {
+    /// Open a network device by URI, e.g. `net:/pci/network/0`.
+    ///
+    /// Fails with `Busy` if another handle already owns the device.
+    pub fn open(uri: &str) -> Result<Self> {
+        Self::from_handle(environment::open(uri, 0, 0)?)
+    }
+
+    /// Open a network device and attach it to `mailbox`, which then
+    /// receives `EVENT_NET_RX` whenever frames arrive.
+    pub fn open_with_mailbox(uri: &str, mailbox: &Mailbox) -> Result<Self> {
+        let handle = environment::open(uri, mailbox.handle().as_raw(), EVENT_NET_RX)?;
+        Self::from_handle(handle)
+    }
+
+    fn from_handle(handle: Handle) -> Result<Self> {
+        let close = |code: ErrorCode| {
+            let _ = sys::file::close(handle);
+            code
+        };
+        let mut info = NetInfo::default();
+        let result = sys::net::info(handle, &mut info);
+        if result < 0 {
+            return Err(close(error::from_code(result)));
+        }
+        let mut buffer = Buffer::alloc(ring::RING_SIZE).ok_or_else(|| close(ErrorCode::NoSpace))?;
+        let result = sys::net::ring(handle, buffer.handle());
+        if result < 0 {
+            return Err(close(error::from_code(result)));
+        }
+        let base = buffer.as_mut_slice().as_mut_ptr();
+        Ok(Self {
+            handle,
+            info,
+            _ring: buffer,
+            base,
+        })
+    }
+
+    /// The underlying handle, for matching mailbox events.
+    pub fn handle(&self) -> Handle {
+        self.handle
+    }
+
+    /// The device's ethernet (MAC) address.
+    pub fn mac_address(&self) -> [u8; 6] {
+        self.info.mac
+    }
+
+    /// Largest frame the device accepts, ethernet header included.
+    pub fn max_frame_size(&self) -> usize {
+        self.info.max_frame_size as usize
+    }
+
+    /// Transmit one ethernet frame: [`queue`](Self::queue) it, then
+    /// [`flush`](Self::flush).
+    pub fn send(&self, frame: &[u8]) -> Result<()> {
+        self.queue(frame)?;
+        self.flush().map(|_| ())
+    }
+
+    /// Put one ethernet frame in the ring to be sent on the next
+    /// [`flush`](Self::flush). A full ring is flushed first.
+    ///
+    /// Fails with `InvalidArgument` if `frame` is shorter than an ethernet
+    /// header or longer than [`max_frame_size`](Self::max_frame_size), and
+    /// with `WouldBlock` if the device's queue is too full to make room.
+    pub fn queue(&self, frame: &[u8]) -> Result<()> {
+        if !(NET_MIN_FRAME_SIZE..=self.max_frame_size()).contains(&frame.len()) {
+            return Err(ErrorCode::InvalidArgument);
+        }
+        let header = self.header();
+        let head = header.tx_head.load(Ordering::Relaxed);
+        let full = |tail: u32| head.wrapping_sub(tail) >= ring::RING_SLOTS as u32;
+        if full(header.tx_tail.load(Ordering::Acquire)) {
+            self.flush()?;
+            if full(header.tx_tail.load(Ordering::Acquire)) {
+                return Err(ErrorCode::WouldBlock);
+            }
+        }
+        let (len, data) = self.slot(ring::tx_slot(head));
+        // SAFETY: the slot has room for any frame up to the maximum size.
+        unsafe { core::ptr::copy_nonoverlapping(frame.as_ptr(), data, frame.len()) };
+        len.store(frame.len() as u32, Ordering::Relaxed);
+        header
+            .tx_head
+            .store(head.wrapping_add(1), Ordering::Release);
+        Ok(())
+    }
+
+    /// Have the kernel transmit the queued frames. Returns how many went;
+    /// any the device had no room for stay queued.
+    pub fn flush(&self) -> Result<usize> {
+        error::from_syscall(sys::net::kick(self.handle))
+    }
+
+    /// Receive one frame if any is waiting.
+    ///
+    /// Returns `Ok(None)` when no frame is queued. A frame longer than
+    /// `buf` is truncated.
+    pub fn try_recv(&self, buf: &mut [u8]) -> Result<Option<usize>> {
+        let header = self.header();
+        let tail = header.rx_tail.load(Ordering::Relaxed);
+        if header.rx_head.load(Ordering::Acquire) == tail {
+            return Ok(None);
+        }
+        let (len, data) = self.slot(ring::rx_slot(tail));
+        let len = (len.load(Ordering::Relaxed) as usize)
+            .min(NET_MAX_FRAME_SIZE)
+            .min(buf.len());
+        // SAFETY: the slot holds at least `len` bytes.
+        unsafe { core::ptr::copy_nonoverlapping(data, buf.as_mut_ptr(), len) };
+        header
+            .rx_tail
+            .store(tail.wrapping_add(1), Ordering::Release);
+        Ok(Some(len))
+    }
+
+    fn header(&self) -> &RingHeader {
+        // SAFETY: the ring buffer starts with the header and lives as long
+        // as `self`; its fields are atomics shared with the kernel.
+        unsafe { &*(self.base as *const RingHeader) }
+    }
+
+    /// The slot at `offset`: its length field and the frame bytes after it.
+    fn slot(&self, offset: usize) -> (&AtomicU32, *mut u8) {
+        // SAFETY: `offset` is a slot in the ring, which is `RING_SIZE`
+        // bytes long, and slots are aligned for their length.
+        unsafe {
+            let slot = self.base.add(offset);
+            (
+                AtomicU32::from_ptr(slot as *mut u32),
+                slot.add(ring::SLOT_LEN_SIZE),
+            )
+        }
+    }
+}
+
+impl Drop for NetDevice {
+    fn drop(&mut self) {
+        let _ = sys::file::close(self.handle);
+    }
+}
@@ -0,0 +1,273 @@
+//! Pointer (mouse and tablet) input.
+//!
+//! A `pointer:` handle delivers the device's raw evdev records; a
+//! [`PointerDevice`] gathers each run of them up to `SYN_REPORT` into one
+//! [`PointerEvent`]. Mice report relative motion ([`PointerEvent::dx`] and
+//! [`PointerEvent::dy`]); tablets report an absolute position in their own
+//! units ([`PointerEvent::position`]), which [`PointerDevice::scale`] maps
+//! to the screen.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use libpanda::mailbox::Mailbox;
+//! use libpanda::pointer::PointerDevice;
+//!
+//! let mailbox = Mailbox::default();
+//! let mut pointer = PointerDevice::open_with_mailbox("pointer:/pci/input/0", &mailbox).unwrap();
+//! loop {
+//!     let (_, events) = mailbox.recv();
+//!     if events.is_pointer() {
+//!         while let Ok(Some(event)) = pointer.try_read_event() {
+//!             if let Some((x, y)) = event.position {
+//!                 let (x, y) = pointer.scale(x, y, 1280, 800);
+//!                 // move the cursor to (x, y)
+//!             }
+//!         }
+//!     }
+//! }
+//! ```
+
+use crate::environment;
+use crate::error::{self, Result};
+use crate::handle::Handle;
+use crate::keyboard::RawInputEvent;
+use crate::mailbox::Mailbox;
+use crate::sys;
+use panda_abi::*;
+
+// Event types and codes (evdev encoding, as in `keyboard`).
+pub const EV_SYN: u16 = 0x00;
+pub const EV_KEY: u16 = 0x01;
+pub const EV_REL: u16 = 0x02;
+pub const EV_ABS: u16 = 0x03;
+pub const SYN_REPORT: u16 = 0x00;
+pub const REL_X: u16 = 0x00;
+pub const REL_Y: u16 = 0x01;
+pub const REL_WHEEL: u16 = 0x08;
+pub const ABS_X: u16 = 0x00;
+pub const ABS_Y: u16 = 0x01;
+pub const BTN_LEFT: u16 = 0x110;
+pub const BTN_RIGHT: u16 = 0x111;
+pub const BTN_MIDDLE: u16 = 0x112;
+
+/// A set of pointer buttons.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Buttons(u8);
+
+impl Buttons {
+    pub const NONE: Self = Self(0);
+    pub const LEFT: Self = Self(1 << 0);
+    pub const RIGHT: Self = Self(1 << 1);
+    pub const MIDDLE: Self = Self(1 << 2);
+
+    /// The button for an `EV_KEY` code, if it is one we track.
+    pub fn from_code(code: u16) -> Option<Self> {
+        match code {
+            BTN_LEFT => Some(Self::LEFT),
+            BTN_RIGHT => Some(Self::RIGHT),
+            BTN_MIDDLE => Some(Self::MIDDLE),
+            _ => None,
+        }
+    }
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    fn insert(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+
+    fn remove(&mut self, other: Self) {
+        self.0 &= !other.0;
+    }
+}
+
+/// One report from a pointer device: everything between two `SYN_REPORT`s.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PointerEvent {
+    /// Horizontal motion since the last report (relative devices).
+    pub dx: i32,
+    /// Vertical motion since the last report (relative devices).
+    pub dy: i32,
+    /// The position after this report, in device units (absolute devices,
+    /// and only when the report moved the pointer).
+    pub position: Option<(u32, u32)>,
+    /// Scroll wheel clicks; positive is away from the user.
+    pub wheel: i32,
+    /// Buttons held down after this report.
+    pub buttons: Buttons,
+    /// Buttons that went down in this report.
+    pub pressed: Buttons,
+    /// Buttons that came up in this report.
+    pub released: Buttons,
+}
+
+impl PointerEvent {
+    /// Whether the report moved the pointer.
+    pub fn is_motion(&self) -> bool {
+        self.dx != 0 || self.dy != 0 || self.position.is_some()
+    }
+}
+
+/// An open mouse or tablet. Closed on drop.
+pub struct PointerDevice {
+    handle: Handle,
+    info: PointerInfo,
+    /// The report being assembled from raw events.
+    pending: PointerEvent,
+    buttons: Buttons,
+    x: u32,
+    y: u32,
+}
+
+impl PointerDevice {
+    /// Open a pointer device by URI, e.g. `pointer:/pci/input/0`.
+    pub fn open(uri: &str) -> Result<Self> {
+        Self::from_handle(environment::open(uri, 0, 0)?)
+    }
+
+    /// Open a pointer device and attach it to `mailbox`, which then
+    /// receives `EVENT_POINTER` whenever a report arrives.
+    pub fn open_with_mailbox(uri: &str, mailbox: &Mailbox) -> Result<Self> {
+        let handle = environment::open(uri, mailbox.handle().as_raw(), EVENT_POINTER)?;
+        Self::from_handle(handle)
+    }
+
+    fn from_handle(handle: Handle) -> Result<Self> {
+        let mut info = PointerInfo::default();
+        let result = sys::pointer::info(handle, &mut info);
+        if result < 0 {
+            let _ = sys::file::close(handle);
+            return Err(error::from_code(result));
+        }
+        Ok(Self {
+            handle,
+            info,
+            pending: PointerEvent::default(),
+            buttons: Buttons::NONE,
+            x: info.x_min,
+            y: info.y_min,
+        })
+    }
+
+    /// The underlying handle, for matching mailbox events.
+    pub fn handle(&self) -> Handle {
+        self.handle
+    }
+
+    /// The device description from `OP_POINTER_INFO`.
+    pub fn info(&self) -> PointerInfo {
+        self.info
+    }
+
+    /// Whether the device reports absolute positions (a tablet) rather than
+    /// relative motion (a mouse).
+    pub fn is_absolute(&self) -> bool {
+        self.info.flags & POINTER_ABSOLUTE != 0
+    }
+
+    /// Whether the device has a scroll wheel.
+    pub fn has_wheel(&self) -> bool {
+        self.info.flags & POINTER_WHEEL != 0
+    }
+
+    /// Map an absolute position to a `width` x `height` screen.
+    pub fn scale(&self, x: u32, y: u32, width: u32, height: u32) -> (u32, u32) {
+        (
+            scale_axis(x, self.info.x_min, self.info.x_max, width),
+            scale_axis(y, self.info.y_min, self.info.y_max, height),
+        )
+    }
+
+    /// Read the next report, blocking until one is complete.
+    pub fn read_event(&mut self) -> Result<PointerEvent> {
+        loop {
+            let mut raw = [0u8; 8];
+            let len = error::from_syscall(sys::file::read(self.handle, &mut raw))?;
+            if let Some(event) = self.feed(&raw[..len]) {
+                return Ok(event);
+            }
+        }
+    }
+
+    /// Read the next report if one is complete.
+    ///
+    /// Returns `Ok(None)` once the queued events are used up; any partial
+    /// report is kept for the next call.
+    pub fn try_read_event(&mut self) -> Result<Option<PointerEvent>> {
+        loop {
+            let mut raw = [0u8; 8];
+            let len = error::from_syscall(sys::file::try_read(self.handle, &mut raw))?;
+            if len == 0 {
+                return Ok(None);
+            }
+            if let Some(event) = self.feed(&raw[..len]) {
+                return Ok(Some(event));
+            }
+        }
+    }
+
+    /// Add one raw record to the pending report, returning the report if the
+    /// record completes it.
+    fn feed(&mut self, raw: &[u8]) -> Option<PointerEvent> {
+        if raw.len() < core::mem::size_of::<RawInputEvent>() {
+            return None;
+        }
+        let event = RawInputEvent {
+            event_type: u16::from_ne_bytes([raw[0], raw[1]]),
+            code: u16::from_ne_bytes([raw[2], raw[3]]),
+            value: u32::from_ne_bytes([raw[4], raw[5], raw[6], raw[7]]),
+        };
+        let pending = &mut self.pending;
+        match (event.event_type, event.code) {
+            (EV_REL, REL_X) => pending.dx += event.value as i32,
+            (EV_REL, REL_Y) => pending.dy += event.value as i32,
+            (EV_REL, REL_WHEEL) => pending.wheel += event.value as i32,
+            (EV_ABS, ABS_X) => {
+                self.x = event.value;
+                pending.position = Some((self.x, self.y));
+            }
+            (EV_ABS, ABS_Y) => {
+                self.y = event.value;
+                pending.position = Some((self.x, self.y));
+            }
+            (EV_KEY, code) => {
+                let button = Buttons::from_code(code)?;
+                if event.value != 0 {
+                    self.buttons.insert(button);
+                    pending.pressed.insert(button);
+                } else {
+                    self.buttons.remove(button);
+                    pending.released.insert(button);
+                }
+            }
+            (EV_SYN, SYN_REPORT) => {
+                let mut report = core::mem::take(pending);
+                report.buttons = self.buttons;
+                return Some(report);
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+impl Drop for PointerDevice {
+    fn drop(&mut self) {
+        let _ = sys::file::close(self.handle);
+    }
+}
+
+fn scale_axis(value: u32, min: u32, max: u32, size: u32) -> u32 {
+    if max <= min || size == 0 {
+        return 0;
+    }
+    let value = value.clamp(min, max) - min;
+    ((value as u64 * (size as u64 - 1)) / (max - min) as u64) as u32
+}
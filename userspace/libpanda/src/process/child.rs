@@ -238,8 +238,9 @@ impl<'a> ChildBuilder<'a> {
         let stdin_raw = self.stdin.map_or(0, |h| h.as_raw());
         let stdout_raw = self.stdout.map_or(0, |h| h.as_raw());
 
+        let path = crate::env::resolve_path(self.path);
         let result = sys::env::spawn(
-            self.path,
+            &path,
             mailbox_raw,
             self.event_mask,
             stdin_raw,
@@ -284,9 +285,10 @@ impl<'a> ChildBuilder<'a> {
             .map(|(k, v)| (k.as_str(), v.as_str()))
             .collect();
 
-        // Send startup message with arguments and environment
+        // Send startup message with arguments, environment and working directory
         let mut buf = [0u8; MAX_MESSAGE_SIZE];
-        if let Ok(len) = crate::startup::encode_with_env(&self.args, &env_refs, &mut buf) {
+        let cwd = crate::env::current_dir();
+        if let Ok(len) = crate::startup::encode_with_cwd(&self.args, &env_refs, &cwd, &mut buf) {
             // Best effort - ignore send errors
             let _ = sys::channel::send_msg(handle, &buf[..len]);
         }
@@ -323,11 +325,18 @@ impl ExitStatus {
 }
 
 /// Signals that can be sent to a process.
+///
+/// The kernel acts on these itself; the process is not told. A process
+/// ended by one exits with [`panda_abi::SIGNAL_EXIT_BASE`] plus the signal.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u32)]
 pub enum Signal {
-    /// Terminate the process gracefully.
-    Term = 0,
+    /// Terminate the process.
+    Term = panda_abi::SIGNAL_TERM,
     /// Kill the process immediately.
-    Kill = 1,
+    Kill = panda_abi::SIGNAL_KILL,
+    /// Stop the process until it is continued.
+    Stop = panda_abi::SIGNAL_STOP,
+    /// Resume a stopped process.
+    Continue = panda_abi::SIGNAL_CONTINUE,
 }
@@ -18,7 +18,7 @@
 
 mod child;
 
-pub use child::{Child, ChildBuilder, ExitStatus};
+pub use child::{Child, ChildBuilder, ExitStatus, Signal};
 
 use crate::Handle;
 use crate::sys;
@@ -49,7 +49,7 @@ pub fn wait(child_handle: Handle) -> i32 {
     sys::process::wait(child_handle)
 }
 
-/// Send a signal to a process.
+/// Send a signal to a process: one of the [`Signal`]s, as a number.
 ///
 /// Returns 0 on success, or negative error code.
 #[inline(always)]
@@ -0,0 +1,240 @@
+//! TCP and UDP sockets.
+//!
+//! Sockets are channels to the network service (`netd`), opened by
+//! connecting to its `tcp:` and `udp:` schemes; `panda_abi::socket`
+//! documents the paths and what each channel carries. The types here just
+//! build those paths and frame the messages.
+//!
+//! A refused or unreachable connection fails with `NotFound`, the error the
+//! kernel reports for any failed connect.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use libpanda::io::{Read, Write};
+//! use libpanda::socket::{Ipv4Addr, SocketAddrV4, TcpStream};
+//!
+//! let addr = SocketAddrV4::new(Ipv4Addr::new(10, 0, 2, 2), 8080);
+//! let mut stream = TcpStream::connect(addr).unwrap();
+//! stream.write_all(b"GET / HTTP/1.0\r\n\r\n").unwrap();
+//! let mut response = libpanda::Vec::new();
+//! stream.read_to_end(&mut response).unwrap();
+//! ```
+
+use alloc::format;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::environment;
+use crate::error::Result;
+use crate::io::{Read, Write};
+use crate::ipc::Channel;
+use panda_abi::socket::ADDRESS_LEN;
+use panda_abi::{ErrorCode, MAX_MESSAGE_SIZE};
+
+pub use panda_abi::socket::{Ipv4Addr, MAX_DATAGRAM_SIZE, SocketAddrV4};
+
+fn connect(uri: &str) -> Result<Channel> {
+    let handle = environment::connect(uri)?;
+    Channel::from_handle(handle).ok_or(ErrorCode::InvalidHandle)
+}
+
+/// A TCP connection. Closed on drop.
+///
+/// Reads return 0 once the peer has closed its side and everything it sent
+/// has been read.
+pub struct TcpStream {
+    channel: Channel,
+    peer: SocketAddrV4,
+    /// The unread rest of the last message received.
+    rx: Vec<u8>,
+    rx_pos: usize,
+}
+
+impl TcpStream {
+    /// Connect to `addr`, blocking until the connection is established or
+    /// fails.
+    pub fn connect(addr: SocketAddrV4) -> Result<Self> {
+        let channel = connect(&format!("tcp:{}", addr))?;
+        Ok(Self::from_channel(channel, addr))
+    }
+
+    fn from_channel(channel: Channel, peer: SocketAddrV4) -> Self {
+        Self {
+            channel,
+            peer,
+            rx: Vec::new(),
+            rx_pos: 0,
+        }
+    }
+
+    /// The address of the remote end.
+    pub fn peer_addr(&self) -> SocketAddrV4 {
+        self.peer
+    }
+
+    /// The channel carrying the byte stream, e.g. to attach to a mailbox.
+    pub fn channel(&self) -> &Channel {
+        &self.channel
+    }
+}
+
+impl Read for TcpStream {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if self.rx_pos == self.rx.len() {
+            self.rx.resize(MAX_MESSAGE_SIZE, 0);
+            self.rx_pos = 0;
+            match self.channel.recv(&mut self.rx) {
+                Ok(len) => self.rx.truncate(len),
+                Err(ErrorCode::ChannelClosed) => {
+                    self.rx.clear();
+                    return Ok(0);
+                }
+                Err(e) => {
+                    self.rx.clear();
+                    return Err(e);
+                }
+            }
+        }
+        let len = buf.len().min(self.rx.len() - self.rx_pos);
+        buf[..len].copy_from_slice(&self.rx[self.rx_pos..self.rx_pos + len]);
+        self.rx_pos += len;
+        Ok(len)
+    }
+}
+
+impl Write for TcpStream {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let len = buf.len().min(MAX_MESSAGE_SIZE);
+        self.channel.send(&buf[..len])?;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// A TCP listener. Stops listening on drop.
+pub struct TcpListener {
+    channel: Channel,
+    port: u16,
+}
+
+impl TcpListener {
+    /// Listen for connections on `port`. Fails with `NotFound` if the port
+    /// is already taken.
+    pub fn bind(port: u16) -> Result<Self> {
+        let channel = connect(&format!("tcp:/listen/{}", port))?;
+        Ok(Self { channel, port })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Wait for the next connection.
+    pub fn accept(&self) -> Result<(TcpStream, SocketAddrV4)> {
+        let mut buf = [0u8; ADDRESS_LEN];
+        let (len, handle) = self.channel.recv_with_handle(&mut buf)?;
+        let peer = SocketAddrV4::decode(&buf[..len]).ok_or(ErrorCode::Protocol)?;
+        let channel = handle
+            .and_then(Channel::from_handle)
+            .ok_or(ErrorCode::Protocol)?;
+        Ok((TcpStream::from_channel(channel, peer), peer))
+    }
+}
+
+/// A UDP socket: either connected to one peer or bound to a local port.
+/// Closed on drop.
+pub struct UdpSocket {
+    channel: Channel,
+    /// The peer of a connected socket.
+    peer: Option<SocketAddrV4>,
+}
+
+impl UdpSocket {
+    /// Bind to a local port (0 picks one) to exchange datagrams with any
+    /// peer through [`send_to`](Self::send_to) and
+    /// [`recv_from`](Self::recv_from).
+    pub fn bind(port: u16) -> Result<Self> {
+        let channel = connect(&format!("udp:/bind/{}", port))?;
+        Ok(Self {
+            channel,
+            peer: None,
+        })
+    }
+
+    /// A socket that exchanges datagrams with `addr` only, through
+    /// [`send`](Self::send) and [`recv`](Self::recv).
+    pub fn connect(addr: SocketAddrV4) -> Result<Self> {
+        let channel = connect(&format!("udp:{}", addr))?;
+        Ok(Self {
+            channel,
+            peer: Some(addr),
+        })
+    }
+
+    /// The peer of a connected socket.
+    pub fn peer_addr(&self) -> Option<SocketAddrV4> {
+        self.peer
+    }
+
+    /// Send a datagram to the connected peer.
+    pub fn send(&self, data: &[u8]) -> Result<()> {
+        if self.peer.is_none() {
+            return Err(ErrorCode::InvalidArgument);
+        }
+        if data.len() > MAX_DATAGRAM_SIZE {
+            return Err(ErrorCode::MessageTooLarge);
+        }
+        self.channel.send(data)
+    }
+
+    /// Receive a datagram from the connected peer, blocking until one
+    /// arrives. A datagram longer than `buf` is truncated.
+    pub fn recv(&self, buf: &mut [u8]) -> Result<usize> {
+        if self.peer.is_none() {
+            return Err(ErrorCode::InvalidArgument);
+        }
+        let mut message = [0u8; MAX_DATAGRAM_SIZE];
+        let len = self.channel.recv(&mut message)?;
+        let len = len.min(buf.len());
+        buf[..len].copy_from_slice(&message[..len]);
+        Ok(len)
+    }
+
+    /// Send a datagram to `addr` from a bound socket.
+    pub fn send_to(&self, data: &[u8], addr: SocketAddrV4) -> Result<()> {
+        if self.peer.is_some() {
+            return Err(ErrorCode::InvalidArgument);
+        }
+        if data.len() > MAX_DATAGRAM_SIZE {
+            return Err(ErrorCode::MessageTooLarge);
+        }
+        let mut message = vec![0u8; ADDRESS_LEN + data.len()];
+        addr.encode(&mut message)
+            .ok_or(ErrorCode::InvalidArgument)?;
+        message[ADDRESS_LEN..].copy_from_slice(data);
+        self.channel.send(&message)
+    }
+
+    /// Receive a datagram and its sender on a bound socket, blocking until
+    /// one arrives. A datagram longer than `buf` is truncated.
+    pub fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddrV4)> {
+        if self.peer.is_some() {
+            return Err(ErrorCode::InvalidArgument);
+        }
+        let mut message = [0u8; ADDRESS_LEN + MAX_DATAGRAM_SIZE];
+        let len = self.channel.recv(&mut message)?;
+        let from = SocketAddrV4::decode(&message[..len]).ok_or(ErrorCode::Protocol)?;
+        let len = (len - ADDRESS_LEN).min(buf.len());
+        buf[..len].copy_from_slice(&message[ADDRESS_LEN..ADDRESS_LEN + len]);
+        Ok((len, from))
+    }
+
+    /// The channel carrying the datagrams, e.g. to attach to a mailbox.
+    pub fn channel(&self) -> &Channel {
+        &self.channel
+    }
+}
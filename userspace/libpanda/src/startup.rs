@@ -7,6 +7,7 @@
 //! - version: u16
 //! - args: Vec<String>
 //! - env: Vec<(String, String)>
+//! - cwd: String (optional: the working directory; absent means `file:/`)
 
 use alloc::string::String;
 use alloc::vec::Vec;
@@ -85,6 +86,42 @@ pub fn encode_with_env(
     args: &[&str],
     env: &[(&str, &str)],
     buf: &mut [u8],
+) -> Result<usize, StartupError> {
+    encode_message(args, env, None, buf)
+}
+
+/// Encode arguments, environment variables and the working directory into
+/// a startup message.
+///
+/// Returns the number of bytes written to `buf`.
+///
+/// # Examples
+///
+/// ```
+/// use libpanda::startup::{decode_with_cwd, encode_with_cwd};
+///
+/// let mut buf = [0u8; 256];
+/// let len = encode_with_cwd(&["prog"], &[], "file:/mnt", &mut buf).unwrap();
+///
+/// let (args, env, cwd) = decode_with_cwd(&buf[..len]).unwrap();
+/// assert_eq!(args, vec!["prog"]);
+/// assert!(env.is_empty());
+/// assert_eq!(cwd.as_deref(), Some("file:/mnt"));
+/// ```
+pub fn encode_with_cwd(
+    args: &[&str],
+    env: &[(&str, &str)],
+    cwd: &str,
+    buf: &mut [u8],
+) -> Result<usize, StartupError> {
+    encode_message(args, env, Some(cwd), buf)
+}
+
+fn encode_message(
+    args: &[&str],
+    env: &[(&str, &str)],
+    cwd: Option<&str>,
+    buf: &mut [u8],
 ) -> Result<usize, StartupError> {
     let mut enc = Encoder::new();
 
@@ -104,6 +141,10 @@ pub fn encode_with_env(
         enc.write_string(value);
     }
 
+    if let Some(cwd) = cwd {
+        enc.write_string(cwd);
+    }
+
     let encoded = enc.finish();
     if encoded.len() > buf.len() {
         return Err(StartupError::BufferTooSmall);
@@ -153,6 +194,29 @@ pub fn decode(buf: &[u8]) -> Result<Vec<String>, StartupError> {
 /// assert_eq!(decode_full(&buf[..len - 3]), Err(StartupError::InvalidMessage));
 /// ```
 pub fn decode_full(buf: &[u8]) -> Result<(Vec<String>, Vec<(String, String)>), StartupError> {
+    let (args, env, _cwd) = decode_with_cwd(buf)?;
+    Ok((args, env))
+}
+
+/// A startup message's arguments, environment variables and working
+/// directory.
+pub type Startup = (Vec<String>, Vec<(String, String)>, Option<String>);
+
+/// Decode a startup message into arguments, environment variables and the
+/// working directory, if the message has one.
+///
+/// # Examples
+///
+/// ```
+/// use libpanda::startup::{decode_with_cwd, encode_with_env};
+///
+/// // A message without a working directory
+/// let mut buf = [0u8; 256];
+/// let len = encode_with_env(&["prog"], &[], &mut buf).unwrap();
+/// let (_, _, cwd) = decode_with_cwd(&buf[..len]).unwrap();
+/// assert_eq!(cwd, None);
+/// ```
+pub fn decode_with_cwd(buf: &[u8]) -> Result<Startup, StartupError> {
     let mut dec = Decoder::new(buf);
 
     // Read version
@@ -184,7 +248,17 @@ pub fn decode_full(buf: &[u8]) -> Result<(Vec<String>, Vec<(String, String)>), S
         env.push((key, value));
     }
 
-    Ok((args, env))
+    // Read cwd, if present
+    let cwd = if dec.is_empty() {
+        None
+    } else {
+        Some(
+            dec.read_string()
+                .map_err(|_| StartupError::InvalidMessage)?,
+        )
+    };
+
+    Ok((args, env, cwd))
 }
 
 /// Calculate the size needed to encode the given arguments.
@@ -219,18 +293,19 @@ pub fn encoded_size_with_env(args: &[&str], env: &[(&str, &str)]) -> usize {
 /// Blocks until the startup message is received.
 /// Returns an empty Vec if no parent channel exists or the message is invalid.
 pub fn receive_args() -> Vec<String> {
-    let (args, _env) = receive_startup();
+    let (args, _env, _cwd) = receive_startup();
     args
 }
 
-/// Receive startup arguments and environment from the parent process.
+/// Receive startup arguments, environment and working directory from the
+/// parent process.
 ///
 /// This should be called early in program startup to receive the
 /// startup message sent by the parent via the HANDLE_PARENT channel.
 ///
 /// Blocks until the startup message is received.
 /// Returns empty Vecs if no parent channel exists or the message is invalid.
-pub fn receive_startup() -> (Vec<String>, Vec<(String, String)>) {
+pub fn receive_startup() -> Startup {
     use crate::channel;
     use crate::handle::Handle;
     use panda_abi::HANDLE_PARENT;
@@ -240,7 +315,7 @@ pub fn receive_startup() -> (Vec<String>, Vec<(String, String)>) {
 
     // Block waiting for the startup message from parent
     match channel::recv(parent, &mut buf) {
-        Ok(len) => decode_full(&buf[..len]).unwrap_or_default(),
-        Err(_) => (Vec::new(), Vec::new()),
+        Ok(len) => decode_with_cwd(&buf[..len]).unwrap_or_default(),
+        Err(_) => (Vec::new(), Vec::new(), None),
     }
 }
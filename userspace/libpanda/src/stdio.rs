@@ -9,6 +9,10 @@
 //! should use `Handle::PARENT` directly to communicate with their parent
 //! (typically the terminal).
 //!
+//! The shell can also redirect either handle to a file (`cmd < in > out`).
+//! Reads and writes here then go to the file instead: a read returns its
+//! bytes in turn, and a written `Value` is written as text.
+//!
 //! # Design
 //!
 //! - `stdin()` / `stdout()` - return the raw stdio handles (may be invalid)
@@ -37,6 +41,7 @@
 use crate::error::{self, Result};
 use crate::handle::Handle;
 use crate::sys;
+use panda_abi::ErrorCode;
 
 /// Returns the standard input handle.
 ///
@@ -74,6 +79,9 @@ pub fn parent() -> Handle {
 /// the peer closed the channel.
 pub fn read(buf: &mut [u8]) -> Result<usize> {
     let result = sys::channel::recv_msg(Handle::STDIN, buf);
+    if not_a_channel(result) {
+        return error::from_syscall(sys::file::read(Handle::STDIN, buf));
+    }
     if result < 0 {
         Err(error::from_code(result))
     } else {
@@ -86,6 +94,9 @@ pub fn read(buf: &mut [u8]) -> Result<usize> {
 /// Returns `Ok(n)` with bytes read, or `Err` if no data available or error.
 pub fn try_read(buf: &mut [u8]) -> Result<usize> {
     let result = sys::channel::try_recv_msg(Handle::STDIN, buf);
+    if not_a_channel(result) {
+        return error::from_syscall(sys::file::read(Handle::STDIN, buf));
+    }
     if result < 0 {
         Err(error::from_code(result))
     } else {
@@ -99,6 +110,9 @@ pub fn try_read(buf: &mut [u8]) -> Result<usize> {
 /// or the peer closed the channel.
 pub fn write(data: &[u8]) -> Result<()> {
     let result = sys::channel::send_msg(Handle::STDOUT, data);
+    if not_a_channel(result) {
+        return write_file(data);
+    }
     if result < 0 {
         Err(error::from_code(result))
     } else {
@@ -111,6 +125,9 @@ pub fn write(data: &[u8]) -> Result<()> {
 /// Returns `Ok(())` if written, or `Err` if queue full or error.
 pub fn try_write(data: &[u8]) -> Result<()> {
     let result = sys::channel::try_send_msg(Handle::STDOUT, data);
+    if not_a_channel(result) {
+        return write_file(data);
+    }
     if result < 0 {
         Err(error::from_code(result))
     } else {
@@ -118,6 +135,24 @@ pub fn try_write(data: &[u8]) -> Result<()> {
     }
 }
 
+/// Whether a channel operation on a stdio handle failed because the handle
+/// is not a channel, as when the shell redirected it to a file.
+fn not_a_channel(result: isize) -> bool {
+    result < 0 && error::from_code(result) == ErrorCode::InvalidHandle
+}
+
+/// Write all of `data` to standard output as a file.
+fn write_file(mut data: &[u8]) -> Result<()> {
+    while !data.is_empty() {
+        let written = error::from_syscall(sys::file::write(Handle::STDOUT, data))?;
+        if written == 0 {
+            return Err(ErrorCode::NoSpace);
+        }
+        data = &data[written..];
+    }
+    Ok(())
+}
+
 /// Write a string to standard output.
 #[inline]
 pub fn print(s: &str) -> Result<()> {
@@ -134,15 +169,26 @@ pub fn println(s: &str) -> Result<()> {
 // Value-based I/O for structured pipelines
 // =============================================================================
 
-use panda_abi::MAX_MESSAGE_SIZE;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
 use panda_abi::encoding::{Decode, Decoder, Encode, Encoder};
 use panda_abi::value::Value;
+use panda_abi::{FileStat, MAX_MESSAGE_SIZE};
 
 /// Write a structured Value to standard output.
 ///
 /// This encodes the Value to binary and sends it through the stdout channel.
-/// Used for structured pipeline communication.
+/// Used for structured pipeline communication. If stdout is a file, the
+/// Value is written as text: strings and bytes as they are, anything else
+/// a line at a time.
 pub fn write_value(value: &Value) -> Result<()> {
+    if is_file(Handle::STDOUT) {
+        let mut text = Vec::new();
+        write_text(value, &mut text);
+        return write_file(&text);
+    }
+
     let mut encoder = Encoder::new();
     value.encode(&mut encoder);
     let bytes = encoder.finish();
@@ -157,7 +203,9 @@ pub fn write_value(value: &Value) -> Result<()> {
 /// Read a structured Value from standard input.
 ///
 /// This reads bytes from stdin and decodes them as a Value.
-/// Returns `None` if the channel is closed.
+/// Returns `None` if the channel is closed. If stdin is a file, each read
+/// returns the next chunk of it as a `Value::String`, or as `Value::Bytes`
+/// if the chunk is not UTF-8, and `None` at its end.
 pub fn read_value() -> Result<Option<Value>> {
     let mut buf = [0u8; MAX_MESSAGE_SIZE];
     let n = read(&mut buf)?;
@@ -166,6 +214,10 @@ pub fn read_value() -> Result<Option<Value>> {
         return Ok(None);
     }
 
+    if is_file(Handle::STDIN) {
+        return Ok(Some(bytes_value(&buf[..n])));
+    }
+
     let mut decoder = Decoder::new(&buf[..n]);
     match Value::decode(&mut decoder) {
         Ok(value) => Ok(Some(value)),
@@ -180,6 +232,7 @@ pub fn read_value() -> Result<Option<Value>> {
 pub fn try_read_value() -> Result<Option<Value>> {
     let mut buf = [0u8; MAX_MESSAGE_SIZE];
     match try_read(&mut buf) {
+        Ok(n) if n > 0 && is_file(Handle::STDIN) => Ok(Some(bytes_value(&buf[..n]))),
         Ok(n) if n > 0 => {
             let mut decoder = Decoder::new(&buf[..n]);
             match Value::decode(&mut decoder) {
@@ -192,14 +245,81 @@ pub fn try_read_value() -> Result<Option<Value>> {
     }
 }
 
+/// Whether `handle` is a file rather than a channel.
+fn is_file(handle: Handle) -> bool {
+    let mut stat = FileStat {
+        size: 0,
+        is_dir: false,
+    };
+    sys::file::stat(handle, &mut stat) >= 0
+}
+
+/// Bytes read from a file, as text if they are text.
+fn bytes_value(bytes: &[u8]) -> Value {
+    match core::str::from_utf8(bytes) {
+        Ok(text) => Value::String(String::from(text)),
+        Err(_) => Value::Bytes(bytes.to_vec()),
+    }
+}
+
+/// Append `value` to `out` as plain text.
+fn write_text(value: &Value, out: &mut Vec<u8>) {
+    match value {
+        Value::String(text) => out.extend_from_slice(text.as_bytes()),
+        Value::Bytes(bytes) => out.extend_from_slice(bytes),
+        Value::Styled(_, inner) | Value::Link { inner, .. } => write_text(inner, out),
+        Value::Array(items) => {
+            for item in items {
+                write_line(item, out);
+            }
+        }
+        Value::Map(map) => {
+            for (key, item) in map {
+                out.extend_from_slice(key.as_bytes());
+                out.extend_from_slice(b": ");
+                write_line(item, out);
+            }
+        }
+        Value::Table(table) => {
+            for row in table.headers.as_deref().into_iter().chain(table.row_iter()) {
+                for (column, cell) in row.iter().enumerate() {
+                    if column > 0 {
+                        out.push(b'\t');
+                    }
+                    write_text(cell, out);
+                }
+                out.push(b'\n');
+            }
+        }
+        Value::Null | Value::Bool(_) | Value::Int(_) | Value::Float(_) => {
+            write_line(value, out);
+        }
+    }
+}
+
+/// Append `value` to `out` as a line of text.
+fn write_line(value: &Value, out: &mut Vec<u8>) {
+    match value {
+        Value::Null => out.extend_from_slice(b"null"),
+        Value::Bool(b) => out.extend_from_slice(if *b { b"true" } else { b"false" }),
+        Value::Int(i) => out.extend_from_slice(format!("{}", i).as_bytes()),
+        Value::Float(f) => out.extend_from_slice(format!("{}", f).as_bytes()),
+        value => write_text(value, out),
+    }
+    if out.last() != Some(&b'\n') {
+        out.push(b'\n');
+    }
+}
+
 // =============================================================================
 // Pipeline detection and output helpers
 // =============================================================================
 
 /// Check if this process is running in a pipeline (has valid STDOUT).
 ///
-/// Returns `true` if STDOUT is connected to another pipeline stage,
-/// `false` if running standalone (output goes to PARENT/terminal).
+/// Returns `true` if STDOUT is connected to another pipeline stage or
+/// redirected to a file, `false` if running standalone (output goes to
+/// PARENT/terminal).
 ///
 /// This is determined by attempting a non-blocking write to STDOUT.
 /// If STDOUT is invalid (not set up by parent), the operation fails.
@@ -211,7 +331,7 @@ pub fn is_pipeline() -> bool {
     // If result is 0, STDOUT is valid and we're in a pipeline
     // If result is negative, either STDOUT is invalid or queue is full
     // We consider "queue full" (-1) as a valid pipeline state
-    result >= -1
+    result >= -1 || is_file(Handle::STDOUT)
 }
 
 /// Output a Value, choosing the appropriate channel based on context.
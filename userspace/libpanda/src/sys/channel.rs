@@ -76,6 +76,21 @@ pub fn send_msg_with_handle(handle: Handle, msg: &[u8], attach: Handle) -> isize
     )
 }
 
+/// Send a message on a channel with an attached handle (non-blocking).
+///
+/// Returns 0 on success, or negative error code (e.g., queue full).
+#[inline(always)]
+pub fn try_send_msg_with_handle(handle: Handle, msg: &[u8], attach: Handle) -> isize {
+    send(
+        handle,
+        OP_CHANNEL_SEND,
+        msg.as_ptr() as usize,
+        msg.len(),
+        CHANNEL_NONBLOCK as usize,
+        u64::from(attach) as usize,
+    )
+}
+
 /// Receive a message from a channel (blocking if queue empty).
 ///
 /// Returns number of bytes received on success, or negative error code.
@@ -1,13 +1,12 @@
 //! Low-level device driver model syscalls.
 //!
-//! Only [`subscribe`] and [`claim`] round-trip to a working kernel handler
-//! today (see `panda-kernel/src/syscall/device.rs`). The MMIO/DMA/IRQ
-//! operations are defined for ABI completeness but require IOMMU support
-//! (a later, separate task) to implement safely — see the `Err`-returning
-//! stubs in `crate::device` for those.
+//! The MMIO and IRQ operations have no kernel handler yet — see the
+//! `Err`-returning stubs in `crate::device` for those.
 
 use super::{Handle, send};
-use panda_abi::device::{OP_DEVICE_CLAIM, OP_DEVICE_SUBSCRIBE};
+use panda_abi::device::{
+    DmaAllocInfo, OP_DEVICE_CLAIM, OP_DEVICE_SUBSCRIBE, OP_DMA_ALLOC, OP_DMA_FREE,
+};
 
 /// Subscribe to device events for `bus_type`, matching `match_data`.
 ///
@@ -40,3 +39,27 @@ pub fn subscribe(bus_type: u32, match_data: &[u8], mailbox: Handle) -> isize {
 pub fn claim(token: Handle) -> isize {
     send(token, OP_DEVICE_CLAIM, 0, 0, 0, 0)
 }
+
+/// Allocate `size` bytes of DMA memory for a claimed device, filling in
+/// `info` with its address and IOVA.
+///
+/// Returns 0, or a negative error code (`NotSupported` without an IOMMU).
+#[inline(always)]
+pub fn dma_alloc(device: Handle, size: usize, info: &mut DmaAllocInfo) -> isize {
+    send(
+        device,
+        OP_DMA_ALLOC,
+        size,
+        info as *mut DmaAllocInfo as usize,
+        0,
+        0,
+    )
+}
+
+/// Free memory allocated by [`dma_alloc`].
+///
+/// Returns 0, or a negative error code.
+#[inline(always)]
+pub fn dma_free(device: Handle, virt_addr: usize, size: usize) -> isize {
+    send(device, OP_DMA_FREE, virt_addr, size, 0, 0)
+}
@@ -43,3 +43,65 @@ pub fn flush(handle: Handle, rect: Option<&SurfaceRect>) -> isize {
     };
     send(handle, OP_DISPLAY_FLUSH, rect_ptr, 0, 0, 0)
 }
+
+/// Load the hardware cursor's image, or hide the cursor with `None`.
+///
+/// `image` is `DISPLAY_CURSOR_SIZE * DISPLAY_CURSOR_SIZE * 4` bytes of ARGB
+/// pixels and `(hot_x, hot_y)` the point within it that tracks the pointer.
+/// Returns 0 on success, or a negative error code (`NotSupported` if the
+/// display has no cursor plane).
+#[inline(always)]
+pub fn cursor_set(handle: Handle, image: Option<&[u8]>, hot_x: u32, hot_y: u32) -> isize {
+    let image_ptr = match image {
+        Some(image) => image.as_ptr() as usize,
+        None => 0,
+    };
+    send(
+        handle,
+        OP_DISPLAY_CURSOR_SET,
+        image_ptr,
+        hot_x as usize,
+        hot_y as usize,
+        0,
+    )
+}
+
+/// Move the hardware cursor's hotspot to a screen position.
+///
+/// Returns 0 on success, or a negative error code.
+#[inline(always)]
+pub fn cursor_move(handle: Handle, x: u32, y: u32) -> isize {
+    send(handle, OP_DISPLAY_CURSOR_MOVE, x as usize, y as usize, 0, 0)
+}
+
+/// List the display's modes into `modes`.
+///
+/// Returns how many modes the display has, which may be more than `modes`
+/// holds, or a negative error code.
+#[inline(always)]
+pub fn modes(handle: Handle, modes: &mut [DisplayMode]) -> isize {
+    send(
+        handle,
+        OP_DISPLAY_MODES,
+        modes.as_mut_ptr() as usize,
+        modes.len(),
+        0,
+        0,
+    )
+}
+
+/// Switch the display to one of its modes.
+///
+/// Returns 0 on success, or a negative error code. On success the
+/// framebuffer is replaced: re-query `info` and `map` again.
+#[inline(always)]
+pub fn set_mode(handle: Handle, width: u32, height: u32) -> isize {
+    send(
+        handle,
+        OP_DISPLAY_SET_MODE,
+        width as usize,
+        height as usize,
+        0,
+        0,
+    )
+}
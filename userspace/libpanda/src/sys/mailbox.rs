@@ -52,3 +52,18 @@ pub fn poll(mailbox: Handle, result: &mut MailboxEventResult) -> isize {
         0,
     )
 }
+
+/// Attach an open handle to a mailbox.
+///
+/// Returns 0 on success, negative error code on failure.
+#[inline(always)]
+pub fn attach(mailbox: Handle, handle: Handle, event_mask: u32) -> isize {
+    send(
+        mailbox,
+        OP_MAILBOX_ATTACH,
+        handle.as_raw() as usize,
+        event_mask as usize,
+        0,
+        0,
+    )
+}
@@ -14,6 +14,8 @@ pub mod display;
 pub mod env;
 pub mod file;
 pub mod mailbox;
+pub mod net;
+pub mod pointer;
 pub mod process;
 pub mod scheme;
 
@@ -33,6 +35,8 @@ pub use panda_abi::{
     EVENT_CHANNEL_WRITABLE,
     EVENT_DISPLAY_CHANGED,
     EVENT_KEYBOARD_KEY,
+    EVENT_NET_RX,
+    EVENT_POINTER,
     EVENT_PROCESS_EXITED,
     FILE_NONBLOCK,
     FileStat,
@@ -0,0 +1,32 @@
+//! Low-level network device operations.
+//!
+//! These act on a handle opened from the `net:` scheme
+//! (`net:/pci/network/0`). Frames themselves move through a ring in a
+//! shared buffer, laid out as in [`panda_abi::net`].
+
+use super::{Handle, send};
+use panda_abi::*;
+
+/// Get the device's MAC address and maximum frame size.
+///
+/// Returns 0 on success, or a negative error code.
+#[inline(always)]
+pub fn info(handle: Handle, info: &mut NetInfo) -> isize {
+    send(handle, OP_NET_INFO, info as *mut NetInfo as usize, 0, 0, 0)
+}
+
+/// Attach `buffer` as the device's frame ring.
+///
+/// Returns 0 on success, or a negative error code.
+#[inline(always)]
+pub fn ring(handle: Handle, buffer: Handle) -> isize {
+    send(handle, OP_NET_RING, buffer.as_raw() as usize, 0, 0, 0)
+}
+
+/// Transmit the frames queued in the ring.
+///
+/// Returns how many were sent, or a negative error code.
+#[inline(always)]
+pub fn kick(handle: Handle) -> isize {
+    send(handle, OP_NET_KICK, 0, 0, 0, 0)
+}
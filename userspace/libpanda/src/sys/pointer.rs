@@ -0,0 +1,23 @@
+//! Low-level pointer device operations.
+//!
+//! These act on a handle opened from the `pointer:` scheme
+//! (`pointer:/pci/input/0`). Events are read with [`super::file::read`],
+//! one 8-byte evdev record per call.
+
+use super::{Handle, send};
+use panda_abi::*;
+
+/// Get whether the device is relative or absolute, and its axis ranges.
+///
+/// Returns 0 on success, or a negative error code.
+#[inline(always)]
+pub fn info(handle: Handle, info: &mut PointerInfo) -> isize {
+    send(
+        handle,
+        OP_POINTER_INFO,
+        info as *mut PointerInfo as usize,
+        0,
+        0,
+        0,
+    )
+}
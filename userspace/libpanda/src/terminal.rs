@@ -35,6 +35,8 @@ use panda_abi::{HANDLE_PARENT, MAX_MESSAGE_SIZE};
 
 use crate::Handle;
 use crate::channel;
+use crate::error::Result;
+use crate::ipc::Channel;
 
 // Re-export commonly used types
 pub use panda_abi::terminal::{
@@ -347,6 +349,41 @@ pub fn capabilities() -> Option<TerminalCapabilities> {
     }
 }
 
+/// Ask the terminal for a connection to the compositor that may capture the
+/// screen and every window (see `graphics::capture`). `None` if it has none
+/// to give.
+pub fn capture_connection() -> Option<Channel> {
+    send_request(Request::Query(TerminalQuery::CaptureConnection));
+
+    let parent = unsafe { Handle::from_raw(HANDLE_PARENT) };
+    let mut buf = [0u8; MAX_MESSAGE_SIZE];
+
+    loop {
+        match channel::recv_with_handle(parent, &mut buf) {
+            Ok((len, attached)) => {
+                if let Ok((msg, _)) = Event::from_bytes(&buf[..len]) {
+                    if let Event::QueryResponse(QueryResponse::CaptureConnection { granted }) = msg
+                    {
+                        return attached.filter(|_| granted).and_then(Channel::from_handle);
+                    }
+                }
+            }
+            Err(_) => return None,
+        }
+    }
+}
+
+/// Answer a child's [`capture_connection`] with `connection`, or refuse it
+/// with `None`.
+pub fn send_capture_connection(child: Handle, connection: Option<&Channel>) -> Result<()> {
+    let granted = connection.is_some();
+    let bytes = Event::QueryResponse(QueryResponse::CaptureConnection { granted }).to_bytes();
+    match connection {
+        Some(connection) => channel::send_with_handle(child, &bytes, connection.untyped_handle()),
+        None => channel::send(child, &bytes),
+    }
+}
+
 // =============================================================================
 // Internal helpers
 // =============================================================================
@@ -0,0 +1,49 @@
+//! Helpers for the userspace tests in `userspace/tests/`.
+//!
+//! A test that checks a command-line tool spawns it from the initrd, where
+//! the Makefile copies the tools listed in the test's `_TOOLS` variable,
+//! and reads back what it sent over the terminal protocol.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use panda_abi::MAX_MESSAGE_SIZE;
+use panda_abi::terminal::Request;
+use panda_abi::value::Value;
+
+use crate::process::ChildBuilder;
+use crate::{Handle, channel, process};
+
+/// Run `file:/initrd/<tool>` with `args`, and `stdin` if given, and collect
+/// its exit code, the values it sent on and the errors it reported. A tool
+/// that could not be spawned exits `-1` with nothing sent.
+pub fn run(tool: &str, args: &[&str], stdin: Option<Handle>) -> (i32, Vec<Value>, Vec<String>) {
+    let mut argv = alloc::vec![tool];
+    argv.extend_from_slice(args);
+    let path = format!("file:/initrd/{tool}");
+    let mut builder = ChildBuilder::new(&path).args(&argv);
+    if let Some(stdin) = stdin {
+        builder = builder.stdin(stdin);
+    }
+    let Ok(child) = builder.spawn_handle() else {
+        return (-1, Vec::new(), Vec::new());
+    };
+
+    let mut values = Vec::new();
+    let mut errors = Vec::new();
+    let mut buf = [0u8; MAX_MESSAGE_SIZE];
+    while let Ok(len) = channel::recv(child, &mut buf) {
+        match Request::from_bytes(&buf[..len]) {
+            Ok((Request::Write(value), _)) => values.push(value),
+            Ok((Request::Error(Value::String(message)), _)) => errors.push(message),
+            _ => {}
+        }
+    }
+    (process::wait(child), values, errors)
+}
+
+/// `Value::String` of `s`.
+pub fn string(s: &str) -> Value {
+    Value::String(String::from(s))
+}
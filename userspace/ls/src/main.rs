@@ -19,18 +19,6 @@ fn rdtsc() -> u64 {
     ((hi as u64) << 32) | lo as u64
 }
 
-fn format_size(bytes: u64) -> String {
-    if bytes < 1024 {
-        alloc::format!("{}", bytes)
-    } else if bytes < 1024 * 1024 {
-        alloc::format!("{}K", bytes / 1024)
-    } else if bytes < 1024 * 1024 * 1024 {
-        alloc::format!("{}M", bytes / (1024 * 1024))
-    } else {
-        alloc::format!("{}G", bytes / (1024 * 1024 * 1024))
-    }
-}
-
 libpanda::main! { |args|
     // Default to current directory (root of mounted fs)
     let path = if args.len() > 1 {
@@ -117,7 +105,8 @@ libpanda::main! { |args|
         } else {
             cells.push(Value::String(name.clone()));
             cells.push(Value::String(String::from("file")));
-            cells.push(Value::String(format_size(size)));
+            // A number, in bytes, so that `where` and `sort-by` can compare it.
+            cells.push(Value::Int(size as i64));
         }
     }
 
@@ -0,0 +1,40 @@
+//! The network service.
+//!
+//! Owns the NIC, runs the `netstack` TCP/IP stack against it, and serves
+//! the stack's sockets as the `tcp:` and `udp:` schemes (see
+//! `panda_abi::socket` for the connect paths and channel framing).
+
+#![no_std]
+
+extern crate alloc;
+
+pub mod service;
+
+use netstack::{Config, Ipv4Addr, Ipv4Config};
+
+/// Parse netd's arguments (program name excluded):
+///
+/// ```text
+/// (none) | dhcp                      configure by DHCP
+/// static <ip>/<prefix> [<gateway>]   a fixed address
+/// ```
+pub fn parse_config(args: &[&str]) -> Option<Config> {
+    match args {
+        [] | ["dhcp"] => Some(Config::Dhcp),
+        ["static", address, rest @ ..] if rest.len() <= 1 => {
+            let (address, prefix_len) = address.split_once('/')?;
+            let prefix_len: u8 = prefix_len.parse().ok().filter(|&len| len <= 32)?;
+            let gateway = match rest {
+                [gateway] => Some(Ipv4Addr::parse(gateway)?),
+                _ => None,
+            };
+            Some(Config::Static(Ipv4Config {
+                address: Ipv4Addr::parse(address)?,
+                prefix_len,
+                gateway,
+                dns: None,
+            }))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,22 @@
+#![no_std]
+#![no_main]
+
+use libpanda::{Vec, environment};
+use netd::service::NetService;
+
+libpanda::main! { |args|
+    let args: Vec<&str> = args.iter().skip(1).map(|arg| arg.as_str()).collect();
+    let Some(config) = netd::parse_config(&args) else {
+        environment::log("usage: netd [dhcp | static <ip>/<prefix> [<gateway>]]");
+        return 1;
+    };
+
+    let mut service = match NetService::new(config) {
+        Ok(service) => service,
+        Err(_) => {
+            environment::log("netd: no usable network device");
+            return 1;
+        }
+    };
+    service.run()
+}
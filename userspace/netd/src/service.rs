@@ -0,0 +1,513 @@
+//! The service loop: NIC frames in and out of the stack, scheme requests in,
+//! and one channel per socket between the stack and its application.
+//!
+//! Everything is polled. The loop makes one pass over the NIC, both scheme
+//! providers and every socket channel, and sleeps briefly when a pass finds
+//! nothing to do — there is no way to block on a NIC, two providers and a
+//! changing set of channels at once.
+
+use alloc::vec::Vec;
+use libpanda::ipc::Channel;
+use libpanda::net::NetDevice;
+use libpanda::scheme::SchemeProvider;
+use libpanda::{environment, ipc, process};
+use netstack::{
+    Config, Ipv4Config, SocketAddrV4, SocketHandle, Stack, StackError, TcpError, TcpState,
+};
+use panda_abi::scheme_protocol::Request;
+use panda_abi::socket::{ADDRESS_LEN, MAX_DATAGRAM_SIZE, SocketPath};
+use panda_abi::{ErrorCode, MAX_MESSAGE_SIZE, NET_MAX_FRAME_SIZE};
+
+/// The NIC this service drives.
+pub const NIC: &str = "net:/pci/network/0";
+
+/// How long to sleep after a pass that found nothing to do. Short, since
+/// TCP's timers and the applications' latency both ride on it.
+pub const IDLE_SLEEP_MS: u64 = 2;
+
+/// A TCP connection and the application channel carrying its byte stream.
+struct Stream {
+    channel: Channel,
+    socket: SocketHandle,
+    /// Data from the application the send buffer had no room for yet.
+    to_stack: Vec<u8>,
+    /// Data for the application the channel had no room for yet.
+    to_app: Vec<u8>,
+    /// The application closed its end; the connection closes once
+    /// `to_stack` is flushed.
+    app_closed: bool,
+}
+
+impl Stream {
+    fn new(channel: Channel, socket: SocketHandle) -> Self {
+        Self {
+            channel,
+            socket,
+            to_stack: Vec::new(),
+            to_app: Vec::new(),
+            app_closed: false,
+        }
+    }
+}
+
+/// A TCP listener and the channel its accepted connections are handed out
+/// on.
+struct Listener {
+    channel: Channel,
+    socket: SocketHandle,
+}
+
+/// A UDP socket and its application channel.
+struct Datagram {
+    channel: Channel,
+    socket: SocketHandle,
+    /// Connected sockets carry bare datagrams; bound ones prefix each with
+    /// the peer's address.
+    connected: bool,
+}
+
+/// A `tcp:<ip>:<port>` connect waiting for the handshake. The reply is
+/// deferred until the connection is established or fails, so a caller
+/// never holds a stream to a connection that was refused.
+struct Connecting {
+    request_id: u64,
+    socket: SocketHandle,
+}
+
+pub struct NetService {
+    nic: NetDevice,
+    stack: Stack,
+    tcp: SchemeProvider,
+    udp: SchemeProvider,
+    connecting: Vec<Connecting>,
+    streams: Vec<Stream>,
+    listeners: Vec<Listener>,
+    datagrams: Vec<Datagram>,
+    /// The address last reported, to log changes.
+    reported: Option<Ipv4Config>,
+}
+
+impl NetService {
+    /// Open the NIC and register the `tcp:` and `udp:` schemes.
+    pub fn new(config: Config) -> libpanda::error::Result<Self> {
+        let nic = NetDevice::open(NIC)?;
+        let mac = nic.mac_address();
+        let tcp = SchemeProvider::register("tcp")?;
+        let udp = SchemeProvider::register("udp")?;
+
+        // No entropy source yet: boot time and the MAC at least keep two
+        // machines on one LAN, or two boots of one machine, apart.
+        let seed = mac.iter().fold(environment::time() as u64, |seed, &b| {
+            seed.rotate_left(8) ^ b as u64
+        });
+
+        environment::log("netd: registered the tcp: and udp: schemes");
+        Ok(Self {
+            nic,
+            stack: Stack::new(mac, config, seed),
+            tcp,
+            udp,
+            connecting: Vec::new(),
+            streams: Vec::new(),
+            listeners: Vec::new(),
+            datagrams: Vec::new(),
+            reported: None,
+        })
+    }
+
+    /// The interface's address, once it has one.
+    pub fn ipv4_config(&self) -> Option<Ipv4Config> {
+        self.stack.ipv4_config()
+    }
+
+    /// Serve forever.
+    pub fn run(&mut self) -> ! {
+        loop {
+            if !self.poll() {
+                process::sleep(IDLE_SLEEP_MS);
+            }
+        }
+    }
+
+    /// Make one pass over everything. Returns whether any frame, request
+    /// or application message was handled.
+    pub fn poll(&mut self) -> bool {
+        let now = environment::time() as u64;
+        let mut busy = false;
+
+        let mut frame = [0u8; NET_MAX_FRAME_SIZE];
+        while let Ok(Some(len)) = self.nic.try_recv(&mut frame) {
+            self.stack.receive(&frame[..len], now);
+            busy = true;
+        }
+
+        busy |= self.serve_tcp_requests();
+        busy |= self.serve_udp_requests();
+        busy |= self.pump_to_stack(now);
+
+        self.stack.poll(now);
+
+        self.finish_connects();
+        self.accept_connections();
+        busy |= self.pump_to_apps();
+
+        let mut queued = false;
+        while let Some(frame) = self.stack.transmit() {
+            let _ = self.nic.queue(&frame);
+            queued = true;
+        }
+        if queued {
+            let _ = self.nic.flush();
+            busy = true;
+        }
+
+        self.report_config();
+        busy
+    }
+
+    fn report_config(&mut self) {
+        let config = self.stack.ipv4_config();
+        if config == self.reported {
+            return;
+        }
+        self.reported = config;
+        match config {
+            Some(config) => environment::log(&alloc::format!(
+                "netd: configured {}/{}",
+                config.address,
+                config.prefix_len
+            )),
+            None => environment::log("netd: lost the interface address"),
+        }
+    }
+
+    // =========================================================================
+    // Scheme requests
+    // =========================================================================
+
+    fn serve_tcp_requests(&mut self) -> bool {
+        let mut buf = [0u8; MAX_MESSAGE_SIZE];
+        let mut busy = false;
+        while let Ok(Some(request)) = self.tcp.try_recv(&mut buf) {
+            busy = true;
+            let Request::Connect { request_id, path } = request else {
+                refuse(&self.tcp, request);
+                continue;
+            };
+            match SocketPath::parse(path) {
+                Some(SocketPath::Remote(remote)) => match self.stack.tcp_connect(remote) {
+                    Ok(socket) => self.connecting.push(Connecting { request_id, socket }),
+                    Err(e) => {
+                        let _ = self.tcp.reply_connect_err(request_id, stack_error(e));
+                    }
+                },
+                Some(SocketPath::Listen(port)) => match self.stack.tcp_listen(port) {
+                    Ok(socket) => match reply_with_channel(&self.tcp, request_id) {
+                        Some(channel) => self.listeners.push(Listener { channel, socket }),
+                        None => self.stack.close(socket),
+                    },
+                    Err(e) => {
+                        let _ = self.tcp.reply_connect_err(request_id, stack_error(e));
+                    }
+                },
+                _ => {
+                    let _ = self
+                        .tcp
+                        .reply_connect_err(request_id, ErrorCode::InvalidArgument);
+                }
+            }
+        }
+        busy
+    }
+
+    fn serve_udp_requests(&mut self) -> bool {
+        let mut buf = [0u8; MAX_MESSAGE_SIZE];
+        let mut busy = false;
+        while let Ok(Some(request)) = self.udp.try_recv(&mut buf) {
+            busy = true;
+            let Request::Connect { request_id, path } = request else {
+                refuse(&self.udp, request);
+                continue;
+            };
+            let (bound, connected) = match SocketPath::parse(path) {
+                Some(SocketPath::Remote(remote)) => (self.stack.udp_bind(0, Some(remote)), true),
+                Some(SocketPath::Bind(port)) => (self.stack.udp_bind(port, None), false),
+                _ => {
+                    let _ = self
+                        .udp
+                        .reply_connect_err(request_id, ErrorCode::InvalidArgument);
+                    continue;
+                }
+            };
+            match bound {
+                Ok(socket) => match reply_with_channel(&self.udp, request_id) {
+                    Some(channel) => self.datagrams.push(Datagram {
+                        channel,
+                        socket,
+                        connected,
+                    }),
+                    None => self.stack.close(socket),
+                },
+                Err(e) => {
+                    let _ = self.udp.reply_connect_err(request_id, stack_error(e));
+                }
+            }
+        }
+        busy
+    }
+
+    /// Answer connects whose handshake has finished, one way or the other.
+    fn finish_connects(&mut self) {
+        let Self {
+            stack,
+            tcp,
+            connecting,
+            streams,
+            ..
+        } = self;
+        connecting.retain(|pending| match stack.tcp_state(pending.socket) {
+            Ok(TcpState::SynSent) => true,
+            Ok(TcpState::Closed) | Err(_) => {
+                let error = match stack.tcp_error(pending.socket) {
+                    Ok(Some(TcpError::TimedOut)) => ErrorCode::IoError,
+                    _ => ErrorCode::NotFound,
+                };
+                let _ = tcp.reply_connect_err(pending.request_id, error);
+                stack.close(pending.socket);
+                false
+            }
+            Ok(_) => {
+                match reply_with_channel(tcp, pending.request_id) {
+                    Some(channel) => streams.push(Stream::new(channel, pending.socket)),
+                    None => stack.abort(pending.socket),
+                }
+                false
+            }
+        });
+    }
+
+    /// Hand each newly accepted connection to its listener's application.
+    fn accept_connections(&mut self) {
+        let Self {
+            stack,
+            listeners,
+            streams,
+            ..
+        } = self;
+        listeners.retain(|listener| {
+            // Applications don't send on a listener channel; the only
+            // thing to notice is them closing it.
+            let mut buf = [0u8; MAX_MESSAGE_SIZE];
+            if let Err(ErrorCode::ChannelClosed) = listener.channel.try_recv(&mut buf) {
+                stack.close(listener.socket);
+                return false;
+            }
+
+            while let Ok(Some((socket, peer))) = stack.tcp_accept(listener.socket) {
+                let mut address = [0u8; ADDRESS_LEN];
+                let _ = peer.encode(&mut address);
+                let Ok((ours, theirs)) = ipc::create_pair() else {
+                    stack.abort(socket);
+                    continue;
+                };
+                let theirs = Channel::from_typed(theirs);
+                if listener
+                    .channel
+                    .send_with_handle(&address, theirs.untyped_handle())
+                    .is_err()
+                {
+                    stack.abort(socket);
+                    continue;
+                }
+                streams.push(Stream::new(Channel::from_typed(ours), socket));
+            }
+            true
+        });
+    }
+
+    // =========================================================================
+    // Socket channels
+    // =========================================================================
+
+    /// Move application messages into the stack.
+    fn pump_to_stack(&mut self, now: u64) -> bool {
+        let mut busy = false;
+        let mut buf = [0u8; MAX_MESSAGE_SIZE];
+        let Self {
+            stack,
+            streams,
+            datagrams,
+            ..
+        } = self;
+
+        streams.retain_mut(|stream| {
+            loop {
+                if !stream.to_stack.is_empty() {
+                    match stack.tcp_send(stream.socket, &stream.to_stack) {
+                        Ok(sent) => drop(stream.to_stack.drain(..sent)),
+                        Err(_) => {
+                            stack.close(stream.socket);
+                            return false;
+                        }
+                    }
+                    if !stream.to_stack.is_empty() {
+                        break;
+                    }
+                }
+                if stream.app_closed {
+                    break;
+                }
+                match stream.channel.try_recv(&mut buf) {
+                    Ok(Some(len)) => {
+                        stream.to_stack.extend_from_slice(&buf[..len]);
+                        busy = true;
+                    }
+                    Ok(None) => break,
+                    Err(ErrorCode::ChannelClosed) => stream.app_closed = true,
+                    Err(err) => {
+                        // Not the application's doing; try again next time.
+                        environment::log(&alloc::format!("netd: stream receive failed: {:?}", err));
+                        break;
+                    }
+                }
+            }
+            if stream.app_closed && stream.to_stack.is_empty() {
+                stack.close(stream.socket);
+                return false;
+            }
+            true
+        });
+
+        datagrams.retain(|datagram| {
+            loop {
+                let len = match datagram.channel.try_recv(&mut buf) {
+                    Ok(Some(len)) => len,
+                    Ok(None) => return true,
+                    Err(ErrorCode::ChannelClosed) => {
+                        stack.close(datagram.socket);
+                        return false;
+                    }
+                    Err(err) => {
+                        environment::log(&alloc::format!(
+                            "netd: datagram receive failed: {:?}",
+                            err
+                        ));
+                        return true;
+                    }
+                };
+                busy = true;
+                let message = &buf[..len];
+                // Undeliverable datagrams are dropped, as UDP would.
+                if datagram.connected {
+                    let _ = stack.udp_send(datagram.socket, None, message, now);
+                } else if let Some(dst) = SocketAddrV4::decode(message) {
+                    let _ =
+                        stack.udp_send(datagram.socket, Some(dst), &message[ADDRESS_LEN..], now);
+                }
+            }
+        });
+        busy
+    }
+
+    /// Move received data out of the stack to the applications.
+    fn pump_to_apps(&mut self) -> bool {
+        let mut busy = false;
+        let Self {
+            stack,
+            streams,
+            datagrams,
+            ..
+        } = self;
+
+        let mut buf = [0u8; MAX_MESSAGE_SIZE];
+        streams.retain_mut(|stream| {
+            loop {
+                if !stream.to_app.is_empty() {
+                    match stream.channel.try_send(&stream.to_app) {
+                        Ok(()) => stream.to_app.clear(),
+                        Err(ErrorCode::WouldBlock) => return true,
+                        // The application is gone; pump_to_stack notices
+                        // on its next pass.
+                        Err(_) => return true,
+                    }
+                    busy = true;
+                }
+                match stack.tcp_recv(stream.socket, &mut buf) {
+                    Ok(0) => return true,
+                    Ok(len) => stream.to_app.extend_from_slice(&buf[..len]),
+                    // The peer has finished sending, or the connection
+                    // failed. Either way dropping the channel tells the
+                    // application, once it has read what was delivered.
+                    Err(_) => {
+                        stack.close(stream.socket);
+                        return false;
+                    }
+                }
+            }
+        });
+
+        let mut message = [0u8; ADDRESS_LEN + MAX_DATAGRAM_SIZE];
+        for datagram in datagrams.iter() {
+            while let Ok(Some((src, data))) = stack.udp_recv(datagram.socket) {
+                busy = true;
+                let len = if datagram.connected {
+                    message[..data.len()].copy_from_slice(&data);
+                    data.len()
+                } else {
+                    let _ = src.encode(&mut message);
+                    message[ADDRESS_LEN..ADDRESS_LEN + data.len()].copy_from_slice(&data);
+                    ADDRESS_LEN + data.len()
+                };
+                // A full channel drops the datagram rather than stall
+                // every other socket.
+                let _ = datagram.channel.try_send(&message[..len]);
+            }
+        }
+        busy
+    }
+}
+
+/// Create a channel pair and hand one end to the connecting process.
+/// Returns our end, or `None` (after replying with an error) on failure.
+fn reply_with_channel(provider: &SchemeProvider, request_id: u64) -> Option<Channel> {
+    let Ok((ours, theirs)) = ipc::create_pair() else {
+        let _ = provider.reply_connect_err(request_id, ErrorCode::IoError);
+        return None;
+    };
+    // Our copy of `theirs` drops at the end of this function; the
+    // connecting process holds the duplicate installed by the kernel.
+    let theirs = Channel::from_typed(theirs);
+    provider.reply_connect_ok(request_id, &theirs).ok()?;
+    Some(Channel::from_typed(ours))
+}
+
+/// Answer a request other than `Connect`: sockets are only reachable as
+/// channels.
+fn refuse(provider: &SchemeProvider, request: Request<'_>) {
+    let _ = match request {
+        Request::Open { request_id, .. } => {
+            provider.reply_open_err(request_id, ErrorCode::NotSupported)
+        }
+        Request::Readdir { request_id, .. } => {
+            provider.reply_readdir_err(request_id, ErrorCode::NotSupported)
+        }
+        Request::Read { request_id, .. } => {
+            provider.reply_read_err(request_id, ErrorCode::NotSupported)
+        }
+        Request::Write { request_id, .. } => {
+            provider.reply_write_err(request_id, ErrorCode::NotSupported)
+        }
+        Request::Close { request_id, .. } => provider.reply_close_ok(request_id),
+        Request::Connect { .. } => Ok(()),
+    };
+}
+
+fn stack_error(error: StackError) -> ErrorCode {
+    match error {
+        StackError::NotConfigured | StackError::NoRoute => ErrorCode::NotFound,
+        StackError::AddressInUse => ErrorCode::AlreadyExists,
+        StackError::InvalidSocket => ErrorCode::InvalidHandle,
+        StackError::Closed => ErrorCode::ChannelClosed,
+        StackError::TooLarge => ErrorCode::MessageTooLarge,
+    }
+}
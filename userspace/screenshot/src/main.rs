@@ -0,0 +1,122 @@
+#![no_std]
+#![no_main]
+
+//! Save the screen, or one window, to a file.
+//!
+//! Usage: `screenshot [--window <id>] <path>`. A path ending in `.ppm` is
+//! written as a binary PPM, anything else as a PNG. The image comes from
+//! the compositor's capture request (`libpanda::graphics::capture`), so it
+//! is exactly what the compositor composited, without the pointer.
+
+extern crate alloc;
+
+mod png;
+mod ppm;
+
+use alloc::format;
+use alloc::string::String;
+use libpanda::graphics::{self, CaptureSource};
+use libpanda::{environment, file, terminal};
+use panda_abi::ErrorCode;
+
+/// How much is written per `write` call.
+const WRITE_CHUNK: usize = 64 * 1024;
+
+libpanda::main! { |args|
+    let mut source = CaptureSource::Screen;
+    let mut path = None;
+    let mut rest = args.iter().skip(1);
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--window" | "-w" => {
+                let Some(id) = rest.next().and_then(|id| id.parse().ok()) else {
+                    terminal::error("screenshot: --window needs a window id");
+                    return 1;
+                };
+                source = CaptureSource::Window(id);
+            }
+            _ if path.is_none() => path = Some(arg),
+            _ => {
+                terminal::error("Usage: screenshot [--window <id>] <path>");
+                return 1;
+            }
+        }
+    }
+    let Some(path) = path else {
+        terminal::error("Usage: screenshot [--window <id>] <path>");
+        return 1;
+    };
+
+    let image = match graphics::capture(source) {
+        Ok(image) => image,
+        Err(ErrorCode::PermissionDenied) => {
+            terminal::error("screenshot: the compositor refused the capture");
+            return 1;
+        }
+        Err(ErrorCode::NotFound) => {
+            terminal::error("screenshot: no such window");
+            return 1;
+        }
+        Err(_) => {
+            terminal::error("screenshot: could not capture the screen");
+            return 1;
+        }
+    };
+
+    let encoded = if path.ends_with(".ppm") {
+        ppm::encode(&image)
+    } else {
+        // The composited screen is opaque; a window may not be.
+        png::encode(&image, matches!(source, CaptureSource::Window(_)))
+    };
+
+    if let Err(message) = write_file(path, &encoded) {
+        terminal::error(&format!("screenshot: {}: {}", path, message));
+        return 1;
+    }
+    terminal::println(&format!(
+        "Saved a {}x{} screenshot to {}",
+        image.width(),
+        image.height(),
+        path
+    ));
+    0
+}
+
+/// Create (or replace) the file at `path` and write `data` to it.
+fn write_file(path: &str, data: &[u8]) -> Result<(), &'static str> {
+    let path = path.strip_prefix("file:").unwrap_or(path);
+    let (dir, name) = match path.rfind('/') {
+        Some(0) => ("/", &path[1..]),
+        Some(slash) => (&path[..slash], &path[slash + 1..]),
+        None => return Err("expected an absolute path"),
+    };
+    if name.is_empty() {
+        return Err("expected a file name");
+    }
+
+    let dir_uri = String::from("file:") + dir;
+    let Ok(dir) = environment::opendir(&dir_uri) else {
+        return Err("no such directory");
+    };
+    let created = match environment::create(dir, name, 0o644, 0) {
+        Err(ErrorCode::AlreadyExists) => {
+            environment::unlink(dir, name).and_then(|()| environment::create(dir, name, 0o644, 0))
+        }
+        result => result,
+    };
+    file::close(dir);
+    let Ok(handle) = created else {
+        return Err("could not create the file");
+    };
+
+    let mut result = Ok(());
+    for chunk in data.chunks(WRITE_CHUNK) {
+        if file::write(handle, chunk) != chunk.len() as isize {
+            result = Err("could not write the file");
+            break;
+        }
+    }
+    file::close(handle);
+    result
+}
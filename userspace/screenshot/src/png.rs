@@ -0,0 +1,80 @@
+//! A minimal PNG encoder.
+//!
+//! Every row is unfiltered and the zlib stream is made of stored deflate
+//! blocks, so the file is about the size of the raw pixels. That keeps the
+//! encoder small; the point is a file any viewer or `compare` can read.
+
+use alloc::vec::Vec;
+use image::{adler32, crc32};
+use libpanda::graphics::PixelBuffer;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+const COLOUR_RGB: u8 = 2;
+const COLOUR_RGBA: u8 = 6;
+/// The largest stored deflate block.
+const MAX_STORED: usize = 0xFFFF;
+
+/// Encode `image`, keeping its alpha channel only if `alpha`.
+pub fn encode(image: &PixelBuffer, alpha: bool) -> Vec<u8> {
+    let (width, height) = (image.width(), image.height());
+    let channels = if alpha { 4 } else { 3 };
+
+    // Each row starts with its filter type, 0 (none).
+    let mut raw = Vec::with_capacity(height as usize * (1 + width as usize * channels));
+    for row in image.pixels().chunks_exact(width.max(1) as usize) {
+        raw.push(0);
+        for &pixel in row {
+            let [b, g, r, a] = pixel.to_le_bytes();
+            raw.extend_from_slice(&[r, g, b]);
+            if alpha {
+                raw.push(a);
+            }
+        }
+    }
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    let colour = if alpha { COLOUR_RGBA } else { COLOUR_RGB };
+    // Bit depth, colour type, compression, filter method, no interlace.
+    header.extend_from_slice(&[8, colour, 0, 0, 0]);
+
+    let mut out = Vec::with_capacity(raw.len() + raw.len() / MAX_STORED * 5 + 64);
+    out.extend_from_slice(&SIGNATURE);
+    chunk(&mut out, b"IHDR", &header);
+    chunk(&mut out, b"IDAT", &zlib_stored(&raw));
+    chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+/// Append a chunk: length, type, data, and the CRC of type and data.
+fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Wrap `data` in a zlib stream of stored (uncompressed) deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + data.len() / MAX_STORED * 5 + 11);
+    // Deflate with a 32K window, no preset dictionary; the check bits make
+    // the header a multiple of 31.
+    out.extend_from_slice(&[0x78, 0x01]);
+    let mut blocks = data.chunks(MAX_STORED).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        out.push(last as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
@@ -0,0 +1,17 @@
+//! Binary PPM (`P6`): a text header, then RGB bytes.
+
+use alloc::format;
+use alloc::vec::Vec;
+use libpanda::graphics::PixelBuffer;
+
+/// Encode `image`, dropping its alpha channel.
+pub fn encode(image: &PixelBuffer) -> Vec<u8> {
+    let header = format!("P6\n{} {}\n255\n", image.width(), image.height());
+    let mut out = Vec::with_capacity(header.len() + image.pixels().len() * 3);
+    out.extend_from_slice(header.as_bytes());
+    for &pixel in image.pixels() {
+        let [b, g, r, _] = pixel.to_le_bytes();
+        out.extend_from_slice(&[r, g, b]);
+    }
+    out
+}
@@ -0,0 +1,360 @@
+//! Text with ANSI escape sequences in it, from programs written for
+//! VT100-style terminals.
+//!
+//! A program's text goes through a [`Parser`] of its own once it has
+//! written an escape, a carriage return or a backspace, and from then on,
+//! so that a style set in one write carries over to the next.
+//!
+//! On the normal screen the scrollback stays a list of lines: characters
+//! are written into the last line, at a column that carriage returns,
+//! backspaces, tabs and the horizontal cursor movements move, so progress
+//! bars and redrawn prompts work. Erasing and editing in the line change it
+//! in the buffer; clearing the screen clears the scrollback. Rows can't be
+//! gone back to, so vertical movement, scroll regions and the rest are
+//! ignored there.
+//!
+//! A program that switches to the alternate screen gets a [`Grid`] the size
+//! of the window instead, drawn into a buffer of its own and shown instead
+//! of the framebuffer until it switches back or exits.
+
+use alloc::vec::Vec;
+use libpanda::graphics::PixelBuffer;
+use libpanda::{environment, Handle};
+use panda_abi::terminal::{ClearRegion, Style};
+use panda_abi::value::Value;
+use terminal::vt::{Action, Grid, Parser};
+
+use crate::render::{colour_to_argb, draw_cell, fill, Pen};
+use crate::{
+    Decoration, Line, Segment, Terminal, COLOUR_BACKGROUND, COLOUR_DEFAULT_FG, LINE_HEIGHT, MARGIN,
+};
+
+/// Columns between tab stops.
+const TAB_WIDTH: usize = 8;
+
+/// Thickness of the bar drawn under the cursor's cell.
+const CURSOR_HEIGHT: u32 = 2;
+
+/// A character of a line, with how it is drawn.
+type Char = (char, u32, Decoration);
+
+/// The screen a program switched to.
+pub struct AlternateScreen {
+    owner: Handle,
+    grid: Grid,
+    /// What the grid draws, shown instead of the framebuffer.
+    screen: PixelBuffer,
+}
+
+/// How text in `style` is written into the scrollback.
+fn pen(style: &Style) -> (u32, Decoration) {
+    let colour = style
+        .foreground
+        .as_ref()
+        .map_or(COLOUR_DEFAULT_FG, colour_to_argb);
+    let decoration = Decoration {
+        background: style.background.as_ref().map(colour_to_argb),
+        underline: style.underline,
+        link: None,
+    };
+    (colour, decoration)
+}
+
+impl Terminal {
+    pub fn alternate_shown(&self) -> bool {
+        self.session.alternate.is_some()
+    }
+
+    /// Write a program's text through its parser, if it has one or the
+    /// text needs one. Returns whether it did.
+    pub(crate) fn vt_output(&mut self, value: &Value, handle: Handle) -> bool {
+        let bytes = match value {
+            Value::String(text) => text.as_bytes(),
+            Value::Bytes(bytes) => bytes.as_slice(),
+            _ => return false,
+        };
+        let known = self
+            .session
+            .vt
+            .iter()
+            .position(|(owner, _)| *owner == handle);
+        if known.is_none() && !bytes.iter().any(|b| matches!(b, 0x1b | b'\r' | 0x08)) {
+            return false;
+        }
+        let at = known.unwrap_or_else(|| {
+            self.session.vt.push((handle, Parser::new()));
+            self.session.vt.len() - 1
+        });
+        let parser = &mut self.session.vt[at].1;
+        let mut style = parser.style();
+        let actions = parser.feed(bytes);
+
+        let mut edited = false;
+        for action in actions {
+            if let Action::Style(new) = &action {
+                style = new.clone();
+            }
+            let owns_screen = self
+                .session
+                .alternate
+                .as_ref()
+                .is_some_and(|alternate| alternate.owner == handle);
+            match action {
+                Action::SetTitle(title) => self.set_title(&title),
+                Action::AlternateScreen(true) if self.session.alternate.is_none() => {
+                    self.open_alternate(handle, &style)
+                }
+                Action::AlternateScreen(false) if owns_screen => self.close_alternate(),
+                action if owns_screen => {
+                    if let Some(alternate) = &mut self.session.alternate {
+                        alternate.grid.apply(&action);
+                    }
+                }
+                action => edited |= self.vt_line(action, &style),
+            }
+        }
+
+        if edited {
+            self.follow_output();
+            self.render_visible_lines();
+        }
+        self.render_alternate();
+        self.flush();
+        true
+    }
+
+    /// A program has exited: forget its parser, and leave the screen it
+    /// switched to.
+    pub(crate) fn vt_exited(&mut self, handle: Handle) {
+        self.session.vt.retain(|(owner, _)| *owner != handle);
+        if self
+            .session
+            .alternate
+            .as_ref()
+            .is_some_and(|alternate| alternate.owner == handle)
+        {
+            self.close_alternate();
+        }
+    }
+
+    /// The characters of the last line.
+    fn last_line(&self) -> Vec<Char> {
+        let Some(line) = self.session.display_lines.last() else {
+            return Vec::new();
+        };
+        line.segments
+            .iter()
+            .flat_map(|segment| {
+                let decoration = &segment.decoration;
+                segment
+                    .text
+                    .chars()
+                    .map(move |ch| (ch, segment.colour, decoration.clone()))
+            })
+            .collect()
+    }
+
+    /// Replace the last line's characters, runs drawn alike in a segment.
+    fn set_last_line(&mut self, chars: Vec<Char>) {
+        let mut segments: Vec<Segment> = Vec::new();
+        for (ch, colour, decoration) in chars {
+            match segments.last_mut() {
+                Some(last) if last.colour == colour && last.decoration == decoration => {
+                    last.text.push(ch)
+                }
+                _ => segments.push(Segment {
+                    text: ch.into(),
+                    colour,
+                    decoration,
+                }),
+            }
+        }
+        if let Some(line) = self.session.display_lines.last_mut() {
+            *line = Line { segments };
+        }
+    }
+
+    /// Write a character at the end of the last line, wrapping first if it
+    /// doesn't fit.
+    fn append_char(&mut self, ch: char, style: &Style) {
+        let (colour, decoration) = pen(style);
+        let char_width = self.measure_char(ch);
+        if self.cursor_x > MARGIN && self.cursor_x + char_width > self.width - MARGIN {
+            self.wrap();
+        }
+        self.session.decoration = decoration;
+        self.emit_char(ch, colour);
+        self.session.decoration = Decoration::default();
+    }
+
+    /// Carry out an action on the last line of the scrollback. Returns
+    /// whether the line was changed other than at its end, and so needs
+    /// drawing again.
+    fn vt_line(&mut self, action: Action, style: &Style) -> bool {
+        let len = self.session.display_lines.last().map_or(0, |line| {
+            line.segments
+                .iter()
+                .map(|segment| segment.text.chars().count())
+                .sum()
+        });
+        let col = self.session.column.unwrap_or(len);
+
+        // Moving, and writing at the end.
+        match action {
+            Action::Print(ch) if col >= len => {
+                for _ in len..col {
+                    self.append_char(' ', &Style::default());
+                }
+                self.append_char(ch, style);
+                self.session.column = None;
+                return false;
+            }
+            Action::LineFeed => {
+                self.newline();
+                return false;
+            }
+            Action::CarriageReturn => self.session.column = Some(0),
+            Action::Backspace => self.session.column = Some(col.saturating_sub(1)),
+            Action::Tab => {
+                let stop = (col / TAB_WIDTH + 1) * TAB_WIDTH;
+                self.session.column = Some(stop);
+                if stop >= len {
+                    for _ in len..stop {
+                        self.append_char(' ', &Style::default());
+                    }
+                    self.session.column = None;
+                }
+            }
+            Action::CursorTo { col: Some(to), .. } => self.session.column = Some(to as usize),
+            Action::CursorBy { cols, .. } => {
+                self.session.column = Some(col.saturating_add_signed(cols as isize));
+            }
+            Action::Clear(ClearRegion::Screen) => {
+                self.clear();
+                self.session.column = None;
+            }
+            _ => {}
+        }
+
+        // Changing the line.
+        let changes = match action {
+            Action::Print(_) => true,
+            Action::Clear(ClearRegion::ToEndOfScreen | ClearRegion::ToEndOfLine)
+            | Action::EraseChars(_)
+            | Action::InsertChars(_)
+            | Action::DeleteChars(_) => col < len,
+            Action::Clear(ClearRegion::Line) | Action::ClearToCursor { .. } => len > 0,
+            // Rows can't be gone back to, so the rest mean nothing here.
+            _ => false,
+        };
+        if !changes {
+            return false;
+        }
+        let mut chars = self.last_line();
+        let blank = || (' ', COLOUR_DEFAULT_FG, Decoration::default());
+        match action {
+            Action::Print(ch) => {
+                let (colour, decoration) = pen(style);
+                chars[col] = (ch, colour, decoration);
+                self.session.column = Some(col + 1).filter(|&col| col < len);
+            }
+            Action::Clear(ClearRegion::Line) => chars.clear(),
+            Action::Clear(_) => chars.truncate(col),
+            Action::ClearToCursor { .. } => chars[..(col + 1).min(len)].fill(blank()),
+            Action::EraseChars(n) => chars[col..(col + n as usize).min(len)].fill(blank()),
+            Action::InsertChars(n) => {
+                chars.splice(col..col, core::iter::repeat_n(blank(), n as usize));
+            }
+            Action::DeleteChars(n) => {
+                chars.drain(col..(col + n as usize).min(len));
+            }
+            _ => {}
+        }
+        self.set_last_line(chars);
+        true
+    }
+
+    fn open_alternate(&mut self, owner: Handle, style: &Style) {
+        let Ok(mut screen) = PixelBuffer::new(self.width, self.height) else {
+            environment::log("terminal: Failed to allocate the alternate screen");
+            return;
+        };
+        fill(
+            &mut screen,
+            0,
+            0,
+            self.width,
+            self.height,
+            COLOUR_BACKGROUND,
+        );
+        let (cols, rows) = self.size_in_cells();
+        let mut grid = Grid::new(rows as usize, cols as usize);
+        grid.apply(&Action::Style(style.clone()));
+        self.session.alternate = Some(AlternateScreen {
+            owner,
+            grid,
+            screen,
+        });
+    }
+
+    fn close_alternate(&mut self) {
+        self.session.alternate = None;
+        // Show the scrollback as it now is.
+        self.mark_dirty(0, 0, self.width, self.height);
+        self.flush();
+    }
+
+    /// The window changed size: so does the alternate screen.
+    pub(crate) fn resize_alternate(&mut self) {
+        if self.session.alternate.is_none() {
+            return;
+        }
+        let Ok(mut screen) = PixelBuffer::new(self.width, self.height) else {
+            self.close_alternate();
+            return;
+        };
+        fill(
+            &mut screen,
+            0,
+            0,
+            self.width,
+            self.height,
+            COLOUR_BACKGROUND,
+        );
+        let (cols, rows) = self.size_in_cells();
+        if let Some(alternate) = &mut self.session.alternate {
+            alternate.screen = screen;
+            alternate.grid.resize(rows as usize, cols as usize);
+        }
+        self.render_alternate();
+    }
+
+    /// Draw the rows of the alternate screen that changed, with a bar under
+    /// the cursor, and show it unless its session is parked.
+    pub(crate) fn render_alternate(&mut self) {
+        let cell = self.avg_char_width;
+        let width = self.width;
+        let Some(alternate) = &mut self.session.alternate else {
+            return;
+        };
+        let cursor = alternate.grid.cursor();
+        for row in alternate.grid.take_damage() {
+            let y = MARGIN + row as u32 * LINE_HEIGHT;
+            let screen = &mut alternate.screen;
+            fill(screen, 0, y, width, LINE_HEIGHT, COLOUR_BACKGROUND);
+            for (col, cell_at) in alternate.grid.row(row).iter().enumerate() {
+                let x = MARGIN + col as u32 * cell;
+                let pen = Pen::new(&cell_at.style, COLOUR_DEFAULT_FG);
+                draw_cell(screen, &self.font, cell_at.ch, x, y, cell, &pen);
+                if cursor == Some((row, col)) {
+                    let bar_y = y + LINE_HEIGHT - CURSOR_HEIGHT;
+                    fill(screen, x, bar_y, cell, CURSOR_HEIGHT, pen.colour);
+                }
+            }
+        }
+        if !self.hidden {
+            let _ = self.window.blit(&alternate.screen, 0, 0);
+            let _ = self.window.flush();
+        }
+    }
+}
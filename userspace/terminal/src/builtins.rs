@@ -0,0 +1,221 @@
+//! Commands the shell runs itself, because they change the shell's own
+//! state (its working directory, its variables) or the terminal.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Write;
+use libpanda::process::ChildBuilder;
+use libpanda::{env, file, Handle};
+use terminal::geometry::Geometry;
+
+use crate::Terminal;
+
+/// How far a window the `window` builtin opens is from the terminal's own,
+/// across and down.
+const WINDOW_OFFSET: u32 = 30;
+
+/// Every builtin's usage and what it does, in the order `help` lists them.
+const BUILTINS: &[(&str, &str)] = &[
+    ("cd [dir]", "change directory (default $HOME)"),
+    ("pwd", "print the working directory"),
+    ("export [NAME=VALUE ...]", "set variables, or list them"),
+    ("unset NAME ...", "remove variables"),
+    ("env", "list variables"),
+    ("which NAME ...", "show where commands are found"),
+    ("clear", "clear the screen"),
+    ("jobs", "list background and stopped jobs"),
+    ("fg [job]", "continue a job in the foreground"),
+    ("bg [job]", "continue a stopped job in the background"),
+    ("window [WIDTHxHEIGHT]", "open another terminal window"),
+    ("exit [status]", "close the session"),
+    ("help", "show this list"),
+];
+
+/// The builtins' names.
+pub fn names() -> impl Iterator<Item = &'static str> {
+    BUILTINS
+        .iter()
+        .filter_map(|(usage, _)| usage.split(' ').next())
+}
+
+pub fn is_builtin(name: &str) -> bool {
+    names().any(|builtin| builtin == name)
+}
+
+/// Whether `name` can be a variable name.
+fn is_name(name: &str) -> bool {
+    let is_name_char = |ch: char| ch.is_ascii_alphanumeric() || ch == '_';
+    name.starts_with(|ch: char| !ch.is_ascii_digit()) && name.chars().all(is_name_char)
+}
+
+impl Terminal {
+    /// Run the builtin `args[0]` for job `job`, writing its output to `out`
+    /// and its errors straight to the screen. Returns its exit status, or
+    /// `None` if it handed the terminal to another job (`fg`).
+    pub fn run_builtin(&mut self, job: usize, args: &[String], out: &mut String) -> Option<i32> {
+        let rest = &args[1..];
+        let status = match args[0].as_str() {
+            "cd" => self.cd(rest),
+            "pwd" => {
+                let _ = writeln!(out, "{}", env::current_dir());
+                0
+            }
+            "export" => self.export(rest, out),
+            "unset" => {
+                for name in rest {
+                    env::remove(name);
+                }
+                0
+            }
+            "env" => {
+                write_vars(out);
+                0
+            }
+            "which" => self.which(rest, out),
+            "clear" => {
+                self.clear();
+                0
+            }
+            "window" => self.open_window(rest),
+            "exit" => {
+                let status = match rest.first() {
+                    Some(status) => status.parse().unwrap_or(self.session.last_status),
+                    None => self.session.last_status,
+                };
+                // Nothing more of the line runs; the session closes once
+                // the line has been handled.
+                self.session.script.clear();
+                if let Some(job) = self.job_mut(job) {
+                    job.steps.clear();
+                }
+                self.session.exit = Some(status);
+                status
+            }
+            "jobs" => {
+                self.write_jobs(out);
+                0
+            }
+            "fg" => return self.fg(job, rest),
+            "bg" => self.bg(rest),
+            "help" => {
+                for (usage, summary) in BUILTINS {
+                    let _ = writeln!(out, "{:<24} {}", usage, summary);
+                }
+                0
+            }
+            name => unreachable!("{} is not a builtin", name),
+        };
+        Some(status)
+    }
+
+    fn cd(&mut self, args: &[String]) -> i32 {
+        let dir = match args.first() {
+            Some(dir) => dir.clone(),
+            None => env::get("HOME").unwrap_or_else(|| String::from("/")),
+        };
+        match env::set_current_dir(&dir) {
+            Ok(()) => 0,
+            Err(err) => {
+                self.write_line(&format!("cd: {}: {}", dir, err));
+                1
+            }
+        }
+    }
+
+    fn export(&mut self, args: &[String], out: &mut String) -> i32 {
+        if args.is_empty() {
+            write_vars(out);
+            return 0;
+        }
+
+        let mut status = 0;
+        for arg in args {
+            // Every variable is passed to children already, so a bare name
+            // has nothing to do.
+            let (name, value) = arg.split_once('=').unwrap_or((arg, ""));
+            if !is_name(name) {
+                self.write_line(&format!("export: `{}': not a valid name", arg));
+                status = 1;
+            } else if arg.contains('=') {
+                env::set(name, value);
+            }
+        }
+        status
+    }
+
+    fn which(&mut self, args: &[String], out: &mut String) -> i32 {
+        let mut status = 0;
+        for name in args {
+            if is_builtin(name) {
+                let _ = writeln!(out, "{}: shell builtin", name);
+            } else if let Some(path) = self.resolve_command(name) {
+                let _ = writeln!(out, "{}", path);
+            } else {
+                self.write_line(&format!("{} not found", name));
+                status = 1;
+            }
+        }
+        status
+    }
+
+    /// Start another terminal, in a window of its own next to this one's,
+    /// the same size unless another is given.
+    fn open_window(&mut self, args: &[String]) -> i32 {
+        let (width, height) = match args.first() {
+            Some(size) => match Geometry::parse(size) {
+                Some(geometry) => (geometry.width, geometry.height),
+                None => {
+                    self.write_line(&format!("window: {}: not WIDTHxHEIGHT", size));
+                    return 1;
+                }
+            },
+            None => (self.width, self.height),
+        };
+        let Some(path) = self.resolve_command("terminal") else {
+            self.write_line("window: terminal not found");
+            return 1;
+        };
+        let (x, y) = self.window.position();
+        let geometry = Geometry {
+            width,
+            height,
+            position: Some((x + WINDOW_OFFSET, y + WINDOW_OFFSET)),
+        }
+        .to_string();
+        // It is a terminal of its own, so its exit is none of this one's
+        // business; all it waits for is a connection to capture through.
+        match ChildBuilder::new(&path)
+            .args(&["terminal", &geometry])
+            .spawn_handle()
+        {
+            Ok(handle) => {
+                self.share_capture(handle);
+                0
+            }
+            Err(err) => {
+                self.write_line(&format!("window: {}", err));
+                1
+            }
+        }
+    }
+
+    /// Send a builtin's output to the file it was redirected to, or to the
+    /// screen.
+    pub fn write_builtin_output(&mut self, out: &str, stdout: Option<Handle>) {
+        match stdout {
+            Some(handle) => {
+                file::write(handle, out.as_bytes());
+            }
+            None => self.write_str(out),
+        }
+    }
+}
+
+fn write_vars(out: &mut String) {
+    let mut vars: Vec<(String, String)> = env::vars();
+    vars.sort();
+    for (name, value) in vars {
+        let _ = writeln!(out, "{}={}", name, value);
+    }
+}
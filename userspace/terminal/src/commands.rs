@@ -1,251 +1,289 @@
 //! Command execution and child process handling.
 
+use alloc::format;
 use alloc::string::String;
 use alloc::vec::Vec;
-use libpanda::{channel, environment, process, process::ChildBuilder, Handle};
+use libpanda::{channel, env, environment, file, process::ChildBuilder, ErrorCode, Handle};
 use panda_abi::terminal::Request;
 use panda_abi::value::Value;
-use panda_abi::{EVENT_CHANNEL_READABLE, EVENT_PROCESS_EXITED, MAX_MESSAGE_SIZE};
+use panda_abi::{EVENT_CHANNEL_READABLE, EVENT_PROCESS_EXITED, MAX_MESSAGE_SIZE, SEEK_END};
+use terminal::shell::{self, Command, Pipeline, Redirect, RedirectKind, Word};
 
+use crate::builtins;
 use crate::Terminal;
 
-/// A single command in a pipeline with its arguments.
-struct PipelineStage {
-    cmd: String,
+/// The status of a command that could not be found.
+const STATUS_NOT_FOUND: i32 = 127;
+/// The status of a command that was found but could not be started.
+const STATUS_NOT_STARTED: i32 = 126;
+/// The status of a line the shell could not parse.
+const STATUS_SYNTAX_ERROR: i32 = 2;
+
+/// A command ready to spawn: its words expanded and its redirections
+/// opened.
+struct Prepared {
+    name: String,
+    path: String,
     args: Vec<String>,
+    stdin: Option<Handle>,
+    stdout: Option<Handle>,
 }
 
-impl Terminal {
-    /// Parse the line buffer into pipeline stages.
-    ///
-    /// Returns None if the line is empty, otherwise returns a list of stages.
-    /// Each stage is a (command, args) pair.
-    fn parse_pipeline(&self) -> Option<Vec<PipelineStage>> {
-        let trimmed = self.line_buffer.trim();
-        if trimmed.is_empty() {
-            return None;
-        }
-
-        // Split by pipe character
-        let segments: Vec<&str> = trimmed.split('|').collect();
-        let mut stages = Vec::new();
-
-        for segment in segments {
-            let segment = segment.trim();
-            if segment.is_empty() {
-                continue;
-            }
-
-            let parts: Vec<&str> = segment.split_whitespace().collect();
-            if parts.is_empty() {
-                continue;
-            }
-
-            let cmd = String::from(parts[0]);
-            let args: Vec<String> = parts.iter().map(|s| String::from(*s)).collect();
-            stages.push(PipelineStage { cmd, args });
-        }
-
-        if stages.is_empty() {
-            None
-        } else {
-            Some(stages)
+impl Prepared {
+    /// Close the redirected files. The child has its own handles to them.
+    fn close_files(&self) {
+        for handle in [self.stdin, self.stdout].into_iter().flatten() {
+            file::close(handle);
         }
     }
+}
 
-    /// Parse the line buffer into command and arguments (legacy single command).
-    pub fn parse_command(&self) -> Option<(String, Vec<String>)> {
-        let trimmed = self.line_buffer.trim();
-        if trimmed.is_empty() {
-            return None;
-        }
-
-        let parts: Vec<&str> = trimmed.split_whitespace().collect();
-        if parts.is_empty() {
-            return None;
-        }
+/// Open `path` to be written: emptied first, or with writes going to its
+/// end if `append`. It is created if it does not exist.
+pub fn open_output(path: &str, append: bool) -> Result<Handle, ErrorCode> {
+    let uri = env::resolve_path(path);
+    if append && let Ok(handle) = environment::open(&uri, 0, 0) {
+        file::seek(handle, 0, SEEK_END);
+        return Ok(handle);
+    }
 
-        let cmd = String::from(parts[0]);
-        let args: Vec<String> = parts.iter().map(|s| String::from(*s)).collect();
-        Some((cmd, args))
+    let Some((dir, name)) = uri.rsplit_once('/') else {
+        return Err(ErrorCode::InvalidArgument);
+    };
+    let dir = environment::opendir(&format!("{}/", dir))?;
+    // There is no truncation, so replacing a file means recreating it.
+    if !append {
+        let _ = environment::unlink(dir, name);
     }
+    let result = environment::create(dir, name, 0o644, 0);
+    file::close(dir);
+    result
+}
 
-    /// Resolve a command name to an executable path.
+impl Terminal {
+    /// Resolve a command name to an executable path. A name with a `/` in
+    /// it is a path; any other is looked for in each directory of `PATH`.
     pub fn resolve_command(&self, cmd: &str) -> Option<String> {
+        let is_program = |path: &str| environment::stat(path).is_ok_and(|stat| !stat.is_dir);
+
         if cmd.contains('/') {
-            return Some(alloc::format!("file:{}", cmd));
+            let path = env::resolve_path(cmd);
+            return is_program(&path).then_some(path);
         }
 
-        // Try /mnt first (ext2 filesystem)
-        let mnt_path = alloc::format!("file:/mnt/{}", cmd);
-        if environment::stat(&mnt_path).is_ok() {
-            return Some(mnt_path);
-        }
+        let search = env::get("PATH").unwrap_or_default();
+        search
+            .split(':')
+            .filter(|dir| !dir.is_empty())
+            .map(|dir| env::resolve_path(&format!("{}/{}", dir, cmd)))
+            .find(|path| is_program(path))
+    }
 
-        // Try /initrd
-        let initrd_path = alloc::format!("file:/initrd/{}", cmd);
-        if environment::stat(&initrd_path).is_ok() {
-            return Some(initrd_path);
+    /// Run a line typed at the prompt as a script.
+    pub fn execute_command(&mut self, line: &str) {
+        match shell::parse(line) {
+            Ok(script) => self.session.script = script.lists.into(),
+            Err(err) => {
+                self.write_line(&format!("syntax error: {}", err));
+                self.session.last_status = STATUS_SYNTAX_ERROR;
+            }
         }
+        self.run_script();
+    }
 
-        None
+    /// Expand a word for a command of job `job`, whose status is `$?`.
+    fn expand(&self, job: usize, word: &Word) -> String {
+        let status = self
+            .job(job)
+            .map_or(self.session.last_status, |job| job.status);
+        word.expand(|name| match name {
+            "?" => Some(format!("{}", status)),
+            name => env::get(name),
+        })
     }
 
-    /// Execute a command (handles both single commands and pipelines).
-    pub fn execute_command(&mut self) {
-        let Some(stages) = self.parse_pipeline() else {
-            return;
-        };
+    /// Start a pipeline for job `job`, or run it there and then if it is a
+    /// builtin or cannot start. Returns its exit status if it has already
+    /// finished.
+    pub fn run_pipeline(&mut self, job: usize, pipeline: &Pipeline) -> Option<i32> {
+        if let [command] = pipeline.commands.as_slice()
+            && builtins::is_builtin(&self.expand(job, &command.words[0]))
+        {
+            return self.run_builtin_command(job, command);
+        }
 
-        // Handle built-in commands (only for single-stage pipelines)
-        if stages.len() == 1 {
-            match stages[0].cmd.as_str() {
-                "clear" => {
-                    self.clear();
-                    return;
+        // Find every program and open every file before starting anything,
+        // so a mistake anywhere leaves nothing half-started.
+        let mut prepared: Vec<Prepared> = Vec::new();
+        for command in &pipeline.commands {
+            match self.prepare(job, command) {
+                Ok(command) => prepared.push(command),
+                Err(status) => {
+                    prepared.iter().for_each(Prepared::close_files);
+                    return Some(status);
                 }
-                "exit" => {
-                    process::exit(0);
-                }
-                _ => {}
             }
         }
+        self.spawn_pipeline(job, prepared)
+    }
 
-        if stages.len() == 1 {
-            // Single command - use simple spawn
-            self.execute_single_command(&stages[0]);
-        } else {
-            // Pipeline - spawn multiple processes with channels
-            self.execute_pipeline(stages);
-        }
+    /// Expand a command's words, find its program and open its files.
+    fn prepare(&mut self, job: usize, command: &Command) -> Result<Prepared, i32> {
+        let args: Vec<String> = command
+            .words
+            .iter()
+            .map(|word| self.expand(job, word))
+            .collect();
+        let name = args[0].clone();
+        let Some(path) = self.resolve_command(&name) else {
+            if builtins::is_builtin(&name) {
+                self.write_line(&format!("{}: a shell builtin cannot be piped", name));
+                return Err(1);
+            }
+            self.write_line(&format!("{}: command not found", name));
+            return Err(STATUS_NOT_FOUND);
+        };
+        let (stdin, stdout) = self.open_redirects(job, &command.redirects)?;
+        Ok(Prepared {
+            name,
+            path,
+            args,
+            stdin,
+            stdout,
+        })
     }
 
-    /// Execute a single command (no pipeline).
-    fn execute_single_command(&mut self, stage: &PipelineStage) {
-        let Some(path) = self.resolve_command(&stage.cmd) else {
-            self.write_line(&alloc::format!("{}: command not found", stage.cmd));
-            return;
+    /// Run a builtin as a command of its own, with its output going where
+    /// it is redirected. Its input is never read, so `<` only has to open.
+    /// Returns its status, or `None` if it handed the terminal to another
+    /// job (`fg`).
+    fn run_builtin_command(&mut self, job: usize, command: &Command) -> Option<i32> {
+        let args: Vec<String> = command
+            .words
+            .iter()
+            .map(|word| self.expand(job, word))
+            .collect();
+        let (stdin, stdout) = match self.open_redirects(job, &command.redirects) {
+            Ok(files) => files,
+            Err(status) => return Some(status),
         };
 
-        let arg_refs: Vec<&str> = stage.args.iter().map(|s| s.as_str()).collect();
-        let events = EVENT_PROCESS_EXITED | EVENT_CHANNEL_READABLE;
+        let mut out = String::new();
+        let status = self.run_builtin(job, &args, &mut out);
+        self.write_builtin_output(&out, stdout);
 
-        match ChildBuilder::new(&path)
-            .args(&arg_refs)
-            .mailbox(self.mailbox.handle(), events)
-            .spawn_handle()
-        {
-            Ok(child_handle) => {
-                self.child = Some(child_handle);
-                self.pipeline_children.clear();
-            }
-            Err(_) => {
-                self.write_line(&alloc::format!("{}: failed to execute", stage.cmd));
-            }
+        for handle in [stdin, stdout].into_iter().flatten() {
+            file::close(handle);
         }
+        status
     }
 
-    /// Execute a pipeline of commands.
-    fn execute_pipeline(&mut self, stages: Vec<PipelineStage>) {
-        // Clear any existing children
-        self.child = None;
-        self.pipeline_children.clear();
-
-        let n = stages.len();
-        if n == 0 {
-            return;
+    /// Open a command's redirections, returning its standard input and
+    /// output files. The last redirection of each stream wins.
+    fn open_redirects(
+        &mut self,
+        job: usize,
+        redirects: &[Redirect],
+    ) -> Result<(Option<Handle>, Option<Handle>), i32> {
+        let mut stdin: Option<Handle> = None;
+        let mut stdout: Option<Handle> = None;
+
+        for redirect in redirects {
+            let target = self.expand(job, &redirect.target);
+            let opened = match redirect.kind {
+                RedirectKind::Input => environment::open(&target, 0, 0),
+                RedirectKind::Output => open_output(&target, false),
+                RedirectKind::Append => open_output(&target, true),
+            };
+            let handle = match opened {
+                Ok(handle) => handle,
+                Err(err) => {
+                    self.write_line(&format!("{}: {}", target, err));
+                    for handle in [stdin, stdout].into_iter().flatten() {
+                        file::close(handle);
+                    }
+                    return Err(1);
+                }
+            };
+            let stream = match redirect.kind {
+                RedirectKind::Input => &mut stdin,
+                RedirectKind::Output | RedirectKind::Append => &mut stdout,
+            };
+            if let Some(previous) = stream.replace(handle) {
+                file::close(previous);
+            }
         }
 
-        // Create channels between stages
-        // For n stages, we need n-1 channels
+        Ok((stdin, stdout))
+    }
+
+    /// Spawn a pipeline's commands, each reading from the one before it
+    /// and writing to the one after it, except where a redirection says
+    /// otherwise. Returns a status if none of them started.
+    fn spawn_pipeline(&mut self, job: usize, commands: Vec<Prepared>) -> Option<i32> {
+        let mut processes = Vec::new();
+
+        // For n commands, we need n-1 channels
         let mut channels: Vec<(Handle, Handle)> = Vec::new();
-        for _ in 0..(n - 1) {
+        for _ in 1..commands.len() {
             let Ok((a, b)) = channel::create_pair() else {
                 self.write_line("failed to create pipeline channel");
-                return;
+                for (a, b) in channels {
+                    file::close(a);
+                    file::close(b);
+                }
+                commands.iter().for_each(Prepared::close_files);
+                return Some(STATUS_NOT_STARTED);
             };
             channels.push((a.into(), b.into()));
         }
 
         let events = EVENT_PROCESS_EXITED | EVENT_CHANNEL_READABLE;
+        let last = commands.len() - 1;
 
-        // Spawn each stage
-        for (i, stage) in stages.iter().enumerate() {
-            let Some(path) = self.resolve_command(&stage.cmd) else {
-                self.write_line(&alloc::format!("{}: command not found", stage.cmd));
-                // Clean up already spawned processes
-                for handle in &self.pipeline_children {
-                    // Best effort cleanup
-                    let _ = process::wait(*handle);
-                }
-                self.pipeline_children.clear();
-                return;
-            };
-
-            let arg_refs: Vec<&str> = stage.args.iter().map(|s| s.as_str()).collect();
-
-            // Build spawn command with optional stdin/stdout redirection
-            let mut builder = ChildBuilder::new(&path)
+        for (i, command) in commands.iter().enumerate() {
+            let arg_refs: Vec<&str> = command.args.iter().map(|s| s.as_str()).collect();
+            let mut builder = ChildBuilder::new(&command.path)
                 .args(&arg_refs)
                 .mailbox(self.mailbox.handle(), events);
 
-            // First stage: no stdin redirection
-            // Middle/last stages: use read end of previous channel
-            if i > 0 {
-                builder = builder.stdin(channels[i - 1].0);
+            // A redirection takes the place of the pipe. With neither, the
+            // first command reads from and the last writes to the terminal.
+            let stdin = command.stdin.or((i > 0).then(|| channels[i - 1].0));
+            if let Some(stdin) = stdin {
+                builder = builder.stdin(stdin);
             }
-
-            // Last stage: no stdout redirection (output goes to terminal)
-            // First/middle stages: use write end of next channel
-            if i < n - 1 {
-                builder = builder.stdout(channels[i].1);
+            let stdout = command.stdout.or((i < last).then(|| channels[i].1));
+            if let Some(stdout) = stdout {
+                builder = builder.stdout(stdout);
             }
 
             match builder.spawn_handle() {
-                Ok(child_handle) => {
-                    self.pipeline_children.push(child_handle);
-                }
+                Ok(child_handle) => processes.push(child_handle),
                 Err(_) => {
-                    self.write_line(&alloc::format!("{}: failed to execute", stage.cmd));
-                    // Clean up already spawned processes
-                    for handle in &self.pipeline_children {
-                        let _ = process::wait(*handle);
-                    }
-                    self.pipeline_children.clear();
-                    return;
+                    self.write_line(&format!("{}: failed to execute", command.name));
+                    break;
                 }
             }
         }
 
-        // The last child is the "main" child for output purposes
-        if let Some(last) = self.pipeline_children.last() {
-            self.child = Some(*last);
+        // The children hold their own handles to the channels and files, so
+        // a reader sees the end of its input once its writer exits.
+        for (a, b) in channels {
+            file::close(a);
+            file::close(b);
         }
-    }
+        commands.iter().for_each(Prepared::close_files);
 
-    /// Handle child process exit.
-    pub fn handle_child_exit(&mut self, handle: Handle) {
-        // Check if it's the main child
-        if let Some(child) = self.child {
-            if child.as_raw() == handle.as_raw() {
-                let exit_code = process::wait(child);
-                if exit_code != 0 {
-                    self.write_line(&alloc::format!("(exited with code {})", exit_code));
-                }
-                self.child = None;
-                self.pending_input = None;
-            }
+        // The last child is the "main" child for output purposes
+        let Some(&main) = processes.last() else {
+            return Some(STATUS_NOT_STARTED);
+        };
+        if let Some(job) = self.job_mut(job) {
+            job.processes = processes;
+            job.main = Some(main);
         }
-
-        // Also check pipeline children
-        self.pipeline_children.retain(|&h: &Handle| {
-            if h.as_raw() == handle.as_raw() {
-                let _ = process::wait(h);
-                false // Remove from list
-            } else {
-                true // Keep in list
-            }
-        });
+        None
     }
 
     /// Process channel messages from child.
@@ -260,8 +298,7 @@ impl Terminal {
                         self.handle_request(msg, handle);
                     } else if let Ok(value) = Value::from_bytes(&buf[..len]) {
                         // Raw Value from pipeline output
-                        self.render_value(&value);
-                        self.flush();
+                        self.show_output(&value, handle);
                     }
                 }
                 _ => break,
@@ -0,0 +1,303 @@
+//! The line being typed, at the shell prompt or for a program's line
+//! request: drawing it as it is edited, tab completion, and the history
+//! file.
+//!
+//! Typing and rubbing out at the end of the line draw just the characters
+//! that changed. Any other edit puts the line back in the scrollback buffer
+//! and re-renders the screen, with a bar under the character at the cursor
+//! when that is not the end.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use libpanda::{env, environment, file, io::File, DirEntry};
+use panda_abi::terminal::InputKind;
+use panda_abi::DIRENT_NAME_MAX;
+use terminal::line_editor::{Completion, Edit, LineEditor, HISTORY_LIMIT};
+
+use crate::commands::open_output;
+use crate::{builtins, Terminal, COLOUR_DEFAULT_FG, LINE_HEIGHT, MARGIN};
+
+/// The history file, in `$HOME`.
+const HISTORY_FILE: &str = ".history";
+
+/// Thickness of the bar drawn under the character at the cursor.
+const CARET_HEIGHT: u32 = 2;
+
+/// The names in a directory, each with whether it is a directory itself.
+fn read_dir(path: &str) -> Vec<(String, bool)> {
+    let Ok(dir) = environment::opendir(path) else {
+        return Vec::new();
+    };
+    let mut entry = DirEntry {
+        name_len: 0,
+        is_dir: false,
+        name: [0; DIRENT_NAME_MAX],
+    };
+    let mut entries = Vec::new();
+    while file::readdir(dir, &mut entry) > 0 {
+        entries.push((String::from(entry.name()), entry.is_dir));
+    }
+    file::close(dir);
+    entries
+}
+
+/// What the path `word` could go on to be: the entries of the directory
+/// it is in, directories ending in `/`. Hidden entries only count if the
+/// word asks for them.
+fn path_candidates(word: &str) -> Vec<String> {
+    let (dir, prefix) = word.split_at(word.rfind('/').map_or(0, |at| at + 1));
+    let path = env::resolve_path(if dir.is_empty() { "." } else { dir });
+    read_dir(&path)
+        .into_iter()
+        .filter(|(name, _)| !name.starts_with('.') || prefix.starts_with('.'))
+        .map(|(name, is_dir)| format!("{}{}{}", dir, name, if is_dir { "/" } else { "" }))
+        .collect()
+}
+
+fn history_path() -> String {
+    let home = env::get("HOME").unwrap_or_else(|| String::from("/"));
+    env::resolve_path(&format!("{}/{}", home, HISTORY_FILE))
+}
+
+impl Terminal {
+    /// The editor keys go to: that of a program's line request, or the
+    /// shell's when no job is in the foreground.
+    fn active_editor(&mut self) -> Option<&mut LineEditor> {
+        match &mut self.session.pending_input {
+            Some(pending) => matches!(pending.kind, InputKind::Line | InputKind::Password)
+                .then_some(&mut pending.editor),
+            None => self
+                .session
+                .foreground
+                .is_none()
+                .then_some(&mut self.session.editor),
+        }
+    }
+
+    /// The line to show and the cursor's place in it, passwords masked.
+    fn input_display(&self) -> Option<(String, usize)> {
+        match &self.session.pending_input {
+            Some(pending) => match pending.kind {
+                InputKind::Line => Some(pending.editor.display()),
+                InputKind::Password => {
+                    let (text, cursor) = pending.editor.display();
+                    Some((text.chars().map(|_| '*').collect(), cursor))
+                }
+                _ => None,
+            },
+            None => self
+                .session
+                .foreground
+                .is_none()
+                .then(|| self.session.editor.display()),
+        }
+    }
+
+    /// Apply a key's edit to the line being typed, if there is one.
+    pub fn edit(&mut self, edit: Edit) {
+        let Some(editor) = self.active_editor() else {
+            return;
+        };
+        editor.apply(edit);
+        self.redraw_input(true);
+        self.flush();
+    }
+
+    /// Insert text at the cursor of the line being typed, if there is one,
+    /// drawing it once.
+    pub fn insert_text(&mut self, text: &str) {
+        let Some(editor) = self.active_editor() else {
+            return;
+        };
+        for ch in text.chars().filter(|ch| !ch.is_control()) {
+            editor.apply(Edit::Insert(ch));
+        }
+        self.redraw_input(true);
+        self.flush();
+    }
+
+    /// Bring the line on screen up to date with its editor, with the caret
+    /// if `caret` and the cursor is not at the end.
+    fn redraw_input(&mut self, caret: bool) {
+        let Some((text, cursor)) = self.input_display() else {
+            return;
+        };
+        let len = text.chars().count();
+        let caret = caret && cursor < len;
+        let shown = core::mem::replace(&mut self.session.input_shown, text.clone());
+
+        if !self.session.caret_shown && !caret {
+            if let Some(added) = text.strip_prefix(shown.as_str()) {
+                for ch in added.chars() {
+                    self.type_char(ch);
+                }
+                return;
+            }
+            if let Some(removed) = shown.strip_prefix(text.as_str()) {
+                for ch in removed.chars().rev() {
+                    let char_width = self.measure_char(ch);
+                    self.unrecord_char();
+                    self.backspace_width(char_width);
+                }
+                return;
+            }
+        }
+
+        for _ in shown.chars() {
+            self.unrecord_char();
+        }
+        for ch in text.chars() {
+            self.record_char(ch, self.session.current_fg);
+        }
+        self.session.scroll = 0;
+        self.render_visible_lines();
+        self.session.caret_shown = caret;
+        if caret {
+            self.draw_caret(len - cursor);
+        }
+    }
+
+    /// Draw the caret again, if the line has one, after the screen has been
+    /// re-rendered under it.
+    pub(crate) fn show_caret(&mut self) {
+        if !self.session.caret_shown {
+            return;
+        }
+        if let Some((text, cursor)) = self.input_display() {
+            self.draw_caret(text.chars().count() - cursor);
+        }
+    }
+
+    /// Underline the character `from_end` characters before the end of the
+    /// last line, which has just been rendered.
+    fn draw_caret(&mut self, from_end: usize) {
+        let Some(line) = self.session.display_lines.last() else {
+            return;
+        };
+        let (glyphs, (_, end_row)) = self.layout_line(line);
+        let Some(glyph) = glyphs.len().checked_sub(from_end).map(|at| &glyphs[at]) else {
+            return;
+        };
+        // The cursor is on the last line's last row.
+        let rows_up = (end_row - glyph.row) as u32;
+        let Some(y) = self.cursor_y.checked_sub(rows_up * LINE_HEIGHT) else {
+            return;
+        };
+        if y < MARGIN {
+            return;
+        }
+        let width = self.measure_char(glyph.ch);
+        let x = glyph.x;
+        self.fb_fill(
+            x,
+            y + LINE_HEIGHT - CARET_HEIGHT,
+            width,
+            CARET_HEIGHT,
+            COLOUR_DEFAULT_FG,
+        );
+    }
+
+    /// Leave the line on screen as it stands, without the caret, so that
+    /// output can follow it.
+    pub fn end_input(&mut self) {
+        self.redraw_input(false);
+        self.session.input_shown.clear();
+        self.session.caret_shown = false;
+    }
+
+    /// Finish the line being typed: show it as typed, without the caret
+    /// or a search prompt, and take its text.
+    pub fn take_line(&mut self) -> String {
+        let Some(editor) = self.active_editor() else {
+            return String::new();
+        };
+        // Moving the cursor accepts a search's match.
+        editor.apply(Edit::End);
+        self.end_input();
+        self.active_editor()
+            .map(LineEditor::submit)
+            .unwrap_or_default()
+    }
+
+    /// Tab: complete the word at the cursor as a command name or a path.
+    /// When the candidates agree on nothing more they are listed.
+    pub fn complete(&mut self) {
+        if self.session.pending_input.is_some() || self.session.foreground.is_some() {
+            return;
+        }
+        let word = self.session.editor.completion_word();
+        let candidates = if word.command && !word.text.contains('/') {
+            self.command_names()
+        } else {
+            path_candidates(&word.text)
+        };
+
+        match self.session.editor.complete(&word, &candidates) {
+            Completion::None | Completion::Completed => self.redraw_input(true),
+            Completion::Ambiguous(matches) => {
+                self.end_input();
+                self.newline();
+                // Just the last part of a path.
+                let names: Vec<&str> = matches
+                    .iter()
+                    .map(|path| {
+                        let base = path.trim_end_matches('/');
+                        &path[base.rfind('/').map_or(0, |at| at + 1)..]
+                    })
+                    .collect();
+                self.write_line(&names.join("  "));
+                self.show_prompt();
+                self.redraw_input(true);
+            }
+        }
+        self.flush();
+    }
+
+    /// Every builtin, and every file in a `PATH` directory.
+    fn command_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = builtins::names().map(String::from).collect();
+        let path = env::get("PATH").unwrap_or_default();
+        for dir in path.split(':').filter(|dir| !dir.is_empty()) {
+            let entries = read_dir(&env::resolve_path(dir));
+            names.extend(
+                entries
+                    .into_iter()
+                    .filter(|(_, is_dir)| !is_dir)
+                    .map(|(name, _)| name),
+            );
+        }
+        names
+    }
+
+    /// Read the history file into the shell's editor. A file grown to twice
+    /// the history's length is written again with just what was kept.
+    pub fn load_history(&mut self) {
+        let Ok(text) = File::read_to_string_path(&history_path()) else {
+            return;
+        };
+        for line in text.lines() {
+            self.session.editor.add_history(line);
+        }
+        if text.lines().count() > 2 * HISTORY_LIMIT
+            && let Ok(handle) = open_output(&history_path(), false)
+        {
+            let mut kept = self.session.editor.history().join("\n");
+            kept.push('\n');
+            file::write(handle, kept.as_bytes());
+            file::close(handle);
+        }
+    }
+
+    /// Add a line run at the prompt to the history, and to the end of the
+    /// history file.
+    pub fn remember(&mut self, line: &str) {
+        if !self.session.editor.add_history(line) {
+            return;
+        }
+        if let Ok(handle) = open_output(&history_path(), true) {
+            file::write(handle, format!("{}\n", line).as_bytes());
+            file::close(handle);
+        }
+    }
+}
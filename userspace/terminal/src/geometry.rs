@@ -0,0 +1,82 @@
+//! Window geometry as the terminal takes it on its command line:
+//! `WIDTHxHEIGHT`, optionally followed by `+X+Y` for where it goes.
+
+use core::fmt;
+
+/// A window's size, and where it goes if that is given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Geometry {
+    pub width: u32,
+    pub height: u32,
+    pub position: Option<(u32, u32)>,
+}
+
+impl Geometry {
+    /// Parse `WIDTHxHEIGHT` or `WIDTHxHEIGHT+X+Y`. A size of nothing is no
+    /// size at all.
+    pub fn parse(text: &str) -> Option<Self> {
+        let (size, position) = match text.split_once('+') {
+            Some((size, position)) => {
+                let (x, y) = position.split_once('+')?;
+                (size, Some((x.parse().ok()?, y.parse().ok()?)))
+            }
+            None => (text, None),
+        };
+        let (width, height) = size.split_once('x')?;
+        let (width, height) = (width.parse().ok()?, height.parse().ok()?);
+        if width == 0 || height == 0 {
+            return None;
+        }
+        Some(Self {
+            width,
+            height,
+            position,
+        })
+    }
+}
+
+impl fmt::Display for Geometry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)?;
+        if let Some((x, y)) = self.position {
+            write!(f, "+{}+{}", x, y)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn size_and_position() {
+        assert_eq!(
+            Geometry::parse("640x480"),
+            Some(Geometry {
+                width: 640,
+                height: 480,
+                position: None,
+            })
+        );
+        let geometry = Geometry::parse("800x600+80+30").unwrap();
+        assert_eq!(geometry.position, Some((80, 30)));
+        assert_eq!(geometry.to_string(), "800x600+80+30");
+    }
+
+    #[test]
+    fn nonsense_is_refused() {
+        for text in [
+            "",
+            "800",
+            "800x",
+            "0x600",
+            "800x600+5",
+            "800x600+a+b",
+            "-1x5",
+        ] {
+            assert_eq!(Geometry::parse(text), None, "{}", text);
+        }
+    }
+}
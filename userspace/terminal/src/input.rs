@@ -1,15 +1,16 @@
 //! Input handling for the terminal.
 //!
 //! This module handles pending input requests from child processes and
-//! keyboard event processing.
+//! the key events the compositor sends the terminal's window.
 
-use alloc::string::String;
 use libpanda::{
-    channel, file,
-    keyboard::{self, KeyValue, RawInputEvent, KEY_BACKSPACE, KEY_ENTER},
+    channel,
+    graphics::WindowEvent,
+    keyboard::{KeyEvent, KeyValue, KeyboardState, Keysym},
     Handle,
 };
 use panda_abi::terminal::{Event as TerminalEvent, InputKind, InputResponse, InputValue};
+use terminal::line_editor::{Edit, LineEditor};
 
 use crate::Terminal;
 
@@ -21,14 +22,15 @@ pub struct PendingInput {
     pub kind: InputKind,
     /// Handle to send response to
     pub handle: Handle,
-    /// Buffer for line input
-    pub buffer: String,
+    /// Line being typed, for line and password input
+    pub editor: LineEditor,
 }
 
 impl Terminal {
     /// Send input response to child
     pub fn send_input_response(&mut self, value: Option<InputValue>) {
-        if let Some(pending) = self.pending_input.take() {
+        self.end_input();
+        if let Some(pending) = self.session.pending_input.take() {
             let response = InputResponse {
                 id: pending.id,
                 value,
@@ -41,7 +43,7 @@ impl Terminal {
 
     /// Handle a typed character when there's a pending input request
     pub fn handle_input_char(&mut self, ch: char) {
-        let Some(ref pending) = self.pending_input else {
+        let Some(ref pending) = self.session.pending_input else {
             return;
         };
 
@@ -50,22 +52,13 @@ impl Terminal {
         match kind {
             InputKind::Char => {
                 // Single character - send immediately
-                self.emit_char(ch, self.current_fg);
+                self.emit_char(ch, self.session.current_fg);
                 self.flush();
                 self.send_input_response(Some(InputValue::Char(ch)));
             }
             InputKind::Line | InputKind::Password => {
-                if kind == InputKind::Password {
-                    // Don't echo password characters, just show *
-                    self.emit_char('*', self.current_fg);
-                } else {
-                    self.emit_char(ch, self.current_fg);
-                }
-                // Now we can mutate pending_input
-                if let Some(ref mut pending) = self.pending_input {
-                    pending.buffer.push(ch);
-                }
-                self.flush();
+                // Password characters are shown as *
+                self.edit(Edit::Insert(ch));
             }
             InputKind::Confirm => {
                 // Accept y/Y for yes, n/N for no
@@ -75,7 +68,7 @@ impl Terminal {
                     _ => None,
                 };
                 if let Some(b) = result {
-                    self.emit_char(ch, self.current_fg);
+                    self.emit_char(ch, self.session.current_fg);
                     self.newline();
                     self.flush();
                     self.send_input_response(Some(InputValue::Bool(b)));
@@ -89,15 +82,13 @@ impl Terminal {
 
     /// Handle Enter key when there's a pending input request
     pub fn handle_input_enter(&mut self) {
-        let Some(ref pending) = self.pending_input else {
+        let Some(ref pending) = self.session.pending_input else {
             return;
         };
 
-        let kind = pending.kind;
-        let text = pending.buffer.clone();
-
-        match kind {
+        match pending.kind {
             InputKind::Line | InputKind::Password => {
+                let text = self.take_line();
                 self.newline();
                 self.flush();
                 self.send_input_response(Some(InputValue::Text(text)));
@@ -105,92 +96,118 @@ impl Terminal {
             _ => {}
         }
     }
-
-    /// Handle Backspace key when there's a pending input request
-    pub fn handle_input_backspace(&mut self) {
-        let Some(ref pending) = self.pending_input else {
-            return;
-        };
-
-        let kind = pending.kind;
-        let is_empty = pending.buffer.is_empty();
-
-        match kind {
-            InputKind::Line | InputKind::Password => {
-                if !is_empty {
-                    // Get the character being removed to measure its width
-                    let removed_char = if let Some(ref mut pending) = self.pending_input {
-                        pending.buffer.pop()
-                    } else {
-                        None
-                    };
-                    // For password mode, we displayed '*', so measure that instead
-                    let display_char = if kind == InputKind::Password {
-                        '*'
-                    } else {
-                        removed_char.unwrap_or(' ')
-                    };
-                    let char_width = self.measure_char(display_char);
-                    self.unrecord_char();
-                    self.backspace_width(char_width);
-                    self.flush();
-                }
-            }
-            _ => {}
-        }
-    }
 }
 
 /// Handle a key event
-pub fn handle_key_event(term: &mut Terminal, code: u16, value: KeyValue, shift_pressed: &mut bool) {
-    match value {
-        KeyValue::Press | KeyValue::Repeat => {
-            // Track shift state
-            if keyboard::is_shift_key(code) {
-                *shift_pressed = true;
+pub fn handle_key_event(term: &mut Terminal, code: u16, value: KeyValue, state: &mut KeyboardState) {
+    let event = state.process(code, value);
+    if !event.is_press() || term.handle_session_key(&event) {
+        return;
+    }
+    if term.pager_open() {
+        term.pager_key(&event);
+        return;
+    }
+    // The scrollback is out of sight behind the alternate screen.
+    if !term.alternate_shown() && term.handle_view_key(&event) {
+        return;
+    }
+
+    // Handle special keys
+    match (event.keysym, event.char()) {
+        (_, Some('\u{3}')) => term.interrupt(),
+        (_, Some('\u{1a}')) => term.suspend(),
+        (Keysym::Enter, _) => term.handle_enter(),
+        (Keysym::Tab, _) => term.complete(),
+        _ => {
+            if let Some(edit) = edit_for_key(&event) {
+                term.edit(edit);
                 return;
             }
-
-            // Handle special keys
-            match code {
-                KEY_ENTER => term.handle_enter(),
-                KEY_BACKSPACE => term.handle_backspace(),
-                _ => {
-                    // Try to convert to character
-                    if let Some(ch) = keyboard::keycode_to_char(code, *shift_pressed) {
-                        // If there's pending input from child, route to that
-                        if term.pending_input.is_some() {
-                            term.handle_input_char(ch);
-                        } else if term.child.is_none() {
-                            // Only accept shell input when no child is running
-                            term.handle_char(ch);
-                        }
-                    }
+            for ch in event.text.chars().filter(|ch| !ch.is_control()) {
+                // If there's pending input from child, route to that
+                if term.session.pending_input.is_some() {
+                    term.handle_input_char(ch);
+                } else {
+                    // Only accepted by the shell when no job is in the foreground
+                    term.edit(Edit::Insert(ch));
                 }
             }
         }
-        KeyValue::Release => {
-            if keyboard::is_shift_key(code) {
-                *shift_pressed = false;
-            }
-        }
     }
 }
 
-/// Process any pending keyboard events
-pub fn process_keyboard_events(term: &mut Terminal, shift_pressed: &mut bool) {
-    let mut buf = [0u8; 8]; // RawInputEvent is 8 bytes
-
-    loop {
-        let n = file::try_read(term.keyboard, &mut buf);
-        if n <= 0 {
-            break;
-        }
+/// The edit a key makes to the line being typed, if it makes one: the
+/// navigation keys, Emacs-style control keys and Alt-B/F/D.
+fn edit_for_key(event: &KeyEvent) -> Option<Edit> {
+    let edit = match (event.keysym, event.char()) {
+        (Keysym::Left, _) if event.modifiers.ctrl() => Edit::WordLeft,
+        (Keysym::Right, _) if event.modifiers.ctrl() => Edit::WordRight,
+        (Keysym::Left, _) => Edit::Left,
+        (Keysym::Right, _) => Edit::Right,
+        (Keysym::Home, _) => Edit::Home,
+        (Keysym::End, _) => Edit::End,
+        (Keysym::Up, _) => Edit::HistoryPrev,
+        (Keysym::Down, _) => Edit::HistoryNext,
+        (Keysym::Backspace, _) => Edit::Backspace,
+        (Keysym::Delete, _) => Edit::Delete,
+        (Keysym::Escape, _) => Edit::Cancel,
+        (_, Some(ch)) if event.modifiers.alt() => match ch {
+            'b' => Edit::WordLeft,
+            'f' => Edit::WordRight,
+            'd' => Edit::KillWordForward,
+            _ => return None,
+        },
+        (_, Some(ch)) => match ch {
+            '\u{1}' => Edit::Home,
+            '\u{2}' => Edit::Left,
+            '\u{4}' => Edit::Delete,
+            '\u{5}' => Edit::End,
+            '\u{6}' => Edit::Right,
+            '\u{7}' => Edit::Cancel,
+            '\u{8}' => Edit::Backspace,
+            '\u{b}' => Edit::KillToEnd,
+            '\u{e}' => Edit::HistoryNext,
+            '\u{10}' => Edit::HistoryPrev,
+            '\u{12}' => Edit::Search,
+            '\u{14}' => Edit::Transpose,
+            '\u{15}' => Edit::KillToStart,
+            '\u{17}' => Edit::KillWordBack,
+            '\u{19}' => Edit::Yank,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(edit)
+}
 
-        if n >= 8 {
-            let event = unsafe { &*(buf.as_ptr() as *const RawInputEvent) };
-            let value = KeyValue::from_u32(event.value);
-            handle_key_event(term, event.code, value, shift_pressed);
+/// Process the events the compositor has sent the window
+pub fn process_window_events(term: &mut Terminal, state: &mut KeyboardState) {
+    while let Some(event) = term.window.poll_event() {
+        match event {
+            WindowEvent::Key { code, value } => handle_key_event(term, code, value, state),
+            // Keys held as the focus left will never report their release.
+            WindowEvent::FocusOut => state.release_all(),
+            WindowEvent::Configure {
+                serial,
+                width,
+                height,
+            } => term.resize(serial, width, height),
+            // The pager and the alternate screen have no use for the
+            // buttons.
+            WindowEvent::PointerButton { .. } | WindowEvent::PointerMotion { .. }
+                if term.pager_open() || term.alternate_shown() => {}
+            WindowEvent::PointerButton {
+                x,
+                y,
+                button,
+                pressed,
+            } => term.pointer_button(x, y, button, pressed),
+            WindowEvent::PointerMotion { x, y } => term.pointer_motion(x, y),
+            WindowEvent::PointerWheel { delta, .. } if term.pager_open() => term.pager_wheel(delta),
+            WindowEvent::PointerWheel { .. } if term.alternate_shown() => {}
+            WindowEvent::PointerWheel { delta, .. } => term.pointer_wheel(delta),
+            _ => {}
         }
     }
 }
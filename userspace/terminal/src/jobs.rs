@@ -0,0 +1,399 @@
+//! Jobs: the and-or lists the shell is running, in the foreground or the
+//! background, and the keys and builtins that move them between the two.
+//!
+//! A job runs one pipeline at a time. Ctrl-C tells each process of the
+//! foreground job's pipeline (with a `Signal::Interrupt` event on its
+//! control channel) and drops the rest of the line. The kernel kills a
+//! process that cannot be told, its channel full or closed, at once, and
+//! one that carries on regardless [`INTERRUPT_GRACE_MS`] later or at the
+//! next Ctrl-C, whichever comes first. Ctrl-Z tells
+//! them with `Signal::Suspend` and then stops them with the kernel, since
+//! a process cannot stop itself; `fg` and `bg` continue them.
+
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Write;
+use libpanda::{channel, environment, process, Handle};
+use panda_abi::terminal::{Event as TerminalEvent, Signal};
+use panda_abi::{SIGNAL_CONTINUE, SIGNAL_EXIT_BASE, SIGNAL_KILL, SIGNAL_STOP};
+use terminal::shell::{AndOrList, Step};
+
+use crate::Terminal;
+
+/// The status of a line whose job was stopped.
+const STATUS_STOPPED: i32 = SIGNAL_EXIT_BASE + SIGNAL_STOP as i32;
+
+/// How long an interrupted process has to exit before it is killed.
+pub const INTERRUPT_GRACE_MS: u64 = 2_000;
+
+pub struct Job {
+    /// The number `jobs`, `fg` and `bg` know it by.
+    pub id: usize,
+    /// The list as shell text.
+    pub text: String,
+    /// The pipelines still to run.
+    pub steps: VecDeque<Step>,
+    /// The processes of the running pipeline that have not exited.
+    pub processes: Vec<Handle>,
+    /// The pipeline's last command, whose exit code is its status.
+    pub main: Option<Handle>,
+    /// The status of the last pipeline, for `&&`, `||` and `$?`.
+    pub status: i32,
+    pub stopped: bool,
+    /// Ctrl-C has been pressed once already.
+    pub interrupted: bool,
+    /// When the job is killed if Ctrl-C has not ended it, in
+    /// `environment::time` milliseconds.
+    pub kill_at: Option<u64>,
+}
+
+impl Job {
+    fn signal(&self, signal: u32) {
+        for &handle in &self.processes {
+            process::signal(handle, signal);
+        }
+    }
+
+    /// Tell every process over its control channel. A process that does not
+    /// read the channel never sees it.
+    fn notify(&self, signal: Signal) {
+        let message = TerminalEvent::Signal(signal).to_bytes();
+        for &handle in &self.processes {
+            let _ = channel::try_send(handle, &message);
+        }
+    }
+
+    /// Tell every process to stop what it is doing, killing at once any
+    /// that cannot be told, and arm the deadline for the rest.
+    fn interrupt(&mut self, now: u64) {
+        let message = TerminalEvent::Signal(Signal::Interrupt).to_bytes();
+        for &handle in &self.processes {
+            if channel::try_send(handle, &message).is_err() {
+                process::signal(handle, SIGNAL_KILL);
+            }
+        }
+        self.interrupted = true;
+        self.kill_at = Some(now + INTERRUPT_GRACE_MS);
+    }
+}
+
+impl Terminal {
+    pub fn job(&self, id: usize) -> Option<&Job> {
+        self.session.jobs.iter().find(|job| job.id == id)
+    }
+
+    pub fn job_mut(&mut self, id: usize) -> Option<&mut Job> {
+        self.session.jobs.iter_mut().find(|job| job.id == id)
+    }
+
+    /// The process the terminal talks to: the foreground pipeline's last.
+    pub fn foreground_process(&self) -> Option<Handle> {
+        self.job(self.session.foreground?)?.main
+    }
+
+    /// Whether `handle` is a process of the foreground job.
+    pub fn is_foreground(&self, handle: Handle) -> bool {
+        self.session
+            .foreground
+            .and_then(|id| self.job(id))
+            .is_some_and(|job| job.processes.contains(&handle))
+    }
+
+    /// Carry on with the line: start its next list once the foreground job
+    /// is done, or show the prompt when nothing is left.
+    pub fn run_script(&mut self) {
+        while self.session.foreground.is_none() {
+            let Some(list) = self.session.script.pop_front() else {
+                self.show_prompt();
+                return;
+            };
+            self.start_job(list);
+        }
+    }
+
+    fn is_foreground_job(&self, id: usize) -> bool {
+        self.session.foreground == Some(id)
+    }
+
+    /// Make a job of `list` and run it as far as it goes without waiting.
+    fn start_job(&mut self, list: AndOrList) {
+        // The lowest number not in use.
+        let id = (1..)
+            .find(|&id| self.job(id).is_none())
+            .expect("job numbers are not all in use");
+        let background = list.background;
+        self.session.jobs.push(Job {
+            id,
+            text: list.to_string(),
+            steps: list.into_steps().into(),
+            processes: Vec::new(),
+            main: None,
+            status: 0,
+            stopped: false,
+            interrupted: false,
+            kill_at: None,
+        });
+
+        if background {
+            self.write_line(&format!("[{}]", id));
+            self.session.last_status = 0;
+        } else {
+            self.session.foreground = Some(id);
+            self.collect_output();
+        }
+        self.advance(id);
+    }
+
+    /// Run a job's next steps, until one starts processes (the rest waits
+    /// for them to exit) or none are left and the job is done.
+    pub fn advance(&mut self, id: usize) {
+        while let Some(job) = self.job_mut(id) {
+            let Some(step) = job.steps.pop_front() else {
+                self.finish_job(id);
+                return;
+            };
+            if !step.condition.holds(job.status) {
+                continue;
+            }
+            match self.run_pipeline(id, &step.pipeline) {
+                Some(status) => self.set_status(id, status),
+                // Started, or taken over by `fg`.
+                None => return,
+            }
+        }
+    }
+
+    fn set_status(&mut self, id: usize, status: i32) {
+        if let Some(job) = self.job_mut(id) {
+            job.status = status;
+        }
+        if self.is_foreground_job(id) {
+            self.session.last_status = status;
+        }
+    }
+
+    fn finish_job(&mut self, id: usize) {
+        let Some(index) = self.session.jobs.iter().position(|job| job.id == id) else {
+            return;
+        };
+        let job = self.session.jobs.remove(index);
+        if self.is_foreground_job(id) {
+            self.session.foreground = None;
+        } else {
+            let state = match job.status {
+                0 => String::from("Done"),
+                status => format!("Exit {}", status),
+            };
+            self.session
+                .notices
+                .push(format!("[{}] {}  {}", id, state, job.text));
+        }
+    }
+
+    /// Show the prompt, after any news of background jobs.
+    pub fn show_prompt(&mut self) {
+        self.output_done();
+        for notice in core::mem::take(&mut self.session.notices) {
+            self.write_line(&notice);
+        }
+        self.write_str("> ");
+    }
+
+    /// Handle child process exit. Once the whole pipeline has exited, its
+    /// job carries on with the main child's status.
+    pub fn handle_child_exit(&mut self, handle: Handle) {
+        self.vt_exited(handle);
+        let Some(job) = self
+            .session
+            .jobs
+            .iter_mut()
+            .find(|job| job.processes.contains(&handle))
+        else {
+            return;
+        };
+        let exit_code = process::wait(handle);
+        job.processes.retain(|&h| h != handle);
+
+        let id = job.id;
+        let pipeline_done = job.processes.is_empty();
+        if job.main == Some(handle) {
+            job.main = None;
+            if self.is_foreground_job(id) && exit_code != 0 {
+                self.write_line(&format!("(exited with code {})", exit_code));
+            }
+            self.set_status(id, exit_code);
+        }
+        if self
+            .session
+            .pending_input
+            .as_ref()
+            .is_some_and(|pending| pending.handle == handle)
+        {
+            self.session.pending_input = None;
+        }
+
+        if pipeline_done {
+            let foreground = self.is_foreground_job(id);
+            self.advance(id);
+            // A background job's news waits for the next prompt.
+            if foreground {
+                self.run_script();
+            }
+        }
+    }
+
+    /// Ctrl-C: interrupt the foreground job, or throw away the line being
+    /// typed if there is none.
+    pub fn interrupt(&mut self) {
+        self.end_input();
+        self.write_line("^C");
+        let Some(id) = self.session.foreground else {
+            self.session.editor.clear();
+            self.show_prompt();
+            self.flush();
+            return;
+        };
+
+        // Nothing more of the line runs, whatever the pipeline does.
+        self.session.script.clear();
+        let Some(job) = self.job_mut(id) else {
+            return;
+        };
+        job.steps.clear();
+        if job.interrupted {
+            job.signal(SIGNAL_KILL);
+        } else {
+            job.interrupt(environment::time() as u64);
+        }
+        self.flush();
+    }
+
+    /// The earliest deadline of an interrupted job, in any session.
+    pub fn kill_deadline(&self) -> Option<u64> {
+        core::iter::once(&self.session)
+            .chain(&self.parked)
+            .flat_map(|session| &session.jobs)
+            .filter_map(|job| job.kill_at)
+            .min()
+    }
+
+    /// Kill the interrupted jobs whose deadline has passed.
+    pub fn kill_overdue(&mut self) {
+        let now = environment::time() as u64;
+        for session in core::iter::once(&mut self.session).chain(&mut self.parked) {
+            for job in &mut session.jobs {
+                if job.kill_at.is_some_and(|at| at <= now) {
+                    job.kill_at = None;
+                    job.signal(SIGNAL_KILL);
+                }
+            }
+        }
+    }
+
+    /// Ctrl-Z: stop the foreground job and carry on with the line.
+    pub fn suspend(&mut self) {
+        let Some(id) = self.session.foreground else {
+            return;
+        };
+        let Some(job) = self.job_mut(id) else {
+            return;
+        };
+        job.notify(Signal::Suspend);
+        job.signal(SIGNAL_STOP);
+        job.stopped = true;
+        // Stopping it is what was asked for now, not ending it.
+        job.kill_at = None;
+        let notice = format!("[{}] Stopped  {}", id, job.text);
+
+        // Whatever was being typed for it goes nowhere now.
+        self.send_input_response(None);
+        self.session.foreground = None;
+        self.session.last_status = STATUS_STOPPED;
+        self.write_line("^Z");
+        self.write_line(&notice);
+        self.run_script();
+        self.flush();
+    }
+
+    /// The job an `fg` or `bg` argument names (`2` or `%2`), or the newest.
+    fn find_job(&mut self, name: &str, args: &[String]) -> Option<usize> {
+        let id = match args.first() {
+            Some(arg) => arg.trim_start_matches('%').parse().ok(),
+            None => self.session.jobs.iter().map(|job| job.id).max(),
+        };
+        match id.filter(|&id| self.job(id).is_some()) {
+            Some(id) => Some(id),
+            None => {
+                let which = args.first().map(String::as_str).unwrap_or("current");
+                self.write_line(&format!("{}: {}: no such job", name, which));
+                None
+            }
+        }
+    }
+
+    /// `fg [job]`: continue a job in the foreground. The job takes the
+    /// place of the one running `fg`, and whatever follows `fg` in that one
+    /// runs after it.
+    pub fn fg(&mut self, caller: usize, args: &[String]) -> Option<i32> {
+        let Some(id) = self.find_job("fg", args) else {
+            return Some(1);
+        };
+        if id == caller {
+            self.write_line("fg: a job cannot wait for itself");
+            return Some(1);
+        }
+
+        let rest = self
+            .job_mut(caller)
+            .map(|job| core::mem::take(&mut job.steps))
+            .unwrap_or_default();
+        self.session.jobs.retain(|job| job.id != caller);
+        if self.is_foreground_job(caller) {
+            self.session.foreground = Some(id);
+        }
+
+        let job = self.job_mut(id)?;
+        job.steps.extend(rest);
+        job.interrupted = false;
+        job.kill_at = None;
+        let text = job.text.clone();
+        if core::mem::take(&mut job.stopped) {
+            job.signal(SIGNAL_CONTINUE);
+        }
+        let idle = job.processes.is_empty();
+        self.write_line(&text);
+        if idle {
+            self.advance(id);
+        }
+        None
+    }
+
+    /// `bg [job]`: continue a stopped job in the background.
+    pub fn bg(&mut self, args: &[String]) -> i32 {
+        let Some(id) = self.find_job("bg", args) else {
+            return 1;
+        };
+        let Some(job) = self.job_mut(id) else {
+            return 1;
+        };
+        if core::mem::take(&mut job.stopped) {
+            job.signal(SIGNAL_CONTINUE);
+        }
+        let notice = format!("[{}] {} &", id, job.text.trim_end_matches(" &"));
+        self.write_line(&notice);
+        0
+    }
+
+    /// `jobs`: list the background and stopped jobs.
+    pub fn write_jobs(&self, out: &mut String) {
+        for job in &self.session.jobs {
+            if self.is_foreground_job(job.id) {
+                continue;
+            }
+            let state = if job.stopped { "Stopped" } else { "Running" };
+            let _ = writeln!(out, "[{}] {}  {}", job.id, state, job.text);
+        }
+    }
+}
@@ -0,0 +1,18 @@
+//! The terminal.
+//!
+//! The `os` feature (on by default) builds the terminal program itself.
+//! Without it only [`shell`], [`line_editor`], [`value_layout`], [`pager`],
+//! [`vt`] and [`geometry`] compile, so the shell language, line editing, the
+//! layout and paging of output, escape sequences and window geometry can be
+//! unit-tested on the host.
+
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+pub mod geometry;
+pub mod line_editor;
+pub mod pager;
+pub mod shell;
+pub mod value_layout;
+pub mod vt;
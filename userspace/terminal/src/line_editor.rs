@@ -0,0 +1,635 @@
+//! Editing a line of input: the shell's command line, and the lines
+//! programs ask the terminal for (`InputKind::Line`).
+//!
+//! [`LineEditor`] only keeps the text, the cursor, the history and the
+//! kill buffer; the terminal maps keys to [`Edit`]s (Emacs-style: Ctrl-A,
+//! Ctrl-E, Ctrl-K, Ctrl-R and so on) and draws what [`LineEditor::display`]
+//! returns. Completion works the same way: the editor finds the word under
+//! the cursor, the terminal lists what it could be, and the editor fills in
+//! as much as the candidates agree on.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Most lines the history keeps; the oldest go first.
+pub const HISTORY_LIMIT: usize = 500;
+
+/// A change to the line, usually from a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    Insert(char),
+    /// Left one character (Left, Ctrl-B).
+    Left,
+    /// Right one character (Right, Ctrl-F).
+    Right,
+    /// To the start of the word (Ctrl-Left, Alt-B).
+    WordLeft,
+    /// Past the end of the word (Ctrl-Right, Alt-F).
+    WordRight,
+    /// To the start of the line (Home, Ctrl-A).
+    Home,
+    /// To the end of the line (End, Ctrl-E).
+    End,
+    /// Delete the character before the cursor (Backspace, Ctrl-H).
+    Backspace,
+    /// Delete the character under the cursor (Delete, Ctrl-D).
+    Delete,
+    /// Cut back to the previous whitespace (Ctrl-W).
+    KillWordBack,
+    /// Cut to the end of the word (Alt-D).
+    KillWordForward,
+    /// Cut to the end of the line (Ctrl-K).
+    KillToEnd,
+    /// Cut to the start of the line (Ctrl-U).
+    KillToStart,
+    /// Paste the last text cut (Ctrl-Y).
+    Yank,
+    /// Swap the characters either side of the cursor (Ctrl-T).
+    Transpose,
+    /// The previous line in the history (Up, Ctrl-P).
+    HistoryPrev,
+    /// The next line in the history, or back to the line being typed
+    /// (Down, Ctrl-N).
+    HistoryNext,
+    /// Search the history backwards, or find the next older match if
+    /// already searching (Ctrl-R).
+    Search,
+    /// Give up a search, putting the line back as it was (Ctrl-G, Escape).
+    Cancel,
+}
+
+/// What [`LineEditor::complete`] did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Completion {
+    /// Nothing starts with the word.
+    None,
+    /// The word was completed, fully or as far as the candidates agree.
+    Completed,
+    /// The candidates agree on nothing more: these are the ones to show.
+    Ambiguous(Vec<String>),
+}
+
+/// The word under the cursor, for completion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionWord {
+    /// Where the word starts, in characters.
+    pub start: usize,
+    /// The word up to the cursor.
+    pub text: String,
+    /// Whether the word is a command name rather than an argument.
+    pub command: bool,
+}
+
+/// A reverse search through the history in progress.
+#[derive(Debug, Clone)]
+struct Search {
+    query: String,
+    /// The history entry shown, if any has matched.
+    found: Option<usize>,
+    /// The last search found nothing.
+    failing: bool,
+    /// The line and cursor to put back on [`Edit::Cancel`].
+    saved: (Vec<char>, usize),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LineEditor {
+    line: Vec<char>,
+    /// Characters before the cursor.
+    cursor: usize,
+    history: Vec<String>,
+    /// The history entry being shown, and the line typed before Up was
+    /// first pressed.
+    browsing: Option<(usize, Vec<char>)>,
+    /// The last text cut, for [`Edit::Yank`].
+    killed: Vec<char>,
+    search: Option<Search>,
+}
+
+/// Characters that end a word for completion, besides whitespace.
+fn is_operator(ch: char) -> bool {
+    matches!(ch, '|' | '&' | ';' | '<' | '>')
+}
+
+fn is_word_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+impl LineEditor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The line as typed (or as the search has found it).
+    pub fn text(&self) -> String {
+        self.line.iter().collect()
+    }
+
+    /// The cursor's position in characters.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.line.is_empty()
+    }
+
+    /// What to show for the line, and where in it the cursor is (in
+    /// characters). While searching this is the search prompt followed by
+    /// the match.
+    pub fn display(&self) -> (String, usize) {
+        let text = self.text();
+        match &self.search {
+            Some(search) => {
+                let label = if search.failing {
+                    "failing reverse-i-search"
+                } else {
+                    "reverse-i-search"
+                };
+                let prompt = format!("({})`{}': ", label, search.query);
+                let offset = prompt.chars().count();
+                (prompt + &text, offset + self.cursor)
+            }
+            None => (text, self.cursor),
+        }
+    }
+
+    /// Empty the line, ending any search and history browsing.
+    pub fn clear(&mut self) {
+        self.line.clear();
+        self.cursor = 0;
+        self.browsing = None;
+        self.search = None;
+    }
+
+    /// Take the finished line (accepting any search match) and start a new
+    /// one.
+    pub fn submit(&mut self) -> String {
+        let text = self.text();
+        self.clear();
+        text
+    }
+
+    /// The history, oldest first.
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    /// Add a line to the history, unless it is blank or the same as the
+    /// last one. Returns whether it was added.
+    pub fn add_history(&mut self, line: &str) -> bool {
+        if line.trim().is_empty() || self.history.last().is_some_and(|last| last == line) {
+            return false;
+        }
+        self.history.push(String::from(line));
+        if self.history.len() > HISTORY_LIMIT {
+            let excess = self.history.len() - HISTORY_LIMIT;
+            self.history.drain(..excess);
+        }
+        true
+    }
+
+    pub fn apply(&mut self, edit: Edit) {
+        if self.search.is_some() {
+            match edit {
+                Edit::Insert(ch) => return self.search_insert(ch),
+                Edit::Backspace => return self.search_backspace(),
+                Edit::Search => return self.search_older(),
+                Edit::Cancel => {
+                    if let Some(search) = self.search.take() {
+                        (self.line, self.cursor) = search.saved;
+                    }
+                    return;
+                }
+                // Anything else edits the match.
+                _ => self.search = None,
+            }
+        }
+
+        match edit {
+            Edit::Insert(ch) => {
+                self.line.insert(self.cursor, ch);
+                self.cursor += 1;
+            }
+            Edit::Left => self.cursor = self.cursor.saturating_sub(1),
+            Edit::Right => self.cursor = (self.cursor + 1).min(self.line.len()),
+            Edit::WordLeft => self.cursor = self.word_start(),
+            Edit::WordRight => self.cursor = self.word_end(),
+            Edit::Home => self.cursor = 0,
+            Edit::End => self.cursor = self.line.len(),
+            Edit::Backspace => {
+                if self.cursor > 0 {
+                    self.cursor -= 1;
+                    self.line.remove(self.cursor);
+                }
+            }
+            Edit::Delete => {
+                if self.cursor < self.line.len() {
+                    self.line.remove(self.cursor);
+                }
+            }
+            Edit::KillWordBack => {
+                let mut start = self.cursor;
+                while start > 0 && self.line[start - 1].is_whitespace() {
+                    start -= 1;
+                }
+                while start > 0 && !self.line[start - 1].is_whitespace() {
+                    start -= 1;
+                }
+                self.kill(start, self.cursor);
+            }
+            Edit::KillWordForward => self.kill(self.cursor, self.word_end()),
+            Edit::KillToEnd => self.kill(self.cursor, self.line.len()),
+            Edit::KillToStart => self.kill(0, self.cursor),
+            Edit::Yank => {
+                let killed = self.killed.clone();
+                self.line
+                    .splice(self.cursor..self.cursor, killed.iter().copied());
+                self.cursor += killed.len();
+            }
+            Edit::Transpose => {
+                // At the end of the line, the last two characters.
+                let at = self.cursor.min(self.line.len().saturating_sub(1));
+                if at > 0 {
+                    self.line.swap(at - 1, at);
+                    self.cursor = at + 1;
+                }
+            }
+            Edit::HistoryPrev => self.history_prev(),
+            Edit::HistoryNext => self.history_next(),
+            Edit::Search => {
+                self.search = Some(Search {
+                    query: String::new(),
+                    found: None,
+                    failing: false,
+                    saved: (self.line.clone(), self.cursor),
+                });
+            }
+            Edit::Cancel => {}
+        }
+    }
+
+    /// Where the word before the cursor starts.
+    fn word_start(&self) -> usize {
+        let mut at = self.cursor;
+        while at > 0 && !is_word_char(self.line[at - 1]) {
+            at -= 1;
+        }
+        while at > 0 && is_word_char(self.line[at - 1]) {
+            at -= 1;
+        }
+        at
+    }
+
+    /// Where the word at or after the cursor ends.
+    fn word_end(&self) -> usize {
+        let mut at = self.cursor;
+        while at < self.line.len() && !is_word_char(self.line[at]) {
+            at += 1;
+        }
+        while at < self.line.len() && is_word_char(self.line[at]) {
+            at += 1;
+        }
+        at
+    }
+
+    fn kill(&mut self, start: usize, end: usize) {
+        if start < end {
+            self.killed = self.line.drain(start..end).collect();
+            self.cursor = start;
+        }
+    }
+
+    fn show(&mut self, text: &str) {
+        self.line = text.chars().collect();
+        self.cursor = self.line.len();
+    }
+
+    fn history_prev(&mut self) {
+        let index = match &self.browsing {
+            Some((index, _)) => index.saturating_sub(1),
+            None if self.history.is_empty() => return,
+            None => self.history.len() - 1,
+        };
+        let typed = match self.browsing.take() {
+            Some((_, typed)) => typed,
+            None => self.line.clone(),
+        };
+        self.browsing = Some((index, typed));
+        self.show(&self.history[index].clone());
+    }
+
+    fn history_next(&mut self) {
+        let Some((index, typed)) = self.browsing.take() else {
+            return;
+        };
+        if index + 1 < self.history.len() {
+            self.browsing = Some((index + 1, typed));
+            self.show(&self.history[index + 1].clone());
+        } else {
+            self.line = typed;
+            self.cursor = self.line.len();
+        }
+    }
+
+    fn search_insert(&mut self, ch: char) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+        search.query.push(ch);
+        // The match shown may still match.
+        let before = search.found.map_or(self.history.len(), |index| index + 1);
+        self.search_before(before);
+    }
+
+    fn search_backspace(&mut self) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+        search.query.pop();
+        search.found = None;
+        self.search_before(self.history.len());
+    }
+
+    fn search_older(&mut self) {
+        let Some(search) = &self.search else {
+            return;
+        };
+        let before = search.found.unwrap_or(self.history.len());
+        self.search_before(before);
+    }
+
+    /// Show the newest history entry older than `before` that contains the
+    /// query, with the cursor at the match.
+    fn search_before(&mut self, before: usize) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+        if search.query.is_empty() {
+            search.failing = false;
+            return;
+        }
+        let found = self.history[..before]
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(index, entry)| Some((index, entry.find(search.query.as_str())?)));
+        match found {
+            Some((index, at)) => {
+                search.found = Some(index);
+                search.failing = false;
+                let entry = &self.history[index];
+                self.line = entry.chars().collect();
+                self.cursor = entry[..at].chars().count();
+            }
+            None => search.failing = true,
+        }
+    }
+
+    /// The word the cursor is at the end of, and whether it is in command
+    /// position: the first word of the line or after `|`, `;`, `&`, `&&`
+    /// or `||`.
+    pub fn completion_word(&self) -> CompletionWord {
+        let before = &self.line[..self.cursor];
+        let start = before
+            .iter()
+            .rposition(|&ch| ch.is_whitespace() || is_operator(ch))
+            .map_or(0, |at| at + 1);
+        let previous = before[..start].iter().rev().find(|ch| !ch.is_whitespace());
+        CompletionWord {
+            start,
+            text: before[start..].iter().collect(),
+            command: matches!(previous, None | Some('|' | ';' | '&')),
+        }
+    }
+
+    /// Complete `word` from `candidates`, each a whole replacement for it
+    /// (those that don't start with it are ignored). A single match is
+    /// followed by a space, unless it is a directory ending in `/`.
+    pub fn complete(&mut self, word: &CompletionWord, candidates: &[String]) -> Completion {
+        let mut matches: Vec<&String> = candidates
+            .iter()
+            .filter(|candidate| candidate.starts_with(word.text.as_str()))
+            .collect();
+        matches.sort();
+        matches.dedup();
+
+        let Some(first) = matches.first() else {
+            return Completion::None;
+        };
+        let mut completion = if matches.len() == 1 {
+            let mut whole = (*first).clone();
+            if !whole.ends_with('/') {
+                whole.push(' ');
+            }
+            whole
+        } else {
+            let common = matches[1..]
+                .iter()
+                .fold(first.chars().count(), |len, candidate| {
+                    first
+                        .chars()
+                        .zip(candidate.chars())
+                        .take(len)
+                        .take_while(|(a, b)| a == b)
+                        .count()
+                });
+            first.chars().take(common).collect()
+        };
+
+        if completion.chars().count() <= word.text.chars().count() {
+            return match matches.len() {
+                1 => Completion::Completed,
+                _ => Completion::Ambiguous(matches.into_iter().cloned().collect()),
+            };
+        }
+        self.search = None;
+        let end = word.start + word.text.chars().count();
+        let new: Vec<char> = completion.drain(..).collect();
+        self.cursor = word.start + new.len();
+        self.line.splice(word.start..end, new);
+        Completion::Completed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    fn typed(text: &str) -> LineEditor {
+        let mut editor = LineEditor::new();
+        for ch in text.chars() {
+            editor.apply(Edit::Insert(ch));
+        }
+        editor
+    }
+
+    fn apply(editor: &mut LineEditor, edits: &[Edit]) {
+        for &edit in edits {
+            editor.apply(edit);
+        }
+    }
+
+    #[test]
+    fn edits_happen_at_the_cursor() {
+        let mut editor = typed("helo");
+        apply(&mut editor, &[Edit::Left, Edit::Insert('l')]);
+        assert_eq!((editor.text(), editor.cursor()), ("hello".to_string(), 4));
+
+        apply(&mut editor, &[Edit::Home, Edit::Delete, Edit::Insert('j')]);
+        assert_eq!(editor.text(), "jello");
+        apply(&mut editor, &[Edit::End, Edit::Backspace, Edit::Right]);
+        assert_eq!((editor.text(), editor.cursor()), ("jell".to_string(), 4));
+    }
+
+    #[test]
+    fn words_move_and_cut() {
+        let mut editor = typed("cat foo_bar.txt");
+        editor.apply(Edit::WordLeft);
+        assert_eq!(editor.cursor(), 12);
+        apply(&mut editor, &[Edit::WordLeft, Edit::WordLeft]);
+        assert_eq!(editor.cursor(), 0);
+        editor.apply(Edit::WordRight);
+        assert_eq!(editor.cursor(), 3);
+
+        apply(&mut editor, &[Edit::End, Edit::KillWordBack]);
+        assert_eq!(editor.text(), "cat ");
+        editor.apply(Edit::Yank);
+        assert_eq!(editor.text(), "cat foo_bar.txt");
+        apply(&mut editor, &[Edit::Home, Edit::KillWordForward]);
+        assert_eq!(editor.text(), " foo_bar.txt");
+    }
+
+    #[test]
+    fn kills_to_the_ends_and_yanks_back() {
+        let mut editor = typed("echo hello");
+        apply(&mut editor, &[Edit::WordLeft, Edit::KillToEnd]);
+        assert_eq!(editor.text(), "echo ");
+        apply(&mut editor, &[Edit::KillToStart, Edit::Yank, Edit::Yank]);
+        assert_eq!(editor.text(), "echo echo ");
+
+        let mut editor = typed("ab");
+        editor.apply(Edit::Transpose);
+        assert_eq!(editor.text(), "ba");
+    }
+
+    #[test]
+    fn history_browses_back_to_the_typed_line() {
+        let mut editor = typed("");
+        editor.add_history("ls");
+        editor.add_history("cat x");
+        editor.add_history("cat x");
+        editor.add_history("  ");
+        assert_eq!(editor.history(), ["ls", "cat x"]);
+
+        apply(&mut editor, &[Edit::Insert('p'), Edit::HistoryPrev]);
+        assert_eq!(editor.text(), "cat x");
+        apply(&mut editor, &[Edit::HistoryPrev, Edit::HistoryPrev]);
+        assert_eq!(editor.text(), "ls");
+        apply(&mut editor, &[Edit::HistoryNext, Edit::HistoryNext]);
+        assert_eq!((editor.text(), editor.cursor()), ("p".to_string(), 1));
+    }
+
+    #[test]
+    fn reverse_search_finds_older_matches() {
+        let mut editor = typed("draft");
+        for line in ["cat one", "ls", "cat two"] {
+            editor.add_history(line);
+        }
+
+        apply(
+            &mut editor,
+            &[Edit::Search, Edit::Insert('c'), Edit::Insert('a')],
+        );
+        assert_eq!(
+            editor.display(),
+            ("(reverse-i-search)`ca': cat two".to_string(), 24)
+        );
+        editor.apply(Edit::Search);
+        assert_eq!(editor.text(), "cat one");
+        editor.apply(Edit::Search);
+        assert_eq!(
+            editor.display().0,
+            "(failing reverse-i-search)`ca': cat one"
+        );
+
+        editor.apply(Edit::Cancel);
+        assert_eq!(editor.display(), ("draft".to_string(), 5));
+
+        apply(&mut editor, &[Edit::Search, Edit::Insert('l'), Edit::End]);
+        assert_eq!(editor.display(), ("ls".to_string(), 2));
+        assert_eq!(editor.submit(), "ls");
+    }
+
+    #[test]
+    fn completion_words_know_command_position() {
+        let word = |text: &str| typed(text).completion_word();
+        assert_eq!(
+            word("ca"),
+            CompletionWord {
+                start: 0,
+                text: "ca".to_string(),
+                command: true
+            }
+        );
+        assert_eq!(
+            word("cat /mnt/fo"),
+            CompletionWord {
+                start: 4,
+                text: "/mnt/fo".to_string(),
+                command: false
+            }
+        );
+        assert!(word("ls | gr").command);
+        assert!(word("true && ec").command);
+        assert!(!word("echo >ou").command);
+        assert_eq!(word("echo ").text, "");
+    }
+
+    #[test]
+    fn completion_fills_in_what_candidates_agree_on() {
+        let candidates = vec!["cat".to_string(), "clear".to_string(), "cd".to_string()];
+
+        let mut editor = typed("cl x");
+        apply(&mut editor, &[Edit::WordLeft, Edit::Left]);
+        let word = editor.completion_word();
+        assert_eq!(editor.complete(&word, &candidates), Completion::Completed);
+        assert_eq!(
+            (editor.text(), editor.cursor()),
+            ("clear  x".to_string(), 6)
+        );
+
+        let mut editor = typed("c");
+        let word = editor.completion_word();
+        assert_eq!(
+            editor.complete(&word, &candidates),
+            Completion::Ambiguous(vec![
+                "cat".to_string(),
+                "cd".to_string(),
+                "clear".to_string()
+            ])
+        );
+
+        let candidates = vec!["/mnt/docs/".to_string(), "/mnt/doom".to_string()];
+        let mut editor = typed("ls /mnt/d");
+        let word = editor.completion_word();
+        assert_eq!(editor.complete(&word, &candidates), Completion::Completed);
+        assert_eq!(editor.text(), "ls /mnt/do");
+        let word = editor.completion_word();
+        assert!(matches!(
+            editor.complete(&word, &candidates),
+            Completion::Ambiguous(_)
+        ));
+        editor.apply(Edit::Insert('c'));
+        let word = editor.completion_word();
+        editor.complete(&word, &candidates);
+        assert_eq!(editor.text(), "ls /mnt/docs/");
+
+        let mut editor = typed("zz");
+        let word = editor.completion_word();
+        assert_eq!(editor.complete(&word, &candidates), Completion::None);
+    }
+}
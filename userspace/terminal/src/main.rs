@@ -4,43 +4,59 @@
 extern crate alloc;
 extern crate panda_abi;
 
+mod ansi;
+mod builtins;
 mod commands;
+mod editing;
 mod input;
+mod jobs;
+mod paging;
 mod render;
+mod scrollback;
+mod session;
 
-use alloc::collections::BTreeMap;
 use alloc::string::String;
 use alloc::vec::Vec;
-use fontdue::{Font, FontSettings};
 use libpanda::{
     channel, environment,
-    graphics::{PixelBuffer, Rect as WindowRect, Window},
-    mailbox::{ChannelEvent, Event, InputEvent, Mailbox, ProcessEvent},
-    Handle,
+    graphics::{self, Canvas, Colour, Font, PixelBuffer, Point, Rect as WindowRect, Window},
+    ipc::Channel,
+    keyboard::{self, KeyboardState},
+    mailbox::{ChannelEvent, Event, Mailbox, ProcessEvent},
+    process, Handle,
 };
 use panda_abi::{
     terminal::{
-        ClearRegion, ColourSupport, Event as TerminalEvent, QueryResponse, Request,
-        TerminalCapabilities, TerminalQuery,
+        ClearRegion, ColourSupport, Event as TerminalEvent, InputResponse, QueryResponse,
+        Request, TerminalCapabilities, TerminalQuery,
     },
     value::Value,
 };
 
+use terminal::geometry::Geometry;
+use terminal::line_editor::LineEditor;
+use terminal::value_layout::{self, Span};
+
 use crate::input::PendingInput;
 use crate::render::{colour_to_argb, Word, WordIter};
-
-fn rdtsc() -> u64 {
-    let lo: u32;
-    let hi: u32;
-    unsafe {
-        core::arch::asm!("rdtsc", out("eax") lo, out("edx") hi, options(nomem, nostack));
-    }
-    ((hi as u64) << 32) | lo as u64
-}
+use crate::scrollback::Selection;
+use crate::session::Session;
 
 // Terminal colours (ARGB format)
 const COLOUR_BACKGROUND: u32 = 0xFF1E1E1E; // Dark grey
 const COLOUR_DEFAULT_FG: u32 = 0xFFD4D4D4; // Light grey
+const COLOUR_SELECTION: u32 = 0xFF264F78; // Muted blue
+const COLOUR_LINK: u32 = 0xFF3794FF; // Light blue
+
+/// The window's title when the program in it hasn't set one.
+const TITLE: &str = "Terminal";
+
+/// Where the window goes and how big it is, unless told otherwise.
+const DEFAULT_GEOMETRY: Geometry = Geometry {
+    width: 800,
+    height: 600,
+    position: Some((50, 50)),
+};
 
 const MARGIN: u32 = 10;
 const FONT_SIZE: f32 = 16.0;
@@ -50,22 +66,68 @@ const LINE_HEIGHT: u32 = 20; // Font size + spacing
 /// At ~100 bytes per line this is roughly 100 KB.
 const MAX_SCROLLBACK_LINES: usize = 1000;
 
+/// How often the main loop wakes up while an interrupted job may need
+/// killing.
+const KILL_POLL_MS: u64 = 50;
+
+/// Narrowest the text area gets, in cells, however small the window.
+const MIN_COLUMNS: u32 = 4;
+
+/// How text is drawn besides its colour.
+#[derive(Clone, Default, PartialEq)]
+struct Decoration {
+    background: Option<u32>,
+    underline: bool,
+    /// The URL the text links to, opened by clicking it.
+    link: Option<String>,
+}
+
 /// A styled text segment within a line.
 #[derive(Clone)]
 struct Segment {
     text: String,
     colour: u32,
+    decoration: Decoration,
 }
 
 /// A single logical display line, composed of one or more styled segments.
+///
+/// Soft wraps are not stored: a line is laid out again whenever it is
+/// redrawn, so it reflows when the window width changes.
 #[derive(Clone)]
 struct Line {
     segments: Vec<Segment>,
 }
 
+/// A character of a [`Line`] placed by [`Terminal::layout_line`].
+struct Glyph {
+    ch: char,
+    colour: u32,
+    background: Option<u32>,
+    underline: bool,
+    /// Index of the character's segment in the line.
+    segment: usize,
+    x: u32,
+    /// Physical row, counted from the line's first row.
+    row: usize,
+}
+
 // Embed the Hack font at compile time
 const FONT_DATA: &[u8] = include_bytes!("../fonts/Hack-Regular.ttf");
 
+/// A line laid out for the screen by [`Terminal::visible_layouts`].
+struct Placed {
+    /// Index in `display_lines`.
+    line: usize,
+    /// The line's characters, in order.
+    glyphs: Vec<Glyph>,
+    /// Just past the last character.
+    end: (u32, usize),
+    /// Where the line's first row is, above the text area if it is cut
+    /// off at the top.
+    y: i32,
+}
+
 /// Dirty rectangle tracking for batched blits.
 #[derive(Clone, Copy)]
 struct DirtyRect {
@@ -84,76 +146,67 @@ impl DirtyRect {
     }
 }
 
-/// Terminal state
+/// The window and what is drawn in it. What is on screen is the active
+/// session's; the other sessions are parked until they are switched to.
 pub struct Terminal {
     pub window: Window,
-    pub keyboard: Handle,
     pub mailbox: Mailbox,
     font: Font,
     width: u32,
     height: u32,
     cursor_x: u32,
     cursor_y: u32,
-    pub line_buffer: String,
-    /// Currently running child process (last stage of pipeline, or single command)
-    pub child: Option<Handle>,
-    /// All children in a pipeline (for cleanup)
-    pub pipeline_children: Vec<Handle>,
-    /// Pending input request from child
-    pub pending_input: Option<PendingInput>,
-    /// Current foreground colour
-    current_fg: u32,
     /// Average character width for grid-based calculations (terminal size, cursor positioning)
     avg_char_width: u32,
     /// Persistent framebuffer — all rendering composites into this buffer
     framebuffer: PixelBuffer,
     /// Dirty region tracking for batched blits
     dirty: Option<DirtyRect>,
-    /// Glyph cache: avoids re-rasterizing the same character repeatedly
-    glyph_cache: BTreeMap<char, (fontdue::Metrics, Vec<u8>)>,
-    /// Scrollback buffer of display lines.
-    /// New lines are pushed to the end; old lines beyond `MAX_SCROLLBACK_LINES` are dropped.
-    display_lines: Vec<Line>,
+    /// The session shown, or the one being worked on while it is hidden.
+    pub session: Session,
+    /// The other sessions, in the order they were opened.
+    pub parked: Vec<Session>,
+    /// Nothing is drawn: a parked session is being worked on.
+    hidden: bool,
+    /// Processes of closed sessions, killed but not yet exited.
+    pub orphans: Vec<Handle>,
+    /// A connection to the compositor that may capture the screen, from
+    /// whoever spawned the terminal.
+    capture: Option<Channel>,
 }
 
 impl Terminal {
     fn new(
         window: Window,
-        keyboard: Handle,
         mailbox: Mailbox,
         font: Font,
         width: u32,
         height: u32,
+        capture: Option<Channel>,
     ) -> Self {
         // Measure average character width using 'M' (a wide character)
-        let avg_char_width = font.metrics('M', FONT_SIZE).advance_width as u32;
+        let avg_char_width = font.advance('M', FONT_SIZE) as u32;
 
         // Allocate persistent framebuffer for the entire window surface
         let framebuffer =
             PixelBuffer::new(width, height).expect("Failed to allocate terminal framebuffer");
 
-        let mut display_lines = Vec::new();
-        display_lines.push(Line { segments: Vec::new() });
-
         Self {
             window,
-            keyboard,
             mailbox,
             font,
             width,
             height,
             cursor_x: MARGIN,
             cursor_y: MARGIN,
-            line_buffer: String::new(),
-            child: None,
-            pipeline_children: Vec::new(),
-            pending_input: None,
-            current_fg: COLOUR_DEFAULT_FG,
             avg_char_width,
             framebuffer,
             dirty: None,
-            glyph_cache: BTreeMap::new(),
-            display_lines,
+            session: Session::new(1),
+            parked: Vec::new(),
+            hidden: false,
+            orphans: Vec::new(),
+            capture,
         }
     }
 
@@ -161,21 +214,25 @@ impl Terminal {
     pub fn measure_text(&self, text: &str) -> u32 {
         let mut width = 0u32;
         for ch in text.chars() {
-            let metrics = self.font.metrics(ch, FONT_SIZE);
-            width += metrics.advance_width as u32;
+            width += self.font.advance(ch, FONT_SIZE) as u32;
         }
         width
     }
 
     /// Measure the pixel width of a single character
     pub fn measure_char(&self, ch: char) -> u32 {
-        self.font.metrics(ch, FONT_SIZE).advance_width as u32
+        self.font.advance(ch, FONT_SIZE) as u32
     }
 
     /// Clear the screen and scrollback buffer.
     pub fn clear(&mut self) {
-        self.display_lines.clear();
-        self.display_lines.push(Line { segments: Vec::new() });
+        self.session.scroll = 0;
+        self.session.selection = None;
+        self.session.column = None;
+        self.session.display_lines.clear();
+        self.session.display_lines.push(Line {
+            segments: Vec::new(),
+        });
         self.fb_fill(0, 0, self.width, self.height, COLOUR_BACKGROUND);
         self.flush();
         self.cursor_x = MARGIN;
@@ -184,6 +241,7 @@ impl Terminal {
 
     /// Clear a specific region
     fn clear_region(&mut self, region: ClearRegion) {
+        self.follow_output();
         match region {
             ClearRegion::Screen => self.clear(),
             ClearRegion::ToEndOfScreen => {
@@ -227,6 +285,9 @@ impl Terminal {
 
     /// Mark a region as dirty, expanding the existing dirty rect or creating a new one.
     fn mark_dirty(&mut self, x: u32, y: u32, w: u32, h: u32) {
+        if self.hidden {
+            return;
+        }
         // Clamp to framebuffer bounds
         let x1 = (x + w).min(self.width);
         let y1 = (y + h).min(self.height);
@@ -245,6 +306,9 @@ impl Terminal {
 
     /// Fill a rectangle in the framebuffer with a solid ARGB colour.
     fn fb_fill(&mut self, x: u32, y: u32, w: u32, h: u32, colour: u32) {
+        if self.hidden {
+            return;
+        }
         let fb = self.framebuffer.as_bytes_mut();
         let stride = self.width;
         let b = (colour & 0xFF) as u8;
@@ -270,84 +334,41 @@ impl Terminal {
     /// Draw a single character at current cursor position with colour.
     ///
     /// Composites the glyph directly into the framebuffer — no syscalls.
+    /// Glyphs come from the font's own cache, rasterised once each.
     pub fn draw_char_coloured(
         &mut self,
         ch: char,
         fg: u32,
         _bg: Option<u32>,
     ) -> Result<(), &'static str> {
-        if !self.glyph_cache.contains_key(&ch) {
-            let (m, b) = self.font.rasterize(ch, FONT_SIZE);
-            self.glyph_cache.insert(ch, (m, b));
-        }
-        let (metrics, bitmap) = self.glyph_cache.get(&ch).unwrap();
-        let metrics = *metrics;
-
-        if metrics.width == 0 || metrics.height == 0 {
-            // Space or non-visible character - just advance cursor
-            self.cursor_x += metrics.advance_width as u32;
+        if self.hidden {
+            self.cursor_x += self.measure_char(ch);
             return Ok(());
         }
-
-        let glyph_width = metrics.width;
-        let glyph_height = metrics.height;
-
-        // Foreground colour in BGRA byte order, matching the canonical
-        // `alpha_blend`'s convention (compositor_protocol::blend).
-        let fg_bgra = [
-            (fg & 0xFF) as u8,
-            ((fg >> 8) & 0xFF) as u8,
-            ((fg >> 16) & 0xFF) as u8,
-            255,
-        ];
-
-        // Calculate position in framebuffer
-        let glyph_x = self.cursor_x + metrics.xmin as u32;
-        let glyph_y =
-            self.cursor_y + (FONT_SIZE as i32 - metrics.height as i32 - metrics.ymin) as u32;
-
-        // Composite glyph into framebuffer with alpha blending — the one
-        // canonical implementation (compositor_protocol::alpha_blend),
-        // shared with the compositor and `PixelBuffer` (plans/
-        // userspace-compositor.md, "Client library").
-        let fb = self.framebuffer.as_bytes_mut();
-        let stride = self.width;
-
-        for py in 0..glyph_height {
-            let dst_y = glyph_y + py as u32;
-            if dst_y >= self.height {
-                break;
-            }
-            for px in 0..glyph_width {
-                let dst_x = glyph_x + px as u32;
-                if dst_x >= self.width {
-                    break;
-                }
-
-                let coverage = bitmap[py * glyph_width + px];
-                if coverage == 0 {
-                    continue;
-                }
-
-                let off = ((dst_y * stride + dst_x) * 4) as usize;
-                let mut src = fg_bgra;
-                src[3] = coverage;
-                let dst: [u8; 4] = fb[off..off + 4].try_into().unwrap();
-                let blended = compositor_protocol::alpha_blend(src, dst);
-                fb[off..off + 4].copy_from_slice(&blended);
-            }
+        let mut text = [0u8; 4];
+        // Text is drawn opaque whatever alpha the colour carries; the
+        // glyph's coverage is the only transparency.
+        let colour = Colour(fg).with_alpha(255);
+        let baseline = Point::new(self.cursor_x as f32, self.cursor_y as f32 + FONT_SIZE);
+        let damage = Canvas::new(&mut self.framebuffer).fill_text(
+            &self.font,
+            ch.encode_utf8(&mut text),
+            FONT_SIZE,
+            baseline,
+            colour,
+        );
+        if damage.width > 0 && damage.height > 0 {
+            self.mark_dirty(damage.x, damage.y, damage.width, damage.height);
         }
 
-        self.mark_dirty(glyph_x, glyph_y, glyph_width as u32, glyph_height as u32);
-
         // Advance cursor
-        self.cursor_x += metrics.advance_width as u32;
+        self.cursor_x += self.measure_char(ch);
         Ok(())
     }
 
     /// Draw a single character at current cursor position (default colour)
     pub fn draw_char(&mut self, ch: char) -> Result<(), &'static str> {
-        self.draw_char_coloured(ch, self.current_fg, None)
+        self.draw_char_coloured(ch, self.session.current_fg, None)
     }
 
     /// Record a character to the scrollback buffer and draw it on screen.
@@ -355,8 +376,28 @@ impl Terminal {
     /// This is the preferred way to output a single character, keeping the
     /// buffer and display in sync.
     pub fn emit_char(&mut self, ch: char, colour: u32) {
+        self.follow_output();
         self.record_char(ch, colour);
+        let Decoration {
+            background,
+            underline,
+            ..
+        } = self.session.decoration;
+        self.draw_decorated(ch, colour, background, underline);
+    }
+
+    /// Draw a character at the cursor over its background, underlined if
+    /// `underline`.
+    fn draw_decorated(&mut self, ch: char, colour: u32, background: Option<u32>, underline: bool) {
+        let (x, y) = (self.cursor_x, self.cursor_y);
+        let width = self.measure_char(ch);
+        if let Some(background) = background {
+            self.fb_fill(x, y, width, LINE_HEIGHT, background);
+        }
         let _ = self.draw_char_coloured(ch, colour, None);
+        if underline {
+            self.fb_fill(x, y + FONT_SIZE as u32 + 2, width, 1, colour);
+        }
     }
 
     /// Handle a newline.
@@ -366,13 +407,18 @@ impl Terminal {
     /// from the buffer so that existing content scrolls up instead of being
     /// cleared.
     pub fn newline(&mut self) {
+        self.follow_output();
+        self.session.column = None;
         // Start a new logical line in the buffer
-        self.display_lines.push(Line { segments: Vec::new() });
+        self.session.display_lines.push(Line {
+            segments: Vec::new(),
+        });
 
         // Trim scrollback to the configured maximum
-        if self.display_lines.len() > MAX_SCROLLBACK_LINES {
-            let excess = self.display_lines.len() - MAX_SCROLLBACK_LINES;
-            self.display_lines.drain(..excess);
+        if self.session.display_lines.len() > MAX_SCROLLBACK_LINES {
+            let excess = self.session.display_lines.len() - MAX_SCROLLBACK_LINES;
+            self.session.display_lines.drain(..excess);
+            self.lines_dropped(excess);
         }
 
         self.cursor_x = MARGIN;
@@ -384,107 +430,220 @@ impl Terminal {
         }
     }
 
+    /// Soft-wrap: continue the current logical line on the next row.
+    ///
+    /// Unlike [`newline`](Self::newline) this doesn't start a new line in
+    /// the scrollback buffer, so the line can be laid out again at a
+    /// different width when the window is resized.
+    fn wrap(&mut self) {
+        self.follow_output();
+        self.cursor_x = MARGIN;
+        self.cursor_y += LINE_HEIGHT;
+
+        if self.cursor_y + LINE_HEIGHT > self.height - MARGIN {
+            // Scroll, keeping a row free below the line for the text that
+            // caused the wrap.
+            self.render_lines(1);
+            self.cursor_x = MARGIN;
+            self.cursor_y += LINE_HEIGHT;
+        }
+    }
+
     /// Calculate the number of visible lines that fit on screen.
     fn visible_line_count(&self) -> usize {
         ((self.height - 2 * MARGIN) / LINE_HEIGHT) as usize
     }
 
-    /// Calculate how many physical screen rows a logical line occupies,
-    /// accounting for soft-wrapping at the screen width.
-    fn physical_rows_for_line(&self, line: &Line) -> usize {
+    /// Lay out a logical line at the current window width.
+    ///
+    /// Returns each character with its x position and the physical row it
+    /// falls on (counting from the line's first row), followed by the
+    /// position just past the last character. Words that don't fit on the
+    /// rest of a row start the next one, and words longer than a row are
+    /// broken — the same rules `write_str_coloured` applies as text
+    /// arrives, so re-rendering reproduces what was drawn.
+    fn layout_line(&self, line: &Line) -> (Vec<Glyph>, (u32, usize)) {
         let max_x = self.width - MARGIN;
-        let mut cx = MARGIN;
-        let mut rows = 1usize;
-
-        for segment in &line.segments {
-            for ch in segment.text.chars() {
+        let mut glyphs = Vec::new();
+        let mut x = MARGIN;
+        let mut row = 0;
+
+        let chars: Vec<(char, usize)> = line
+            .segments
+            .iter()
+            .enumerate()
+            .flat_map(|(index, segment)| segment.text.chars().map(move |ch| (ch, index)))
+            .collect();
+        let mut i = 0;
+        while i < chars.len() {
+            // A word runs to the next whitespace, across colour changes.
+            let word_end = if chars[i].0.is_whitespace() {
+                i + 1
+            } else {
+                chars[i..]
+                    .iter()
+                    .position(|(ch, _)| ch.is_whitespace())
+                    .map_or(chars.len(), |n| i + n)
+            };
+            if word_end > i + 1 {
+                let word_width: u32 = chars[i..word_end]
+                    .iter()
+                    .map(|&(ch, _)| self.measure_char(ch))
+                    .sum();
+                if x > MARGIN && x + word_width > max_x {
+                    x = MARGIN;
+                    row += 1;
+                }
+            }
+            for &(ch, segment) in &chars[i..word_end] {
                 let char_width = self.measure_char(ch);
-                if cx + char_width > max_x {
-                    cx = MARGIN;
-                    rows += 1;
+                if x > MARGIN && x + char_width > max_x {
+                    x = MARGIN;
+                    row += 1;
                 }
-                cx += char_width;
+                let Segment {
+                    colour, decoration, ..
+                } = &line.segments[segment];
+                glyphs.push(Glyph {
+                    ch,
+                    colour: *colour,
+                    background: decoration.background,
+                    underline: decoration.underline,
+                    segment,
+                    x,
+                    row,
+                });
+                x += char_width;
             }
+            i = word_end;
         }
-        rows
+        (glyphs, (x, row))
     }
 
     /// Re-render the last N visible lines from the scrollback buffer into the
     /// framebuffer. This is the "full re-render from buffer" approach
     /// recommended in the issue for correctness.
     fn render_visible_lines(&mut self) {
-        let max_physical_rows = self.visible_line_count();
+        self.render_lines(0);
+    }
+
+    /// Lay out the lines on screen, oldest first: the end of the
+    /// scrollback buffer, `scroll` rows back from the newest line, leaving
+    /// `reserve` empty rows at the bottom.
+    ///
+    /// If the oldest line shown doesn't fit it is cut off at the top, so the
+    /// screen stays full even when a single line is taller than it.
+    fn visible_layouts(&self, reserve: usize) -> Vec<Placed> {
+        let wanted = self.visible_line_count().saturating_sub(reserve) + self.session.scroll;
 
         // Walk backwards through display_lines, accumulating physical rows
         // until we fill the screen budget.
-        let total = self.display_lines.len();
-        let mut start = total;
-        let mut budget = max_physical_rows;
-
-        for i in (0..total).rev() {
-            let rows = self.physical_rows_for_line(&self.display_lines[i]);
-            if rows > budget {
+        let mut placed = Vec::new();
+        let mut used = 0;
+        for (line, text) in self.session.display_lines.iter().enumerate().rev() {
+            if used >= wanted {
                 break;
             }
-            budget -= rows;
-            start = i;
+            let (glyphs, end) = self.layout_line(text);
+            used += end.1 + 1;
+            placed.push(Placed {
+                line,
+                glyphs,
+                end,
+                y: 0,
+            });
+        }
+        placed.reverse();
+
+        // Rows of the oldest line that scroll off the top.
+        let skip = used.saturating_sub(wanted) as i32;
+        let mut y = MARGIN as i32 - skip * LINE_HEIGHT as i32;
+        for line in &mut placed {
+            line.y = y;
+            y += (line.end.1 as i32 + 1) * LINE_HEIGHT as i32;
         }
+        placed
+    }
 
-        let lines_to_render: Vec<Line> = self.display_lines[start..].to_vec();
+    /// Re-render the end of the scrollback buffer, leaving `reserve` empty
+    /// rows below it, and leave the cursor just past the last character.
+    /// The selection is highlighted.
+    fn render_lines(&mut self, reserve: usize) {
+        let placed = self.visible_layouts(reserve);
+        let rows = self.visible_line_count().saturating_sub(reserve) as u32;
+        let bottom = (MARGIN + rows * LINE_HEIGHT) as i32;
+        let selected = self.session.selection.map(Selection::range);
 
         // Clear the entire framebuffer
         self.fb_fill(0, 0, self.width, self.height, COLOUR_BACKGROUND);
 
-        // Re-draw each line from the buffer
-        let max_x = self.width - MARGIN;
-        let mut y = MARGIN;
-
-        for line in &lines_to_render {
-            self.cursor_x = MARGIN;
-            self.cursor_y = y;
-
-            for segment in &line.segments {
-                for ch in segment.text.chars() {
-                    let char_width = self.measure_char(ch);
-                    if self.cursor_x + char_width > max_x {
-                        // Soft-wrap: advance to next pixel row
-                        self.cursor_x = MARGIN;
-                        self.cursor_y += LINE_HEIGHT;
-                    }
-                    let _ = self.draw_char_coloured(ch, segment.colour, None);
+        let mut end = (MARGIN, MARGIN);
+        for line in placed {
+            for (index, glyph) in line.glyphs.iter().enumerate() {
+                let y = line.y + glyph.row as i32 * LINE_HEIGHT as i32;
+                // Cut off at the top, or scrolled off the bottom.
+                if y < MARGIN as i32 || y >= bottom {
+                    continue;
                 }
+                let background = if selected
+                    .as_ref()
+                    .is_some_and(|range| range.contains(&(line.line, index)))
+                {
+                    Some(COLOUR_SELECTION)
+                } else {
+                    glyph.background
+                };
+                self.cursor_x = glyph.x;
+                self.cursor_y = y as u32;
+                self.draw_decorated(glyph.ch, glyph.colour, background, glyph.underline);
             }
-
-            // Advance y for the next line
-            // Use the cursor_y that draw_char_coloured left us at (accounts for soft wraps)
-            y = self.cursor_y + LINE_HEIGHT;
+            let end_y = line.y + line.end.1 as i32 * LINE_HEIGHT as i32;
+            end = (line.end.0, end_y.max(MARGIN as i32) as u32);
         }
+        (self.cursor_x, self.cursor_y) = end;
+    }
 
-        // Position cursor on the last rendered line. After the loop, `y` points
-        // to the row *after* the last line, so subtract LINE_HEIGHT.
-        self.cursor_y = y.saturating_sub(LINE_HEIGHT);
-        self.cursor_x = MARGIN;
-
-        // Account for the content already on the last line
-        if let Some(last_line) = lines_to_render.last() {
-            // Re-measure the last line's content to position cursor_x correctly
-            let mut cx = MARGIN;
-            for segment in &last_line.segments {
-                for ch in segment.text.chars() {
-                    let char_width = self.measure_char(ch);
-                    if cx + char_width > max_x {
-                        cx = MARGIN;
-                    }
-                    cx += char_width;
-                }
-            }
-            self.cursor_x = cx;
+    /// Adopt a new window size from the compositor.
+    ///
+    /// Acknowledges the configure, reallocates the framebuffer, reflows the
+    /// scrollback to the new width and tells every session's running
+    /// program how many cells it now has.
+    pub fn resize(&mut self, serial: u32, width: u32, height: u32) {
+        // Never go below one row of a few cells; the compositor accepts
+        // buffers of any size, so a smaller configure is answered with
+        // this minimum.
+        let width = width.max(2 * MARGIN + MIN_COLUMNS * self.avg_char_width);
+        let height = height.max(2 * MARGIN + LINE_HEIGHT);
+
+        let Ok(framebuffer) = PixelBuffer::new(width, height) else {
+            environment::log("terminal: Failed to allocate framebuffer for resize");
+            return;
+        };
+        if self.window.ack_configure(serial, width, height).is_err() {
+            return;
         }
+        self.framebuffer = framebuffer;
+        self.width = width;
+        self.height = height;
+        self.dirty = None;
+
+        self.session_resized();
+        self.render_visible_lines();
+        self.show_caret();
+        self.flush();
+        self.in_each_parked(Self::session_resized);
+    }
+
+    /// The size of the text area in character cells.
+    pub(crate) fn size_in_cells(&self) -> (u16, u16) {
+        let cols = (self.width - 2 * MARGIN) / self.avg_char_width;
+        let rows = (self.height - 2 * MARGIN) / LINE_HEIGHT;
+        (cols as u16, rows as u16)
     }
 
     /// Remove the last character from the current line in the scrollback buffer.
     pub(crate) fn unrecord_char(&mut self) {
-        if let Some(line) = self.display_lines.last_mut() {
+        if let Some(line) = self.session.display_lines.last_mut() {
             if let Some(seg) = line.segments.last_mut() {
                 seg.text.pop();
                 if seg.text.is_empty() {
@@ -495,7 +654,22 @@ impl Terminal {
     }
 
     /// Handle backspace, erasing the given character width
+    ///
+    /// Call after [`unrecord_char`](Self::unrecord_char): at the start of a
+    /// wrapped row the character is on the row above, so the screen is
+    /// re-rendered from the buffer instead.
     pub fn backspace_width(&mut self, char_width: u32) {
+        if self.session.scroll > 0 {
+            // The buffer is already right; just show its end.
+            self.session.scroll = 0;
+            self.render_visible_lines();
+            return;
+        }
+        let line_empty = self
+            .session
+            .display_lines
+            .last()
+            .is_none_or(|line| line.segments.is_empty());
         if self.cursor_x > MARGIN {
             self.cursor_x = self.cursor_x.saturating_sub(char_width);
             self.fb_fill(
@@ -505,6 +679,8 @@ impl Terminal {
                 LINE_HEIGHT,
                 COLOUR_BACKGROUND,
             );
+        } else if !line_empty {
+            self.render_visible_lines();
         }
     }
 
@@ -512,7 +688,12 @@ impl Terminal {
     ///
     /// Blits the framebuffer to the window surface (if dirty), then
     /// asks the compositor to present the frame.
+    /// While the pager or the alternate screen is shown, the framebuffer
+    /// waits until it is gone; a parked session's isn't drawn at all.
     pub fn flush(&mut self) {
+        if self.hidden || self.pager_open() || self.alternate_shown() {
+            return;
+        }
         if let Some(dirty) = self.dirty {
             let rect = WindowRect::new(
                 dirty.x0,
@@ -528,17 +709,14 @@ impl Terminal {
         let _ = self.window.flush();
     }
 
-    /// Handle a typed character (when not in input mode from child)
-    pub fn handle_char(&mut self, ch: char) {
-        // Check if we need to wrap
+    /// Echo a typed character, wrapping first if it doesn't fit.
+    pub fn type_char(&mut self, ch: char) {
         let char_width = self.measure_char(ch);
-        if self.cursor_x + char_width > self.width - MARGIN {
-            self.newline();
+        if self.cursor_x > MARGIN && self.cursor_x + char_width > self.width - MARGIN {
+            self.wrap();
         }
 
-        self.emit_char(ch, self.current_fg);
-        self.line_buffer.push(ch);
-        self.flush();
+        self.emit_char(ch, self.session.current_fg);
     }
 
     /// Write a string to the terminal with default colour
@@ -548,16 +726,20 @@ impl Terminal {
 
     /// Append a single character to the current display line in the scrollback buffer.
     pub(crate) fn record_char(&mut self, ch: char, colour: u32) {
-        if let Some(line) = self.display_lines.last_mut() {
+        if let Some(line) = self.session.display_lines.last_mut() {
             if let Some(last_seg) = line.segments.last_mut() {
-                if last_seg.colour == colour {
+                if last_seg.colour == colour && last_seg.decoration == self.session.decoration {
                     last_seg.text.push(ch);
                     return;
                 }
             }
             let mut s = String::new();
             s.push(ch);
-            line.segments.push(Segment { text: s, colour });
+            line.segments.push(Segment {
+                text: s,
+                colour,
+                decoration: self.session.decoration.clone(),
+            });
         }
     }
 
@@ -572,8 +754,8 @@ impl Terminal {
                 Word::Whitespace(ws) => {
                     for ch in ws.chars() {
                         let char_width = self.measure_char(ch);
-                        if self.cursor_x + char_width > max_x {
-                            self.newline();
+                        if self.cursor_x > MARGIN && self.cursor_x + char_width > max_x {
+                            self.wrap();
                         }
                         self.emit_char(ch, colour);
                     }
@@ -583,14 +765,14 @@ impl Terminal {
                     // If word doesn't fit on current line and we're not at the start,
                     // move to next line first
                     if self.cursor_x > MARGIN && self.cursor_x + word_width > max_x {
-                        self.newline();
+                        self.wrap();
                     }
-                    // Write character by character, recording each after any
-                    // soft-wrap newline so the scrollback stays in sync.
+                    // Write character by character, breaking words longer
+                    // than a whole row.
                     for ch in text.chars() {
                         let char_width = self.measure_char(ch);
-                        if self.cursor_x + char_width > max_x {
-                            self.newline();
+                        if self.cursor_x > MARGIN && self.cursor_x + char_width > max_x {
+                            self.wrap();
                         }
                         self.emit_char(ch, colour);
                     }
@@ -605,231 +787,41 @@ impl Terminal {
         self.newline();
     }
 
-    /// Render a Value object (for structured pipeline data)
-    fn render_value(&mut self, value: &panda_abi::value::Value) {
-        use panda_abi::value::Value;
-        match value {
-            Value::Null => self.write_str("null"),
-            Value::Bool(b) => self.write_str(if *b { "true" } else { "false" }),
-            Value::Int(i) => self.write_str(&alloc::format!("{}", i)),
-            Value::Float(f) => self.write_str(&alloc::format!("{}", f)),
-            Value::String(s) => self.write_str(s),
-            Value::Bytes(b) => self.write_str(&alloc::format!("<{} bytes>", b.len())),
-            Value::Array(arr) => {
-                for (i, item) in arr.iter().enumerate() {
-                    if i > 0 {
-                        self.newline();
-                    }
-                    self.write_str("  - ");
-                    self.render_value(item);
-                }
-            }
-            Value::Map(map) => {
-                for (i, (k, v)) in map.iter().enumerate() {
-                    if i > 0 {
-                        self.newline();
-                    }
-                    self.write_str(k);
-                    self.write_str(": ");
-                    self.render_value(v);
-                }
-            }
-            Value::Styled(style, inner) => {
-                let fg = style
-                    .foreground
-                    .as_ref()
-                    .map(colour_to_argb)
-                    .unwrap_or(COLOUR_DEFAULT_FG);
-                // For styled values, we need to render the inner value with the style
-                // For simplicity, only handle String for now
-                if let Value::String(s) = inner.as_ref() {
-                    self.write_str_coloured(s, fg);
-                } else {
-                    // Fall back to rendering without style
-                    self.render_value(inner);
-                }
-            }
-            Value::Link { url, inner } => {
-                self.render_value(inner);
-                self.write_str(" (");
-                self.write_str(url);
-                self.write_str(")");
-            }
-            Value::Table(table) => {
-                self.render_value_table(table);
-            }
-        }
+    /// Render a Value object (for structured pipeline data), laid out by
+    /// [`value_layout::layout`].
+    fn render_value(&mut self, value: &Value) {
+        self.write_lines(&value_layout::layout(value));
     }
 
-    /// Render a Value::Table with proper column width calculation
-    fn render_value_table(&mut self, table: &panda_abi::value::Table) {
-        let t0 = rdtsc();
-        let num_cols = table.cols as usize;
-        if num_cols == 0 {
-            return;
-        }
-
-        // Calculate column widths by measuring all values
-        let mut col_widths = alloc::vec![0usize; num_cols];
-
-        // Measure headers
-        if let Some(ref headers) = table.headers {
-            for (i, header) in headers.iter().enumerate() {
-                if i < num_cols {
-                    let width = self.measure_value_width(header);
-                    if width > col_widths[i] {
-                        col_widths[i] = width;
-                    }
-                }
-            }
-        }
-
-        // Measure all cells
-        for row in table.row_iter() {
-            for (i, cell) in row.iter().enumerate() {
-                if i < num_cols {
-                    let width = self.measure_value_width(cell);
-                    if width > col_widths[i] {
-                        col_widths[i] = width;
-                    }
-                }
-            }
-        }
-
-        let t1 = rdtsc();
-
-        // Render headers if present
-        if let Some(ref headers) = table.headers {
-            for (i, header) in headers.iter().enumerate() {
-                if i > 0 {
-                    self.write_str("  ");
-                }
-                self.render_value(header);
-                // Pad to column width
-                if i < headers.len() - 1 {
-                    let content_width = self.measure_value_width(header);
-                    let padding = col_widths
-                        .get(i)
-                        .unwrap_or(&0)
-                        .saturating_sub(content_width);
-                    for _ in 0..padding {
-                        self.emit_char(' ', COLOUR_DEFAULT_FG);
-                    }
-                }
+    /// Write laid-out lines, with a line break between each.
+    fn write_lines(&mut self, lines: &[value_layout::Line]) {
+        for (i, line) in lines.iter().enumerate() {
+            if i > 0 {
+                self.newline();
             }
-            self.newline();
-
-            // Separator line
-            let total_width: usize =
-                col_widths.iter().sum::<usize>() + (num_cols.saturating_sub(1)) * 2;
-            for _ in 0..total_width {
-                self.emit_char('-', COLOUR_DEFAULT_FG);
+            for span in line {
+                self.write_span(span);
             }
-            self.newline();
         }
-
-        // Render rows
-        for row in table.row_iter() {
-            let row_vec: alloc::vec::Vec<_> = row.iter().collect();
-            for (i, cell) in row_vec.iter().enumerate() {
-                if i > 0 {
-                    self.write_str("  ");
-                }
-                self.render_value(cell);
-                // Pad to column width (except last column)
-                if i < row_vec.len() - 1 {
-                    let content_width = self.measure_value_width(cell);
-                    let padding = col_widths
-                        .get(i)
-                        .unwrap_or(&0)
-                        .saturating_sub(content_width);
-                    for _ in 0..padding {
-                        self.emit_char(' ', COLOUR_DEFAULT_FG);
-                    }
-                }
-            }
-            self.newline();
-        }
-        self.flush();
-
-        let t2 = rdtsc();
-        environment::log(&alloc::format!(
-            "[terminal table timing] measure={} render={}",
-            t1 - t0,
-            t2 - t1
-        ));
     }
 
-    /// Measure the display width of a Value in characters
-    fn measure_value_width(&self, value: &Value) -> usize {
-        match value {
-            Value::Null => 4, // "null"
-            Value::Bool(b) => {
-                if *b {
-                    4
-                } else {
-                    5
-                }
-            } // "true" or "false"
-            Value::Int(n) => {
-                // Count digits (including sign)
-                let mut v = *n;
-                let mut count = if v < 0 { 1 } else { 0 }; // account for minus sign
-                if v == 0 {
-                    return 1;
-                }
-                if v < 0 {
-                    v = v.wrapping_neg();
-                }
-                while v > 0 {
-                    count += 1;
-                    v /= 10;
-                }
-                count
-            }
-            Value::Float(f) => {
-                // Approximate width for float display
-                alloc::format!("{}", f).len()
-            }
-            Value::String(s) => s.len(),
-            Value::Bytes(data) => {
-                // "<N bytes>"
-                alloc::format!("<{} bytes>", data.len()).len()
-            }
-            Value::Array(items) => {
-                // [item, item, ...]
-                if items.is_empty() {
-                    2 // "[]"
-                } else {
-                    2 + items
-                        .iter()
-                        .map(|v| self.measure_value_width(v))
-                        .sum::<usize>()
-                        + (items.len() - 1) * 2 // ", " separators
-                }
-            }
-            Value::Map(fields) => {
-                // {key: value, ...}
-                if fields.is_empty() {
-                    2 // "{}"
-                } else {
-                    2 + fields
-                        .iter()
-                        .map(|(k, v)| k.len() + 2 + self.measure_value_width(v))
-                        .sum::<usize>()
-                        + (fields.len() - 1) * 2
-                }
-            }
-            Value::Styled(_, inner) => {
-                // Styled doesn't change display width
-                self.measure_value_width(inner)
-            }
-            Value::Link { inner, .. } => {
-                // Link text width
-                self.measure_value_width(inner)
-            }
-            Value::Table(_) => 7, // "<table>" placeholder
-        }
+    /// Write a span in its style. Links are underlined, and light blue
+    /// unless they have a colour of their own.
+    fn write_span(&mut self, span: &Span) {
+        let style = &span.style;
+        let default = if span.link.is_some() {
+            COLOUR_LINK
+        } else {
+            COLOUR_DEFAULT_FG
+        };
+        let colour = style.foreground.as_ref().map_or(default, colour_to_argb);
+        self.session.decoration = Decoration {
+            background: style.background.as_ref().map(colour_to_argb),
+            underline: style.underline || span.link.is_some(),
+            link: span.link.clone(),
+        };
+        self.write_str_coloured(&span.text, colour);
+        self.session.decoration = Decoration::default();
     }
 
     /// Handle a terminal request message from child
@@ -850,9 +842,7 @@ impl Terminal {
                 self.flush();
             }
             Request::Write(value) => {
-                // Render the Value
-                self.render_value(&value);
-                self.flush();
+                self.show_output(&value, child_handle);
             }
             Request::MoveCursor { row, col } => {
                 self.cursor_x = MARGIN + col as u32 * self.avg_char_width;
@@ -861,6 +851,14 @@ impl Terminal {
             Request::Clear(region) => {
                 self.clear_region(region);
             }
+            Request::RequestInput(req) if !self.is_foreground(child_handle) => {
+                // Only the foreground job gets to read the keyboard.
+                let response = TerminalEvent::Input(InputResponse {
+                    id: req.id,
+                    value: None,
+                });
+                let _ = channel::send(child_handle, &response.to_bytes());
+            }
             Request::RequestInput(req) => {
                 // Display prompt if provided
                 if let Some(ref prompt) = req.prompt {
@@ -868,17 +866,14 @@ impl Terminal {
                 }
 
                 // Store pending input state
-                self.pending_input = Some(PendingInput {
+                self.session.pending_input = Some(PendingInput {
                     id: req.id,
                     kind: req.kind,
                     handle: child_handle,
-                    buffer: String::new(),
+                    editor: LineEditor::new(),
                 });
             }
-            Request::SetTitle(title) => {
-                // TODO: Set window title when supported
-                let _ = title;
-            }
+            Request::SetTitle(title) => self.set_title(&title),
             Request::Progress {
                 current,
                 total,
@@ -897,18 +892,14 @@ impl Terminal {
             Request::Query(query) => {
                 let response = match query {
                     TerminalQuery::Size => {
-                        let cols = (self.width - 2 * MARGIN) / self.avg_char_width;
-                        let rows = (self.height - 2 * MARGIN) / LINE_HEIGHT;
-                        QueryResponse::Size {
-                            cols: cols as u16,
-                            rows: rows as u16,
-                        }
+                        let (cols, rows) = self.size_in_cells();
+                        QueryResponse::Size { cols, rows }
                     }
                     TerminalQuery::Capabilities => {
                         QueryResponse::Capabilities(TerminalCapabilities {
                             colours: ColourSupport::TrueColour,
                             images: false,
-                            hyperlinks: false,
+                            hyperlinks: true,
                             unicode: true,
                         })
                     }
@@ -920,6 +911,10 @@ impl Terminal {
                             col: col as u16,
                         }
                     }
+                    TerminalQuery::CaptureConnection => {
+                        self.share_capture(child_handle);
+                        return;
+                    }
                 };
 
                 let event_msg = TerminalEvent::QueryResponse(response);
@@ -932,62 +927,72 @@ impl Terminal {
         }
     }
 
+    /// Give a child a connection of its own that may capture the screen, or
+    /// tell it there is none. What runs in the terminal runs as its user,
+    /// so any child may have one.
+    pub fn share_capture(&self, child_handle: Handle) {
+        let shared = self
+            .capture
+            .as_ref()
+            .and_then(|capture| graphics::share_capture(capture).ok());
+        let _ = libpanda::terminal::send_capture_connection(child_handle, shared.as_ref());
+    }
+
     /// Handle Enter key
     pub fn handle_enter(&mut self) {
         // If there's pending input from child, handle that
-        if self.pending_input.is_some() {
+        if self.session.pending_input.is_some() {
             self.handle_input_enter();
             return;
         }
-
-        self.newline();
-
-        if !self.line_buffer.trim().is_empty() {
-            self.execute_command();
-        }
-
-        self.line_buffer.clear();
-        self.flush();
-    }
-
-    /// Handle Backspace key
-    pub fn handle_backspace(&mut self) {
-        // If there's pending input from child, handle that
-        if self.pending_input.is_some() {
-            self.handle_input_backspace();
+        if self.session.foreground.is_some() {
             return;
         }
 
-        if !self.line_buffer.is_empty() {
-            if let Some(ch) = self.line_buffer.pop() {
-                let char_width = self.measure_char(ch);
-                self.unrecord_char();
-                self.backspace_width(char_width);
-                self.flush();
-            }
-        }
+        let line = self.take_line();
+        self.newline();
+        self.remember(&line);
+        self.execute_command(&line);
+        self.flush();
     }
 }
 
-libpanda::main! {
+libpanda::main! { |args|
     environment::log("terminal: Starting");
 
     // Set up initial environment variables
     libpanda::env::set("PATH", "/mnt:/initrd");
-    libpanda::env::set("HOME", "/");
+    // Home is on the ext2 disk, so what is kept there lasts.
+    libpanda::env::set("HOME", "/mnt");
     libpanda::env::set("TERM", "panda");
 
-    let font = Font::from_bytes(FONT_DATA, FontSettings::default())
-        .expect("Failed to load font");
+    let font = Font::from_bytes(FONT_DATA).expect("Failed to load font");
+
+    // Whoever spawned the terminal (`init`, or the terminal a `window`
+    // builtin ran in) answers with the connection jobs capture through.
+    let capture = libpanda::terminal::capture_connection();
 
     let mailbox = Mailbox::default();
 
-    let window_width = 800u32;
-    let window_height = 600u32;
+    // `terminal [WIDTHxHEIGHT[+X+Y]]`, as the `window` builtin opens one.
+    let geometry = match args.get(1) {
+        Some(arg) => match Geometry::parse(arg) {
+            Some(geometry) => geometry,
+            None => {
+                environment::log(&alloc::format!("terminal: bad geometry {}", arg));
+                return 1;
+            }
+        },
+        None => DEFAULT_GEOMETRY,
+    };
+    let (window_width, window_height) = (geometry.width, geometry.height);
+    let (x, y) = geometry.position.or(DEFAULT_GEOMETRY.position).unwrap_or_default();
 
     let Ok(window) = Window::builder()
         .size(window_width, window_height)
-        .position(50, 50)
+        .position(x, y)
+        .title(TITLE)
+        .decorated(true)
         .visible(true)
         .build()
     else {
@@ -995,17 +1000,15 @@ libpanda::main! {
         return 1;
     };
 
-    let Ok(keyboard) = environment::open(
-        "keyboard:/pci/00:03.0",
-        mailbox.handle().as_raw(),
-        panda_abi::EVENT_KEYBOARD_KEY,
-    ) else {
-        environment::log("terminal: Failed to open keyboard");
+    // Keys come from the compositor, addressed to the window while it has
+    // the focus.
+    if window.attach_mailbox(&mailbox).is_err() {
+        environment::log("terminal: Failed to attach the window to the mailbox");
         return 1;
-    };
+    }
 
     environment::log("terminal: creating Terminal");
-    let mut term = Terminal::new(window, keyboard, mailbox, font, window_width, window_height);
+    let mut term = Terminal::new(window, mailbox, font, window_width, window_height, capture);
     environment::log("terminal: calling clear");
     term.clear();
     environment::log("terminal: clear done");
@@ -1013,30 +1016,53 @@ libpanda::main! {
     term.write_line("Panda OS Terminal");
     environment::log("terminal: wrote first line");
     term.write_line("Type 'help' for available commands.");
+    term.load_history();
     term.write_str("> ");
     term.flush();
 
-    let mut shift_pressed = false;
+    let mut keyboard_state = KeyboardState::new(keyboard::system_keymap());
 
     loop {
-        let (handle, events) = term.mailbox.recv();
+        // Until an interrupted job's deadline passes, poll rather than block
+        // so it is killed on time even if nothing else happens.
+        let next = if term.kill_deadline().is_some() {
+            term.kill_overdue();
+            let next = term.mailbox.try_recv();
+            if next.is_none() {
+                process::sleep(KILL_POLL_MS);
+            }
+            next
+        } else {
+            Some(term.mailbox.recv())
+        };
+        let Some((handle, events)) = next else {
+            continue;
+        };
 
         for event in events {
             match event {
-                Event::Input(InputEvent::Keyboard) => {
-                    input::process_keyboard_events(&mut term, &mut shift_pressed);
+                Event::Channel(ChannelEvent::Readable) if handle == term.window.event_handle() => {
+                    input::process_window_events(&mut term, &mut keyboard_state);
+                }
+                Event::Channel(ChannelEvent::Readable) if term.is_orphan(handle) => {
+                    term.drain_orphan(handle);
                 }
                 Event::Channel(ChannelEvent::Readable) => {
                     // Child process sent a message
-                    term.process_child_messages(handle);
+                    term.in_owner(handle, |term| term.process_child_messages(handle));
+                }
+                Event::Process(ProcessEvent::Exited) if term.is_orphan(handle) => {
+                    term.reap_orphan(handle);
                 }
                 Event::Process(ProcessEvent::Exited) => {
-                    term.handle_child_exit(handle);
-                    term.write_str("> ");
-                    term.flush();
+                    term.in_owner(handle, |term| {
+                        term.handle_child_exit(handle);
+                        term.flush();
+                    });
                 }
                 _ => {}
             }
         }
+        term.close_exited();
     }
 }
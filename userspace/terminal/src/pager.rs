@@ -0,0 +1,405 @@
+//! The pager: output too tall for the window, a screenful at a time.
+//!
+//! Lines are wrapped to the screen's width into rows, which the pager
+//! scrolls through with the keys `less` uses: Space/PageDown/`f` and
+//! `b`/PageUp for a screen, Enter/Down/`j` and Up/`k` for a row, `g`/Home
+//! and `G`/End for the start and the end, `/` and `?` to search forwards
+//! and backwards, `n` and `N` to search again, and `q` or Escape to quit.
+//! More lines can arrive while it is open.
+
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::value_layout::{Line, Span, text};
+
+/// Lines kept; older ones are dropped as more arrive.
+pub const PAGER_LIMIT: usize = 10_000;
+
+/// A key, as far as the pager cares.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Escape,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+}
+
+pub struct Pager {
+    /// The lines as written, the last one still open for more text.
+    lines: VecDeque<Line>,
+    /// How many rows each line wraps to.
+    line_rows: VecDeque<usize>,
+    rows: VecDeque<Line>,
+    /// Characters in a row.
+    cols: usize,
+    /// Rows shown at once.
+    height: usize,
+    /// The first row shown.
+    top: usize,
+    /// The search being typed, and whether it goes backwards.
+    query: Option<(String, bool)>,
+    /// The last search made, and whether it went backwards.
+    search: Option<(String, bool)>,
+    /// The row of the last match found.
+    found: Option<usize>,
+    /// Shown on the status line until the next key.
+    message: Option<&'static str>,
+}
+
+/// Split a line into rows of `cols` characters; an empty line is one
+/// empty row.
+fn wrap(line: &[Span], cols: usize) -> Vec<Line> {
+    let mut rows = Vec::new();
+    let mut row = Line::new();
+    let mut used = 0;
+    for span in line {
+        let mut rest = span.text.as_str();
+        while !rest.is_empty() {
+            if used == cols {
+                rows.push(core::mem::take(&mut row));
+                used = 0;
+            }
+            let take = rest
+                .char_indices()
+                .nth(cols - used)
+                .map_or(rest.len(), |(at, _)| at);
+            let (part, after) = rest.split_at(take);
+            used += part.chars().count();
+            row.push(Span {
+                text: String::from(part),
+                ..span.clone()
+            });
+            rest = after;
+        }
+    }
+    rows.push(row);
+    rows
+}
+
+impl Pager {
+    /// An empty pager showing `height` rows of `cols` characters.
+    pub fn new(cols: usize, height: usize) -> Self {
+        Self {
+            lines: VecDeque::new(),
+            line_rows: VecDeque::new(),
+            rows: VecDeque::new(),
+            cols: cols.max(1),
+            height: height.max(1),
+            top: 0,
+            query: None,
+            search: None,
+            found: None,
+            message: None,
+        }
+    }
+
+    /// Add lines of output. The first carries on the last line there is,
+    /// as text written after it on the screen would.
+    pub fn append(&mut self, lines: Vec<Line>) {
+        let mut lines = lines.into_iter();
+        if let Some(first) = lines.next() {
+            match self.lines.back_mut() {
+                Some(last) => {
+                    last.extend(first);
+                    let old = self.line_rows.pop_back().unwrap_or(0);
+                    self.rows.truncate(self.rows.len() - old);
+                    let last = self.lines.pop_back().unwrap_or_default();
+                    self.push_line(last);
+                }
+                None => self.push_line(first),
+            }
+        }
+        for line in lines {
+            self.push_line(line);
+        }
+
+        while self.lines.len() > PAGER_LIMIT {
+            self.lines.pop_front();
+            let dropped = self.line_rows.pop_front().unwrap_or(0);
+            self.rows.drain(..dropped);
+            self.top = self.top.saturating_sub(dropped);
+            self.found = self.found.and_then(|row| row.checked_sub(dropped));
+        }
+    }
+
+    fn push_line(&mut self, line: Line) {
+        let rows = wrap(&line, self.cols);
+        self.line_rows.push_back(rows.len());
+        self.rows.extend(rows);
+        self.lines.push_back(line);
+    }
+
+    /// Show `height` rows of `cols` characters from now on.
+    pub fn resize(&mut self, cols: usize, height: usize) {
+        self.cols = cols.max(1);
+        self.height = height.max(1);
+        self.rows.clear();
+        self.line_rows.clear();
+        for line in core::mem::take(&mut self.lines) {
+            self.push_line(line);
+        }
+        self.found = None;
+        self.scroll_to(self.top);
+    }
+
+    /// How many rows the lines wrap to.
+    pub fn row_count(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// The rows on screen, each with the character ranges of the matches
+    /// of the last search in it.
+    pub fn visible(&self) -> impl Iterator<Item = (&Line, Vec<Range<usize>>)> {
+        self.rows
+            .iter()
+            .skip(self.top)
+            .take(self.height)
+            .map(|row| (row, self.matches(row)))
+    }
+
+    fn matches(&self, row: &[Span]) -> Vec<Range<usize>> {
+        let Some((query, _)) = self.search.as_ref().filter(|(query, _)| !query.is_empty()) else {
+            return Vec::new();
+        };
+        let text = text(row);
+        text.match_indices(query.as_str())
+            .map(|(at, found)| {
+                let start = text[..at].chars().count();
+                start..start + found.chars().count()
+            })
+            .collect()
+    }
+
+    fn last_top(&self) -> usize {
+        self.rows.len().saturating_sub(self.height)
+    }
+
+    fn scroll_to(&mut self, top: usize) {
+        self.top = top.min(self.last_top());
+    }
+
+    /// What the status line under the rows says.
+    pub fn status(&self) -> String {
+        if let Some((query, backwards)) = &self.query {
+            return format!("{}{}", if *backwards { '?' } else { '/' }, query);
+        }
+        if let Some(message) = self.message {
+            return String::from(message);
+        }
+        let last = (self.top + self.height).min(self.rows.len());
+        let end = if last == self.rows.len() {
+            " (END)"
+        } else {
+            ""
+        };
+        format!(
+            "rows {}-{} of {}{}",
+            (self.top + 1).min(last),
+            last,
+            self.rows.len(),
+            end
+        )
+    }
+
+    /// Handle a key. Returns whether the pager is still open.
+    pub fn key(&mut self, key: Key) -> bool {
+        self.message = None;
+        if let Some((query, backwards)) = &mut self.query {
+            match key {
+                Key::Char(ch) => query.push(ch),
+                Key::Backspace if !query.is_empty() => {
+                    query.pop();
+                }
+                Key::Enter => {
+                    let search = (core::mem::take(query), *backwards);
+                    self.query = None;
+                    self.found = None;
+                    if !search.0.is_empty() {
+                        self.search = Some(search);
+                    }
+                    self.search_again(false);
+                }
+                Key::Backspace | Key::Escape => self.query = None,
+                _ => {}
+            }
+            return true;
+        }
+
+        let page = self.height.saturating_sub(1).max(1);
+        match key {
+            Key::Char('q' | 'Q') | Key::Escape => return false,
+            Key::Char(' ' | 'f') | Key::PageDown => self.scroll_to(self.top + page),
+            Key::Char('b') | Key::PageUp => self.scroll_to(self.top.saturating_sub(page)),
+            Key::Char('j') | Key::Enter | Key::Down => self.scroll_to(self.top + 1),
+            Key::Char('k') | Key::Up => self.scroll_to(self.top.saturating_sub(1)),
+            Key::Char('g') | Key::Home => self.scroll_to(0),
+            Key::Char('G') | Key::End => self.scroll_to(self.last_top()),
+            Key::Char('/') => self.query = Some((String::new(), false)),
+            Key::Char('?') => self.query = Some((String::new(), true)),
+            Key::Char('n') => self.search_again(false),
+            Key::Char('N') => self.search_again(true),
+            _ => {}
+        }
+        true
+    }
+
+    /// Scroll to the next match of the last search, in its direction or
+    /// the other way if `reverse`. A search goes on from the last match
+    /// while that is on screen, and from the top row otherwise.
+    fn search_again(&mut self, reverse: bool) {
+        let Some((_, backwards)) = self.search else {
+            self.message = Some("No previous search");
+            return;
+        };
+        let on_screen = self
+            .found
+            .filter(|row| (self.top..self.top + self.height).contains(row));
+        let is_match = |row: &usize| !self.matches(&self.rows[*row]).is_empty();
+        let found = if backwards != reverse {
+            let before = on_screen.unwrap_or(self.top);
+            (0..before).rev().find(is_match)
+        } else {
+            let from = on_screen.map_or(self.top, |row| row + 1);
+            (from..self.rows.len()).find(is_match)
+        };
+        match found {
+            Some(row) => {
+                self.found = Some(row);
+                self.scroll_to(row);
+            }
+            None => self.message = Some("Pattern not found"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    /// A pager of 4 rows of 10 characters holding `lines`.
+    fn pager(lines: &[&str]) -> Pager {
+        let mut pager = Pager::new(10, 4);
+        pager.append(lines.iter().map(|line| vec![Span::plain(*line)]).collect());
+        pager
+    }
+
+    fn shown(pager: &Pager) -> Vec<String> {
+        pager.visible().map(|(row, _)| text(row)).collect()
+    }
+
+    fn numbered(count: usize) -> Vec<String> {
+        (1..=count).map(|n| format!("line {}", n)).collect()
+    }
+
+    fn keys(pager: &mut Pager, keys: &[Key]) {
+        for &key in keys {
+            pager.key(key);
+        }
+    }
+
+    #[test]
+    fn long_lines_wrap_into_rows() {
+        let pager = pager(&["0123456789abcdef", "", "short"]);
+        assert_eq!(shown(&pager), ["0123456789", "abcdef", "", "short"]);
+        assert_eq!(pager.row_count(), 4);
+    }
+
+    #[test]
+    fn text_carries_on_the_last_line() {
+        let mut pager = Pager::new(10, 4);
+        pager.append(vec![vec![Span::plain("abc")]]);
+        pager.append(vec![vec![Span::plain("defghij")], vec![]]);
+        pager.append(vec![vec![Span::plain("xy")]]);
+        assert_eq!(shown(&pager), ["abcdefghij", "xy"]);
+    }
+
+    #[test]
+    fn scrolling_stays_in_bounds() {
+        let lines = numbered(10);
+        let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+        let mut pager = pager(&lines);
+        assert_eq!(pager.status(), "rows 1-4 of 10");
+
+        keys(&mut pager, &[Key::Char(' ')]);
+        assert_eq!(shown(&pager)[0], "line 4");
+        keys(&mut pager, &[Key::PageDown, Key::PageDown]);
+        assert_eq!(shown(&pager)[0], "line 7");
+        assert_eq!(pager.status(), "rows 7-10 of 10 (END)");
+        keys(&mut pager, &[Key::Up, Key::Char('k')]);
+        assert_eq!(shown(&pager)[0], "line 5");
+        keys(&mut pager, &[Key::Home, Key::Up]);
+        assert_eq!(shown(&pager)[0], "line 1");
+        keys(&mut pager, &[Key::Char('G')]);
+        assert_eq!(shown(&pager)[0], "line 7");
+
+        assert!(!pager.key(Key::Char('q')));
+    }
+
+    #[test]
+    fn searches_go_both_ways() {
+        let lines = numbered(12);
+        let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+        let mut pager = pager(&lines);
+
+        keys(&mut pager, &[Key::Char('/'), Key::Char('1')]);
+        assert_eq!(pager.status(), "/1");
+        keys(&mut pager, &[Key::Enter]);
+        assert_eq!(shown(&pager)[0], "line 1");
+        let (_, matches) = pager.visible().next().unwrap();
+        assert_eq!(matches, core::slice::from_ref(&(5..6)));
+
+        keys(&mut pager, &[Key::Char('n')]);
+        assert_eq!(shown(&pager)[0], "line 9");
+        // The last screen can't scroll any further, but n still finds
+        // the matches on it.
+        keys(&mut pager, &[Key::Char('n'), Key::Char('n')]);
+        assert_eq!(pager.found, Some(11));
+        keys(&mut pager, &[Key::Char('n')]);
+        assert_eq!(pager.status(), "Pattern not found");
+        keys(&mut pager, &[Key::Char('N')]);
+        assert_eq!(pager.found, Some(10));
+
+        keys(&mut pager, &[Key::Char('g'), Key::Char('?')]);
+        keys(
+            &mut pager,
+            &[Key::Char('x'), Key::Backspace, Key::Char('3')],
+        );
+        keys(&mut pager, &[Key::Enter]);
+        assert_eq!(pager.status(), "Pattern not found");
+        keys(
+            &mut pager,
+            &[Key::Char('G'), Key::Char('?'), Key::Char('3')],
+        );
+        keys(&mut pager, &[Key::Enter]);
+        assert_eq!(shown(&pager)[0], "line 3");
+    }
+
+    #[test]
+    fn old_lines_go_past_the_limit() {
+        let mut pager = Pager::new(20, 4);
+        let lines = (0..PAGER_LIMIT + 5)
+            .map(|n| vec![Span::plain(n.to_string())])
+            .collect();
+        pager.append(lines);
+        assert_eq!(pager.row_count(), PAGER_LIMIT);
+        assert_eq!(shown(&pager)[0], "5");
+    }
+
+    #[test]
+    fn resizing_rewraps() {
+        let mut pager = pager(&["0123456789abcdef"]);
+        pager.resize(20, 4);
+        assert_eq!(shown(&pager), ["0123456789abcdef"]);
+    }
+}
@@ -0,0 +1,231 @@
+//! The pager over the screen: a command line's output once it grows taller
+//! than the window, and files opened by clicking links.
+//!
+//! While a command line's jobs run in the foreground their output is
+//! collected into a [`Pager`] as well as written to the scrollback. Once it
+//! has more rows than the screen the pager opens and takes the keyboard,
+//! drawing into a buffer of its own that is shown instead of the
+//! framebuffer; the scrollback carries on underneath, and is shown again
+//! when the pager is quit.
+
+use alloc::format;
+use libpanda::graphics::PixelBuffer;
+use libpanda::io::File;
+use libpanda::keyboard::{KeyEvent, Keysym};
+use libpanda::{env, environment, Handle};
+use panda_abi::value::Value;
+use terminal::pager::{Key, Pager};
+use terminal::value_layout;
+
+use crate::render::{draw_cell, fill, Pen};
+use crate::{
+    Terminal, COLOUR_BACKGROUND, COLOUR_DEFAULT_FG, COLOUR_LINK, COLOUR_SELECTION, LINE_HEIGHT,
+    MARGIN,
+};
+
+/// Rows the wheel scrolls the pager per click.
+const WHEEL_ROWS: i32 = 3;
+
+impl Terminal {
+    /// A pager the size of the screen, less the status line.
+    fn new_pager(&self) -> Pager {
+        let (cols, _) = self.size_in_cells();
+        Pager::new(cols as usize, self.visible_line_count() - 1)
+    }
+
+    pub fn pager_open(&self) -> bool {
+        self.session.pager_screen.is_some()
+    }
+
+    /// Start collecting the foreground's output, unless it already is.
+    pub(crate) fn collect_output(&mut self) {
+        if self.session.output.is_none() {
+            self.session.output = Some(self.new_pager());
+        }
+    }
+
+    /// Back at the prompt: stop collecting output, unless the pager is
+    /// showing it.
+    pub(crate) fn output_done(&mut self) {
+        if !self.pager_open() {
+            self.session.output = None;
+        }
+    }
+
+    /// Write a value a program sent. Output of the foreground goes to the
+    /// pager as well, which opens once the output is taller than the
+    /// screen, unless a program has the alternate screen. Text with
+    /// escape sequences in it goes through [`vt_output`](Self::vt_output),
+    /// and isn't paged.
+    pub(crate) fn show_output(&mut self, value: &Value, handle: Handle) {
+        if self.vt_output(value, handle) {
+            return;
+        }
+        let lines = value_layout::layout(value);
+        self.write_lines(&lines);
+        let screen_rows = self.visible_line_count();
+        if self.is_foreground(handle)
+            && !self.alternate_shown()
+            && let Some(pager) = &mut self.session.output
+        {
+            pager.append(lines);
+            let overflows = pager.row_count() > screen_rows;
+            if self.pager_open() {
+                self.render_pager();
+            } else if overflows {
+                self.open_pager();
+            }
+        }
+        self.flush();
+    }
+
+    fn open_pager(&mut self) {
+        let Ok(screen) = PixelBuffer::new(self.width, self.height) else {
+            environment::log("terminal: Failed to allocate the pager's screen");
+            self.session.output = None;
+            return;
+        };
+        self.session.pager_screen = Some(screen);
+        self.render_pager();
+    }
+
+    fn close_pager(&mut self) {
+        self.session.pager_screen = None;
+        self.session.output = None;
+        // Show the scrollback as it now is.
+        self.mark_dirty(0, 0, self.width, self.height);
+        self.flush();
+    }
+
+    /// The window changed size: wrap the pager's lines again to fit.
+    pub(crate) fn resize_pager(&mut self) {
+        if !self.pager_open() {
+            return;
+        }
+        let Ok(screen) = PixelBuffer::new(self.width, self.height) else {
+            self.close_pager();
+            return;
+        };
+        self.session.pager_screen = Some(screen);
+        let (cols, _) = self.size_in_cells();
+        let rows = self.visible_line_count() - 1;
+        if let Some(pager) = &mut self.session.output {
+            pager.resize(cols as usize, rows);
+        }
+        self.render_pager();
+    }
+
+    /// Draw the pager's rows, matches of its search highlighted, and its
+    /// status line, and show them unless their session is parked.
+    pub(crate) fn render_pager(&mut self) {
+        let cell = self.avg_char_width;
+        let status_y = MARGIN + (self.visible_line_count() as u32 - 1) * LINE_HEIGHT;
+        let session = &mut self.session;
+        let (Some(screen), Some(pager)) = (&mut session.pager_screen, &session.output) else {
+            return;
+        };
+        fill(screen, 0, 0, self.width, self.height, COLOUR_BACKGROUND);
+
+        let mut y = MARGIN;
+        for (row, matches) in pager.visible() {
+            let mut col = 0;
+            for span in row {
+                let default = if span.link.is_some() {
+                    COLOUR_LINK
+                } else {
+                    COLOUR_DEFAULT_FG
+                };
+                let mut pen = Pen::new(&span.style, default);
+                pen.underline |= span.link.is_some();
+                let background = pen.background;
+                for ch in span.text.chars() {
+                    let x = MARGIN + col as u32 * cell;
+                    pen.background = if matches.iter().any(|range| range.contains(&col)) {
+                        Some(COLOUR_SELECTION)
+                    } else {
+                        background
+                    };
+                    draw_cell(screen, &self.font, ch, x, y, cell, &pen);
+                    col += 1;
+                }
+            }
+            y += LINE_HEIGHT;
+        }
+
+        // The status line, in reverse.
+        fill(
+            screen,
+            0,
+            status_y,
+            self.width,
+            LINE_HEIGHT,
+            COLOUR_DEFAULT_FG,
+        );
+        let reversed = Pen {
+            colour: COLOUR_BACKGROUND,
+            background: None,
+            underline: false,
+        };
+        for (col, ch) in pager.status().chars().enumerate() {
+            let x = MARGIN + col as u32 * cell;
+            draw_cell(screen, &self.font, ch, x, status_y, cell, &reversed);
+        }
+
+        if !self.hidden {
+            let _ = self.window.blit(screen, 0, 0);
+            let _ = self.window.flush();
+        }
+    }
+
+    /// A key pressed while the pager is open. Ctrl-C quits it too.
+    pub fn pager_key(&mut self, event: &KeyEvent) {
+        let key = match (event.keysym, event.char()) {
+            (_, Some('\u{3}')) | (Keysym::Escape, _) => Key::Escape,
+            (Keysym::Enter, _) => Key::Enter,
+            (Keysym::Backspace, _) => Key::Backspace,
+            (Keysym::Up, _) => Key::Up,
+            (Keysym::Down, _) => Key::Down,
+            (Keysym::PageUp, _) => Key::PageUp,
+            (Keysym::PageDown, _) => Key::PageDown,
+            (Keysym::Home, _) => Key::Home,
+            (Keysym::End, _) => Key::End,
+            (_, Some(ch)) if !ch.is_control() => Key::Char(ch),
+            _ => return,
+        };
+        self.pager_keys(key, 1);
+    }
+
+    /// The wheel turned while the pager is open.
+    pub fn pager_wheel(&mut self, delta: i32) {
+        let key = if delta > 0 { Key::Up } else { Key::Down };
+        self.pager_keys(key, (delta.abs() * WHEEL_ROWS) as usize);
+    }
+
+    fn pager_keys(&mut self, key: Key, times: usize) {
+        let Some(pager) = &mut self.session.output else {
+            return;
+        };
+        if (0..times).all(|_| pager.key(key)) {
+            self.render_pager();
+        } else {
+            self.close_pager();
+        }
+    }
+
+    /// Follow a link: a file is shown in the pager. Links to anything else
+    /// can't be followed yet, and none are while a job is in the
+    /// foreground, since its output may need the pager.
+    pub(crate) fn open_link(&mut self, url: &str) {
+        if self.session.foreground.is_some() {
+            return;
+        }
+        let Ok(text) = File::read_to_string_path(&env::resolve_path(url)) else {
+            environment::log(&format!("terminal: can't open the link {}", url));
+            return;
+        };
+        let mut pager = self.new_pager();
+        pager.append(value_layout::layout(&Value::String(text)));
+        self.session.output = Some(pager);
+        self.open_pager();
+    }
+}
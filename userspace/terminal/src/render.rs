@@ -1,9 +1,13 @@
 //! Rendering utilities for the terminal.
 //!
-//! This module provides text measurement, colour conversion, and word iteration
-//! for line wrapping.
+//! This module provides text measurement, colour conversion, word iteration
+//! for line wrapping, and drawing into the screens laid out in cells (the
+//! pager's and the alternate screen).
 
-use panda_abi::terminal::{Colour, NamedColour};
+use libpanda::graphics::{Canvas, Colour as Argb, Font, PixelBuffer, Point};
+use panda_abi::terminal::{Colour, NamedColour, Style};
+
+use crate::{FONT_SIZE, LINE_HEIGHT};
 
 // =============================================================================
 // Word iterator for line wrapping
@@ -134,3 +138,62 @@ pub fn colour_to_argb(colour: &Colour) -> u32 {
         }
     }
 }
+
+// =============================================================================
+// Drawing cells
+// =============================================================================
+
+/// Fill a rectangle of a screen with a solid ARGB colour.
+pub fn fill(screen: &mut PixelBuffer, x: u32, y: u32, width: u32, height: u32, colour: u32) {
+    Canvas::new(screen).fill_rect(
+        Point::new(x as f32, y as f32),
+        width as f32,
+        height as f32,
+        Argb(colour).with_alpha(255),
+    );
+}
+
+/// How a character is drawn in its cell.
+pub struct Pen {
+    pub colour: u32,
+    pub background: Option<u32>,
+    pub underline: bool,
+}
+
+impl Pen {
+    /// The pen for `style`, in `default` if it sets no colour.
+    pub fn new(style: &Style, default: u32) -> Self {
+        Self {
+            colour: style.foreground.as_ref().map_or(default, colour_to_argb),
+            background: style.background.as_ref().map(colour_to_argb),
+            underline: style.underline,
+        }
+    }
+}
+
+/// Draw a character in the cell `width` wide whose top left is `(x, y)`:
+/// its background, the character, and the line under it.
+pub fn draw_cell(
+    screen: &mut PixelBuffer,
+    font: &Font,
+    ch: char,
+    x: u32,
+    y: u32,
+    width: u32,
+    pen: &Pen,
+) {
+    if let Some(background) = pen.background {
+        fill(screen, x, y, width, LINE_HEIGHT, background);
+    }
+    let mut text = [0u8; 4];
+    Canvas::new(screen).fill_text(
+        font,
+        ch.encode_utf8(&mut text),
+        FONT_SIZE,
+        Point::new(x as f32, y as f32 + FONT_SIZE),
+        Argb(pen.colour).with_alpha(255),
+    );
+    if pen.underline {
+        fill(screen, x, y + FONT_SIZE as u32 + 2, width, 1, pen.colour);
+    }
+}
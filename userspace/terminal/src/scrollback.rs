@@ -0,0 +1,283 @@
+//! Looking back through the scrollback, selecting text in it, and copying
+//! and pasting through the clipboard.
+//!
+//! The screen shows the end of the scrollback buffer unless it has been
+//! scrolled back with PageUp/PageDown or the wheel; anything written brings
+//! it back to the end. Text is selected by dragging with the left button,
+//! or with Shift and the arrow keys, Home and End (starting from the end of
+//! the text). Ctrl-Shift-C copies the selection to the clipboard and
+//! Ctrl-Shift-V pastes the clipboard's text into the line being typed.
+
+use alloc::string::String;
+use core::ops::Range;
+use libpanda::clipboard;
+use libpanda::keyboard::{KeyEvent, Keysym, Modifiers};
+use libpanda::pointer::BTN_LEFT;
+
+use crate::{Terminal, LINE_HEIGHT};
+
+/// Rows the wheel scrolls per click.
+const WHEEL_ROWS: isize = 3;
+
+/// A place in the scrollback: a line of the buffer, and a boundary between
+/// two of its characters.
+pub type Position = (usize, usize);
+
+/// Selected text, from where the selection started to where it has been
+/// taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Selection {
+    pub anchor: Position,
+    pub head: Position,
+}
+
+impl Selection {
+    /// The characters selected, the earlier end first.
+    pub fn range(self) -> Range<Position> {
+        self.anchor.min(self.head)..self.anchor.max(self.head)
+    }
+}
+
+impl Terminal {
+    /// Bring the newest line back on screen, before anything is drawn at
+    /// the cursor.
+    pub(crate) fn follow_output(&mut self) {
+        if self.session.scroll > 0 {
+            self.session.scroll = 0;
+            self.render_visible_lines();
+        }
+    }
+
+    /// `count` lines have gone from the front of the buffer: move the
+    /// selection with the text, as much of it as is left.
+    pub(crate) fn lines_dropped(&mut self, count: usize) {
+        let shift = |(line, at): Position| {
+            if line < count {
+                (0, 0)
+            } else {
+                (line - count, at)
+            }
+        };
+        self.session.selection = self
+            .session
+            .selection
+            .map(|selection| Selection {
+                anchor: shift(selection.anchor),
+                head: shift(selection.head),
+            })
+            .filter(|selection| selection.anchor != selection.head);
+    }
+
+    /// Redraw the screen after the view or the selection changed.
+    fn refresh(&mut self) {
+        self.render_visible_lines();
+        if self.session.scroll == 0 {
+            self.show_caret();
+        }
+        self.flush();
+    }
+
+    /// Scroll back `rows` rows (forward if negative), no further than the
+    /// oldest line and no nearer than the newest.
+    pub fn scroll_by(&mut self, rows: isize) {
+        let total: usize = self
+            .session
+            .display_lines
+            .iter()
+            .map(|line| self.layout_line(line).1 .1 + 1)
+            .sum();
+        let max = total.saturating_sub(self.visible_line_count());
+        let scroll = self.session.scroll.saturating_add_signed(rows).min(max);
+        if scroll != self.session.scroll {
+            self.session.scroll = scroll;
+            self.refresh();
+        }
+    }
+
+    /// Handle the keys that move the view or the selection, or copy and
+    /// paste. Returns whether `event` was one of them.
+    pub fn handle_view_key(&mut self, event: &KeyEvent) -> bool {
+        let page = self.visible_line_count().saturating_sub(1).max(1) as isize;
+        let shift = event.modifiers.contains(Modifiers::SHIFT);
+        match event.keysym {
+            Keysym::PageUp => self.scroll_by(page),
+            Keysym::PageDown => self.scroll_by(-page),
+            Keysym::Char('c' | 'C') if shift && event.modifiers.ctrl() => self.copy_selection(),
+            Keysym::Char('v' | 'V') if shift && event.modifiers.ctrl() => self.paste(),
+            Keysym::Left
+            | Keysym::Right
+            | Keysym::Up
+            | Keysym::Down
+            | Keysym::Home
+            | Keysym::End
+                if shift =>
+            {
+                self.extend_selection(event.keysym)
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn line_len(&self, line: usize) -> usize {
+        self.session.display_lines.get(line).map_or(0, |line| {
+            line.segments
+                .iter()
+                .map(|segment| segment.text.chars().count())
+                .sum()
+        })
+    }
+
+    /// Move the selection's head a character, a line, or to the start or
+    /// end of its line.
+    fn extend_selection(&mut self, key: Keysym) {
+        let last = self.session.display_lines.len().saturating_sub(1);
+        let end = (last, self.line_len(last));
+        let Selection { anchor, head } = self.session.selection.unwrap_or(Selection {
+            anchor: end,
+            head: end,
+        });
+        let (line, at) = head;
+        let head = match key {
+            Keysym::Left if at > 0 => (line, at - 1),
+            Keysym::Left if line > 0 => (line - 1, self.line_len(line - 1)),
+            Keysym::Right if at < self.line_len(line) => (line, at + 1),
+            Keysym::Right if line < last => (line + 1, 0),
+            Keysym::Up if line > 0 => (line - 1, at.min(self.line_len(line - 1))),
+            Keysym::Down if line < last => (line + 1, at.min(self.line_len(line + 1))),
+            Keysym::Home => (line, 0),
+            Keysym::End => (line, self.line_len(line)),
+            _ => head,
+        };
+        self.session.selection = Some(Selection { anchor, head });
+        self.refresh();
+    }
+
+    /// The place in the text nearest the window point `(x, y)`: above the
+    /// text is its start, below it its end.
+    fn position_at(&self, x: i32, y: i32) -> Option<Position> {
+        let placed = self.visible_layouts(0);
+        let first = placed.first()?;
+        let line = placed
+            .iter()
+            .rev()
+            .find(|line| line.y <= y)
+            .unwrap_or(first);
+        let row = ((y - line.y).max(0) / LINE_HEIGHT as i32) as usize;
+
+        // The row's characters, up to the first whose middle is past x.
+        let before = line
+            .glyphs
+            .iter()
+            .take_while(|glyph| glyph.row < row)
+            .count();
+        let on_row = line.glyphs[before..]
+            .iter()
+            .take_while(|glyph| glyph.row == row)
+            .take_while(|glyph| x >= (glyph.x + self.measure_char(glyph.ch) / 2) as i32)
+            .count();
+        Some((line.line, before + on_row))
+    }
+
+    /// The left button starts a selection where it goes down and ends it
+    /// where it comes up; a click selects nothing, but follows a link.
+    pub fn pointer_button(&mut self, x: i32, y: i32, button: u16, pressed: bool) {
+        if button != BTN_LEFT {
+            return;
+        }
+        if pressed {
+            let Some(position) = self.position_at(x, y) else {
+                return;
+            };
+            self.session.selection = Some(Selection {
+                anchor: position,
+                head: position,
+            });
+            self.session.selecting = true;
+        } else {
+            self.session.selecting = false;
+            let click = self
+                .session
+                .selection
+                .is_some_and(|selection| selection.anchor == selection.head);
+            self.session.selection = self
+                .session
+                .selection
+                .filter(|selection| selection.anchor != selection.head);
+            if click && let Some(url) = self.link_at(x, y) {
+                self.refresh();
+                self.open_link(&url);
+                return;
+            }
+        }
+        self.refresh();
+    }
+
+    /// The URL of the link under the window point `(x, y)`, if any.
+    fn link_at(&self, x: i32, y: i32) -> Option<String> {
+        let placed = self.visible_layouts(0);
+        let line = placed.iter().rev().find(|line| line.y <= y)?;
+        let row = ((y - line.y) / LINE_HEIGHT as i32) as usize;
+        let glyph = line.glyphs.iter().find(|glyph| {
+            let x0 = glyph.x as i32;
+            glyph.row == row && (x0..x0 + self.measure_char(glyph.ch) as i32).contains(&x)
+        })?;
+        let segment = &self.session.display_lines[line.line].segments[glyph.segment];
+        segment.decoration.link.clone()
+    }
+
+    /// Dragging with the left button takes the selection with it.
+    pub fn pointer_motion(&mut self, x: i32, y: i32) {
+        if !self.session.selecting {
+            return;
+        }
+        let Some(head) = self.position_at(x, y) else {
+            return;
+        };
+        if let Some(selection) = &mut self.session.selection
+            && selection.head != head
+        {
+            selection.head = head;
+            self.refresh();
+        }
+    }
+
+    /// The wheel scrolls three rows a click, back when turned away.
+    pub fn pointer_wheel(&mut self, delta: i32) {
+        self.scroll_by(delta as isize * WHEEL_ROWS);
+    }
+
+    /// The selected text, its lines joined with newlines.
+    fn selected_text(&self) -> Option<String> {
+        let Range { start, end } = self.session.selection?.range();
+        let mut text = String::new();
+        for line in start.0..=end.0 {
+            if line > start.0 {
+                text.push('\n');
+            }
+            let from = if line == start.0 { start.1 } else { 0 };
+            let to = if line == end.0 { end.1 } else { usize::MAX };
+            let chars = self.session.display_lines[line]
+                .segments
+                .iter()
+                .flat_map(|segment| segment.text.chars());
+            text.extend(chars.take(to).skip(from));
+        }
+        Some(text)
+    }
+
+    /// Ctrl-Shift-C: put the selected text on the clipboard.
+    fn copy_selection(&mut self) {
+        if let Some(text) = self.selected_text() {
+            let _ = clipboard::set_text(&text);
+        }
+    }
+
+    /// Ctrl-Shift-V: type the clipboard's text into the line being typed.
+    /// Line breaks become spaces, so nothing runs until Enter.
+    fn paste(&mut self) {
+        if let Ok(text) = clipboard::get_text() {
+            self.insert_text(&text.replace('\n', " "));
+        }
+    }
+}
@@ -0,0 +1,404 @@
+//! Sessions: independent shells sharing the terminal's window, each with
+//! its own scrollback, line editor, jobs and environment.
+//!
+//! One session is shown at a time. The others are parked, but their
+//! programs carry on: a message or exit from a parked session's process is
+//! handled with that session swapped in and nothing drawn, so its
+//! scrollback is up to date when it is switched to. The process has a
+//! single set of variables and working directory, so each session keeps
+//! its own while parked and they are swapped along with it.
+//!
+//! Ctrl-Shift-T opens a session and Ctrl-Shift-W closes one, killing its
+//! jobs; Ctrl-PageDown or Ctrl-Tab goes to the next, Ctrl-PageUp or
+//! Ctrl-Shift-Tab to the previous, and Alt-1 to Alt-9 straight to one.
+//! Closing the last session closes the terminal.
+
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use libpanda::graphics::PixelBuffer;
+use libpanda::keyboard::{KeyEvent, Keysym, Modifiers};
+use libpanda::{channel, env, process, Handle};
+use panda_abi::terminal::Event as TerminalEvent;
+use panda_abi::{MAX_MESSAGE_SIZE, SIGNAL_KILL};
+use terminal::line_editor::LineEditor;
+use terminal::pager::Pager;
+use terminal::shell::AndOrList;
+use terminal::vt::Parser;
+
+use crate::ansi::AlternateScreen;
+use crate::input::PendingInput;
+use crate::jobs::Job;
+use crate::scrollback::Selection;
+use crate::{Decoration, Line, Terminal, COLOUR_DEFAULT_FG, TITLE};
+
+/// A session's variables and working directory, kept while another
+/// session's are the process's own.
+#[derive(Clone)]
+pub struct Environment {
+    vars: Vec<(String, String)>,
+    cwd: String,
+}
+
+impl Environment {
+    /// The process's variables and working directory as they are.
+    pub fn current() -> Self {
+        Self {
+            vars: env::vars(),
+            cwd: env::current_dir(),
+        }
+    }
+
+    /// Make these the process's variables and working directory.
+    pub fn restore(&self) {
+        for (name, _) in env::vars() {
+            if !self.vars.iter().any(|(kept, _)| *kept == name) {
+                env::remove(&name);
+            }
+        }
+        for (name, value) in &self.vars {
+            env::set(name, value);
+        }
+        // The directory may have gone while the session was parked.
+        let _ = env::set_current_dir(&self.cwd);
+    }
+}
+
+/// A shell and what it has written.
+pub struct Session {
+    /// The number it was opened with, which orders the sessions.
+    pub id: usize,
+    /// The shell's command line.
+    pub editor: LineEditor,
+    /// The line being typed as it is on screen, at the end of the last
+    /// display line.
+    pub(crate) input_shown: String,
+    /// A caret is drawn under the line on screen.
+    pub(crate) caret_shown: bool,
+    /// Jobs started and not yet finished, foreground and background.
+    pub jobs: Vec<Job>,
+    /// The job the terminal waits for, whose processes it talks to.
+    pub foreground: Option<usize>,
+    /// The lists of the line still to run, each started once the
+    /// foreground job before it is done.
+    pub script: VecDeque<AndOrList>,
+    /// Exit status of the last foreground pipeline.
+    pub last_status: i32,
+    /// Background jobs that have finished, reported with the next prompt.
+    pub notices: Vec<String>,
+    /// Pending input request from child
+    pub pending_input: Option<PendingInput>,
+    /// Current foreground colour
+    pub(crate) current_fg: u32,
+    /// Decoration of the text being written
+    pub(crate) decoration: Decoration,
+    /// Scrollback buffer of display lines.
+    /// New lines are pushed to the end; old lines beyond `MAX_SCROLLBACK_LINES` are dropped.
+    pub(crate) display_lines: Vec<Line>,
+    /// Rows of the scrollback between the bottom of the screen and the
+    /// newest line: 0 unless scrolled back.
+    pub(crate) scroll: usize,
+    /// Text selected with the mouse or the keyboard.
+    pub(crate) selection: Option<Selection>,
+    /// Whether the left button is down, dragging out the selection.
+    pub(crate) selecting: bool,
+    /// The foreground job's output, collected in case it outgrows the
+    /// screen; or what the pager shows while it is open.
+    pub(crate) output: Option<Pager>,
+    /// What the pager draws, shown instead of the framebuffer while it is
+    /// open.
+    pub(crate) pager_screen: Option<PixelBuffer>,
+    /// Parsers of the programs that have written escape sequences, by
+    /// process.
+    pub(crate) vt: Vec<(Handle, Parser)>,
+    /// Where on the last line escape-sequence output goes next, when
+    /// carriage returns or cursor movement have taken it off the end.
+    pub(crate) column: Option<usize>,
+    /// The screen a program has switched to, shown instead of the
+    /// framebuffer.
+    pub(crate) alternate: Option<AlternateScreen>,
+    /// The variables and working directory, while the session is parked.
+    pub environment: Environment,
+    /// The title a program gave the window.
+    pub title: Option<String>,
+    /// Set by `exit`: the session closes once the event is handled.
+    pub exit: Option<i32>,
+}
+
+impl Session {
+    /// A session starting out with the process's environment as it is.
+    pub fn new(id: usize) -> Self {
+        Self {
+            id,
+            editor: LineEditor::new(),
+            input_shown: String::new(),
+            caret_shown: false,
+            jobs: Vec::new(),
+            foreground: None,
+            script: VecDeque::new(),
+            last_status: 0,
+            notices: Vec::new(),
+            pending_input: None,
+            current_fg: COLOUR_DEFAULT_FG,
+            decoration: Decoration::default(),
+            display_lines: alloc::vec![Line {
+                segments: Vec::new()
+            }],
+            scroll: 0,
+            selection: None,
+            selecting: false,
+            output: None,
+            pager_screen: None,
+            vt: Vec::new(),
+            column: None,
+            alternate: None,
+            environment: Environment::current(),
+            title: None,
+            exit: None,
+        }
+    }
+
+    /// Whether `handle` is one of the session's processes.
+    fn owns(&self, handle: Handle) -> bool {
+        self.jobs.iter().any(|job| job.processes.contains(&handle))
+    }
+
+    /// Kill every process of every job, returning them to be waited for.
+    fn kill_jobs(&self) -> Vec<Handle> {
+        let processes: Vec<Handle> = self
+            .jobs
+            .iter()
+            .flat_map(|job| job.processes.iter().copied())
+            .collect();
+        for &handle in &processes {
+            process::signal(handle, SIGNAL_KILL);
+        }
+        processes
+    }
+}
+
+impl Terminal {
+    /// The shown session's place among all of them, counting from 0.
+    fn position(&self) -> usize {
+        self.parked
+            .iter()
+            .filter(|session| session.id < self.session.id)
+            .count()
+    }
+
+    /// Name the window after the shown session, with its place among the
+    /// sessions when there is more than one.
+    fn update_title(&mut self) {
+        let title = self.session.title.as_deref().unwrap_or(TITLE);
+        let title = if self.parked.is_empty() {
+            String::from(title)
+        } else {
+            format!(
+                "{} [{}/{}]",
+                title,
+                self.position() + 1,
+                self.parked.len() + 1
+            )
+        };
+        let _ = self.window.set_title(&title);
+    }
+
+    /// A program set the window's title. A parked session's shows once it
+    /// is switched to.
+    pub(crate) fn set_title(&mut self, title: &str) {
+        self.session.title = Some(String::from(title));
+        if !self.hidden {
+            self.update_title();
+        }
+    }
+
+    /// Draw the shown session from scratch: its scrollback, or its pager or
+    /// alternate screen over it.
+    fn show_session(&mut self) {
+        self.update_title();
+        self.render_visible_lines();
+        self.show_caret();
+        if self.pager_open() {
+            self.render_pager();
+        } else if self.alternate_shown() {
+            self.render_alternate();
+        } else {
+            self.flush();
+        }
+    }
+
+    /// Show the parked session at `index`, parking the shown one.
+    fn switch_to(&mut self, index: usize) {
+        self.session.environment = Environment::current();
+        self.session.selecting = false;
+        let session = self.parked.remove(index);
+        let old = core::mem::replace(&mut self.session, session);
+        let at = self.parked.partition_point(|session| session.id < old.id);
+        self.parked.insert(at, old);
+        self.session.environment.restore();
+        self.show_session();
+    }
+
+    /// Show the session `step` places after the shown one, going round.
+    fn switch_by(&mut self, step: isize) {
+        let count = self.parked.len() + 1;
+        if count == 1 {
+            return;
+        }
+        let to = (self.position() as isize + step).rem_euclid(count as isize) as usize;
+        self.switch_to_position(to);
+    }
+
+    /// Show the session at place `to`, if there is one.
+    fn switch_to_position(&mut self, to: usize) {
+        let here = self.position();
+        if to == here || to > self.parked.len() {
+            return;
+        }
+        // The parked sessions are the others, in order.
+        self.switch_to(if to < here { to } else { to - 1 });
+    }
+
+    /// Open a session, in the shown one's environment, and show it.
+    pub fn open_session(&mut self) {
+        let id = self
+            .parked
+            .iter()
+            .map(|session| session.id)
+            .chain([self.session.id])
+            .max()
+            .unwrap_or(0)
+            + 1;
+        self.parked.push(Session::new(id));
+        self.switch_to(self.parked.len() - 1);
+        self.load_history();
+        self.show_prompt();
+        self.flush();
+    }
+
+    /// Close the shown session, killing its jobs, and show the next one.
+    /// The last session closing closes the terminal, with `status`.
+    pub fn close_session(&mut self, status: i32) {
+        let killed = self.session.kill_jobs();
+        self.orphans.extend(killed);
+        if self.parked.is_empty() {
+            process::exit(status);
+        }
+        let next = self
+            .parked
+            .partition_point(|session| session.id < self.session.id)
+            .min(self.parked.len() - 1);
+        self.session = self.parked.remove(next);
+        self.session.environment.restore();
+        self.show_session();
+    }
+
+    /// Close the sessions `exit` was run in.
+    pub fn close_exited(&mut self) {
+        while let Some(index) = self
+            .parked
+            .iter()
+            .position(|session| session.exit.is_some())
+        {
+            let session = self.parked.remove(index);
+            let killed = session.kill_jobs();
+            self.orphans.extend(killed);
+            self.update_title();
+        }
+        if let Some(status) = self.session.exit {
+            self.close_session(status);
+        }
+    }
+
+    /// Work on the parked session at `index` with nothing drawn, leaving
+    /// the screen and the environment as they were.
+    fn in_session(&mut self, index: usize, f: impl FnOnce(&mut Self)) {
+        let cursor = (self.cursor_x, self.cursor_y);
+        let shown = Environment::current();
+        core::mem::swap(&mut self.session, &mut self.parked[index]);
+        self.session.environment.restore();
+        self.hidden = true;
+        // Put the cursor where the session's text ends.
+        self.render_visible_lines();
+
+        f(self);
+
+        self.hidden = false;
+        self.session.environment = Environment::current();
+        core::mem::swap(&mut self.session, &mut self.parked[index]);
+        shown.restore();
+        (self.cursor_x, self.cursor_y) = cursor;
+    }
+
+    /// Handle an event from the process `handle` in the session it belongs
+    /// to: the shown one's unless a parked one's job has it.
+    pub fn in_owner(&mut self, handle: Handle, f: impl FnOnce(&mut Self)) {
+        if !self.session.owns(handle)
+            && let Some(index) = self.parked.iter().position(|session| session.owns(handle))
+        {
+            self.in_session(index, f);
+        } else {
+            f(self);
+        }
+    }
+
+    /// Do the same to every parked session.
+    pub(crate) fn in_each_parked(&mut self, f: impl Fn(&mut Self)) {
+        for index in 0..self.parked.len() {
+            self.in_session(index, &f);
+        }
+    }
+
+    /// The window changed size: fit the session's pager and alternate
+    /// screen to it and tell its running program.
+    pub(crate) fn session_resized(&mut self) {
+        // Rows move when the lines reflow.
+        self.session.scroll = 0;
+        self.resize_pager();
+        self.resize_alternate();
+
+        if let Some(child) = self.foreground_process() {
+            let (cols, rows) = self.size_in_cells();
+            let _ = channel::send(child, &TerminalEvent::Resize { cols, rows }.to_bytes());
+        }
+    }
+
+    pub fn is_orphan(&self, handle: Handle) -> bool {
+        self.orphans.contains(&handle)
+    }
+
+    /// Throw away what a closed session's process sent.
+    pub fn drain_orphan(&mut self, handle: Handle) {
+        let mut buf = [0u8; MAX_MESSAGE_SIZE];
+        while channel::try_recv(handle, &mut buf).is_ok_and(|len| len > 0) {}
+    }
+
+    /// A closed session's process has exited.
+    pub fn reap_orphan(&mut self, handle: Handle) {
+        process::wait(handle);
+        self.orphans.retain(|&orphan| orphan != handle);
+    }
+
+    /// Handle the keys that open, close and switch between sessions.
+    /// Returns whether `event` was one of them.
+    pub fn handle_session_key(&mut self, event: &KeyEvent) -> bool {
+        let ctrl = event.modifiers.ctrl();
+        let shift = event.modifiers.contains(Modifiers::SHIFT);
+        match event.keysym {
+            Keysym::Char('t' | 'T') if ctrl && shift => self.open_session(),
+            Keysym::Char('w' | 'W') if ctrl && shift => {
+                let status = self.session.last_status;
+                self.close_session(status);
+            }
+            Keysym::PageDown if ctrl => self.switch_by(1),
+            Keysym::PageUp if ctrl => self.switch_by(-1),
+            Keysym::Tab if ctrl => self.switch_by(if shift { -1 } else { 1 }),
+            Keysym::Char(digit @ '1'..='9') if event.modifiers.alt() => {
+                self.switch_to_position(digit as usize - '1' as usize)
+            }
+            _ => return false,
+        }
+        true
+    }
+}
@@ -0,0 +1,323 @@
+//! Splitting a line into words and operators.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::iter::Peekable;
+use core::str::Chars;
+
+use super::{ParseError, Word, WordPart};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    Word(Word),
+    /// `|`
+    Pipe,
+    /// `&&`
+    And,
+    /// `||`
+    Or,
+    /// `;` or a newline.
+    Separator,
+    /// `&`
+    Background,
+    /// `<`
+    RedirectIn,
+    /// `>`
+    RedirectOut,
+    /// `>>`
+    RedirectAppend,
+}
+
+pub fn tokenize(line: &str) -> Result<Vec<Token>, ParseError> {
+    let mut chars = line.chars().peekable();
+    let mut tokens = Vec::new();
+
+    while let Some(&ch) = chars.peek() {
+        match ch {
+            ' ' | '\t' | '\r' => {
+                chars.next();
+            }
+            '#' => skip_comment(&mut chars),
+            '\n' | ';' => {
+                chars.next();
+                tokens.push(Token::Separator);
+            }
+            '|' => {
+                chars.next();
+                tokens.push(if chars.next_if_eq(&'|').is_some() {
+                    Token::Or
+                } else {
+                    Token::Pipe
+                });
+            }
+            '&' => {
+                chars.next();
+                tokens.push(if chars.next_if_eq(&'&').is_some() {
+                    Token::And
+                } else {
+                    Token::Background
+                });
+            }
+            '<' => {
+                chars.next();
+                tokens.push(Token::RedirectIn);
+            }
+            '>' => {
+                chars.next();
+                tokens.push(if chars.next_if_eq(&'>').is_some() {
+                    Token::RedirectAppend
+                } else {
+                    Token::RedirectOut
+                });
+            }
+            _ => tokens.extend(word(&mut chars)?.map(Token::Word)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Skip a comment, leaving the newline that ends it.
+fn skip_comment(chars: &mut Peekable<Chars>) {
+    while chars.next_if(|&ch| ch != '\n').is_some() {}
+}
+
+/// Whether `ch` ends an unquoted word.
+fn is_delimiter(ch: char) -> bool {
+    matches!(ch, ' ' | '\t' | '\r' | '\n' | ';' | '|' | '&' | '<' | '>')
+}
+
+/// Builds a word out of literal runs and variables.
+#[derive(Default)]
+struct WordBuilder {
+    parts: Vec<WordPart>,
+    literal: String,
+}
+
+impl WordBuilder {
+    fn push(&mut self, ch: char) {
+        self.literal.push(ch);
+    }
+
+    fn variable(&mut self, name: String) {
+        self.end_literal();
+        self.parts.push(WordPart::Variable(name));
+    }
+
+    fn end_literal(&mut self) {
+        if !self.literal.is_empty() {
+            self.parts
+                .push(WordPart::Literal(core::mem::take(&mut self.literal)));
+        }
+    }
+
+    /// The word, or `None` if there was nothing to it: `''` is an argument,
+    /// just an empty one, but a lone `\` and newline is only a gap between
+    /// words.
+    fn finish(mut self, quoted: bool) -> Option<Word> {
+        self.end_literal();
+        if self.parts.is_empty() {
+            if !quoted {
+                return None;
+            }
+            self.parts.push(WordPart::Literal(String::new()));
+        }
+        Some(Word { parts: self.parts })
+    }
+}
+
+fn word(chars: &mut Peekable<Chars>) -> Result<Option<Word>, ParseError> {
+    let mut word = WordBuilder::default();
+    let mut quoted = false;
+
+    while let Some(ch) = chars.next_if(|&ch| !is_delimiter(ch)) {
+        match ch {
+            '\'' => {
+                quoted = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(ch) => word.push(ch),
+                        None => return Err(ParseError::UnterminatedQuote),
+                    }
+                }
+            }
+            '"' => {
+                quoted = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next_if(|&ch| matches!(ch, '"' | '\\' | '$')) {
+                            Some(ch) => word.push(ch),
+                            None => word.push('\\'),
+                        },
+                        Some('$') => dollar(chars, &mut word)?,
+                        Some(ch) => word.push(ch),
+                        None => return Err(ParseError::UnterminatedQuote),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                // A backslash before a newline joins the lines.
+                Some('\n') => {}
+                Some(ch) => word.push(ch),
+                None => word.push('\\'),
+            },
+            '$' => dollar(chars, &mut word)?,
+            ch => word.push(ch),
+        }
+    }
+
+    Ok(word.finish(quoted))
+}
+
+fn is_name_start(ch: char) -> bool {
+    ch.is_ascii_alphabetic() || ch == '_'
+}
+
+fn is_name_char(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || ch == '_'
+}
+
+/// What follows a `$`: a variable, or a literal `$` if no name does.
+fn dollar(chars: &mut Peekable<Chars>, word: &mut WordBuilder) -> Result<(), ParseError> {
+    if chars.next_if_eq(&'?').is_some() {
+        word.variable(String::from("?"));
+    } else if chars.next_if_eq(&'{').is_some() {
+        let mut name = String::new();
+        loop {
+            match chars.next() {
+                Some('}') => break,
+                Some(ch) => name.push(ch),
+                None => return Err(ParseError::BadSubstitution),
+            }
+        }
+        let valid =
+            name == "?" || name.starts_with(is_name_start) && name.chars().all(is_name_char);
+        if !valid {
+            return Err(ParseError::BadSubstitution);
+        }
+        word.variable(name);
+    } else if chars.peek().is_some_and(|&ch| is_name_start(ch)) {
+        let mut name = String::new();
+        while let Some(ch) = chars.next_if(|&ch| is_name_char(ch)) {
+            name.push(ch);
+        }
+        word.variable(name);
+    } else {
+        word.push('$');
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    fn literal(text: &str) -> Token {
+        Token::Word(Word {
+            parts: vec![WordPart::Literal(text.to_string())],
+        })
+    }
+
+    #[test]
+    fn splits_words_and_operators() {
+        assert_eq!(
+            tokenize("a|b||c&&d;e<f>g>>h\ni&j").unwrap(),
+            [
+                literal("a"),
+                Token::Pipe,
+                literal("b"),
+                Token::Or,
+                literal("c"),
+                Token::And,
+                literal("d"),
+                Token::Separator,
+                literal("e"),
+                Token::RedirectIn,
+                literal("f"),
+                Token::RedirectOut,
+                literal("g"),
+                Token::RedirectAppend,
+                literal("h"),
+                Token::Separator,
+                literal("i"),
+                Token::Background,
+                literal("j"),
+            ]
+        );
+    }
+
+    #[test]
+    fn quotes_and_escapes_make_one_word() {
+        assert_eq!(
+            tokenize(r#"'a b'"c d"e\ f 'x"y' "p'q" \| '' "#).unwrap(),
+            [
+                literal("a bc de f"),
+                literal("x\"y"),
+                literal("p'q"),
+                literal("|"),
+                literal(""),
+            ]
+        );
+        assert_eq!(
+            tokenize(r#""a\"b\\c\$d\e""#).unwrap(),
+            [literal(r#"a"b\c$d\e"#)]
+        );
+    }
+
+    #[test]
+    fn variables_expand_outside_single_quotes() {
+        let tokens = tokenize(r#"$HOME/x "${A_1}y" '$B' $? a$ $1"#).unwrap();
+        let variable = |name: &str| WordPart::Variable(name.to_string());
+        let text = |text: &str| WordPart::Literal(text.to_string());
+        assert_eq!(
+            tokens,
+            [
+                Token::Word(Word {
+                    parts: vec![variable("HOME"), text("/x")]
+                }),
+                Token::Word(Word {
+                    parts: vec![variable("A_1"), text("y")]
+                }),
+                literal("$B"),
+                Token::Word(Word {
+                    parts: vec![variable("?")]
+                }),
+                literal("a$"),
+                literal("$1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn comments_run_to_the_end_of_the_line() {
+        assert_eq!(
+            tokenize("a # b c\nd").unwrap(),
+            [literal("a"), Token::Separator, literal("d")]
+        );
+        // Inside a word, `#` is just a character.
+        assert_eq!(tokenize("a#b").unwrap(), [literal("a#b")]);
+    }
+
+    #[test]
+    fn backslash_newline_joins_lines() {
+        assert_eq!(tokenize("a\\\nb").unwrap(), [literal("ab")]);
+        // Between words it is only space.
+        assert_eq!(
+            tokenize("a \\\n b \\\n").unwrap(),
+            [literal("a"), literal("b")]
+        );
+        assert_eq!(tokenize("\\\n").unwrap(), []);
+    }
+
+    #[test]
+    fn reports_malformed_input() {
+        assert_eq!(tokenize("'abc"), Err(ParseError::UnterminatedQuote));
+        assert_eq!(tokenize("\"abc"), Err(ParseError::UnterminatedQuote));
+        assert_eq!(tokenize("${A"), Err(ParseError::BadSubstitution));
+        assert_eq!(tokenize("${1x}"), Err(ParseError::BadSubstitution));
+    }
+}
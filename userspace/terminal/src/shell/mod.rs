@@ -0,0 +1,319 @@
+//! The shell language the terminal runs.
+//!
+//! A line is a [`Script`]: and-or lists separated by `;` or newlines (or
+//! ended by `&` to run in the background), each
+//! a chain of pipelines joined by `&&` and `||`, each pipeline a chain of
+//! commands joined by `|`. A command is words plus `<`, `>` and `>>`
+//! redirections. Words are quoted with `'...'` (literally) or `"..."` (with
+//! `$` expansion), and characters escaped with `\`. `$NAME`, `${NAME}` and
+//! `$?` (the last exit status) are kept in the [`Word`] and expanded when
+//! the command runs, so `$?` sees the status of the pipeline before it.
+//!
+//! There is no field splitting: a variable expands to exactly one argument,
+//! spaces and all, even when it is empty.
+
+mod lexer;
+mod parser;
+
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+pub use parser::parse;
+
+/// A parsed command line.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Script {
+    /// Run one after the other, whatever their status.
+    pub lists: Vec<AndOrList>,
+}
+
+/// Pipelines joined by `&&` and `||`, run left to right.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AndOrList {
+    pub first: Pipeline,
+    pub rest: Vec<(Connector, Pipeline)>,
+    /// Ended by `&`: the shell carries on without waiting for it.
+    pub background: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connector {
+    /// `&&`: run the next pipeline if the last one succeeded.
+    And,
+    /// `||`: run the next pipeline if the last one failed.
+    Or,
+}
+
+/// Commands joined by `|`, each reading what the one before it writes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pipeline {
+    pub commands: Vec<Command>,
+}
+
+/// A program and its arguments (`words[0]` is the program), with the
+/// files its input and output go to instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Command {
+    pub words: Vec<Word>,
+    pub redirects: Vec<Redirect>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirect {
+    pub kind: RedirectKind,
+    pub target: Word,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedirectKind {
+    /// `< file`: standard input reads the file.
+    Input,
+    /// `> file`: standard output replaces the file.
+    Output,
+    /// `>> file`: standard output is added to the end of the file.
+    Append,
+}
+
+/// A word as written, with its quotes removed and its variables not yet
+/// expanded.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Word {
+    pub parts: Vec<WordPart>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WordPart {
+    Literal(String),
+    /// `$NAME`, `${NAME}` or `$?` (named `?`).
+    Variable(String),
+}
+
+impl Word {
+    /// The word with each variable replaced by `lookup`'s value for it, or
+    /// by nothing if it has none.
+    pub fn expand(&self, lookup: impl Fn(&str) -> Option<String>) -> String {
+        let mut text = String::new();
+        for part in &self.parts {
+            match part {
+                WordPart::Literal(literal) => text.push_str(literal),
+                WordPart::Variable(name) => {
+                    if let Some(value) = lookup(name) {
+                        text.push_str(&value);
+                    }
+                }
+            }
+        }
+        text
+    }
+
+    /// The word's text, if it is one literal with no variables in it.
+    pub fn literal(&self) -> Option<&str> {
+        match &self.parts[..] {
+            [WordPart::Literal(text)] => Some(text),
+            _ => None,
+        }
+    }
+}
+
+/// Whether a [`Step`] runs, going by the exit status of the pipeline that
+/// ran (or was skipped over) before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    Always,
+    IfSuccess,
+    IfFailure,
+}
+
+impl Condition {
+    pub fn holds(self, status: i32) -> bool {
+        match self {
+            Condition::Always => true,
+            Condition::IfSuccess => status == 0,
+            Condition::IfFailure => status != 0,
+        }
+    }
+}
+
+/// One pipeline of a script, in the order they are considered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub condition: Condition,
+    pub pipeline: Pipeline,
+}
+
+impl AndOrList {
+    /// The list as a flat list of steps. A skipped step leaves the status
+    /// as it was, which gives `&&` and `||` their usual meaning: in
+    /// `a && b || c`, `c` runs if either `a` or `b` fails.
+    pub fn into_steps(self) -> Vec<Step> {
+        let mut steps = vec![Step {
+            condition: Condition::Always,
+            pipeline: self.first,
+        }];
+        for (connector, pipeline) in self.rest {
+            let condition = match connector {
+                Connector::And => Condition::IfSuccess,
+                Connector::Or => Condition::IfFailure,
+            };
+            steps.push(Step {
+                condition,
+                pipeline,
+            });
+        }
+        steps
+    }
+}
+
+// Written back out as shell text, such as for a job's description: parsing
+// it again gives the same list, though not always the same spelling.
+
+impl fmt::Display for AndOrList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.first)?;
+        for (connector, pipeline) in &self.rest {
+            let operator = match connector {
+                Connector::And => "&&",
+                Connector::Or => "||",
+            };
+            write!(f, " {} {}", operator, pipeline)?;
+        }
+        if self.background {
+            write!(f, " &")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, command) in self.commands.iter().enumerate() {
+            if i > 0 {
+                write!(f, " | ")?;
+            }
+            write!(f, "{}", command)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, word) in self.words.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", word)?;
+        }
+        for redirect in &self.redirects {
+            let operator = match redirect.kind {
+                RedirectKind::Input => "<",
+                RedirectKind::Output => ">",
+                RedirectKind::Append => ">>",
+            };
+            write!(f, " {} {}", operator, redirect.target)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Word {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for part in &self.parts {
+            match part {
+                WordPart::Literal(text) if needs_quotes(text) => {
+                    write!(f, "'{}'", text.replace('\'', "'\\''"))?
+                }
+                WordPart::Literal(text) => write!(f, "{}", text)?,
+                WordPart::Variable(name) => write!(f, "${{{}}}", name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether `text` has to be quoted to read back as the same literal.
+fn needs_quotes(text: &str) -> bool {
+    let plain = |ch: char| ch.is_alphanumeric() || "-_./=:,+%@^~".contains(ch);
+    text.is_empty() || !text.chars().all(plain)
+}
+
+/// Why a line is not a valid script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseError {
+    /// A `'` or `"` with no closing quote.
+    UnterminatedQuote,
+    /// `${` with no closing `}`, or a `${}` not naming a variable.
+    BadSubstitution,
+    /// An operator where a command should be: `| ls`, `ls ;;`, `ls &&`.
+    MissingCommand,
+    /// A redirection not followed by a file name.
+    MissingRedirectTarget,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::UnterminatedQuote => write!(f, "unterminated quote"),
+            ParseError::BadSubstitution => write!(f, "bad substitution"),
+            ParseError::MissingCommand => write!(f, "missing command"),
+            ParseError::MissingRedirectTarget => write!(f, "missing file name after redirection"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn expands_variables_in_place() {
+        let word = Word {
+            parts: vec![
+                WordPart::Literal("x=".to_string()),
+                WordPart::Variable("X".to_string()),
+                WordPart::Variable("UNSET".to_string()),
+                WordPart::Literal("!".to_string()),
+            ],
+        };
+        let lookup = |name: &str| (name == "X").then(|| "a b".to_string());
+        assert_eq!(word.expand(lookup), "x=a b!");
+    }
+
+    #[test]
+    fn and_or_skips_keep_the_last_status() {
+        let mut script = parse("false && a || b").unwrap();
+        let steps = script.lists.remove(0).into_steps();
+        let conditions: Vec<_> = steps.iter().map(|step| step.condition).collect();
+        assert_eq!(
+            conditions,
+            [
+                Condition::Always,
+                Condition::IfSuccess,
+                Condition::IfFailure
+            ]
+        );
+
+        // `false` fails, so `a` is skipped and `b` runs on its status.
+        let status = 1;
+        assert!(!steps[1].condition.holds(status));
+        assert!(steps[2].condition.holds(status));
+    }
+
+    #[test]
+    fn lists_display_as_text_that_parses_the_same() {
+        let line = r#"cat < "in file" | grep -v $X"y" > 'it'\''s' && x '' || y=$? &"#;
+        let list = parse(line).unwrap().lists.remove(0);
+        let text = list.to_string();
+        assert_eq!(
+            text,
+            "cat < 'in file' | grep -v ${X}y > 'it'\\''s' && x '' || y=${?} &"
+        );
+        assert_eq!(parse(&text).unwrap().lists, [list]);
+
+        let list = parse("echo \\\n a").unwrap().lists.remove(0);
+        assert_eq!(list.to_string(), "echo a");
+    }
+}
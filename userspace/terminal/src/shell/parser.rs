@@ -0,0 +1,236 @@
+//! Building a [`Script`] out of tokens.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::iter::Peekable;
+
+use super::lexer::{Token, tokenize};
+use super::{
+    AndOrList, Command, Connector, ParseError, Pipeline, Redirect, RedirectKind, Script, Word,
+    WordPart,
+};
+
+/// Parse a command line. A blank line is an empty script.
+pub fn parse(line: &str) -> Result<Script, ParseError> {
+    let mut tokens = tokenize(line)?.into_iter().peekable();
+    let mut script = Script::default();
+
+    while tokens.peek().is_some() {
+        let mut list = and_or_list(&mut tokens)?;
+        match tokens.next() {
+            Some(Token::Separator) | None => {}
+            Some(Token::Background) => list.background = true,
+            // Anything else would have been taken into the list.
+            Some(_) => unreachable!(),
+        }
+        script.lists.push(list);
+    }
+
+    Ok(script)
+}
+
+type Tokens = Peekable<vec::IntoIter<Token>>;
+
+fn and_or_list(tokens: &mut Tokens) -> Result<AndOrList, ParseError> {
+    let first = pipeline(tokens)?;
+    let mut rest = Vec::new();
+    loop {
+        let connector = match tokens.peek() {
+            Some(Token::And) => Connector::And,
+            Some(Token::Or) => Connector::Or,
+            _ => break,
+        };
+        tokens.next();
+        rest.push((connector, pipeline(tokens)?));
+    }
+    Ok(AndOrList {
+        first,
+        rest,
+        background: false,
+    })
+}
+
+fn pipeline(tokens: &mut Tokens) -> Result<Pipeline, ParseError> {
+    let mut commands = vec![command(tokens)?];
+    while tokens.next_if_eq(&Token::Pipe).is_some() {
+        commands.push(command(tokens)?);
+    }
+    Ok(Pipeline { commands })
+}
+
+fn command(tokens: &mut Tokens) -> Result<Command, ParseError> {
+    let mut command = Command {
+        words: Vec::new(),
+        redirects: Vec::new(),
+    };
+
+    loop {
+        let kind = match tokens.peek() {
+            Some(Token::Word(_)) => {
+                let Some(Token::Word(word)) = tokens.next() else {
+                    unreachable!();
+                };
+                command.words.push(word);
+                continue;
+            }
+            Some(Token::RedirectIn | Token::RedirectOut | Token::RedirectAppend)
+                if takes_expression(&command) =>
+            {
+                command.words.push(comparison(tokens));
+                continue;
+            }
+            Some(Token::RedirectIn) => RedirectKind::Input,
+            Some(Token::RedirectOut) => RedirectKind::Output,
+            Some(Token::RedirectAppend) => RedirectKind::Append,
+            _ => break,
+        };
+        tokens.next();
+        let Some(Token::Word(target)) = tokens.next() else {
+            return Err(ParseError::MissingRedirectTarget);
+        };
+        command.redirects.push(Redirect { kind, target });
+    }
+
+    if command.words.is_empty() {
+        return Err(ParseError::MissingCommand);
+    }
+    Ok(command)
+}
+
+/// Commands whose arguments are an expression, in which `<` and `>` compare
+/// rather than redirect.
+const EXPRESSION_COMMANDS: &[&str] = &["where"];
+
+fn takes_expression(command: &Command) -> bool {
+    command
+        .words
+        .first()
+        .and_then(Word::literal)
+        .is_some_and(|name| EXPRESSION_COMMANDS.contains(&name))
+}
+
+/// A `<` or `>` as a word of an expression. The lexer splits `>=` into `>`
+/// and a word starting `=`, so that goes back together.
+fn comparison(tokens: &mut Tokens) -> Word {
+    let operator = match tokens.next() {
+        Some(Token::RedirectIn) => "<",
+        Some(Token::RedirectOut) => ">",
+        _ => ">>",
+    };
+    let rest = tokens.next_if(|token| match token {
+        Token::Word(word) => {
+            matches!(word.parts.first(), Some(WordPart::Literal(text)) if text.starts_with('='))
+        }
+        _ => false,
+    });
+    let mut word = match rest {
+        Some(Token::Word(word)) => word,
+        _ => Word::default(),
+    };
+    match word.parts.first_mut() {
+        Some(WordPart::Literal(text)) => text.insert_str(0, operator),
+        _ => word.parts.push(WordPart::Literal(operator.into())),
+    }
+    word
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shell::{Word, WordPart};
+    use alloc::string::{String, ToString};
+
+    fn words(command: &Command) -> Vec<String> {
+        command
+            .words
+            .iter()
+            .map(|word| word.expand(|_| None))
+            .collect()
+    }
+
+    #[test]
+    fn parses_a_pipeline_with_redirections() {
+        let script = parse("cat < in.txt | grep 'a b' > out.txt >> log").unwrap();
+        assert_eq!(script.lists.len(), 1);
+        let list = &script.lists[0];
+        assert!(list.rest.is_empty());
+
+        let commands = &list.first.commands;
+        assert_eq!(commands.len(), 2);
+        assert_eq!(words(&commands[0]), ["cat"]);
+        assert_eq!(
+            commands[0].redirects,
+            [Redirect {
+                kind: RedirectKind::Input,
+                target: Word {
+                    parts: vec![WordPart::Literal("in.txt".to_string())]
+                },
+            }]
+        );
+        assert_eq!(words(&commands[1]), ["grep", "a b"]);
+        let kinds: Vec<_> = commands[1].redirects.iter().map(|r| r.kind).collect();
+        assert_eq!(kinds, [RedirectKind::Output, RedirectKind::Append]);
+    }
+
+    #[test]
+    fn redirections_can_come_before_the_command() {
+        let script = parse("> out echo hi").unwrap();
+        let command = &script.lists[0].first.commands[0];
+        assert_eq!(words(command), ["echo", "hi"]);
+        assert_eq!(command.redirects[0].kind, RedirectKind::Output);
+    }
+
+    #[test]
+    fn parses_sequences_and_and_or_lists() {
+        let script = parse("a && b || c; d\ne;").unwrap();
+        assert_eq!(script.lists.len(), 3);
+        let connectors: Vec<_> = script.lists[0].rest.iter().map(|(c, _)| *c).collect();
+        assert_eq!(connectors, [Connector::And, Connector::Or]);
+        assert_eq!(words(&script.lists[1].first.commands[0]), ["d"]);
+        assert_eq!(words(&script.lists[2].first.commands[0]), ["e"]);
+    }
+
+    #[test]
+    fn ampersand_runs_a_list_in_the_background() {
+        let script = parse("a && b & c; d &").unwrap();
+        let background: Vec<_> = script.lists.iter().map(|list| list.background).collect();
+        assert_eq!(background, [true, false, true]);
+        assert_eq!(script.lists[0].rest.len(), 1);
+        assert_eq!(parse("& a"), Err(ParseError::MissingCommand));
+        assert_eq!(parse("a & ; b"), Err(ParseError::MissingCommand));
+    }
+
+    #[test]
+    fn blank_lines_and_comments_are_empty_scripts() {
+        assert_eq!(parse("").unwrap(), Script::default());
+        assert_eq!(parse("   # nothing").unwrap(), Script::default());
+    }
+
+    #[test]
+    fn reports_operators_without_commands() {
+        for line in ["| a", "a |", "a &&", "|| a", "a ;; b", "; a", "a | | b"] {
+            assert_eq!(parse(line), Err(ParseError::MissingCommand), "{line}");
+        }
+        assert_eq!(parse("a >"), Err(ParseError::MissingRedirectTarget));
+        assert_eq!(parse("a > \\\n"), Err(ParseError::MissingRedirectTarget));
+        assert_eq!(parse("a < | b"), Err(ParseError::MissingRedirectTarget));
+        assert_eq!(parse("a 'b"), Err(ParseError::UnterminatedQuote));
+    }
+
+    #[test]
+    fn where_compares_instead_of_redirecting() {
+        let script = parse("ls | where size > 1024 and size >= 5 or n <= $N > out").unwrap();
+        let command = &script.lists[0].first.commands[1];
+        assert_eq!(
+            words(command),
+            [
+                "where", "size", ">", "1024", "and", "size", ">=", "5", "or", "n", "<=", "", ">",
+                "out"
+            ]
+        );
+        assert!(command.redirects.is_empty());
+        // Anything else still redirects.
+        let script = parse("echo size > 1024").unwrap();
+        assert_eq!(script.lists[0].first.commands[0].redirects.len(), 1);
+    }
+}
@@ -0,0 +1,401 @@
+//! Laying out a [`Value`] as lines of styled text, for the screen and the
+//! pager.
+//!
+//! Scalars take one line, and strings one line per line of text. Arrays
+//! and maps are indented trees: an item goes after `- ` and an entry after
+//! `key: ` when it is a scalar, and an item or entry that is itself an
+//! array, map or table goes on the lines below, indented. Tables are
+//! columns of cells laid out the same way, so a cell holding an array or
+//! another table makes its row several lines tall.
+//!
+//! Widths are counted in characters: the terminal's font is monospaced.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use panda_abi::terminal::Style;
+use panda_abi::value::{Table, Value};
+
+/// Spaces a nested item is indented by.
+const INDENT: usize = 2;
+
+/// Spaces between table columns.
+const COLUMN_GAP: usize = 2;
+
+/// A run of text drawn one way.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub style: Style,
+    /// The URL of the `Value::Link` the text belongs to.
+    pub link: Option<String>,
+}
+
+impl Span {
+    pub fn plain(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Self::default()
+        }
+    }
+}
+
+/// One line of text, with no line breaks in it.
+pub type Line = Vec<Span>;
+
+/// The width of a line in characters.
+pub fn width(line: &[Span]) -> usize {
+    line.iter().map(|span| span.text.chars().count()).sum()
+}
+
+/// The text of a line, without its styles.
+pub fn text(line: &[Span]) -> String {
+    line.iter().map(|span| span.text.as_str()).collect()
+}
+
+/// Lay out `value` as lines, to be written one after another with a line
+/// break between each. A table ends with a line break, so it ends with an
+/// empty line.
+pub fn layout(value: &Value) -> Vec<Line> {
+    let mut lines = block(value);
+    if matches!(value, Value::Table(_)) {
+        lines.push(Line::new());
+    }
+    lines
+}
+
+fn plain_line(text: &str) -> Line {
+    if text.is_empty() {
+        Line::new()
+    } else {
+        vec![Span::plain(text)]
+    }
+}
+
+/// How a scalar reads.
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Int(i) => i.to_string(),
+        Value::Float(f) => f.to_string(),
+        Value::Bytes(bytes) => format!("<{} bytes>", bytes.len()),
+        _ => String::new(),
+    }
+}
+
+/// Whether a value lays out as a tree or a table, and so goes below the
+/// `- ` or `key:` it belongs to rather than after it.
+fn is_nested(value: &Value) -> bool {
+    match value {
+        Value::Array(items) => !items.is_empty(),
+        Value::Map(map) => !map.is_empty(),
+        Value::Table(table) => table.cols > 0,
+        Value::Styled(_, inner) | Value::Link { inner, .. } => is_nested(inner),
+        _ => false,
+    }
+}
+
+fn indented(lines: Vec<Line>) -> impl Iterator<Item = Line> {
+    lines.into_iter().map(|mut line| {
+        if !line.is_empty() {
+            line.insert(0, Span::plain(" ".repeat(INDENT)));
+        }
+        line
+    })
+}
+
+fn block(value: &Value) -> Vec<Line> {
+    match value {
+        Value::String(s) => s.split('\n').map(plain_line).collect(),
+        Value::Array(items) if items.is_empty() => vec![plain_line("[]")],
+        Value::Array(items) => items
+            .iter()
+            .flat_map(|item| {
+                // The item's first line goes after the dash, even if the
+                // item is a tree.
+                let mut lines = block(item).into_iter();
+                let mut first = vec![Span::plain("- ")];
+                first.extend(lines.next().unwrap_or_default());
+                core::iter::once(first).chain(indented(lines.collect()))
+            })
+            .collect(),
+        Value::Map(map) if map.is_empty() => vec![plain_line("{}")],
+        Value::Map(map) => map
+            .iter()
+            .flat_map(|(key, value)| {
+                let lines = block(value);
+                if is_nested(value) {
+                    let key = vec![Span::plain(format!("{}:", key))];
+                    core::iter::once(key).chain(indented(lines))
+                } else {
+                    let mut lines = lines.into_iter();
+                    let mut first = vec![Span::plain(format!("{}: ", key))];
+                    first.extend(lines.next().unwrap_or_default());
+                    core::iter::once(first).chain(indented(lines.collect()))
+                }
+            })
+            .collect(),
+        Value::Table(table) => table_lines(table),
+        Value::Styled(style, inner) => {
+            let mut lines = block(inner);
+            // What the inner value sets for itself wins.
+            for span in lines.iter_mut().flatten() {
+                let own = &mut span.style;
+                own.foreground = own.foreground.or(style.foreground);
+                own.background = own.background.or(style.background);
+                own.bold |= style.bold;
+                own.italic |= style.italic;
+                own.underline |= style.underline;
+                own.strikethrough |= style.strikethrough;
+            }
+            lines
+        }
+        Value::Link { url, inner } => {
+            let mut lines = block(inner);
+            for span in lines.iter_mut().flatten() {
+                span.link.get_or_insert_with(|| url.clone());
+            }
+            lines
+        }
+        _ => vec![plain_line(&scalar(value))],
+    }
+}
+
+/// Whether a cell is a number, which lines up on the right.
+fn is_number(value: &Value) -> bool {
+    match value {
+        Value::Int(_) | Value::Float(_) => true,
+        Value::Styled(_, inner) | Value::Link { inner, .. } => is_number(inner),
+        _ => false,
+    }
+}
+
+fn table_lines(table: &Table) -> Vec<Line> {
+    let cols = table.cols as usize;
+    if cols == 0 {
+        return Vec::new();
+    }
+    let lay_out = |row: &[Value]| row.iter().map(block).collect::<Vec<_>>();
+    let header = table.headers.as_deref().map(lay_out);
+    let rows: Vec<Vec<Vec<Line>>> = table.row_iter().map(lay_out).collect();
+
+    let mut widths = vec![0; cols];
+    for cells in header.iter().chain(&rows) {
+        for (width, cell) in widths.iter_mut().zip(cells) {
+            let widest = cell.iter().map(|line| self::width(line)).max();
+            *width = (*width).max(widest.unwrap_or(0));
+        }
+    }
+
+    let mut lines = Vec::new();
+    if let (Some(headers), Some(header)) = (&table.headers, header) {
+        lines.extend(row_lines(headers, header, &widths));
+        let total = widths.iter().sum::<usize>() + (cols - 1) * COLUMN_GAP;
+        lines.push(vec![Span::plain("-".repeat(total))]);
+    }
+    for (values, cells) in table.row_iter().zip(rows) {
+        lines.extend(row_lines(values, cells, &widths));
+    }
+    lines
+}
+
+/// The lines of one table row: as many as its tallest cell has, each cell
+/// padded to its column's width. The last column isn't padded on the right.
+fn row_lines(values: &[Value], cells: Vec<Vec<Line>>, widths: &[usize]) -> Vec<Line> {
+    let height = cells.iter().map(Vec::len).max().unwrap_or(0).max(1);
+    let mut cells: Vec<_> = cells.into_iter().map(Vec::into_iter).collect();
+    let pad = |line: &mut Line, n: usize| {
+        if n > 0 {
+            line.push(Span::plain(" ".repeat(n)));
+        }
+    };
+
+    (0..height)
+        .map(|_| {
+            let mut line = Line::new();
+            let last = cells.len() - 1;
+            for (col, cell) in cells.iter_mut().enumerate() {
+                let text = cell.next().unwrap_or_default();
+                let padding = widths[col] - width(&text);
+                if col > 0 {
+                    pad(&mut line, COLUMN_GAP);
+                }
+                if is_number(&values[col]) {
+                    pad(&mut line, padding);
+                    line.extend(text);
+                } else {
+                    line.extend(text);
+                    if col < last {
+                        pad(&mut line, padding);
+                    }
+                }
+            }
+            // Nothing but padding after the last cell with text.
+            while line
+                .last()
+                .is_some_and(|span| span.style == Style::default() && span.text.trim().is_empty())
+            {
+                line.pop();
+            }
+            line
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::boxed::Box;
+    use alloc::collections::BTreeMap;
+    use panda_abi::terminal::{Colour, NamedColour};
+
+    fn lines(value: &Value) -> Vec<String> {
+        layout(value).iter().map(|line| text(line)).collect()
+    }
+
+    fn string(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+
+    fn map(entries: &[(&str, Value)]) -> Value {
+        let map: BTreeMap<String, Value> = entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect();
+        Value::Map(map)
+    }
+
+    #[test]
+    fn scalars_take_a_line() {
+        assert_eq!(lines(&Value::Null), ["null"]);
+        assert_eq!(lines(&Value::Int(-42)), ["-42"]);
+        assert_eq!(lines(&Value::Bytes(vec![0; 3])), ["<3 bytes>"]);
+        assert_eq!(lines(&string("a\nb\n")), ["a", "b", ""]);
+    }
+
+    #[test]
+    fn arrays_and_maps_are_trees() {
+        let value = map(&[
+            ("name", string("panda")),
+            (
+                "tags",
+                Value::Array(vec![string("a"), Value::Array(vec![])]),
+            ),
+            (
+                "owner",
+                map(&[("id", Value::Int(1)), ("groups", Value::Array(vec![]))]),
+            ),
+        ]);
+        assert_eq!(
+            lines(&value),
+            [
+                "name: panda",
+                "owner:",
+                "  groups: []",
+                "  id: 1",
+                "tags:",
+                "  - a",
+                "  - []",
+            ]
+        );
+
+        let value = Value::Array(vec![
+            map(&[("a", Value::Int(1)), ("b", Value::Int(2))]),
+            Value::Array(vec![Value::Bool(true), Value::Bool(false)]),
+        ]);
+        assert_eq!(lines(&value), ["- a: 1", "  b: 2", "- - true", "  - false"]);
+    }
+
+    #[test]
+    fn tables_line_up_in_columns() {
+        let table = Table::new(
+            2,
+            Some(vec![string("name"), string("size")]),
+            vec![
+                string("kernel"),
+                Value::Int(1024),
+                string("a"),
+                Value::Int(7),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            lines(&Value::Table(table)),
+            [
+                "name    size",
+                "------------",
+                "kernel  1024",
+                "a          7",
+                "",
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_cells_make_tall_rows() {
+        let inner = Table::new(1, Some(vec![string("x")]), vec![Value::Int(1)]).unwrap();
+        let table = Table::new(
+            3,
+            None,
+            vec![
+                string("one"),
+                Value::Array(vec![string("p"), string("q")]),
+                string("end"),
+                string("two"),
+                Value::Table(inner),
+                Value::Null,
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            lines(&Value::Table(table)),
+            [
+                "one  - p  end",
+                "     - q",
+                "two  x    null",
+                "     -",
+                "     1",
+                "",
+            ]
+        );
+    }
+
+    #[test]
+    fn styles_and_links_reach_every_span() {
+        let red = Style {
+            foreground: Some(Colour::Named(NamedColour::Red)),
+            ..Style::default()
+        };
+        let blue = Style {
+            foreground: Some(Colour::Named(NamedColour::Blue)),
+            underline: true,
+            ..Style::default()
+        };
+        let value = Value::Link {
+            url: "/mnt/notes".to_string(),
+            inner: Box::new(Value::Styled(
+                red.clone(),
+                Box::new(Value::Array(vec![
+                    string("plain"),
+                    Value::Styled(blue, Box::new(string("blue"))),
+                ])),
+            )),
+        };
+        let laid = layout(&value);
+        let spans: Vec<&Span> = laid.iter().flatten().collect();
+        assert!(
+            spans
+                .iter()
+                .all(|span| span.link.as_deref() == Some("/mnt/notes"))
+        );
+        assert!(spans.iter().all(|span| span.style.foreground.is_some()));
+        let blue_span = spans.iter().find(|span| span.text == "blue").unwrap();
+        assert_eq!(
+            blue_span.style.foreground,
+            Some(Colour::Named(NamedColour::Blue))
+        );
+        assert!(blue_span.style.underline);
+        assert_eq!(laid[0][1].style, red);
+    }
+}
@@ -0,0 +1,374 @@
+//! A screen of character cells that [`Action`]s are carried out on.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use panda_abi::terminal::{ClearRegion, Style};
+
+use super::Action;
+
+/// Columns between tab stops.
+const TAB_WIDTH: usize = 8;
+
+/// A character on the screen and how it is drawn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cell {
+    pub ch: char,
+    pub style: Style,
+}
+
+impl Cell {
+    /// An empty cell cleared in `style`, which keeps just its background.
+    fn blank(style: &Style) -> Self {
+        Self {
+            ch: ' ',
+            style: Style {
+                background: style.background,
+                ..Style::default()
+            },
+        }
+    }
+}
+
+/// A whole screen drawn by a program, with a cursor, a scroll region and a
+/// style to draw in.
+///
+/// The rows changed since the screen was last drawn are remembered, so
+/// that only they need drawing again; see [`take_damage`](Self::take_damage).
+pub struct Grid {
+    cells: Vec<Vec<Cell>>,
+    cols: usize,
+    row: usize,
+    col: usize,
+    /// A character went in the last column: the next goes at the start of
+    /// the next row.
+    wrap_next: bool,
+    style: Style,
+    /// The scroll region's first and last rows.
+    top: usize,
+    bottom: usize,
+    /// Where `ESC 7` left the cursor, and the style.
+    saved: (usize, usize, Style),
+    cursor_visible: bool,
+    damage: Vec<bool>,
+}
+
+impl Grid {
+    /// A blank screen, at least a cell in size.
+    pub fn new(rows: usize, cols: usize) -> Self {
+        let (rows, cols) = (rows.max(1), cols.max(1));
+        Self {
+            cells: vec![vec![Cell::blank(&Style::default()); cols]; rows],
+            cols,
+            row: 0,
+            col: 0,
+            wrap_next: false,
+            style: Style::default(),
+            top: 0,
+            bottom: rows - 1,
+            saved: (0, 0, Style::default()),
+            cursor_visible: true,
+            damage: vec![true; rows],
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn row(&self, row: usize) -> &[Cell] {
+        &self.cells[row]
+    }
+
+    /// The cursor's row and column, unless it is hidden.
+    pub fn cursor(&self) -> Option<(usize, usize)> {
+        self.cursor_visible.then_some((self.row, self.col))
+    }
+
+    /// The rows changed since last asked, to be drawn again.
+    pub fn take_damage(&mut self) -> Vec<usize> {
+        let rows = self.damage.iter().enumerate().filter(|(_, d)| **d);
+        let rows = rows.map(|(row, _)| row).collect();
+        self.damage.fill(false);
+        rows
+    }
+
+    /// Change the size, keeping the top left of the screen, or the rows up
+    /// to the cursor's if the cursor would fall off the bottom. The scroll
+    /// region becomes the whole screen.
+    pub fn resize(&mut self, rows: usize, cols: usize) {
+        let (rows, cols) = (rows.max(1), cols.max(1));
+        if self.row >= rows {
+            let gone = self.row + 1 - rows;
+            self.cells.drain(..gone);
+            self.row -= gone;
+        }
+        let blank = Cell::blank(&Style::default());
+        self.cells.resize(rows, vec![blank.clone(); cols]);
+        for line in &mut self.cells {
+            line.resize(cols, blank.clone());
+        }
+        self.cols = cols;
+        self.col = self.col.min(cols - 1);
+        self.wrap_next = false;
+        self.top = 0;
+        self.bottom = rows - 1;
+        self.damage = vec![true; rows];
+    }
+
+    pub fn apply(&mut self, action: &Action) {
+        let before = self.row;
+        if !matches!(action, Action::Print(_)) {
+            self.wrap_next = false;
+        }
+        match action {
+            Action::Print(ch) => self.print(*ch),
+            Action::LineFeed => self.line_feed(),
+            Action::CarriageReturn => self.col = 0,
+            Action::Backspace => self.col = self.col.saturating_sub(1),
+            Action::Tab => self.col = ((self.col / TAB_WIDTH + 1) * TAB_WIDTH).min(self.cols - 1),
+            Action::Style(style) => self.style = style.clone(),
+            Action::CursorTo { row, col } => {
+                if let Some(row) = row {
+                    self.row = (*row as usize).min(self.rows() - 1);
+                }
+                if let Some(col) = col {
+                    self.col = (*col as usize).min(self.cols - 1);
+                }
+            }
+            Action::CursorBy { rows, cols } => {
+                let last_row = self.rows() as isize - 1;
+                let last_col = self.cols as isize - 1;
+                self.row = (self.row as isize + *rows as isize).clamp(0, last_row) as usize;
+                self.col = (self.col as isize + *cols as isize).clamp(0, last_col) as usize;
+            }
+            Action::Clear(region) => match region {
+                ClearRegion::Screen => self.blank_rows(0..self.rows()),
+                ClearRegion::ToEndOfScreen => {
+                    self.blank_cells(self.col..self.cols);
+                    self.blank_rows(self.row + 1..self.rows());
+                }
+                ClearRegion::ToEndOfLine => self.blank_cells(self.col..self.cols),
+                ClearRegion::Line => self.blank_cells(0..self.cols),
+            },
+            Action::ClearToCursor { screen } => {
+                self.blank_cells(0..self.col + 1);
+                if *screen {
+                    self.blank_rows(0..self.row);
+                }
+            }
+            Action::EraseChars(n) => self.blank_cells(self.col..self.col + *n as usize),
+            Action::InsertChars(n) => {
+                let blank = Cell::blank(&self.style);
+                let (col, cols) = (self.col, self.cols);
+                let line = &mut self.cells[self.row];
+                let n = (*n as usize).min(cols - col);
+                line.splice(col..col, core::iter::repeat_n(blank, n));
+                line.truncate(cols);
+                self.damage[self.row] = true;
+            }
+            Action::DeleteChars(n) => {
+                let blank = Cell::blank(&self.style);
+                let col = self.col;
+                let line = &mut self.cells[self.row];
+                let n = (*n as usize).min(line.len() - col);
+                line.drain(col..col + n);
+                line.extend(core::iter::repeat_n(blank, n));
+                self.damage[self.row] = true;
+            }
+            Action::InsertLines(n) => {
+                if (self.top..=self.bottom).contains(&self.row) {
+                    self.scroll_down(self.row, *n as usize);
+                    self.col = 0;
+                }
+            }
+            Action::DeleteLines(n) => {
+                if (self.top..=self.bottom).contains(&self.row) {
+                    self.scroll_up(self.row, *n as usize);
+                    self.col = 0;
+                }
+            }
+            Action::ScrollUp(n) => self.scroll_up(self.top, *n as usize),
+            Action::ScrollDown(n) => self.scroll_down(self.top, *n as usize),
+            Action::ScrollRegion { top, bottom } => {
+                let last = self.rows() - 1;
+                let top = *top as usize;
+                let bottom = bottom.map_or(last, |bottom| (bottom as usize).min(last));
+                (self.top, self.bottom) = if top < bottom {
+                    (top, bottom)
+                } else {
+                    (0, last)
+                };
+                (self.row, self.col) = (0, 0);
+            }
+            Action::ReverseIndex => {
+                if self.row == self.top {
+                    self.scroll_down(self.top, 1);
+                } else {
+                    self.row = self.row.saturating_sub(1);
+                }
+            }
+            Action::SaveCursor => self.saved = (self.row, self.col, self.style.clone()),
+            Action::RestoreCursor => {
+                let (row, col, style) = self.saved.clone();
+                self.row = row.min(self.rows() - 1);
+                self.col = col.min(self.cols - 1);
+                self.style = style;
+            }
+            Action::CursorVisible(visible) => self.cursor_visible = *visible,
+            Action::Reset => *self = Self::new(self.rows(), self.cols),
+            // The terminal switches screens and sets titles.
+            Action::AlternateScreen(_) | Action::SetTitle(_) => {}
+        }
+        // The cursor is drawn on its row.
+        self.damage[before] = true;
+        self.damage[self.row] = true;
+    }
+
+    fn print(&mut self, ch: char) {
+        if self.wrap_next {
+            self.wrap_next = false;
+            self.col = 0;
+            self.line_feed();
+        }
+        self.cells[self.row][self.col] = Cell {
+            ch,
+            style: self.style.clone(),
+        };
+        if self.col + 1 < self.cols {
+            self.col += 1;
+        } else {
+            self.wrap_next = true;
+        }
+    }
+
+    fn line_feed(&mut self) {
+        if self.row == self.bottom {
+            self.scroll_up(self.top, 1);
+        } else if self.row + 1 < self.rows() {
+            self.row += 1;
+        }
+    }
+
+    /// Move the rows from `from` to the bottom of the scroll region up `n`,
+    /// blank rows coming in below.
+    fn scroll_up(&mut self, from: usize, n: usize) {
+        let n = n.min(self.bottom + 1 - from);
+        let blank = vec![Cell::blank(&self.style); self.cols];
+        self.cells.drain(from..from + n);
+        let at = self.bottom + 1 - n;
+        self.cells.splice(at..at, core::iter::repeat_n(blank, n));
+        self.damage[from..=self.bottom].fill(true);
+    }
+
+    /// Move the rows from `from` to the bottom of the scroll region down
+    /// `n`, blank rows coming in above.
+    fn scroll_down(&mut self, from: usize, n: usize) {
+        let n = n.min(self.bottom + 1 - from);
+        let blank = vec![Cell::blank(&self.style); self.cols];
+        self.cells.drain(self.bottom + 1 - n..=self.bottom);
+        self.cells
+            .splice(from..from, core::iter::repeat_n(blank, n));
+        self.damage[from..=self.bottom].fill(true);
+    }
+
+    fn blank_rows(&mut self, rows: core::ops::Range<usize>) {
+        for row in rows {
+            self.cells[row].fill(Cell::blank(&self.style));
+            self.damage[row] = true;
+        }
+    }
+
+    /// Blank cells of the cursor's row.
+    fn blank_cells(&mut self, cols: core::ops::Range<usize>) {
+        let end = cols.end.min(self.cols);
+        let start = cols.start.min(end);
+        self.cells[self.row][start..end].fill(Cell::blank(&self.style));
+        self.damage[self.row] = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vt::Parser;
+    use alloc::string::String;
+
+    fn run(grid: &mut Grid, text: &str) {
+        for action in Parser::new().feed(text.as_bytes()) {
+            grid.apply(&action);
+        }
+    }
+
+    fn screen(grid: &Grid) -> Vec<String> {
+        (0..grid.rows())
+            .map(|row| {
+                let text: String = grid.row(row).iter().map(|cell| cell.ch).collect();
+                String::from(text.trim_end())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn printing_wraps_and_scrolls() {
+        let mut grid = Grid::new(3, 4);
+        run(&mut grid, "abcdef\r\nxy\r\nz");
+        assert_eq!(screen(&grid), ["ef", "xy", "z"]);
+        assert_eq!(grid.cursor(), Some((2, 1)));
+
+        // A character in the last column leaves the cursor there.
+        let mut grid = Grid::new(2, 4);
+        run(&mut grid, "abcd");
+        assert_eq!(grid.cursor(), Some((0, 3)));
+        run(&mut grid, "\re");
+        assert_eq!(screen(&grid), ["ebcd", ""]);
+    }
+
+    #[test]
+    fn moving_and_erasing() {
+        let mut grid = Grid::new(3, 6);
+        run(&mut grid, "aaaaaa\r\nbbbbbb\r\ncccccc");
+        run(&mut grid, "\x1b[2;3H\x1b[K\x1b[1;2H\x1b[1K\x1b[3;5H\x1b[2X");
+        assert_eq!(screen(&grid), ["  aaaa", "bb", "cccc"]);
+        run(&mut grid, "\x1b[1;1H\x1b[2P\x1b[@!");
+        assert_eq!(screen(&grid), ["!aaaa", "bb", "cccc"]);
+        run(&mut grid, "\x1b[2;2H\x1b[J");
+        assert_eq!(screen(&grid), ["!aaaa", "b", ""]);
+        run(&mut grid, "\x1b[2J");
+        assert_eq!(screen(&grid), ["", "", ""]);
+    }
+
+    #[test]
+    fn scroll_regions_and_lines() {
+        let mut grid = Grid::new(4, 3);
+        run(&mut grid, "1\r\n2\r\n3\r\n4");
+        // Rows 2 and 3 scroll; the first and last stay put.
+        run(&mut grid, "\x1b[2;3r\x1b[3;1H\nx");
+        assert_eq!(screen(&grid), ["1", "3", "x", "4"]);
+        run(&mut grid, "\x1b[2;1H\x1bM");
+        assert_eq!(screen(&grid), ["1", "", "3", "4"]);
+        run(&mut grid, "\x1b[r\x1b[2;1H\x1b[M");
+        assert_eq!(screen(&grid), ["1", "3", "4", ""]);
+        run(&mut grid, "\x1b[L");
+        assert_eq!(screen(&grid), ["1", "", "3", "4"]);
+        run(&mut grid, "\x1b[2S");
+        assert_eq!(screen(&grid), ["3", "4", "", ""]);
+    }
+
+    #[test]
+    fn damage_and_resize() {
+        let mut grid = Grid::new(3, 3);
+        assert_eq!(grid.take_damage(), [0, 1, 2]);
+        run(&mut grid, "\x1b[3;1Hab");
+        assert_eq!(grid.take_damage(), [0, 2]);
+        assert!(grid.take_damage().is_empty());
+
+        // Shrinking keeps the cursor's row.
+        grid.resize(2, 2);
+        assert_eq!(screen(&grid), ["", "ab"]);
+        assert_eq!(grid.cursor(), Some((1, 1)));
+    }
+}
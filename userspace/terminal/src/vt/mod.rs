@@ -0,0 +1,96 @@
+//! Escape sequences in the text of programs written for VT100-style
+//! terminals.
+//!
+//! Programs that speak the terminal protocol send styled values. Programs
+//! ported from elsewhere write plain text with ANSI escape sequences in it
+//! instead. A [`Parser`] turns such text into [`Action`]s in the protocol's
+//! own terms: SGR becomes a [`Style`], and ED and EL become a
+//! [`ClearRegion`] where there is one. A [`Grid`] carries the actions out
+//! on a screen of character cells, for programs that switch to the
+//! alternate screen and draw all over it.
+//!
+//! Understood are the C0 controls `\n`, `\r`, `\b` and `\t`; `ESC 7`,
+//! `ESC 8`, `ESC D`, `ESC E`, `ESC M` and `ESC c`; the CSI sequences for
+//! moving the cursor (CUU, CUD, CUF, CUB, CNL, CPL, CHA, HPA, VPA, CUP,
+//! HVP), erasing (ED, EL, ECH), editing (ICH, DCH, IL, DL), scrolling (SU,
+//! SD, DECSTBM), saving the cursor (`CSI s`, `CSI u`), SGR, and the private
+//! modes 25 (the cursor) and 47, 1047 and 1049 (the alternate screen); and
+//! OSC 0 and 2, which set the title. Everything else is read and dropped.
+
+mod grid;
+mod parser;
+
+use alloc::string::String;
+use panda_abi::terminal::{ClearRegion, Style};
+
+pub use grid::{Cell, Grid};
+pub use parser::Parser;
+
+/// What a piece of text asks the terminal to do. Rows and columns count
+/// from 0, from the top left of the screen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Draw a character at the cursor and move right.
+    Print(char),
+    /// `\n` (and VT, FF, `ESC D`): down a row, scrolling the scroll region
+    /// at its bottom.
+    LineFeed,
+    /// `\r`: to the first column.
+    CarriageReturn,
+    /// `\b`: left a column.
+    Backspace,
+    /// `\t`: to the next tab stop. There is one every eight columns.
+    Tab,
+    /// SGR: the style characters are drawn in from now on, reverse video
+    /// already applied.
+    Style(Style),
+    /// To a row, a column or both.
+    CursorTo {
+        row: Option<u16>,
+        col: Option<u16>,
+    },
+    /// Down `rows` and right `cols`; up and left when negative.
+    CursorBy {
+        rows: i32,
+        cols: i32,
+    },
+    /// ED 0, 2 and 3 and EL 0 and 2, which clear from the cursor or all of
+    /// a line or the screen.
+    Clear(ClearRegion),
+    /// ED 1 and EL 1: clear from the start of the screen or of the line up
+    /// to the cursor, the cursor's cell included.
+    ClearToCursor {
+        screen: bool,
+    },
+    /// ECH: blank this many characters from the cursor on.
+    EraseChars(u16),
+    /// ICH: push the rest of the line right, blanks in front of it.
+    InsertChars(u16),
+    /// DCH: pull the rest of the line left over this many characters.
+    DeleteChars(u16),
+    /// IL: push the rows from the cursor's down the scroll region.
+    InsertLines(u16),
+    /// DL: pull the rows below the cursor's up the scroll region.
+    DeleteLines(u16),
+    /// SU: scroll the scroll region up, blank rows coming in at the bottom.
+    ScrollUp(u16),
+    /// SD: scroll the scroll region down.
+    ScrollDown(u16),
+    /// DECSTBM: the rows from `top` to `bottom` (the last row when `None`)
+    /// are all that scroll.
+    ScrollRegion {
+        top: u16,
+        bottom: Option<u16>,
+    },
+    /// `ESC M`: up a row, scrolling the scroll region down at its top.
+    ReverseIndex,
+    SaveCursor,
+    RestoreCursor,
+    /// Switch to the alternate screen, or back to the normal one.
+    AlternateScreen(bool),
+    CursorVisible(bool),
+    /// OSC 0 or 2.
+    SetTitle(String),
+    /// `ESC c`: back to how the screen started.
+    Reset,
+}
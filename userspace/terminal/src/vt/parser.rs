@@ -0,0 +1,540 @@
+//! Reading escape sequences out of text.
+
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use panda_abi::terminal::{ClearRegion, Colour, NamedColour, Style};
+
+use super::Action;
+
+/// Parameters of a CSI sequence kept; any more are dropped.
+const MAX_PARAMS: usize = 16;
+
+/// Longest OSC string kept; the rest of a longer one is dropped.
+const OSC_LIMIT: usize = 1024;
+
+/// The colours of SGR 30-37 and 90-97, in order.
+const NAMED: [NamedColour; 16] = [
+    NamedColour::Black,
+    NamedColour::Red,
+    NamedColour::Green,
+    NamedColour::Yellow,
+    NamedColour::Blue,
+    NamedColour::Magenta,
+    NamedColour::Cyan,
+    NamedColour::White,
+    NamedColour::BrightBlack,
+    NamedColour::BrightRed,
+    NamedColour::BrightGreen,
+    NamedColour::BrightYellow,
+    NamedColour::BrightBlue,
+    NamedColour::BrightMagenta,
+    NamedColour::BrightCyan,
+    NamedColour::BrightWhite,
+];
+
+/// A CSI sequence read up to its final character.
+struct Csi {
+    /// `?` and the like, before the parameters.
+    private: Option<char>,
+    /// Missing parameters are 0.
+    params: Vec<u16>,
+    /// It had intermediate characters, which nothing understood does.
+    intermediate: bool,
+}
+
+enum State {
+    Ground,
+    /// After ESC.
+    Escape,
+    /// After `ESC (` and the like, which choose a character set: the next
+    /// character is dropped.
+    Charset,
+    Csi(Csi),
+    Osc(String),
+    /// After ESC in an OSC string, which ends it when `\` follows.
+    OscEscape(String),
+}
+
+/// Turns text with escape sequences in it into [`Action`]s.
+///
+/// Text may arrive in pieces that split a sequence, or a character's UTF-8
+/// encoding, anywhere: what is left over is kept for the next piece.
+pub struct Parser {
+    state: State,
+    /// The start of a character whose last bytes haven't arrived yet.
+    partial: Vec<u8>,
+    /// The style set by SGR, before reverse video.
+    style: Style,
+    /// SGR 7.
+    reverse: bool,
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Parser {
+    pub fn new() -> Self {
+        Self {
+            state: State::Ground,
+            partial: Vec::new(),
+            style: Style::default(),
+            reverse: false,
+        }
+    }
+
+    /// Whether the parser is between sequences, so that text without
+    /// escapes in it would only be printed.
+    pub fn is_idle(&self) -> bool {
+        matches!(self.state, State::Ground) && self.partial.is_empty()
+    }
+
+    /// The style characters are drawn in, reverse video applied. The
+    /// default colours reverse to white on black.
+    pub fn style(&self) -> Style {
+        let mut style = self.style.clone();
+        if self.reverse {
+            let foreground = style
+                .background
+                .unwrap_or(Colour::Named(NamedColour::Black));
+            let background = style
+                .foreground
+                .unwrap_or(Colour::Named(NamedColour::White));
+            style.foreground = Some(foreground);
+            style.background = Some(background);
+        }
+        style
+    }
+
+    /// Read the next piece of text. Bytes that aren't UTF-8 read as U+FFFD.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<Action> {
+        let mut input = core::mem::take(&mut self.partial);
+        input.extend_from_slice(bytes);
+        let mut actions = Vec::new();
+        let mut rest = &input[..];
+        loop {
+            match core::str::from_utf8(rest) {
+                Ok(text) => {
+                    for ch in text.chars() {
+                        self.advance(ch, &mut actions);
+                    }
+                    break;
+                }
+                Err(error) => {
+                    let (valid, after) = rest.split_at(error.valid_up_to());
+                    let valid = core::str::from_utf8(valid).unwrap_or_default();
+                    for ch in valid.chars() {
+                        self.advance(ch, &mut actions);
+                    }
+                    match error.error_len() {
+                        Some(len) => {
+                            self.advance(char::REPLACEMENT_CHARACTER, &mut actions);
+                            rest = &after[len..];
+                        }
+                        None => {
+                            self.partial = after.to_vec();
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        actions
+    }
+
+    fn advance(&mut self, ch: char, actions: &mut Vec<Action>) {
+        match core::mem::replace(&mut self.state, State::Ground) {
+            State::Ground => match ch {
+                '\x1b' => self.state = State::Escape,
+                ch if ch.is_control() => control(ch, actions),
+                ch => actions.push(Action::Print(ch)),
+            },
+            State::Escape => match ch {
+                '[' => {
+                    self.state = State::Csi(Csi {
+                        private: None,
+                        params: vec![0],
+                        intermediate: false,
+                    })
+                }
+                ']' => self.state = State::Osc(String::new()),
+                '(' | ')' | '*' | '+' => self.state = State::Charset,
+                '7' => actions.push(Action::SaveCursor),
+                '8' => actions.push(Action::RestoreCursor),
+                'D' => actions.push(Action::LineFeed),
+                'E' => actions.extend([Action::CarriageReturn, Action::LineFeed]),
+                'M' => actions.push(Action::ReverseIndex),
+                'c' => {
+                    self.style = Style::default();
+                    self.reverse = false;
+                    actions.push(Action::Reset);
+                }
+                '\x1b' => self.state = State::Escape,
+                // Controls are obeyed in the middle of a sequence.
+                ch if ch.is_control() => {
+                    control(ch, actions);
+                    self.state = State::Escape;
+                }
+                _ => {}
+            },
+            State::Charset => {}
+            State::Csi(mut csi) => {
+                match ch {
+                    '0'..='9' => {
+                        if let Some(last) = csi.params.last_mut() {
+                            let digit = ch as u16 - '0' as u16;
+                            *last = last.saturating_mul(10).saturating_add(digit);
+                        }
+                    }
+                    ';' | ':' => {
+                        if csi.params.len() < MAX_PARAMS {
+                            csi.params.push(0);
+                        }
+                    }
+                    '<'..='?' => csi.private = Some(ch),
+                    ' '..='/' => csi.intermediate = true,
+                    '@'..='~' => return self.csi(&csi, ch, actions),
+                    '\x1b' => {
+                        self.state = State::Escape;
+                        return;
+                    }
+                    ch if ch.is_control() => control(ch, actions),
+                    // Not part of any sequence: give up on this one.
+                    _ => return,
+                }
+                self.state = State::Csi(csi);
+            }
+            State::Osc(mut text) => match ch {
+                '\x07' => osc(&text, actions),
+                '\x1b' => self.state = State::OscEscape(text),
+                ch => {
+                    if text.len() < OSC_LIMIT {
+                        text.push(ch);
+                    }
+                    self.state = State::Osc(text);
+                }
+            },
+            State::OscEscape(text) => {
+                osc(&text, actions);
+                // Anything but `\` starts another sequence.
+                if ch != '\\' {
+                    self.state = State::Escape;
+                    self.advance(ch, actions);
+                }
+            }
+        }
+    }
+
+    fn csi(&mut self, csi: &Csi, final_char: char, actions: &mut Vec<Action>) {
+        let param = |at: usize| csi.params.get(at).copied().unwrap_or(0);
+        // Counts and positions of 0 mean 1.
+        let count = |at: usize| param(at).max(1);
+
+        if csi.intermediate {
+            return;
+        }
+        if csi.private == Some('?') {
+            let on = match final_char {
+                'h' => true,
+                'l' => false,
+                _ => return,
+            };
+            for &mode in &csi.params {
+                match mode {
+                    47 | 1047 | 1049 => actions.push(Action::AlternateScreen(on)),
+                    25 => actions.push(Action::CursorVisible(on)),
+                    _ => {}
+                }
+            }
+            return;
+        }
+        if csi.private.is_some() {
+            return;
+        }
+
+        let by = |rows: i32, cols: i32| Action::CursorBy { rows, cols };
+        let action = match final_char {
+            'A' => by(-i32::from(count(0)), 0),
+            'B' | 'e' => by(i32::from(count(0)), 0),
+            'C' | 'a' => by(0, i32::from(count(0))),
+            'D' => by(0, -i32::from(count(0))),
+            'E' => {
+                actions.push(Action::CarriageReturn);
+                by(i32::from(count(0)), 0)
+            }
+            'F' => {
+                actions.push(Action::CarriageReturn);
+                by(-i32::from(count(0)), 0)
+            }
+            'G' | '`' => Action::CursorTo {
+                row: None,
+                col: Some(count(0) - 1),
+            },
+            'd' => Action::CursorTo {
+                row: Some(count(0) - 1),
+                col: None,
+            },
+            'H' | 'f' => Action::CursorTo {
+                row: Some(count(0) - 1),
+                col: Some(count(1) - 1),
+            },
+            'J' => match param(0) {
+                0 => Action::Clear(ClearRegion::ToEndOfScreen),
+                1 => Action::ClearToCursor { screen: true },
+                2 | 3 => Action::Clear(ClearRegion::Screen),
+                _ => return,
+            },
+            'K' => match param(0) {
+                0 => Action::Clear(ClearRegion::ToEndOfLine),
+                1 => Action::ClearToCursor { screen: false },
+                2 => Action::Clear(ClearRegion::Line),
+                _ => return,
+            },
+            '@' => Action::InsertChars(count(0)),
+            'P' => Action::DeleteChars(count(0)),
+            'X' => Action::EraseChars(count(0)),
+            'L' => Action::InsertLines(count(0)),
+            'M' => Action::DeleteLines(count(0)),
+            'S' => Action::ScrollUp(count(0)),
+            'T' => Action::ScrollDown(count(0)),
+            'r' => Action::ScrollRegion {
+                top: count(0) - 1,
+                bottom: (param(1) > 0).then(|| param(1) - 1),
+            },
+            's' => Action::SaveCursor,
+            'u' => Action::RestoreCursor,
+            'm' => {
+                self.sgr(&csi.params);
+                Action::Style(self.style())
+            }
+            _ => return,
+        };
+        actions.push(action);
+    }
+
+    /// Select graphic rendition: change the style.
+    fn sgr(&mut self, params: &[u16]) {
+        let mut params = params.iter().copied();
+        while let Some(param) = params.next() {
+            let style = &mut self.style;
+            match param {
+                0 => {
+                    *style = Style::default();
+                    self.reverse = false;
+                }
+                1 => style.bold = true,
+                3 => style.italic = true,
+                4 => style.underline = true,
+                7 => self.reverse = true,
+                9 => style.strikethrough = true,
+                22 => style.bold = false,
+                23 => style.italic = false,
+                24 => style.underline = false,
+                27 => self.reverse = false,
+                29 => style.strikethrough = false,
+                30..=37 => style.foreground = Some(Colour::Named(NAMED[param as usize - 30])),
+                38 => style.foreground = extended_colour(&mut params).or(style.foreground),
+                39 => style.foreground = None,
+                40..=47 => style.background = Some(Colour::Named(NAMED[param as usize - 40])),
+                48 => style.background = extended_colour(&mut params).or(style.background),
+                49 => style.background = None,
+                90..=97 => style.foreground = Some(Colour::Named(NAMED[param as usize - 82])),
+                100..=107 => style.background = Some(Colour::Named(NAMED[param as usize - 92])),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// The colour after SGR 38 or 48: `5;n` from the 256-colour palette, or
+/// `2;r;g;b`.
+fn extended_colour(params: &mut impl Iterator<Item = u16>) -> Option<Colour> {
+    let mut byte = || params.next().map(|value| value.min(255) as u8);
+    match byte()? {
+        5 => Some(Colour::Palette(byte()?)),
+        2 => Some(Colour::Rgb {
+            r: byte()?,
+            g: byte()?,
+            b: byte()?,
+        }),
+        _ => None,
+    }
+}
+
+fn control(ch: char, actions: &mut Vec<Action>) {
+    let action = match ch {
+        '\n' | '\x0b' | '\x0c' => Action::LineFeed,
+        '\r' => Action::CarriageReturn,
+        '\x08' => Action::Backspace,
+        '\t' => Action::Tab,
+        _ => return,
+    };
+    actions.push(action);
+}
+
+/// An OSC string: `0;title` and `2;title` set the title.
+fn osc(text: &str, actions: &mut Vec<Action>) {
+    if let Some((command, title)) = text.split_once(';')
+        && matches!(command, "0" | "2")
+    {
+        actions.push(Action::SetTitle(String::from(title)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prints(text: &str) -> Vec<Action> {
+        text.chars().map(Action::Print).collect()
+    }
+
+    fn fg(colour: Colour) -> Style {
+        Style {
+            foreground: Some(colour),
+            ..Style::default()
+        }
+    }
+
+    #[test]
+    fn text_and_controls() {
+        let mut parser = Parser::new();
+        let mut expected = prints("ab");
+        expected.extend([
+            Action::CarriageReturn,
+            Action::LineFeed,
+            Action::Tab,
+            Action::Backspace,
+        ]);
+        assert_eq!(parser.feed(b"ab\r\n\t\x08\x07"), expected);
+    }
+
+    #[test]
+    fn sgr_maps_onto_styles() {
+        let mut parser = Parser::new();
+        assert_eq!(
+            parser.feed(b"\x1b[1;31mA\x1b[0m"),
+            [
+                Action::Style(Style {
+                    bold: true,
+                    ..fg(Colour::Named(NamedColour::Red))
+                }),
+                Action::Print('A'),
+                Action::Style(Style::default()),
+            ]
+        );
+        assert_eq!(
+            parser.feed(b"\x1b[38;5;208m"),
+            [Action::Style(fg(Colour::Palette(208)))]
+        );
+        assert_eq!(
+            parser.feed(b"\x1b[38;2;1;2;3;94m"),
+            [Action::Style(fg(Colour::Named(NamedColour::BrightBlue)))]
+        );
+        assert_eq!(
+            parser.feed(b"\x1b[39;48;2;1;2;3m"),
+            [Action::Style(Style {
+                background: Some(Colour::Rgb { r: 1, g: 2, b: 3 }),
+                ..Style::default()
+            })]
+        );
+        // An empty SGR resets, and reverse video swaps the colours.
+        assert_eq!(parser.feed(b"\x1b[m"), [Action::Style(Style::default())]);
+        assert_eq!(
+            parser.feed(b"\x1b[32;7m"),
+            [Action::Style(Style {
+                foreground: Some(Colour::Named(NamedColour::Black)),
+                background: Some(Colour::Named(NamedColour::Green)),
+                ..Style::default()
+            })]
+        );
+    }
+
+    #[test]
+    fn cursor_and_erasing() {
+        let mut parser = Parser::new();
+        assert_eq!(
+            parser.feed(b"\x1b[H\x1b[5;10f\x1b[3A\x1b[C\x1b[7G"),
+            [
+                Action::CursorTo {
+                    row: Some(0),
+                    col: Some(0)
+                },
+                Action::CursorTo {
+                    row: Some(4),
+                    col: Some(9)
+                },
+                Action::CursorBy { rows: -3, cols: 0 },
+                Action::CursorBy { rows: 0, cols: 1 },
+                Action::CursorTo {
+                    row: None,
+                    col: Some(6)
+                },
+            ]
+        );
+        assert_eq!(
+            parser.feed(b"\x1b[J\x1b[1J\x1b[2J\x1b[K\x1b[2K"),
+            [
+                Action::Clear(ClearRegion::ToEndOfScreen),
+                Action::ClearToCursor { screen: true },
+                Action::Clear(ClearRegion::Screen),
+                Action::Clear(ClearRegion::ToEndOfLine),
+                Action::Clear(ClearRegion::Line),
+            ]
+        );
+    }
+
+    #[test]
+    fn modes_regions_and_titles() {
+        let mut parser = Parser::new();
+        assert_eq!(
+            parser.feed(b"\x1b[?1049h\x1b[?25l\x1b[2;20r\x1b]0;top\x07\x1b]2;vi\x1b\\x"),
+            [
+                Action::AlternateScreen(true),
+                Action::CursorVisible(false),
+                Action::ScrollRegion {
+                    top: 1,
+                    bottom: Some(19)
+                },
+                Action::SetTitle(String::from("top")),
+                Action::SetTitle(String::from("vi")),
+                Action::Print('x'),
+            ]
+        );
+        // Unknown sequences and character sets are dropped whole.
+        assert_eq!(parser.feed(b"\x1b[>1;2c\x1b(Bz\x1b[1 q"), prints("z"));
+    }
+
+    #[test]
+    fn pieces_may_split_sequences_and_characters() {
+        let mut parser = Parser::new();
+        let text = "\x1b[31mé\x1b[0m".as_bytes();
+        let mut actions = Vec::new();
+        for byte in text {
+            actions.extend(parser.feed(core::slice::from_ref(byte)));
+            if actions.is_empty() {
+                assert!(!parser.is_idle());
+            }
+        }
+        assert!(parser.is_idle());
+        assert_eq!(
+            actions,
+            [
+                Action::Style(fg(Colour::Named(NamedColour::Red))),
+                Action::Print('é'),
+                Action::Style(Style::default()),
+            ]
+        );
+        assert_eq!(
+            parser.feed(b"a\xffb"),
+            [
+                Action::Print('a'),
+                Action::Print('\u{fffd}'),
+                Action::Print('b')
+            ]
+        );
+    }
+}
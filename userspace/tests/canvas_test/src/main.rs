@@ -0,0 +1,152 @@
+#![no_std]
+#![no_main]
+
+//! `libpanda::graphics::Canvas` drawing into a `PixelBuffer`, read back
+//! pixel by pixel. No compositor is involved: the canvas only writes the
+//! buffer.
+
+use libpanda::environment;
+use libpanda::graphics::{Canvas, Colour, Font, PixelBuffer, Point, Rect};
+
+const FONT: &[u8] = include_bytes!("../../../terminal/fonts/Hack-Regular.ttf");
+
+fn canvas_buffer() -> Option<PixelBuffer> {
+    let mut buffer = PixelBuffer::new(64, 64).ok()?;
+    buffer.clear(Colour::WHITE);
+    Some(buffer)
+}
+
+libpanda::main! {
+    environment::log("Canvas test starting");
+
+    // A rectangle on whole pixels covers them exactly, with no fringe.
+    let Some(mut buffer) = canvas_buffer() else {
+        environment::log("FAIL: Could not allocate buffer");
+        return 1;
+    };
+    let damage = Canvas::new(&mut buffer).fill_rect(Point::new(4.0, 4.0), 8.0, 8.0, Colour::RED);
+    if damage != Rect::new(4, 4, 8, 8)
+        || buffer.get_pixel(4, 4) != Colour::RED
+        || buffer.get_pixel(11, 11) != Colour::RED
+        || buffer.get_pixel(3, 4) != Colour::WHITE
+        || buffer.get_pixel(12, 11) != Colour::WHITE
+    {
+        environment::log("FAIL: Rectangle has the wrong pixels");
+        return 1;
+    }
+    environment::log("PASS: Filled a pixel-aligned rectangle");
+
+    // A circle is solid inside, and partly covers the pixels its edge
+    // crosses.
+    let Some(mut buffer) = canvas_buffer() else {
+        environment::log("FAIL: Could not allocate buffer");
+        return 1;
+    };
+    Canvas::new(&mut buffer).fill_circle(Point::new(32.0, 32.0), 10.0, Colour::BLUE);
+    let edge = buffer.get_pixel(41, 36);
+    if buffer.get_pixel(32, 32) != Colour::BLUE
+        || buffer.get_pixel(32, 20) != Colour::WHITE
+        || edge == Colour::BLUE
+        || edge == Colour::WHITE
+    {
+        environment::log("FAIL: Circle is not anti-aliased");
+        return 1;
+    }
+    environment::log("PASS: Anti-aliased a circle's edge");
+
+    // Nothing is drawn outside the clip, and restoring lifts it.
+    let Some(mut buffer) = canvas_buffer() else {
+        environment::log("FAIL: Could not allocate buffer");
+        return 1;
+    };
+    let mut canvas = Canvas::new(&mut buffer);
+    canvas.save();
+    canvas.clip_rect(Point::new(0.0, 0.0), 32.0, 64.0);
+    canvas.fill_rect(Point::new(0.0, 0.0), 64.0, 32.0, Colour::GREEN);
+    canvas.restore();
+    canvas.fill_rect(Point::new(0.0, 32.0), 64.0, 32.0, Colour::GREEN);
+    if buffer.get_pixel(31, 0) != Colour::GREEN
+        || buffer.get_pixel(32, 0) != Colour::WHITE
+        || buffer.get_pixel(63, 63) != Colour::GREEN
+    {
+        environment::log("FAIL: Clip was not honoured");
+        return 1;
+    }
+    environment::log("PASS: Clipped to a rectangle");
+
+    // Shapes go through the transform: a 4x4 square scaled by two and
+    // moved to (40, 40).
+    let Some(mut buffer) = canvas_buffer() else {
+        environment::log("FAIL: Could not allocate buffer");
+        return 1;
+    };
+    let mut canvas = Canvas::new(&mut buffer);
+    canvas.translate(40.0, 40.0);
+    canvas.scale(2.0, 2.0);
+    let damage = canvas.fill_rect(Point::new(0.0, 0.0), 4.0, 4.0, Colour::BLACK);
+    if damage != Rect::new(40, 40, 8, 8)
+        || buffer.get_pixel(47, 47) != Colour::BLACK
+        || buffer.get_pixel(48, 48) != Colour::WHITE
+    {
+        environment::log("FAIL: Transform was not applied");
+        return 1;
+    }
+    environment::log("PASS: Transformed a shape");
+
+    // An image is blended by its own alpha.
+    let Some(mut buffer) = canvas_buffer() else {
+        environment::log("FAIL: Could not allocate buffer");
+        return 1;
+    };
+    let Ok(mut image) = PixelBuffer::new(2, 2) else {
+        environment::log("FAIL: Could not allocate image");
+        return 1;
+    };
+    image.clear(Colour::BLACK.with_alpha(128));
+    image.set_pixel(1, 1, Colour::RED);
+    Canvas::new(&mut buffer).draw_image(&image, Point::new(10.0, 10.0));
+    let half = buffer.get_pixel(10, 10);
+    if buffer.get_pixel(11, 11) != Colour::RED
+        || !(120..=136).contains(&half.r())
+        || buffer.get_pixel(12, 12) != Colour::WHITE
+    {
+        environment::log("FAIL: Image was not blended");
+        return 1;
+    }
+    environment::log("PASS: Blended an image by its alpha");
+
+    let Ok(font) = Font::from_bytes(FONT) else {
+        environment::log("FAIL: Could not load the font");
+        return 1;
+    };
+    let Some(mut buffer) = canvas_buffer() else {
+        environment::log("FAIL: Could not allocate buffer");
+        return 1;
+    };
+    let damage = Canvas::new(&mut buffer).fill_text(
+        &font,
+        "Hi",
+        16.0,
+        Point::new(2.0, 20.0),
+        Colour::BLACK,
+    );
+    let inked = (damage.y..damage.bottom())
+        .flat_map(|y| (damage.x..damage.right()).map(move |x| (x, y)))
+        .any(|(x, y)| buffer.get_pixel(x, y) != Colour::WHITE);
+    if damage.width == 0 || damage.bottom() > 21 || !inked {
+        environment::log("FAIL: Text was not drawn above its baseline");
+        return 1;
+    }
+    environment::log("PASS: Drew text");
+
+    let one_line = font.measure("wrap me please", 16.0);
+    let layout = font.layout("wrap me please", 16.0, Some(one_line * 0.6));
+    if layout.lines() < 2 || layout.width() > one_line * 0.6 {
+        environment::log("FAIL: Text did not wrap");
+        return 1;
+    }
+    environment::log("PASS: Wrapped text");
+
+    environment::log("Canvas test passed");
+    0
+}
@@ -0,0 +1,45 @@
+//! The clipboard service spawned by a test rather than by `init`.
+//!
+//! Tells the parent `ready` once the `clipboard:` scheme is registered, and
+//! serves it until the parent sends `die` or goes away.
+
+#![no_std]
+#![no_main]
+
+use clipboard::ClipboardService;
+use libpanda::ipc::Channel;
+use libpanda::{environment, process};
+
+libpanda::main! {
+    environment::log("clipboard_child: starting");
+
+    let Some(parent) = Channel::parent() else {
+        environment::log("FAIL: no parent channel");
+        return 1;
+    };
+
+    let mut service = match ClipboardService::new() {
+        Ok(service) => service,
+        Err(_) => {
+            environment::log("FAIL: could not register the clipboard: scheme");
+            let _ = parent.send(b"fail");
+            return 1;
+        }
+    };
+    let _ = parent.send(b"ready");
+
+    let mut buf = [0u8; 16];
+    loop {
+        match parent.try_recv(&mut buf) {
+            Ok(Some(len)) if &buf[..len] == b"die" => break,
+            Err(_) => break,
+            _ => {}
+        }
+        if !service.poll() {
+            process::yield_now();
+        }
+    }
+
+    environment::log("clipboard_child: finished");
+    0
+}
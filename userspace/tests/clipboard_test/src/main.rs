@@ -0,0 +1,83 @@
+//! Test the clipboard service: values keep their type through the
+//! clipboard, and anything on it reads as text.
+
+#![no_std]
+#![no_main]
+
+use libpanda::process::Child;
+use libpanda::{String, clipboard, environment, vec};
+use panda_abi::ErrorCode;
+use panda_abi::value::Value;
+
+libpanda::main! {
+    environment::log("clipboard_test: starting");
+
+    let Ok(mut service) = Child::spawn("file:/initrd/clipboard_child") else {
+        environment::log("FAIL: could not spawn clipboard_child");
+        return 1;
+    };
+    let Some(channel) = service.channel() else {
+        environment::log("FAIL: clipboard_child has no channel");
+        return 1;
+    };
+    let mut buf = [0u8; 16];
+    match channel.recv(&mut buf) {
+        Ok(len) if &buf[..len] == b"ready" => {}
+        _ => {
+            environment::log("FAIL: clipboard_child did not start");
+            return 1;
+        }
+    }
+
+    if clipboard::get() != Ok(Value::Null) || clipboard::get_text().as_deref() != Ok("") {
+        environment::log("FAIL: the clipboard did not start empty");
+        return 1;
+    }
+    environment::log("clipboard_test: starts empty");
+
+    let value = Value::Array(vec![Value::Int(42), Value::String(String::from("panda"))]);
+    if clipboard::set(&value).is_err() || clipboard::get() != Ok(value) {
+        environment::log("FAIL: a value did not come back as it was put");
+        return 1;
+    }
+    if clipboard::get_text().as_deref() != Ok("42\npanda") {
+        environment::log("FAIL: an array did not read as lines of text");
+        return 1;
+    }
+    environment::log("clipboard_test: values keep their type");
+
+    if clipboard::set_text("hello").is_err()
+        || clipboard::get() != Ok(Value::String(String::from("hello")))
+    {
+        environment::log("FAIL: text did not become a string");
+        return 1;
+    }
+    environment::log("clipboard_test: text is a string");
+
+    // More than one transfer arrives whole.
+    let long: String = (0..5000).map(|i| (b'a' + (i % 26) as u8) as char).collect();
+    if clipboard::set_text(&long).is_err() || clipboard::get_text().as_deref() != Ok(long.as_str()) {
+        environment::log("FAIL: a long text was cut short");
+        return 1;
+    }
+    environment::log("clipboard_test: long text survives");
+
+    let huge: String = (0..=clipboard::MAX_SIZE).map(|_| 'x').collect();
+    if clipboard::set_text(&huge) != Err(ErrorCode::NoSpace)
+        || clipboard::get_text().as_deref() != Ok(long.as_str())
+    {
+        environment::log("FAIL: a value over the limit was not refused whole");
+        return 1;
+    }
+    environment::log("clipboard_test: values over the limit are refused");
+
+    if environment::open("clipboard:/other", 0, 0).is_ok() {
+        environment::log("FAIL: opened a path the clipboard does not have");
+        return 1;
+    }
+
+    let _ = channel.send(b"die");
+    let _ = service.wait();
+    environment::log("PASS");
+    0
+}
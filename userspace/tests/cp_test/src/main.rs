@@ -0,0 +1,156 @@
+//! Test the `cp` utility on the ext2 filesystem mounted at /mnt.
+//!
+//! Exercises:
+//! 1. Copy a file to a new name
+//! 2. Copy a file into a directory
+//! 3. Copy a binary file larger than a page
+//! 4. A directory is not copied without -r
+//! 5. cp -rv copies a tree and sends on a From/To table
+//! 6. A file is not copied onto itself
+//! 7. A directory is not copied into itself
+
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc::string::String;
+use libpanda::environment;
+use libpanda::io::File;
+use libpanda::testing::run;
+use panda_abi::value::Value;
+
+libpanda::main! {
+    environment::log("cp_test: Starting");
+
+    if environment::mount("ext2", "/mnt").is_err() {
+        environment::log("FAIL: Could not mount ext2 filesystem");
+        return 1;
+    }
+    environment::log("cp_test: ext2 mounted at /mnt");
+
+    // =========================================================================
+    // Test 1: Copy a file to a new name
+    // =========================================================================
+    environment::log("cp_test: Test 1 - Copy a file");
+    let (code, _, errors) = run("cp", &["/mnt/hello.txt", "/mnt/copy.txt"], None);
+    if code != 0 || !errors.is_empty() {
+        environment::log(&libpanda::format!("FAIL: cp exited {} with {:?}", code, errors));
+        return 1;
+    }
+    match File::read_all("file:/mnt/copy.txt") {
+        Ok(data) if data == b"Hello from ext2!\n" => {}
+        _ => {
+            environment::log("FAIL: copy.txt does not hold what hello.txt does");
+            return 1;
+        }
+    }
+    environment::log("cp_test: Test 1 passed");
+
+    // =========================================================================
+    // Test 2: Copy a file into a directory
+    // =========================================================================
+    environment::log("cp_test: Test 2 - Copy into a directory");
+    let (code, _, _) = run("cp", &["/mnt/hello.txt", "/mnt/subdir"], None);
+    if code != 0 {
+        environment::log("FAIL: cp into subdir failed");
+        return 1;
+    }
+    if environment::stat("file:/mnt/subdir/hello.txt").map(|s| s.size) != Ok(17) {
+        environment::log("FAIL: subdir/hello.txt missing or the wrong size");
+        return 1;
+    }
+    environment::log("cp_test: Test 2 passed");
+
+    // =========================================================================
+    // Test 3: Copy a binary file larger than a page
+    // =========================================================================
+    environment::log("cp_test: Test 3 - Copy a binary file");
+    let (code, _, _) = run("cp", &["/mnt/large.bin", "/mnt/large.copy"], None);
+    let original = File::read_all("file:/mnt/large.bin");
+    let copy = File::read_all("file:/mnt/large.copy");
+    if code != 0 || original.is_err() || original != copy {
+        environment::log("FAIL: large.copy differs from large.bin");
+        return 1;
+    }
+    environment::log("cp_test: Test 3 passed");
+
+    // =========================================================================
+    // Test 4: A directory is not copied without -r
+    // =========================================================================
+    environment::log("cp_test: Test 4 - Directory without -r");
+    let (code, _, errors) = run("cp", &["/mnt/a", "/mnt/a2"], None);
+    if code != 1 || !errors.iter().any(|e| e.contains("is a directory")) {
+        environment::log(&libpanda::format!("FAIL: expected an error, got {} {:?}", code, errors));
+        return 1;
+    }
+    if environment::stat("file:/mnt/a2").is_ok() {
+        environment::log("FAIL: a2 was created");
+        return 1;
+    }
+    environment::log("cp_test: Test 4 passed");
+
+    // =========================================================================
+    // Test 5: cp -rv copies a tree and sends on a From/To table
+    // =========================================================================
+    environment::log("cp_test: Test 5 - Recursive copy");
+    let (code, values, _) = run("cp", &["-rv", "/mnt/a", "/mnt/a2"], None);
+    if code != 0 {
+        environment::log("FAIL: cp -rv failed");
+        return 1;
+    }
+    match values.as_slice() {
+        [Value::Table(table)] if table.rows() == 4 => {
+            let last = Value::String(String::from("file:/mnt/a2/b/c/deep.txt"));
+            if table.cells.last() != Some(&last) {
+                environment::log(&libpanda::format!("FAIL: unexpected table {:?}", table));
+                return 1;
+            }
+        }
+        other => {
+            environment::log(&libpanda::format!("FAIL: expected a 4-row table, got {:?}", other));
+            return 1;
+        }
+    }
+    match File::read_all("file:/mnt/a2/b/c/deep.txt") {
+        Ok(data) if data == b"Deep file\n" => {}
+        _ => {
+            environment::log("FAIL: a2/b/c/deep.txt was not copied");
+            return 1;
+        }
+    }
+    environment::log("cp_test: Test 5 passed");
+
+    // =========================================================================
+    // Test 6: A file is not copied onto itself
+    // =========================================================================
+    environment::log("cp_test: Test 6 - Copy onto itself");
+    let (code, _, errors) = run("cp", &["/mnt/hello.txt", "/mnt/"], None);
+    if code != 1 || !errors.iter().any(|e| e.contains("is the file itself")) {
+        environment::log(&libpanda::format!("FAIL: expected an error, got {} {:?}", code, errors));
+        return 1;
+    }
+    if environment::stat("file:/mnt/hello.txt").map(|s| s.size) != Ok(17) {
+        environment::log("FAIL: hello.txt was harmed");
+        return 1;
+    }
+    environment::log("cp_test: Test 6 passed");
+
+    // =========================================================================
+    // Test 7: A directory is not copied into itself
+    // =========================================================================
+    environment::log("cp_test: Test 7 - Copy into itself");
+    let (code, _, errors) = run("cp", &["-r", "/mnt/a", "/mnt/a/b"], None);
+    if code != 1 || !errors.iter().any(|e| e.contains("inside the directory itself")) {
+        environment::log(&libpanda::format!("FAIL: expected an error, got {} {:?}", code, errors));
+        return 1;
+    }
+    if environment::stat("file:/mnt/a/b/a").is_ok() {
+        environment::log("FAIL: a/b/a was created");
+        return 1;
+    }
+    environment::log("cp_test: Test 7 passed");
+
+    environment::log("cp_test: All tests passed!");
+    0
+}
@@ -4,8 +4,10 @@
 //! test runs as its own init process with no compositor running, so it is
 //! free to become that owner itself and exercise the full round trip —
 //! open, a second open refused `Busy`, `OP_DISPLAY_INFO`/`MAP`/`FLUSH`
-//! succeeding for the owner, close releasing the claim, and a reopen then
-//! succeeding — plus path resolution and rejection of non-display handles.
+//! succeeding for the owner, `OP_DISPLAY_MODES`/`SET_MODE` changing the
+//! resolution and posting `EVENT_DISPLAY_CHANGED`, close releasing the
+//! claim, and a reopen then succeeding — plus path resolution and rejection
+//! of non-display handles.
 //!
 //! Before Phase 5 of plans/userspace-compositor.md this round trip was not
 //! testable: the in-kernel compositor held the display's claim permanently,
@@ -16,14 +18,23 @@
 #![no_std]
 #![no_main]
 
+use libpanda::mailbox::Mailbox;
 use libpanda::{ErrorCode, Handle, environment, file, sys};
-use panda_abi::{SurfaceInfoOut, SurfaceRect};
+use panda_abi::{
+    DISPLAY_MODE_CURRENT, DisplayMode, EVENT_DISPLAY_CHANGED, MAX_DISPLAY_MODES, SurfaceInfoOut,
+    SurfaceRect,
+};
 
 libpanda::main! {
     environment::log("display_test: starting");
 
-    // Claim the display.
-    let Ok(display) = environment::open("display:/pci/display/0", 0, 0) else {
+    // Claim the display, asking to hear when it changes.
+    let mailbox = Mailbox::default();
+    let Ok(display) = environment::open(
+        "display:/pci/display/0",
+        mailbox.handle().as_raw(),
+        EVENT_DISPLAY_CHANGED,
+    ) else {
         environment::log("FAIL: could not open display:/pci/display/0");
         return 1;
     };
@@ -81,6 +92,63 @@ libpanda::main! {
     }
     environment::log("display_test: OP_DISPLAY_FLUSH succeeded for the owner");
 
+    // OP_DISPLAY_MODES lists the mode the display is in.
+    let mut modes = [DisplayMode::default(); MAX_DISPLAY_MODES];
+    let count = sys::display::modes(display, &mut modes);
+    if count <= 0 {
+        environment::log("FAIL: OP_DISPLAY_MODES listed no modes");
+        return 1;
+    }
+    let modes = &modes[..(count as usize).min(MAX_DISPLAY_MODES)];
+    let current = modes
+        .iter()
+        .find(|mode| mode.flags & DISPLAY_MODE_CURRENT != 0)
+        .map(|mode| (mode.width, mode.height));
+    if current != Some((owner_info.width, owner_info.height)) {
+        environment::log("FAIL: OP_DISPLAY_MODES did not list the current mode");
+        return 1;
+    }
+    environment::log("display_test: OP_DISPLAY_MODES listed the current mode");
+
+    // OP_DISPLAY_SET_MODE switches to another listed mode and says so.
+    let Some(other) = modes
+        .iter()
+        .find(|mode| mode.flags & DISPLAY_MODE_CURRENT == 0)
+    else {
+        environment::log("FAIL: OP_DISPLAY_MODES listed only the current mode");
+        return 1;
+    };
+    if sys::display::set_mode(display, other.width, other.height) < 0 {
+        environment::log("FAIL: OP_DISPLAY_SET_MODE failed for a listed mode");
+        return 1;
+    }
+    let (handle, events) = mailbox.recv();
+    if handle != display || !events.is_display_changed() {
+        environment::log("FAIL: mode change did not post EVENT_DISPLAY_CHANGED");
+        return 1;
+    }
+    let mut changed_info = owner_info;
+    if sys::display::info(display, &mut changed_info) < 0
+        || (changed_info.width, changed_info.height) != (other.width, other.height)
+    {
+        environment::log("FAIL: OP_DISPLAY_INFO did not report the new mode");
+        return 1;
+    }
+    // The old mapping is gone with the old framebuffer; map the new one.
+    if sys::display::map(display) < 0 || sys::display::flush(display, None) < 0 {
+        environment::log("FAIL: could not map and flush after a mode change");
+        return 1;
+    }
+    environment::log("display_test: OP_DISPLAY_SET_MODE changed the mode");
+
+    // A size the display does not list is refused.
+    let result = sys::display::set_mode(display, 123, 45);
+    if libpanda::error::from_code(result) != ErrorCode::InvalidArgument {
+        environment::log("FAIL: OP_DISPLAY_SET_MODE accepted an unlisted mode");
+        return 1;
+    }
+    environment::log("display_test: unlisted mode rejected with InvalidArgument");
+
     // Closing the handle releases the claim, so a reopen succeeds.
     file::close(display);
     let Ok(display2) = environment::open("display:/pci/display/0", 0, 0) else {
@@ -130,11 +198,14 @@ libpanda::main! {
     };
     // A valid handle of the wrong type, and a handle that does not exist.
     let bogus = unsafe { Handle::from_raw(0xDEAD_BEEF) };
+    let mut modes = [DisplayMode::default(); 1];
     for handle in [Handle::MAILBOX, bogus] {
         for result in [
             sys::display::info(handle, &mut info),
             sys::display::map(handle),
             sys::display::flush(handle, Some(&rect)),
+            sys::display::modes(handle, &mut modes),
+            sys::display::set_mode(handle, 640, 480),
         ] {
             if result >= 0 {
                 environment::log("FAIL: display operation accepted a non-display handle");
@@ -146,7 +217,7 @@ libpanda::main! {
             }
         }
     }
-    environment::log("display_test: INFO/MAP/FLUSH on non-display handles rejected");
+    environment::log("display_test: display operations on non-display handles rejected");
 
     environment::log("PASS");
     0
@@ -68,6 +68,19 @@ libpanda::main! { |args|
                 }
             }
         }
+        "cwd" => {
+            // Test the working directory: the parent changed to /initrd
+            let cwd = env::current_dir();
+            if cwd != "file:/initrd" {
+                environment::log(&alloc::format!("cwd: FAIL cwd={}", cwd));
+                return 1;
+            }
+            if environment::stat("hello.txt").is_err() {
+                environment::log("cwd: FAIL hello.txt not found relative to cwd");
+                return 1;
+            }
+            environment::log("cwd: file:/initrd OK");
+        }
         _ => {
             environment::log(&alloc::format!("Unknown test case: {}", test_case));
             return 1;
@@ -1,7 +1,7 @@
 #![no_std]
 #![no_main]
 
-use libpanda::{env, environment, process::Child};
+use libpanda::{ErrorCode, env, environment, process::Child};
 
 libpanda::main! {
     environment::log("env_test: starting");
@@ -138,6 +138,46 @@ libpanda::main! {
         }
     }
 
+    // Test 8: Working directory
+    environment::log("env_test: Test 8 - working directory");
+    if env::current_dir() != "file:/" {
+        environment::log("FAIL: initial working directory is not file:/");
+        return 1;
+    }
+    if env::set_current_dir("/initrd/hello.txt") != Err(ErrorCode::NotDirectory) {
+        environment::log("FAIL: a file became the working directory");
+        return 1;
+    }
+    if env::set_current_dir("initrd").is_err() || env::current_dir() != "file:/initrd" {
+        environment::log("FAIL: could not change to a relative directory");
+        return 1;
+    }
+    if environment::stat("hello.txt").is_err() || environment::stat("../initrd/hello.txt").is_err() {
+        environment::log("FAIL: relative paths not resolved against the working directory");
+        return 1;
+    }
+
+    // Test 9: The working directory is inherited, relative spawn paths included
+    environment::log("env_test: Test 9 - working directory inheritance");
+    let mut child = match Child::builder("env_child")
+        .args(&["env_child", "cwd"])
+        .spawn()
+    {
+        Ok(c) => c,
+        Err(_) => {
+            environment::log("FAIL: spawn cwd child failed");
+            return 1;
+        }
+    };
+
+    match child.wait() {
+        Ok(status) if status.success() => {}
+        _ => {
+            environment::log("FAIL: cwd child failed");
+            return 1;
+        }
+    }
+
     environment::log("env_test: all tests passed");
     environment::log("PASS");
     0
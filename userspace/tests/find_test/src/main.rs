@@ -0,0 +1,126 @@
+//! Test the `find` utility on the ext2 filesystem mounted at /mnt.
+//!
+//! Exercises:
+//! 1. find walks a tree, its start included, into a Path/Type/Size table
+//! 2. -name keeps the paths whose name matches a pattern
+//! 3. -type keeps only files or only directories
+//! 4. A missing start is reported
+//! 5. A test it does not know is a usage error
+
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use libpanda::environment;
+use libpanda::testing::{run, string};
+use panda_abi::value::Value;
+
+/// The Path column of the one table in `values`.
+fn paths(values: &[Value]) -> Option<Vec<&str>> {
+    let [Value::Table(table)] = values else {
+        return None;
+    };
+    if table.headers.as_deref() != Some(&[string("Path"), string("Type"), string("Size")][..]) {
+        return None;
+    }
+    table
+        .cells
+        .chunks(3)
+        .map(|row| match &row[0] {
+            Value::String(path) => Some(path.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+libpanda::main! {
+    environment::log("find_test: Starting");
+
+    if environment::mount("ext2", "/mnt").is_err() {
+        environment::log("FAIL: Could not mount ext2 filesystem");
+        return 1;
+    }
+    environment::log("find_test: ext2 mounted at /mnt");
+
+    // =========================================================================
+    // Test 1: find walks a tree into a Path/Type/Size table
+    // =========================================================================
+    environment::log("find_test: Test 1 - Walk a tree");
+    let (code, values, errors) = run("find", &["/mnt/a"], None);
+    let expected = ["/mnt/a", "/mnt/a/b", "/mnt/a/b/c", "/mnt/a/b/c/deep.txt"];
+    if code != 0 || !errors.is_empty() || paths(&values).as_deref() != Some(&expected[..]) {
+        environment::log(&libpanda::format!(
+            "FAIL: find exited {} with {:?} {:?}",
+            code, values, errors
+        ));
+        return 1;
+    }
+    let [Value::Table(table)] = values.as_slice() else {
+        environment::log("FAIL: find did not send on a table");
+        return 1;
+    };
+    if table.cells[1] != string("dir") || table.cells[10..] != [string("file"), Value::Int(10)] {
+        environment::log(&libpanda::format!("FAIL: unexpected rows {:?}", table.cells));
+        return 1;
+    }
+    environment::log("find_test: Test 1 passed");
+
+    // =========================================================================
+    // Test 2: -name keeps the paths whose name matches a pattern
+    // =========================================================================
+    environment::log("find_test: Test 2 - Match names");
+    let (code, values, _) = run("find", &["/mnt", "-name", "*.txt"], None);
+    let expected = ["/mnt/a/b/c/deep.txt", "/mnt/hello.txt", "/mnt/subdir/nested.txt"];
+    if code != 0 || paths(&values).as_deref() != Some(&expected[..]) {
+        environment::log(&libpanda::format!("FAIL: unexpected output {:?}", values));
+        return 1;
+    }
+    environment::log("find_test: Test 2 passed");
+
+    // =========================================================================
+    // Test 3: -type keeps only files or only directories
+    // =========================================================================
+    environment::log("find_test: Test 3 - Match types");
+    let (code, values, _) = run("find", &["/mnt", "-type", "d", "-name", "?"], None);
+    let expected = ["/mnt/a", "/mnt/a/b", "/mnt/a/b/c"];
+    if code != 0 || paths(&values).as_deref() != Some(&expected[..]) {
+        environment::log(&libpanda::format!("FAIL: unexpected output {:?}", values));
+        return 1;
+    }
+    let (code, values, _) = run("find", &["/mnt/subdir", "-type", "f"], None);
+    if code != 0 || paths(&values).as_deref() != Some(&["/mnt/subdir/nested.txt"][..]) {
+        environment::log(&libpanda::format!("FAIL: unexpected output {:?}", values));
+        return 1;
+    }
+    environment::log("find_test: Test 3 passed");
+
+    // =========================================================================
+    // Test 4: A missing start is reported
+    // =========================================================================
+    environment::log("find_test: Test 4 - Missing start");
+    let (code, values, errors) = run("find", &["/mnt/missing"], None);
+    if code != 1
+        || !errors.iter().any(|e| e.contains("missing: not found"))
+        || paths(&values).is_some_and(|paths| !paths.is_empty())
+    {
+        environment::log(&libpanda::format!("FAIL: expected an error, got {} {:?}", code, errors));
+        return 1;
+    }
+    environment::log("find_test: Test 4 passed");
+
+    // =========================================================================
+    // Test 5: A test it does not know is a usage error
+    // =========================================================================
+    environment::log("find_test: Test 5 - Usage");
+    let (code, _, errors) = run("find", &["/mnt", "-type", "x"], None);
+    if code != 1 || !errors.iter().any(|e| e.starts_with("Usage: find")) {
+        environment::log(&libpanda::format!("FAIL: expected usage, got {} {:?}", code, errors));
+        return 1;
+    }
+    environment::log("find_test: Test 5 passed");
+
+    environment::log("find_test: All tests passed!");
+    0
+}
@@ -0,0 +1,79 @@
+#![no_std]
+#![no_main]
+
+//! `PixelBuffer::decode` on each image format, read back pixel by pixel.
+
+use libpanda::ErrorCode;
+use libpanda::environment;
+use libpanda::graphics::{Colour, ImageFormat, PixelBuffer};
+
+/// The README's screenshot: 640x480 RGB, deflate-compressed.
+const PNG: &[u8] = include_bytes!("../../../../assets/terminal.png");
+
+/// A 2x2 24-bit BMP, bottom row first: red and green over blue and white.
+const BMP: &[u8] = &[
+    b'B', b'M', 70, 0, 0, 0, 0, 0, 0, 0, 54, 0, 0, 0, // file header
+    40, 0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0, 1, 0, 24, 0, 0, 0, 0, 0, // info header
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, //
+    255, 0, 0, 255, 255, 255, 0, 0, // bottom row
+    0, 0, 255, 0, 255, 0, 0, 0, // top row
+];
+
+libpanda::main! {
+    environment::log("Image test starting");
+
+    if ImageFormat::detect(PNG) != Some(ImageFormat::Png) {
+        environment::log("FAIL: PNG not detected");
+        return 1;
+    }
+    let Ok(png) = PixelBuffer::decode(PNG) else {
+        environment::log("FAIL: Could not decode PNG");
+        return 1;
+    };
+    if png.width() != 640
+        || png.height() != 480
+        || png.get_pixel(0, 0) != Colour(0xff2e3440)
+        || png.get_pixel(94, 64) != Colour(0xffd4d4d4)
+        || png.get_pixel(73, 67) != Colour(0xffc7c7c7)
+        || png.get_pixel(639, 479) != Colour(0xff2e3440)
+    {
+        environment::log("FAIL: PNG has the wrong pixels");
+        return 1;
+    }
+    environment::log("PASS: Decoded a PNG");
+
+    let Ok(bmp) = PixelBuffer::decode(BMP) else {
+        environment::log("FAIL: Could not decode BMP");
+        return 1;
+    };
+    if bmp.get_pixel(0, 0) != Colour::RED
+        || bmp.get_pixel(1, 0) != Colour::GREEN
+        || bmp.get_pixel(0, 1) != Colour::BLUE
+        || bmp.get_pixel(1, 1) != Colour::WHITE
+    {
+        environment::log("FAIL: BMP has the wrong pixels");
+        return 1;
+    }
+    environment::log("PASS: Decoded a BMP");
+
+    let Ok(ppm) = PixelBuffer::decode(b"P3\n# orange\n1 1\n255\n255 128 0\n") else {
+        environment::log("FAIL: Could not decode PPM");
+        return 1;
+    };
+    if ppm.get_pixel(0, 0) != Colour(0xffff8000) {
+        environment::log("FAIL: PPM has the wrong pixel");
+        return 1;
+    }
+    environment::log("PASS: Decoded a PPM");
+
+    if PixelBuffer::decode(b"GIF89a").err() != Some(ErrorCode::InvalidArgument)
+        || PixelBuffer::decode(&PNG[..PNG.len() / 2]).err() != Some(ErrorCode::InvalidArgument)
+    {
+        environment::log("FAIL: Decoded something that is not an image");
+        return 1;
+    }
+    environment::log("PASS: Rejected data that is not an image");
+
+    environment::log("Image test passed");
+    0
+}
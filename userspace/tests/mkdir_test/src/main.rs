@@ -0,0 +1,117 @@
+//! Test the `mkdir` utility on the ext2 filesystem mounted at /mnt.
+//!
+//! Exercises:
+//! 1. Make a directory
+//! 2. A directory already there is reported
+//! 3. A missing parent is reported
+//! 4. mkdir -pv makes the parents and sends on a Path table
+//! 5. mkdir -p is content with a directory already there
+//! 6. mkdir -p cannot make a directory inside a file
+
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use libpanda::environment;
+use libpanda::testing::{run, string};
+use panda_abi::value::Value;
+
+fn is_dir(path: &str) -> bool {
+    environment::stat(path).is_ok_and(|stat| stat.is_dir)
+}
+
+libpanda::main! {
+    environment::log("mkdir_test: Starting");
+
+    if environment::mount("ext2", "/mnt").is_err() {
+        environment::log("FAIL: Could not mount ext2 filesystem");
+        return 1;
+    }
+    environment::log("mkdir_test: ext2 mounted at /mnt");
+
+    // =========================================================================
+    // Test 1: Make a directory
+    // =========================================================================
+    environment::log("mkdir_test: Test 1 - Make a directory");
+    let (code, values, errors) = run("mkdir", &["/mnt/new"], None);
+    if code != 0 || !errors.is_empty() || !values.is_empty() {
+        environment::log(&libpanda::format!("FAIL: mkdir exited {} with {:?}", code, errors));
+        return 1;
+    }
+    if !is_dir("file:/mnt/new") {
+        environment::log("FAIL: new is not a directory");
+        return 1;
+    }
+    environment::log("mkdir_test: Test 1 passed");
+
+    // =========================================================================
+    // Test 2: A directory already there is reported
+    // =========================================================================
+    environment::log("mkdir_test: Test 2 - Already there");
+    let (code, _, errors) = run("mkdir", &["/mnt/subdir"], None);
+    if code != 1 || !errors.iter().any(|e| e.contains("subdir: already exists")) {
+        environment::log(&libpanda::format!("FAIL: expected an error, got {} {:?}", code, errors));
+        return 1;
+    }
+    environment::log("mkdir_test: Test 2 passed");
+
+    // =========================================================================
+    // Test 3: A missing parent is reported
+    // =========================================================================
+    environment::log("mkdir_test: Test 3 - Missing parent");
+    let (code, _, errors) = run("mkdir", &["/mnt/x/y"], None);
+    if code != 1 || errors.len() != 1 || is_dir("file:/mnt/x") {
+        environment::log(&libpanda::format!("FAIL: expected an error, got {} {:?}", code, errors));
+        return 1;
+    }
+    environment::log("mkdir_test: Test 3 passed");
+
+    // =========================================================================
+    // Test 4: mkdir -pv makes the parents and sends on a Path table
+    // =========================================================================
+    environment::log("mkdir_test: Test 4 - Make parents");
+    let (code, values, _) = run("mkdir", &["-pv", "/mnt/x/y/z"], None);
+    if code != 0 {
+        environment::log("FAIL: mkdir -pv failed");
+        return 1;
+    }
+    let expected = [string("file:/mnt/x"), string("file:/mnt/x/y"), string("file:/mnt/x/y/z")];
+    match values.as_slice() {
+        [Value::Table(table)] if table.cells == expected => {}
+        other => {
+            environment::log(&libpanda::format!("FAIL: unexpected output {:?}", other));
+            return 1;
+        }
+    }
+    if !is_dir("file:/mnt/x/y/z") {
+        environment::log("FAIL: x/y/z is not a directory");
+        return 1;
+    }
+    environment::log("mkdir_test: Test 4 passed");
+
+    // =========================================================================
+    // Test 5: mkdir -p is content with a directory already there
+    // =========================================================================
+    environment::log("mkdir_test: Test 5 - Parents already there");
+    let (code, _, errors) = run("mkdir", &["-p", "/mnt/a/b", "/mnt/a/b/c/d"], None);
+    if code != 0 || !errors.is_empty() || !is_dir("file:/mnt/a/b/c/d") {
+        environment::log(&libpanda::format!("FAIL: mkdir -p exited {} with {:?}", code, errors));
+        return 1;
+    }
+    environment::log("mkdir_test: Test 5 passed");
+
+    // =========================================================================
+    // Test 6: mkdir -p cannot make a directory inside a file
+    // =========================================================================
+    environment::log("mkdir_test: Test 6 - Inside a file");
+    let (code, _, errors) = run("mkdir", &["-p", "/mnt/hello.txt/d"], None);
+    if code != 1 || !errors.iter().any(|e| e.contains("hello.txt: not a directory")) {
+        environment::log(&libpanda::format!("FAIL: expected an error, got {} {:?}", code, errors));
+        return 1;
+    }
+    environment::log("mkdir_test: Test 6 passed");
+
+    environment::log("mkdir_test: All tests passed!");
+    0
+}
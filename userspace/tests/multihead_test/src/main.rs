@@ -0,0 +1,90 @@
+//! Test two displays as independent heads.
+//!
+//! The VM has a second virtio-gpu (needs-second-display), which the display
+//! scheme exposes as `display:/pci/display/1`. Each head is claimed on its
+//! own, so both open at once; changing the mode of one posts
+//! `EVENT_DISPLAY_CHANGED` for that head alone and leaves the other as it
+//! was.
+
+#![no_std]
+#![no_main]
+
+use libpanda::mailbox::Mailbox;
+use libpanda::{Handle, environment, file, sys};
+use panda_abi::{
+    DISPLAY_MODE_CURRENT, DisplayMode, EVENT_DISPLAY_CHANGED, MAX_DISPLAY_MODES, SurfaceInfoOut,
+};
+
+fn info(display: Handle) -> Option<(u32, u32)> {
+    let mut info = SurfaceInfoOut {
+        width: 0,
+        height: 0,
+        format: 0,
+        stride: 0,
+    };
+    (sys::display::info(display, &mut info) >= 0).then_some((info.width, info.height))
+}
+
+libpanda::main! {
+    environment::log("multihead_test: starting");
+
+    let mailbox = Mailbox::default();
+    let open = |path| environment::open(path, mailbox.handle().as_raw(), EVENT_DISPLAY_CHANGED);
+    let (Ok(first), Ok(second)) = (open("display:/pci/display/0"), open("display:/pci/display/1"))
+    else {
+        environment::log("FAIL: could not open both displays");
+        return 1;
+    };
+    environment::log("multihead_test: claimed both displays");
+
+    let Some(first_size) = info(first) else {
+        environment::log("FAIL: OP_DISPLAY_INFO failed on the first display");
+        return 1;
+    };
+
+    // Switch the second head to a mode it lists but is not in.
+    let mut modes = [DisplayMode::default(); MAX_DISPLAY_MODES];
+    let count = sys::display::modes(second, &mut modes);
+    if count <= 0 {
+        environment::log("FAIL: OP_DISPLAY_MODES listed no modes for the second display");
+        return 1;
+    }
+    let modes = &modes[..(count as usize).min(MAX_DISPLAY_MODES)];
+    let Some(other) = modes
+        .iter()
+        .find(|mode| mode.flags & DISPLAY_MODE_CURRENT == 0)
+    else {
+        environment::log("FAIL: the second display listed only its current mode");
+        return 1;
+    };
+    if sys::display::set_mode(second, other.width, other.height) < 0 {
+        environment::log("FAIL: OP_DISPLAY_SET_MODE failed on the second display");
+        return 1;
+    }
+
+    let (handle, events) = mailbox.recv();
+    if handle != second || !events.is_display_changed() {
+        environment::log("FAIL: the mode change was not reported for the second display");
+        return 1;
+    }
+    if info(second) != Some((other.width, other.height)) {
+        environment::log("FAIL: the second display did not report its new mode");
+        return 1;
+    }
+    environment::log("multihead_test: second display changed mode");
+
+    if info(first) != Some(first_size) {
+        environment::log("FAIL: the first display changed with the second");
+        return 1;
+    }
+    if sys::display::map(first) < 0 || sys::display::flush(first, None) < 0 {
+        environment::log("FAIL: the first display could not be mapped and flushed");
+        return 1;
+    }
+    environment::log("multihead_test: first display unaffected");
+
+    file::close(second);
+    file::close(first);
+    environment::log("PASS");
+    0
+}
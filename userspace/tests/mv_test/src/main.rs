@@ -0,0 +1,130 @@
+//! Test the `mv` utility on the ext2 filesystem mounted at /mnt.
+//!
+//! Exercises:
+//! 1. Move a file to a new name
+//! 2. mv -v moves a file into a directory and sends on a From/To table
+//! 3. Move a directory and everything in it
+//! 4. A missing source is reported
+//! 5. Several sources need a directory to go into
+
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use libpanda::environment;
+use libpanda::io::File;
+use libpanda::testing::{run, string};
+use panda_abi::value::Value;
+
+libpanda::main! {
+    environment::log("mv_test: Starting");
+
+    if environment::mount("ext2", "/mnt").is_err() {
+        environment::log("FAIL: Could not mount ext2 filesystem");
+        return 1;
+    }
+    environment::log("mv_test: ext2 mounted at /mnt");
+
+    // =========================================================================
+    // Test 1: Move a file to a new name
+    // =========================================================================
+    environment::log("mv_test: Test 1 - Move a file");
+    let (code, values, errors) = run("mv", &["/mnt/hello.txt", "/mnt/moved.txt"], None);
+    if code != 0 || !errors.is_empty() || !values.is_empty() {
+        environment::log(&libpanda::format!("FAIL: mv exited {} with {:?}", code, errors));
+        return 1;
+    }
+    if environment::stat("file:/mnt/hello.txt").is_ok() {
+        environment::log("FAIL: hello.txt is still there");
+        return 1;
+    }
+    match File::read_all("file:/mnt/moved.txt") {
+        Ok(data) if data == b"Hello from ext2!\n" => {}
+        _ => {
+            environment::log("FAIL: moved.txt does not hold what hello.txt did");
+            return 1;
+        }
+    }
+    environment::log("mv_test: Test 1 passed");
+
+    // =========================================================================
+    // Test 2: mv -v moves a file into a directory and sends on a table
+    // =========================================================================
+    environment::log("mv_test: Test 2 - Move into a directory");
+    let (code, values, _) = run("mv", &["-v", "/mnt/moved.txt", "/mnt/subdir"], None);
+    if code != 0 {
+        environment::log("FAIL: mv -v failed");
+        return 1;
+    }
+    let expected = [string("file:/mnt/moved.txt"), string("file:/mnt/subdir/moved.txt")];
+    match values.as_slice() {
+        [Value::Table(table)] if table.cells == expected => {}
+        other => {
+            environment::log(&libpanda::format!("FAIL: unexpected output {:?}", other));
+            return 1;
+        }
+    }
+    if environment::stat("file:/mnt/moved.txt").is_ok()
+        || environment::stat("file:/mnt/subdir/moved.txt").is_err()
+    {
+        environment::log("FAIL: moved.txt is not in subdir");
+        return 1;
+    }
+    environment::log("mv_test: Test 2 passed");
+
+    // =========================================================================
+    // Test 3: Move a directory and everything in it
+    // =========================================================================
+    environment::log("mv_test: Test 3 - Move a directory");
+    let (code, _, _) = run("mv", &["/mnt/a", "/mnt/z"], None);
+    if code != 0 {
+        environment::log("FAIL: mv of a directory failed");
+        return 1;
+    }
+    if environment::stat("file:/mnt/a").is_ok() {
+        environment::log("FAIL: a is still there");
+        return 1;
+    }
+    match File::read_all("file:/mnt/z/b/c/deep.txt") {
+        Ok(data) if data == b"Deep file\n" => {}
+        _ => {
+            environment::log("FAIL: z/b/c/deep.txt is missing");
+            return 1;
+        }
+    }
+    environment::log("mv_test: Test 3 passed");
+
+    // =========================================================================
+    // Test 4: A missing source is reported
+    // =========================================================================
+    environment::log("mv_test: Test 4 - Missing source");
+    let (code, _, errors) = run("mv", &["/mnt/missing.txt", "/mnt/other.txt"], None);
+    if code != 1 || !errors.iter().any(|e| e.contains("missing.txt: not found")) {
+        environment::log(&libpanda::format!("FAIL: expected an error, got {} {:?}", code, errors));
+        return 1;
+    }
+    environment::log("mv_test: Test 4 passed");
+
+    // =========================================================================
+    // Test 5: Several sources need a directory to go into
+    // =========================================================================
+    environment::log("mv_test: Test 5 - Several sources, no directory");
+    let (code, _, errors) = run("mv", &[
+        "/mnt/large.bin",
+        "/mnt/subdir/moved.txt",
+        "/mnt/subdir/nested.txt",
+    ], None);
+    if code != 1 || !errors.iter().any(|e| e.contains("not a directory")) {
+        environment::log(&libpanda::format!("FAIL: expected an error, got {} {:?}", code, errors));
+        return 1;
+    }
+    if environment::stat("file:/mnt/large.bin").is_err() {
+        environment::log("FAIL: large.bin was moved");
+        return 1;
+    }
+    environment::log("mv_test: Test 5 passed");
+
+    environment::log("mv_test: All tests passed!");
+    0
+}
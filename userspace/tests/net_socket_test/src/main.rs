@@ -0,0 +1,184 @@
+//! Test the `tcp:` and `udp:` schemes served by the network service.
+//!
+//! Spawns `netd_child`, which configures the NIC by DHCP from QEMU's
+//! user-mode network and registers both schemes, then drives them through
+//! `libpanda::socket`:
+//!
+//! - a TCP round trip with a remote host: the `needs-net` marker forwards
+//!   10.0.2.100:7 to a `cat` process on the host, which echoes;
+//! - a listener on our own address, with a transfer larger than one
+//!   message and one TCP send buffer, and end-of-stream on close;
+//! - a refused connection;
+//! - UDP between a connected and a bound socket.
+
+#![no_std]
+#![no_main]
+
+use libpanda::io::{Read, Write};
+use libpanda::ipc::Channel;
+use libpanda::socket::{Ipv4Addr, SocketAddrV4, TcpListener, TcpStream, UdpSocket};
+use libpanda::{ErrorCode, Vec, environment, process, vec};
+
+/// The guestfwd rule in `needs-net`.
+const ECHO: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(10, 0, 2, 100), 7);
+const LISTEN_PORT: u16 = 8080;
+const CLOSED_PORT: u16 = 9;
+const UDP_PORT: u16 = 5353;
+/// More than a channel message and a TCP send buffer, so the transfer
+/// needs segmentation, window updates and channel flow control.
+const BULK_LEN: usize = 40_000;
+
+libpanda::main! {
+    environment::log("net_socket_test: starting");
+
+    let Ok(netd) = environment::spawn("file:/initrd/netd_child") else {
+        environment::log("FAIL: could not spawn netd_child");
+        return 1;
+    };
+    let Some(to_netd) = Channel::from_handle_borrowed(netd) else {
+        environment::log("FAIL: netd_child handle is not a channel");
+        return 1;
+    };
+
+    let mut msg = [0u8; 64];
+    let address = match to_netd.recv(&mut msg) {
+        Ok(len) => core::str::from_utf8(&msg[..len])
+            .ok()
+            .and_then(|msg| msg.strip_prefix("ready "))
+            .and_then(Ipv4Addr::parse),
+        Err(_) => None,
+    };
+    let Some(address) = address else {
+        environment::log("FAIL: netd_child did not come up");
+        return 1;
+    };
+    if address != Ipv4Addr::new(10, 0, 2, 15) {
+        environment::log("FAIL: DHCP gave an unexpected address");
+        return 1;
+    }
+    environment::log("net_socket_test: DHCP configured 10.0.2.15");
+
+    // TCP to a remote host.
+    let Ok(mut echo) = TcpStream::connect(ECHO) else {
+        environment::log("FAIL: could not connect to the echo service");
+        return 1;
+    };
+    if echo.write_all(b"hello, netd").is_err() {
+        environment::log("FAIL: write to the echo service failed");
+        return 1;
+    }
+    let mut reply = [0u8; 11];
+    if echo.read_exact(&mut reply).is_err() || &reply != b"hello, netd" {
+        environment::log("FAIL: the echo service did not echo");
+        return 1;
+    }
+    drop(echo);
+    environment::log("net_socket_test: echoed through 10.0.2.100:7");
+
+    // A listener on our own address.
+    let Ok(listener) = TcpListener::bind(LISTEN_PORT) else {
+        environment::log("FAIL: could not listen");
+        return 1;
+    };
+    if TcpListener::bind(LISTEN_PORT).is_ok() {
+        environment::log("FAIL: a second listener took the same port");
+        return 1;
+    }
+    let Ok(mut client) = TcpStream::connect(SocketAddrV4::new(address, LISTEN_PORT)) else {
+        environment::log("FAIL: could not connect to our own listener");
+        return 1;
+    };
+    let Ok((mut server, peer)) = listener.accept() else {
+        environment::log("FAIL: accept failed");
+        return 1;
+    };
+    if peer.ip != address {
+        environment::log("FAIL: accepted connection has the wrong peer");
+        return 1;
+    }
+    environment::log("net_socket_test: accepted a loopback connection");
+
+    let bulk: Vec<u8> = (0..BULK_LEN).map(|i| (i % 251) as u8).collect();
+    if client.write_all(&bulk).is_err() {
+        environment::log("FAIL: bulk write failed");
+        return 1;
+    }
+    let mut received = vec![0u8; BULK_LEN];
+    if server.read_exact(&mut received).is_err() || received != bulk {
+        environment::log("FAIL: bulk data did not arrive intact");
+        return 1;
+    }
+    if server.write_all(b"thanks").is_err() {
+        environment::log("FAIL: write from the accepted side failed");
+        return 1;
+    }
+    let mut thanks = [0u8; 6];
+    if client.read_exact(&mut thanks).is_err() || &thanks != b"thanks" {
+        environment::log("FAIL: reply from the accepted side did not arrive");
+        return 1;
+    }
+    environment::log("net_socket_test: loopback carried 40000 bytes");
+
+    drop(client);
+    let mut rest = Vec::new();
+    match server.read_to_end(&mut rest) {
+        Ok(0) => environment::log("net_socket_test: close reached the other end"),
+        _ => {
+            environment::log("FAIL: no end-of-stream after close");
+            return 1;
+        }
+    }
+    drop(server);
+    drop(listener);
+
+    match TcpStream::connect(SocketAddrV4::new(address, CLOSED_PORT)) {
+        Err(ErrorCode::NotFound) => environment::log("net_socket_test: closed port refused"),
+        _ => {
+            environment::log("FAIL: connect to a closed port did not fail");
+            return 1;
+        }
+    }
+
+    // UDP.
+    let Ok(bound) = UdpSocket::bind(UDP_PORT) else {
+        environment::log("FAIL: could not bind a UDP port");
+        return 1;
+    };
+    let Ok(connected) = UdpSocket::connect(SocketAddrV4::new(address, UDP_PORT)) else {
+        environment::log("FAIL: could not connect a UDP socket");
+        return 1;
+    };
+    if connected.send(b"ping").is_err() {
+        environment::log("FAIL: UDP send failed");
+        return 1;
+    }
+    let mut datagram = [0u8; 16];
+    let Ok((len, from)) = bound.recv_from(&mut datagram) else {
+        environment::log("FAIL: UDP receive failed");
+        return 1;
+    };
+    if &datagram[..len] != b"ping" || from.ip != address {
+        environment::log("FAIL: wrong UDP datagram or sender");
+        return 1;
+    }
+    if bound.send_to(b"pong", from).is_err() {
+        environment::log("FAIL: UDP send_to failed");
+        return 1;
+    }
+    match connected.recv(&mut datagram) {
+        Ok(4) if &datagram[..4] == b"pong" => environment::log("net_socket_test: UDP round trip"),
+        _ => {
+            environment::log("FAIL: UDP reply did not arrive");
+            return 1;
+        }
+    }
+
+    let _ = to_netd.send(b"die");
+    if process::wait(netd) != 0 {
+        environment::log("FAIL: netd_child exited with an error");
+        return 1;
+    }
+
+    environment::log("PASS");
+    0
+}
@@ -0,0 +1,147 @@
+//! Test the `net:` scheme (raw ethernet frames on a virtio-net device).
+//!
+//! QEMU attaches the NIC to a user-mode network with `restrict=on` (see the
+//! `needs-net` marker), so nothing leaves the host. Its built-in gateway at
+//! 10.0.2.2 answers ARP, which gives a full transmit/receive round trip
+//! without any protocol stack: queue hand-built ARP requests in the frame
+//! ring, flush them, wait for `EVENT_NET_RX`, and find the reply among the
+//! frames the kernel wrote into the ring.
+
+#![no_std]
+#![no_main]
+
+use libpanda::mailbox::Mailbox;
+use libpanda::net::NetDevice;
+use libpanda::{ErrorCode, environment, format};
+use panda_abi::NET_MAX_FRAME_SIZE;
+
+const NIC: &str = "net:/pci/network/0";
+const OUR_IP: [u8; 4] = [10, 0, 2, 15];
+const GATEWAY_IP: [u8; 4] = [10, 0, 2, 2];
+
+/// Frames to examine before giving up on the ARP reply.
+const MAX_FRAMES: usize = 32;
+
+libpanda::main! {
+    environment::log("net_test: starting");
+
+    let mailbox = Mailbox::default();
+    let Ok(nic) = NetDevice::open_with_mailbox(NIC, &mailbox) else {
+        environment::log("FAIL: could not open net:/pci/network/0");
+        return 1;
+    };
+    environment::log("net_test: opened net:/pci/network/0");
+
+    // The device is exclusively claimed.
+    match NetDevice::open(NIC) {
+        Err(ErrorCode::Busy) => environment::log("net_test: second open refused with Busy"),
+        _ => {
+            environment::log("FAIL: second open did not report Busy");
+            return 1;
+        }
+    }
+
+    let mac = nic.mac_address();
+    environment::log(&format!(
+        "net_test: mac {:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+        mac[0], mac[1], mac[2], mac[3], mac[4], mac[5]
+    ));
+    if nic.max_frame_size() != NET_MAX_FRAME_SIZE {
+        environment::log("FAIL: unexpected maximum frame size");
+        return 1;
+    }
+
+    // Anything shorter than an ethernet header or longer than the maximum
+    // frame is refused whole.
+    let short = [0u8; 10];
+    let long = [0u8; NET_MAX_FRAME_SIZE + 1];
+    if nic.send(&short) != Err(ErrorCode::InvalidArgument)
+        || nic.send(&long) != Err(ErrorCode::InvalidArgument)
+    {
+        environment::log("FAIL: malformed frame was not rejected");
+        return 1;
+    }
+    environment::log("net_test: malformed frames rejected with InvalidArgument");
+
+    // Nothing goes out until the ring is flushed, and then all of it does.
+    let request = arp_request(mac);
+    if nic.queue(&request).is_err() || nic.queue(&request).is_err() {
+        environment::log("FAIL: could not queue ARP requests");
+        return 1;
+    }
+    if nic.flush() != Ok(2) {
+        environment::log("FAIL: flush did not send both queued frames");
+        return 1;
+    }
+    environment::log("net_test: sent ARP request for 10.0.2.2");
+    if nic.flush() != Ok(0) {
+        environment::log("FAIL: flush of an empty ring sent frames");
+        return 1;
+    }
+    environment::log("net_test: flush of an empty ring sent nothing");
+
+    let mut frame = [0u8; NET_MAX_FRAME_SIZE];
+    let mut seen = 0;
+    'wait: loop {
+        let (handle, events) = mailbox.recv();
+        if handle != nic.handle() || !events.is_net_rx() {
+            continue;
+        }
+        while let Ok(Some(len)) = nic.try_recv(&mut frame) {
+            if is_arp_reply(&frame[..len], mac) {
+                break 'wait;
+            }
+            seen += 1;
+            if seen == MAX_FRAMES {
+                environment::log("FAIL: no ARP reply from the gateway");
+                return 1;
+            }
+        }
+    }
+    environment::log("net_test: ARP reply from 10.0.2.2");
+
+    // Closing releases the claim.
+    drop(nic);
+    let Ok(nic) = NetDevice::open(NIC) else {
+        environment::log("FAIL: reopen after close failed");
+        return 1;
+    };
+    environment::log("net_test: reopen after close succeeded");
+    drop(nic);
+
+    match NetDevice::open("net:/pci/display/0") {
+        Err(ErrorCode::NotFound) => {
+            environment::log("net_test: non-network device rejected with NotFound")
+        }
+        _ => {
+            environment::log("FAIL: non-network device was accepted by the net scheme");
+            return 1;
+        }
+    }
+
+    0
+}
+
+/// A broadcast ARP request: who has 10.0.2.2? Tell 10.0.2.15.
+fn arp_request(mac: [u8; 6]) -> [u8; 42] {
+    let mut frame = [0u8; 42];
+    // Ethernet header: broadcast destination, our source, ARP ethertype.
+    frame[0..6].copy_from_slice(&[0xff; 6]);
+    frame[6..12].copy_from_slice(&mac);
+    frame[12..14].copy_from_slice(&[0x08, 0x06]);
+    // ARP: Ethernet/IPv4, 6-byte and 4-byte addresses, opcode 1 (request).
+    frame[14..22].copy_from_slice(&[0x00, 0x01, 0x08, 0x00, 6, 4, 0x00, 0x01]);
+    frame[22..28].copy_from_slice(&mac);
+    frame[28..32].copy_from_slice(&OUR_IP);
+    frame[32..38].copy_from_slice(&[0; 6]);
+    frame[38..42].copy_from_slice(&GATEWAY_IP);
+    frame
+}
+
+fn is_arp_reply(frame: &[u8], mac: [u8; 6]) -> bool {
+    frame.len() >= 42
+        && frame[12..14] == [0x08, 0x06]
+        && frame[20..22] == [0x00, 0x02]
+        && frame[28..32] == GATEWAY_IP
+        && frame[32..38] == mac
+}
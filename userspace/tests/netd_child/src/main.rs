@@ -0,0 +1,66 @@
+//! The network service spawned by a test rather than by `init`.
+//!
+//! Runs `netd`'s service loop configured by DHCP against QEMU's user-mode
+//! network, tells the parent `ready <address>` once the interface has an
+//! address, and serves until the parent sends `die` or goes away. Sends
+//! `fail` instead if DHCP hasn't finished within `CONFIGURE_TIMEOUT_MS`.
+
+#![no_std]
+#![no_main]
+
+use libpanda::ipc::Channel;
+use libpanda::{environment, format, process};
+use netd::service::{IDLE_SLEEP_MS, NetService};
+use netstack::Config;
+
+/// Slirp answers DHCP at once; this only bounds a broken exchange.
+const CONFIGURE_TIMEOUT_MS: u64 = 10_000;
+
+libpanda::main! {
+    environment::log("netd_child: starting");
+
+    let Some(parent) = Channel::parent() else {
+        environment::log("FAIL: no parent channel");
+        return 1;
+    };
+
+    let mut service = match NetService::new(Config::Dhcp) {
+        Ok(service) => service,
+        Err(_) => {
+            environment::log("FAIL: could not start the network service");
+            let _ = parent.send(b"fail");
+            return 1;
+        }
+    };
+
+    let deadline = environment::time() as u64 + CONFIGURE_TIMEOUT_MS;
+    let address = loop {
+        if let Some(config) = service.ipv4_config() {
+            break config.address;
+        }
+        if environment::time() as u64 >= deadline {
+            environment::log("FAIL: DHCP did not configure the interface");
+            let _ = parent.send(b"fail");
+            return 1;
+        }
+        if !service.poll() {
+            process::sleep(IDLE_SLEEP_MS);
+        }
+    };
+    let _ = parent.send(format!("ready {}", address).as_bytes());
+
+    let mut buf = [0u8; 16];
+    loop {
+        match parent.try_recv(&mut buf) {
+            Ok(Some(len)) if &buf[..len] == b"die" => break,
+            Err(_) => break,
+            _ => {}
+        }
+        if !service.poll() {
+            process::sleep(IDLE_SLEEP_MS);
+        }
+    }
+
+    environment::log("netd_child: finished");
+    0
+}
@@ -0,0 +1,107 @@
+//! Test the `pointer:` scheme with QEMU's virtio mouse and tablet.
+//!
+//! Every test VM has a `virtio-mouse`; the `needs-tablet` marker adds a
+//! `virtio-tablet-pci` after the keyboard. Both are found by walking the
+//! input devices, and `OP_POINTER_INFO` must tell them apart. The monitor
+//! file then moves the pointer and clicks; QEMU delivers each to whichever
+//! device handles that kind of event, so the test accepts them from either.
+
+#![no_std]
+#![no_main]
+
+use libpanda::mailbox::Mailbox;
+use libpanda::pointer::{Buttons, PointerDevice};
+use libpanda::{ErrorCode, environment, format};
+
+/// Input devices to look through (mouse, keyboard, tablet).
+const MAX_INPUT_DEVICES: usize = 8;
+
+libpanda::main! {
+    environment::log("pointer_test: starting");
+
+    let mailbox = Mailbox::default();
+    let mut mouse = None;
+    let mut tablet = None;
+    for index in 0..MAX_INPUT_DEVICES {
+        let uri = format!("pointer:/pci/input/{}", index);
+        match PointerDevice::open_with_mailbox(&uri, &mailbox) {
+            Ok(device) if device.is_absolute() => tablet = Some(device),
+            Ok(device) => mouse = Some(device),
+            Err(_) => {}
+        }
+    }
+
+    let Some(mut mouse) = mouse else {
+        environment::log("FAIL: no relative pointer found");
+        return 1;
+    };
+    environment::log("pointer_test: found a relative pointer");
+
+    let Some(mut tablet) = tablet else {
+        environment::log("FAIL: no absolute pointer found");
+        return 1;
+    };
+    let info = tablet.info();
+    if info.x_max <= info.x_min || info.y_max <= info.y_min {
+        environment::log("FAIL: tablet reported an empty axis range");
+        return 1;
+    }
+    environment::log("pointer_test: found an absolute pointer");
+
+    // The keyboard at 00:03.0 is an input device too, but not a pointer.
+    match PointerDevice::open("pointer:/pci/00:03.0") {
+        Err(ErrorCode::NotFound) => environment::log("pointer_test: keyboard is not a pointer"),
+        _ => {
+            environment::log("FAIL: keyboard opened as a pointer");
+            return 1;
+        }
+    }
+
+    environment::log("pointer_test: ready for input");
+
+    let mut motion = false;
+    let mut pressed = false;
+    let mut released = false;
+    while !(motion && pressed && released) {
+        let (handle, events) = mailbox.recv();
+        if !events.is_pointer() {
+            continue;
+        }
+        let device = if handle == mouse.handle() {
+            &mut mouse
+        } else if handle == tablet.handle() {
+            &mut tablet
+        } else {
+            continue;
+        };
+        loop {
+            let event = match device.try_read_event() {
+                Ok(Some(event)) => event,
+                Ok(None) => break,
+                Err(_) => {
+                    environment::log("FAIL: reading pointer events failed");
+                    return 1;
+                }
+            };
+            if event.is_motion() && !motion {
+                motion = true;
+                environment::log("pointer_test: received motion");
+            }
+            if event.pressed.contains(Buttons::LEFT) && !pressed {
+                if !event.buttons.contains(Buttons::LEFT) {
+                    environment::log("FAIL: pressed button not reported as held");
+                    return 1;
+                }
+                pressed = true;
+                environment::log("pointer_test: left button pressed");
+            }
+            if event.released.contains(Buttons::LEFT) && pressed && !released {
+                released = true;
+                environment::log("pointer_test: left button released");
+            }
+        }
+    }
+
+    environment::log("PASS");
+    0
+}
@@ -0,0 +1,119 @@
+//! Test the `rm` utility on the ext2 filesystem mounted at /mnt.
+//!
+//! Exercises:
+//! 1. Remove a file
+//! 2. A directory is not removed without -r
+//! 3. rm -rv removes a tree, deepest first, and sends on a Path table
+//! 4. A missing path is reported, unless -f is given
+//! 5. A root is not removed
+
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use libpanda::environment;
+use libpanda::testing::{run, string};
+use panda_abi::value::Value;
+
+libpanda::main! {
+    environment::log("rm_test: Starting");
+
+    if environment::mount("ext2", "/mnt").is_err() {
+        environment::log("FAIL: Could not mount ext2 filesystem");
+        return 1;
+    }
+    environment::log("rm_test: ext2 mounted at /mnt");
+
+    // =========================================================================
+    // Test 1: Remove a file
+    // =========================================================================
+    environment::log("rm_test: Test 1 - Remove a file");
+    let (code, values, errors) = run("rm", &["/mnt/hello.txt"], None);
+    if code != 0 || !errors.is_empty() || !values.is_empty() {
+        environment::log(&libpanda::format!("FAIL: rm exited {} with {:?}", code, errors));
+        return 1;
+    }
+    if environment::stat("file:/mnt/hello.txt").is_ok() {
+        environment::log("FAIL: hello.txt is still there");
+        return 1;
+    }
+    environment::log("rm_test: Test 1 passed");
+
+    // =========================================================================
+    // Test 2: A directory is not removed without -r
+    // =========================================================================
+    environment::log("rm_test: Test 2 - Directory without -r");
+    let (code, _, errors) = run("rm", &["/mnt/subdir"], None);
+    if code != 1 || !errors.iter().any(|e| e.contains("is a directory")) {
+        environment::log(&libpanda::format!("FAIL: expected an error, got {} {:?}", code, errors));
+        return 1;
+    }
+    if environment::stat("file:/mnt/subdir/nested.txt").is_err() {
+        environment::log("FAIL: subdir was emptied");
+        return 1;
+    }
+    environment::log("rm_test: Test 2 passed");
+
+    // =========================================================================
+    // Test 3: rm -rv removes a tree and sends on a Path table
+    // =========================================================================
+    environment::log("rm_test: Test 3 - Recursive remove");
+    let (code, values, _) = run("rm", &["-rv", "/mnt/a"], None);
+    if code != 0 {
+        environment::log("FAIL: rm -rv failed");
+        return 1;
+    }
+    let expected = [
+        string("file:/mnt/a/b/c/deep.txt"),
+        string("file:/mnt/a/b/c"),
+        string("file:/mnt/a/b"),
+        string("file:/mnt/a"),
+    ];
+    match values.as_slice() {
+        [Value::Table(table)] if table.cells == expected => {}
+        other => {
+            environment::log(&libpanda::format!("FAIL: unexpected output {:?}", other));
+            return 1;
+        }
+    }
+    if environment::stat("file:/mnt/a").is_ok() {
+        environment::log("FAIL: a is still there");
+        return 1;
+    }
+    environment::log("rm_test: Test 3 passed");
+
+    // =========================================================================
+    // Test 4: A missing path is reported, unless -f is given
+    // =========================================================================
+    environment::log("rm_test: Test 4 - Missing path");
+    let (code, _, errors) = run("rm", &["/mnt/missing.txt"], None);
+    if code != 1 || !errors.iter().any(|e| e.contains("missing.txt: not found")) {
+        environment::log(&libpanda::format!("FAIL: expected an error, got {} {:?}", code, errors));
+        return 1;
+    }
+    let (code, _, errors) = run("rm", &["-f", "/mnt/missing.txt"], None);
+    if code != 0 || !errors.is_empty() {
+        environment::log(&libpanda::format!("FAIL: rm -f exited {} with {:?}", code, errors));
+        return 1;
+    }
+    environment::log("rm_test: Test 4 passed");
+
+    // =========================================================================
+    // Test 5: A root is not removed
+    // =========================================================================
+    environment::log("rm_test: Test 5 - Root");
+    let (code, _, errors) = run("rm", &["-r", "/"], None);
+    if code != 1 || !errors.iter().any(|e| e.contains("is a root")) {
+        environment::log(&libpanda::format!("FAIL: expected an error, got {} {:?}", code, errors));
+        return 1;
+    }
+    if environment::stat("file:/mnt/large.bin").is_err() {
+        environment::log("FAIL: large.bin was removed");
+        return 1;
+    }
+    environment::log("rm_test: Test 5 passed");
+
+    environment::log("rm_test: All tests passed!");
+    0
+}
@@ -0,0 +1,170 @@
+#![no_std]
+#![no_main]
+
+//! Capture through a real compositor process: a window captured over its
+//! own connection comes back exactly as committed, the screen comes back
+//! with the window composited into it, and a window that does not exist is
+//! an error rather than a hang. A connection made through the `compositor:`
+//! scheme may not capture the screen or share capture; one shared by the
+//! compositor's spawner may. The compositor side of the copies is covered
+//! by `compositor`'s own tests in `userspace/compositor/src/manager.rs`.
+
+use libpanda::environment;
+use libpanda::graphics::{
+    CaptureSource, Colour, PixelBuffer, Rect, Window, capture_on, share_capture,
+};
+use libpanda::ipc::Channel;
+use libpanda::process;
+use panda_abi::ErrorCode;
+
+const WINDOW_X: u32 = 50;
+const WINDOW_Y: u32 = 50;
+
+libpanda::main! {
+    environment::log("Screenshot test starting");
+
+    let Ok(compositor_handle) = environment::spawn("file:/initrd/compositor_test_child") else {
+        environment::log("FAIL: could not spawn compositor_test_child");
+        return 1;
+    };
+    let Some(channel) = Channel::from_handle_borrowed(compositor_handle) else {
+        environment::log("FAIL: compositor handle is not a channel");
+        return 1;
+    };
+
+    let mut window = match Window::builder()
+        .size(200, 100)
+        .position(WINDOW_X, WINDOW_Y)
+        .visible(true)
+        .channel(channel)
+        .build()
+    {
+        Ok(w) => w,
+        Err(_) => {
+            environment::log("FAIL: Could not create window");
+            return 1;
+        }
+    };
+
+    let (width, height) = window.size();
+    let Ok(mut buffer) = PixelBuffer::new(width, height) else {
+        environment::log("FAIL: Could not allocate buffer");
+        return 1;
+    };
+    buffer.fill_rect(Rect::new(0, 0, width / 2, height), Colour::RED);
+    buffer.fill_rect(Rect::new(width / 2, 0, width - width / 2, height), Colour::BLUE);
+    if window.blit(&buffer, 0, 0).is_err() || window.flush().is_err() {
+        environment::log("FAIL: Could not draw the window");
+        return 1;
+    }
+    environment::log("PASS: Drew the window");
+
+    let id = window.id();
+    match window.capture(CaptureSource::Window(id)) {
+        Ok(image) if image.width() == width && image.height() == height => {
+            if image.get_pixel(0, 0) != Colour::RED
+                || image.get_pixel(width - 1, height - 1) != Colour::BLUE
+            {
+                environment::log("FAIL: Window capture has the wrong pixels");
+                return 1;
+            }
+        }
+        Ok(_) => {
+            environment::log("FAIL: Window capture has the wrong size");
+            return 1;
+        }
+        Err(_) => {
+            environment::log("FAIL: Could not capture the window");
+            return 1;
+        }
+    }
+    environment::log("PASS: Captured the window");
+
+    // This process spawned the compositor, so it may capture the screen.
+    match window.capture(CaptureSource::Screen) {
+        Ok(image) => {
+            // The middle of each half, which a title bar above the window
+            // cannot reach.
+            let y = WINDOW_Y + height / 2;
+            if image.get_pixel(WINDOW_X + width / 4, y) != Colour::RED
+                || image.get_pixel(WINDOW_X + width * 3 / 4, y) != Colour::BLUE
+            {
+                environment::log("FAIL: Screen capture does not show the window");
+                return 1;
+            }
+        }
+        Err(_) => {
+            environment::log("FAIL: Could not capture the screen");
+            return 1;
+        }
+    }
+    environment::log("PASS: Captured the screen");
+
+    match window.capture(CaptureSource::Window(id + 1000)) {
+        Err(ErrorCode::NotFound) => environment::log("PASS: Capturing a missing window failed"),
+        _ => {
+            environment::log("FAIL: Capturing a missing window did not fail with NotFound");
+            return 1;
+        }
+    }
+
+    // A connection through the scheme may capture only its own windows.
+    let connect = || {
+        environment::connect("compositor:/connect")
+            .ok()
+            .and_then(Channel::from_handle)
+    };
+    let Some(ordinary) = connect() else {
+        environment::log("FAIL: Could not connect to the compositor");
+        return 1;
+    };
+    match capture_on(ordinary, CaptureSource::Screen) {
+        Err(ErrorCode::PermissionDenied) => {
+            environment::log("PASS: A scheme connection may not capture the screen")
+        }
+        _ => {
+            environment::log("FAIL: A scheme connection captured the screen");
+            return 1;
+        }
+    }
+    let Some(ordinary) = connect() else {
+        environment::log("FAIL: Could not connect to the compositor");
+        return 1;
+    };
+    match share_capture(&ordinary) {
+        Err(ErrorCode::PermissionDenied) => {
+            environment::log("PASS: A scheme connection may not share capture")
+        }
+        _ => {
+            environment::log("FAIL: A scheme connection shared capture");
+            return 1;
+        }
+    }
+
+    // The window waits for nothing more, so `share_capture` may drop any
+    // event it reads off the window's channel.
+    let Some(parent) = Channel::from_handle_borrowed(compositor_handle) else {
+        environment::log("FAIL: compositor handle is not a channel");
+        return 1;
+    };
+    let screen = share_capture(&parent).and_then(|shared| capture_on(shared, CaptureSource::Screen));
+    let (x, y) = (WINDOW_X + width / 4, WINDOW_Y + height / 2);
+    match screen {
+        Ok(image) if image.get_pixel(x, y) == Colour::RED => {
+            environment::log("PASS: Captured the screen over a shared connection")
+        }
+        _ => {
+            environment::log("FAIL: Could not capture the screen over a shared connection");
+            return 1;
+        }
+    }
+
+    drop(window);
+    let exit_code = process::wait(compositor_handle);
+    if exit_code != 0 {
+        environment::log("FAIL: compositor_test_child exited with non-zero code");
+        return 1;
+    }
+
+    0
+}
@@ -0,0 +1,22 @@
+//! Helper for signal_test: ticks to its parent every 10ms until it is
+//! killed. It grows its heap first, so being killed has heap pages to free.
+
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc::vec;
+
+use libpanda::{Handle, channel, process};
+
+libpanda::main! {
+    let heap = vec![0x5au8; 64 * 1024];
+    core::hint::black_box(&heap);
+    loop {
+        if channel::send(Handle::PARENT, b"tick").is_err() {
+            return 1;
+        }
+        process::sleep(10);
+    }
+}
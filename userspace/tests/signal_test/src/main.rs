@@ -0,0 +1,110 @@
+//! Test kernel signals: stopping, continuing and killing a child.
+//!
+//! signal_child sends a tick every 10ms, so a stopped child is one whose
+//! ticks stop coming, and a continued one starts sending them again.
+//! Killing it must tear down only its memory: this process's stack and heap
+//! sit at the same addresses as the child's.
+
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use libpanda::ipc::Channel;
+use libpanda::process::{self, Child, Signal};
+use libpanda::{ErrorCode, environment};
+use panda_abi::{SIGNAL_EXIT_BASE, SIGNAL_KILL};
+
+/// Throw away the ticks already sent, returning how many there were.
+fn drain(channel: &Channel) -> usize {
+    let mut buf = [0u8; 16];
+    let mut count = 0;
+    while let Ok(Some(_)) = channel.try_recv(&mut buf) {
+        count += 1;
+    }
+    count
+}
+
+libpanda::main! {
+    environment::log("signal_test: starting");
+
+    let Ok(mut child) = Child::spawn("file:/initrd/signal_child") else {
+        environment::log("FAIL: spawn failed");
+        return 1;
+    };
+    let Some(channel) = child.channel() else {
+        environment::log("FAIL: child has no channel");
+        return 1;
+    };
+    let mut buf = [0u8; 16];
+    if channel.recv(&mut buf).is_err() {
+        environment::log("FAIL: no tick from the child");
+        return 1;
+    }
+
+    // Stopped, the child sends nothing more.
+    if child.signal(Signal::Stop).is_err() {
+        environment::log("FAIL: stop failed");
+        return 1;
+    }
+    drain(&channel);
+    process::sleep(100);
+    if drain(&channel) != 0 {
+        environment::log("FAIL: the child ticked while stopped");
+        return 1;
+    }
+    environment::log("signal_test: stopped child is quiet");
+
+    // Continued, it carries on where it was.
+    if child.signal(Signal::Continue).is_err() {
+        environment::log("FAIL: continue failed");
+        return 1;
+    }
+    if channel.recv(&mut buf).is_err() {
+        environment::log("FAIL: no tick after continuing");
+        return 1;
+    }
+    environment::log("signal_test: continued child ticks again");
+
+    if ErrorCode::from_isize(process::signal(child.handle(), 99)) != Some(ErrorCode::NotSupported) {
+        environment::log("FAIL: unknown signal was not rejected");
+        return 1;
+    }
+
+    // Killed, it exits at once with the signal in its exit code.
+    let stack = core::hint::black_box([0xa5u8; 4096]);
+    let heap: Vec<u8> = core::hint::black_box(vec![0x3cu8; 64 * 1024]);
+    if child.kill().is_err() {
+        environment::log("FAIL: kill failed");
+        return 1;
+    }
+    match child.wait() {
+        Ok(status) if status.code() == SIGNAL_EXIT_BASE + SIGNAL_KILL as i32 => {}
+        _ => {
+            environment::log("FAIL: killed child had the wrong exit code");
+            return 1;
+        }
+    }
+    if child.signal(Signal::Continue) != Err(ErrorCode::NotFound) {
+        environment::log("FAIL: signalling an exited child succeeded");
+        return 1;
+    }
+    environment::log("signal_test: killed child exited");
+
+    // The kill freed the child's pages, not this process's.
+    let more: Vec<u8> = core::hint::black_box(vec![0x69u8; 64 * 1024]);
+    if stack.iter().any(|&b| b != 0xa5)
+        || heap.iter().any(|&b| b != 0x3c)
+        || more.iter().any(|&b| b != 0x69)
+    {
+        environment::log("FAIL: killing the child disturbed the parent's memory");
+        return 1;
+    }
+    environment::log("signal_test: parent stack and heap intact after the kill");
+
+    environment::log("PASS");
+    0
+}
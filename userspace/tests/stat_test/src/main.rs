@@ -0,0 +1,104 @@
+//! Test the `stat` utility on the ext2 filesystem mounted at /mnt.
+//!
+//! Exercises:
+//! 1. stat a file sends on a Path/Type/Size table
+//! 2. A row for each path, directories included
+//! 3. A missing path is reported and the rest still described
+//! 4. No paths at all is a usage error
+
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use libpanda::environment;
+use libpanda::testing::{run, string};
+use panda_abi::value::Value;
+
+/// The cells of the one table in `values`, if it is headed Path, Type, Size.
+fn cells(values: &[Value]) -> Option<&[Value]> {
+    match values {
+        [Value::Table(table)]
+            if table.headers.as_deref()
+                == Some(&[string("Path"), string("Type"), string("Size")][..]) =>
+        {
+            Some(&table.cells)
+        }
+        _ => None,
+    }
+}
+
+libpanda::main! {
+    environment::log("stat_test: Starting");
+
+    if environment::mount("ext2", "/mnt").is_err() {
+        environment::log("FAIL: Could not mount ext2 filesystem");
+        return 1;
+    }
+    environment::log("stat_test: ext2 mounted at /mnt");
+
+    // =========================================================================
+    // Test 1: stat a file sends on a Path/Type/Size table
+    // =========================================================================
+    environment::log("stat_test: Test 1 - stat a file");
+    let (code, values, errors) = run("stat", &["/mnt/hello.txt"], None);
+    let expected = [string("/mnt/hello.txt"), string("file"), Value::Int(17)];
+    if code != 0 || !errors.is_empty() || cells(&values) != Some(&expected[..]) {
+        environment::log(&libpanda::format!(
+            "FAIL: stat exited {} with {:?} {:?}",
+            code, values, errors
+        ));
+        return 1;
+    }
+    environment::log("stat_test: Test 1 passed");
+
+    // =========================================================================
+    // Test 2: A row for each path, directories included
+    // =========================================================================
+    environment::log("stat_test: Test 2 - Several paths");
+    let (code, values, _) = run("stat", &["/mnt/large.bin", "/mnt/subdir"], None);
+    let Some(rows) = cells(&values).filter(|cells| cells.len() == 6) else {
+        environment::log(&libpanda::format!("FAIL: expected two rows, got {:?}", values));
+        return 1;
+    };
+    if code != 0
+        || rows[..3] != [string("/mnt/large.bin"), string("file"), Value::Int(8192)]
+        || rows[3..5] != [string("/mnt/subdir"), string("dir")]
+    {
+        environment::log(&libpanda::format!("FAIL: unexpected rows {:?}", rows));
+        return 1;
+    }
+    environment::log("stat_test: Test 2 passed");
+
+    // =========================================================================
+    // Test 3: A missing path is reported and the rest still described
+    // =========================================================================
+    environment::log("stat_test: Test 3 - Missing path");
+    let (code, values, errors) = run("stat", &["/mnt/missing.txt", "/mnt/a/b/c/deep.txt"], None);
+    let expected = [string("/mnt/a/b/c/deep.txt"), string("file"), Value::Int(10)];
+    if code != 1
+        || !errors.iter().any(|e| e.contains("missing.txt: not found"))
+        || cells(&values) != Some(&expected[..])
+    {
+        environment::log(&libpanda::format!(
+            "FAIL: stat exited {} with {:?} {:?}",
+            code, values, errors
+        ));
+        return 1;
+    }
+    environment::log("stat_test: Test 3 passed");
+
+    // =========================================================================
+    // Test 4: No paths at all is a usage error
+    // =========================================================================
+    environment::log("stat_test: Test 4 - Usage");
+    let (code, values, errors) = run("stat", &[], None);
+    if code != 1 || !values.is_empty() || !errors.iter().any(|e| e.starts_with("Usage: stat")) {
+        environment::log(&libpanda::format!("FAIL: expected usage, got {} {:?}", code, errors));
+        return 1;
+    }
+    environment::log("stat_test: Test 4 passed");
+
+    environment::log("stat_test: All tests passed!");
+    0
+}
@@ -0,0 +1,258 @@
+//! Test the `head`, `tail` and `wc` utilities on the ext2 filesystem
+//! mounted at /mnt.
+//!
+//! Exercises, for each of head and tail:
+//! 1. It sends on ten lines of a file: the first or the last
+//! 2. -n takes another number of lines, as `-n 3` or `-n3`
+//! 3. Without a file it reads stdin
+//! 4. A missing file is reported
+//! 5. A count that is not a number is a usage error
+//!
+//! And for wc:
+//! 6. wc counts a file into a Name/Lines/Words/Bytes table
+//! 7. Several files get a row each and a total
+//! 8. Without a file it counts stdin
+//! 9. A missing file is reported and the rest still counted
+
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use libpanda::testing::{run, string};
+use libpanda::{environment, file};
+use panda_abi::value::Value;
+
+const LINES: &str = "one\ntwo\nthree\nfour\nfive\nsix\nseven\neight\nnine\nten\neleven\ntwelve\n";
+
+/// What `head` or `tail` should send on from [`LINES`].
+struct LineTool {
+    name: &'static str,
+    /// With no count.
+    ten: &'static str,
+    /// With `-n 3`.
+    three: &'static str,
+    /// With `-n 2`, from stdin.
+    two: &'static str,
+}
+
+const LINE_TOOLS: [LineTool; 2] = [
+    LineTool {
+        name: "head",
+        ten: "one\ntwo\nthree\nfour\nfive\nsix\nseven\neight\nnine\nten\n",
+        three: "one\ntwo\nthree\n",
+        two: "one\ntwo\n",
+    },
+    LineTool {
+        name: "tail",
+        ten: "three\nfour\nfive\nsix\nseven\neight\nnine\nten\neleven\ntwelve\n",
+        three: "ten\neleven\ntwelve\n",
+        two: "eleven\ntwelve\n",
+    },
+];
+
+/// The rows of a `wc` table, or `None` if it sent on anything else.
+fn rows(values: &[Value]) -> Option<Vec<(&str, [i64; 3])>> {
+    let [Value::Table(table)] = values else {
+        return None;
+    };
+    let headers = ["Name", "Lines", "Words", "Bytes"].map(|h| Value::String(String::from(h)));
+    if table.headers.as_deref() != Some(&headers[..]) {
+        return None;
+    }
+    table
+        .cells
+        .chunks(4)
+        .map(|row| match row {
+            [Value::String(name), Value::Int(lines), Value::Int(words), Value::Int(bytes)] => {
+                Some((name.as_str(), [*lines, *words, *bytes]))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Tests 1-5 for `head` or `tail`.
+fn check_line_tool(tool: &LineTool) -> bool {
+    let name = tool.name;
+    let log = |message: &str| environment::log(&libpanda::format!("text_tools_test: {}", message));
+
+    // =========================================================================
+    // Test 1: It sends on ten lines of a file
+    // =========================================================================
+    log(&libpanda::format!("Test 1 - {}: ten lines", name));
+    let (code, values, errors) = run(name, &["/mnt/lines.txt"], None);
+    if code != 0 || !errors.is_empty() || values != [string(tool.ten)] {
+        environment::log(&libpanda::format!(
+            "FAIL: {} exited {} with {:?} {:?}",
+            name, code, values, errors
+        ));
+        return false;
+    }
+    log("Test 1 passed");
+
+    // =========================================================================
+    // Test 2: -n takes another number of lines
+    // =========================================================================
+    log(&libpanda::format!("Test 2 - {}: line count", name));
+    for args in [&["-n", "3", "/mnt/lines.txt"][..], &["-n3", "/mnt/lines.txt"]] {
+        let (code, values, _) = run(name, args, None);
+        if code != 0 || values != [string(tool.three)] {
+            environment::log(&libpanda::format!("FAIL: {} sent {:?}", name, values));
+            return false;
+        }
+    }
+    log("Test 2 passed");
+
+    // =========================================================================
+    // Test 3: Without a file it reads stdin
+    // =========================================================================
+    log(&libpanda::format!("Test 3 - {}: stdin", name));
+    let Ok(stdin) = environment::open("file:/mnt/lines.txt", 0, 0) else {
+        environment::log("FAIL: Could not open lines.txt");
+        return false;
+    };
+    let (code, values, _) = run(name, &["-n", "2"], Some(stdin));
+    file::close(stdin);
+    if code != 0 || values != [string(tool.two)] {
+        environment::log(&libpanda::format!("FAIL: {} sent {:?}", name, values));
+        return false;
+    }
+    log("Test 3 passed");
+
+    // =========================================================================
+    // Test 4: A missing file is reported
+    // =========================================================================
+    log(&libpanda::format!("Test 4 - {}: missing file", name));
+    let (code, values, errors) = run(name, &["/mnt/missing.txt"], None);
+    if code != 1
+        || !values.is_empty()
+        || !errors.iter().any(|e| e.contains("missing.txt: not found"))
+    {
+        environment::log(&libpanda::format!("FAIL: expected an error, got {} {:?}", code, errors));
+        return false;
+    }
+    log("Test 4 passed");
+
+    // =========================================================================
+    // Test 5: A count that is not a number is a usage error
+    // =========================================================================
+    log(&libpanda::format!("Test 5 - {}: usage", name));
+    let (code, _, errors) = run(name, &["-n", "many", "/mnt/lines.txt"], None);
+    let usage = libpanda::format!("Usage: {}", name);
+    if code != 1 || !errors.iter().any(|e| e.starts_with(&usage)) {
+        environment::log(&libpanda::format!("FAIL: expected usage, got {} {:?}", code, errors));
+        return false;
+    }
+    log("Test 5 passed");
+    true
+}
+
+libpanda::main! {
+    environment::log("text_tools_test: Starting");
+
+    if environment::mount("ext2", "/mnt").is_err() {
+        environment::log("FAIL: Could not mount ext2 filesystem");
+        return 1;
+    }
+    environment::log("text_tools_test: ext2 mounted at /mnt");
+
+    // Twelve lines for head and tail to take some of
+    let Ok(root_dir) = environment::opendir("file:/mnt") else {
+        environment::log("FAIL: Could not opendir file:/mnt");
+        return 1;
+    };
+    let Ok(lines) = environment::create(root_dir, "lines.txt", 0o644, 0) else {
+        environment::log("FAIL: Could not create lines.txt");
+        return 1;
+    };
+    if file::write(lines, LINES.as_bytes()) != LINES.len() as isize {
+        environment::log("FAIL: Could not write lines.txt");
+        return 1;
+    }
+    file::close(lines);
+    file::close(root_dir);
+    environment::log("text_tools_test: lines.txt written");
+
+    for tool in &LINE_TOOLS {
+        if !check_line_tool(tool) {
+            return 1;
+        }
+    }
+
+    // =========================================================================
+    // Test 6: wc counts a file into a Name/Lines/Words/Bytes table
+    // =========================================================================
+    environment::log("text_tools_test: Test 6 - wc: count a file");
+    let (code, values, errors) = run("wc", &["/mnt/hello.txt"], None);
+    let expected = alloc::vec![("/mnt/hello.txt", [1, 3, 17])];
+    if code != 0 || !errors.is_empty() || rows(&values) != Some(expected) {
+        environment::log(&libpanda::format!(
+            "FAIL: wc exited {} with {:?} {:?}",
+            code, values, errors
+        ));
+        return 1;
+    }
+    environment::log("text_tools_test: Test 6 passed");
+
+    // =========================================================================
+    // Test 7: Several files get a row each and a total
+    // =========================================================================
+    environment::log("text_tools_test: Test 7 - wc: several files");
+    let files = ["/mnt/hello.txt", "/mnt/subdir/nested.txt", "/mnt/large.bin"];
+    let (code, values, _) = run("wc", &files, None);
+    let Some(counted) = rows(&values).filter(|rows| rows.len() == 4) else {
+        environment::log(&libpanda::format!("FAIL: expected four rows, got {:?}", values));
+        return 1;
+    };
+    if code != 0
+        || counted[1] != ("/mnt/subdir/nested.txt", [1, 3, 20])
+        || counted[2].0 != "/mnt/large.bin"
+        || counted[2].1[2] != 8192
+        || counted[3].0 != "total"
+        || counted[3].1[2] != 17 + 20 + 8192
+    {
+        environment::log(&libpanda::format!("FAIL: unexpected rows {:?}", counted));
+        return 1;
+    }
+    environment::log("text_tools_test: Test 7 passed");
+
+    // =========================================================================
+    // Test 8: Without a file it counts stdin
+    // =========================================================================
+    environment::log("text_tools_test: Test 8 - wc: count stdin");
+    let Ok(stdin) = environment::open("file:/mnt/a/b/c/deep.txt", 0, 0) else {
+        environment::log("FAIL: Could not open deep.txt");
+        return 1;
+    };
+    let (code, values, _) = run("wc", &[], Some(stdin));
+    file::close(stdin);
+    if code != 0 || rows(&values) != Some(alloc::vec![("-", [1, 2, 10])]) {
+        environment::log(&libpanda::format!("FAIL: unexpected output {:?}", values));
+        return 1;
+    }
+    environment::log("text_tools_test: Test 8 passed");
+
+    // =========================================================================
+    // Test 9: A missing file is reported and the rest still counted
+    // =========================================================================
+    environment::log("text_tools_test: Test 9 - wc: missing file");
+    let (code, values, errors) = run("wc", &["/mnt/missing.txt", "/mnt/hello.txt"], None);
+    let expected = alloc::vec![("/mnt/hello.txt", [1, 3, 17]), ("total", [1, 3, 17])];
+    if code != 1
+        || !errors.iter().any(|e| e.contains("missing.txt: not found"))
+        || rows(&values) != Some(expected)
+    {
+        environment::log(&libpanda::format!(
+            "FAIL: wc exited {} with {:?} {:?}",
+            code, values, errors
+        ));
+        return 1;
+    }
+    environment::log("text_tools_test: Test 9 passed");
+
+    environment::log("text_tools_test: All tests passed!");
+    0
+}
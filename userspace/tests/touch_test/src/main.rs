@@ -0,0 +1,102 @@
+//! Test the `touch` utility on the ext2 filesystem mounted at /mnt.
+//!
+//! Exercises:
+//! 1. Make an empty file
+//! 2. A file already there is left alone
+//! 3. Make several files
+//! 4. A missing directory is reported, and the other files still made
+//! 5. No files at all is a usage error
+
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use libpanda::environment;
+use libpanda::testing::run;
+
+fn size(path: &str) -> Option<u64> {
+    environment::stat(path).ok().filter(|stat| !stat.is_dir).map(|stat| stat.size)
+}
+
+libpanda::main! {
+    environment::log("touch_test: Starting");
+
+    if environment::mount("ext2", "/mnt").is_err() {
+        environment::log("FAIL: Could not mount ext2 filesystem");
+        return 1;
+    }
+    environment::log("touch_test: ext2 mounted at /mnt");
+
+    // =========================================================================
+    // Test 1: Make an empty file
+    // =========================================================================
+    environment::log("touch_test: Test 1 - Make an empty file");
+    let (code, values, errors) = run("touch", &["/mnt/empty.txt"], None);
+    if code != 0 || !errors.is_empty() || !values.is_empty() {
+        environment::log(&libpanda::format!("FAIL: touch exited {} with {:?}", code, errors));
+        return 1;
+    }
+    if size("file:/mnt/empty.txt") != Some(0) {
+        environment::log("FAIL: empty.txt is not an empty file");
+        return 1;
+    }
+    environment::log("touch_test: Test 1 passed");
+
+    // =========================================================================
+    // Test 2: A file already there is left alone
+    // =========================================================================
+    environment::log("touch_test: Test 2 - Existing file");
+    let (code, _, _) = run("touch", &["/mnt/hello.txt"], None);
+    if code != 0 || size("file:/mnt/hello.txt") != Some(17) {
+        environment::log("FAIL: hello.txt was changed");
+        return 1;
+    }
+    environment::log("touch_test: Test 2 passed");
+
+    // =========================================================================
+    // Test 3: Make several files
+    // =========================================================================
+    environment::log("touch_test: Test 3 - Several files");
+    let (code, _, _) = run("touch", &["/mnt/one.txt", "/mnt/subdir/two.txt"], None);
+    if code != 0
+        || size("file:/mnt/one.txt") != Some(0)
+        || size("file:/mnt/subdir/two.txt") != Some(0)
+    {
+        environment::log("FAIL: one.txt and subdir/two.txt were not both made");
+        return 1;
+    }
+    environment::log("touch_test: Test 3 passed");
+
+    // =========================================================================
+    // Test 4: A missing directory is reported, the other files still made
+    // =========================================================================
+    environment::log("touch_test: Test 4 - Missing directory");
+    let (code, _, errors) = run("touch", &["/mnt/nowhere/file.txt", "/mnt/three.txt"], None);
+    if code != 1
+        || errors.len() != 1
+        || !errors[0].starts_with("touch: file:/mnt/nowhere/file.txt")
+    {
+        environment::log(&libpanda::format!("FAIL: expected an error, got {} {:?}", code, errors));
+        return 1;
+    }
+    if size("file:/mnt/three.txt") != Some(0) {
+        environment::log("FAIL: three.txt was not made");
+        return 1;
+    }
+    environment::log("touch_test: Test 4 passed");
+
+    // =========================================================================
+    // Test 5: No files at all is a usage error
+    // =========================================================================
+    environment::log("touch_test: Test 5 - Usage");
+    let (code, _, errors) = run("touch", &[], None);
+    if code != 1 || !errors.iter().any(|e| e.starts_with("Usage: touch")) {
+        environment::log(&libpanda::format!("FAIL: expected usage, got {} {:?}", code, errors));
+        return 1;
+    }
+    environment::log("touch_test: Test 5 passed");
+
+    environment::log("touch_test: All tests passed!");
+    0
+}